@@ -1,15 +1,29 @@
 use std::io::{self, Write};
 
-use core::std_lib::std_result::StdResult;
+use core::{flags::network::Network, std_lib::std_result::StdResult};
+
+use crate::{
+    debugger::run_debugger,
+    inspect::{print_inspect_help, run_inspect, Inspect, Options},
+};
 
 #[derive(Debug, PartialEq)]
 enum Command {
     Exit,
+    Help,
+    Debug(String),
+    Inspect(Inspect, Options),
     Unknown,
 }
 
 pub fn print_exit_help() {
-    println!("'exit' to close the application");
+    println!("'help' to list the commands, 'exit' to close the application");
+}
+
+fn print_help() {
+    println!("debug <script>: step through a script written in assembly");
+    print_inspect_help();
+    print_exit_help();
 }
 
 pub fn run_command_line() {
@@ -22,6 +36,16 @@ pub fn run_command_line() {
         let user_command = read_command();
         match translate_command(&user_command) {
             Ok(Command::Exit) => exit = true,
+            Ok(Command::Help) => print_help(),
+            Ok(Command::Debug(script)) => {
+                if let Err(e) = run_debugger(&script) {
+                    println!("Error: {}", e);
+                }
+            }
+            Ok(Command::Inspect(inspect, options)) => match run_inspect(&inspect, &options) {
+                Ok(output) => println!("{}", output),
+                Err(e) => println!("Error: {}", e),
+            },
             Ok(Command::Unknown) => println!("Unknown command: {}", user_command),
             Err(e) => println!("Error: {}", e),
         }
@@ -41,15 +65,66 @@ fn read_command() -> String {
 fn translate_command(command: &str) -> StdResult<Command> {
     let normalized_command = command.trim().to_lowercase();
 
-    match normalized_command.as_str() {
-        "exit" => Ok(Command::Exit),
-        _ => Ok(Command::Unknown),
+    // the script is case sensitive (opcodes, placeholders)
+    if normalized_command.starts_with("debug ") {
+        let script = command.trim()["debug ".len()..].trim();
+        return Ok(Command::Debug(script.to_string()));
+    }
+
+    // arguments (hexadecimal, base58) keep their case
+    let mut words = command.split_whitespace();
+    let name = words.next().unwrap_or_default().to_lowercase();
+
+    let mut options = Options::default();
+    let mut arguments: Vec<String> = vec![];
+    for word in words {
+        match word.to_lowercase().as_str() {
+            "--json" => options.json = true,
+            "--testnet" => options.network = Network::Testnet,
+            "--show-secret" => options.show_secret = true,
+            w if w.starts_with("--") => Err("unknown_option")?,
+            _ => arguments.push(word.to_string()),
+        }
+    }
+
+    let inspect = match name.as_str() {
+        "exit" => return Ok(Command::Exit),
+        "help" => return Ok(Command::Help),
+        "decoderawtransaction" => Inspect::DecodeRawTransaction(single_argument(arguments)?),
+        "decodescript" => Inspect::DecodeScript(single_argument(arguments)?),
+        "decodeblockheader" => Inspect::DecodeBlockHeader(single_argument(arguments)?),
+        "validateaddress" => Inspect::ValidateAddress(single_argument(arguments)?),
+        "getnewaddress" => Inspect::GetNewAddress(optional_argument(arguments)?),
+        "hash256" => Inspect::Hash256(single_argument(arguments)?),
+        "hash160" => Inspect::Hash160(single_argument(arguments)?),
+        "base58encode" => Inspect::Base58Encode(single_argument(arguments)?),
+        "base58decode" => Inspect::Base58Decode(single_argument(arguments)?),
+        "analyze" => Inspect::Analyze(single_argument(arguments)?),
+        _ => return Ok(Command::Unknown),
+    };
+
+    Ok(Command::Inspect(inspect, options))
+}
+
+fn optional_argument(mut arguments: Vec<String>) -> StdResult<Option<String>> {
+    match arguments.len() {
+        0 => Ok(None),
+        1 => Ok(Some(arguments.remove(0))),
+        _ => Err("unexpected_argument")?,
+    }
+}
+
+fn single_argument(mut arguments: Vec<String>) -> StdResult<String> {
+    match arguments.len() {
+        0 => Err("missing_argument")?,
+        1 => Ok(arguments.remove(0)),
+        _ => Err("unexpected_argument")?,
     }
 }
 
 #[cfg(test)]
 mod command_line_tests {
-    use super::{translate_command, Command};
+    use super::{translate_command, Command, Inspect, Network, Options};
 
     #[test]
     fn unknown_command() {
@@ -63,9 +138,72 @@ mod command_line_tests {
         assert_eq!(command.unwrap(), Command::Exit);
     }
 
+    #[test]
+    fn debug_script() {
+        let command = translate_command("Debug OP_1 OP_2 OP_ADD\n");
+        assert_eq!(command.unwrap(), Command::Debug("OP_1 OP_2 OP_ADD".to_string()));
+    }
+
     #[test]
     fn correct_non_trimmed_exit() {
         let command = translate_command(" Exit  ");
         assert_eq!(command.unwrap(), Command::Exit);
     }
+
+    #[test]
+    fn help() {
+        let command = translate_command("help");
+        assert_eq!(command.unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn inspect_with_options() {
+        let command = translate_command("DecodeScript 76a914 --JSON --testnet\n");
+        let options = Options {
+            json: true,
+            network: Network::Testnet,
+            ..Options::default()
+        };
+        assert_eq!(
+            command.unwrap(),
+            Command::Inspect(Inspect::DecodeScript("76a914".to_string()), options)
+        );
+
+        let command = translate_command("base58decode 11Ldp");
+        assert_eq!(
+            command.unwrap(),
+            Command::Inspect(Inspect::Base58Decode("11Ldp".to_string()), Options::default())
+        );
+
+        let command = translate_command("getnewaddress");
+        assert_eq!(
+            command.unwrap(),
+            Command::Inspect(Inspect::GetNewAddress(None), Options::default())
+        );
+
+        let command = translate_command("getnewaddress Wallets/main.wallet --show-secret");
+        let options = Options {
+            show_secret: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            command.unwrap(),
+            Command::Inspect(Inspect::GetNewAddress(Some("Wallets/main.wallet".to_string())), options)
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let err = translate_command("hash256").expect_err("Err");
+        assert_eq!(err.to_string(), "missing_argument");
+
+        let err = translate_command("hash256 00 01").expect_err("Err");
+        assert_eq!(err.to_string(), "unexpected_argument");
+
+        let err = translate_command("getnewaddress a.wallet b.wallet").expect_err("Err");
+        assert_eq!(err.to_string(), "unexpected_argument");
+
+        let err = translate_command("hash256 00 --mainnet").expect_err("Err");
+        assert_eq!(err.to_string(), "unknown_option");
+    }
 }
@@ -0,0 +1,190 @@
+use std::io::{self, Write};
+
+use rug::Integer;
+
+use core::{
+    scripting::{
+        asm::{assemble_script, disassemble},
+        context::Context,
+        debugger::{Debugger, DebuggerState, Snapshot},
+        script_lang::ScriptLang,
+        token::Token,
+    },
+    std_lib::std_result::StdResult,
+};
+
+#[derive(Debug, PartialEq)]
+enum DebugCommand {
+    Step,
+    Continue,
+    Break(usize),
+    Delete(usize),
+    Back(usize),
+    Trace,
+    Quit,
+    Unknown,
+}
+
+fn print_debug_help() {
+    println!("step (s), continue (c), break <n> (b), delete <n> (d), back <n>, trace, quit (q)");
+}
+
+/*
+    Step through a script written in assembly (e.g. `debug OP_1 OP_2 OP_ADD OP_3 OP_EQUAL`).
+    Signatures are checked against a zero hash: only scripts without signatures evaluate as in a transaction.
+*/
+pub fn run_debugger(source: &str) -> StdResult<()> {
+    let script = assemble_script(source, &[])?;
+    let context = Context::new(script.tokens(), Integer::from(0));
+    let mut debugger = Debugger::new(&script, context)?;
+
+    print_debug_help();
+    print_state(&debugger);
+
+    loop {
+        print!("debug> ");
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        match translate_debug_command(&line) {
+            DebugCommand::Step => {
+                debugger.step();
+                print_last_step(&debugger);
+            }
+            DebugCommand::Continue => {
+                debugger.resume();
+                print_last_step(&debugger);
+            }
+            DebugCommand::Break(position) => debugger.add_breakpoint(position),
+            DebugCommand::Delete(position) => {
+                if !debugger.remove_breakpoint(position) {
+                    println!("No breakpoint at {}", position);
+                }
+            }
+            DebugCommand::Back(back) => match debugger.snapshot_back(back) {
+                Some(snapshot) => print_snapshot(snapshot),
+                None => println!("No step {} steps back", back),
+            },
+            DebugCommand::Trace => {
+                for snapshot in debugger.trace() {
+                    print_step(snapshot);
+                }
+            }
+            DebugCommand::Quit => return Ok(()),
+            DebugCommand::Unknown => print_debug_help(),
+        }
+    }
+}
+
+fn translate_debug_command(command: &str) -> DebugCommand {
+    let normalized_command = command.trim().to_lowercase();
+    let words: Vec<&str> = normalized_command.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["s"] | ["step"] => DebugCommand::Step,
+        ["c"] | ["continue"] => DebugCommand::Continue,
+        ["b" | "break", n] => n.parse().map_or(DebugCommand::Unknown, DebugCommand::Break),
+        ["d" | "delete", n] => n.parse().map_or(DebugCommand::Unknown, DebugCommand::Delete),
+        ["back"] => DebugCommand::Back(0),
+        ["back", n] => n.parse().map_or(DebugCommand::Unknown, DebugCommand::Back),
+        ["trace"] => DebugCommand::Trace,
+        ["q"] | ["quit"] => DebugCommand::Quit,
+        _ => DebugCommand::Unknown,
+    }
+}
+
+fn print_last_step(debugger: &Debugger) {
+    if let Some(snapshot) = debugger.snapshot_back(0) {
+        print_snapshot(snapshot);
+    }
+
+    print_state(debugger);
+}
+
+fn print_state(debugger: &Debugger) {
+    match debugger.state() {
+        DebuggerState::Paused(position) => println!("Next {}: {}", position, next_token(debugger)),
+        DebuggerState::Breakpoint(position) => println!("Breakpoint at {}: {}", position, next_token(debugger)),
+        DebuggerState::Finished(valid) => println!("Finished: {}", if *valid { "valid" } else { "invalid" }),
+        DebuggerState::Failed { position, error } => println!("Failed at {}: {}", position, error),
+    }
+}
+
+fn next_token(debugger: &Debugger) -> String {
+    debugger.next_token().map(token).unwrap_or_default()
+}
+
+fn print_step(snapshot: &Snapshot) {
+    println!(
+        "{:>4} {}{}",
+        snapshot.position,
+        token(&snapshot.token),
+        skipped(snapshot)
+    );
+}
+
+fn print_snapshot(snapshot: &Snapshot) {
+    print_step(snapshot);
+    println!("     stack: {}", tokens(&snapshot.stack));
+    println!("     alt stack: {}", tokens(&snapshot.alt_stack));
+    println!("     condition stack: {:?}", snapshot.condition_stack);
+}
+
+fn skipped(snapshot: &Snapshot) -> &'static str {
+    match snapshot.executed {
+        true => "",
+        false => " (skipped)",
+    }
+}
+
+fn token(token: &Token) -> String {
+    match token {
+        Token::Element(bytes) if bytes.is_empty() => "0x".to_string(),
+        token => {
+            let raw = ScriptLang::from_tokens(vec![token.clone()])
+                .serialize()
+                .unwrap_or_default();
+            disassemble(&raw)
+        }
+    }
+}
+
+// From the top
+fn tokens(tokens: &[Token]) -> String {
+    let items: Vec<String> = tokens.iter().map(token).collect();
+    format!("[{}]", items.join(", "))
+}
+
+#[cfg(test)]
+mod debugger_tests {
+    use super::{translate_debug_command, DebugCommand};
+
+    #[test]
+    fn step_command() {
+        assert_eq!(translate_debug_command("s"), DebugCommand::Step);
+        assert_eq!(translate_debug_command(" Step "), DebugCommand::Step);
+    }
+
+    #[test]
+    fn break_command() {
+        assert_eq!(translate_debug_command("b 3"), DebugCommand::Break(3));
+        assert_eq!(translate_debug_command("break 12"), DebugCommand::Break(12));
+        assert_eq!(translate_debug_command("break"), DebugCommand::Unknown);
+        assert_eq!(translate_debug_command("break x"), DebugCommand::Unknown);
+    }
+
+    #[test]
+    fn back_command() {
+        assert_eq!(translate_debug_command("back"), DebugCommand::Back(0));
+        assert_eq!(translate_debug_command("back 2"), DebugCommand::Back(2));
+    }
+
+    #[test]
+    fn unknown_command() {
+        assert_eq!(translate_debug_command("run"), DebugCommand::Unknown);
+    }
+}
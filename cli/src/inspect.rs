@@ -0,0 +1,467 @@
+use std::io::{self, Write};
+
+use rug::integer::Order;
+
+use core::{
+    block::header::{bits_to_target, difficulty, Header, HEADER_LENGTH},
+    flags::{compression::Compression, network::Network},
+    hashing::{hash160::hash160, hash256::Hash256},
+    scripting::{
+        asm::disassemble,
+        standard::{p2sh_script, standard_type, StandardType},
+    },
+    std_lib::{
+        base58::{base58_decode_binary, base58_encode},
+        rand::{EntropySource, OsEntropy},
+        std_result::StdResult,
+        vector::{bytes_to_hex_string, bytes_to_string_64, hex_string_to_bytes},
+    },
+    transaction::{script::Script, tx::Tx, tx_in::TxIn, tx_out::TxOut},
+    validate::tx::analyze,
+    wallet::{
+        address::{address, script_pub_key},
+        key::{self, UserKey},
+        wallet_file::WalletFile,
+    },
+};
+
+use crate::output::{object, Value};
+
+// Commands decoding and inspecting data: hexadecimal input unless otherwise stated
+#[derive(Debug, PartialEq)]
+pub enum Inspect {
+    DecodeRawTransaction(String),
+    DecodeScript(String),
+    DecodeBlockHeader(String),
+    ValidateAddress(String),
+    // wallet file storing the key
+    GetNewAddress(Option<String>),
+    Hash256(String),
+    Hash160(String),
+    Base58Encode(String),
+    // base58 string
+    Base58Decode(String),
+    Analyze(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    pub json: bool,
+    pub network: Network,
+    // private keys are printed (WIF)
+    pub show_secret: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            json: false,
+            network: Network::Mainnet,
+            show_secret: false,
+        }
+    }
+}
+
+pub fn print_inspect_help() {
+    println!("decoderawtransaction <hex>, decodescript <hex>, decodeblockheader <hex>, validateaddress <address>,");
+    println!("getnewaddress [<wallet file>], hash256 <hex>, hash160 <hex>, base58encode <hex>, base58decode <base58>,");
+    println!("analyze <hex>");
+    println!("options: --json (JSON output), --testnet (testnet addresses and transactions),");
+    println!("         --show-secret (private keys, needed by getnewaddress without a wallet file)");
+}
+
+pub fn run_inspect(inspect: &Inspect, options: &Options) -> StdResult<String> {
+    let network = options.network;
+
+    let value = match inspect {
+        Inspect::DecodeRawTransaction(hex) => decode_raw_transaction(&Tx::deserialize(&bytes(hex)?, network)?)?,
+        Inspect::DecodeScript(hex) => decode_script(&bytes(hex)?, network),
+        Inspect::DecodeBlockHeader(hex) => decode_block_header(&bytes(hex)?)?,
+        Inspect::ValidateAddress(address) => validate_address(address, network),
+        Inspect::GetNewAddress(wallet) => get_new_address(wallet, options)?,
+        Inspect::Hash256(hex) => bytes_to_hex_string(&Hash256::calc(&bytes(hex)?).0).into(),
+        Inspect::Hash160(hex) => bytes_to_hex_string(&hash160(&bytes(hex)?)).into(),
+        Inspect::Base58Encode(hex) => base58_encode(&bytes(hex)?).into(),
+        Inspect::Base58Decode(s) => bytes_to_hex_string(&base58_decode_binary(s)?).into(),
+        Inspect::Analyze(hex) => analyze_transaction(&Tx::deserialize(&bytes(hex)?, network)?)?,
+    };
+
+    if options.json {
+        Ok(value.to_json())
+    } else {
+        Ok(value.to_text())
+    }
+}
+
+fn bytes(hex: &str) -> StdResult<Vec<u8>> {
+    // also keeps the slicing of `hex_string_to_bytes` on char boundaries
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        Err("invalid_hex")?;
+    }
+
+    hex_string_to_bytes(hex)
+}
+
+/*
+    Type of a ScriptPubKey: the witness programs and P2SH by their template,
+    the other ones by `standard_type`.
+*/
+fn script_type(script: &Script) -> &'static str {
+    let raw = script.raw.as_slice();
+
+    match raw {
+        [0xA9, 0x14, .., 0x87] if raw.len() == 23 => "p2sh",
+        [0x00, 0x14, ..] if raw.len() == 22 => "p2wpkh",
+        [0x00, 0x20, ..] if raw.len() == 34 => "p2wsh",
+        [0x51, 0x20, ..] if raw.len() == 34 => "p2tr",
+        [0x51..=0x60, length, program @ ..] if program.len() == *length as usize && (2..=40).contains(length) => {
+            "witness_unknown"
+        }
+        _ => match standard_type(&script.script_lang) {
+            StandardType::P2pk => "p2pk",
+            StandardType::P2pkh => "p2pkh",
+            StandardType::P2ms => "multisig",
+            StandardType::Data => "nulldata",
+            StandardType::Unknown => "nonstandard",
+        },
+    }
+}
+
+fn script_fields(script: &Script, network: Network) -> Vec<(&'static str, Value)> {
+    vec![
+        ("asm", disassemble(&script.raw).into()),
+        ("hex", bytes_to_hex_string(&script.raw).into()),
+        ("type", script_type(script).into()),
+        ("address", address(script, network).ok().into()),
+    ]
+}
+
+fn input_value(tx_in: &TxIn) -> Value {
+    let witnesses: Vec<String> = tx_in.witnesses.iter().map(|w| bytes_to_hex_string(w)).collect();
+    let script_sig = &tx_in.script_sig.raw;
+
+    let mut fields = if tx_in.is_coinbase() {
+        vec![("coinbase", bytes_to_hex_string(script_sig).into())]
+    } else {
+        vec![
+            ("txid", format!("{:064X}", tx_in.previous_transaction_id).into()),
+            ("vout", tx_in.previous_transaction_index.into()),
+            (
+                "script_sig",
+                object(vec![
+                    ("asm", disassemble(script_sig).into()),
+                    ("hex", bytes_to_hex_string(script_sig).into()),
+                ]),
+            ),
+        ]
+    };
+    fields.push(("witness", witnesses.into()));
+    fields.push(("sequence", tx_in.sequence.into()));
+
+    object(fields)
+}
+
+fn output_value(n: usize, tx_out: &TxOut, network: Network) -> Value {
+    object(vec![
+        ("n", n.into()),
+        ("amount", tx_out.amount.into()),
+        ("script_pub_key", object(script_fields(&tx_out.script_pub_key, network))),
+    ])
+}
+
+fn decode_raw_transaction(tx: &Tx) -> StdResult<Value> {
+    let inputs = (0..tx.input_len())
+        .map(|i| tx.input(i).map(input_value))
+        .collect::<StdResult<Vec<Value>>>()?;
+    let outputs: Vec<Value> = (0..tx.output_len())
+        .map(|i| output_value(i, tx.outputs(i), tx.network))
+        .collect();
+
+    // weight: 4 units per byte without witnesses, 1 unit per witness byte (BIP141)
+    let size = tx.serialize_with_witnesses().len();
+    let weight = tx.serialize().len() * 3 + size;
+
+    Ok(object(vec![
+        ("txid", tx.id().into()),
+        ("version", tx.version().into()),
+        ("size", size.into()),
+        ("vsize", weight.div_ceil(4).into()),
+        ("weight", weight.into()),
+        ("locktime", tx.locktime().into()),
+        ("inputs", Value::Array(inputs)),
+        ("outputs", Value::Array(outputs)),
+    ]))
+}
+
+fn decode_script(raw: &[u8], network: Network) -> Value {
+    let script = Script::new_from_raw(raw.to_vec());
+    let mut fields = script_fields(&script, network);
+
+    // the address paying to this script, if it was a redeem script
+    if script_type(&script) != "p2sh" {
+        let p2sh = Script::new_from_script_lang(&p2sh_script(&hash160(raw)));
+        fields.push(("p2sh", address(&p2sh, network).ok().into()));
+    }
+
+    object(fields)
+}
+
+fn decode_block_header(raw: &[u8]) -> StdResult<Value> {
+    if raw.len() != HEADER_LENGTH {
+        Err("invalid_header_length")?;
+    }
+
+    let header = Header::deserialize(raw)?;
+    let target = bits_to_target(header.bits);
+
+    Ok(object(vec![
+        ("id", header.id_str().into()),
+        ("version", header.version.into()),
+        (
+            "previous_block",
+            bytes_to_string_64(&header.previous_block.to_digits(Order::Msf)).into(),
+        ),
+        (
+            "merkle_root",
+            bytes_to_string_64(&header.merkle_root.to_digits(Order::Msf)).into(),
+        ),
+        ("timestamp", header.timestamp.into()),
+        ("bits", format!("{:08X}", header.bits).into()),
+        ("nonce", header.nonce.into()),
+        ("target", bytes_to_string_64(&target.to_digits(Order::Msf)).into()),
+        ("difficulty", difficulty(target).into()),
+    ]))
+}
+
+// An invalid address is a result, not an error
+fn validate_address(address: &str, network: Network) -> Value {
+    match script_pub_key(address, network) {
+        Ok(script) => object(vec![
+            ("valid", true.into()),
+            ("address", address.into()),
+            ("script_pub_key", bytes_to_hex_string(&script.raw).into()),
+            ("type", script_type(&script).into()),
+        ]),
+        Err(e) => object(vec![
+            ("valid", false.into()),
+            ("address", address.into()),
+            ("error", e.to_string().into()),
+        ]),
+    }
+}
+
+/*
+    The key is stored in the wallet file (asking for its passphrase), or at least printed: a key kept nowhere would
+    burn the coins sent to its address.
+*/
+fn get_new_address(wallet: &Option<String>, options: &Options) -> StdResult<Value> {
+    match wallet {
+        Some(path) => add_new_key(path, &read_passphrase()?, options.show_secret, &mut OsEntropy),
+        None if options.show_secret => {
+            let user_key = key::new_from_entropy(options.network, &mut OsEntropy)?;
+            Ok(new_key_value(&user_key, options.network, true))
+        }
+        None => Err("missing_wallet")?,
+    }
+}
+
+// New key of the wallet network, saved
+pub fn add_new_key(
+    path: &str,
+    passphrase: &str,
+    show_secret: bool,
+    entropy: &mut dyn EntropySource,
+) -> StdResult<Value> {
+    let mut wallet = WalletFile::load(path)?;
+    wallet.unlock(passphrase)?;
+
+    let network = wallet.contents()?.network;
+    let user_key = key::new_from_entropy(network, entropy)?;
+
+    wallet.contents_mut()?.add_key(user_key.secret.clone(), "")?;
+    wallet.save(path, entropy)?;
+
+    Ok(new_key_value(&user_key, network, show_secret))
+}
+
+fn new_key_value(user_key: &UserKey, network: Network, show_secret: bool) -> Value {
+    let mut fields: Vec<(&str, Value)> = vec![
+        ("address", user_key.address.clone().into()),
+        ("pubkey", bytes_to_hex_string(&user_key.pubkey).into()),
+    ];
+
+    if show_secret {
+        fields.push(("wif", user_key.key.to_wif(Compression::Compressed, network).into()));
+    }
+
+    object(fields)
+}
+
+// From the console (echoed)
+fn read_passphrase() -> StdResult<String> {
+    print!("passphrase: ");
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// The previous transactions are fetched from the network
+fn analyze_transaction(tx: &Tx) -> StdResult<Value> {
+    let result = analyze(tx)?;
+
+    let outputs: Vec<Value> = result
+        .outputs
+        .iter()
+        .map(|output| {
+            object(vec![
+                ("type", format!("{:?}", output.standard).to_lowercase().into()),
+                ("data", output.data.as_ref().map(|d| bytes_to_hex_string(d)).into()),
+            ])
+        })
+        .collect();
+
+    Ok(object(vec![
+        ("txid", tx.id().into()),
+        ("valid", result.valid.into()),
+        ("fee", result.fee.into()),
+        ("outputs", Value::Array(outputs)),
+    ]))
+}
+
+#[cfg(test)]
+mod inspect_test {
+    use core::{
+        encryption::scrypt::ScryptParams, std_lib::rand::DeterministicEntropy, wallet::wallet_file::WalletContents,
+    };
+
+    use super::*;
+
+    // private key 1
+    const P2PKH_SCRIPT: &str = "76A914751E76E8199196D454941C45D1B3A323F1433BD688AC";
+    const GENESIS_HEADER: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003BA3EDFD7A7B12B27AC72C3E67768F617FC81BC3888A51323A9FB8AA4B1E5E4A29AB5F49FFFF001D1DAC2B7C";
+
+    fn text(inspect: Inspect) -> String {
+        run_inspect(&inspect, &Options::default()).unwrap()
+    }
+
+    #[test]
+    fn hashes() {
+        assert_eq!(
+            text(Inspect::Hash256(String::new())),
+            "5DF6E0E2761359D30A8275058E299FCC0381534545F55CF43E41983F5D4C9456"
+        );
+        assert_eq!(
+            text(Inspect::Hash160(String::new())),
+            "B472A266D0BD89C13706A4132CCFB16F7C3B9FCB"
+        );
+    }
+
+    #[test]
+    fn base58() {
+        assert_eq!(text(Inspect::Base58Encode("0000010203".to_string())), "11Ldp");
+        assert_eq!(text(Inspect::Base58Decode("11Ldp".to_string())), "0000010203");
+
+        let err = run_inspect(&Inspect::Base58Decode("0OIl".to_string()), &Options::default()).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_character");
+    }
+
+    #[test]
+    fn decode_p2pkh_script() {
+        let decoded = text(Inspect::DecodeScript(P2PKH_SCRIPT.to_string()));
+
+        assert!(decoded.contains("type: p2pkh"));
+        assert!(decoded.contains("address: 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"));
+        assert!(decoded.contains("asm: OP_DUP OP_HASH160"));
+    }
+
+    #[test]
+    fn decode_genesis_block_header() {
+        let options = Options {
+            json: true,
+            ..Options::default()
+        };
+
+        let decoded = run_inspect(&Inspect::DecodeBlockHeader(GENESIS_HEADER.to_string()), &options).unwrap();
+
+        assert!(decoded.contains("\"id\": \"000000000019D6689C085AE165831E934FF763AE46A2A6C172B3F1B60A8CE26F\""));
+        assert!(decoded.contains("\"bits\": \"1D00FFFF\""));
+        assert!(decoded.contains("\"nonce\": 2083236893"));
+        assert!(decoded.contains("\"difficulty\": 1"));
+
+        let err = run_inspect(&Inspect::DecodeBlockHeader("0100".to_string()), &options).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_header_length");
+    }
+
+    #[test]
+    fn validate_addresses() {
+        let valid = text(Inspect::ValidateAddress(
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string(),
+        ));
+        assert!(valid.contains("valid: true"));
+        assert!(valid.contains("script_pub_key: 0014751E76E8199196D454941C45D1B3A323F1433BD6"));
+        assert!(valid.contains("type: p2wpkh"));
+
+        let invalid = text(Inspect::ValidateAddress(
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMh".to_string(),
+        ));
+        assert!(invalid.contains("valid: false"));
+    }
+
+    #[test]
+    fn new_testnet_address() {
+        let options = Options {
+            network: Network::Testnet,
+            show_secret: true,
+            ..Options::default()
+        };
+
+        let new_address = run_inspect(&Inspect::GetNewAddress(None), &options).unwrap();
+
+        assert!(new_address.starts_with("address: m") || new_address.starts_with("address: n"));
+        assert!(new_address.contains("pubkey: 0"));
+        assert!(new_address.contains("wif: c"));
+    }
+
+    #[test]
+    fn new_address_without_wallet() {
+        let err = run_inspect(&Inspect::GetNewAddress(None), &Options::default()).expect_err("Err");
+
+        assert_eq!(err.to_string(), "missing_wallet");
+    }
+
+    #[test]
+    fn new_address_in_wallet() {
+        let mut entropy = DeterministicEntropy::new(b"inspect");
+        let path = std::env::temp_dir().join("inspect_test.wallet");
+        let path = path.to_str().unwrap();
+
+        let contents = WalletContents::new(Network::Testnet);
+        let params = ScryptParams::new(4, 1, 1).unwrap();
+        let mut wallet = WalletFile::create(contents, "passphrase", params, &mut entropy).unwrap();
+        wallet.save(path, &mut entropy).unwrap();
+
+        let err = add_new_key(path, "wrong", false, &mut entropy).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_passphrase");
+
+        let new_address = add_new_key(path, "passphrase", false, &mut entropy).unwrap().to_text();
+        assert!(!new_address.contains("wif"));
+
+        let mut wallet = WalletFile::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        wallet.unlock("passphrase").unwrap();
+
+        let keys = &wallet.contents().unwrap().keys;
+        assert_eq!(keys.len(), 1);
+        assert!(new_address.starts_with(&format!("address: {}", keys[0].address(Network::Testnet))));
+    }
+
+    #[test]
+    fn invalid_hex() {
+        let err = run_inspect(&Inspect::Hash256("ABC".to_string()), &Options::default()).expect_err("Err");
+
+        assert_eq!(err.to_string(), "invalid_hex");
+    }
+}
@@ -10,6 +10,9 @@ Entry point
 // )]
 
 mod command_line;
+mod debugger;
+mod inspect;
+mod output;
 use command_line::{print_exit_help, run_command_line};
 
 fn main() {
@@ -0,0 +1,268 @@
+/*
+    Result of a command, printed as JSON (pretty, 2 spaces) or as human-readable text:
+        object      one `key: value` line per field, nested objects and arrays indented below their key
+        array       one `- value` line per item, objects as `[index]` followed by their fields
+    Fields keep their insertion order.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(i128),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+const INDENT: usize = 2;
+
+pub fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(n: u32) -> Self {
+        Value::Number(n as i128)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Self {
+        Value::Number(n as i128)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Self {
+        Value::Number(n as i128)
+    }
+}
+
+impl From<i128> for Value {
+    fn from(n: i128) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(o: Option<T>) -> Self {
+        o.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl Value {
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(0, &mut out);
+        out
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        match self {
+            Value::Object(_) | Value::Array(_) => self.write_text(0, &mut out),
+            _ => out.push_str(&self.scalar_text()),
+        }
+        out.trim_end().to_string()
+    }
+
+    fn is_nested(&self) -> bool {
+        match self {
+            Value::Array(items) => !items.is_empty(),
+            Value::Object(fields) => !fields.is_empty(),
+            _ => false,
+        }
+    }
+
+    fn write_json(&self, indent: usize, out: &mut String) {
+        let padding = " ".repeat(indent + INDENT);
+
+        match self {
+            Value::Array(items) if !items.is_empty() => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&padding);
+                    item.write_json(indent + INDENT, out);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&format!("{}]", " ".repeat(indent)));
+            }
+            Value::Object(fields) if !fields.is_empty() => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(&format!("{}{}: ", padding, escape(key)));
+                    value.write_json(indent + INDENT, out);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&format!("{}}}", " ".repeat(indent)));
+            }
+            Value::Array(_) => out.push_str("[]"),
+            Value::Object(_) => out.push_str("{}"),
+            Value::String(s) => out.push_str(&escape(s)),
+            // NaN and infinity are not valid JSON numbers
+            Value::Float(f) if !f.is_finite() => out.push_str("null"),
+            _ => out.push_str(&self.scalar_text()),
+        }
+    }
+
+    fn write_text(&self, indent: usize, out: &mut String) {
+        let padding = " ".repeat(indent);
+
+        match self {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    if value.is_nested() {
+                        out.push_str(&format!("{}{}:\n", padding, key));
+                        value.write_text(indent + INDENT, out);
+                    } else {
+                        out.push_str(&format!("{}{}: {}\n", padding, key, value.scalar_text()));
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if item.is_nested() {
+                        out.push_str(&format!("{}[{}]\n", padding, i));
+                        item.write_text(indent + INDENT, out);
+                    } else {
+                        out.push_str(&format!("{}- {}\n", padding, item.scalar_text()));
+                    }
+                }
+            }
+            _ => out.push_str(&format!("{}{}\n", padding, self.scalar_text())),
+        }
+    }
+
+    fn scalar_text(&self) -> String {
+        match self {
+            Value::Null => "null".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::String(s) => s.clone(),
+            Value::Array(_) => "[]".to_string(),
+            Value::Object(_) => "{}".to_string(),
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod output_test {
+    use super::*;
+
+    fn sample() -> Value {
+        object(vec![
+            ("txid", "AB".into()),
+            ("valid", true.into()),
+            ("fee", Value::Number(-1)),
+            ("address", Option::<String>::None.into()),
+            ("witnesses", vec!["01", "02"].into()),
+            ("outputs", Value::Array(vec![object(vec![("amount", 5u64.into())])])),
+            ("inputs", Value::Array(vec![])),
+        ])
+    }
+
+    #[test]
+    fn json() {
+        let expected = r#"{
+  "txid": "AB",
+  "valid": true,
+  "fee": -1,
+  "address": null,
+  "witnesses": [
+    "01",
+    "02"
+  ],
+  "outputs": [
+    {
+      "amount": 5
+    }
+  ],
+  "inputs": []
+}"#;
+
+        assert_eq!(sample().to_json(), expected);
+    }
+
+    #[test]
+    fn text() {
+        let expected = "txid: AB
+valid: true
+fee: -1
+address: null
+witnesses:
+  - 01
+  - 02
+outputs:
+  [0]
+    amount: 5
+inputs: []";
+
+        assert_eq!(sample().to_text(), expected);
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(Value::from("00FF").to_text(), "00FF");
+        assert_eq!(Value::from("00FF").to_json(), "\"00FF\"");
+        assert_eq!(Value::Float(1.5).to_json(), "1.5");
+        assert_eq!(Value::Float(f64::NAN).to_json(), "null");
+    }
+
+    #[test]
+    fn escaped_strings() {
+        assert_eq!(Value::from("a\"b\\c\nd\u{1}").to_json(), r#""a\"b\\c\nd\u0001""#);
+    }
+}
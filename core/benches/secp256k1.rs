@@ -0,0 +1,97 @@
+/*
+    secp256k1 scalar multiplication: fixed width backend vs "double and add" with generic field arithmetic.
+
+    How to run:
+        cargo bench --bench secp256k1
+*/
+
+extern crate core;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rug::{integer::Order, Integer};
+
+use core::{
+    bitcoin::ecdsa::{G, N},
+    ecdsa::{point::Point, scalar256::Scalar256},
+    keys::key::Key,
+    std_lib::{integer_extended::IntegerExtended, vector::vect_to_array_32},
+};
+
+fn secret() -> Integer {
+    Integer::from_hex_str("A23B7C91D3E5F10243B5C3A3E8D0F6A9987654321FEDCBA0123456789ABCDEF0")
+}
+
+fn hashed_message() -> Integer {
+    Integer::from_hex_str("EC208BAA0FC1C19F708A9CA96FDEFF3AC3F230BB4A7BA4AEDE4942AD003C0F60")
+}
+
+fn generator_multiplication(c: &mut Criterion) {
+    let k = secret();
+    let secret_bytes = vect_to_array_32(&k.to_digits::<u8>(Order::Msf));
+    let mut group = c.benchmark_group("k * G");
+
+    group.bench_function("double_and_add", |b| {
+        b.iter(|| (*G).mul_double_and_add(black_box(k.clone())))
+    });
+    group.bench_function("fixed_width", |b| b.iter(|| Point::mul_generator(black_box(&k))));
+    group.bench_function("constant_time", |b| {
+        b.iter(|| Point::mul_generator_secret(black_box(&Scalar256::from_bytes_reduced(&secret_bytes))))
+    });
+
+    group.finish();
+}
+
+fn point_multiplication(c: &mut Criterion) {
+    let k = secret();
+    let point = Point::mul_generator(&Integer::from(5000));
+    let mut group = c.benchmark_group("k * P");
+
+    group.bench_function("double_and_add", |b| {
+        b.iter(|| point.mul_double_and_add(black_box(k.clone())))
+    });
+    group.bench_function("fixed_width", |b| b.iter(|| &point * black_box(k.clone())));
+
+    group.finish();
+}
+
+fn signature_verification(c: &mut Criterion) {
+    let key = Key::new(secret());
+    let point = key.public_key();
+    let z = hashed_message();
+    let signature = key.sign(z.clone());
+
+    // u * G + v * P as computed by `Key::verify_signature`
+    let s_inv = signature.s.invert_by_modulo(&N);
+    let u = Integer::from(&z * &s_inv) % &*N;
+    let v = Integer::from(&signature.r * &s_inv) % &*N;
+
+    let mut group = c.benchmark_group("u * G + v * P");
+
+    group.bench_function("double_and_add", |b| {
+        b.iter(|| (*G).mul_double_and_add(black_box(u.clone())) + &point.mul_double_and_add(black_box(v.clone())))
+    });
+    group.bench_function("fixed_width", |b| {
+        b.iter(|| Point::mul_generator_add(black_box(&u), &point, black_box(&v)))
+    });
+    group.bench_function("verify_signature", |b| {
+        b.iter(|| Key::verify_signature(&point, black_box(&z), &signature))
+    });
+
+    group.finish();
+}
+
+fn signing(c: &mut Criterion) {
+    let key = Key::new(secret());
+    let z = hashed_message();
+
+    c.bench_function("sign", |b| b.iter(|| key.sign(black_box(z.clone()))));
+}
+
+criterion_group!(
+    benches,
+    generator_multiplication,
+    point_multiplication,
+    signature_verification,
+    signing
+);
+criterion_main!(benches);
@@ -6,13 +6,13 @@ pub enum AddressPrefix {
     PrivateKeyP2pkhMainnet = Network::Mainnet as isize,
     // PRIVATE_KEY_P2SH_MAINNET = 0x05,
     PrivateKeyMainnet = 0x80,
-    // PUBLIC_KEY_BIP32_MAINNET = 0x0488B21E,
-    // PRIVATE_KEY_BIP32_MAINNET = 0x0488ADE4,
+    PublicKeyBip32Mainnet = 0x0488B21E,
+    PrivateKeyBip32Mainnet = 0x0488ADE4,
     PublicKeyP2pkhTestnet = Network::Testnet as isize,
     // PUBLIC_KEY_SCRIPT_TESTNET = 0xC4,
     PrivateKeyTestnet = 0xEF,
-    // PUBLIC_KEY_BIP32_TESTNET = 0x043587CF,
-    // PRIVATE_KEY_BIP32_TESTNET = 0x04358394,
+    PublicKeyBip32Testnet = 0x043587CF,
+    PrivateKeyBip32Testnet = 0x04358394,
     // EXT_PUBLIC_KEY_SEGWIT = 0x04B24746,
     // EXT_SECRET_KEY_SEGWIT = 0x04B2430C,
 }
@@ -22,8 +22,12 @@ impl Display for AddressPrefix {
         let n = match self {
             AddressPrefix::PrivateKeyP2pkhMainnet => "PrivateKeyP2pkhMainnet",
             AddressPrefix::PrivateKeyMainnet => "PrivateKeyMainnet",
+            AddressPrefix::PublicKeyBip32Mainnet => "PublicKeyBip32Mainnet",
+            AddressPrefix::PrivateKeyBip32Mainnet => "PrivateKeyBip32Mainnet",
             AddressPrefix::PublicKeyP2pkhTestnet => "PublicKeyP2pkhTestnet",
             AddressPrefix::PrivateKeyTestnet => "PrivateKeyTestnet",
+            AddressPrefix::PublicKeyBip32Testnet => "PublicKeyBip32Testnet",
+            AddressPrefix::PrivateKeyBip32Testnet => "PrivateKeyBip32Testnet",
         };
         writeln!(f, "{:}", n)
     }
@@ -0,0 +1,202 @@
+/*
+    Block: the header, then the number of transactions (varint) and the transactions (with witnesses).
+
+    Raw block files (`blk*.dat` of Bitcoin Core) store each block as:
+        <network magic (4 bytes)> <block size (4 bytes, little endian)> <block>
+    Blocks are in the order they were received, not by height: `chain_blocks` orders them by previous block.
+*/
+
+use std::collections::HashMap;
+
+use rug::{integer::Order, Integer};
+
+use crate::{
+    flags::{network::Network, network_magic::NetworkMagic},
+    std_lib::{std_result::StdResult, varint::encode},
+    transaction::{
+        tx::Tx,
+        tx_lib::{le_bytes_to_u32, varint_decode},
+    },
+};
+
+use super::header::{Header, HEADER_LENGTH};
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub header: Header,
+    pub transactions: Vec<Tx>,
+}
+
+// Same serialization (header and transactions with witnesses)
+impl PartialEq for Block {
+    fn eq(&self, other: &Self) -> bool {
+        self.serialize() == other.serialize()
+    }
+}
+
+impl Block {
+    pub fn new(header: Header, transactions: Vec<Tx>) -> Self {
+        Self { header, transactions }
+    }
+
+    pub fn id_str(&self) -> String {
+        self.header.id_str()
+    }
+
+    // As `Header::previous_block`
+    pub fn id_as_integer(&self) -> Integer {
+        Integer::from_digits(&self.header.id().0, Order::Lsf)
+    }
+
+    pub fn deserialize(bytes: &[u8], network: Network) -> StdResult<Self> {
+        let header = Header::deserialize(bytes)?;
+        let mut cursor = HEADER_LENGTH;
+
+        let tx_count = varint_decode(bytes, cursor)?;
+        cursor += tx_count.length;
+
+        let mut transactions: Vec<Tx> = vec![];
+        for _ in 0..tx_count.value {
+            let (tx, c) = Tx::deserialize_from(bytes, cursor, network)?;
+            cursor = c;
+
+            transactions.push(tx);
+        }
+
+        if cursor != bytes.len() {
+            log::error!("Block partially read. Cursor: {:?}, length: {:?}", cursor, bytes.len());
+            Err("partially_read_block")?;
+        }
+
+        Ok(Self { header, transactions })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut serialized = self.header.serialize();
+        serialized.extend(encode(self.transactions.len() as u64));
+
+        for tx in self.transactions.iter() {
+            serialized.extend(tx.serialize_with_witnesses());
+        }
+
+        serialized
+    }
+}
+
+// Blocks of a raw block file, the file can end with zeros (preallocated space)
+pub fn read_block_file(data: &[u8], magic: NetworkMagic, network: Network) -> StdResult<Vec<Block>> {
+    let magic = magic.to_le_bytes();
+
+    let mut blocks: Vec<Block> = vec![];
+    let mut cursor: usize = 0;
+
+    while cursor < data.len() {
+        if data[cursor..].iter().all(|b| *b == 0) {
+            break;
+        }
+
+        if data.get(cursor..cursor + 4) != Some(magic.as_slice()) {
+            Err("invalid_block_file_magic")?;
+        }
+
+        let size = le_bytes_to_u32(data, cursor + 4)? as usize;
+        cursor += 8;
+
+        let block = match data.get(cursor..cursor.saturating_add(size)) {
+            Some(block) => block,
+            None => Err("block_file_out_of_bounds")?,
+        };
+
+        blocks.push(Block::deserialize(block, network)?);
+        cursor += size;
+    }
+
+    Ok(blocks)
+}
+
+/*
+    The blocks following `previous_block` one after the other, in height order.
+    Blocks not connected to it (or of another branch, once a block has more children) are left out.
+*/
+pub fn chain_blocks(blocks: Vec<Block>, previous_block: &Integer) -> Vec<Block> {
+    let mut by_previous: HashMap<Integer, Block> = HashMap::new();
+    for block in blocks {
+        by_previous.entry(block.header.previous_block.clone()).or_insert(block);
+    }
+
+    let mut chain: Vec<Block> = vec![];
+    let mut previous = previous_block.clone();
+
+    while let Some(block) = by_previous.remove(&previous) {
+        previous = block.id_as_integer();
+        chain.push(block);
+    }
+
+    chain
+}
+
+#[cfg(test)]
+mod block_test {
+    use crate::std_lib::vector::hex_string_to_bytes;
+
+    use super::*;
+
+    // Genesis block of mainnet
+    const GENESIS_BLOCK: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    #[test]
+    fn deserialize_and_serialize() {
+        let bytes = hex_string_to_bytes(GENESIS_BLOCK).unwrap();
+
+        let block = Block::deserialize(&bytes, Network::Mainnet).unwrap();
+        assert_eq!(
+            block.id_str(),
+            "000000000019D6689C085AE165831E934FF763AE46A2A6C172B3F1B60A8CE26F"
+        );
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(
+            block.transactions[0].id(),
+            "4A5E1E4BAAB89F3A32518A88C31BC87F618F76673E2CC77AB2127B7AFDEDA33B"
+        );
+        assert_eq!(block.serialize(), bytes);
+
+        let err = Block::deserialize(&bytes[..bytes.len() - 1], Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_4_bytes_length");
+    }
+
+    #[test]
+    fn block_file() {
+        let block = hex_string_to_bytes(GENESIS_BLOCK).unwrap();
+
+        let mut data = NetworkMagic::Mainnet.to_le_bytes().to_vec();
+        data.extend((block.len() as u32).to_le_bytes());
+        data.extend(&block);
+        data.extend([0x00; 16]);
+
+        let blocks = read_block_file(&data, NetworkMagic::Mainnet, Network::Mainnet).unwrap();
+        assert_eq!(blocks.len(), 1);
+
+        let err = read_block_file(&data, NetworkMagic::Testnet3, Network::Testnet).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_block_file_magic");
+
+        let err = read_block_file(&data[..100], NetworkMagic::Mainnet, Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "block_file_out_of_bounds");
+    }
+
+    #[test]
+    fn chain() {
+        let genesis = Block::deserialize(&hex_string_to_bytes(GENESIS_BLOCK).unwrap(), Network::Mainnet).unwrap();
+
+        let mut next = genesis.clone();
+        next.header.previous_block = genesis.id_as_integer();
+        let mut last = genesis.clone();
+        last.header.previous_block = next.id_as_integer();
+
+        let chain = chain_blocks(vec![last.clone(), genesis.clone(), next.clone()], &Integer::new());
+        let ids: Vec<String> = chain.iter().map(|b| b.id_str()).collect();
+        assert_eq!(ids, vec![genesis.id_str(), next.id_str(), last.id_str()]);
+
+        let chain = chain_blocks(vec![next], &Integer::new());
+        assert!(chain.is_empty());
+    }
+}
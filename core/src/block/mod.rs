@@ -1 +1,2 @@
+pub mod full_block;
 pub mod header;
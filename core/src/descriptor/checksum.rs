@@ -0,0 +1,130 @@
+/*
+    Descriptor checksum (BIP380): 8 characters after `#`, a BCH code over the descriptor characters.
+    Each character is a position in INPUT_CHARSET: its low 5 bits are a symbol, the high bits of groups of
+    three characters one more symbol (so that case errors are found too).
+*/
+
+use crate::std_lib::std_result::StdResult;
+
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+
+const CHECKSUM_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u64; 5] = [0xF5DEE51989, 0xA9FDCA3312, 0x1BAB10E32D, 0x3706B1677A, 0x644D626FFD];
+
+pub const CHECKSUM_LENGTH: usize = 8;
+
+fn polymod(symbols: &[u64]) -> u64 {
+    let mut checksum: u64 = 1;
+
+    for symbol in symbols {
+        let top = checksum >> 35;
+        checksum = ((checksum & 0x7FFFFFFFF) << 5) ^ symbol;
+
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+
+    checksum
+}
+
+fn expand(descriptor: &str) -> StdResult<Vec<u64>> {
+    let mut symbols: Vec<u64> = vec![];
+    let mut groups: Vec<u64> = vec![];
+
+    for c in descriptor.chars() {
+        let position = match INPUT_CHARSET.find(c) {
+            Some(position) => position as u64,
+            None => Err("invalid_descriptor_character")?,
+        };
+
+        symbols.push(position & 31);
+        groups.push(position >> 5);
+
+        if groups.len() == 3 {
+            symbols.push(groups[0] * 9 + groups[1] * 3 + groups[2]);
+            groups.clear();
+        }
+    }
+
+    match groups.as_slice() {
+        [g] => symbols.push(*g),
+        [g0, g1] => symbols.push(g0 * 3 + g1),
+        _ => {}
+    }
+
+    Ok(symbols)
+}
+
+// Checksum of `descriptor` (without `#`)
+pub fn checksum(descriptor: &str) -> StdResult<String> {
+    let mut symbols = expand(descriptor)?;
+    symbols.extend([0; CHECKSUM_LENGTH]);
+
+    let checksum = polymod(&symbols) ^ 1;
+
+    let checksum = (0..CHECKSUM_LENGTH)
+        .map(|i| CHECKSUM_CHARSET[((checksum >> (5 * (CHECKSUM_LENGTH - 1 - i))) & 31) as usize] as char)
+        .collect();
+
+    Ok(checksum)
+}
+
+pub fn add_checksum(descriptor: &str) -> StdResult<String> {
+    Ok(format!("{}#{}", descriptor, checksum(descriptor)?))
+}
+
+// The descriptor without its checksum, that is verified (if present)
+pub fn strip_checksum(descriptor: &str) -> StdResult<&str> {
+    let Some((descriptor, expected)) = descriptor.split_once('#') else {
+        checksum(descriptor)?;
+        return Ok(descriptor);
+    };
+
+    if expected.len() != CHECKSUM_LENGTH {
+        Err("invalid_descriptor_checksum_length")?;
+    }
+
+    if checksum(descriptor)? != expected {
+        Err("invalid_descriptor_checksum")?;
+    }
+
+    Ok(descriptor)
+}
+
+#[cfg(test)]
+mod checksum_test {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        assert_eq!(checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert_eq!(add_checksum("raw(deadbeef)").unwrap(), "raw(deadbeef)#89f8spxm");
+
+        assert_eq!(strip_checksum("raw(deadbeef)#89f8spxm").unwrap(), "raw(deadbeef)");
+        assert_eq!(strip_checksum("raw(deadbeef)").unwrap(), "raw(deadbeef)");
+    }
+
+    #[test]
+    fn invalid() {
+        let err = strip_checksum("raw(deadbeef)#89f8spxn").expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_descriptor_checksum");
+
+        // a case error is found
+        let err = strip_checksum("raw(deadbeeF)#89f8spxm").expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_descriptor_checksum");
+
+        let err = strip_checksum("raw(deadbeef)#89f8spx").expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_descriptor_checksum_length");
+
+        let err = strip_checksum("raw(deadbeef)#89f8spxm#").expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_descriptor_checksum_length");
+
+        let err = checksum("raw(deadbeef\u{e9})").expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_descriptor_character");
+    }
+}
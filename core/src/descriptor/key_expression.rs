@@ -0,0 +1,340 @@
+/*
+    Key expressions (BIP380): `[origin]KEY`, the origin being the fingerprint of the master key and the path from it.
+    KEY:
+        hex public key      compressed (33 bytes), uncompressed (65 bytes), x-only (32 bytes, in `tr` only)
+        WIF private key
+        xpub, xprv          then a derivation path (`/1/2`), ending with a `*` child for a range of keys
+                            (`*'` hardened, from an xprv only)
+    Hardened child numbers are marked by `'` or `h`.
+*/
+
+use rug::{integer::Order, Integer};
+
+use crate::{
+    bitcoin::ecdsa::P,
+    ecdsa::point::Point,
+    flags::{compression::Compression, network::Network},
+    keys::key::Key,
+    std_lib::{std_result::StdResult, vector::hex_string_to_bytes},
+    wallet::bip32::{DerivationPath, ExtendedPrivateKey, ExtendedPublicKey, Fingerprint, HARDENED_OFFSET},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyOrigin {
+    pub fingerprint: Fingerprint,
+    pub path: DerivationPath,
+}
+
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    // SEC or x-only
+    PublicKey(Vec<u8>),
+    PrivateKey(Key, Compression),
+    Xpub(ExtendedPublicKey),
+    Xprv(ExtendedPrivateKey),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wildcard {
+    None,
+    Unhardened,
+    Hardened,
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyExpression {
+    pub origin: Option<KeyOrigin>,
+    pub source: KeySource,
+    // after the extended key
+    pub path: Vec<u32>,
+    pub wildcard: Wildcard,
+}
+
+impl KeyExpression {
+    pub fn parse(expression: &str, network: Network) -> StdResult<Self> {
+        let (origin, key) = match expression.strip_prefix('[') {
+            Some(rest) => match rest.split_once(']') {
+                Some((origin, key)) => (Some(parse_origin(origin)?), key),
+                None => Err("invalid_key_origin")?,
+            },
+            None => (None, expression),
+        };
+
+        let (key, path) = match key.split_once('/') {
+            Some((key, path)) => (key, Some(path)),
+            None => (key, None),
+        };
+
+        let source = parse_key(key, network)?;
+
+        let (path, wildcard) = match path {
+            Some(path) => {
+                let mut parts: Vec<&str> = path.split('/').collect();
+
+                let wildcard = match parts.last() {
+                    Some(&"*") => Wildcard::Unhardened,
+                    Some(&("*'" | "*h" | "*H")) => Wildcard::Hardened,
+                    _ => Wildcard::None,
+                };
+                if wildcard != Wildcard::None {
+                    parts.pop();
+                }
+
+                let path = std::iter::once("m").chain(parts).collect::<Vec<&str>>().join("/");
+                (DerivationPath::parse(&path)?.child_numbers().to_vec(), wildcard)
+            }
+            None => (vec![], Wildcard::None),
+        };
+
+        match &source {
+            KeySource::PublicKey(_) | KeySource::PrivateKey(..) if !path.is_empty() || wildcard != Wildcard::None => {
+                Err("derivation_from_non_extended_key")?
+            }
+            KeySource::Xpub(_) if wildcard == Wildcard::Hardened || path.iter().any(|c| *c >= HARDENED_OFFSET) => {
+                Err("hardened_derivation_from_xpub")?
+            }
+            _ => {}
+        }
+
+        Ok(Self {
+            origin,
+            source,
+            path,
+            wildcard,
+        })
+    }
+
+    pub fn is_range(&self) -> bool {
+        self.wildcard != Wildcard::None
+    }
+
+    pub fn is_x_only(&self) -> bool {
+        matches!(&self.source, KeySource::PublicKey(public_key) if public_key.len() == 32)
+    }
+
+    pub fn is_uncompressed(&self) -> bool {
+        match &self.source {
+            KeySource::PublicKey(public_key) => public_key.len() == 65,
+            KeySource::PrivateKey(_, compression) => *compression == Compression::Uncompressed,
+            _ => false,
+        }
+    }
+
+    // Child numbers after the extended key, with `index` if a range
+    fn child_numbers(&self, index: u32) -> StdResult<Vec<u32>> {
+        let mut child_numbers = self.path.clone();
+
+        match self.wildcard {
+            Wildcard::None => {}
+            _ if index >= HARDENED_OFFSET => Err("invalid_child_number")?,
+            Wildcard::Unhardened => child_numbers.push(index),
+            Wildcard::Hardened => child_numbers.push(index + HARDENED_OFFSET),
+        }
+
+        Ok(child_numbers)
+    }
+
+    // SEC (or x-only) public key, `index` of the range
+    pub fn public_key(&self, index: u32) -> StdResult<Vec<u8>> {
+        let public_key = match &self.source {
+            KeySource::PublicKey(public_key) => public_key.clone(),
+            KeySource::PrivateKey(key, compression) => key.public_key().serialize(*compression),
+            KeySource::Xpub(xpub) => self
+                .child_numbers(index)?
+                .iter()
+                .try_fold(xpub.clone(), |parent, child_number| parent.derive_child(*child_number))?
+                .public_key_sec(),
+            KeySource::Xprv(xprv) => self
+                .child_numbers(index)?
+                .iter()
+                .try_fold(xprv.clone(), |parent, child_number| parent.derive_child(*child_number))?
+                .extended_public_key()
+                .public_key_sec(),
+        };
+
+        Ok(public_key)
+    }
+
+    // Master key fingerprint and path of the key `index`, if known (e.g. for the PSBT BIP32 derivations)
+    pub fn key_origin(&self, index: u32) -> StdResult<Option<KeyOrigin>> {
+        let (fingerprint, mut path) = match (&self.origin, &self.source) {
+            (Some(origin), _) => (origin.fingerprint, origin.path.clone()),
+            (None, KeySource::Xpub(xpub)) => (xpub.fingerprint(), DerivationPath::default()),
+            (None, KeySource::Xprv(xprv)) => (xprv.fingerprint(), DerivationPath::default()),
+            (None, _) => return Ok(None),
+        };
+
+        for child_number in self.child_numbers(index)? {
+            path = path.child(child_number);
+        }
+
+        Ok(Some(KeyOrigin { fingerprint, path }))
+    }
+}
+
+// `d34db33f/44'/0'/0'`
+fn parse_origin(origin: &str) -> StdResult<KeyOrigin> {
+    let (fingerprint, path) = match origin.split_once('/') {
+        Some((fingerprint, path)) => (fingerprint, format!("m/{}", path)),
+        None => (origin, "m".to_string()),
+    };
+
+    let fingerprint: Fingerprint = match hex_string_to_bytes(fingerprint) {
+        Ok(bytes) if fingerprint.len() == 8 => bytes.try_into().unwrap(),
+        _ => Err("invalid_key_origin_fingerprint")?,
+    };
+
+    Ok(KeyOrigin {
+        fingerprint,
+        path: DerivationPath::parse(&path)?,
+    })
+}
+
+fn parse_key(key: &str, network: Network) -> StdResult<KeySource> {
+    if key.len() >= 64 && key.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let public_key = hex_string_to_bytes(key)?;
+        verify_public_key(&public_key)?;
+
+        return Ok(KeySource::PublicKey(public_key));
+    }
+
+    let source = match key.get(..4) {
+        Some("xpub" | "tpub") => {
+            let xpub = ExtendedPublicKey::from_base58(key)?;
+            if xpub.network() != network {
+                Err("incongruent_network")?;
+            }
+
+            KeySource::Xpub(xpub)
+        }
+        Some("xprv" | "tprv") => {
+            let xprv = ExtendedPrivateKey::from_base58(key)?;
+            if xprv.network() != network {
+                Err("incongruent_network")?;
+            }
+
+            KeySource::Xprv(xprv)
+        }
+        _ => {
+            let (key, compression, key_network) = match Key::from_wif(key) {
+                Ok(wif) => wif,
+                Err(_) => Err("invalid_key")?,
+            };
+            if key_network != network {
+                Err("incongruent_network")?;
+            }
+
+            KeySource::PrivateKey(key, compression)
+        }
+    };
+
+    Ok(source)
+}
+
+// Compressed, uncompressed or x-only, on the curve
+pub fn verify_public_key(public_key: &[u8]) -> StdResult<()> {
+    let sec = match public_key {
+        [0x02 | 0x03, ..] if public_key.len() == 33 => public_key.to_vec(),
+        [0x04, ..] if public_key.len() == 65 => public_key.to_vec(),
+        _ if public_key.len() == 32 => [&[0x02][..], public_key].concat(),
+        _ => Err("invalid_public_key")?,
+    };
+
+    if Integer::from_digits(&sec[1..33], Order::Msf) >= *P || !Point::deserialize(sec).is_on_curve() {
+        Err("invalid_public_key")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod key_expression_test {
+    use super::*;
+    use crate::std_lib::vector::bytes_to_hex_string;
+
+    // BIP32 test vector 1 master key
+    const XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+    const XPRV: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+
+    #[test]
+    fn hex_public_key() {
+        let public_key = "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+        let key = KeyExpression::parse(public_key, Network::Mainnet).unwrap();
+
+        assert!(!key.is_range());
+        assert!(!key.is_uncompressed());
+        assert_eq!(bytes_to_hex_string(&key.public_key(0).unwrap()), public_key);
+        assert!(key.key_origin(0).unwrap().is_none());
+
+        let x_only = KeyExpression::parse(&public_key[2..], Network::Mainnet).unwrap();
+        assert!(x_only.is_x_only());
+
+        let err = KeyExpression::parse(&format!("02{}", "F".repeat(64)), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_public_key");
+
+        let err = KeyExpression::parse(&format!("{}/0", public_key), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "derivation_from_non_extended_key");
+    }
+
+    #[test]
+    fn wif() {
+        // private key 1, uncompressed
+        let key =
+            KeyExpression::parse("5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf", Network::Mainnet).unwrap();
+
+        assert!(key.is_uncompressed());
+        assert_eq!(key.public_key(0).unwrap()[0], 0x04);
+
+        let err = KeyExpression::parse("5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf", Network::Testnet)
+            .expect_err("Err");
+        assert_eq!(err.to_string(), "incongruent_network");
+    }
+
+    #[test]
+    fn extended_keys() {
+        let key = KeyExpression::parse(&format!("[d34db33f/44'/0h]{}/1/*", XPUB), Network::Mainnet).unwrap();
+        assert!(key.is_range());
+        assert_eq!(key.path, vec![1]);
+
+        let origin = key.key_origin(7).unwrap().unwrap();
+        assert_eq!(origin.fingerprint, [0xD3, 0x4D, 0xB3, 0x3F]);
+        assert_eq!(origin.path.to_string(), "m/44'/0'/1/7");
+
+        // same keys from the xprv
+        let private = KeyExpression::parse(&format!("{}/1/*", XPRV), Network::Mainnet).unwrap();
+        assert_eq!(private.public_key(7).unwrap(), key.public_key(7).unwrap());
+        assert_eq!(
+            private.key_origin(7).unwrap().unwrap().fingerprint,
+            [0x34, 0x42, 0x19, 0x3E]
+        );
+
+        let hardened = KeyExpression::parse(&format!("{}/0'/*h", XPRV), Network::Mainnet).unwrap();
+        assert_eq!(hardened.wildcard, Wildcard::Hardened);
+        assert_eq!(hardened.path, vec![HARDENED_OFFSET]);
+
+        let fixed = KeyExpression::parse(&format!("{}/0/1", XPUB), Network::Mainnet).unwrap();
+        assert!(!fixed.is_range());
+        assert_eq!(fixed.path, vec![0, 1]);
+    }
+
+    #[test]
+    fn invalid_extended_keys() {
+        let err = KeyExpression::parse(&format!("{}/0'/*", XPUB), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "hardened_derivation_from_xpub");
+
+        let err = KeyExpression::parse(&format!("{}/*'", XPUB), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "hardened_derivation_from_xpub");
+
+        let err = KeyExpression::parse(XPUB, Network::Testnet).expect_err("Err");
+        assert_eq!(err.to_string(), "incongruent_network");
+
+        let err = KeyExpression::parse(&format!("[d34db33]{}", XPUB), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_key_origin_fingerprint");
+
+        let err = KeyExpression::parse(&format!("[d34db33f{}", XPUB), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_key_origin");
+
+        let err = KeyExpression::parse(&format!("{}/0/x", XPUB), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_child_number");
+    }
+}
@@ -0,0 +1,4 @@
+pub mod checksum;
+pub mod key_expression;
+pub mod output_descriptor;
+pub mod taproot;
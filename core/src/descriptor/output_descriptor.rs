@@ -0,0 +1,563 @@
+/*
+    Output script descriptors (BIP380-386), `SCRIPT#checksum` (the checksum is optional when parsed):
+        pk(KEY)                     <public key> OP_CHECKSIG
+        pkh(KEY)                    P2PKH
+        wpkh(KEY)                   P2WPKH (compressed key), top level or in `sh`
+        sh(SCRIPT)                  P2SH of pk, pkh, wpkh, wsh, multi, sortedmulti, top level only
+        wsh(SCRIPT)                 P2WSH of pk, pkh, multi, sortedmulti (compressed keys), top level or in `sh`
+        multi(k,KEY_1,...,KEY_n)    k-of-n multisig, keys in the order given
+        sortedmulti(k,KEY_1,...)    k-of-n multisig, keys sorted once derived (BIP67)
+        tr(KEY) / tr(KEY,TREE)      P2TR of the internal key (x-only) and the script tree, top level only
+                                    TREE: `pk(KEY)` leaves, `{TREE,TREE}` branches
+        addr(ADDRESS)               ScriptPubKey of the address, top level only
+        raw(HEX)                    ScriptPubKey, top level only
+
+    A descriptor with a range of keys (a `*` child) describes a script for each index (see `key_expression`).
+*/
+
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    flags::network::Network,
+    hashing::{hash160::hash160, sha256::sha256},
+    scripting::{
+        script_lang::ScriptLang,
+        standard::{p2ms_script, p2pk_script, p2pkh_script, p2sh_script, p2tr_script, p2wpkh_script, p2wsh_script},
+    },
+    std_lib::{
+        std_result::StdResult,
+        vector::{bytes_to_hex_string, hex_string_to_bytes},
+    },
+    transaction::script::Script,
+    wallet::address,
+};
+
+use super::{
+    checksum::{add_checksum, strip_checksum},
+    key_expression::{KeyExpression, KeySource},
+    taproot::{branch_hash, leaf_hash, output_key, x_only},
+};
+
+// OP_1 ... OP_16
+const MAX_MULTI_KEYS: usize = 16;
+
+// P2SH redeem script, pushed by the ScriptSig
+const MAX_REDEEM_SCRIPT_SIZE: usize = 520;
+
+// BIP341
+const MAX_TAPROOT_DEPTH: usize = 128;
+
+#[derive(Debug, Clone)]
+pub enum TapTree {
+    Leaf(KeyExpression),
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Expression {
+    Pk(KeyExpression),
+    Pkh(KeyExpression),
+    Wpkh(KeyExpression),
+    Sh(Box<Expression>),
+    Wsh(Box<Expression>),
+    Multi {
+        threshold: usize,
+        keys: Vec<KeyExpression>,
+        sorted: bool,
+    },
+    Tr {
+        internal_key: KeyExpression,
+        tree: Option<TapTree>,
+    },
+    Addr(Script),
+    Raw(Script),
+}
+
+// Where an expression is
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Context {
+    Top,
+    Sh,
+    Wsh,
+}
+
+#[derive(Debug, Clone)]
+pub struct Descriptor {
+    expression: Expression,
+    network: Network,
+    // with checksum
+    descriptor: String,
+}
+
+impl Display for Descriptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.descriptor)
+    }
+}
+
+impl Descriptor {
+    pub fn parse(descriptor: &str, network: Network) -> StdResult<Self> {
+        let descriptor = strip_checksum(descriptor.trim())?;
+        let expression = parse_expression(descriptor, Context::Top, network)?;
+
+        Ok(Self {
+            expression,
+            network,
+            descriptor: add_checksum(descriptor)?,
+        })
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub fn is_range(&self) -> bool {
+        is_range(&self.expression)
+    }
+
+    // ScriptPubKey, `index` of the range (ignored if not a range)
+    pub fn script_pub_key(&self, index: u32) -> StdResult<ScriptLang> {
+        script(&self.expression, index)
+    }
+
+    pub fn address(&self, index: u32) -> StdResult<String> {
+        let script_pub_key = Script::new_from_script_lang(&self.script_pub_key(index)?);
+
+        address::address(&script_pub_key, self.network)
+    }
+
+    // The script of `sh`
+    pub fn redeem_script(&self, index: u32) -> StdResult<Option<ScriptLang>> {
+        match &self.expression {
+            Expression::Sh(inner) => Ok(Some(script(inner, index)?)),
+            _ => Ok(None),
+        }
+    }
+
+    // The script of `wsh` (also in `sh`)
+    pub fn witness_script(&self, index: u32) -> StdResult<Option<ScriptLang>> {
+        match &self.expression {
+            Expression::Wsh(inner) => Ok(Some(script(inner, index)?)),
+            Expression::Sh(inner) => match inner.as_ref() {
+                Expression::Wsh(inner) => Ok(Some(script(inner, index)?)),
+                _ => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    // Key expressions, in the order they appear
+    pub fn keys(&self) -> Vec<&KeyExpression> {
+        keys(&self.expression)
+    }
+
+    // With WIF or xprv keys
+    pub fn has_private_keys(&self) -> bool {
+        self.keys()
+            .iter()
+            .any(|key| matches!(key.source, KeySource::PrivateKey(..) | KeySource::Xprv(_)))
+    }
+
+    // The same descriptor with the public keys of its WIF and xprv keys (not with hardened children after an xprv)
+    pub fn public(&self) -> StdResult<Descriptor> {
+        let mut public = strip_checksum(&self.descriptor)?.to_string();
+
+        for key in self.keys() {
+            match &key.source {
+                KeySource::PrivateKey(private_key, compression) => {
+                    let wif = private_key.to_wif(*compression, self.network);
+                    public = public.replace(&wif, &bytes_to_hex_string(&key.public_key(0)?).to_lowercase());
+                }
+                KeySource::Xprv(xprv) => {
+                    public = public.replace(&xprv.to_base58(), &xprv.extended_public_key().to_base58());
+                }
+                KeySource::PublicKey(_) | KeySource::Xpub(_) => {}
+            }
+        }
+
+        Descriptor::parse(&public, self.network)
+    }
+}
+
+fn parse_expression(s: &str, context: Context, network: Network) -> StdResult<Expression> {
+    let (name, arguments) = function(s)?;
+
+    let expression = match name {
+        "pk" => Expression::Pk(parse_key(arguments, context, network)?),
+        "pkh" => Expression::Pkh(parse_key(arguments, context, network)?),
+        "wpkh" if context != Context::Wsh => {
+            let key = parse_key(arguments, context, network)?;
+            if key.is_uncompressed() {
+                Err("uncompressed_key_in_segwit")?;
+            }
+
+            Expression::Wpkh(key)
+        }
+        "sh" if context == Context::Top => Expression::Sh(Box::new(parse_expression(arguments, Context::Sh, network)?)),
+        "wsh" if context != Context::Wsh => {
+            Expression::Wsh(Box::new(parse_expression(arguments, Context::Wsh, network)?))
+        }
+        "multi" | "sortedmulti" => parse_multi(arguments, name == "sortedmulti", context, network)?,
+        "tr" if context == Context::Top => parse_tr(arguments, network)?,
+        "addr" if context == Context::Top => Expression::Addr(address::script_pub_key(arguments, network)?),
+        "raw" if context == Context::Top => match hex_string_to_bytes(arguments) {
+            Ok(raw) if !arguments.is_empty() => Expression::Raw(Script::new_from_raw(raw)),
+            _ => Err("invalid_raw_script")?,
+        },
+        "pk" | "pkh" | "wpkh" | "sh" | "wsh" | "tr" | "addr" | "raw" => Err("invalid_descriptor_context")?,
+        _ => Err("unknown_descriptor_function")?,
+    };
+
+    if context == Context::Sh && script(&expression, 0)?.serialize()?.len() > MAX_REDEEM_SCRIPT_SIZE {
+        Err("redeem_script_too_large")?;
+    }
+
+    Ok(expression)
+}
+
+fn parse_key(s: &str, context: Context, network: Network) -> StdResult<KeyExpression> {
+    let key = KeyExpression::parse(s, network)?;
+
+    if key.is_x_only() {
+        Err("x_only_key_outside_tr")?;
+    }
+
+    if context == Context::Wsh && key.is_uncompressed() {
+        Err("uncompressed_key_in_segwit")?;
+    }
+
+    Ok(key)
+}
+
+fn parse_multi(s: &str, sorted: bool, context: Context, network: Network) -> StdResult<Expression> {
+    let arguments = split_arguments(s);
+
+    let threshold: usize = match arguments[0].parse() {
+        Ok(threshold) => threshold,
+        Err(_) => Err("invalid_multi_threshold")?,
+    };
+
+    let keys = arguments[1..]
+        .iter()
+        .map(|key| parse_key(key, context, network))
+        .collect::<StdResult<Vec<KeyExpression>>>()?;
+
+    if keys.is_empty() || keys.len() > MAX_MULTI_KEYS {
+        Err("invalid_multi_keys_count")?;
+    }
+
+    if threshold == 0 || threshold > keys.len() {
+        Err("invalid_multi_threshold")?;
+    }
+
+    Ok(Expression::Multi {
+        threshold,
+        keys,
+        sorted,
+    })
+}
+
+fn parse_tr(s: &str, network: Network) -> StdResult<Expression> {
+    let arguments = split_arguments(s);
+
+    let (internal_key, tree) = match arguments.as_slice() {
+        [internal_key] => (internal_key, None),
+        [internal_key, tree] => (internal_key, Some(parse_tap_tree(tree, 0, network)?)),
+        _ => Err("invalid_tr_arguments")?,
+    };
+
+    Ok(Expression::Tr {
+        internal_key: parse_tap_key(internal_key, network)?,
+        tree,
+    })
+}
+
+fn parse_tap_tree(s: &str, depth: usize, network: Network) -> StdResult<TapTree> {
+    if depth > MAX_TAPROOT_DEPTH {
+        Err("taproot_tree_too_deep")?;
+    }
+
+    if let Some(branches) = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+        return match split_arguments(branches).as_slice() {
+            [left, right] => Ok(TapTree::Branch(
+                Box::new(parse_tap_tree(left, depth + 1, network)?),
+                Box::new(parse_tap_tree(right, depth + 1, network)?),
+            )),
+            _ => Err("invalid_taproot_tree")?,
+        };
+    }
+
+    match function(s)? {
+        ("pk", key) => Ok(TapTree::Leaf(parse_tap_key(key, network)?)),
+        _ => Err("unsupported_tapscript")?,
+    }
+}
+
+fn parse_tap_key(s: &str, network: Network) -> StdResult<KeyExpression> {
+    let key = KeyExpression::parse(s, network)?;
+
+    if key.is_uncompressed() {
+        Err("uncompressed_key_in_segwit")?;
+    }
+
+    Ok(key)
+}
+
+// `name(arguments)`
+pub fn function(s: &str) -> StdResult<(&str, &str)> {
+    match s.split_once('(') {
+        Some((name, rest)) => match rest.strip_suffix(')') {
+            Some(arguments) => Ok((name, arguments)),
+            None => Err("invalid_descriptor")?,
+        },
+        None => Err("invalid_descriptor")?,
+    }
+}
+
+// Arguments separated by commas, not in nested expressions
+pub fn split_arguments(s: &str) -> Vec<&str> {
+    let mut arguments: Vec<&str> = vec![];
+    let mut depth: usize = 0;
+    let mut start: usize = 0;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                arguments.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    arguments.push(&s[start..]);
+    arguments
+}
+
+fn script(expression: &Expression, index: u32) -> StdResult<ScriptLang> {
+    let script = match expression {
+        Expression::Pk(key) => p2pk_script(&key.public_key(index)?),
+        Expression::Pkh(key) => p2pkh_script(&hash160(&key.public_key(index)?)),
+        Expression::Wpkh(key) => p2wpkh_script(&hash160(&key.public_key(index)?)),
+        Expression::Sh(inner) => p2sh_script(&hash160(&script(inner, index)?.serialize()?)),
+        Expression::Wsh(inner) => p2wsh_script(&sha256(&script(inner, index)?.serialize()?)),
+        Expression::Multi {
+            threshold,
+            keys,
+            sorted,
+        } => {
+            let mut public_keys = keys
+                .iter()
+                .map(|key| key.public_key(index))
+                .collect::<StdResult<Vec<Vec<u8>>>>()?;
+
+            if *sorted {
+                public_keys.sort();
+            }
+
+            let public_keys: Vec<&[u8]> = public_keys.iter().map(|key| key.as_slice()).collect();
+            p2ms_script(*threshold, &public_keys)
+        }
+        Expression::Tr { internal_key, tree } => {
+            let merkle_root = match tree {
+                Some(tree) => Some(tap_tree_hash(tree, index)?),
+                None => None,
+            };
+
+            p2tr_script(&output_key(&internal_key.public_key(index)?, merkle_root.as_deref())?)
+        }
+        Expression::Addr(script_pub_key) | Expression::Raw(script_pub_key) => script_pub_key.script_lang.clone(),
+    };
+
+    Ok(script)
+}
+
+fn tap_tree_hash(tree: &TapTree, index: u32) -> StdResult<Vec<u8>> {
+    match tree {
+        TapTree::Leaf(key) => {
+            let leaf_script = p2pk_script(&x_only(&key.public_key(index)?)?);
+            Ok(leaf_hash(&leaf_script.serialize()?))
+        }
+        TapTree::Branch(left, right) => Ok(branch_hash(&tap_tree_hash(left, index)?, &tap_tree_hash(right, index)?)),
+    }
+}
+
+fn keys(expression: &Expression) -> Vec<&KeyExpression> {
+    match expression {
+        Expression::Pk(key) | Expression::Pkh(key) | Expression::Wpkh(key) => vec![key],
+        Expression::Sh(inner) | Expression::Wsh(inner) => keys(inner),
+        Expression::Multi { keys, .. } => keys.iter().collect(),
+        Expression::Tr { internal_key, tree } => {
+            let mut keys = vec![internal_key];
+            if let Some(tree) = tree {
+                tap_tree_keys(tree, &mut keys);
+            }
+
+            keys
+        }
+        Expression::Addr(_) | Expression::Raw(_) => vec![],
+    }
+}
+
+fn tap_tree_keys<'a>(tree: &'a TapTree, keys: &mut Vec<&'a KeyExpression>) {
+    match tree {
+        TapTree::Leaf(key) => keys.push(key),
+        TapTree::Branch(left, right) => {
+            tap_tree_keys(left, keys);
+            tap_tree_keys(right, keys);
+        }
+    }
+}
+
+fn is_range(expression: &Expression) -> bool {
+    keys(expression).iter().any(|key| key.is_range())
+}
+
+#[cfg(test)]
+mod output_descriptor_test {
+    use super::*;
+
+    // private keys 1 and 2
+    const KEY_1: &str = "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+    const KEY_2: &str = "02C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5";
+
+    macro_rules! address_test {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (descriptor, expected) = $value;
+                let descriptor = Descriptor::parse(&descriptor, Network::Mainnet).unwrap();
+
+                assert!(!descriptor.is_range());
+                assert_eq!(descriptor.address(0).unwrap(), expected);
+            }
+        )*
+        }
+    }
+
+    address_test! {
+        pkh: (format!("pkh({KEY_1})"), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"),
+        wpkh: (format!("wpkh({KEY_1})"), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+        sh_wpkh: (format!("sh(wpkh({KEY_1}))"), "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN"),
+        sh_sortedmulti: (format!("sh(sortedmulti(1,{KEY_2},{KEY_1}))"), "38fEX6RbBBMmpu3nbbuULku1xyrrzqqqnE"),
+        wsh_multi: (format!("wsh(multi(1,{KEY_1},{KEY_2}))"), "bc1qd6e6c86xp56gw8pty8suuqhscpttea2c5m2fggzjkxzk5nl9faks5pehxu"),
+        sh_wsh_multi: (format!("sh(wsh(multi(1,{KEY_1},{KEY_2})))"), "3JSuZ22mPR2aD5bf77KsGi36V5ar3p87LX"),
+        tr: (format!("tr({KEY_1})"), "bc1pmfr3p9j00pfxjh0zmgp99y8zftmd3s5pmedqhyptwy6lm87hf5sspknck9"),
+        tr_script_tree: (format!("tr({},pk({}))", &KEY_1[2..], &KEY_2[2..]), "bc1pg44et8f66qnjn5fd0hu6dnnx7tczqslmt3dkzpccjlzeg99psshqn82yk5"),
+        addr: ("addr(1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH)".to_string(), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"),
+        raw: ("raw(0014751E76E8199196D454941C45D1B3A323F1433BD6)".to_string(), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+    }
+
+    #[test]
+    fn checksum() {
+        let descriptor = Descriptor::parse(&format!("pkh({KEY_1})"), Network::Mainnet).unwrap();
+        assert_eq!(descriptor.to_string(), format!("pkh({KEY_1})#dm69h8jt"));
+
+        let same = Descriptor::parse(&descriptor.to_string(), Network::Mainnet).unwrap();
+        assert_eq!(same.address(0).unwrap(), descriptor.address(0).unwrap());
+
+        let err = Descriptor::parse(&format!("pkh({KEY_1})#dm69h8jq"), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_descriptor_checksum");
+    }
+
+    #[test]
+    fn scripts() {
+        let descriptor =
+            Descriptor::parse(&format!("sh(wsh(sortedmulti(1,{KEY_2},{KEY_1})))"), Network::Mainnet).unwrap();
+        let multi = format!("OP_1 {KEY_1} {KEY_2} OP_2 OP_CHECKMULTISIG");
+
+        let witness_script = descriptor.witness_script(0).unwrap().unwrap();
+        assert_eq!(witness_script.to_string(), multi);
+
+        let redeem_script = descriptor.redeem_script(0).unwrap().unwrap();
+        assert_eq!(redeem_script.serialize().unwrap()[..2], [0x00, 0x20]);
+
+        let pkh = Descriptor::parse(&format!("pkh({KEY_1})"), Network::Mainnet).unwrap();
+        assert!(pkh.redeem_script(0).unwrap().is_none());
+        assert!(pkh.witness_script(0).unwrap().is_none());
+    }
+
+    #[test]
+    fn bip86_range() {
+        // "abandon ... about" account m/86'/0'/0'
+        let xpub = "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ";
+        let descriptor = Descriptor::parse(&format!("tr([73c5da0a/86'/0'/0']{xpub}/0/*)"), Network::Mainnet).unwrap();
+
+        assert!(descriptor.is_range());
+        assert!(descriptor.to_string().ends_with("#rg247h69"));
+        assert_eq!(
+            descriptor.address(0).unwrap(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+        assert_eq!(
+            descriptor.address(1).unwrap(),
+            "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh"
+        );
+    }
+
+    #[test]
+    fn public() {
+        // private key 1
+        let wif = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+        let private = Descriptor::parse(&format!("wpkh({wif})"), Network::Mainnet).unwrap();
+        let public = private.public().unwrap();
+
+        assert!(private.has_private_keys());
+        assert!(!public.has_private_keys());
+        assert_eq!(
+            public.to_string(),
+            add_checksum(&format!("wpkh({})", KEY_1.to_lowercase())).unwrap()
+        );
+
+        let xprv = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+        let private = Descriptor::parse(&format!("pkh({xprv}/1/*)"), Network::Mainnet).unwrap();
+        let public = private.public().unwrap();
+
+        assert!(!public.to_string().contains(xprv));
+        assert_eq!(public.address(3).unwrap(), private.address(3).unwrap());
+
+        // hardened children need the private key
+        let hardened = Descriptor::parse(&format!("pkh({xprv}/1/*')"), Network::Mainnet).unwrap();
+        assert!(hardened.public().is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        let uncompressed = "5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf";
+
+        let cases = [
+            (format!("wsh(wpkh({KEY_1}))"), "invalid_descriptor_context"),
+            (format!("sh(sh(pkh({KEY_1})))"), "invalid_descriptor_context"),
+            (format!("wsh(tr({KEY_1}))"), "invalid_descriptor_context"),
+            (format!("wpkh({uncompressed})"), "uncompressed_key_in_segwit"),
+            (format!("wsh(pk({uncompressed}))"), "uncompressed_key_in_segwit"),
+            (format!("pk({})", &KEY_1[2..]), "x_only_key_outside_tr"),
+            (format!("multi(0,{KEY_1})"), "invalid_multi_threshold"),
+            (format!("multi(2,{KEY_1})"), "invalid_multi_threshold"),
+            (
+                format!("multi(1,{})", [KEY_1; 17].join(",")),
+                "invalid_multi_keys_count",
+            ),
+            (
+                format!("sh(multi(1,{}))", [KEY_1; 16].join(",")),
+                "redeem_script_too_large",
+            ),
+            (format!("tr({KEY_1},pkh({KEY_2}))"), "unsupported_tapscript"),
+            (format!("foo({KEY_1})"), "unknown_descriptor_function"),
+            (format!("pkh({KEY_1}"), "invalid_descriptor"),
+            ("raw()".to_string(), "invalid_raw_script"),
+        ];
+
+        for (descriptor, expected) in cases {
+            let err = Descriptor::parse(&descriptor, Network::Mainnet).expect_err("Err");
+            assert_eq!(err.to_string(), expected, "{descriptor}");
+        }
+    }
+}
@@ -0,0 +1,208 @@
+/*
+    Taproot output key (BIP341): the internal key tweaked by the Merkle root of the script tree (if any).
+        t = tagged_hash("TapTweak", x(P) || merkle root), Q = P + t * G (P with even y)
+    Leaves are hashed with their version (tapscript, 0xC0), branches with their children sorted.
+*/
+
+use rug::{integer::Order, Integer};
+
+use crate::{
+    bitcoin::ecdsa::{N, P},
+    ecdsa::point::Point,
+    flags::compression::Compression,
+    hashing::tagged_hash::tagged_hash,
+    keys::key::Key,
+    std_lib::{integer_extended::IntegerExtended, std_result::StdResult, varint::encode},
+};
+
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xC0;
+// the lowest bit of the first byte of the control block is the parity of the output key
+pub const LEAF_VERSION_MASK: u8 = 0xFE;
+const CONTROL_BLOCK_BASE_SIZE: usize = 33;
+const MAX_TREE_DEPTH: usize = 128;
+
+// 32 bytes x-only public key, from a compressed (or x-only) public key
+pub fn x_only(public_key: &[u8]) -> StdResult<Vec<u8>> {
+    match public_key.len() {
+        32 => Ok(public_key.to_vec()),
+        33 => Ok(public_key[1..].to_vec()),
+        _ => Err("invalid_x_only_public_key")?,
+    }
+}
+
+pub fn leaf_hash(script: &[u8]) -> Vec<u8> {
+    leaf_hash_with_version(TAPSCRIPT_LEAF_VERSION, script)
+}
+
+fn leaf_hash_with_version(leaf_version: u8, script: &[u8]) -> Vec<u8> {
+    let leaf = [vec![leaf_version], encode(script.len() as u64), script.to_vec()].concat();
+
+    tagged_hash("TapLeaf", &leaf)
+}
+
+pub fn branch_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let (a, b) = if left <= right { (left, right) } else { (right, left) };
+
+    tagged_hash("TapBranch", &[a, b].concat())
+}
+
+// x-only output key
+pub fn output_key(internal_key: &[u8], merkle_root: Option<&[u8]>) -> StdResult<Vec<u8>> {
+    x_only(&output_point(internal_key, merkle_root)?.serialize(Compression::Compressed))
+}
+
+/*
+    BIP341 script path: the control block proves that `script` is a leaf of the tree committed in `output_key`.
+        control block   leaf version | parity of y of the output key (1 byte), internal key (32 bytes),
+                        Merkle path from the leaf to the root (32 bytes each, up to 128)
+*/
+pub fn verify_script_path(output_key: &[u8], control_block: &[u8], script: &[u8]) -> StdResult<bool> {
+    let path_length = control_block.len().saturating_sub(CONTROL_BLOCK_BASE_SIZE);
+    if control_block.len() < CONTROL_BLOCK_BASE_SIZE || path_length % 32 != 0 || path_length / 32 > MAX_TREE_DEPTH {
+        Err("invalid_control_block_size")?;
+    }
+
+    let leaf_version = control_block[0] & LEAF_VERSION_MASK;
+    let merkle_root = control_block[CONTROL_BLOCK_BASE_SIZE..]
+        .chunks(32)
+        .fold(leaf_hash_with_version(leaf_version, script), |hash, node| {
+            branch_hash(&hash, node)
+        });
+
+    // an internal key not in the curve commits to nothing
+    let Ok(output) = output_point(&control_block[1..CONTROL_BLOCK_BASE_SIZE], Some(&merkle_root)) else {
+        return Ok(false);
+    };
+
+    let serialized = output.serialize(Compression::Compressed);
+    let odd_y = serialized[0] == 0x03;
+
+    Ok(serialized[1..] == *output_key && odd_y == (control_block[0] & 0x01 == 0x01))
+}
+
+// Output key as a point: the parity of its y is in the control block of script path spends
+fn output_point(internal_key: &[u8], merkle_root: Option<&[u8]>) -> StdResult<Point> {
+    let internal_key = x_only(internal_key)?;
+
+    if Integer::from_digits(&internal_key, Order::Msf) >= *P {
+        Err("invalid_x_only_public_key")?;
+    }
+
+    let point = Point::deserialize([vec![0x02], internal_key.clone()].concat());
+    if !point.is_on_curve() {
+        Err("invalid_x_only_public_key")?;
+    }
+
+    let tweak = tweak(&internal_key, merkle_root)?;
+
+    let output = Point::mul_generator_add(&tweak, &point, &Integer::from(1));
+    if output.is_infinite() {
+        Err("invalid_taproot_tweak")?;
+    }
+
+    Ok(output)
+}
+
+// Private key of the output key, for key path spending: (d + t) mod N, with d negated if d * G has odd y
+pub fn tweak_private_key(key: &Key, merkle_root: Option<&[u8]>) -> StdResult<Key> {
+    let tweak = tweak(&key.x_only_public_key(), merkle_root)?;
+
+    let mut secret = key.private_key().clone();
+    if key.public_key().y_as_num().is_odd() {
+        let negated = Integer::from(&*N - &secret);
+        secret.zeroize();
+        secret = negated;
+    }
+
+    let (_q, tweaked) = Integer::from(&secret + &tweak).div_rem_euc((*N).clone());
+    secret.zeroize();
+    if tweaked == 0 {
+        Err("invalid_taproot_tweak")?;
+    }
+
+    Ok(Key::new(tweaked))
+}
+
+fn tweak(internal_key: &[u8], merkle_root: Option<&[u8]>) -> StdResult<Integer> {
+    let tweak = tagged_hash("TapTweak", &[internal_key, merkle_root.unwrap_or(&[])].concat());
+
+    let tweak = Integer::from_digits(&tweak, Order::Msf);
+    if tweak >= *N {
+        Err("invalid_taproot_tweak")?;
+    }
+
+    Ok(tweak)
+}
+
+#[cfg(test)]
+mod taproot_test {
+    use super::*;
+    use crate::std_lib::vector::{bytes_to_hex_string, hex_string_to_bytes};
+
+    // BIP86: m/86'/0'/0'/0/0 of "abandon abandon ... about"
+    #[test]
+    fn key_path_only() {
+        let internal_key =
+            hex_string_to_bytes("CC8A4BC64D897BDDC5FBC2F670F7A8BA0B386779106CF1223C6FC5D7CD6FC115").unwrap();
+
+        assert_eq!(
+            bytes_to_hex_string(&output_key(&internal_key, None).unwrap()),
+            "A60869F0DBCF1DC659C9CECBAF8050135EA9E8CDC487053F1DC6880949DC684C"
+        );
+    }
+
+    #[test]
+    fn tweaked_private_key() {
+        // 6 * G has odd y
+        for secret in [1, 2, 6] {
+            let key = Key::new(Integer::from(secret));
+
+            let tweaked = tweak_private_key(&key, None).unwrap();
+
+            assert_eq!(
+                tweaked.x_only_public_key(),
+                output_key(&key.x_only_public_key(), None).unwrap()
+            );
+        }
+    }
+
+    // BIP341 test vectors (scriptPubKey, two leaves)
+    #[test]
+    fn script_path() {
+        let output_key =
+            hex_string_to_bytes("712447206D7A5238ACC7FF53FBE94A3B64539AD291C7CDBC490B7577E4B17DF5").unwrap();
+        let leaves = [
+            (
+                "20387671353E273264C495656E27E39BA899EA8FEE3BB69FB2A680E22093447D48AC",
+                "C0EE4FE085983462A184015D1F782D6A5F8B9C2B60130AFF050CE221ECF3786592F224A923CD0021AB202AB139CC56802DDB92DCFC172B9212261A539DF79A112A",
+            ),
+            (
+                "06424950333431",
+                "FAEE4FE085983462A184015D1F782D6A5F8B9C2B60130AFF050CE221ECF37865928AD69EC7CF41C2A4001FD1F738BF1E505CE2277ACDCAA63FE4765192497F47A7",
+            ),
+        ];
+
+        for (script, control_block) in leaves {
+            let script = hex_string_to_bytes(script).unwrap();
+            let mut control_block = hex_string_to_bytes(control_block).unwrap();
+
+            assert!(verify_script_path(&output_key, &control_block, &script).unwrap());
+
+            // the parity of the output key
+            control_block[0] ^= 0x01;
+            assert!(!verify_script_path(&output_key, &control_block, &script).unwrap());
+        }
+
+        let err = verify_script_path(&output_key, &[0xC0; 34], &[]).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_control_block_size");
+    }
+
+    #[test]
+    fn invalid_internal_key() {
+        let err = output_key(&[0xFF; 32], None).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_x_only_public_key");
+
+        let err = output_key(&[0x02; 20], None).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_x_only_public_key");
+    }
+}
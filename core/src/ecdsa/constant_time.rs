@@ -0,0 +1,192 @@
+//! Constant time `k * G` for secret scalars (private keys, signature nonces).
+//!
+//! `scalar_mul::mul_generator` skips zero windows and its additions branch on special cases
+//! (infinity, doubling), so its timing depends on `k`. Here:
+//! - every window does exactly one addition, with the complete formulas of Renes, Costello and Batina
+//!   (https://eprint.iacr.org/2015/1060, algorithm 7 with a = 0) in homogeneous projective coordinates:
+//!   the same operations for any input, infinity included;
+//! - the table entry is selected by scanning the whole window (no secret dependent memory access).
+
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use super::{
+    field256::Field256,
+    jacobian_point::AffinePoint,
+    scalar256::Scalar256,
+    scalar_mul::{G_TABLE, G_WINDOW_BITS, G_WINDOW_SIZE},
+};
+
+/// 3 * b
+const CURVE_B3: Field256 = Field256::from_u64(21);
+
+/// (X, Y, Z) represents the affine point (X / Z, Y / Z), infinity is (0, 1, 0).
+#[derive(Debug, Clone, Copy)]
+struct ProjectivePoint {
+    x: Field256,
+    y: Field256,
+    z: Field256,
+}
+
+impl ProjectivePoint {
+    const INFINITY: ProjectivePoint = ProjectivePoint {
+        x: Field256::ZERO,
+        y: Field256::ONE,
+        z: Field256::ZERO,
+    };
+
+    fn from_affine(point: &AffinePoint) -> ProjectivePoint {
+        let finite = ProjectivePoint {
+            x: point.x,
+            y: point.y,
+            z: Field256::ONE,
+        };
+
+        ProjectivePoint::conditional_select(&finite, &ProjectivePoint::INFINITY, Choice::from(point.infinity as u8))
+    }
+
+    /// Complete addition: valid for any couple of points (doubling and infinity included).
+    fn add(&self, other: &ProjectivePoint) -> ProjectivePoint {
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let (x2, y2, z2) = (other.x, other.y, other.z);
+
+        let mut t0 = x1 * x2;
+        let mut t1 = y1 * y2;
+        let mut t2 = z1 * z2;
+        let mut t3 = (x1 + y1) * (x2 + y2);
+        let mut t4 = t0 + t1;
+        t3 = t3 - t4;
+        t4 = (y1 + z1) * (y2 + z2);
+        let mut x3 = t1 + t2;
+        t4 = t4 - x3;
+        x3 = (x1 + z1) * (x2 + z2);
+        let mut y3 = t0 + t2;
+        y3 = x3 - y3;
+        x3 = t0 + t0;
+        t0 = x3 + t0;
+        t2 = CURVE_B3 * t2;
+        let mut z3 = t1 + t2;
+        t1 = t1 - t2;
+        y3 = CURVE_B3 * y3;
+        x3 = t4 * y3;
+        t2 = t3 * t1;
+        x3 = t2 - x3;
+        y3 = y3 * t0;
+        t1 = t1 * z3;
+        y3 = t1 + y3;
+        t0 = t0 * t3;
+        z3 = z3 * t4;
+        z3 = z3 + t0;
+
+        ProjectivePoint { x: x3, y: y3, z: z3 }
+    }
+
+    fn to_affine(&self) -> AffinePoint {
+        // inverse of zero is zero: infinity becomes (0, 0), then flagged
+        let z_inv = self.z.invert();
+
+        AffinePoint {
+            x: self.x * z_inv,
+            y: self.y * z_inv,
+            infinity: bool::from(self.z.ct_eq(&Field256::ZERO)),
+        }
+    }
+}
+
+impl ConditionallySelectable for ProjectivePoint {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        ProjectivePoint {
+            x: Field256::conditional_select(&a.x, &b.x, choice),
+            y: Field256::conditional_select(&a.y, &b.y, choice),
+            z: Field256::conditional_select(&a.z, &b.z, choice),
+        }
+    }
+}
+
+/// `k * G` in constant time.
+pub fn mul_generator(k: &Scalar256) -> AffinePoint {
+    let limbs = k.limbs();
+    let mut acc = ProjectivePoint::INFINITY;
+
+    for (i, window) in G_TABLE.iter().enumerate() {
+        let bit = i * G_WINDOW_BITS;
+        let digit = (limbs[bit / 64] >> (bit % 64)) & (G_WINDOW_SIZE as u64 - 1);
+
+        let mut entry = ProjectivePoint::INFINITY;
+        for (j, point) in window.iter().enumerate() {
+            let candidate = ProjectivePoint::from_affine(point);
+            entry.conditional_assign(&candidate, digit.ct_eq(&(j as u64)));
+        }
+
+        acc = acc.add(&entry);
+    }
+
+    acc.to_affine()
+}
+
+#[cfg(test)]
+mod constant_time_test {
+    use super::*;
+    use crate::ecdsa::{jacobian_point::GENERATOR, scalar_mul};
+
+    fn scalar(hex: &str) -> Scalar256 {
+        let padded = format!("{:0>64}", hex);
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&padded[i * 2..i * 2 + 2], 16).unwrap();
+        }
+
+        Scalar256::from_bytes_reduced(&bytes)
+    }
+
+    macro_rules! same_as_variable_time {
+        ($k:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let k = scalar($k);
+
+                assert_eq!(
+                    mul_generator(&k),
+                    scalar_mul::mul_generator(&k.limbs()).to_affine()
+                );
+            }
+        };
+    }
+
+    same_as_variable_time!("1", generator_multiple_1);
+    same_as_variable_time!("2", generator_multiple_2);
+    same_as_variable_time!("1388", generator_multiple_5000);
+    same_as_variable_time!(
+        "A23B7C91D3E5F10243B5C3A3E8D0F6A9987654321FEDCBA0123456789ABCDEF0",
+        generator_multiple_random
+    );
+    same_as_variable_time!(
+        "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140",
+        generator_multiple_n_minus_1
+    );
+
+    #[test]
+    fn generator_multiple_zero_is_infinite() {
+        assert!(mul_generator(&Scalar256::ZERO).infinity);
+    }
+
+    #[test]
+    fn complete_addition_special_cases() {
+        let g = ProjectivePoint::from_affine(&GENERATOR);
+        let minus_g = ProjectivePoint::from_affine(&-GENERATOR);
+        let two_g = scalar_mul::mul_generator(&scalar("2").limbs()).to_affine();
+
+        // doubling
+        assert_eq!(g.add(&g).to_affine(), two_g);
+        // opposite
+        assert!(g.add(&minus_g).to_affine().infinity);
+        // infinity as both operands
+        assert_eq!(g.add(&ProjectivePoint::INFINITY).to_affine(), GENERATOR);
+        assert_eq!(ProjectivePoint::INFINITY.add(&g).to_affine(), GENERATOR);
+        assert!(
+            ProjectivePoint::INFINITY
+                .add(&ProjectivePoint::INFINITY)
+                .to_affine()
+                .infinity
+        );
+    }
+}
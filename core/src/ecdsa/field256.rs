@@ -0,0 +1,396 @@
+//! Fixed width (256 bits) arithmetic in the secp256k1 prime field.
+//!
+//! Values are stored as 4 little endian `u64` limbs and are always fully reduced (`< P`).
+//! Reduction uses `2^256 = C (mod P)` where `C = 2^32 + 977`, so no generic division is needed.
+//!
+//! Arithmetic (add, sub, mul, invert) doesn't branch on the values, so it can be used with secret data.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// P = 2^256 - 2^32 - 977
+pub const P_LIMBS: [u64; 4] = [
+    0xFFFFFFFEFFFFFC2F,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+/// 2^256 - P
+const C: u64 = 0x1000003D1;
+
+/// P - 2 (exponent for the inversion by Fermat's little theorem)
+const P_MINUS_2: [u64; 4] = [
+    0xFFFFFFFEFFFFFC2D,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+/// (P + 1) / 4 (exponent for the square root, P = 3 mod 4)
+const P_PLUS_1_DIV_4: [u64; 4] = [
+    0xFFFFFFFFBFFFFF0C,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0x3FFFFFFFFFFFFFFF,
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Field256([u64; 4]);
+
+impl Field256 {
+    pub const ZERO: Field256 = Field256([0, 0, 0, 0]);
+    pub const ONE: Field256 = Field256([1, 0, 0, 0]);
+
+    /// New `Field256` from limbs (little endian), `None` if not less than P.
+    pub fn from_limbs(limbs: [u64; 4]) -> Option<Field256> {
+        if greater_or_equal(&limbs, &P_LIMBS) {
+            return None;
+        }
+
+        Some(Field256(limbs))
+    }
+
+    /// New `Field256` from limbs (little endian) known to be less than P (constants).
+    pub(super) const fn from_limbs_unchecked(limbs: [u64; 4]) -> Field256 {
+        Field256(limbs)
+    }
+
+    pub const fn from_u64(value: u64) -> Field256 {
+        Field256([value, 0, 0, 0])
+    }
+
+    /// New `Field256` from 32 bytes (big endian), `None` if not less than P.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Field256> {
+        Self::from_limbs(bytes_to_limbs(bytes))
+    }
+
+    /// 32 bytes big endian.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        limbs_to_bytes(&self.0)
+    }
+
+    pub fn limbs(&self) -> [u64; 4] {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0, 0, 0, 0]
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+
+    pub fn square(&self) -> Field256 {
+        *self * *self
+    }
+
+    pub fn double(&self) -> Field256 {
+        *self + *self
+    }
+
+    pub fn pow(&self, exponent: &[u64; 4]) -> Field256 {
+        let mut res = Field256::ONE;
+
+        for i in (0..256).rev() {
+            res = res.square();
+            if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+                res = res * *self;
+            }
+        }
+
+        res
+    }
+
+    /// Multiplicative inverse (a^(P-2)). Inverse of zero is zero.
+    pub fn invert(&self) -> Field256 {
+        self.pow(&P_MINUS_2)
+    }
+
+    /// Square root (a^((P+1)/4)), `None` if `self` is not a quadratic residue.
+    pub fn sqrt(&self) -> Option<Field256> {
+        let root = self.pow(&P_PLUS_1_DIV_4);
+
+        if root.square() == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    /// 512 bits product reduction: hi * 2^256 + lo = hi * C + lo (mod P)
+    fn reduce_wide(r: &[u64; 8]) -> Field256 {
+        let mut t = [0u64; 5];
+        let mut carry: u128 = 0;
+        for i in 0..4 {
+            let v = r[i] as u128 + (r[i + 4] as u128) * (C as u128) + carry;
+            t[i] = v as u64;
+            carry = v >> 64;
+        }
+        t[4] = carry as u64;
+
+        // t[4] < 2^34: fold it once again
+        let mut res = [0u64; 4];
+        let v = t[0] as u128 + (t[4] as u128) * (C as u128);
+        res[0] = v as u64;
+        let mut carry = v >> 64;
+        for i in 1..4 {
+            let v = t[i] as u128 + carry;
+            res[i] = v as u64;
+            carry = v >> 64;
+        }
+
+        Self::normalize(res, carry != 0)
+    }
+
+    /// Reduce `value + overflow * 2^256` (less than 2P) to a value less than P.
+    fn normalize(value: [u64; 4], overflow: bool) -> Field256 {
+        let (plus_c, carry) = add_small(&value, C);
+
+        // value >= P <=> value + C >= 2^256
+        let choice = Choice::from((overflow | carry) as u8);
+        Field256::conditional_select(&Field256(value), &Field256(plus_c), choice)
+    }
+}
+
+impl ConditionallySelectable for Field256 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Field256([
+            u64::conditional_select(&a.0[0], &b.0[0], choice),
+            u64::conditional_select(&a.0[1], &b.0[1], choice),
+            u64::conditional_select(&a.0[2], &b.0[2], choice),
+            u64::conditional_select(&a.0[3], &b.0[3], choice),
+        ])
+    }
+}
+
+impl ConstantTimeEq for Field256 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0[0].ct_eq(&other.0[0])
+            & self.0[1].ct_eq(&other.0[1])
+            & self.0[2].ct_eq(&other.0[2])
+            & self.0[3].ct_eq(&other.0[3])
+    }
+}
+
+impl Add for Field256 {
+    type Output = Field256;
+
+    fn add(self, other: Field256) -> Field256 {
+        let mut res = [0u64; 4];
+        let mut carry = false;
+
+        for (i, r) in res.iter_mut().enumerate() {
+            let (s1, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (s2, c2) = s1.overflowing_add(carry as u64);
+            *r = s2;
+            carry = c1 || c2;
+        }
+
+        Field256::normalize(res, carry)
+    }
+}
+
+impl Sub for Field256 {
+    type Output = Field256;
+
+    fn sub(self, other: Field256) -> Field256 {
+        let mut res = [0u64; 4];
+        let mut borrow = false;
+
+        for (i, r) in res.iter_mut().enumerate() {
+            let (d1, b1) = self.0[i].overflowing_sub(other.0[i]);
+            let (d2, b2) = d1.overflowing_sub(borrow as u64);
+            *r = d2;
+            borrow = b1 || b2;
+        }
+
+        // res + P (mod 2^256) = res - C (mod 2^256)
+        let plus_p = sub_small(&res, C);
+
+        Field256::conditional_select(&Field256(res), &Field256(plus_p), Choice::from(borrow as u8))
+    }
+}
+
+impl Neg for Field256 {
+    type Output = Field256;
+
+    fn neg(self) -> Field256 {
+        Field256::ZERO - self
+    }
+}
+
+impl Mul for Field256 {
+    type Output = Field256;
+
+    fn mul(self, other: Field256) -> Field256 {
+        let mut r = [0u64; 8];
+
+        for i in 0..4 {
+            let mut carry: u128 = 0;
+            for j in 0..4 {
+                let v = (self.0[i] as u128) * (other.0[j] as u128) + r[i + j] as u128 + carry;
+                r[i + j] = v as u64;
+                carry = v >> 64;
+            }
+            r[i + 4] = carry as u64;
+        }
+
+        Field256::reduce_wide(&r)
+    }
+}
+
+/// `a >= b` for 256 bits little endian limbs.
+pub fn greater_or_equal(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    for i in (0..4).rev() {
+        if a[i] != b[i] {
+            return a[i] > b[i];
+        }
+    }
+
+    true
+}
+
+/// 32 bytes big endian to 4 limbs little endian.
+pub fn bytes_to_limbs(bytes: &[u8; 32]) -> [u64; 4] {
+    let mut limbs = [0u64; 4];
+
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let start = 32 - (i + 1) * 8;
+        let mut chunk = [0u8; 8];
+        chunk.copy_from_slice(&bytes[start..start + 8]);
+        *limb = u64::from_be_bytes(chunk);
+    }
+
+    limbs
+}
+
+/// 4 limbs little endian to 32 bytes big endian.
+pub fn limbs_to_bytes(limbs: &[u64; 4]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+
+    for (i, limb) in limbs.iter().enumerate() {
+        let start = 32 - (i + 1) * 8;
+        bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+    }
+
+    bytes
+}
+
+fn add_small(a: &[u64; 4], value: u64) -> ([u64; 4], bool) {
+    let mut res = *a;
+    let mut carry = value;
+
+    for r in res.iter_mut() {
+        let (s, c) = r.overflowing_add(carry);
+        *r = s;
+        carry = c as u64;
+    }
+
+    (res, carry != 0)
+}
+
+fn sub_small(a: &[u64; 4], value: u64) -> [u64; 4] {
+    let mut res = *a;
+    let mut borrow = value;
+
+    for r in res.iter_mut() {
+        let (d, b) = r.overflowing_sub(borrow);
+        *r = d;
+        borrow = b as u64;
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod field256_test {
+    use super::*;
+
+    fn fe(hex: &str) -> Field256 {
+        let mut bytes = [0u8; 32];
+        let padded = format!("{:0>64}", hex);
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&padded[i * 2..i * 2 + 2], 16).unwrap();
+        }
+
+        Field256::from_bytes(&bytes).unwrap()
+    }
+
+    const P_MINUS_1: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2E";
+    const GX: &str = "79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+    const GY: &str = "483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8";
+
+    #[test]
+    fn reject_values_not_less_than_p() {
+        assert!(Field256::from_limbs(P_LIMBS).is_none());
+        assert!(Field256::from_limbs([u64::MAX; 4]).is_none());
+        assert!(Field256::from_bytes(&limbs_to_bytes(&P_LIMBS)).is_none());
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let a = fe(GX);
+        assert_eq!(fe(GX), Field256::from_bytes(&a.to_bytes()).unwrap());
+    }
+
+    #[test]
+    fn add_with_reduction() {
+        assert_eq!(fe(P_MINUS_1) + Field256::ONE, Field256::ZERO);
+        assert_eq!(fe(P_MINUS_1) + fe(P_MINUS_1), fe(P_MINUS_1) - Field256::ONE);
+    }
+
+    #[test]
+    fn sub_with_borrow() {
+        assert_eq!(Field256::ZERO - Field256::ONE, fe(P_MINUS_1));
+        assert_eq!(-Field256::ONE, fe(P_MINUS_1));
+        assert_eq!(-Field256::ZERO, Field256::ZERO);
+    }
+
+    #[test]
+    fn mul() {
+        // (P - 1)^2 = 1
+        assert_eq!(fe(P_MINUS_1) * fe(P_MINUS_1), Field256::ONE);
+
+        assert_eq!(
+            fe(GX) * fe(GY),
+            fe("FD3DC529C6EB60FB9D166034CF3C1A5A72324AA9DFD3428A56D7E1CE0179FD9B")
+        );
+    }
+
+    #[test]
+    fn generator_is_in_curve() {
+        let x = fe(GX);
+        let y = fe(GY);
+
+        assert_eq!(y.square(), x.square() * x + Field256::from_u64(7));
+    }
+
+    #[test]
+    fn invert() {
+        let x = fe(GX);
+
+        assert_eq!(x * x.invert(), Field256::ONE);
+        assert_eq!(
+            Field256::from_u64(2).invert(),
+            fe("7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF7FFFFE18")
+        );
+        assert_eq!(Field256::ZERO.invert(), Field256::ZERO);
+    }
+
+    #[test]
+    fn sqrt() {
+        let y = fe(GY);
+        let root = y.square().sqrt().unwrap();
+
+        assert!(root == y || root == -y);
+    }
+
+    #[test]
+    fn sqrt_of_non_residue() {
+        // 5^3 + 7 is not a square
+        assert!(Field256::from_u64(132).sqrt().is_none());
+    }
+}
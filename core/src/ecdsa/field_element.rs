@@ -88,6 +88,11 @@ impl FieldElement {
     pub fn num(&self) -> Integer {
         self.num.clone()
     }
+
+    /// Get the reference prime as Integer.
+    pub fn prime(&self) -> Integer {
+        self.prime.clone()
+    }
 }
 
 impl Display for FieldElement {
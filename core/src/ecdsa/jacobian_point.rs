@@ -0,0 +1,323 @@
+//! secp256k1 points over `Field256`.
+//!
+//! `JacobianPoint` (X, Y, Z) represents the affine point (X / Z^2, Y / Z^3), so additions and doublings
+//! don't need a field inversion. Only the final conversion to `AffinePoint` inverts Z.
+
+use super::field256::Field256;
+
+/// b = 7 (y^2 = x^3 + 7)
+const CURVE_B: Field256 = Field256::from_u64(7);
+
+pub const GENERATOR: AffinePoint = AffinePoint {
+    x: Field256::from_limbs_unchecked([
+        0x59F2815B16F81798,
+        0x029BFCDB2DCE28D9,
+        0x55A06295CE870B07,
+        0x79BE667EF9DCBBAC,
+    ]),
+    y: Field256::from_limbs_unchecked([
+        0x9C47D08FFB10D4B8,
+        0xFD17B448A6855419,
+        0x5DA4FBFC0E1108A8,
+        0x483ADA7726A3C465,
+    ]),
+    infinity: false,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AffinePoint {
+    pub x: Field256,
+    pub y: Field256,
+    pub infinity: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct JacobianPoint {
+    x: Field256,
+    y: Field256,
+    z: Field256,
+}
+
+impl AffinePoint {
+    pub const INFINITY: AffinePoint = AffinePoint {
+        x: Field256::ZERO,
+        y: Field256::ZERO,
+        infinity: true,
+    };
+
+    /// New `AffinePoint`, `None` if (x, y) doesn't satisfy the curve equation.
+    pub fn new(x: Field256, y: Field256) -> Option<AffinePoint> {
+        let point = AffinePoint { x, y, infinity: false };
+
+        if point.is_on_curve() {
+            Some(point)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        self.infinity || self.y.square() == self.x.square() * self.x + CURVE_B
+    }
+
+    pub fn to_jacobian(&self) -> JacobianPoint {
+        if self.infinity {
+            return JacobianPoint::INFINITY;
+        }
+
+        JacobianPoint {
+            x: self.x,
+            y: self.y,
+            z: Field256::ONE,
+        }
+    }
+}
+
+impl std::ops::Neg for AffinePoint {
+    type Output = AffinePoint;
+
+    fn neg(self) -> AffinePoint {
+        AffinePoint {
+            x: self.x,
+            y: -self.y,
+            infinity: self.infinity,
+        }
+    }
+}
+
+impl JacobianPoint {
+    pub const INFINITY: JacobianPoint = JacobianPoint {
+        x: Field256::ONE,
+        y: Field256::ONE,
+        z: Field256::ZERO,
+    };
+
+    pub fn is_infinite(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    /// 2 * self (a = 0 formulas).
+    pub fn double(&self) -> JacobianPoint {
+        if self.is_infinite() || self.y.is_zero() {
+            return JacobianPoint::INFINITY;
+        }
+
+        let a = self.x.square();
+        let b = self.y.square();
+        let c = b.square();
+        let d = ((self.x + b).square() - a - c).double();
+        let e = a.double() + a;
+        let f = e.square();
+
+        let x = f - d.double();
+        let y = e * (d - x) - c.double().double().double();
+        let z = (self.y * self.z).double();
+
+        JacobianPoint { x, y, z }
+    }
+
+    /// self + other (both in Jacobian coordinates).
+    pub fn add(&self, other: &JacobianPoint) -> JacobianPoint {
+        if self.is_infinite() {
+            return *other;
+        }
+        if other.is_infinite() {
+            return *self;
+        }
+
+        let z1z1 = self.z.square();
+        let z2z2 = other.z.square();
+        let u1 = self.x * z2z2;
+        let u2 = other.x * z1z1;
+        let s1 = self.y * other.z * z2z2;
+        let s2 = other.y * self.z * z1z1;
+
+        self.add_with(u1, u2, s1, s2, self.z * other.z)
+    }
+
+    /// self + other with `other` in affine coordinates (Z = 1), cheaper than `add`.
+    pub fn add_affine(&self, other: &AffinePoint) -> JacobianPoint {
+        if other.infinity {
+            return *self;
+        }
+        if self.is_infinite() {
+            return other.to_jacobian();
+        }
+
+        let z1z1 = self.z.square();
+        let u2 = other.x * z1z1;
+        let s2 = other.y * self.z * z1z1;
+
+        self.add_with(self.x, u2, self.y, s2, self.z)
+    }
+
+    fn add_with(&self, u1: Field256, u2: Field256, s1: Field256, s2: Field256, z1z2: Field256) -> JacobianPoint {
+        let h = u2 - u1;
+        let r = s2 - s1;
+
+        if h.is_zero() {
+            // same x: either the same point or its opposite
+            if r.is_zero() {
+                return self.double();
+            }
+            return JacobianPoint::INFINITY;
+        }
+
+        let h2 = h.square();
+        let h3 = h * h2;
+        let u1h2 = u1 * h2;
+
+        let x = r.square() - h3 - u1h2.double();
+        let y = r * (u1h2 - x) - s1 * h3;
+        let z = z1z2 * h;
+
+        JacobianPoint { x, y, z }
+    }
+
+    pub fn neg(&self) -> JacobianPoint {
+        JacobianPoint {
+            x: self.x,
+            y: -self.y,
+            z: self.z,
+        }
+    }
+
+    pub fn to_affine(&self) -> AffinePoint {
+        if self.is_infinite() {
+            return AffinePoint::INFINITY;
+        }
+
+        let z_inv = self.z.invert();
+        let z_inv2 = z_inv.square();
+
+        AffinePoint {
+            x: self.x * z_inv2,
+            y: self.y * z_inv2 * z_inv,
+            infinity: false,
+        }
+    }
+}
+
+impl From<AffinePoint> for JacobianPoint {
+    fn from(point: AffinePoint) -> JacobianPoint {
+        point.to_jacobian()
+    }
+}
+
+/// Converts many points to affine with a single field inversion (Montgomery's trick).
+pub fn batch_to_affine(points: &[JacobianPoint]) -> Vec<AffinePoint> {
+    let mut prefix = Vec::with_capacity(points.len());
+    let mut acc = Field256::ONE;
+
+    for point in points {
+        prefix.push(acc);
+        if !point.is_infinite() {
+            acc = acc * point.z;
+        }
+    }
+
+    let mut inv = acc.invert();
+    let mut res = vec![AffinePoint::INFINITY; points.len()];
+
+    for i in (0..points.len()).rev() {
+        let point = &points[i];
+        if point.is_infinite() {
+            continue;
+        }
+
+        // inv = 1 / (z_0 * ... * z_i), prefix[i] = z_0 * ... * z_(i-1)
+        let z_inv = inv * prefix[i];
+        inv = inv * point.z;
+
+        let z_inv2 = z_inv.square();
+        res[i] = AffinePoint {
+            x: point.x * z_inv2,
+            y: point.y * z_inv2 * z_inv,
+            infinity: false,
+        };
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod jacobian_point_test {
+    use super::*;
+
+    fn fe(hex: &str) -> Field256 {
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+        }
+
+        Field256::from_bytes(&bytes).unwrap()
+    }
+
+    fn g2() -> AffinePoint {
+        AffinePoint::new(
+            fe("C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5"),
+            fe("1AE168FEA63DC339A3C58419466CEAEEF7F632653266D0E1236431A950CFE52A"),
+        )
+        .unwrap()
+    }
+
+    fn g3() -> AffinePoint {
+        AffinePoint::new(
+            fe("F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            fe("388F7B0F632DE8140FE337E62A37F3566500A99934C2231B6CB9FD7584B8E672"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn generator_is_on_curve() {
+        assert!(GENERATOR.is_on_curve());
+        assert!(AffinePoint::new(GENERATOR.x, GENERATOR.x).is_none());
+    }
+
+    #[test]
+    fn double_generator() {
+        assert_eq!(GENERATOR.to_jacobian().double().to_affine(), g2());
+    }
+
+    #[test]
+    fn add_generator() {
+        let g = GENERATOR.to_jacobian();
+
+        assert_eq!(g.double().add(&g).to_affine(), g3());
+        assert_eq!(g.double().add_affine(&GENERATOR).to_affine(), g3());
+        assert_eq!(g.add(&g).to_affine(), g2());
+        assert_eq!(g.add_affine(&GENERATOR).to_affine(), g2());
+    }
+
+    #[test]
+    fn add_opposite_is_infinite() {
+        let g = GENERATOR.to_jacobian();
+
+        assert!(g.add(&g.neg()).is_infinite());
+        assert!(g.add_affine(&-GENERATOR).is_infinite());
+        assert!(JacobianPoint::INFINITY.double().is_infinite());
+    }
+
+    #[test]
+    fn add_infinite() {
+        let g = GENERATOR.to_jacobian();
+
+        assert_eq!(g.add(&JacobianPoint::INFINITY).to_affine(), GENERATOR);
+        assert_eq!(JacobianPoint::INFINITY.add(&g).to_affine(), GENERATOR);
+        assert_eq!(JacobianPoint::INFINITY.add_affine(&GENERATOR).to_affine(), GENERATOR);
+        assert_eq!(g.add_affine(&AffinePoint::INFINITY).to_affine(), GENERATOR);
+        assert_eq!(JacobianPoint::INFINITY.to_affine(), AffinePoint::INFINITY);
+    }
+
+    #[test]
+    fn batch_conversion() {
+        let g = GENERATOR.to_jacobian();
+        let points = [g, JacobianPoint::INFINITY, g.double(), g.double().add(&g)];
+
+        assert_eq!(
+            batch_to_affine(&points),
+            vec![GENERATOR, AffinePoint::INFINITY, g2(), g3()]
+        );
+    }
+}
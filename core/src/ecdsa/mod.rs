@@ -1,2 +1,7 @@
+pub mod constant_time;
+pub mod field256;
 pub mod field_element;
+pub mod jacobian_point;
 pub mod point;
+pub mod scalar256;
+pub mod scalar_mul;
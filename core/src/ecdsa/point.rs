@@ -6,13 +6,20 @@ use std::{
 };
 
 use crate::{
-    bitcoin::ecdsa::{N, P, SEVEN, ZERO},
+    bitcoin::ecdsa::{G, GX, GY, N, P, SEVEN, ZERO},
     flags::compression::Compression,
     hashing::hash160::hash160,
-    std_lib::vector::vect_to_array_32,
+    std_lib::{std_result::StdResult, vector::vect_to_array_32},
 };
 
-use super::field_element::FieldElement;
+use super::{
+    constant_time,
+    field256::Field256,
+    field_element::FieldElement,
+    jacobian_point::{AffinePoint, JacobianPoint},
+    scalar256::Scalar256,
+    scalar_mul::{self, Scalar},
+};
 
 /// `Point` is a point on an elliptic curve.
 /// Both `x` and `y` as `None` indicates point at infinite.
@@ -31,6 +38,10 @@ enum PointCoordinateParity {
     Even = 0x02,
 }
 
+// Hybrid SEC format (uncompressed, with the parity of `y` in the prefix), still accepted by consensus
+const HYBRID_EVEN: u8 = 0x06;
+const HYBRID_ODD: u8 = 0x07;
+
 impl Point {
     /// New `Point` from all elements.
     pub fn new(x: Option<FieldElement>, y: Option<FieldElement>, a: FieldElement, b: FieldElement) -> Point {
@@ -71,6 +82,17 @@ impl Point {
         self.x.is_none() && self.y.is_none()
     }
 
+    /// `true` if `Point` satisfies the curve equation (infinite is always in the curve).
+    pub fn is_on_curve(&self) -> bool {
+        match (&self.x, &self.y) {
+            (Some(x), Some(y)) => {
+                let right_side = x.pow_by_i32(3) + self.a.clone() * x + self.b.clone();
+                y.pow_by_i32(2).num() == right_side.num()
+            }
+            _ => true,
+        }
+    }
+
     /// Get `x` value.
     pub fn x_as_num(&self) -> Integer {
         self.x.clone().unwrap().num()
@@ -177,10 +199,145 @@ impl Point {
         panic!("unknown binary type in deserialization");
     }
 
+    /// As `deserialize`, for untrusted data (public keys in scripts): an invalid encoding, a coordinate out of
+    /// the field or a point not in the curve is an error instead of a panic.
+    pub fn try_deserialize(serialized: &[u8]) -> StdResult<Point> {
+        let prefix = *serialized.first().ok_or("invalid_public_key_length")?;
+
+        let expected_length = match prefix {
+            p if p == PointCoordinateParity::Even as u8 || p == PointCoordinateParity::Odd as u8 => 33,
+            p if p == PointCoordinateParity::Uncompressed as u8 || p == HYBRID_EVEN || p == HYBRID_ODD => 65,
+            _ => Err("invalid_public_key_prefix")?,
+        };
+
+        if serialized.len() != expected_length {
+            Err("invalid_public_key_length")?;
+        }
+
+        let coordinates = &serialized[1..];
+        if coordinates
+            .chunks(32)
+            .any(|c| Integer::from_digits(c, Order::Msf) >= *P)
+        {
+            Err("invalid_public_key")?;
+        }
+
+        let point = if expected_length == 33 {
+            Self::deserialize_compressed(serialized)
+        } else {
+            Self::deserialize_uncompressed(serialized)
+        };
+
+        let hybrid = prefix == HYBRID_EVEN || prefix == HYBRID_ODD;
+        if !point.is_on_curve() || (hybrid && point.y_as_num().is_odd() != (prefix == HYBRID_ODD)) {
+            Err("invalid_public_key")?;
+        }
+
+        Ok(point)
+    }
+
     pub fn hash160(&self, compression: Compression) -> Vec<u8> {
         let serialized = self.serialize(compression);
         hash160(&serialized)
     }
+
+    /// `k * G` in secp256k1 (precomputed generator table).
+    pub fn mul_generator(k: &Integer) -> Point {
+        let res = scalar_mul::mul_generator(&Self::to_scalar(k));
+
+        Self::from_jacobian(&res, &G.a, &G.b)
+    }
+
+    /// `k * G` in secp256k1 in constant time, for secret scalars (private keys).
+    pub fn mul_generator_secret(k: &Scalar256) -> Point {
+        Self::from_affine(&constant_time::mul_generator(k), &G.a, &G.b)
+    }
+
+    /// `u * G + v * point` in secp256k1 (Strauss-Shamir), as needed by signature verification.
+    pub fn mul_generator_add(u: &Integer, point: &Point, v: &Integer) -> Point {
+        let res = scalar_mul::mul_add(&Self::to_scalar(u), &point.to_jacobian(), &Self::to_scalar(v));
+
+        Self::from_jacobian(&res, &G.a, &G.b)
+    }
+
+    /// Scalar multiplication by "double and add" with generic field arithmetic.
+    /// It works in any curve; in secp256k1 `Mul` uses the fixed width backend instead.
+    pub fn mul_double_and_add(&self, coefficient: Integer) -> Point {
+        let mut sel: Point = self.clone();
+        let (_q, mut coef) = coefficient.div_rem_euc((*N).clone());
+        let mut result = Point::new_infinite(&self.a, &self.b);
+
+        while coef > 0 {
+            if coef.get_bit(0) {
+                result = result + &sel;
+            }
+
+            sel = sel.clone() + &sel;
+
+            coef >>= 1;
+        }
+
+        result
+    }
+
+    /// `true` if `Point` belongs to secp256k1 (`a = 0`, `b = 7` in Fp).
+    fn is_in_secp256k1(&self) -> bool {
+        self.a.num() == 0 && self.b.num() == 7 && self.a.prime() == *P && self.b.prime() == *P
+    }
+
+    fn is_generator(&self) -> bool {
+        match (&self.x, &self.y) {
+            (Some(x), Some(y)) => x.num() == *GX && y.num() == *GY,
+            _ => false,
+        }
+    }
+
+    fn to_jacobian(&self) -> JacobianPoint {
+        match (&self.x, &self.y) {
+            (Some(x), Some(y)) => {
+                let x = Field256::from_limbs(Self::to_limbs(&x.num(), &P)).unwrap();
+                let y = Field256::from_limbs(Self::to_limbs(&y.num(), &P)).unwrap();
+
+                AffinePoint { x, y, infinity: false }.to_jacobian()
+            }
+            _ => JacobianPoint::INFINITY,
+        }
+    }
+
+    fn from_jacobian(point: &JacobianPoint, a: &FieldElement, b: &FieldElement) -> Point {
+        Self::from_affine(&point.to_affine(), a, b)
+    }
+
+    fn from_affine(affine: &AffinePoint, a: &FieldElement, b: &FieldElement) -> Point {
+        if affine.infinity {
+            return Point::new_infinite(a, b);
+        }
+
+        let x = Integer::from_digits(&affine.x.limbs(), Order::Lsf);
+        let y = Integer::from_digits(&affine.y.limbs(), Order::Lsf);
+
+        Point {
+            x: Some(FieldElement::new(x, (*P).clone())),
+            y: Some(FieldElement::new(y, (*P).clone())),
+            a: a.clone(),
+            b: b.clone(),
+        }
+    }
+
+    fn to_scalar(k: &Integer) -> Scalar {
+        Self::to_limbs(k, &N)
+    }
+
+    /// `n mod modulus` as 4 limbs (little endian), `modulus` must fit in 256 bits.
+    fn to_limbs(n: &Integer, modulus: &Integer) -> [u64; 4] {
+        let (_q, reduced) = n.clone().div_rem_euc(modulus.clone());
+        let digits = reduced.to_digits::<u64>(Order::Lsf);
+
+        let mut limbs = [0u64; 4];
+        limbs[..digits.len()].copy_from_slice(&digits);
+
+        limbs
+    }
 }
 
 impl Display for Point {
@@ -298,21 +455,18 @@ impl Mul<Integer> for &Point {
             panic!("TODO: multiplication by zero not implemented");
         }
 
-        let mut sel: Point = self.clone();
-        let (_q, mut coef) = coefficient.div_rem_euc((*N).clone());
-        let mut result = Point::new_infinite(&self.a, &self.b);
-
-        while coef > 0 {
-            if coef.get_bit(0) {
-                result = result + &sel;
-            }
-
-            sel = sel.clone() + &sel;
-
-            coef >>= 1;
+        if !self.is_in_secp256k1() {
+            return self.mul_double_and_add(coefficient);
         }
 
-        result
+        let k = Point::to_scalar(&coefficient);
+        let res = if self.is_generator() {
+            scalar_mul::mul_generator(&k)
+        } else {
+            scalar_mul::mul(&self.to_jacobian(), &k)
+        };
+
+        Point::from_jacobian(&res, &self.a, &self.b)
     }
 }
 
@@ -322,7 +476,7 @@ mod point_test {
     use crate::{
         bitcoin::ecdsa::G,
         keys::{key::Key, signature::Signature},
-        std_lib::integer_extended::IntegerExtended,
+        std_lib::{integer_extended::IntegerExtended, vector::hex_string_to_bytes},
     };
 
     #[test]
@@ -588,6 +742,80 @@ mod point_test {
         assert!(p.is_infinite());
     }
 
+    #[test]
+    fn generator_multiplication_matches_double_and_add() {
+        let k = Integer::from_hex_str("A23B7C91D3E5F10243B5C3A3E8D0F6A9987654321FEDCBA0123456789ABCDEF0");
+
+        let expected = (*G).mul_double_and_add(k.clone());
+
+        assert_eq!(Point::mul_generator(&k), expected);
+        assert_eq!(&(*G).clone() * k, expected);
+    }
+
+    #[test]
+    fn point_multiplication_matches_double_and_add() {
+        let point = Point::mul_generator(&Integer::from(5000));
+        let k = (*N).clone() - 1;
+
+        assert_eq!(&point * k.clone(), point.mul_double_and_add(k));
+    }
+
+    #[test]
+    fn generator_multiplication_and_addition() {
+        // 5000 * G
+        let point = Point::deserialize(
+            hex_string_to_bytes("03FFE558E388852F0120E46AF2D1B370F85854A8EB0841811ECE0E3E03D282D57C").unwrap(),
+        );
+        let u = Integer::from_hex_str("C0FFEE");
+        let v = Integer::from_hex_str("DEADBEEF12345");
+
+        let expected = (*G).mul_double_and_add(u.clone()) + &point.mul_double_and_add(v.clone());
+
+        assert_eq!(Point::mul_generator_add(&u, &point, &v), expected);
+    }
+
+    #[test]
+    fn try_deserialize() {
+        let point = Point::mul_generator(&Integer::from(5000));
+        let compressed = point.serialize(Compression::Compressed);
+        let uncompressed = point.serialize(Compression::Uncompressed);
+
+        assert_eq!(Point::try_deserialize(&compressed).unwrap(), point);
+        assert_eq!(Point::try_deserialize(&uncompressed).unwrap(), point);
+
+        // 5000 * G has odd y
+        let hybrid = [[HYBRID_ODD].as_slice(), &uncompressed[1..]].concat();
+        assert_eq!(Point::try_deserialize(&hybrid).unwrap(), point);
+
+        let errors = [
+            (vec![], "invalid_public_key_length"),
+            (compressed[..32].to_vec(), "invalid_public_key_length"),
+            (
+                [[0x05].as_slice(), &compressed[1..]].concat(),
+                "invalid_public_key_prefix",
+            ),
+            (
+                [[HYBRID_EVEN].as_slice(), &uncompressed[1..]].concat(),
+                "invalid_public_key",
+            ),
+            ([[0x02].as_slice(), &[0xFF; 32]].concat(), "invalid_public_key"),
+            (
+                [&uncompressed[..64], &[uncompressed[64] ^ 1]].concat(),
+                "invalid_public_key",
+            ),
+        ];
+
+        for (serialized, error) in errors {
+            assert_eq!(Point::try_deserialize(&serialized).unwrap_err().to_string(), error);
+        }
+    }
+
+    #[test]
+    fn generator_multiplication_to_infinite() {
+        assert!(Point::mul_generator(&(*N).clone()).is_infinite());
+        assert!(Point::mul_generator_add(&Integer::from(1), &G, &((*N).clone() - 1)).is_infinite());
+    }
+
     #[test]
     fn a_signature_1_verification() {
         let z = Integer::from_256_digits(
@@ -0,0 +1,329 @@
+//! Fixed width (256 bits) arithmetic modulo N, the order of the secp256k1 generator.
+//!
+//! Same layout as `Field256` (4 little endian `u64` limbs, always `< N`) and no branches on the values:
+//! it is meant for secrets (private keys and nonces) in signing.
+//! Reduction uses `2^256 = NC (mod N)` where `NC = 2^256 - N` (129 bits).
+
+use std::ops::{Add, Mul, Neg};
+
+use subtle::{Choice, ConditionallySelectable};
+use zeroize::Zeroize;
+
+use super::field256::{bytes_to_limbs, limbs_to_bytes};
+
+/// N = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+pub const N_LIMBS: [u64; 4] = [
+    0xBFD25E8CD0364141,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+/// 2^256 - N
+const NC: [u64; 3] = [0x402DA1732FC9BEBF, 0x4551231950B75FC4, 0x1];
+
+/// N - 2 (exponent for the inversion by Fermat's little theorem)
+const N_MINUS_2: [u64; 4] = [
+    0xBFD25E8CD036413F,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+/// N / 2
+const N_HALF: [u64; 4] = [
+    0xDFE92F46681B20A0,
+    0x5D576E7357A4501D,
+    0xFFFFFFFFFFFFFFFF,
+    0x7FFFFFFFFFFFFFFF,
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Scalar256([u64; 4]);
+
+impl Scalar256 {
+    pub const ZERO: Scalar256 = Scalar256([0, 0, 0, 0]);
+    pub const ONE: Scalar256 = Scalar256([1, 0, 0, 0]);
+
+    /// New `Scalar256` from limbs (little endian) reduced modulo N.
+    pub fn from_limbs_reduced(limbs: [u64; 4]) -> Scalar256 {
+        // any 256 bits value is less than 2N: at most one subtraction
+        let (minus_n, borrow) = sub_limbs(&limbs, &N_LIMBS);
+
+        Scalar256::conditional_select(&Scalar256(minus_n), &Scalar256(limbs), Choice::from(borrow as u8))
+    }
+
+    /// New `Scalar256` from 32 bytes (big endian) reduced modulo N.
+    pub fn from_bytes_reduced(bytes: &[u8; 32]) -> Scalar256 {
+        Self::from_limbs_reduced(bytes_to_limbs(bytes))
+    }
+
+    /// New `Scalar256` from 32 bytes (big endian), `None` if not in [1, N - 1].
+    pub fn from_bytes_nonzero(bytes: &[u8; 32]) -> Option<Scalar256> {
+        let limbs = bytes_to_limbs(bytes);
+        let (_, borrow) = sub_limbs(&limbs, &N_LIMBS);
+
+        if !borrow || limbs == [0; 4] {
+            return None;
+        }
+
+        Some(Scalar256(limbs))
+    }
+
+    /// 32 bytes big endian.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        limbs_to_bytes(&self.0)
+    }
+
+    pub fn limbs(&self) -> [u64; 4] {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0, 0, 0, 0]
+    }
+
+    /// `true` if `self > N / 2` ("high S" in signatures).
+    pub fn is_high(&self) -> bool {
+        let (_, borrow) = sub_limbs(&N_HALF, &self.0);
+        borrow
+    }
+
+    pub fn square(&self) -> Scalar256 {
+        *self * *self
+    }
+
+    /// Multiplicative inverse (a^(N-2)), the exponent is public so the sequence of operations is fixed.
+    /// Inverse of zero is zero.
+    pub fn invert(&self) -> Scalar256 {
+        let mut res = Scalar256::ONE;
+
+        for i in (0..256).rev() {
+            res = res.square();
+            if (N_MINUS_2[i / 64] >> (i % 64)) & 1 == 1 {
+                res = res * *self;
+            }
+        }
+
+        res
+    }
+
+    /// `lo + hi * NC` (`hi` of any length up to 5 limbs), 8 limbs result.
+    fn fold(lo: &[u64; 4], hi: &[u64]) -> [u64; 8] {
+        let mut res = [0u64; 8];
+        res[..4].copy_from_slice(lo);
+
+        for (i, h) in hi.iter().enumerate() {
+            let mut carry: u128 = 0;
+            for (j, c) in NC.iter().enumerate() {
+                let v = (*h as u128) * (*c as u128) + res[i + j] as u128 + carry;
+                res[i + j] = v as u64;
+                carry = v >> 64;
+            }
+            for r in res.iter_mut().skip(i + NC.len()) {
+                let v = *r as u128 + carry;
+                *r = v as u64;
+                carry = v >> 64;
+            }
+        }
+
+        res
+    }
+
+    /// 512 bits product reduction.
+    fn reduce_wide(r: &[u64; 8]) -> Scalar256 {
+        // 512 bits -> 386 bits
+        let t = Self::fold(&[r[0], r[1], r[2], r[3]], &r[4..8]);
+        // 386 bits -> 260 bits
+        let u = Self::fold(&[t[0], t[1], t[2], t[3]], &t[4..8]);
+        // 260 bits -> 257 bits
+        let v = Self::fold(&[u[0], u[1], u[2], u[3]], &u[4..5]);
+
+        // v = v[4] * 2^256 + low where v[4] is 0 or 1: 2^256 = NC (mod N) and low + NC doesn't overflow
+        let low = [v[0], v[1], v[2], v[3]];
+        let folded = Self::fold(&low, &[v[4]]);
+
+        Self::from_limbs_reduced([folded[0], folded[1], folded[2], folded[3]])
+    }
+}
+
+impl ConditionallySelectable for Scalar256 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Scalar256([
+            u64::conditional_select(&a.0[0], &b.0[0], choice),
+            u64::conditional_select(&a.0[1], &b.0[1], choice),
+            u64::conditional_select(&a.0[2], &b.0[2], choice),
+            u64::conditional_select(&a.0[3], &b.0[3], choice),
+        ])
+    }
+}
+
+impl Zeroize for Scalar256 {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Add for Scalar256 {
+    type Output = Scalar256;
+
+    fn add(self, other: Scalar256) -> Scalar256 {
+        let mut sum = [0u64; 4];
+        let mut carry = false;
+
+        for (i, s) in sum.iter_mut().enumerate() {
+            let (s1, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (s2, c2) = s1.overflowing_add(carry as u64);
+            *s = s2;
+            carry = c1 || c2;
+        }
+
+        // sum < 2N: subtract N if sum >= N (carry set, or no borrow)
+        let (minus_n, borrow) = sub_limbs(&sum, &N_LIMBS);
+        let choice = Choice::from((carry | !borrow) as u8);
+
+        Scalar256::conditional_select(&Scalar256(sum), &Scalar256(minus_n), choice)
+    }
+}
+
+impl Neg for Scalar256 {
+    type Output = Scalar256;
+
+    fn neg(self) -> Scalar256 {
+        let (res, _) = sub_limbs(&N_LIMBS, &self.0);
+
+        // N - 0 must be 0
+        let is_zero = Choice::from(((self.0[0] | self.0[1] | self.0[2] | self.0[3]) == 0) as u8);
+        Scalar256::conditional_select(&Scalar256(res), &Scalar256::ZERO, is_zero)
+    }
+}
+
+impl Mul for Scalar256 {
+    type Output = Scalar256;
+
+    fn mul(self, other: Scalar256) -> Scalar256 {
+        let mut r = [0u64; 8];
+
+        for i in 0..4 {
+            let mut carry: u128 = 0;
+            for j in 0..4 {
+                let v = (self.0[i] as u128) * (other.0[j] as u128) + r[i + j] as u128 + carry;
+                r[i + j] = v as u64;
+                carry = v >> 64;
+            }
+            r[i + 4] = carry as u64;
+        }
+
+        let res = Scalar256::reduce_wide(&r);
+        r.zeroize();
+
+        res
+    }
+}
+
+/// `a - b` and the final borrow.
+fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut res = [0u64; 4];
+    let mut borrow = false;
+
+    for (i, r) in res.iter_mut().enumerate() {
+        let (d1, b1) = a[i].overflowing_sub(b[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow as u64);
+        *r = d2;
+        borrow = b1 || b2;
+    }
+
+    (res, borrow)
+}
+
+#[cfg(test)]
+mod scalar256_test {
+    use super::*;
+
+    fn sc(hex: &str) -> Scalar256 {
+        let padded = format!("{:0>64}", hex);
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&padded[i * 2..i * 2 + 2], 16).unwrap();
+        }
+
+        Scalar256::from_bytes_reduced(&bytes)
+    }
+
+    const N_MINUS_1: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140";
+    const A: &str = "A23B7C91D3E5F10243B5C3A3E8D0F6A9987654321FEDCBA0123456789ABCDEF0";
+    const B: &str = "EC208BAA0FC1C19F708A9CA96FDEFF3AC3F230BB4A7BA4AEDE4942AD003C0F60";
+
+    #[test]
+    fn reduce_on_creation() {
+        assert_eq!(
+            sc("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141"),
+            Scalar256::ZERO
+        );
+        assert_eq!(
+            sc("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"),
+            sc("14551231950B75FC4402DA1732FC9BEBE")
+        );
+    }
+
+    #[test]
+    fn nonzero_range() {
+        assert!(Scalar256::from_bytes_nonzero(&[0u8; 32]).is_none());
+        assert!(Scalar256::from_bytes_nonzero(&limbs_to_bytes(&N_LIMBS)).is_none());
+        assert_eq!(
+            Scalar256::from_bytes_nonzero(&sc(N_MINUS_1).to_bytes()),
+            Some(sc(N_MINUS_1))
+        );
+    }
+
+    #[test]
+    fn add() {
+        assert_eq!(sc(N_MINUS_1) + Scalar256::ONE, Scalar256::ZERO);
+        assert_eq!(
+            sc(A) + sc(B),
+            sc("8E5C083BE3A7B2A1B440604D58AFF5E5A1B9A806BB20D01330AB3A98CAC2AD0F")
+        );
+    }
+
+    #[test]
+    fn neg() {
+        assert_eq!(-Scalar256::ONE, sc(N_MINUS_1));
+        assert_eq!(-Scalar256::ZERO, Scalar256::ZERO);
+        assert_eq!(sc(A) + -sc(A), Scalar256::ZERO);
+    }
+
+    #[test]
+    fn mul() {
+        // (N - 1)^2 = 1
+        assert_eq!(sc(N_MINUS_1) * sc(N_MINUS_1), Scalar256::ONE);
+        assert_eq!(
+            sc(A) * sc(B),
+            sc("A707A8DEED6ABFBD005CAF5940B060CC0091A160C8C024FB68CE52DC7722616A")
+        );
+    }
+
+    #[test]
+    fn invert() {
+        assert_eq!(sc(A) * sc(A).invert(), Scalar256::ONE);
+        assert_eq!(sc(N_MINUS_1).invert(), sc(N_MINUS_1));
+        assert_eq!(Scalar256::ZERO.invert(), Scalar256::ZERO);
+    }
+
+    #[test]
+    fn high_half() {
+        let half = Scalar256(N_HALF);
+
+        assert!(!half.is_high());
+        assert!((half + Scalar256::ONE).is_high());
+        assert!(sc(N_MINUS_1).is_high());
+        assert!(!Scalar256::ONE.is_high());
+    }
+
+    #[test]
+    fn zeroize() {
+        let mut a = sc(A);
+        a.zeroize();
+
+        assert!(a.is_zero());
+    }
+}
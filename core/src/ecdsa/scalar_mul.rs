@@ -0,0 +1,322 @@
+//! Scalar multiplication on secp256k1.
+//!
+//! - `k * G`: fixed base, precomputed table of 4 bits windows (64 mixed additions, no doublings).
+//! - `k * P`: wNAF (window 5) with the odd multiples of P.
+//! - `u * G + v * P`: Strauss-Shamir, both wNAF expansions share the same doublings (signature verification).
+//!
+//! Scalars are 256 bits little endian limbs (`[u64; 4]`).
+
+use once_cell::sync::Lazy;
+
+use super::jacobian_point::{batch_to_affine, AffinePoint, JacobianPoint, GENERATOR};
+
+pub type Scalar = [u64; 4];
+
+/// Bits per window of the fixed base table.
+pub(super) const G_WINDOW_BITS: usize = 4;
+const G_WINDOWS: usize = 256 / G_WINDOW_BITS;
+pub(super) const G_WINDOW_SIZE: usize = 1 << G_WINDOW_BITS;
+
+/// wNAF window for `G` in `mul_add` (odd multiples precomputed once).
+const G_WNAF_WINDOW: u32 = 8;
+/// wNAF window for a variable point (odd multiples computed per call).
+const WNAF_WINDOW: u32 = 5;
+
+/// `G_TABLE[i][j] = j * 16^i * G`
+pub(super) static G_TABLE: Lazy<Vec<[AffinePoint; G_WINDOW_SIZE]>> = Lazy::new(|| {
+    let mut points = Vec::with_capacity(G_WINDOWS * G_WINDOW_SIZE);
+    let mut base = GENERATOR.to_jacobian();
+
+    for _ in 0..G_WINDOWS {
+        let mut acc = JacobianPoint::INFINITY;
+        for _ in 0..G_WINDOW_SIZE {
+            points.push(acc);
+            acc = acc.add(&base);
+        }
+        // acc = 16 * base
+        base = acc;
+    }
+
+    batch_to_affine(&points)
+        .chunks(G_WINDOW_SIZE)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect()
+});
+
+/// `G_ODD_MULTIPLES[i] = (2i + 1) * G`
+static G_ODD_MULTIPLES: Lazy<Vec<AffinePoint>> =
+    Lazy::new(|| batch_to_affine(&odd_multiples(&GENERATOR.to_jacobian(), G_WNAF_WINDOW)));
+
+/// `k * G`
+pub fn mul_generator(k: &Scalar) -> JacobianPoint {
+    let mut acc = JacobianPoint::INFINITY;
+
+    for (i, window) in G_TABLE.iter().enumerate() {
+        let bit = i * G_WINDOW_BITS;
+        let digit = (k[bit / 64] >> (bit % 64)) as usize & (G_WINDOW_SIZE - 1);
+        if digit != 0 {
+            acc = acc.add_affine(&window[digit]);
+        }
+    }
+
+    acc
+}
+
+/// `k * point`
+pub fn mul(point: &JacobianPoint, k: &Scalar) -> JacobianPoint {
+    let table = odd_multiples(point, WNAF_WINDOW);
+    let digits = wnaf(k, WNAF_WINDOW);
+
+    let mut acc = JacobianPoint::INFINITY;
+    for digit in digits.iter().rev() {
+        acc = acc.double();
+        acc = add_digit(&acc, *digit, |i| table[i]);
+    }
+
+    acc
+}
+
+/// `u * G + v * point`
+pub fn mul_add(u: &Scalar, point: &JacobianPoint, v: &Scalar) -> JacobianPoint {
+    let table = odd_multiples(point, WNAF_WINDOW);
+    let g_digits = wnaf(u, G_WNAF_WINDOW);
+    let p_digits = wnaf(v, WNAF_WINDOW);
+
+    let mut acc = JacobianPoint::INFINITY;
+    for i in (0..g_digits.len().max(p_digits.len())).rev() {
+        acc = acc.double();
+
+        if let Some(digit) = g_digits.get(i) {
+            acc = add_affine_digit(&acc, *digit, |i| G_ODD_MULTIPLES[i]);
+        }
+        if let Some(digit) = p_digits.get(i) {
+            acc = add_digit(&acc, *digit, |i| table[i]);
+        }
+    }
+
+    acc
+}
+
+/// Width-w non-adjacent form: `k = sum(digits[i] * 2^i)`, every non zero digit is odd and
+/// `|digit| < 2^(w-1)`, and any w consecutive digits contain at most one non zero.
+pub fn wnaf(k: &Scalar, window: u32) -> Vec<i32> {
+    // one extra limb: subtracting a negative digit may carry past 256 bits
+    let mut n = [k[0], k[1], k[2], k[3], 0u64];
+    let mut digits = Vec::with_capacity(257);
+    let modulus = 1i64 << window;
+
+    while n != [0; 5] {
+        let mut digit = 0i32;
+
+        if n[0] & 1 == 1 {
+            let low = (n[0] & (modulus as u64 - 1)) as i64;
+            let value = if low >= modulus / 2 { low - modulus } else { low };
+
+            if value > 0 {
+                sub_small(&mut n, value as u64);
+            } else {
+                add_small(&mut n, (-value) as u64);
+            }
+            digit = value as i32;
+        }
+
+        digits.push(digit);
+        shift_right_1(&mut n);
+    }
+
+    digits
+}
+
+/// `[P, 3P, 5P, ..., (2^(w-1) - 1)P]`
+fn odd_multiples(point: &JacobianPoint, window: u32) -> Vec<JacobianPoint> {
+    let count = 1 << (window - 2);
+    let double = point.double();
+
+    let mut res = Vec::with_capacity(count);
+    res.push(*point);
+    for i in 1..count {
+        res.push(res[i - 1].add(&double));
+    }
+
+    res
+}
+
+fn add_digit(acc: &JacobianPoint, digit: i32, table: impl Fn(usize) -> JacobianPoint) -> JacobianPoint {
+    match digit {
+        0 => *acc,
+        d if d > 0 => acc.add(&table((d as usize) / 2)),
+        d => acc.add(&table((-d as usize) / 2).neg()),
+    }
+}
+
+fn add_affine_digit(acc: &JacobianPoint, digit: i32, table: impl Fn(usize) -> AffinePoint) -> JacobianPoint {
+    match digit {
+        0 => *acc,
+        d if d > 0 => acc.add_affine(&table((d as usize) / 2)),
+        d => acc.add_affine(&-table((-d as usize) / 2)),
+    }
+}
+
+fn add_small(n: &mut [u64; 5], value: u64) {
+    let mut carry = value;
+
+    for limb in n.iter_mut() {
+        let (s, c) = limb.overflowing_add(carry);
+        *limb = s;
+        carry = c as u64;
+    }
+}
+
+fn sub_small(n: &mut [u64; 5], value: u64) {
+    let mut borrow = value;
+
+    for limb in n.iter_mut() {
+        let (d, b) = limb.overflowing_sub(borrow);
+        *limb = d;
+        borrow = b as u64;
+    }
+}
+
+fn shift_right_1(n: &mut [u64; 5]) {
+    for i in 0..5 {
+        n[i] >>= 1;
+        if i < 4 {
+            n[i] |= n[i + 1] << 63;
+        }
+    }
+}
+
+#[cfg(test)]
+mod scalar_mul_test {
+    use super::*;
+    use crate::ecdsa::field256::{bytes_to_limbs, Field256};
+
+    fn scalar(hex: &str) -> Scalar {
+        let padded = format!("{:0>64}", hex);
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&padded[i * 2..i * 2 + 2], 16).unwrap();
+        }
+
+        bytes_to_limbs(&bytes)
+    }
+
+    fn x_hex(point: &JacobianPoint) -> String {
+        Field256::to_bytes(&point.to_affine().x)
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect()
+    }
+
+    const N_MINUS_1: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140";
+
+    macro_rules! generator_multiple {
+        ($k:literal, $x:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let k = scalar($k);
+
+                assert_eq!(x_hex(&mul_generator(&k)), $x);
+                assert_eq!(x_hex(&mul(&GENERATOR.to_jacobian(), &k)), $x);
+            }
+        };
+    }
+
+    generator_multiple!(
+        "1",
+        "79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+        generator_multiple_1
+    );
+    generator_multiple!(
+        "1388",
+        "FFE558E388852F0120E46AF2D1B370F85854A8EB0841811ECE0E3E03D282D57C",
+        generator_multiple_5000
+    );
+    generator_multiple!(
+        "76E54A40EFB620",
+        "027F3DA1918455E03C46F659266A1BB5204E959DB7364D2F473BDF8F0A13CC9D",
+        generator_multiple_2018_pow_5
+    );
+    generator_multiple!(
+        "DEADBEEF12345",
+        "D90CD625EE87DD38656DD95CF79F65F60F7273B67D3096E68BD81E4F5342691F",
+        generator_multiple_0xdeadbeef12345
+    );
+    generator_multiple!(
+        "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140",
+        "79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+        generator_multiple_n_minus_1
+    );
+
+    #[test]
+    fn generator_multiple_n_is_infinite() {
+        let n = scalar("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+
+        assert!(mul_generator(&n).is_infinite());
+        assert!(mul(&GENERATOR.to_jacobian(), &n).is_infinite());
+    }
+
+    #[test]
+    fn generator_multiple_zero_is_infinite() {
+        assert!(mul_generator(&[0; 4]).is_infinite());
+        assert!(mul(&GENERATOR.to_jacobian(), &[0; 4]).is_infinite());
+    }
+
+    #[test]
+    fn mul_add_is_sum_of_products() {
+        let point = mul_generator(&scalar("1388"));
+        let u = scalar("A23B7C91D3E5F10243B5C3A3E8D0F6A9987654321FEDCBA0123456789ABCDEF0");
+        let v = scalar(N_MINUS_1);
+
+        let expected = mul_generator(&u).add(&mul(&point, &v));
+
+        assert_eq!(mul_add(&u, &point, &v).to_affine(), expected.to_affine());
+    }
+
+    #[test]
+    fn mul_add_to_infinite() {
+        // 1 * G + (N - 1) * G
+        let g = GENERATOR.to_jacobian();
+
+        assert!(mul_add(&scalar("1"), &g, &scalar(N_MINUS_1)).is_infinite());
+    }
+
+    #[test]
+    fn wnaf_digits() {
+        let k = scalar("D6A80DB4AF34BD0C6DF68A8E5C8E1A7B78E2B6B1AB24A2566E9BAA2AE5F6DB81");
+
+        for window in [2, 5, 8] {
+            let digits = wnaf(&k, window);
+
+            let mut last_non_zero: Option<usize> = None;
+            for (i, digit) in digits.iter().enumerate() {
+                if *digit != 0 {
+                    assert_eq!(digit.abs() % 2, 1);
+                    assert!(digit.abs() < 1 << (window - 1));
+                    if let Some(last) = last_non_zero {
+                        assert!(i - last >= window as usize);
+                    }
+                    last_non_zero = Some(i);
+                }
+            }
+
+            // Horner: n = 2n + digit
+            let mut n = [0u64; 5];
+            for digit in digits.iter().rev() {
+                let mut carry = 0;
+                for limb in n.iter_mut() {
+                    let next = *limb >> 63;
+                    *limb = (*limb << 1) | carry;
+                    carry = next;
+                }
+                if *digit > 0 {
+                    add_small(&mut n, *digit as u64);
+                } else {
+                    sub_small(&mut n, (-*digit) as u64);
+                }
+            }
+
+            assert_eq!(n, [k[0], k[1], k[2], k[3], 0]);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+/*
+    ChaCha20-Poly1305 (RFC 8439): authenticated encryption with associated data.
+        ciphertext  plaintext XOR the ChaCha20 key stream from block 1
+        tag         Poly1305 of `aad | pad | ciphertext | pad | len(aad) | len(ciphertext)`, keyed by ChaCha20 block 0
+    A nonce must never be used twice with the same key.
+*/
+
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::std_lib::std_result::StdResult;
+
+pub const KEY_LENGTH: usize = 32;
+pub const NONCE_LENGTH: usize = 12;
+pub const TAG_LENGTH: usize = 16;
+
+const CHACHA20_CONSTANTS: [u32; 4] = [0x61707865, 0x3320646E, 0x79622D32, 0x6B206574];
+const BLOCK_LENGTH: usize = 64;
+
+// 26 bits limbs
+const LIMB_MASK: u32 = 0x3FFFFFF;
+
+// Ciphertext followed by the tag
+pub fn encrypt(key: &[u8; KEY_LENGTH], nonce: &[u8; NONCE_LENGTH], plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+    let ciphertext = chacha20(key, nonce, 1, plaintext);
+    let tag = tag(key, nonce, &ciphertext, aad);
+
+    [ciphertext, tag.to_vec()].concat()
+}
+
+pub fn decrypt(key: &[u8; KEY_LENGTH], nonce: &[u8; NONCE_LENGTH], sealed: &[u8], aad: &[u8]) -> StdResult<Vec<u8>> {
+    if sealed.len() < TAG_LENGTH {
+        Err("invalid_ciphertext_length")?;
+    }
+
+    let (ciphertext, received) = sealed.split_at(sealed.len() - TAG_LENGTH);
+    let expected = tag(key, nonce, ciphertext, aad);
+
+    if !bool::from(expected.ct_eq(received)) {
+        Err("authentication_failed")?;
+    }
+
+    Ok(chacha20(key, nonce, 1, ciphertext))
+}
+
+fn tag(key: &[u8; KEY_LENGTH], nonce: &[u8; NONCE_LENGTH], ciphertext: &[u8], aad: &[u8]) -> [u8; TAG_LENGTH] {
+    let mut block = chacha20_block(key, nonce, 0);
+    let mut one_time_key = [0u8; 32];
+    one_time_key.copy_from_slice(&block[..32]);
+    block.zeroize();
+
+    let pad = |data: &[u8]| vec![0u8; (16 - data.len() % 16) % 16];
+    let message = [
+        aad,
+        &pad(aad),
+        ciphertext,
+        &pad(ciphertext),
+        &(aad.len() as u64).to_le_bytes(),
+        &(ciphertext.len() as u64).to_le_bytes(),
+    ]
+    .concat();
+
+    let tag = poly1305(&one_time_key, &message);
+    one_time_key.zeroize();
+
+    tag
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+fn chacha20_block(key: &[u8; KEY_LENGTH], nonce: &[u8; NONCE_LENGTH], counter: u32) -> [u8; BLOCK_LENGTH] {
+    let mut initial = [0u32; 16];
+    initial[..4].copy_from_slice(&CHACHA20_CONSTANTS);
+    for (i, word) in key.chunks(4).enumerate() {
+        initial[4 + i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    initial[12] = counter;
+    for (i, word) in nonce.chunks(4).enumerate() {
+        initial[13 + i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+
+    let mut state = initial;
+    for _ in 0..10 {
+        // columns
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        // diagonals
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    let mut block = [0u8; BLOCK_LENGTH];
+    for (i, chunk) in block.chunks_mut(4).enumerate() {
+        chunk.copy_from_slice(&state[i].wrapping_add(initial[i]).to_le_bytes());
+    }
+
+    state.zeroize();
+    initial.zeroize();
+
+    block
+}
+
+// `data` XOR the key stream from the block `counter`
+fn chacha20(key: &[u8; KEY_LENGTH], nonce: &[u8; NONCE_LENGTH], counter: u32, data: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(data.len());
+
+    for (i, chunk) in data.chunks(BLOCK_LENGTH).enumerate() {
+        let mut key_stream = chacha20_block(key, nonce, counter.wrapping_add(i as u32));
+        res.extend(
+            chunk
+                .iter()
+                .zip(key_stream.iter())
+                .map(|(byte, key_byte)| byte ^ key_byte),
+        );
+        key_stream.zeroize();
+    }
+
+    res
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+// Poly1305 with 26 bits limbs (no secret dependent branches)
+fn poly1305(key: &[u8; 32], message: &[u8]) -> [u8; TAG_LENGTH] {
+    // clamped
+    let r = [
+        le_u32(&key[0..]) & 0x3FFFFFF,
+        (le_u32(&key[3..]) >> 2) & 0x3FFFF03,
+        (le_u32(&key[6..]) >> 4) & 0x3FFC0FF,
+        (le_u32(&key[9..]) >> 6) & 0x3F03FFF,
+        (le_u32(&key[12..]) >> 8) & 0x00FFFFF,
+    ];
+    let s = [r[1] * 5, r[2] * 5, r[3] * 5, r[4] * 5];
+    let mut h = [0u32; 5];
+
+    for chunk in message.chunks(16) {
+        // a full block has the 2^128 bit, a partial one is terminated by 0x01
+        let mut block = [0u8; 17];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block[chunk.len()] = 0x01;
+        let high_bit = (block[16] as u32) << 24;
+
+        h[0] += le_u32(&block[0..]) & LIMB_MASK;
+        h[1] += (le_u32(&block[3..]) >> 2) & LIMB_MASK;
+        h[2] += (le_u32(&block[6..]) >> 4) & LIMB_MASK;
+        h[3] += (le_u32(&block[9..]) >> 6) & LIMB_MASK;
+        h[4] += (le_u32(&block[12..]) >> 8) | high_bit;
+
+        // h * r mod 2^130 - 5
+        let m = |a: u32, b: u32| a as u64 * b as u64;
+        let d = [
+            m(h[0], r[0]) + m(h[1], s[3]) + m(h[2], s[2]) + m(h[3], s[1]) + m(h[4], s[0]),
+            m(h[0], r[1]) + m(h[1], r[0]) + m(h[2], s[3]) + m(h[3], s[2]) + m(h[4], s[1]),
+            m(h[0], r[2]) + m(h[1], r[1]) + m(h[2], r[0]) + m(h[3], s[3]) + m(h[4], s[2]),
+            m(h[0], r[3]) + m(h[1], r[2]) + m(h[2], r[1]) + m(h[3], r[0]) + m(h[4], s[3]),
+            m(h[0], r[4]) + m(h[1], r[3]) + m(h[2], r[2]) + m(h[3], r[1]) + m(h[4], r[0]),
+        ];
+
+        let mut carry = 0u64;
+        for (limb, d) in h.iter_mut().zip(d.iter()) {
+            let sum = d + carry;
+            *limb = sum as u32 & LIMB_MASK;
+            carry = sum >> 26;
+        }
+        let limb = h[0] as u64 + carry * 5;
+        h[0] = limb as u32 & LIMB_MASK;
+        h[1] += (limb >> 26) as u32;
+    }
+
+    // full carry
+    let mut carry = 0u32;
+    for limb in h.iter_mut().skip(1) {
+        *limb += carry;
+        carry = *limb >> 26;
+        *limb &= LIMB_MASK;
+    }
+    h[0] += carry * 5;
+    carry = h[0] >> 26;
+    h[0] &= LIMB_MASK;
+    h[1] += carry;
+
+    // h - p = h + 5 - 2^130, selected if not negative
+    let mut g = [0u32; 5];
+    carry = 5;
+    for (g, h) in g.iter_mut().zip(h.iter()).take(4) {
+        *g = h + carry;
+        carry = *g >> 26;
+        *g &= LIMB_MASK;
+    }
+    g[4] = h[4].wrapping_add(carry).wrapping_sub(1 << 26);
+
+    let select_g = (g[4] >> 31).wrapping_sub(1);
+    for (h, g) in h.iter_mut().zip(g.iter()) {
+        *h = (*h & !select_g) | (g & select_g);
+    }
+
+    // h + s mod 2^128
+    let words = [
+        h[0] | (h[1] << 26),
+        (h[1] >> 6) | (h[2] << 20),
+        (h[2] >> 12) | (h[3] << 14),
+        (h[3] >> 18) | (h[4] << 8),
+    ];
+
+    let mut tag = [0u8; TAG_LENGTH];
+    let mut carry = 0u64;
+    for (i, word) in words.iter().enumerate() {
+        let sum = *word as u64 + le_u32(&key[16 + 4 * i..]) as u64 + carry;
+        tag[4 * i..4 * i + 4].copy_from_slice(&(sum as u32).to_le_bytes());
+        carry = sum >> 32;
+    }
+
+    tag
+}
+
+#[cfg(test)]
+mod chacha20_poly1305_test {
+    use super::*;
+    use crate::std_lib::vector::{bytes_to_hex_string, hex_string_to_bytes};
+
+    fn key() -> [u8; KEY_LENGTH] {
+        let mut key = [0u8; KEY_LENGTH];
+        key.copy_from_slice(
+            &hex_string_to_bytes("808182838485868788898A8B8C8D8E8F909192939495969798999A9B9C9D9E9F").unwrap(),
+        );
+        key
+    }
+
+    fn nonce() -> [u8; NONCE_LENGTH] {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        nonce.copy_from_slice(&hex_string_to_bytes("070000004041424344454647").unwrap());
+        nonce
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc8439 (2.5.2)
+    #[test]
+    fn verify_a_poly1305() {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(
+            &hex_string_to_bytes("85D6BE7857556D337F4452FE42D506A80103808AFB0DB2FD4ABFF6AF4149F51B").unwrap(),
+        );
+
+        assert_eq!(
+            bytes_to_hex_string(&poly1305(&key, b"Cryptographic Forum Research Group")),
+            "A8061DC1305136C6C22B8BAF0C0127A9"
+        );
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc8439 (2.8.2)
+    #[test]
+    fn verify_an_encryption() {
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let aad = hex_string_to_bytes("50515253C0C1C2C3C4C5C6C7").unwrap();
+
+        let sealed = encrypt(&key(), &nonce(), plaintext, &aad);
+
+        assert_eq!(
+            bytes_to_hex_string(&sealed[..plaintext.len()]),
+            "D31A8D34648E60DB7B86AFBC53EF7EC2A4ADED51296E08FEA9E2B5A736EE62D63DBEA45E8CA9671282FAFB69DA92728B1A71DE0A9E060B2905D6A5B67ECD3B3692DDBD7F2D778B8C9803AEE328091B58FAB324E4FAD675945585808B4831D7BC3FF4DEF08E4B7A9DE576D26586CEC64B6116"
+        );
+        assert_eq!(
+            bytes_to_hex_string(&sealed[plaintext.len()..]),
+            "1AE10B594F09E26A7E902ECBD0600691"
+        );
+
+        assert_eq!(decrypt(&key(), &nonce(), &sealed, &aad).unwrap(), plaintext.to_vec());
+    }
+
+    #[test]
+    fn empty_plaintext() {
+        let sealed = encrypt(&[0u8; KEY_LENGTH], &[0u8; NONCE_LENGTH], &[], &[]);
+        assert_eq!(bytes_to_hex_string(&sealed), "4EB972C9A8FB3A1B382BB4D36F5FFAD1");
+    }
+
+    #[test]
+    fn authentication() {
+        let sealed = encrypt(&key(), &nonce(), b"secret", b"header");
+
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 0x01;
+        assert_eq!(
+            decrypt(&key(), &nonce(), &tampered, b"header")
+                .expect_err("Err")
+                .to_string(),
+            "authentication_failed"
+        );
+
+        assert_eq!(
+            decrypt(&key(), &nonce(), &sealed, b"other")
+                .expect_err("Err")
+                .to_string(),
+            "authentication_failed"
+        );
+
+        assert_eq!(
+            decrypt(&key(), &nonce(), &sealed[..10], b"header")
+                .expect_err("Err")
+                .to_string(),
+            "invalid_ciphertext_length"
+        );
+    }
+}
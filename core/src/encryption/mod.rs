@@ -0,0 +1,2 @@
+pub mod chacha20_poly1305;
+pub mod scrypt;
@@ -0,0 +1,217 @@
+/*
+    scrypt (RFC 7914): a memory-hard key derivation from a passphrase, to make guessing it expensive.
+        B       PBKDF2-HMAC-SHA256(passphrase, salt, 1, p * 128 * r)
+        B_i     ROMix of each 128 * r bytes block: N BlockMix (Salsa20/8) steps, N pseudo-random reads of them
+        key     PBKDF2-HMAC-SHA256(passphrase, B, 1, length)
+    Memory used is 128 * r * N bytes.
+*/
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::std_lib::std_result::StdResult;
+
+// Parameters are read from files too: the memory (128 * r * N bytes) and the time (p times it) are capped
+const MAX_LOG_N: u8 = 20;
+const MAX_MEMORY: u64 = 1 << 30;
+const MAX_P: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScryptParams {
+    // N = 2^log_n
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    // 32 MiB, as for interactive logins
+    fn default() -> Self {
+        Self { log_n: 15, r: 8, p: 1 }
+    }
+}
+
+impl ScryptParams {
+    pub fn new(log_n: u8, r: u32, p: u32) -> StdResult<Self> {
+        if log_n == 0 || log_n > MAX_LOG_N || r == 0 || p == 0 || p > MAX_P {
+            Err("invalid_scrypt_params")?;
+        }
+
+        if 128 * r as u64 * (1 << log_n) > MAX_MEMORY {
+            Err("scrypt_memory_exceeded")?;
+        }
+
+        Ok(Self { log_n, r, p })
+    }
+}
+
+pub fn scrypt(passphrase: &[u8], salt: &[u8], params: ScryptParams, length: usize) -> StdResult<Vec<u8>> {
+    let params = ScryptParams::new(params.log_n, params.r, params.p)?;
+    let block_length = 128 * params.r as usize;
+
+    let mut b = pbkdf2_hmac_sha256(passphrase, salt, params.p as usize * block_length);
+    for block in b.chunks_mut(block_length) {
+        ro_mix(block, 1 << params.log_n, params.r as usize);
+    }
+
+    let key = pbkdf2_hmac_sha256(passphrase, &b, length);
+    b.zeroize();
+
+    Ok(key)
+}
+
+// One iteration, `length` bytes
+fn pbkdf2_hmac_sha256(passphrase: &[u8], salt: &[u8], length: usize) -> Vec<u8> {
+    let mut res = Vec::with_capacity(length);
+
+    let mut block_index: u32 = 1;
+    while res.len() < length {
+        let mut hmac = Hmac::<Sha256>::new_from_slice(passphrase).expect("HMAC initialization failed");
+        hmac.update(salt);
+        hmac.update(&block_index.to_be_bytes());
+
+        res.extend_from_slice(hmac.finalize().into_bytes().as_slice());
+        block_index += 1;
+    }
+
+    res.truncate(length);
+
+    res
+}
+
+fn ro_mix(block: &mut [u8], n: usize, r: usize) {
+    let words = 32 * r;
+
+    let mut x: Vec<u32> = block
+        .chunks(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+
+    let mut v: Vec<u32> = Vec::with_capacity(n * words);
+    for _ in 0..n {
+        v.extend_from_slice(&x);
+        x = block_mix(&x, r);
+    }
+
+    for _ in 0..n {
+        // Integerify (N is at most 2^20, the low word is enough)
+        let j = x[words - 16] as usize & (n - 1);
+        for (word, v_word) in x.iter_mut().zip(v[j * words..(j + 1) * words].iter()) {
+            *word ^= v_word;
+        }
+        x = block_mix(&x, r);
+    }
+
+    for (chunk, word) in block.chunks_mut(4).zip(x.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+
+    x.zeroize();
+    v.zeroize();
+}
+
+// Salsa20/8 of each 64 bytes block XOR the previous one, even blocks first then odd ones
+fn block_mix(b: &[u32], r: usize) -> Vec<u32> {
+    let mut x = [0u32; 16];
+    x.copy_from_slice(&b[(2 * r - 1) * 16..]);
+
+    let mut y = vec![0u32; 32 * r];
+    for (i, chunk) in b.chunks(16).enumerate() {
+        for (x_word, word) in x.iter_mut().zip(chunk.iter()) {
+            *x_word ^= word;
+        }
+        salsa20_8(&mut x);
+
+        let position = (i / 2 + (i % 2) * r) * 16;
+        y[position..position + 16].copy_from_slice(&x);
+    }
+
+    x.zeroize();
+
+    y
+}
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+    x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+    x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+    x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+}
+
+fn salsa20_8(block: &mut [u32; 16]) {
+    let mut x = *block;
+
+    for _ in 0..4 {
+        // columns
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 5, 9, 13, 1);
+        quarter_round(&mut x, 10, 14, 2, 6);
+        quarter_round(&mut x, 15, 3, 7, 11);
+        // rows
+        quarter_round(&mut x, 0, 1, 2, 3);
+        quarter_round(&mut x, 5, 6, 7, 4);
+        quarter_round(&mut x, 10, 11, 8, 9);
+        quarter_round(&mut x, 15, 12, 13, 14);
+    }
+
+    for (word, mixed) in block.iter_mut().zip(x.iter()) {
+        *word = word.wrapping_add(*mixed);
+    }
+
+    x.zeroize();
+}
+
+#[cfg(test)]
+mod scrypt_test {
+    use super::*;
+    use crate::std_lib::vector::bytes_to_hex_string;
+
+    // https://www.rfc-editor.org/rfc/rfc7914 (12, first vector)
+    #[test]
+    fn verify_a_scrypt() {
+        let key = scrypt(b"", b"", ScryptParams::new(4, 1, 1).unwrap(), 64).unwrap();
+
+        assert_eq!(
+            bytes_to_hex_string(&key),
+            "77D6576238657B203B19CA42C18A0497F16B4844E3074AE8DFDFFA3FEDE21442FCD0069DED0948F8326A753A0FC81F17E8D3E0FB2E0D3628CF35E20C38D18906"
+        );
+    }
+
+    #[test]
+    fn verify_a_scrypt_with_parallelization() {
+        let key = scrypt(b"passphrase", b"salt", ScryptParams::new(4, 2, 3).unwrap(), 32).unwrap();
+
+        assert_eq!(
+            bytes_to_hex_string(&key),
+            "C7CFB6EAE2385887CD71A9492836C260FD7B007E7964863B99FCF5212FF4ADB9"
+        );
+    }
+
+    #[test]
+    fn invalid_params() {
+        for (log_n, r, p) in [(0, 8, 1), (21, 8, 1), (15, 0, 1), (15, 8, 0)] {
+            assert_eq!(
+                ScryptParams::new(log_n, r, p).expect_err("Err").to_string(),
+                "invalid_scrypt_params"
+            );
+        }
+    }
+
+    #[test]
+    fn memory_and_time_capped() {
+        assert!(ScryptParams::new(20, 8, 16).is_ok());
+
+        assert_eq!(
+            ScryptParams::new(15, 8, 17).expect_err("Err").to_string(),
+            "invalid_scrypt_params"
+        );
+
+        for (log_n, r) in [(20, 9), (10, 8193), (1, u32::MAX)] {
+            assert_eq!(
+                ScryptParams::new(log_n, r, 1).expect_err("Err").to_string(),
+                "scrypt_memory_exceeded"
+            );
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod compression;
 pub mod network;
 pub mod network_magic;
+pub mod script_flags;
 pub mod sighash;
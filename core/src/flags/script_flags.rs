@@ -0,0 +1,235 @@
+use std::ops::{BitOr, BitOrAssign};
+
+use super::network::Network;
+
+/*
+    Script verification flags: which consensus (or policy) rules apply while evaluating a script.
+    Same bits of Bitcoin Core: https://github.com/bitcoin/bitcoin/blob/master/src/script/interpreter.h
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScriptFlags(u32);
+
+impl ScriptFlags {
+    pub const NONE: ScriptFlags = ScriptFlags(0);
+    // BIP16: evaluate the redeem script of P2SH outputs
+    pub const P2SH: ScriptFlags = ScriptFlags(1 << 0);
+    // BIP66: signatures must be strict DER
+    pub const DERSIG: ScriptFlags = ScriptFlags(1 << 2);
+    // BIP146: the S value of signatures must be in the lower half of the curve order
+    pub const LOW_S: ScriptFlags = ScriptFlags(1 << 3);
+    // BIP147: the extra element consumed by OP_CHECKMULTISIG must be empty
+    pub const NULLDUMMY: ScriptFlags = ScriptFlags(1 << 4);
+    // pushes and numbers must use the shortest encoding
+    pub const MINIMALDATA: ScriptFlags = ScriptFlags(1 << 6);
+    // BIP65: OP_CHECKLOCKTIMEVERIFY (was OP_NOP2)
+    pub const CHECKLOCKTIMEVERIFY: ScriptFlags = ScriptFlags(1 << 9);
+    // BIP112: OP_CHECKSEQUENCEVERIFY (was OP_NOP3)
+    pub const CHECKSEQUENCEVERIFY: ScriptFlags = ScriptFlags(1 << 10);
+    // BIP141: segregated witness
+    pub const WITNESS: ScriptFlags = ScriptFlags(1 << 11);
+    // the argument of OP_IF/OP_NOTIF must be empty or [0x01]
+    pub const MINIMALIF: ScriptFlags = ScriptFlags(1 << 13);
+    // BIP146: a failed signature check requires an empty signature
+    pub const NULLFAIL: ScriptFlags = ScriptFlags(1 << 14);
+    // BIP341/342: taproot and tapscript
+    pub const TAPROOT: ScriptFlags = ScriptFlags(1 << 17);
+
+    /*
+        Relay policy of new transactions: every consensus rule plus the policy ones (not enforced in blocks).
+    */
+    pub const STANDARD: ScriptFlags = ScriptFlags(
+        Self::P2SH.0
+            | Self::DERSIG.0
+            | Self::LOW_S.0
+            | Self::NULLDUMMY.0
+            | Self::MINIMALDATA.0
+            | Self::CHECKLOCKTIMEVERIFY.0
+            | Self::CHECKSEQUENCEVERIFY.0
+            | Self::WITNESS.0
+            | Self::MINIMALIF.0
+            | Self::NULLFAIL.0
+            | Self::TAPROOT.0,
+    );
+
+    pub fn contains(&self, other: ScriptFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(&self, other: ScriptFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /*
+        Consensus rules in force in the block at `height`: the union of the deployments activated at or before it.
+    */
+    pub fn consensus(network: Network, height: u32) -> ScriptFlags {
+        let activations = match network {
+            Network::Mainnet => &MAINNET_ACTIVATIONS,
+            Network::Testnet => &TESTNET_ACTIVATIONS,
+        };
+
+        activations
+            .iter()
+            .filter(|activation| activation.height <= height)
+            .fold(ScriptFlags::NONE, |flags, activation| flags | activation.flags)
+    }
+}
+
+impl BitOr for ScriptFlags {
+    type Output = ScriptFlags;
+
+    fn bitor(self, other: ScriptFlags) -> ScriptFlags {
+        ScriptFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for ScriptFlags {
+    fn bitor_assign(&mut self, other: ScriptFlags) {
+        self.0 |= other.0;
+    }
+}
+
+struct Activation {
+    height: u32,
+    flags: ScriptFlags,
+}
+
+const SEGWIT_FLAGS: ScriptFlags = ScriptFlags(ScriptFlags::WITNESS.0 | ScriptFlags::NULLDUMMY.0);
+
+/*
+    Activation heights: https://github.com/bitcoin/bitcoin/blob/master/src/kernel/chainparams.cpp
+*/
+const MAINNET_ACTIVATIONS: [Activation; 6] = [
+    Activation {
+        height: 173_805,
+        flags: ScriptFlags::P2SH,
+    },
+    Activation {
+        height: 363_725,
+        flags: ScriptFlags::DERSIG,
+    },
+    Activation {
+        height: 388_381,
+        flags: ScriptFlags::CHECKLOCKTIMEVERIFY,
+    },
+    Activation {
+        height: 419_328,
+        flags: ScriptFlags::CHECKSEQUENCEVERIFY,
+    },
+    Activation {
+        height: 481_824,
+        flags: SEGWIT_FLAGS,
+    },
+    Activation {
+        height: 709_632,
+        flags: ScriptFlags::TAPROOT,
+    },
+];
+
+const TESTNET_ACTIVATIONS: [Activation; 6] = [
+    Activation {
+        height: 514,
+        flags: ScriptFlags::P2SH,
+    },
+    Activation {
+        height: 330_776,
+        flags: ScriptFlags::DERSIG,
+    },
+    Activation {
+        height: 581_885,
+        flags: ScriptFlags::CHECKLOCKTIMEVERIFY,
+    },
+    Activation {
+        height: 770_112,
+        flags: ScriptFlags::CHECKSEQUENCEVERIFY,
+    },
+    Activation {
+        height: 834_624,
+        flags: SEGWIT_FLAGS,
+    },
+    Activation {
+        height: 2_011_968,
+        flags: ScriptFlags::TAPROOT,
+    },
+];
+
+#[cfg(test)]
+mod script_flags_test {
+    use super::*;
+
+    #[test]
+    fn combine_flags() {
+        let flags = ScriptFlags::DERSIG | ScriptFlags::LOW_S;
+
+        assert!(flags.contains(ScriptFlags::DERSIG));
+        assert!(flags.contains(ScriptFlags::LOW_S));
+        assert!(flags.contains(ScriptFlags::NONE));
+        assert!(!ScriptFlags::DERSIG.contains(flags));
+        assert!(ScriptFlags::DERSIG.intersects(flags));
+        assert!(!ScriptFlags::NONE.intersects(flags));
+    }
+
+    #[test]
+    fn default_is_none() {
+        assert_eq!(ScriptFlags::default(), ScriptFlags::NONE);
+        assert_eq!(ScriptFlags::default().bits(), 0);
+    }
+
+    macro_rules! consensus_flags {
+        ($network:expr, $height:literal, $flags:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                assert_eq!(ScriptFlags::consensus($network, $height), $flags);
+            }
+        };
+    }
+
+    consensus_flags!(Network::Mainnet, 0, ScriptFlags::NONE, mainnet_genesis);
+    consensus_flags!(Network::Mainnet, 173_804, ScriptFlags::NONE, mainnet_before_p2sh);
+    consensus_flags!(Network::Mainnet, 173_805, ScriptFlags::P2SH, mainnet_p2sh);
+    consensus_flags!(
+        Network::Mainnet,
+        363_725,
+        ScriptFlags::P2SH | ScriptFlags::DERSIG,
+        mainnet_dersig
+    );
+    consensus_flags!(
+        Network::Mainnet,
+        481_823,
+        ScriptFlags::P2SH | ScriptFlags::DERSIG | ScriptFlags::CHECKLOCKTIMEVERIFY | ScriptFlags::CHECKSEQUENCEVERIFY,
+        mainnet_before_segwit
+    );
+    consensus_flags!(
+        Network::Mainnet,
+        481_824,
+        ScriptFlags::P2SH
+            | ScriptFlags::DERSIG
+            | ScriptFlags::CHECKLOCKTIMEVERIFY
+            | ScriptFlags::CHECKSEQUENCEVERIFY
+            | ScriptFlags::WITNESS
+            | ScriptFlags::NULLDUMMY,
+        mainnet_segwit
+    );
+    consensus_flags!(Network::Testnet, 513, ScriptFlags::NONE, testnet_before_p2sh);
+    consensus_flags!(
+        Network::Testnet,
+        330_776,
+        ScriptFlags::P2SH | ScriptFlags::DERSIG,
+        testnet_dersig
+    );
+
+    #[test]
+    fn policy_rules_are_not_consensus() {
+        let flags = ScriptFlags::consensus(Network::Mainnet, u32::MAX);
+
+        assert!(flags.contains(ScriptFlags::TAPROOT));
+        assert!(!flags.intersects(
+            ScriptFlags::LOW_S | ScriptFlags::NULLFAIL | ScriptFlags::MINIMALDATA | ScriptFlags::MINIMALIF
+        ));
+        assert!(ScriptFlags::STANDARD.contains(flags));
+    }
+}
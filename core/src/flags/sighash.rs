@@ -1,4 +1,6 @@
 pub enum SigHash {
+    // Taproot only (BIP341): as All, but the signature has no hash type byte
+    Default = 0x00,
     All = 0x01,
     // None = 0x02,
     // Single = 0x03,
@@ -0,0 +1,44 @@
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha512;
+
+pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut hmac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC initialization failed");
+    hmac.update(data);
+
+    let mut res = [0u8; 64];
+    res.copy_from_slice(hmac.finalize().into_bytes().as_slice());
+
+    res
+}
+
+#[cfg(test)]
+mod hashing_test {
+    use super::hmac_sha512;
+    use crate::std_lib::vector::bytes_to_hex_string;
+
+    // https://www.rfc-editor.org/rfc/rfc4231 (test case 2)
+    #[test]
+    fn verify_a_hmac_sha512() {
+        let hashed = hmac_sha512("Jefe".as_bytes(), "what do ya want for nothing?".as_bytes());
+
+        assert_eq!(
+            bytes_to_hex_string(&hashed),
+            "164B7A7BFCF819E2E395FBE73B56E0A387BD64222E831FD610270CD7EA2505549758BF75C05A994A6D034F65F8F0E6FDCAEAB1A34D4A6B4B636E070A38BCE737"
+        );
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc4231 (test case 6: key longer than block size)
+    #[test]
+    fn verify_a_hmac_sha512_with_long_key() {
+        let key = [0xAAu8; 131];
+        let hashed = hmac_sha512(
+            &key,
+            "Test Using Larger Than Block-Size Key - Hash Key First".as_bytes(),
+        );
+
+        assert_eq!(
+            bytes_to_hex_string(&hashed),
+            "80B24263C7C1A3EBB71493C1DD7BE8B49B46D1F41B4AEEC1121B013783F8F3526B56D037E05F2598BD0FD2215D6A1E5295E64F73F63F0AEC8B915A985D786598"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 pub mod hash160;
 pub mod hash256;
+pub mod hmac_sha512;
 pub mod ripemd160;
 pub mod sha1;
 pub mod sha256;
+pub mod tagged_hash;
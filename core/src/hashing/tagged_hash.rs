@@ -0,0 +1,24 @@
+use super::sha256::sha256;
+
+// BIP340: sha256(sha256(tag) || sha256(tag) || message)
+pub fn tagged_hash(tag: &str, message: &[u8]) -> Vec<u8> {
+    let tag_hash = sha256(tag.as_bytes());
+
+    sha256(&[tag_hash.as_slice(), tag_hash.as_slice(), message].concat())
+}
+
+#[cfg(test)]
+mod tagged_hash_test {
+    use super::tagged_hash;
+    use crate::std_lib::vector::bytes_to_hex_string;
+
+    #[test]
+    fn tap_tweak() {
+        let hashed = tagged_hash("TapTweak", &[]);
+
+        assert_eq!(
+            bytes_to_hex_string(&hashed),
+            "8AA4229474AB0100B2D6F0687F031D1FC9D8EEF92A042AD97D279BFF456B15E4"
+        );
+    }
+}
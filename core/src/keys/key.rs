@@ -2,23 +2,24 @@
 
 use hmac::KeyInit;
 use rug::{integer::Order, Integer};
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use zeroize::Zeroize;
 
 use crate::{
-    bitcoin::{
-        address_prefix::AddressPrefix,
-        ecdsa::{G, N},
-    },
-    ecdsa::point::Point,
+    bitcoin::{address_prefix::AddressPrefix, ecdsa::N},
+    ecdsa::{constant_time, point::Point, scalar256::Scalar256},
     flags::{compression::Compression, network::Network},
-    keys::signature::Signature,
+    keys::{
+        schnorr,
+        signature::{RecoverableSignature, Signature},
+    },
     std_lib::base58,
     std_lib::vector::{padding_left, vect_to_array_32},
     std_lib::{integer_extended::IntegerExtended, std_result::StdResult},
 };
 
 /// Key structure.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Key {
     /// real private key (k)
     private_key: Integer,
@@ -29,7 +30,10 @@ pub struct Key {
 impl Key {
     /// New `PrivateKey` by secret.
     pub fn new(private_key: Integer) -> Key {
-        let public_key = &(*G).clone() * private_key.clone();
+        let mut secret = Self::secret_scalar(&private_key);
+        let public_key = Point::mul_generator_secret(&secret);
+        secret.zeroize();
+
         Key {
             private_key,
             public_key,
@@ -40,8 +44,29 @@ impl Key {
         Self::verify_signature(&self.public_key, z, sig)
     }
 
-    pub fn from_wif(_wif: &str) -> Key {
-        unimplemented!()
+    /// Decode a WIF (Wallet Import Format) private key.
+    /// Return the `Key` with the `Compression` and the `Network` encoded in `wif`.
+    ///
+    /// Format:
+    ///    [prefix (0x80 or 0xEF), secret (32 bytes), compression flag (0x01, only if compressed)] |> base58check()
+    pub fn from_wif(wif: &str) -> StdResult<(Key, Compression, Network)> {
+        let decoded = base58::base58_decode_with_checksum(wif)?;
+
+        let compression = match decoded.len() {
+            33 => Compression::Uncompressed,
+            34 if decoded[33] == 0x01 => Compression::Compressed,
+            34 => Err("invalid_compression_flag")?,
+            _ => Err("invalid_length")?,
+        };
+
+        let network = Self::wif_network(decoded[0])?;
+
+        let private_key = Integer::from_digits(&decoded[1..33], Order::Msf);
+        if private_key == 0 || private_key >= *N {
+            Err("invalid_private_key")?;
+        }
+
+        Ok((Key::new(private_key), compression, network))
     }
 
     // ANCHOR: fn_address
@@ -71,21 +96,62 @@ impl Key {
     /// Sign a message.
     /// `z` is the hash of the message.
     /// Return the `Signature` for the signed message.
+    ///
+    /// The computations with secrets (`k * G`, `1 / k`, `r * d`) run in constant time over fixed width scalars.
     pub fn sign(&self, z: Integer) -> Signature {
-        let k = Self::deterministic_k(&self.private_key, &z);
+        self.sign_recoverable(z).signature
+    }
 
-        let r = (&(*G).clone() * k.clone()).x_as_num();
+    /// Sign a message as `sign`, with the recovery id of the signature (to recover the public key from it).
+    pub fn sign_recoverable(&self, z: Integer) -> RecoverableSignature {
+        let mut secret_bytes = Self::secret_bytes(&self.private_key);
+        let z_bytes = Self::hashed_bytes(&z);
 
-        let k_inv = k.invert_by_modulo(&N);
+        let mut k_bytes = Self::rfc6979_nonce(&secret_bytes, &z_bytes);
 
-        let sl = (z + &r * &self.private_key) * k_inv;
-        let (_q, mut s) = sl.div_rem_euc((*N).clone());
+        let mut d = Scalar256::from_bytes_reduced(&secret_bytes);
+        let mut k = Scalar256::from_bytes_reduced(&k_bytes);
+        let z = Scalar256::from_bytes_reduced(&z_bytes);
 
-        if s > ((*N).clone() / 2) {
-            s = (*N).clone() - s;
+        let nonce_point = constant_time::mul_generator(&k);
+        let nonce_x = nonce_point.x.to_bytes();
+        let r = Scalar256::from_bytes_reduced(&nonce_x);
+
+        // x(R) >= N is public (as r): it is the case where r has been reduced
+        let x_overflow = Integer::from_digits(&nonce_x, Order::Msf) >= *N;
+        let mut recovery_id = (nonce_point.y.is_odd() as u8) | ((x_overflow as u8) << 1);
+
+        let mut k_inv = k.invert();
+        let mut s = k_inv * (z + r * d);
+
+        // s and N - s are both valid, with R and -R (y of the opposite parity)
+        if s.is_high() {
+            s = -s;
+            recovery_id ^= 1;
+        }
+
+        let signature = Signature {
+            r: Integer::from_digits(&r.to_bytes(), Order::Msf),
+            s: Integer::from_digits(&s.to_bytes(), Order::Msf),
         };
 
-        Signature { r, s }
+        secret_bytes.zeroize();
+        k_bytes.zeroize();
+        d.zeroize();
+        k.zeroize();
+        k_inv.zeroize();
+
+        RecoverableSignature { signature, recovery_id }
+    }
+
+    /// BIP340 Schnorr signature of `message` (usually a 32 bytes hash) for the x-only public key.
+    /// `aux_rand` are 32 fresh random bytes (all zero is still secure, but leaks more to side channels).
+    pub fn sign_schnorr(&self, message: &[u8], aux_rand: &[u8; 32]) -> StdResult<[u8; 64]> {
+        let mut secret_bytes = Self::secret_bytes(&self.private_key);
+        let signature = schnorr::sign(&secret_bytes, message, aux_rand);
+        secret_bytes.zeroize();
+
+        signature
     }
 
     fn hmac_for_data(data: &[u8], mut k: [u8; 32]) -> [u8; 32] {
@@ -101,38 +167,41 @@ impl Key {
 
     /// https://www.rfc-editor.org/rfc/rfc6979.txt
     pub fn deterministic_k(secret: &Integer, hashed: &Integer) -> Integer {
-        let mut z = hashed.clone();
+        let mut secret_bytes = Self::secret_bytes(secret);
+        let mut k = Self::rfc6979_nonce(&secret_bytes, &Self::hashed_bytes(hashed));
 
-        if z > *N {
-            z -= (*N).clone();
-        }
+        let res = Integer::from_digits(&k, Order::Msf);
 
-        let zero: [u8; 1] = [0u8];
-        let one: [u8; 1] = [1u8];
+        secret_bytes.zeroize();
+        k.zeroize();
 
-        let z_vect: Vec<u8> = z.to_digits::<u8>(Order::Msf);
-        let z_bytes: [u8; 32] = vect_to_array_32(&z_vect);
+        res
+    }
 
-        let secret_vect: Vec<u8> = secret.to_digits::<u8>(Order::Msf);
-        let secret_bytes: [u8; 32] = vect_to_array_32(&secret_vect);
+    /// RFC6979 nonce (32 bytes big endian, in [1, N - 1]) from the secret and the hashed message (both 32 bytes).
+    fn rfc6979_nonce(secret_bytes: &[u8; 32], z_bytes: &[u8; 32]) -> [u8; 32] {
+        let zero: [u8; 1] = [0u8];
+        let one: [u8; 1] = [1u8];
 
         let mut k: [u8; 32] = [0u8; 32];
         let mut v: [u8; 32] = [1u8; 32];
 
-        let mut data: Vec<u8> = [v.as_slice(), zero.as_slice(), &secret_bytes, &z_bytes].concat();
+        let mut data: Vec<u8> = [v.as_slice(), zero.as_slice(), secret_bytes, z_bytes].concat();
         k = Key::hmac_for_data(&data, k);
         v = Key::hmac_for_data(&v, k);
+        data.zeroize();
 
-        data = [v.as_slice(), one.as_slice(), &secret_bytes, &z_bytes].concat();
+        data = [v.as_slice(), one.as_slice(), secret_bytes, z_bytes].concat();
         k = Key::hmac_for_data(&data, k);
         v = Key::hmac_for_data(&v, k);
+        data.zeroize();
 
         loop {
             v = Key::hmac_for_data(&v, k);
-            let candidate: Integer = Integer::from_digits(&v, Order::Msf);
 
-            if candidate >= 1 && candidate < *N {
-                return candidate;
+            if Scalar256::from_bytes_nonzero(&v).is_some() {
+                k.zeroize();
+                return v;
             }
 
             data = [v.as_slice(), zero.as_slice()].concat();
@@ -141,12 +210,50 @@ impl Key {
         }
     }
 
+    /// `z` (hashed message) as 32 bytes, reduced by N once as in RFC6979 (`bits2octets`).
+    fn hashed_bytes(hashed: &Integer) -> [u8; 32] {
+        let mut z = hashed.clone();
+
+        if z >= *N {
+            z -= (*N).clone();
+        }
+
+        vect_to_array_32(&z.to_digits::<u8>(Order::Msf))
+    }
+
+    /// Secret as 32 bytes (big endian), the temporary digits are wiped.
+    fn secret_bytes(secret: &Integer) -> [u8; 32] {
+        let mut digits = secret.to_digits::<u8>(Order::Msf);
+        let bytes = vect_to_array_32(&digits);
+        digits.zeroize();
+
+        bytes
+    }
+
+    fn secret_scalar(secret: &Integer) -> Scalar256 {
+        let mut bytes = Self::secret_bytes(secret);
+        let scalar = Scalar256::from_bytes_reduced(&bytes);
+        bytes.zeroize();
+
+        scalar
+    }
+
     pub fn public_key_sec(&self) -> Vec<u8> {
         self.public_key.serialize(Compression::Compressed)
     }
 
-    pub fn private_key(&self) -> Integer {
-        self.private_key.clone()
+    /// BIP340: 32 bytes, the x coordinate of the public key.
+    pub fn x_only_public_key(&self) -> Vec<u8> {
+        self.public_key.serialize(Compression::Compressed)[1..].to_vec()
+    }
+
+    // A reference: a copy of the secret would not be wiped when dropped
+    pub fn private_key(&self) -> &Integer {
+        &self.private_key
+    }
+
+    pub fn public_key(&self) -> Point {
+        self.public_key.clone()
     }
 
     pub fn to_wif(&self, compression: Compression, network: Network) -> String {
@@ -167,6 +274,14 @@ impl Key {
         }
     }
 
+    fn wif_network(prefix: u8) -> StdResult<Network> {
+        match prefix {
+            p if p == AddressPrefix::PrivateKeyMainnet as u8 => Ok(Network::Mainnet),
+            p if p == AddressPrefix::PrivateKeyTestnet as u8 => Ok(Network::Testnet),
+            _ => Err("invalid_network_prefix")?,
+        }
+    }
+
     fn wif_compression_prefix(compression: Compression) -> Vec<u8> {
         match compression {
             Compression::Uncompressed => vec![],
@@ -178,6 +293,12 @@ impl Key {
     /// `z`: the hashed message
     /// `sig`: the public signature
     pub fn verify_signature(point: &Point, z: &Integer, signature: &Signature) -> bool {
+        // r and s must be in [1, N - 1] (a lax DER signature could be zero or overflow)
+        let in_range = |value: &Integer| *value > 0 && *value < *N;
+        if !in_range(&signature.r) || !in_range(&signature.s) {
+            return false;
+        }
+
         let s_inv = signature.s.invert_by_modulo(&N);
 
         let mu = z * &s_inv;
@@ -186,27 +307,46 @@ impl Key {
         let mv = &signature.r * &s_inv;
         let (_q, v) = Integer::from(mv).div_rem_euc((*N).clone());
 
-        let total = (&(*G).clone() * u) + &(point * v);
+        let total = Point::mul_generator_add(&u, point, &v);
+        if total.is_infinite() {
+            return false;
+        }
 
         total.x_as_num() == signature.r
     }
 }
 
+impl Drop for Key {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
 impl Display for Key {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "Private key({}", self.public_key)
     }
 }
 
+// The secret is never printed
+impl Debug for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
 #[cfg(test)]
 mod private_key_test {
     use rug::{integer::Order, ops::Pow, Integer};
 
     use crate::{
+        bitcoin::ecdsa::{G, N},
         ecdsa::point::Point,
         flags::{compression::Compression, network::Network},
         hashing::hash256::Hash256,
-        keys::key::Key,
+        keys::{key::Key, signature::Signature},
         std_lib::{
             integer_extended::IntegerExtended,
             vector::{bytes_to_hex_string, hex_string_to_bytes},
@@ -400,6 +540,105 @@ mod private_key_test {
         );
     }
 
+    /// `Key::sign` as computed before the constant time implementation (rug integers, double and add).
+    fn sign_variable_time(key: &Key, z: Integer) -> Signature {
+        let k = Key::deterministic_k(&key.private_key, &z);
+        let r = (*G).mul_double_and_add(k.clone()).x_as_num();
+
+        let k_inv = k.invert_by_modulo(&N);
+
+        let sl = (z + &r * &key.private_key) * k_inv;
+        let (_q, mut s) = sl.div_rem_euc((*N).clone());
+
+        if s > ((*N).clone() / 2) {
+            s = (*N).clone() - s;
+        };
+
+        Signature { r, s }
+    }
+
+    macro_rules! sign_as_variable_time {
+        ($secret:literal, $z:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let key = Key::new(Integer::from_hex_str($secret));
+                let z = Integer::from_hex_str($z);
+
+                let signature = key.sign(z.clone());
+                let expected = sign_variable_time(&key, z.clone());
+
+                assert_eq!(signature.r, expected.r);
+                assert_eq!(signature.s, expected.s);
+                assert_eq!(
+                    key.public_key,
+                    (*G).mul_double_and_add(Integer::from_hex_str($secret))
+                );
+                assert!(key.verify(&z, &signature));
+            }
+        };
+    }
+
+    sign_as_variable_time!("1", "1", sign_as_variable_time_1);
+    sign_as_variable_time!("2345", "6789", sign_as_variable_time_2);
+    sign_as_variable_time!(
+        "A23B7C91D3E5F10243B5C3A3E8D0F6A9987654321FEDCBA0123456789ABCDEF0",
+        "EC208BAA0FC1C19F708A9CA96FDEFF3AC3F230BB4A7BA4AEDE4942AD003C0F60",
+        sign_as_variable_time_3
+    );
+    sign_as_variable_time!(
+        "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140",
+        "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+        sign_as_variable_time_4
+    );
+
+    // sha256("Satoshi Nakamoto"), secret 1 and N - 1
+    #[test]
+    fn sign_rfc6979_known_signatures() {
+        let z = Integer::from_hex_str("A0DC65FFCA799873CBEA0AC274015B9526505DAAAED385155425F7337704883E");
+
+        let signature = Key::new(Integer::from(1)).sign(z.clone());
+        assert_eq!(
+            signature.r,
+            Integer::from_hex_str("934B1EA10A4B3C1757E2B0C017D0B6143CE3C9A7E6A4A49860D7A6AB210EE3D8")
+        );
+        assert_eq!(
+            signature.s,
+            Integer::from_hex_str("2442CE9D2B916064108014783E923EC36B49743E2FFA1C4496F01A512AAFD9E5")
+        );
+
+        let signature = Key::new((*N).clone() - 1).sign(z);
+        assert_eq!(
+            signature.r,
+            Integer::from_hex_str("FD567D121DB66E382991534ADA77A6BD3106F0A1098C231E47993447CD6AF2D0")
+        );
+        assert_eq!(
+            signature.s,
+            Integer::from_hex_str("6B39CD0EB1BC8603E159EF5C20A5C8AD685A45B06CE9BEBED3F153D10D93BED5")
+        );
+    }
+
+    // bits2octets: z = N is reduced to 0 for the nonce
+    #[test]
+    fn sign_z_equal_to_n() {
+        let key = Key::new(Integer::from(2345));
+
+        let signature = key.sign((*N).clone());
+        let expected = key.sign(Integer::from(0));
+
+        assert_eq!(signature.r, expected.r);
+        assert_eq!(signature.s, expected.s);
+    }
+
+    #[test]
+    fn secret_is_not_printed() {
+        let key = Key::new(Integer::from_hex_str("54321DEADBEEF"));
+
+        let printed = format!("{:?}", key);
+        assert!(printed.contains("public_key"));
+        assert!(!printed.contains(&key.private_key.to_string()));
+        assert!(!printed.to_uppercase().contains("54321DEADBEEF"));
+    }
+
     #[test]
     fn address_1() {
         let private_key = Key::new(Integer::from(5002));
@@ -447,4 +686,103 @@ mod private_key_test {
 
         assert_eq!("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgiuQJv1h8Ytr2S53a", addr);
     }
+
+    macro_rules! from_wif {
+        ($wif:literal, $secret:literal, $compression:expr, $network:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let (key, compression, network) = Key::from_wif($wif).unwrap();
+
+                assert_eq!(*key.private_key(), Integer::from_hex_str($secret));
+                assert_eq!(compression, $compression);
+                assert_eq!(network, $network);
+                assert_eq!(key.to_wif(compression, network), $wif);
+            }
+        };
+    }
+
+    from_wif!(
+        "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ",
+        "0C28FCA386C7A227600B2FE50B7CAE11EC86D3BF1FBE471BE89827E19D72AA1D",
+        Compression::Uncompressed,
+        Network::Mainnet,
+        from_wif_uncompressed_mainnet
+    );
+    from_wif!(
+        "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617",
+        "0C28FCA386C7A227600B2FE50B7CAE11EC86D3BF1FBE471BE89827E19D72AA1D",
+        Compression::Compressed,
+        Network::Mainnet,
+        from_wif_compressed_mainnet
+    );
+    from_wif!(
+        "cMzLdeGd5vEqxB8B6VFQoRopQ3sLAAvEzDAoQgvX54xwofSWj1fx",
+        "0C28FCA386C7A227600B2FE50B7CAE11EC86D3BF1FBE471BE89827E19D72AA1D",
+        Compression::Compressed,
+        Network::Testnet,
+        from_wif_compressed_testnet
+    );
+    from_wif!(
+        "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN8rFTv2sfUK",
+        "138B",
+        Compression::Compressed,
+        Network::Testnet,
+        from_wif_1
+    );
+    from_wif!(
+        "91avARGdfge8E4tZfYLoxeJ5sGBdNJQH4kvjpWAxgzczjbCwxic",
+        "77C8350C02B595",
+        Compression::Uncompressed,
+        Network::Testnet,
+        from_wif_2
+    );
+    from_wif!(
+        "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgiuQJv1h8Ytr2S53a",
+        "54321DEADBEEF",
+        Compression::Compressed,
+        Network::Mainnet,
+        from_wif_3
+    );
+
+    macro_rules! from_wif_error {
+        ($wif:literal, $reason:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let res = Key::from_wif($wif);
+                assert_eq!(res.err().unwrap().to_string(), $reason);
+            }
+        };
+    }
+
+    from_wif_error!(
+        "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTK",
+        "invalid_checksum",
+        from_wif_invalid_checksum
+    );
+    from_wif_error!(
+        "5KrPNVvAhnRBNMYRJUq58YMfyUMyVMQrQhhfFtcbT9rK67poC3F",
+        "invalid_network_prefix",
+        from_wif_invalid_network_prefix
+    );
+    from_wif_error!(
+        "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvWxyf5d",
+        "invalid_compression_flag",
+        from_wif_invalid_compression_flag
+    );
+    from_wif_error!("RVnPfpC2", "invalid_length", from_wif_invalid_length);
+    from_wif_error!(
+        "5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAbuatmU",
+        "invalid_private_key",
+        from_wif_zero_private_key
+    );
+    from_wif_error!(
+        "L5oLkpV3aqBjhki6LmvChTCV6odsp4SXM6FfU2Gppt5kFqRzExJJ",
+        "invalid_private_key",
+        from_wif_private_key_out_of_range
+    );
+    from_wif_error!(
+        "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvy0J",
+        "invalid_character",
+        from_wif_invalid_character
+    );
 }
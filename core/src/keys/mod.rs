@@ -1,2 +1,3 @@
 pub mod key;
+pub mod schnorr;
 pub mod signature;
@@ -0,0 +1,191 @@
+/*
+    Schnorr signatures (BIP340) with x-only public keys (the point with even y):
+        d       secret, negated if d * G has odd y
+        k       tagged_hash("BIP0340/nonce", (d XOR tagged_hash("BIP0340/aux", aux)) || x(P) || m),
+                negated if k * G has odd y
+        e       tagged_hash("BIP0340/challenge", x(R) || x(P) || m)
+        sig     x(R) || (k + e * d) mod N
+    Verification: R = s * G - e * P must not be infinite, must have even y and x(R) = r.
+*/
+
+use rug::{integer::Order, Integer};
+use subtle::{Choice, ConditionallySelectable};
+use zeroize::Zeroize;
+
+use crate::{
+    bitcoin::ecdsa::{N, P},
+    ecdsa::{constant_time, point::Point, scalar256::Scalar256},
+    hashing::tagged_hash::tagged_hash,
+    std_lib::{std_result::StdResult, vector::vect_to_array_32},
+};
+
+pub const SIGNATURE_LENGTH: usize = 64;
+
+// The nonce and the secret arithmetic run in constant time, as `Key::sign`
+pub fn sign(secret: &[u8; 32], message: &[u8], aux_rand: &[u8; 32]) -> StdResult<[u8; SIGNATURE_LENGTH]> {
+    let mut d = match Scalar256::from_bytes_nonzero(secret) {
+        Some(d) => d,
+        None => Err("invalid_private_key")?,
+    };
+
+    let public_key = constant_time::mul_generator(&d);
+    d = Scalar256::conditional_select(&d, &-d, Choice::from(public_key.y.is_odd() as u8));
+    let public_key_x = public_key.x.to_bytes();
+
+    let mut masked_secret: Vec<u8> = d
+        .to_bytes()
+        .iter()
+        .zip(tagged_hash("BIP0340/aux", aux_rand))
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    let mut nonce_data = [masked_secret.as_slice(), &public_key_x, message].concat();
+    let mut nonce_bytes = vect_to_array_32(&tagged_hash("BIP0340/nonce", &nonce_data));
+    masked_secret.zeroize();
+    nonce_data.zeroize();
+
+    let mut k = Scalar256::from_bytes_reduced(&nonce_bytes);
+    nonce_bytes.zeroize();
+    if k.is_zero() {
+        Err("invalid_schnorr_nonce")?;
+    }
+
+    let nonce_point = constant_time::mul_generator(&k);
+    k = Scalar256::conditional_select(&k, &-k, Choice::from(nonce_point.y.is_odd() as u8));
+    let r = nonce_point.x.to_bytes();
+
+    let e = Scalar256::from_bytes_reduced(&challenge(&r, &public_key_x, message));
+    let s = k + e * d;
+    d.zeroize();
+    k.zeroize();
+
+    let mut signature = [0u8; SIGNATURE_LENGTH];
+    signature[..32].copy_from_slice(&r);
+    signature[32..].copy_from_slice(&s.to_bytes());
+
+    // as recommended by BIP340, against faults in the computation
+    if !verify(&public_key_x, message, &signature) {
+        Err("invalid_schnorr_signature")?;
+    }
+
+    Ok(signature)
+}
+
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    if signature.len() != SIGNATURE_LENGTH {
+        return false;
+    }
+
+    let Some(point) = lift_x(public_key) else {
+        return false;
+    };
+
+    let r = Integer::from_digits(&signature[..32], Order::Msf);
+    let s = Integer::from_digits(&signature[32..], Order::Msf);
+    if r >= *P || s >= *N {
+        return false;
+    }
+
+    let e = Integer::from_digits(&challenge(&signature[..32], public_key, message), Order::Msf);
+    let nonce_point = Point::mul_generator_add(&s, &point, &-e);
+
+    !nonce_point.is_infinite() && nonce_point.y_as_num().is_even() && nonce_point.x_as_num() == r
+}
+
+// The point with x coordinate `x` (32 bytes) and even y, if any
+pub fn lift_x(x: &[u8]) -> Option<Point> {
+    if x.len() != 32 || Integer::from_digits(x, Order::Msf) >= *P {
+        return None;
+    }
+
+    let point = Point::deserialize([[0x02].as_slice(), x].concat());
+
+    point.is_on_curve().then_some(point)
+}
+
+fn challenge(r: &[u8], public_key: &[u8], message: &[u8]) -> [u8; 32] {
+    vect_to_array_32(&tagged_hash("BIP0340/challenge", &[r, public_key, message].concat()))
+}
+
+#[cfg(test)]
+mod schnorr_test {
+    use super::*;
+    use crate::std_lib::vector::{bytes_to_hex_string, hex_string_to_bytes};
+
+    fn array_32(hex: &str) -> [u8; 32] {
+        vect_to_array_32(&hex_string_to_bytes(hex).unwrap())
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+    macro_rules! sign {
+        ($secret:expr, $public_key:expr, $aux_rand:expr, $message:expr, $signature:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let message = hex_string_to_bytes($message).unwrap();
+
+                let signature = sign(&array_32($secret), &message, &array_32($aux_rand)).unwrap();
+
+                assert_eq!(bytes_to_hex_string(&signature), $signature);
+                assert!(verify(
+                    &hex_string_to_bytes($public_key).unwrap(),
+                    &message,
+                    &signature
+                ));
+            }
+        };
+    }
+
+    sign!(
+        "0000000000000000000000000000000000000000000000000000000000000003",
+        "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0",
+        vector_0
+    );
+    sign!(
+        "B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF",
+        "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+        "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A",
+        vector_1
+    );
+    sign!(
+        "C90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B14E5C9",
+        "DD308AFEC5777E13121FA72B9CC1B7CC0139715309B086C960E18FD969774EB8",
+        "C87AA53824B4D7AE2EB035A2B5BBBCCC080E76CDC6D1692C4B0B62D798E6D906",
+        "7E2D58D8B3BCDF1ABADEC7829054F90DDA9805AAB56C77333024B9D0A508B75C",
+        "5831AAEED7B44BB74E5EAB94BA9D4294C49BCF2A60728D8B4C200F50DD313C1BAB745879A5AD954A72C45A91C3A51D3C7ADEA98D82F8481E0E1E03674A6F3FB7",
+        vector_2
+    );
+
+    #[test]
+    fn invalid_signatures() {
+        let public_key =
+            hex_string_to_bytes("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659").unwrap();
+        let message = hex_string_to_bytes("243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89").unwrap();
+        let signature = hex_string_to_bytes("6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A").unwrap();
+
+        // public key not on the curve (vector 5)
+        let not_on_curve =
+            hex_string_to_bytes("EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34").unwrap();
+        assert!(!verify(&not_on_curve, &message, &signature));
+
+        // s >= N
+        let mut s_overflow = signature.clone();
+        s_overflow[32..].fill(0xFF);
+        assert!(!verify(&public_key, &message, &s_overflow));
+
+        // another message
+        assert!(!verify(&public_key, &[0u8; 32], &signature));
+
+        assert!(!verify(&public_key, &message, &signature[..63]));
+    }
+
+    #[test]
+    fn invalid_secret() {
+        let err = sign(&[0u8; 32], &[0u8; 32], &[0u8; 32]).expect_err("Err");
+
+        assert_eq!(err.to_string(), "invalid_private_key");
+    }
+}
@@ -1,9 +1,18 @@
 use rug::{integer::Order, Integer};
 use std::fmt::{Display, Formatter};
 
-use crate::std_lib::{std_result::StdResult, vector::trim_left};
-
-#[derive(Debug)]
+use crate::{
+    bitcoin::ecdsa::{N, P},
+    ecdsa::point::Point,
+    flags::compression::Compression,
+    std_lib::{
+        integer_extended::IntegerExtended,
+        std_result::StdResult,
+        vector::{trim_left, vect_to_array_32},
+    },
+};
+
+#[derive(Debug, Clone)]
 pub struct Signature {
     pub r: Integer,
     pub s: Integer,
@@ -12,6 +21,11 @@ pub struct Signature {
 const DER_MARKER: u8 = 0x30;
 const DER_INTEGER: u8 = 0x02;
 
+const COMPACT_LENGTH: usize = 64;
+const COMPACT_HEADER: u8 = 27;
+const COMPACT_COMPRESSED: u8 = 4;
+const MAX_RECOVERY_ID: u8 = 3;
+
 /*
     https://github.com/bitcoin/bips/blob/master/bip-0066.mediawiki
     https://github.com/libbitcoin/libbitcoin-system/wiki/ECDSA-and-DER-Signatures
@@ -55,6 +69,26 @@ impl Signature {
         Ok(Signature { r, s })
     }
 
+    // r || s, 32 bytes each
+    pub fn new_from_compact(compact: &[u8]) -> StdResult<Self> {
+        if compact.len() != COMPACT_LENGTH {
+            Err("invalid_signature_length")?;
+        }
+
+        Ok(Signature {
+            r: Integer::from_digits(&compact[..32], Order::Msf),
+            s: Integer::from_digits(&compact[32..], Order::Msf),
+        })
+    }
+
+    pub fn serialize_compact(&self) -> Vec<u8> {
+        [
+            vect_to_array_32(&self.r.to_digits::<u8>(Order::Msf)),
+            vect_to_array_32(&self.s.to_digits::<u8>(Order::Msf)),
+        ]
+        .concat()
+    }
+
     pub fn der(&self) -> Vec<u8> {
         let r_value = Self::der_serialize(&self.r);
         let s_value = Self::der_serialize(&self.s);
@@ -96,6 +130,10 @@ impl Signature {
         let content_start = start + 1;
         let content_end = content_start + length;
 
+        if content_end > der.len() {
+            Err("invalid_rs_lenght")?;
+        }
+
         let bytes = der[content_start..content_end].to_vec();
 
         /*
@@ -108,6 +146,196 @@ impl Signature {
 
         Ok((Integer::from_digits(&bytes, Order::Msf), content_end))
     }
+
+    /*
+        BIP66 strict DER check (IsValidSignatureEncoding), `sig` includes the trailing SIGHASH byte:
+            0x30 [total-length] 0x02 [R-length] [R] 0x02 [S-length] [S] [sighash]
+
+        R and S are positive integers with the minimal encoding: no leading 0x00 unless the next byte
+        has the highest bit set (otherwise they would be negative).
+    */
+    pub fn check_strict_der(sig: &[u8]) -> StdResult<()> {
+        let length = sig.len();
+
+        // 0x30 + total-length + 0x02 + R-length + R (1..=33) + 0x02 + S-length + S (1..=33) + sighash
+        if !(9..=73).contains(&length) {
+            Err("invalid_signature_length")?;
+        }
+
+        if sig[0] != DER_MARKER {
+            Err("invalid_initial_marker")?;
+        }
+
+        // total-length covers everything but the marker, itself and the sighash
+        if sig[1] as usize != length - 3 {
+            Err("signature_lengths_do_not_match")?;
+        }
+
+        let r_length = sig[3] as usize;
+        if 5 + r_length >= length {
+            Err("missing_s_marker")?;
+        }
+
+        let s_length = sig[5 + r_length] as usize;
+        if r_length + s_length + 7 != length {
+            Err("signature_lengths_do_not_match")?;
+        }
+
+        if sig[2] != DER_INTEGER {
+            Err("invalid_r_marker")?;
+        }
+
+        if r_length == 0 {
+            Err("invalid_rs_lenght")?;
+        }
+
+        if sig[4] & 0x80 != 0 {
+            Err("negative_r")?;
+        }
+
+        if r_length > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+            Err("non_minimal_r")?;
+        }
+
+        if sig[r_length + 4] != DER_INTEGER {
+            Err("invalid_s_marker")?;
+        }
+
+        if s_length == 0 {
+            Err("invalid_rs_lenght")?;
+        }
+
+        if sig[r_length + 6] & 0x80 != 0 {
+            Err("negative_s")?;
+        }
+
+        if s_length > 1 && sig[r_length + 6] == 0x00 && sig[r_length + 7] & 0x80 == 0 {
+            Err("non_minimal_s")?;
+        }
+
+        Ok(())
+    }
+
+    /*
+        Lax DER parser for signatures included in blocks before BIP66 activation (no SIGHASH byte).
+        Port of `ecdsa_signature_parse_der_lax` (libsecp256k1 contrib, used by Bitcoin Core):
+        - long form lengths are accepted (and ignored for the sequence);
+        - R and S may have any number of leading zeros (or be "negative");
+        - bytes after S are ignored.
+        R or S greater than 32 bytes (zeros excluded) give a zero signature, that never verifies.
+    */
+    pub fn new_from_der_lax(der: &[u8]) -> StdResult<Self> {
+        let length = der.len();
+        let mut pos = 0;
+
+        if pos == length || der[pos] != DER_MARKER {
+            Err("invalid_initial_marker")?;
+        }
+        pos += 1;
+
+        if pos == length {
+            Err("invalid_signature_length")?;
+        }
+        let sequence_length = der[pos];
+        pos += 1;
+        if sequence_length & 0x80 != 0 {
+            let bytes = (sequence_length - 0x80) as usize;
+            if bytes > length - pos {
+                Err("invalid_signature_length")?;
+            }
+            pos += bytes;
+        }
+
+        if pos == length || der[pos] != DER_INTEGER {
+            Err("invalid_r_marker")?;
+        }
+        pos += 1;
+        let (r_start, r_length) = Self::lax_integer_bounds(der, &mut pos)?;
+
+        if pos == length || der[pos] != DER_INTEGER {
+            Err("invalid_s_marker")?;
+        }
+        pos += 1;
+        let (s_start, s_length) = Self::lax_integer_bounds(der, &mut pos)?;
+
+        let r = Self::lax_integer_value(&der[r_start..r_start + r_length]);
+        let s = Self::lax_integer_value(&der[s_start..s_start + s_length]);
+
+        match (r, s) {
+            (Some(r), Some(s)) => Ok(Signature { r, s }),
+            _ => Ok(Signature {
+                r: Integer::from(0),
+                s: Integer::from(0),
+            }),
+        }
+    }
+
+    // Start and length of the integer content at `pos` (just after its 0x02 marker), `pos` moves after it.
+    fn lax_integer_bounds(der: &[u8], pos: &mut usize) -> StdResult<(usize, usize)> {
+        let length = der.len();
+
+        if *pos == length {
+            Err("invalid_rs_start")?;
+        }
+        let mut length_byte = der[*pos] as usize;
+        *pos += 1;
+
+        let content_length = if length_byte & 0x80 != 0 {
+            length_byte -= 0x80;
+            if length_byte > length - *pos {
+                Err("invalid_rs_lenght")?;
+            }
+
+            while length_byte > 0 && der[*pos] == 0 {
+                *pos += 1;
+                length_byte -= 1;
+            }
+
+            if length_byte >= std::mem::size_of::<usize>() {
+                Err("invalid_rs_lenght")?;
+            }
+
+            let mut content_length = 0;
+            while length_byte > 0 {
+                content_length = (content_length << 8) + der[*pos] as usize;
+                *pos += 1;
+                length_byte -= 1;
+            }
+            content_length
+        } else {
+            length_byte
+        };
+
+        if content_length > length - *pos {
+            Err("invalid_rs_lenght")?;
+        }
+
+        let start = *pos;
+        *pos += content_length;
+
+        Ok((start, content_length))
+    }
+
+    // Leading zeros are ignored, `None` if the value doesn't fit in 32 bytes.
+    fn lax_integer_value(bytes: &[u8]) -> Option<Integer> {
+        let bytes = trim_left(bytes, 0);
+
+        if bytes.len() > 32 {
+            return None;
+        }
+
+        Some(Integer::from_digits(&bytes, Order::Msf))
+    }
+
+    /*
+        BIP146 (LOW_S): s must be less than or equal to N / 2.
+        For every valid (r, s) also (r, N - s) is valid: only the low one is accepted to avoid malleability.
+    */
+    pub fn is_low_s(&self) -> bool {
+        let half_n = Integer::from(&*N >> 1);
+
+        self.s <= half_n
+    }
 }
 
 impl Display for Signature {
@@ -116,6 +344,86 @@ impl Display for Signature {
     }
 }
 
+/*
+    A signature with its recovery id (0 to 3), enough to compute the public key from the signed hash `z`.
+    Among the points with x = r (mod N), the recovery id tells which one is R = k * G:
+        bit 0   parity of y(R)
+        bit 1   x(R) = r + N (only if r + N < P: almost never)
+    Then Q = r^-1 * (s * R - z * G).
+    Compact encoding (65 bytes): header (27 + recovery id, + 4 if the public key is compressed) || r || s
+*/
+#[derive(Debug)]
+pub struct RecoverableSignature {
+    pub signature: Signature,
+    pub recovery_id: u8,
+}
+
+impl RecoverableSignature {
+    pub fn new(signature: Signature, recovery_id: u8) -> StdResult<Self> {
+        if recovery_id > MAX_RECOVERY_ID {
+            Err("invalid_recovery_id")?;
+        }
+
+        Ok(Self { signature, recovery_id })
+    }
+
+    pub fn serialize_compact(&self, compression: Compression) -> Vec<u8> {
+        let header = match compression {
+            Compression::Uncompressed => COMPACT_HEADER + self.recovery_id,
+            Compression::Compressed => COMPACT_HEADER + COMPACT_COMPRESSED + self.recovery_id,
+        };
+
+        [[header].as_slice(), &self.signature.serialize_compact()].concat()
+    }
+
+    pub fn deserialize_compact(compact: &[u8]) -> StdResult<(Self, Compression)> {
+        if compact.len() != COMPACT_LENGTH + 1 {
+            Err("invalid_signature_length")?;
+        }
+
+        let (compression, recovery_id) = match compact[0].checked_sub(COMPACT_HEADER) {
+            Some(h) if h <= MAX_RECOVERY_ID => (Compression::Uncompressed, h),
+            Some(h) if h - COMPACT_COMPRESSED <= MAX_RECOVERY_ID => (Compression::Compressed, h - COMPACT_COMPRESSED),
+            _ => Err("invalid_signature_header")?,
+        };
+
+        let signature = Signature::new_from_compact(&compact[1..])?;
+
+        Ok((Self::new(signature, recovery_id)?, compression))
+    }
+
+    pub fn recover_public_key(&self, z: &Integer) -> StdResult<Point> {
+        let Signature { r, s } = &self.signature;
+
+        let in_range = |value: &Integer| *value > 0 && *value < *N;
+        if !in_range(r) || !in_range(s) {
+            Err("invalid_signature")?;
+        }
+
+        let x = r.clone() + Integer::from(self.recovery_id >> 1) * &*N;
+        if x >= *P {
+            Err("public_key_not_recoverable")?;
+        }
+
+        let x = vect_to_array_32(&x.to_digits::<u8>(Order::Msf));
+        let nonce_point = Point::deserialize([[0x02 | (self.recovery_id & 0x01)].as_slice(), &x].concat());
+        if !nonce_point.is_on_curve() {
+            Err("public_key_not_recoverable")?;
+        }
+
+        let r_inv = r.invert_by_modulo(&N);
+        let u = -Integer::from(z * &r_inv);
+        let v = Integer::from(s * &r_inv);
+
+        let public_key = Point::mul_generator_add(&u, &nonce_point, &v);
+        if public_key.is_infinite() {
+            Err("public_key_not_recoverable")?;
+        }
+
+        Ok(public_key)
+    }
+}
+
 #[cfg(test)]
 mod signature_test {
     use rug::{integer::Order, Integer};
@@ -124,6 +432,7 @@ mod signature_test {
     use crate::{
         bitcoin::ecdsa::{G, N, P},
         ecdsa::{field_element::FieldElement, point::Point},
+        keys::key::Key,
         std_lib::{integer_extended::IntegerExtended, vector::hex_string_to_bytes},
     };
 
@@ -332,4 +641,215 @@ mod signature_test {
         assert_eq!(expected_r, sig.r);
         assert_eq!(expected_s, sig.s);
     }
+
+    macro_rules! strict_der_error {
+        ($sig:literal, $err:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let sig = hex_string_to_bytes($sig).unwrap();
+
+                assert_eq!(Signature::check_strict_der(&sig).err().unwrap().to_string(), $err);
+            }
+        };
+    }
+
+    strict_der_error!("3006020101020101", "invalid_signature_length", strict_der_too_short);
+    strict_der_error!(
+        "310602010102010101",
+        "invalid_initial_marker",
+        strict_der_invalid_initial_marker
+    );
+    strict_der_error!(
+        "300702010102010101",
+        "signature_lengths_do_not_match",
+        strict_der_total_length_mismatch
+    );
+    strict_der_error!("300602040102010101", "missing_s_marker", strict_der_r_too_long);
+    strict_der_error!(
+        "300602010102020101",
+        "signature_lengths_do_not_match",
+        strict_der_s_length_mismatch
+    );
+    strict_der_error!("300603010102010101", "invalid_r_marker", strict_der_invalid_r_marker);
+    strict_der_error!("300602000202010101", "invalid_rs_lenght", strict_der_zero_r_length);
+    strict_der_error!(
+        "302402108777777777777777777777777777777702107777777777777777777777777777777701",
+        "negative_r",
+        strict_der_negative_r
+    );
+    strict_der_error!(
+        "30250211007777777777777777777777777777777702107777777777777777777777777777777701",
+        "non_minimal_r",
+        strict_der_non_minimal_r
+    );
+    strict_der_error!("300602010103010101", "invalid_s_marker", strict_der_invalid_s_marker);
+    strict_der_error!(
+        "302402107777777777777777777777777777777702108777777777777777777777777777777701",
+        "negative_s",
+        strict_der_negative_s
+    );
+    strict_der_error!(
+        "30250210777777777777777777777777777777770211007777777777777777777777777777777701",
+        "non_minimal_s",
+        strict_der_non_minimal_s
+    );
+
+    #[test]
+    fn strict_der_valid() {
+        // "00" padding is required when the highest bit is set
+        let sig = hex_string_to_bytes("3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601").unwrap();
+        assert!(Signature::check_strict_der(&sig).is_ok());
+
+        let sig = hex_string_to_bytes("300602010102010101").unwrap();
+        assert!(Signature::check_strict_der(&sig).is_ok());
+    }
+
+    #[test]
+    fn lax_der_accepts_non_canonical_encodings() {
+        let expected_r = Integer::from_hex_str("77777777777777777777777777777777");
+        let expected_s = Integer::from_hex_str("87777777777777777777777777777777");
+
+        let encodings = [
+            // strict
+            "3025021077777777777777777777777777777777021100877777777777777777777777777777777777",
+            // negative s
+            "3024021077777777777777777777777777777777021087777777777777777777777777777777",
+            // padded r, wrong sequence length
+            "300002130000007777777777777777777777777777777777021087777777777777777777777777777777",
+            // long form lengths
+            "30812602811077777777777777777777777777777777028200108777777777777777777777777777777777",
+            // trailing garbage
+            "3024021077777777777777777777777777777777021087777777777777777777777777777777deadbeef",
+        ];
+
+        for encoding in encodings {
+            let der = hex_string_to_bytes(encoding).unwrap();
+            let sig = Signature::new_from_der_lax(&der).unwrap();
+
+            assert_eq!(sig.r, expected_r, "{}", encoding);
+            assert_eq!(sig.s, expected_s, "{}", encoding);
+        }
+    }
+
+    #[test]
+    fn lax_der_overflow_gives_zero_signature() {
+        let der =
+            hex_string_to_bytes("302a02217777777777777777777777777777777777777777777777777777777777777777770201010000")
+                .unwrap();
+        let sig = Signature::new_from_der_lax(&der).unwrap();
+
+        assert_eq!(sig.r, Integer::from(0));
+        assert_eq!(sig.s, Integer::from(0));
+    }
+
+    macro_rules! lax_der_error {
+        ($der:literal, $err:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let der = hex_string_to_bytes($der).unwrap();
+
+                assert_eq!(Signature::new_from_der_lax(&der).err().unwrap().to_string(), $err);
+            }
+        };
+    }
+
+    lax_der_error!("", "invalid_initial_marker", lax_der_empty);
+    lax_der_error!("30", "invalid_signature_length", lax_der_missing_length);
+    lax_der_error!(
+        "3085000000",
+        "invalid_signature_length",
+        lax_der_sequence_length_too_long
+    );
+    lax_der_error!("30060301010201", "invalid_r_marker", lax_der_invalid_r_marker);
+    lax_der_error!("3006020501020101", "invalid_rs_lenght", lax_der_r_out_of_bounds);
+    lax_der_error!("3006020101030101", "invalid_s_marker", lax_der_invalid_s_marker);
+    lax_der_error!("300602010102", "invalid_rs_start", lax_der_missing_s_length);
+
+    // private key 1, BIP137 hash of "Hello World"
+    const Z: &str = "A7AF0BAAD5AE99B97FC69B3A0D1ABCF3EF17F131CC4776E1BC11933EC8550F49";
+    const COMPACT: &str = "2065C7D3CE41F59116C10AA93BFCC2F60B49655112EC380B3873CE34EE666EF0AA5CF23DD5A4B3FBFA5893F1CAE30E8D498BB3C60462DEA98DB3F6D3CFD3E3AF5A";
+
+    #[test]
+    fn sign_and_recover_a_public_key() {
+        let z = Integer::from_hex_str(Z);
+
+        // 6 * G has odd y
+        for secret in [1, 6, 0xDEADBEEF_u32] {
+            let key = Key::new(Integer::from(secret));
+
+            let signature = key.sign_recoverable(z.clone());
+            assert!(key.verify(&z, &signature.signature));
+
+            let public_key = signature.recover_public_key(&z).unwrap();
+            assert_eq!(public_key.serialize(Compression::Compressed), key.public_key_sec());
+
+            // with another recovery id, another public key
+            let another = RecoverableSignature::new(signature.signature.clone(), signature.recovery_id ^ 1).unwrap();
+            let another_public_key = another.recover_public_key(&z).unwrap();
+            assert_ne!(
+                another_public_key.serialize(Compression::Compressed),
+                key.public_key_sec()
+            );
+        }
+    }
+
+    #[test]
+    fn compact_signature() {
+        let compact = hex_string_to_bytes(COMPACT).unwrap();
+
+        let (signature, compression) = RecoverableSignature::deserialize_compact(&compact).unwrap();
+        assert_eq!(signature.recovery_id, 1);
+        assert_eq!(compression, Compression::Compressed);
+        assert_eq!(signature.serialize_compact(Compression::Compressed), compact);
+
+        let public_key = signature.recover_public_key(&Integer::from_hex_str(Z)).unwrap();
+        assert_eq!(
+            public_key.serialize(Compression::Compressed),
+            Key::new(Integer::from(1)).public_key_sec()
+        );
+
+        let key = Key::new(Integer::from(1));
+        assert_eq!(
+            key.sign_recoverable(Integer::from_hex_str(Z))
+                .serialize_compact(Compression::Compressed),
+            compact
+        );
+    }
+
+    #[test]
+    fn invalid_compact_signatures() {
+        let compact = hex_string_to_bytes(COMPACT).unwrap();
+
+        let err = RecoverableSignature::deserialize_compact(&compact[..64]).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_signature_length");
+
+        for header in [26, 35] {
+            let invalid = [[header].as_slice(), &compact[1..]].concat();
+
+            let err = RecoverableSignature::deserialize_compact(&invalid).expect_err("Err");
+            assert_eq!(err.to_string(), "invalid_signature_header");
+        }
+
+        let err = RecoverableSignature::new(Signature::new(Integer::from(1), Integer::from(1)), 4).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_recovery_id");
+
+        let zero_r = RecoverableSignature::new(Signature::new(Integer::from(0), Integer::from(1)), 0).unwrap();
+        let err = zero_r.recover_public_key(&Integer::from(1)).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_signature");
+    }
+
+    #[test]
+    fn low_s() {
+        let half_n = Integer::from(&*N >> 1);
+
+        assert!(Signature::new(Integer::from(1), half_n.clone()).is_low_s());
+        assert!(!Signature::new(Integer::from(1), half_n + 1).is_low_s());
+
+        let der =
+            hex_string_to_bytes("304402200060558477337b9022e70534f1fea71a318caf836812465a2509931c5e7c4987022078ec32bd50ac9e03a349ba953dfd9fe1c8d2dd8bdb1d38ddca844d3d5c78c118").unwrap();
+        assert!(Signature::new_from_der(der).unwrap().is_low_s());
+
+        let der = hex_string_to_bytes("3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec").unwrap();
+        assert!(!Signature::new_from_der(der).unwrap().is_low_s());
+    }
 }
@@ -13,12 +13,17 @@ pub mod bitcoin;
 pub mod block;
 pub mod chain;
 pub mod cli;
+pub mod descriptor;
 pub mod ecdsa;
+pub mod encryption;
 pub mod flags;
 pub mod hashing;
 pub mod keys;
 pub mod merkle;
+pub mod message;
+pub mod miniscript;
 pub mod network;
+pub mod psbt;
 pub mod scripting;
 pub mod std_lib;
 pub mod transaction;
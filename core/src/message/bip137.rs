@@ -0,0 +1,227 @@
+/*
+    Legacy signed messages ("Bitcoin Signed Message", BIP137):
+        hash        hash256(varstr("Bitcoin Signed Message:\n") || varstr(message))
+        signature   base64(header || r || s), header = 27 + recovery id (0 to 3) + address type:
+                        0 P2PKH (uncompressed key), 4 P2PKH, 8 P2SH-P2WPKH, 12 P2WPKH
+    There is no public key in the signature: it is recovered from it (`RecoverableSignature`), then its address is
+    compared with the signer's one.
+*/
+
+use rug::{integer::Order, Integer};
+
+use crate::{
+    ecdsa::point::Point,
+    flags::{compression::Compression, network::Network},
+    hashing::{hash160::hash160, hash256::Hash256},
+    keys::{
+        key::Key,
+        signature::{RecoverableSignature, Signature},
+    },
+    scripting::standard::{p2pkh_script, p2sh_script, p2wpkh_script},
+    std_lib::{
+        base64::{base64_decode, base64_encode},
+        std_result::StdResult,
+        varint::encode,
+    },
+    transaction::script::Script,
+    wallet::address::script_pub_key,
+};
+
+const MESSAGE_MAGIC: &str = "Bitcoin Signed Message:\n";
+const HEADER_BASE: u8 = 27;
+const SIGNATURE_LENGTH: usize = 65;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureType {
+    P2pkhUncompressed = 0,
+    P2pkh = 4,
+    P2shP2wpkh = 8,
+    P2wpkh = 12,
+}
+
+impl SignatureType {
+    fn from_header(header: u8) -> StdResult<Self> {
+        match header.checked_sub(HEADER_BASE).map(|h| h & !0x03) {
+            Some(0) => Ok(Self::P2pkhUncompressed),
+            Some(4) => Ok(Self::P2pkh),
+            Some(8) => Ok(Self::P2shP2wpkh),
+            Some(12) => Ok(Self::P2wpkh),
+            _ => Err("invalid_signature_header")?,
+        }
+    }
+
+    fn compression(&self) -> Compression {
+        match self {
+            Self::P2pkhUncompressed => Compression::Uncompressed,
+            _ => Compression::Compressed,
+        }
+    }
+
+    fn script_pub_key(&self, public_key: &Point) -> Script {
+        let key_hash = public_key.hash160(self.compression());
+
+        let script = match self {
+            Self::P2pkhUncompressed | Self::P2pkh => p2pkh_script(&key_hash),
+            Self::P2shP2wpkh => p2sh_script(&hash160(&Script::new_from_script_lang(&p2wpkh_script(&key_hash)).raw)),
+            Self::P2wpkh => p2wpkh_script(&key_hash),
+        };
+
+        Script::new_from_script_lang(&script)
+    }
+}
+
+pub fn message_hash(message: &[u8]) -> Integer {
+    let data = [
+        encode(MESSAGE_MAGIC.len() as u64).as_slice(),
+        MESSAGE_MAGIC.as_bytes(),
+        &encode(message.len() as u64),
+        message,
+    ]
+    .concat();
+
+    Integer::from_digits(&Hash256::calc(&data).0, Order::Msf)
+}
+
+pub fn sign(key: &Key, message: &[u8], signature_type: SignatureType) -> String {
+    let signature = key.sign_recoverable(message_hash(message));
+
+    let header = HEADER_BASE + signature_type as u8 + signature.recovery_id;
+    let serialized = [[header].as_slice(), &signature.signature.serialize_compact()].concat();
+
+    base64_encode(&serialized)
+}
+
+// Ok(false) if the signature is well formed, but not by the key of `address`
+pub fn verify(address: &str, message: &[u8], signature: &str, network: Network) -> StdResult<bool> {
+    let expected = script_pub_key(address, network)?;
+
+    let decoded = base64_decode(signature)?;
+    if decoded.len() != SIGNATURE_LENGTH {
+        Err("invalid_signature_length")?;
+    }
+
+    let signature_type = SignatureType::from_header(decoded[0])?;
+    let recovery_id = (decoded[0] - HEADER_BASE) & 0x03;
+    let signature = RecoverableSignature::new(Signature::new_from_compact(&decoded[1..])?, recovery_id)?;
+
+    let Ok(public_key) = signature.recover_public_key(&message_hash(message)) else {
+        return Ok(false);
+    };
+
+    Ok(signature_type.script_pub_key(&public_key).raw == expected.raw)
+}
+
+#[cfg(test)]
+mod bip137_test {
+    use super::*;
+    use crate::std_lib::vector::bytes_to_hex_string;
+
+    // private key 1
+    const WIF: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+    const SIGNATURE: &str = "GXH085B9ZEWwQqpO/zC9gtJZVES7DgLOHPONO5mbvCqXPI91aSz+/pYk/HK4w6NSYuzxgRi3qmNs/bTz9Pjr1o=";
+
+    macro_rules! sign_and_verify {
+        ($signature_type:expr, $address:expr, $signature:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let (key, _, _) = Key::from_wif(WIF).unwrap();
+
+                let signature = sign(&key, b"Hello World", $signature_type);
+
+                assert_eq!(signature, $signature);
+                assert!(verify($address, b"Hello World", &signature, Network::Mainnet).unwrap());
+                assert!(!verify($address, b"Hello World!", &signature, Network::Mainnet).unwrap());
+            }
+        };
+    }
+
+    sign_and_verify!(
+        SignatureType::P2pkhUncompressed,
+        "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm",
+        format!("H{}", SIGNATURE),
+        p2pkh_uncompressed
+    );
+    sign_and_verify!(
+        SignatureType::P2pkh,
+        "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+        format!("I{}", SIGNATURE),
+        p2pkh
+    );
+    sign_and_verify!(
+        SignatureType::P2shP2wpkh,
+        "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN",
+        format!("J{}", SIGNATURE),
+        p2sh_p2wpkh
+    );
+    sign_and_verify!(
+        SignatureType::P2wpkh,
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        format!("K{}", SIGNATURE),
+        p2wpkh
+    );
+
+    #[test]
+    fn hash_a_message() {
+        assert_eq!(
+            bytes_to_hex_string(&message_hash(b"Hello World").to_digits::<u8>(Order::Msf)),
+            "A7AF0BAAD5AE99B97FC69B3A0D1ABCF3EF17F131CC4776E1BC11933EC8550F49"
+        );
+    }
+
+    // 6 * G has odd y: another recovery id
+    #[test]
+    fn sign_with_a_key_with_odd_y() {
+        let key = Key::new(Integer::from(6));
+
+        let signature = sign(&key, b"", SignatureType::P2pkh);
+
+        assert_eq!(
+            signature,
+            "H9VspaGWeaYFISpGhyyjDv4r74qlLSopiiBUw/bwFoEPK+xGdyRN4NZ0GSA+cEMjavQmZpkX/D15OdW2fzN0NjQ="
+        );
+        assert!(verify("1Cf2hs39Woi61YNkYGUAcohL2K2q4pawBq", b"", &signature, Network::Mainnet).unwrap());
+    }
+
+    #[test]
+    fn another_address() {
+        let signature = format!("I{}", SIGNATURE);
+
+        // the address of private key 6
+        assert!(!verify(
+            "1Cf2hs39Woi61YNkYGUAcohL2K2q4pawBq",
+            b"Hello World",
+            &signature,
+            Network::Mainnet
+        )
+        .unwrap());
+        // the right key, but another address type than in the header
+        assert!(!verify(
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            b"Hello World",
+            &signature,
+            Network::Mainnet
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn invalid_signatures() {
+        let err = verify(
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            b"Hello World",
+            "SGVsbG8=",
+            Network::Mainnet,
+        )
+        .expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_signature_length");
+
+        let err = verify(
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            b"Hello World",
+            &format!("A{}", SIGNATURE),
+            Network::Mainnet,
+        )
+        .expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_signature_header");
+    }
+}
@@ -0,0 +1,458 @@
+/*
+    Generic signed messages (BIP322): the signature is a virtual transaction, spending a virtual output locked by the
+    ScriptPubKey of the address.
+        message hash    tagged_hash("BIP0322-signed-message", message)
+        to_spend        version 0, locktime 0
+                        input: 000...000:0xFFFFFFFF, ScriptSig OP_0 <message hash>, sequence 0
+                        output: 0 satoshis to the ScriptPubKey of the address
+        to_sign         version 0, locktime 0
+                        input: to_spend:0, sequence 0, with the ScriptSig and the witness of the signature
+                        output: 0 satoshis to OP_RETURN
+    A simple signature is the witness stack of to_sign, a full one the whole to_sign (both in base64).
+    Supported: P2WPKH and P2TR key path (simple and full), P2SH-P2WPKH and P2PKH (full only, they need a ScriptSig).
+*/
+
+use rug::Integer;
+
+use crate::{
+    descriptor::{key_expression::verify_public_key, taproot::tweak_private_key},
+    ecdsa::point::Point,
+    flags::{network::Network, script_flags::ScriptFlags, sighash::SigHash},
+    hashing::{hash160::hash160, tagged_hash::tagged_hash},
+    keys::{key::Key, schnorr, signature::Signature},
+    scripting::standard::p2pkh_script,
+    std_lib::{
+        base64::{base64_decode, base64_encode},
+        rand::EntropySource,
+        std_result::StdResult,
+    },
+    transaction::{script::Script, tx::Tx, tx_in::TxIn, tx_out::TxOut},
+    validate::tx::verify_input_script,
+    wallet::address::script_pub_key,
+};
+
+const OP_RETURN: u8 = 0x6A;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureFormat {
+    Simple,
+    Full,
+}
+
+enum AddressType {
+    P2pkh(Vec<u8>),
+    P2shP2wpkh(Vec<u8>),
+    P2wpkh(Vec<u8>),
+    P2tr(Vec<u8>),
+}
+
+impl AddressType {
+    // P2SH is assumed to wrap a P2WPKH
+    fn new(script_pub_key: &Script) -> StdResult<Self> {
+        let raw = script_pub_key.raw.as_slice();
+
+        let address_type = match raw {
+            [0x76, 0xA9, 0x14, key_hash @ .., 0x88, 0xAC] if raw.len() == 25 => Self::P2pkh(key_hash.to_vec()),
+            [0xA9, 0x14, script_hash @ .., 0x87] if raw.len() == 23 => Self::P2shP2wpkh(script_hash.to_vec()),
+            [0x00, 0x14, key_hash @ ..] if raw.len() == 22 => Self::P2wpkh(key_hash.to_vec()),
+            [0x51, 0x20, output_key @ ..] if raw.len() == 34 => Self::P2tr(output_key.to_vec()),
+            _ => Err("unsupported_address_type")?,
+        };
+
+        Ok(address_type)
+    }
+}
+
+pub fn message_hash(message: &[u8]) -> Vec<u8> {
+    tagged_hash("BIP0322-signed-message", message)
+}
+
+pub fn to_spend(script_pub_key: &Script, message: &[u8], network: Network) -> Tx {
+    let script_sig = Script::new_from_raw([[0x00, 0x20].as_slice(), &message_hash(message)].concat());
+
+    let mut tx = Tx::new(network);
+    tx.set_version(0);
+    tx.add_input(TxIn::new(Integer::from(0), 0xFFFFFFFF, script_sig, 0, network));
+    tx.add_output(TxOut::new(0, script_pub_key.clone()));
+
+    tx
+}
+
+// Unsigned
+pub fn to_sign(to_spend: &Tx, network: Network) -> Tx {
+    let mut tx = Tx::new(network);
+    tx.set_version(0);
+    tx.add_input(TxIn::new(to_spend.id_as_integer(), 0, Script::new_empty(), 0, network));
+    tx.add_output(TxOut::new(0, Script::new_from_raw(vec![OP_RETURN])));
+
+    tx
+}
+
+// `entropy` is only used by P2TR (BIP340 auxiliary randomness)
+pub fn sign(
+    key: &Key,
+    address: &str,
+    message: &[u8],
+    format: SignatureFormat,
+    network: Network,
+    entropy: &mut dyn EntropySource,
+) -> StdResult<String> {
+    let script_pub_key = script_pub_key(address, network)?;
+    let to_spend = to_spend(&script_pub_key, message, network);
+    let mut to_sign = to_sign(&to_spend, network);
+
+    let public_key = key.public_key_sec();
+    let key_hash = hash160(&public_key);
+
+    match AddressType::new(&script_pub_key)? {
+        AddressType::P2pkh(hash) if hash == key_hash => {
+            let z = to_sign.hash_signature(0, script_pub_key.clone());
+            let signature = [key.sign(z).der(), vec![SigHash::All as u8]].concat();
+
+            to_sign.substitute_script(0, Script::new_from_raw([push(&signature), push(&public_key)].concat()));
+        }
+        AddressType::P2shP2wpkh(hash) if hash == hash160(&p2wpkh_redeem_script(&key_hash)) => {
+            to_sign.substitute_script(0, Script::new_from_raw(push(&p2wpkh_redeem_script(&key_hash))));
+
+            let witnesses = witness_v0(&to_sign, key, &key_hash);
+            to_sign.set_witnesses(0, witnesses);
+        }
+        AddressType::P2wpkh(hash) if hash == key_hash => {
+            let witnesses = witness_v0(&to_sign, key, &key_hash);
+            to_sign.set_witnesses(0, witnesses);
+        }
+        AddressType::P2tr(output_key) => {
+            let tweaked = tweak_private_key(key, None)?;
+            if tweaked.x_only_public_key() != output_key {
+                Err("key_does_not_match_address")?;
+            }
+
+            let sighash = to_sign.hash_signature_taproot(0, &[to_spend.outputs(0).clone()], SigHash::Default as u8)?;
+            let mut aux_rand = [0u8; 32];
+            entropy.fill_bytes(&mut aux_rand)?;
+
+            let signature = tweaked.sign_schnorr(&sighash, &aux_rand)?;
+            to_sign.set_witnesses(0, vec![signature.to_vec()]);
+        }
+        _ => Err("key_does_not_match_address")?,
+    }
+
+    let to_sign_input = to_sign.input(0)?;
+    let serialized = match format {
+        SignatureFormat::Simple if !to_sign_input.script_sig.raw.is_empty() => Err("simple_signature_with_script_sig")?,
+        SignatureFormat::Simple => to_sign_input.serialize_witnesses(),
+        SignatureFormat::Full => to_sign.serialize_with_witnesses(),
+    };
+
+    Ok(base64_encode(&serialized))
+}
+
+/*
+    Ok(false) if the signature is well formed, but not valid for `address`.
+    The signature format is detected: a simple signature decodes as a witness stack, a full one as a transaction.
+*/
+pub fn verify(address: &str, message: &[u8], signature: &str, network: Network) -> StdResult<bool> {
+    let script_pub_key = script_pub_key(address, network)?;
+    let address_type = AddressType::new(&script_pub_key)?;
+    let to_spend = to_spend(&script_pub_key, message, network);
+
+    let decoded = base64_decode(signature)?;
+    let to_sign = match TxIn::deserialize_witnesses(&decoded, 0) {
+        Ok((witnesses, cursor)) if cursor == decoded.len() => {
+            let mut to_sign = to_sign(&to_spend, network);
+            to_sign.set_witnesses(0, witnesses);
+
+            to_sign
+        }
+        _ => {
+            let to_sign = Tx::deserialize(&decoded, network)?;
+            if !is_to_sign(&to_sign, &to_spend)? {
+                return Ok(false);
+            }
+
+            to_sign
+        }
+    };
+
+    let input = to_sign.input(0)?;
+    let script_sig = input.script_sig.raw.as_slice();
+    let witnesses = &input.witnesses;
+
+    let valid = match address_type {
+        AddressType::P2pkh(_) => {
+            witnesses.is_empty()
+                && verify_input_script(&to_sign, 0, &[to_spend.outputs(0).clone()], ScriptFlags::STANDARD)
+                    .unwrap_or(false)
+        }
+        AddressType::P2shP2wpkh(script_hash) => match script_sig {
+            [0x16, redeem_script @ ..] if redeem_script.len() == 22 && hash160(redeem_script) == script_hash => {
+                verify_witness_v0(&to_sign, witnesses, &redeem_script[2..])
+            }
+            _ => false,
+        },
+        AddressType::P2wpkh(key_hash) => script_sig.is_empty() && verify_witness_v0(&to_sign, witnesses, &key_hash),
+        AddressType::P2tr(output_key) => {
+            script_sig.is_empty() && verify_witness_v1(&to_sign, witnesses, &output_key, to_spend.outputs(0))?
+        }
+    };
+
+    Ok(valid)
+}
+
+// The full signature spends to_spend, into a single OP_RETURN
+fn is_to_sign(to_sign: &Tx, to_spend: &Tx) -> StdResult<bool> {
+    if to_sign.input_len() != 1 || to_sign.output_len() != 1 {
+        return Ok(false);
+    }
+
+    let input = to_sign.input(0)?;
+    let output = to_sign.output(0)?;
+
+    Ok(input.previous_transaction_id == to_spend.id_as_integer()
+        && input.previous_transaction_index == 0
+        && output.amount == 0
+        && output.script_pub_key.raw == [OP_RETURN])
+}
+
+fn push(data: &[u8]) -> Vec<u8> {
+    [[data.len() as u8].as_slice(), data].concat()
+}
+
+fn p2wpkh_redeem_script(key_hash: &[u8]) -> Vec<u8> {
+    [[0x00, 0x14].as_slice(), key_hash].concat()
+}
+
+// BIP143: the script code of P2WPKH is the P2PKH script of the key hash, the spent amount is 0
+fn witness_v0(to_sign: &Tx, key: &Key, key_hash: &[u8]) -> Vec<Vec<u8>> {
+    let script_code = Script::new_from_script_lang(&p2pkh_script(key_hash));
+    let z = to_sign.hash_signature_witness_v0(0, &script_code, 0);
+
+    let signature = [key.sign(z).der(), vec![SigHash::All as u8]].concat();
+
+    vec![signature, key.public_key_sec()]
+}
+
+fn verify_witness_v0(to_sign: &Tx, witnesses: &[Vec<u8>], key_hash: &[u8]) -> bool {
+    let [signature, public_key] = witnesses else {
+        return false;
+    };
+
+    if public_key.len() != 33 || hash160(public_key) != key_hash || verify_public_key(public_key).is_err() {
+        return false;
+    }
+
+    let Some((&hash_type, der)) = signature.split_last() else {
+        return false;
+    };
+
+    let Ok(signature) = Signature::new_from_der(der.to_vec()) else {
+        return false;
+    };
+
+    let script_code = Script::new_from_script_lang(&p2pkh_script(key_hash));
+    let z = to_sign.hash_signature_witness_v0(0, &script_code, 0);
+
+    hash_type == SigHash::All as u8 && Key::verify_signature(&Point::deserialize(public_key.clone()), &z, &signature)
+}
+
+// BIP341 key path: a 64 bytes signature (SIGHASH_DEFAULT) or 65 bytes with an explicit hash type
+fn verify_witness_v1(to_sign: &Tx, witnesses: &[Vec<u8>], output_key: &[u8], spent_output: &TxOut) -> StdResult<bool> {
+    let [signature] = witnesses else {
+        return Ok(false);
+    };
+
+    let hash_type = match signature.len() {
+        64 => SigHash::Default as u8,
+        65 if signature[64] == SigHash::All as u8 => signature[64],
+        _ => return Ok(false),
+    };
+
+    let sighash = to_sign.hash_signature_taproot(0, &[spent_output.clone()], hash_type)?;
+
+    Ok(schnorr::verify(output_key, &sighash, &signature[..64]))
+}
+
+#[cfg(test)]
+mod bip322_test {
+    use super::*;
+    use crate::std_lib::vector::bytes_to_hex_string;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki#test-vectors
+    const WIF: &str = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+    const P2WPKH_ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const P2TR_ADDRESS: &str = "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3";
+
+    struct FixedEntropy(u8);
+
+    impl EntropySource for FixedEntropy {
+        fn fill_bytes(&mut self, dest: &mut [u8]) -> StdResult<()> {
+            dest.fill(self.0);
+            Ok(())
+        }
+    }
+
+    fn key() -> Key {
+        Key::from_wif(WIF).unwrap().0
+    }
+
+    #[test]
+    fn hash_a_message() {
+        assert_eq!(
+            bytes_to_hex_string(&message_hash(b"")),
+            "C90C269C4F8FCBE6880F72A721DDFBF1914268A794CBB21CFAFEE13770AE19F1"
+        );
+        assert_eq!(
+            bytes_to_hex_string(&message_hash(b"Hello World")),
+            "F0EB03B1A75AC6D9847F55C624A99169B5DCCBA2A31F5B23BEA77BA270DE0A7A"
+        );
+    }
+
+    #[test]
+    fn to_spend_and_to_sign() {
+        let script_pub_key = script_pub_key(P2WPKH_ADDRESS, Network::Mainnet).unwrap();
+
+        let to_spend_empty = to_spend(&script_pub_key, b"", Network::Mainnet);
+        let to_spend_hello = to_spend(&script_pub_key, b"Hello World", Network::Mainnet);
+        assert_eq!(
+            to_spend_empty.id(),
+            "C5680AA69BB8D860BF82D4E9CD3504B55DDE018DE765A91BB566283C545A99A7"
+        );
+        assert_eq!(
+            to_spend_hello.id(),
+            "B79D196740AD5217771C1098FC4A4B51E0535C32236C71F1EA4D61A2D603352B"
+        );
+
+        assert_eq!(
+            to_sign(&to_spend_empty, Network::Mainnet).id(),
+            "1E9654E951A5BA44C8604C4DE6C67FD78A27E81DCADCFE1EDF638BA3AAEBAED6"
+        );
+        assert_eq!(
+            to_sign(&to_spend_hello, Network::Mainnet).id(),
+            "88737AE86F2077145F93CC4B153AE9A1CB8D56AFA511988C149C5C8C9D93BDDF"
+        );
+    }
+
+    macro_rules! verify {
+        ($address:expr, $message:expr, $signature:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                assert!(verify($address, $message, $signature, Network::Mainnet).unwrap());
+                assert!(!verify($address, b"another message", $signature, Network::Mainnet).unwrap());
+            }
+        };
+    }
+
+    verify!(
+        P2WPKH_ADDRESS,
+        b"",
+        "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=",
+        p2wpkh_empty_message
+    );
+    verify!(
+        P2WPKH_ADDRESS,
+        b"Hello World",
+        "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=",
+        p2wpkh_hello_world
+    );
+    verify!(
+        P2TR_ADDRESS,
+        b"Hello World",
+        "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==",
+        p2tr_hello_world
+    );
+
+    macro_rules! sign_and_verify {
+        ($address:expr, $format:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let signature = sign(
+                    &key(),
+                    $address,
+                    b"Hello World",
+                    $format,
+                    Network::Mainnet,
+                    &mut FixedEntropy(0x2A),
+                )
+                .unwrap();
+
+                assert!(verify($address, b"Hello World", &signature, Network::Mainnet).unwrap());
+                assert!(!verify($address, b"Hello World!", &signature, Network::Mainnet).unwrap());
+            }
+        };
+    }
+
+    sign_and_verify!(P2WPKH_ADDRESS, SignatureFormat::Simple, sign_p2wpkh_simple);
+    sign_and_verify!(P2WPKH_ADDRESS, SignatureFormat::Full, sign_p2wpkh_full);
+    sign_and_verify!(P2TR_ADDRESS, SignatureFormat::Simple, sign_p2tr_simple);
+    sign_and_verify!(P2TR_ADDRESS, SignatureFormat::Full, sign_p2tr_full);
+    sign_and_verify!(
+        "37qyp7jQAzqb2rCBpMvVtLDuuzKAUCVnJb",
+        SignatureFormat::Full,
+        sign_p2sh_p2wpkh_full
+    );
+    sign_and_verify!(
+        "14vV3aCHBeStb5bkenkNHbe2YAFinYdXgc",
+        SignatureFormat::Full,
+        sign_p2pkh_full
+    );
+
+    #[test]
+    fn simple_signature_needs_a_witness_only() {
+        let err = sign(
+            &key(),
+            "14vV3aCHBeStb5bkenkNHbe2YAFinYdXgc",
+            b"Hello World",
+            SignatureFormat::Simple,
+            Network::Mainnet,
+            &mut FixedEntropy(0),
+        )
+        .expect_err("Err");
+
+        assert_eq!(err.to_string(), "simple_signature_with_script_sig");
+    }
+
+    #[test]
+    fn another_key() {
+        let another = Key::new(Integer::from(1));
+
+        for address in [P2WPKH_ADDRESS, P2TR_ADDRESS] {
+            let err = sign(
+                &another,
+                address,
+                b"Hello World",
+                SignatureFormat::Simple,
+                Network::Mainnet,
+                &mut FixedEntropy(0),
+            )
+            .expect_err("Err");
+
+            assert_eq!(err.to_string(), "key_does_not_match_address");
+        }
+
+        // signed by key 1 for its own address, verified for another one
+        let signature = sign(
+            &another,
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            b"Hello World",
+            SignatureFormat::Simple,
+            Network::Mainnet,
+            &mut FixedEntropy(0),
+        )
+        .unwrap();
+        assert!(!verify(P2WPKH_ADDRESS, b"Hello World", &signature, Network::Mainnet).unwrap());
+    }
+
+    #[test]
+    fn unsupported_address() {
+        // P2WSH
+        let err = verify(
+            "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+            b"Hello World",
+            "AA==",
+            Network::Mainnet,
+        )
+        .expect_err("Err");
+
+        assert_eq!(err.to_string(), "unsupported_address_type");
+    }
+}
@@ -0,0 +1,2 @@
+pub mod bip137;
+pub mod bip322;
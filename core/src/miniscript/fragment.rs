@@ -0,0 +1,693 @@
+/*
+    Miniscript (BIP379): P2WSH scripts written as expressions, that can be type checked, analysed and satisfied.
+
+    Fragments and their scripts:
+        0                       OP_0
+        1                       OP_1
+        pk_k(KEY)               <KEY>
+        pk_h(KEY)               OP_DUP OP_HASH160 <HASH160(KEY)> OP_EQUALVERIFY
+        older(n)                <n> OP_CHECKSEQUENCEVERIFY
+        after(n)                <n> OP_CHECKLOCKTIMEVERIFY
+        sha256(h)               OP_SIZE <32> OP_EQUALVERIFY OP_SHA256 <h> OP_EQUAL
+        hash256(h)              OP_SIZE <32> OP_EQUALVERIFY OP_HASH256 <h> OP_EQUAL
+        ripemd160(h)            OP_SIZE <32> OP_EQUALVERIFY OP_RIPEMD160 <h> OP_EQUAL
+        hash160(h)              OP_SIZE <32> OP_EQUALVERIFY OP_HASH160 <h> OP_EQUAL
+        andor(X,Y,Z)            [X] OP_NOTIF [Z] OP_ELSE [Y] OP_ENDIF
+        and_v(X,Y)              [X] [Y]
+        and_b(X,Y)              [X] [Y] OP_BOOLAND
+        or_b(X,Z)               [X] [Z] OP_BOOLOR
+        or_c(X,Z)               [X] OP_NOTIF [Z] OP_ENDIF
+        or_d(X,Z)               [X] OP_IFDUP OP_NOTIF [Z] OP_ENDIF
+        or_i(X,Z)               OP_IF [X] OP_ELSE [Z] OP_ENDIF
+        thresh(k,X1,...,Xn)     [X1] [X2] OP_ADD ... [Xn] OP_ADD <k> OP_EQUAL
+        multi(k,KEY1,...,KEYn)  <k> <KEY1> ... <KEYn> <n> OP_CHECKMULTISIG
+
+    Wrappers, `w:X` (more than one as `vc:X`):
+        a:X     OP_TOALTSTACK [X] OP_FROMALTSTACK
+        s:X     OP_SWAP [X]
+        c:X     [X] OP_CHECKSIG
+        d:X     OP_DUP OP_IF [X] OP_ENDIF
+        v:X     [X] OP_VERIFY (or the VERIFY version of the last opcode of X)
+        j:X     OP_SIZE OP_0NOTEQUAL OP_IF [X] OP_ENDIF
+        n:X     [X] OP_0NOTEQUAL
+
+    Shorthands: pk(KEY) = c:pk_k(KEY), pkh(KEY) = c:pk_h(KEY), and_n(X,Y) = andor(X,Y,0),
+    t:X = and_v(X,1), l:X = or_i(0,X), u:X = or_i(X,0).
+
+    KEY is a hex compressed public key, a miniscript must be of type B (see `types`).
+*/
+
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    descriptor::{
+        key_expression::verify_public_key,
+        output_descriptor::{function, split_arguments},
+    },
+    hashing::{hash160::hash160, hash256::Hash256, ripemd160::ripemd160, sha256::sha256},
+    scripting::{
+        opcode::{
+            OpCode, OP_0, OP_0NOTEQUAL, OP_1, OP_ADD, OP_BOOLAND, OP_BOOLOR, OP_CHECKLOCKTIMEVERIFY, OP_CHECKMULTISIG,
+            OP_CHECKMULTISIGVERIFY, OP_CHECKSEQUENCEVERIFY, OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_DUP, OP_ELSE, OP_ENDIF,
+            OP_EQUAL, OP_EQUALVERIFY, OP_FROMALTSTACK, OP_HASH160, OP_HASH256, OP_IF, OP_IFDUP, OP_NOTIF, OP_NUMEQUAL,
+            OP_NUMEQUALVERIFY, OP_RIPEMD160, OP_SHA256, OP_SIZE, OP_SWAP, OP_TOALTSTACK, OP_VERIFY,
+        },
+        script_lang::ScriptLang,
+        script_num::ScriptNum,
+        standard::p2wsh_script,
+        token::Token,
+    },
+    std_lib::{
+        std_result::StdResult,
+        vector::{bytes_to_hex_string, hex_string_to_bytes},
+    },
+};
+
+use super::{
+    policy::{Policy, SpendingPath},
+    types::{type_of, Base, Type},
+};
+
+// Standardness limit of P2WSH witness scripts
+pub const MAX_SCRIPT_SIZE: usize = 3600;
+
+const FRAGMENTS: [&str; 21] = [
+    "pk_k",
+    "pk_h",
+    "pk",
+    "pkh",
+    "older",
+    "after",
+    "sha256",
+    "hash256",
+    "ripemd160",
+    "hash160",
+    "andor",
+    "and_v",
+    "and_b",
+    "and_n",
+    "or_b",
+    "or_c",
+    "or_d",
+    "or_i",
+    "thresh",
+    "multi",
+    "multi_a",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashType {
+    Sha256,
+    Hash256,
+    Ripemd160,
+    Hash160,
+}
+
+impl HashType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(HashType::Sha256),
+            "hash256" => Some(HashType::Hash256),
+            "ripemd160" => Some(HashType::Ripemd160),
+            "hash160" => Some(HashType::Hash160),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            HashType::Sha256 => "sha256",
+            HashType::Hash256 => "hash256",
+            HashType::Ripemd160 => "ripemd160",
+            HashType::Hash160 => "hash160",
+        }
+    }
+
+    pub fn hash(&self, preimage: &[u8]) -> Vec<u8> {
+        match self {
+            HashType::Sha256 => sha256(preimage),
+            HashType::Hash256 => Hash256::calc(preimage).0.to_vec(),
+            HashType::Ripemd160 => ripemd160(preimage),
+            HashType::Hash160 => hash160(preimage),
+        }
+    }
+
+    // of the digest
+    pub fn length(&self) -> usize {
+        match self {
+            HashType::Sha256 | HashType::Hash256 => 32,
+            HashType::Ripemd160 | HashType::Hash160 => 20,
+        }
+    }
+
+    fn op_code(&self) -> OpCode {
+        match self {
+            HashType::Sha256 => OP_SHA256,
+            HashType::Hash256 => OP_HASH256,
+            HashType::Ripemd160 => OP_RIPEMD160,
+            HashType::Hash160 => OP_HASH160,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fragment {
+    False,
+    True,
+    PkK(Vec<u8>),
+    PkH(Vec<u8>),
+    Older(u32),
+    After(u32),
+    Hash(HashType, Vec<u8>),
+    AndOr(Box<Fragment>, Box<Fragment>, Box<Fragment>),
+    AndV(Box<Fragment>, Box<Fragment>),
+    AndB(Box<Fragment>, Box<Fragment>),
+    OrB(Box<Fragment>, Box<Fragment>),
+    OrC(Box<Fragment>, Box<Fragment>),
+    OrD(Box<Fragment>, Box<Fragment>),
+    OrI(Box<Fragment>, Box<Fragment>),
+    Thresh(usize, Vec<Fragment>),
+    Multi(usize, Vec<Vec<u8>>),
+    // wrappers
+    Alt(Box<Fragment>),
+    Swap(Box<Fragment>),
+    Check(Box<Fragment>),
+    DupIf(Box<Fragment>),
+    Verify(Box<Fragment>),
+    NonZero(Box<Fragment>),
+    ZeroNotEqual(Box<Fragment>),
+}
+
+impl Fragment {
+    pub fn parse(s: &str) -> StdResult<Fragment> {
+        let (wrappers, expression) = match s.split_once(':') {
+            Some((wrappers, expression)) if !wrappers.contains('(') => {
+                if wrappers.is_empty() {
+                    Err("invalid_miniscript_wrapper")?;
+                }
+
+                (wrappers, expression)
+            }
+            _ => ("", s),
+        };
+
+        let mut fragment = Self::parse_unwrapped(expression)?;
+
+        // the innermost wrapper is the last one
+        for wrapper in wrappers.chars().rev() {
+            let inner = Box::new(fragment);
+
+            fragment = match wrapper {
+                'a' => Fragment::Alt(inner),
+                's' => Fragment::Swap(inner),
+                'c' => Fragment::Check(inner),
+                'd' => Fragment::DupIf(inner),
+                'v' => Fragment::Verify(inner),
+                'j' => Fragment::NonZero(inner),
+                'n' => Fragment::ZeroNotEqual(inner),
+                't' => Fragment::AndV(inner, Box::new(Fragment::True)),
+                'l' => Fragment::OrI(Box::new(Fragment::False), inner),
+                'u' => Fragment::OrI(inner, Box::new(Fragment::False)),
+                _ => Err("invalid_miniscript_wrapper")?,
+            };
+        }
+
+        Ok(fragment)
+    }
+
+    fn parse_unwrapped(s: &str) -> StdResult<Fragment> {
+        match s {
+            "0" => return Ok(Fragment::False),
+            "1" => return Ok(Fragment::True),
+            _ => {}
+        }
+
+        let (name, arguments) = function(s).map_err(|_| "invalid_miniscript")?;
+        let arguments = split_arguments(arguments);
+
+        if let Some(hash_type) = HashType::from_name(name) {
+            return match arguments.as_slice() {
+                [hash] => Ok(Fragment::Hash(hash_type, parse_hash(hash, hash_type)?)),
+                _ => Err("invalid_miniscript")?,
+            };
+        }
+
+        let fragment = match (name, arguments.as_slice()) {
+            ("pk_k", [key]) => Fragment::PkK(parse_key(key)?),
+            ("pk_h", [key]) => Fragment::PkH(parse_key(key)?),
+            ("pk", [key]) => Fragment::Check(Box::new(Fragment::PkK(parse_key(key)?))),
+            ("pkh", [key]) => Fragment::Check(Box::new(Fragment::PkH(parse_key(key)?))),
+            ("older", [n]) => Fragment::Older(n.parse().map_err(|_| "invalid_timelock")?),
+            ("after", [n]) => Fragment::After(n.parse().map_err(|_| "invalid_timelock")?),
+            ("andor", [x, y, z]) => Fragment::AndOr(parse_boxed(x)?, parse_boxed(y)?, parse_boxed(z)?),
+            ("and_n", [x, y]) => Fragment::AndOr(parse_boxed(x)?, parse_boxed(y)?, Box::new(Fragment::False)),
+            ("and_v", [x, y]) => Fragment::AndV(parse_boxed(x)?, parse_boxed(y)?),
+            ("and_b", [x, y]) => Fragment::AndB(parse_boxed(x)?, parse_boxed(y)?),
+            ("or_b", [x, z]) => Fragment::OrB(parse_boxed(x)?, parse_boxed(z)?),
+            ("or_c", [x, z]) => Fragment::OrC(parse_boxed(x)?, parse_boxed(z)?),
+            ("or_d", [x, z]) => Fragment::OrD(parse_boxed(x)?, parse_boxed(z)?),
+            ("or_i", [x, z]) => Fragment::OrI(parse_boxed(x)?, parse_boxed(z)?),
+            ("thresh", [k, subs @ ..]) => {
+                let k = k.parse().map_err(|_| "invalid_thresh_threshold")?;
+                let subs = subs
+                    .iter()
+                    .map(|sub| Fragment::parse(sub))
+                    .collect::<StdResult<Vec<Fragment>>>()?;
+
+                Fragment::Thresh(k, subs)
+            }
+            ("multi", [k, keys @ ..]) => {
+                let k = k.parse().map_err(|_| "invalid_multi_threshold")?;
+                let keys = keys
+                    .iter()
+                    .map(|key| parse_key(key))
+                    .collect::<StdResult<Vec<Vec<u8>>>>()?;
+
+                Fragment::Multi(k, keys)
+            }
+            // tapscript only
+            ("multi_a", _) => Err("unsupported_miniscript_fragment")?,
+            _ if FRAGMENTS.contains(&name) => Err("invalid_miniscript")?,
+            _ => Err("unknown_miniscript_fragment")?,
+        };
+
+        Ok(fragment)
+    }
+
+    pub fn tokens(&self) -> Vec<Token> {
+        let command = Token::Command;
+
+        match self {
+            Fragment::False => vec![command(OP_0)],
+            Fragment::True => vec![command(OP_1)],
+            Fragment::PkK(key) => vec![Token::Element(key.clone())],
+            Fragment::PkH(key) => vec![
+                command(OP_DUP),
+                command(OP_HASH160),
+                Token::Element(hash160(key)),
+                command(OP_EQUALVERIFY),
+            ],
+            Fragment::Older(n) => vec![number(*n as usize), command(OP_CHECKSEQUENCEVERIFY)],
+            Fragment::After(n) => vec![number(*n as usize), command(OP_CHECKLOCKTIMEVERIFY)],
+            Fragment::Hash(hash_type, hash) => vec![
+                command(OP_SIZE),
+                number(32),
+                command(OP_EQUALVERIFY),
+                command(hash_type.op_code()),
+                Token::Element(hash.clone()),
+                command(OP_EQUAL),
+            ],
+            Fragment::AndOr(x, y, z) => [
+                x.tokens(),
+                vec![command(OP_NOTIF)],
+                z.tokens(),
+                vec![command(OP_ELSE)],
+                y.tokens(),
+                vec![command(OP_ENDIF)],
+            ]
+            .concat(),
+            Fragment::AndV(x, y) => [x.tokens(), y.tokens()].concat(),
+            Fragment::AndB(x, y) => [x.tokens(), y.tokens(), vec![command(OP_BOOLAND)]].concat(),
+            Fragment::OrB(x, z) => [x.tokens(), z.tokens(), vec![command(OP_BOOLOR)]].concat(),
+            Fragment::OrC(x, z) => [x.tokens(), vec![command(OP_NOTIF)], z.tokens(), vec![command(OP_ENDIF)]].concat(),
+            Fragment::OrD(x, z) => [
+                x.tokens(),
+                vec![command(OP_IFDUP), command(OP_NOTIF)],
+                z.tokens(),
+                vec![command(OP_ENDIF)],
+            ]
+            .concat(),
+            Fragment::OrI(x, z) => [
+                vec![command(OP_IF)],
+                x.tokens(),
+                vec![command(OP_ELSE)],
+                z.tokens(),
+                vec![command(OP_ENDIF)],
+            ]
+            .concat(),
+            Fragment::Thresh(k, subs) => {
+                let mut tokens = vec![];
+
+                for (i, sub) in subs.iter().enumerate() {
+                    tokens.extend(sub.tokens());
+                    if i > 0 {
+                        tokens.push(command(OP_ADD));
+                    }
+                }
+
+                tokens.extend([number(*k), command(OP_EQUAL)]);
+                tokens
+            }
+            Fragment::Multi(k, keys) => {
+                let mut tokens = vec![number(*k)];
+                tokens.extend(keys.iter().map(|key| Token::Element(key.clone())));
+                tokens.extend([number(keys.len()), command(OP_CHECKMULTISIG)]);
+
+                tokens
+            }
+            Fragment::Alt(x) => [vec![command(OP_TOALTSTACK)], x.tokens(), vec![command(OP_FROMALTSTACK)]].concat(),
+            Fragment::Swap(x) => [vec![command(OP_SWAP)], x.tokens()].concat(),
+            Fragment::Check(x) => [x.tokens(), vec![command(OP_CHECKSIG)]].concat(),
+            Fragment::DupIf(x) => [
+                vec![command(OP_DUP), command(OP_IF)],
+                x.tokens(),
+                vec![command(OP_ENDIF)],
+            ]
+            .concat(),
+            Fragment::Verify(x) => {
+                let mut tokens = x.tokens();
+
+                let verify = match tokens.last() {
+                    Some(Token::Command(OP_EQUAL)) => Some(OP_EQUALVERIFY),
+                    Some(Token::Command(OP_NUMEQUAL)) => Some(OP_NUMEQUALVERIFY),
+                    Some(Token::Command(OP_CHECKSIG)) => Some(OP_CHECKSIGVERIFY),
+                    Some(Token::Command(OP_CHECKMULTISIG)) => Some(OP_CHECKMULTISIGVERIFY),
+                    _ => None,
+                };
+
+                match verify {
+                    Some(op_code) => {
+                        tokens.pop();
+                        tokens.push(command(op_code));
+                    }
+                    None => tokens.push(command(OP_VERIFY)),
+                }
+
+                tokens
+            }
+            Fragment::NonZero(x) => [
+                vec![command(OP_SIZE), command(OP_0NOTEQUAL), command(OP_IF)],
+                x.tokens(),
+                vec![command(OP_ENDIF)],
+            ]
+            .concat(),
+            Fragment::ZeroNotEqual(x) => [x.tokens(), vec![command(OP_0NOTEQUAL)]].concat(),
+        }
+    }
+
+    // Public keys, in the order they appear
+    pub fn keys(&self) -> Vec<Vec<u8>> {
+        match self {
+            Fragment::PkK(key) | Fragment::PkH(key) => vec![key.clone()],
+            Fragment::Multi(_, keys) => keys.clone(),
+            Fragment::AndOr(x, y, z) => [x.keys(), y.keys(), z.keys()].concat(),
+            Fragment::AndV(x, y) | Fragment::AndB(x, y) => [x.keys(), y.keys()].concat(),
+            Fragment::OrB(x, z) | Fragment::OrC(x, z) | Fragment::OrD(x, z) | Fragment::OrI(x, z) => {
+                [x.keys(), z.keys()].concat()
+            }
+            Fragment::Thresh(_, subs) => subs.iter().flat_map(|sub| sub.keys()).collect(),
+            Fragment::Alt(x)
+            | Fragment::Swap(x)
+            | Fragment::Check(x)
+            | Fragment::DupIf(x)
+            | Fragment::Verify(x)
+            | Fragment::NonZero(x)
+            | Fragment::ZeroNotEqual(x) => x.keys(),
+            Fragment::False | Fragment::True | Fragment::Older(_) | Fragment::After(_) | Fragment::Hash(..) => vec![],
+        }
+    }
+
+    // `pk` and `pkh` are written as fragments, not as `c:` wrappers
+    fn wrapper(&self) -> Option<(char, &Fragment)> {
+        match self {
+            Fragment::Alt(x) => Some(('a', x)),
+            Fragment::Swap(x) => Some(('s', x)),
+            Fragment::Check(x) if !matches!(x.as_ref(), Fragment::PkK(_) | Fragment::PkH(_)) => Some(('c', x)),
+            Fragment::DupIf(x) => Some(('d', x)),
+            Fragment::Verify(x) => Some(('v', x)),
+            Fragment::NonZero(x) => Some(('j', x)),
+            Fragment::ZeroNotEqual(x) => Some(('n', x)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Fragment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some((letter, x)) = self.wrapper() {
+            return match x.wrapper() {
+                Some(_) => write!(f, "{letter}{x}"),
+                None => write!(f, "{letter}:{x}"),
+            };
+        }
+
+        match self {
+            Fragment::False => write!(f, "0"),
+            Fragment::True => write!(f, "1"),
+            Fragment::PkK(key) => write!(f, "pk_k({})", bytes_to_hex_string(key)),
+            Fragment::PkH(key) => write!(f, "pk_h({})", bytes_to_hex_string(key)),
+            Fragment::Check(x) => match x.as_ref() {
+                Fragment::PkK(key) => write!(f, "pk({})", bytes_to_hex_string(key)),
+                Fragment::PkH(key) => write!(f, "pkh({})", bytes_to_hex_string(key)),
+                _ => write!(f, "c:{x}"),
+            },
+            Fragment::Older(n) => write!(f, "older({n})"),
+            Fragment::After(n) => write!(f, "after({n})"),
+            Fragment::Hash(hash_type, hash) => write!(f, "{}({})", hash_type.name(), bytes_to_hex_string(hash)),
+            Fragment::AndOr(x, y, z) if **z == Fragment::False => write!(f, "and_n({})", join(&[x, y])),
+            Fragment::AndOr(x, y, z) => write!(f, "andor({})", join(&[x, y, z])),
+            Fragment::AndV(x, y) => write!(f, "and_v({})", join(&[x, y])),
+            Fragment::AndB(x, y) => write!(f, "and_b({})", join(&[x, y])),
+            Fragment::OrB(x, z) => write!(f, "or_b({})", join(&[x, z])),
+            Fragment::OrC(x, z) => write!(f, "or_c({})", join(&[x, z])),
+            Fragment::OrD(x, z) => write!(f, "or_d({})", join(&[x, z])),
+            Fragment::OrI(x, z) => write!(f, "or_i({})", join(&[x, z])),
+            Fragment::Thresh(k, subs) => write!(f, "thresh({k},{})", join(subs)),
+            Fragment::Multi(k, keys) => {
+                let keys: Vec<String> = keys.iter().map(|key| bytes_to_hex_string(key)).collect();
+                write!(f, "multi({k},{})", join(&keys))
+            }
+            Fragment::Alt(_)
+            | Fragment::Swap(_)
+            | Fragment::DupIf(_)
+            | Fragment::Verify(_)
+            | Fragment::NonZero(_)
+            | Fragment::ZeroNotEqual(_) => unreachable!("written as a wrapper"),
+        }
+    }
+}
+
+// A type checked fragment of type B
+#[derive(Debug, Clone, PartialEq)]
+pub struct Miniscript {
+    fragment: Fragment,
+    ty: Type,
+}
+
+impl Display for Miniscript {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.fragment)
+    }
+}
+
+impl Miniscript {
+    pub fn new(fragment: Fragment) -> StdResult<Self> {
+        let ty = type_of(&fragment)?;
+        if ty.base != Base::B {
+            Err("miniscript_not_type_b")?;
+        }
+
+        let miniscript = Self { fragment, ty };
+        if miniscript.script().serialize()?.len() > MAX_SCRIPT_SIZE {
+            Err("miniscript_script_too_large")?;
+        }
+
+        Ok(miniscript)
+    }
+
+    pub fn parse(s: &str) -> StdResult<Self> {
+        Self::new(Fragment::parse(s.trim())?)
+    }
+
+    pub fn fragment(&self) -> &Fragment {
+        &self.fragment
+    }
+
+    pub fn ty(&self) -> Type {
+        self.ty
+    }
+
+    // Witness script
+    pub fn script(&self) -> ScriptLang {
+        ScriptLang::from_tokens(self.fragment.tokens())
+    }
+
+    // P2WSH of the witness script
+    pub fn script_pub_key(&self) -> StdResult<ScriptLang> {
+        Ok(p2wsh_script(&sha256(&self.script().serialize()?)))
+    }
+
+    pub fn keys(&self) -> Vec<Vec<u8>> {
+        self.fragment.keys()
+    }
+
+    // Every way to spend it: keys that sign, preimages and timelocks
+    pub fn spending_paths(&self) -> Vec<SpendingPath> {
+        Policy::lift(&self.fragment).spending_paths()
+    }
+
+    pub fn requires_signature(&self) -> bool {
+        self.ty.signed
+    }
+
+    pub fn has_timelock_mix(&self) -> bool {
+        !self.ty.no_timelock_mix
+    }
+
+    /*
+        Every satisfaction requires a signature, no timelock mixing and no repeated key:
+        whoever has the keys can spend it, and nobody else.
+    */
+    pub fn is_sane(&self) -> bool {
+        let mut keys = self.keys();
+        let count = keys.len();
+        keys.sort();
+        keys.dedup();
+
+        self.requires_signature() && !self.has_timelock_mix() && keys.len() == count
+    }
+}
+
+// Arguments separated by commas
+fn join<T: Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn parse_boxed(s: &str) -> StdResult<Box<Fragment>> {
+    Ok(Box::new(Fragment::parse(s)?))
+}
+
+pub fn parse_key(s: &str) -> StdResult<Vec<u8>> {
+    let key = hex_string_to_bytes(s).map_err(|_| "invalid_miniscript_key")?;
+
+    // compressed only (segwit)
+    if key.len() != 33 {
+        Err("invalid_miniscript_key")?;
+    }
+
+    verify_public_key(&key)?;
+
+    Ok(key)
+}
+
+pub fn parse_hash(s: &str, hash_type: HashType) -> StdResult<Vec<u8>> {
+    let hash = hex_string_to_bytes(s)?;
+
+    if hash.len() != hash_type.length() {
+        Err("invalid_hash_length")?;
+    }
+
+    Ok(hash)
+}
+
+// OP_0, OP_1 ... OP_16 or a script number
+fn number(n: usize) -> Token {
+    match n {
+        0 => Token::Command(OP_0),
+        1..=16 => Token::Command(OP_1 + n - 1),
+        _ => Token::Element(ScriptNum::new(n as i64).encode()),
+    }
+}
+
+#[cfg(test)]
+mod fragment_test {
+    use super::*;
+
+    const KEY_1: &str = "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+    const KEY_2: &str = "02C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5";
+    const HASH: &str = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+
+    fn replace(s: &str) -> String {
+        s.replace("KEY_1", KEY_1).replace("KEY_2", KEY_2).replace("HASH", HASH)
+    }
+
+    #[test]
+    fn scripts() {
+        let cases = [
+            ("pk(KEY_1)", "KEY_1 OP_CHECKSIG"),
+            (
+                "pkh(KEY_1)",
+                "OP_DUP OP_HASH160 751E76E8199196D454941C45D1B3A323F1433BD6 OP_EQUALVERIFY OP_CHECKSIG",
+            ),
+            (
+                "and_v(v:pk(KEY_1),pk(KEY_2))",
+                "KEY_1 OP_CHECKSIGVERIFY KEY_2 OP_CHECKSIG",
+            ),
+            (
+                "or_d(pk(KEY_1),older(144))",
+                "KEY_1 OP_CHECKSIG OP_IFDUP OP_NOTIF 9000 OP_CHECKSEQUENCEVERIFY OP_ENDIF",
+            ),
+            ("multi(1,KEY_1,KEY_2)", "OP_1 KEY_1 KEY_2 OP_2 OP_CHECKMULTISIG"),
+            (
+                "and_v(v:sha256(HASH),pk(KEY_1))",
+                "OP_SIZE 20 OP_EQUALVERIFY OP_SHA256 HASH OP_EQUALVERIFY KEY_1 OP_CHECKSIG",
+            ),
+            (
+                "thresh(2,pk(KEY_1),s:pk(KEY_2),sln:older(10))",
+                "KEY_1 OP_CHECKSIG OP_SWAP KEY_2 OP_CHECKSIG OP_ADD OP_SWAP OP_IF OP_0 OP_ELSE OP_10 \
+                 OP_CHECKSEQUENCEVERIFY OP_0NOTEQUAL OP_ENDIF OP_ADD OP_2 OP_EQUAL",
+            ),
+        ];
+
+        for (s, expected) in cases {
+            let miniscript = Miniscript::parse(&replace(s)).unwrap();
+            assert_eq!(miniscript.script().to_string(), replace(expected), "{s}");
+        }
+    }
+
+    #[test]
+    fn display() {
+        let cases = [
+            "pk(KEY_1)",
+            "pkh(KEY_1)",
+            "and_v(v:pk(KEY_1),pk(KEY_2))",
+            "and_n(pk(KEY_1),sha256(HASH))",
+            "or_i(pk(KEY_1),and_v(v:pkh(KEY_2),after(500000)))",
+            "thresh(2,pk(KEY_1),s:pk(KEY_2),sln:older(10))",
+            "c:and_v(v:pkh(KEY_1),pk_k(KEY_2))",
+            "multi(2,KEY_1,KEY_2)",
+        ];
+
+        for s in cases {
+            assert_eq!(Miniscript::parse(&replace(s)).unwrap().to_string(), replace(s));
+        }
+    }
+
+    #[test]
+    fn analysis() {
+        let miniscript = Miniscript::parse(&replace("or_d(pk(KEY_1),and_v(v:pk(KEY_2),older(144)))")).unwrap();
+        assert!(miniscript.is_sane());
+        assert_eq!(miniscript.keys().len(), 2);
+
+        let timelock = Miniscript::parse(&replace("or_d(pk(KEY_1),older(144))")).unwrap();
+        assert!(!timelock.requires_signature());
+        assert!(!timelock.is_sane());
+
+        let repeated = Miniscript::parse(&replace("and_v(v:pk(KEY_1),pk(KEY_1))")).unwrap();
+        assert!(!repeated.is_sane());
+    }
+
+    #[test]
+    fn invalid() {
+        let cases = [
+            ("pk(KEY_1", "invalid_miniscript"),
+            ("pk(KEY_1,KEY_2)", "invalid_miniscript"),
+            ("x:pk(KEY_1)", "invalid_miniscript_wrapper"),
+            ("pk(0279BE)", "invalid_miniscript_key"),
+            ("sha256(E3B0)", "invalid_hash_length"),
+            ("foo(KEY_1)", "unknown_miniscript_fragment"),
+            ("multi_a(1,KEY_1)", "unsupported_miniscript_fragment"),
+            ("v:pk(KEY_1)", "miniscript_not_type_b"),
+            ("pk_k(KEY_1)", "miniscript_not_type_b"),
+        ];
+
+        for (s, expected) in cases {
+            let error = Miniscript::parse(&replace(s)).expect_err("Err");
+            assert_eq!(error.to_string(), expected, "{s}");
+        }
+    }
+}
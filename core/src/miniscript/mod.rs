@@ -0,0 +1,4 @@
+pub mod fragment;
+pub mod policy;
+pub mod satisfier;
+pub mod types;
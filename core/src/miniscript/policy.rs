@@ -0,0 +1,472 @@
+/*
+    Spending policies: what is required to spend, not how a script checks it.
+        pk(KEY)                                         a signature of KEY
+        older(n), after(n)                              relative, absolute timelock
+        sha256(h), hash256(h), ripemd160(h), hash160(h) the preimage of h
+        and(X,Y)                                        both
+        or([N@]X,[M@]Y)                                 either, N and M are the odds of the branches (1 by default)
+        thresh(k,X1,...,Xn)                             k of them
+
+    `compile` gives a miniscript of the policy (valid, but not the smallest one),
+    `lift` the policy of a miniscript (UNSATISFIABLE and TRIVIAL for `0` and `1`),
+    `spending_paths` the ways to satisfy it: the combinations of thresholds are all listed.
+*/
+
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    descriptor::output_descriptor::{function, split_arguments},
+    psbt::constants::LOCKTIME_THRESHOLD,
+    std_lib::{std_result::StdResult, vector::bytes_to_hex_string},
+};
+
+use super::{
+    fragment::{parse_hash, parse_key, Fragment, HashType, Miniscript},
+    types::{check_timelock, type_of, MAX_MULTI_KEYS, SEQUENCE_LOCKTIME_TYPE_FLAG},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Policy {
+    Unsatisfiable,
+    Trivial,
+    Key(Vec<u8>),
+    Older(u32),
+    After(u32),
+    Hash(HashType, Vec<u8>),
+    And(Box<Policy>, Box<Policy>),
+    // branches with their odds
+    Or(Vec<(u32, Policy)>),
+    Thresh(usize, Vec<Policy>),
+}
+
+// What a way to spend requires
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpendingPath {
+    // signatures, sorted
+    pub keys: Vec<Vec<u8>>,
+    // preimages
+    pub hashes: Vec<(HashType, Vec<u8>)>,
+    // highest relative and absolute timelocks
+    pub older: Option<u32>,
+    pub after: Option<u32>,
+}
+
+impl SpendingPath {
+    // Both, None if their timelocks cannot be satisfied together (a height and a time)
+    fn and(&self, other: &SpendingPath) -> Option<SpendingPath> {
+        let older = combine_timelocks(self.older, other.older, |n| n & SEQUENCE_LOCKTIME_TYPE_FLAG != 0)?;
+        let after = combine_timelocks(self.after, other.after, |n| n >= LOCKTIME_THRESHOLD)?;
+
+        let mut keys = [self.keys.clone(), other.keys.clone()].concat();
+        keys.sort();
+        keys.dedup();
+
+        let mut hashes = self.hashes.clone();
+        for hash in other.hashes.iter() {
+            if !hashes.contains(hash) {
+                hashes.push(hash.clone());
+            }
+        }
+
+        Some(SpendingPath {
+            keys,
+            hashes,
+            older,
+            after,
+        })
+    }
+}
+
+impl Policy {
+    pub fn parse(s: &str) -> StdResult<Policy> {
+        let s = s.trim();
+
+        match s {
+            "UNSATISFIABLE" => return Ok(Policy::Unsatisfiable),
+            "TRIVIAL" => return Ok(Policy::Trivial),
+            _ => {}
+        }
+
+        let (name, arguments) = function(s).map_err(|_| "invalid_policy")?;
+        let arguments = split_arguments(arguments);
+
+        if let Some(hash_type) = HashType::from_name(name) {
+            return match arguments.as_slice() {
+                [hash] => Ok(Policy::Hash(hash_type, parse_hash(hash, hash_type)?)),
+                _ => Err("invalid_policy")?,
+            };
+        }
+
+        let policy = match (name, arguments.as_slice()) {
+            ("pk", [key]) => Policy::Key(parse_key(key)?),
+            ("older", [n]) => Policy::Older(parse_timelock(n)?),
+            ("after", [n]) => Policy::After(parse_timelock(n)?),
+            ("and", [x, y]) => Policy::And(Box::new(Policy::parse(x)?), Box::new(Policy::parse(y)?)),
+            ("or", [x, y]) => Policy::Or(vec![parse_weighted(x)?, parse_weighted(y)?]),
+            ("thresh", [k, subs @ ..]) => {
+                let k: usize = k.parse().map_err(|_| "invalid_thresh_threshold")?;
+                if k == 0 || k > subs.len() {
+                    Err("invalid_thresh_threshold")?;
+                }
+
+                let subs = subs
+                    .iter()
+                    .map(|sub| Policy::parse(sub))
+                    .collect::<StdResult<Vec<Policy>>>()?;
+
+                Policy::Thresh(k, subs)
+            }
+            _ => Err("invalid_policy")?,
+        };
+
+        Ok(policy)
+    }
+
+    // The policy of a miniscript
+    pub fn lift(fragment: &Fragment) -> Policy {
+        match fragment {
+            Fragment::False => Policy::Unsatisfiable,
+            Fragment::True => Policy::Trivial,
+            Fragment::PkK(key) | Fragment::PkH(key) => Policy::Key(key.clone()),
+            Fragment::Older(n) => Policy::Older(*n),
+            Fragment::After(n) => Policy::After(*n),
+            Fragment::Hash(hash_type, hash) => Policy::Hash(*hash_type, hash.clone()),
+            Fragment::AndOr(x, y, z) => Policy::Or(vec![
+                (1, Policy::And(Box::new(Policy::lift(x)), Box::new(Policy::lift(y)))),
+                (1, Policy::lift(z)),
+            ]),
+            Fragment::AndV(x, y) | Fragment::AndB(x, y) => {
+                Policy::And(Box::new(Policy::lift(x)), Box::new(Policy::lift(y)))
+            }
+            Fragment::OrB(x, z) | Fragment::OrC(x, z) | Fragment::OrD(x, z) | Fragment::OrI(x, z) => {
+                Policy::Or(vec![(1, Policy::lift(x)), (1, Policy::lift(z))])
+            }
+            Fragment::Thresh(k, subs) => Policy::Thresh(*k, subs.iter().map(Policy::lift).collect()),
+            Fragment::Multi(k, keys) => Policy::Thresh(*k, keys.iter().map(|key| Policy::Key(key.clone())).collect()),
+            Fragment::Alt(x)
+            | Fragment::Swap(x)
+            | Fragment::Check(x)
+            | Fragment::DupIf(x)
+            | Fragment::Verify(x)
+            | Fragment::NonZero(x)
+            | Fragment::ZeroNotEqual(x) => Policy::lift(x),
+        }
+    }
+
+    pub fn compile(&self) -> StdResult<Miniscript> {
+        Miniscript::new(self.compile_fragment()?)
+    }
+
+    // A fragment of type B
+    fn compile_fragment(&self) -> StdResult<Fragment> {
+        let fragment = match self {
+            Policy::Unsatisfiable => Fragment::False,
+            Policy::Trivial => Fragment::True,
+            Policy::Key(key) => Fragment::Check(Box::new(Fragment::PkK(key.clone()))),
+            Policy::Older(n) => Fragment::Older(*n),
+            Policy::After(n) => Fragment::After(*n),
+            Policy::Hash(hash_type, hash) => Fragment::Hash(*hash_type, hash.clone()),
+            // and_v(v:X,Y)
+            Policy::And(x, y) => Fragment::AndV(
+                Box::new(Fragment::Verify(Box::new(x.compile_fragment()?))),
+                Box::new(y.compile_fragment()?),
+            ),
+            Policy::Or(branches) => {
+                // the most likely branch first
+                let mut branches: Vec<&(u32, Policy)> = branches.iter().collect();
+                branches.sort_by(|a, b| b.0.cmp(&a.0));
+
+                let mut fragments = branches.iter().rev().map(|(_, policy)| policy.compile_fragment());
+                let last = match fragments.next() {
+                    Some(fragment) => fragment?,
+                    None => Err("invalid_policy")?,
+                };
+
+                fragments.try_fold(last, |z, x| compile_or(x?, z))?
+            }
+            Policy::Thresh(k, subs) => {
+                let keys: Vec<Vec<u8>> = subs
+                    .iter()
+                    .filter_map(|sub| match sub {
+                        Policy::Key(key) => Some(key.clone()),
+                        _ => None,
+                    })
+                    .collect();
+
+                if keys.len() == subs.len() && keys.len() <= MAX_MULTI_KEYS {
+                    Fragment::Multi(*k, keys)
+                } else {
+                    // thresh(k,X1,a:X2,...,a:Xn), all dissatisfiable and unit
+                    let mut fragments = vec![];
+
+                    for (i, sub) in subs.iter().enumerate() {
+                        let fragment = dissatisfiable_unit(sub.compile_fragment()?)?;
+                        fragments.push(if i == 0 {
+                            fragment
+                        } else {
+                            Fragment::Alt(Box::new(fragment))
+                        });
+                    }
+
+                    Fragment::Thresh(*k, fragments)
+                }
+            }
+        };
+
+        Ok(fragment)
+    }
+
+    pub fn spending_paths(&self) -> Vec<SpendingPath> {
+        match self {
+            Policy::Unsatisfiable => vec![],
+            Policy::Trivial => vec![SpendingPath::default()],
+            Policy::Key(key) => vec![SpendingPath {
+                keys: vec![key.clone()],
+                ..SpendingPath::default()
+            }],
+            Policy::Older(n) => vec![SpendingPath {
+                older: Some(*n),
+                ..SpendingPath::default()
+            }],
+            Policy::After(n) => vec![SpendingPath {
+                after: Some(*n),
+                ..SpendingPath::default()
+            }],
+            Policy::Hash(hash_type, hash) => vec![SpendingPath {
+                hashes: vec![(*hash_type, hash.clone())],
+                ..SpendingPath::default()
+            }],
+            Policy::And(x, y) => and_paths(&x.spending_paths(), &y.spending_paths()),
+            Policy::Or(branches) => branches
+                .iter()
+                .flat_map(|(_, policy)| policy.spending_paths())
+                .collect(),
+            Policy::Thresh(k, subs) => {
+                let subs: Vec<Vec<SpendingPath>> = subs.iter().map(|sub| sub.spending_paths()).collect();
+
+                combinations(0, subs.len(), *k)
+                    .iter()
+                    .flat_map(|combination| {
+                        combination
+                            .iter()
+                            .fold(vec![SpendingPath::default()], |paths, i| and_paths(&paths, &subs[*i]))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Display for Policy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Policy::Unsatisfiable => write!(f, "UNSATISFIABLE"),
+            Policy::Trivial => write!(f, "TRIVIAL"),
+            Policy::Key(key) => write!(f, "pk({})", bytes_to_hex_string(key)),
+            Policy::Older(n) => write!(f, "older({n})"),
+            Policy::After(n) => write!(f, "after({n})"),
+            Policy::Hash(hash_type, hash) => write!(f, "{}({})", hash_type.name(), bytes_to_hex_string(hash)),
+            Policy::And(x, y) => write!(f, "and({x},{y})"),
+            Policy::Or(branches) => {
+                let branches: Vec<String> = branches
+                    .iter()
+                    .map(|(odds, policy)| match odds {
+                        1 => policy.to_string(),
+                        _ => format!("{odds}@{policy}"),
+                    })
+                    .collect();
+
+                write!(f, "or({})", branches.join(","))
+            }
+            Policy::Thresh(k, subs) => {
+                let subs: Vec<String> = subs.iter().map(|sub| sub.to_string()).collect();
+                write!(f, "thresh({k},{})", subs.join(","))
+            }
+        }
+    }
+}
+
+fn parse_timelock(s: &str) -> StdResult<u32> {
+    let n = s.parse().map_err(|_| "invalid_timelock")?;
+    check_timelock(n)?;
+
+    Ok(n)
+}
+
+// `N@X`
+fn parse_weighted(s: &str) -> StdResult<(u32, Policy)> {
+    match s.split_once('@') {
+        Some((odds, policy)) if !odds.contains('(') => {
+            let odds: u32 = odds.parse().map_err(|_| "invalid_policy_odds")?;
+            if odds == 0 {
+                Err("invalid_policy_odds")?;
+            }
+
+            Ok((odds, Policy::parse(policy)?))
+        }
+        _ => Ok((1, Policy::parse(s)?)),
+    }
+}
+
+// or_d(X,Z) if one of them is Bdu (first), or_i(X,Z) otherwise
+fn compile_or(x: Fragment, z: Fragment) -> StdResult<Fragment> {
+    let is_bdu = |fragment: &Fragment| -> StdResult<bool> {
+        let ty = type_of(fragment)?;
+        Ok(ty.dissatisfiable && ty.unit)
+    };
+
+    let fragment = if is_bdu(&x)? {
+        Fragment::OrD(Box::new(x), Box::new(z))
+    } else if is_bdu(&z)? {
+        Fragment::OrD(Box::new(z), Box::new(x))
+    } else {
+        Fragment::OrI(Box::new(x), Box::new(z))
+    };
+
+    Ok(fragment)
+}
+
+// Dissatisfiable with `l:` (or_i(0,X)), unit with `n:`
+fn dissatisfiable_unit(fragment: Fragment) -> StdResult<Fragment> {
+    let fragment = if type_of(&fragment)?.dissatisfiable {
+        fragment
+    } else {
+        Fragment::OrI(Box::new(Fragment::False), Box::new(fragment))
+    };
+
+    if type_of(&fragment)?.unit {
+        Ok(fragment)
+    } else {
+        Ok(Fragment::ZeroNotEqual(Box::new(fragment)))
+    }
+}
+
+// The highest, None if one is a height and the other a time
+fn combine_timelocks(a: Option<u32>, b: Option<u32>, is_time: impl Fn(u32) -> bool) -> Option<Option<u32>> {
+    match (a, b) {
+        (Some(a), Some(b)) if is_time(a) != is_time(b) => None,
+        (Some(a), Some(b)) => Some(Some(a.max(b))),
+        (a, b) => Some(a.or(b)),
+    }
+}
+
+fn and_paths(a: &[SpendingPath], b: &[SpendingPath]) -> Vec<SpendingPath> {
+    a.iter().flat_map(|x| b.iter().filter_map(move |y| x.and(y))).collect()
+}
+
+// Subsets of k indexes in from..n, in lexicographic order
+fn combinations(from: usize, n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+
+    if n - from < k {
+        return vec![];
+    }
+
+    let mut subsets: Vec<Vec<usize>> = combinations(from + 1, n, k - 1)
+        .into_iter()
+        .map(|subset| [vec![from], subset].concat())
+        .collect();
+    subsets.extend(combinations(from + 1, n, k));
+
+    subsets
+}
+
+#[cfg(test)]
+mod policy_test {
+    use super::*;
+
+    const KEY_1: &str = "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+    const KEY_2: &str = "02C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5";
+
+    fn replace(s: &str) -> String {
+        s.replace("KEY_1", KEY_1).replace("KEY_2", KEY_2)
+    }
+
+    fn key(s: &str) -> Vec<u8> {
+        parse_key(s).unwrap()
+    }
+
+    #[test]
+    fn compile() {
+        let cases = [
+            (
+                "or(99@pk(KEY_1),1@and(pk(KEY_2),older(144)))",
+                "or_d(pk(KEY_1),and_v(v:pk(KEY_2),older(144)))",
+            ),
+            ("thresh(2,pk(KEY_1),pk(KEY_2))", "multi(2,KEY_1,KEY_2)"),
+            (
+                "thresh(2,pk(KEY_1),pk(KEY_2),older(144))",
+                "thresh(2,pk(KEY_1),a:pk(KEY_2),an:or_i(0,older(144)))",
+            ),
+            (
+                "and(pk(KEY_1),or(pk(KEY_2),after(500000)))",
+                "and_v(v:pk(KEY_1),or_d(pk(KEY_2),after(500000)))",
+            ),
+        ];
+
+        for (policy, expected) in cases {
+            let miniscript = Policy::parse(&replace(policy)).unwrap().compile().unwrap();
+            assert_eq!(miniscript.to_string(), replace(expected), "{policy}");
+        }
+    }
+
+    #[test]
+    fn display() {
+        let policy = replace("or(99@pk(KEY_1),and(pk(KEY_2),older(144)))");
+        assert_eq!(Policy::parse(&policy).unwrap().to_string(), policy);
+    }
+
+    #[test]
+    fn lift() {
+        let miniscript = Miniscript::parse(&replace("or_d(pk(KEY_1),and_v(v:pkh(KEY_2),older(144)))")).unwrap();
+
+        assert_eq!(
+            Policy::lift(miniscript.fragment()).to_string(),
+            replace("or(pk(KEY_1),and(pk(KEY_2),older(144)))")
+        );
+    }
+
+    #[test]
+    fn spending_paths() {
+        let policy = Policy::parse(&replace("thresh(2,pk(KEY_1),pk(KEY_2),older(144))")).unwrap();
+        let path = |keys: Vec<Vec<u8>>, older: Option<u32>| SpendingPath {
+            keys,
+            older,
+            ..SpendingPath::default()
+        };
+
+        assert_eq!(
+            policy.spending_paths(),
+            vec![
+                path(vec![key(KEY_1), key(KEY_2)], None),
+                path(vec![key(KEY_1)], Some(144)),
+                path(vec![key(KEY_2)], Some(144)),
+            ]
+        );
+
+        // a height and a time
+        let mixed = Policy::parse("and(older(144),older(4194305))").unwrap();
+        assert!(mixed.spending_paths().is_empty());
+
+        let alternatives = Policy::parse("or(after(100),after(500000000))").unwrap();
+        assert_eq!(alternatives.spending_paths().len(), 2);
+    }
+
+    #[test]
+    fn invalid() {
+        let cases = [
+            ("pk(KEY_1", "invalid_policy"),
+            ("and(pk(KEY_1))", "invalid_policy"),
+            ("or(0@pk(KEY_1),pk(KEY_2))", "invalid_policy_odds"),
+            ("thresh(3,pk(KEY_1),pk(KEY_2))", "invalid_thresh_threshold"),
+            ("older(0)", "invalid_timelock"),
+        ];
+
+        for (policy, expected) in cases {
+            let error = Policy::parse(&replace(policy)).expect_err("Err");
+            assert_eq!(error.to_string(), expected, "{policy}");
+        }
+    }
+}
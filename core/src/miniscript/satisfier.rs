@@ -0,0 +1,407 @@
+/*
+    Satisfaction of a miniscript: the witness stack that spends it (without the witness script, last),
+    from the signatures, preimages and timelocks available.
+
+    Each fragment has a satisfaction and a dissatisfaction (if there is one with what is available).
+    A fragment executed first takes its inputs from the top of the stack, so its witness is pushed last:
+        and_v(X,Y)      sat(Y) sat(X)
+        or_d(X,Z)       sat(X), or sat(Z) dsat(X)
+        or_i(X,Z)       sat(X) 1, or sat(Z) 0
+        thresh(k,...)   sat or dsat of Xn ... X1, k of them satisfied
+
+    The smallest witness is chosen among the alternatives, satisfactions are not checked to be non-malleable.
+*/
+
+use std::collections::HashMap;
+
+use crate::{
+    psbt::constants::LOCKTIME_THRESHOLD,
+    std_lib::{std_result::StdResult, varint::encode},
+};
+
+use super::{
+    fragment::{Fragment, Miniscript},
+    types::SEQUENCE_LOCKTIME_TYPE_FLAG,
+};
+
+// BIP68
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000FFFF;
+
+// Stack elements, the first one at the bottom
+pub type Witness = Vec<Vec<u8>>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Satisfier {
+    // DER signatures with the SIGHASH byte, by public key
+    pub signatures: HashMap<Vec<u8>, Vec<u8>>,
+    pub preimages: Vec<Vec<u8>>,
+    // nSequence of the input and nLockTime of the transaction
+    pub sequence: u32,
+    pub lock_time: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Satisfactions {
+    sat: Option<Witness>,
+    dsat: Option<Witness>,
+}
+
+impl Satisfier {
+    pub fn add_signature(&mut self, public_key: &[u8], signature: &[u8]) {
+        self.signatures.insert(public_key.to_vec(), signature.to_vec());
+    }
+
+    pub fn add_preimage(&mut self, preimage: &[u8]) {
+        self.preimages.push(preimage.to_vec());
+    }
+
+    pub fn satisfy(&self, miniscript: &Miniscript) -> StdResult<Witness> {
+        match self.satisfactions(miniscript.fragment()).sat {
+            Some(witness) => Ok(witness),
+            None => Err("miniscript_not_satisfiable")?,
+        }
+    }
+
+    // With the witness script
+    pub fn witness(&self, miniscript: &Miniscript) -> StdResult<Witness> {
+        let mut witness = self.satisfy(miniscript)?;
+        witness.push(miniscript.script().serialize()?);
+
+        Ok(witness)
+    }
+
+    fn satisfactions(&self, fragment: &Fragment) -> Satisfactions {
+        let empty = || Some(vec![vec![]]);
+        let one = || Some(vec![vec![0x01]]);
+
+        let (sat, dsat) = match fragment {
+            Fragment::False => (None, Some(vec![])),
+            Fragment::True => (Some(vec![]), None),
+            Fragment::PkK(key) => (
+                self.signatures.get(key).map(|signature| vec![signature.clone()]),
+                empty(),
+            ),
+            Fragment::PkH(key) => (
+                self.signatures
+                    .get(key)
+                    .map(|signature| vec![signature.clone(), key.clone()]),
+                Some(vec![vec![], key.clone()]),
+            ),
+            Fragment::Older(n) => (self.older(*n).then(Vec::new), None),
+            Fragment::After(n) => (self.after(*n).then(Vec::new), None),
+            Fragment::Hash(hash_type, hash) => (
+                self.preimages
+                    .iter()
+                    .find(|preimage| preimage.len() == 32 && hash_type.hash(preimage) == *hash)
+                    .map(|preimage| vec![preimage.clone()]),
+                // 32 bytes, not the preimage
+                Some(vec![vec![0x00; 32]]),
+            ),
+            Fragment::AndOr(x, y, z) => {
+                let (x, y, z) = (self.satisfactions(x), self.satisfactions(y), self.satisfactions(z));
+                (
+                    smallest([concat(&y.sat, &x.sat), concat(&z.sat, &x.dsat)]),
+                    concat(&z.dsat, &x.dsat),
+                )
+            }
+            Fragment::AndV(x, y) => {
+                let (x, y) = (self.satisfactions(x), self.satisfactions(y));
+                (concat(&y.sat, &x.sat), concat(&y.dsat, &x.sat))
+            }
+            Fragment::AndB(x, y) => {
+                let (x, y) = (self.satisfactions(x), self.satisfactions(y));
+                (concat(&y.sat, &x.sat), concat(&y.dsat, &x.dsat))
+            }
+            Fragment::OrB(x, z) => {
+                let (x, z) = (self.satisfactions(x), self.satisfactions(z));
+                (
+                    smallest([concat(&z.dsat, &x.sat), concat(&z.sat, &x.dsat)]),
+                    concat(&z.dsat, &x.dsat),
+                )
+            }
+            Fragment::OrC(x, z) => {
+                let (x, z) = (self.satisfactions(x), self.satisfactions(z));
+                (smallest([x.sat.clone(), concat(&z.sat, &x.dsat)]), None)
+            }
+            Fragment::OrD(x, z) => {
+                let (x, z) = (self.satisfactions(x), self.satisfactions(z));
+                (
+                    smallest([x.sat.clone(), concat(&z.sat, &x.dsat)]),
+                    concat(&z.dsat, &x.dsat),
+                )
+            }
+            Fragment::OrI(x, z) => {
+                let (x, z) = (self.satisfactions(x), self.satisfactions(z));
+                (
+                    smallest([concat(&x.sat, &one()), concat(&z.sat, &empty())]),
+                    smallest([concat(&x.dsat, &one()), concat(&z.dsat, &empty())]),
+                )
+            }
+            Fragment::Thresh(k, subs) => {
+                let subs: Vec<Satisfactions> = subs.iter().map(|sub| self.satisfactions(sub)).collect();
+                (thresh_satisfaction(*k, &subs), thresh_witness(&subs, &[]))
+            }
+            Fragment::Multi(k, keys) => {
+                let signatures: Witness = keys
+                    .iter()
+                    .filter_map(|key| self.signatures.get(key))
+                    .take(*k)
+                    .cloned()
+                    .collect();
+
+                // OP_CHECKMULTISIG pops one more element
+                let sat = (signatures.len() == *k).then(|| [vec![vec![]], signatures].concat());
+                (sat, Some(vec![vec![]; k + 1]))
+            }
+            Fragment::Alt(x) | Fragment::Swap(x) | Fragment::Check(x) | Fragment::ZeroNotEqual(x) => {
+                let x = self.satisfactions(x);
+                (x.sat, x.dsat)
+            }
+            Fragment::DupIf(x) => (concat(&self.satisfactions(x).sat, &one()), empty()),
+            Fragment::Verify(x) => (self.satisfactions(x).sat, None),
+            Fragment::NonZero(x) => (self.satisfactions(x).sat, empty()),
+        };
+
+        Satisfactions { sat, dsat }
+    }
+
+    // BIP68: a relative lock time of the same kind, at least n
+    fn older(&self, n: u32) -> bool {
+        self.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG == 0
+            && self.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG == n & SEQUENCE_LOCKTIME_TYPE_FLAG
+            && self.sequence & SEQUENCE_LOCKTIME_MASK >= n & SEQUENCE_LOCKTIME_MASK
+    }
+
+    // BIP65: a lock time of the same kind, at least n
+    fn after(&self, n: u32) -> bool {
+        (self.lock_time >= LOCKTIME_THRESHOLD) == (n >= LOCKTIME_THRESHOLD) && self.lock_time >= n
+    }
+}
+
+// `top` on `bottom`
+fn concat(bottom: &Option<Witness>, top: &Option<Witness>) -> Option<Witness> {
+    match (bottom, top) {
+        (Some(bottom), Some(top)) => Some([bottom.clone(), top.clone()].concat()),
+        _ => None,
+    }
+}
+
+fn size(witness: &Witness) -> usize {
+    witness
+        .iter()
+        .map(|element| encode(element.len() as u64).len() + element.len())
+        .sum()
+}
+
+fn smallest<const N: usize>(witnesses: [Option<Witness>; N]) -> Option<Witness> {
+    witnesses.into_iter().flatten().min_by_key(size)
+}
+
+// The k cheapest to satisfy instead of dissatisfying (those without a dissatisfaction first)
+fn thresh_satisfaction(k: usize, subs: &[Satisfactions]) -> Option<Witness> {
+    let mut satisfiable: Vec<usize> = (0..subs.len()).filter(|i| subs[*i].sat.is_some()).collect();
+
+    satisfiable.sort_by_key(|i| {
+        let sat = subs[*i].sat.as_ref().map_or(0, size) as i64;
+        subs[*i].dsat.as_ref().map_or(i64::MIN, |dsat| sat - size(dsat) as i64)
+    });
+
+    if satisfiable.len() < k {
+        return None;
+    }
+
+    thresh_witness(subs, &satisfiable[..k])
+}
+
+// X1 is executed first: its witness is on top
+fn thresh_witness(subs: &[Satisfactions], satisfied: &[usize]) -> Option<Witness> {
+    subs.iter().enumerate().rev().fold(Some(vec![]), |witness, (i, sub)| {
+        let part = if satisfied.contains(&i) { &sub.sat } else { &sub.dsat };
+        concat(&witness, part)
+    })
+}
+
+#[cfg(test)]
+mod satisfier_test {
+    use rug::Integer;
+
+    use crate::{
+        flags::script_flags::ScriptFlags,
+        hashing::sha256::sha256,
+        keys::key::Key,
+        scripting::{
+            context::{Context, SigVersion, SpendingTx},
+            token::Token,
+        },
+        std_lib::{vector::bytes_to_hex_string, vector::hex_string_to_bytes},
+    };
+
+    use super::*;
+
+    const KEY_1: &str = "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+    const KEY_2: &str = "02C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5";
+
+    fn z() -> Integer {
+        Integer::from(0x1234)
+    }
+
+    // DER with SIGHASH_ALL by the private key
+    fn signature(private_key: u32) -> Vec<u8> {
+        let mut signature = Key::new(Integer::from(private_key)).sign(z()).der();
+        signature.push(0x01);
+        signature
+    }
+
+    fn miniscript(s: &str) -> Miniscript {
+        Miniscript::parse(&s.replace("KEY_1", KEY_1).replace("KEY_2", KEY_2)).unwrap()
+    }
+
+    fn satisfier(private_keys: &[u32]) -> Satisfier {
+        let mut satisfier = Satisfier::default();
+        for private_key in private_keys {
+            let public_key = if *private_key == 1 { KEY_1 } else { KEY_2 };
+            satisfier.add_signature(&hex_string_to_bytes(public_key).unwrap(), &signature(*private_key));
+        }
+
+        satisfier
+    }
+
+    /*
+        The witness script evaluated on the witness elements (as they are, not pushed), spent by a transaction
+        with the sequence and the locktime of `satisfier`.
+    */
+    fn evaluate(miniscript: &Miniscript, witness: &Witness, satisfier: &Satisfier) -> StdResult<bool> {
+        let script = miniscript.script();
+        let mut context = Context::new_with_flags(script.tokens(), z(), ScriptFlags::STANDARD);
+        context.set_sig_version(SigVersion::WitnessV0);
+
+        context.set_stack(
+            witness
+                .iter()
+                .rev()
+                .map(|element| Token::Element(element.clone()))
+                .collect(),
+        );
+        context.set_spending_tx(SpendingTx {
+            version: 2,
+            locktime: satisfier.lock_time,
+            sequence: satisfier.sequence,
+        });
+
+        script.evaluate(&mut context)
+    }
+
+    #[test]
+    fn or_d() {
+        let miniscript = miniscript("or_d(pk(KEY_1),and_v(v:pk(KEY_2),older(144)))");
+
+        let satisfier_1 = satisfier(&[1]);
+        let witness = satisfier_1.satisfy(&miniscript).unwrap();
+        assert_eq!(witness, vec![signature(1)]);
+        assert!(evaluate(&miniscript, &witness, &satisfier_1).unwrap());
+
+        let mut timelocked = satisfier(&[2]);
+        timelocked.sequence = 144;
+        let witness = timelocked.satisfy(&miniscript).unwrap();
+        assert_eq!(witness, vec![signature(2), vec![]]);
+        assert!(evaluate(&miniscript, &witness, &timelocked).unwrap());
+
+        // the same witness, spent before the timelock
+        timelocked.sequence = 143;
+        assert_eq!(
+            evaluate(&miniscript, &witness, &timelocked)
+                .expect_err("Err")
+                .to_string(),
+            "unsatisfied_locktime"
+        );
+
+        // the smallest
+        let mut both = satisfier(&[1, 2]);
+        both.sequence = 144;
+        assert_eq!(both.satisfy(&miniscript).unwrap(), vec![signature(1)]);
+
+        let early = satisfier(&[2]);
+        assert_eq!(
+            early.satisfy(&miniscript).expect_err("Err").to_string(),
+            "miniscript_not_satisfiable"
+        );
+    }
+
+    #[test]
+    fn thresh() {
+        let miniscript = miniscript("thresh(2,pk(KEY_1),s:pk(KEY_2),sln:older(10))");
+
+        let mut satisfier = satisfier(&[1]);
+        satisfier.sequence = 10;
+        let witness = satisfier.satisfy(&miniscript).unwrap();
+
+        assert_eq!(witness, vec![vec![], vec![], signature(1)]);
+        assert!(evaluate(&miniscript, &witness, &satisfier).unwrap());
+    }
+
+    #[test]
+    fn multi() {
+        let miniscript = miniscript("multi(1,KEY_1,KEY_2)");
+
+        let satisfier = satisfier(&[2]);
+        let witness = satisfier.satisfy(&miniscript).unwrap();
+        assert_eq!(witness, vec![vec![], signature(2)]);
+        assert!(evaluate(&miniscript, &witness, &satisfier).unwrap());
+    }
+
+    #[test]
+    fn hash() {
+        let preimage = vec![0x01; 32];
+        let miniscript = miniscript(&format!(
+            "and_v(v:sha256({}),pk(KEY_1))",
+            bytes_to_hex_string(&sha256(&preimage))
+        ));
+
+        let mut satisfier = satisfier(&[1]);
+        assert!(satisfier.satisfy(&miniscript).is_err());
+
+        satisfier.add_preimage(&preimage);
+        let witness = satisfier.witness(&miniscript).unwrap();
+
+        assert_eq!(witness[..2], [signature(1), preimage]);
+        assert_eq!(witness[2], miniscript.script().serialize().unwrap());
+        assert!(evaluate(&miniscript, &witness[..2].to_vec(), &satisfier).unwrap());
+    }
+
+    #[test]
+    fn timelocks() {
+        let satisfier = Satisfier {
+            sequence: SEQUENCE_LOCKTIME_TYPE_FLAG | 10,
+            lock_time: 500_000_100,
+            ..Satisfier::default()
+        };
+
+        assert!(satisfier.older(SEQUENCE_LOCKTIME_TYPE_FLAG | 5));
+        assert!(!satisfier.older(5));
+        assert!(satisfier.after(500_000_000));
+        assert!(!satisfier.after(100));
+    }
+
+    #[test]
+    fn after() {
+        let miniscript = miniscript("and_v(v:pk(KEY_1),after(700000))");
+
+        let mut satisfier = satisfier(&[1]);
+        assert!(satisfier.satisfy(&miniscript).is_err());
+
+        satisfier.lock_time = 700000;
+        let witness = satisfier.satisfy(&miniscript).unwrap();
+        assert_eq!(witness, vec![signature(1)]);
+        assert!(evaluate(&miniscript, &witness, &satisfier).unwrap());
+
+        // a time instead of a height
+        satisfier.lock_time = 500_000_100;
+        assert_eq!(
+            evaluate(&miniscript, &witness, &satisfier)
+                .expect_err("Err")
+                .to_string(),
+            "unsatisfied_locktime"
+        );
+    }
+}
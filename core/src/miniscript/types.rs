@@ -0,0 +1,549 @@
+/*
+    Miniscript types (BIP379), checked bottom up from the fragments (P2WSH).
+
+    Basic types:
+        B   base: consumes its inputs from the top of the stack, pushes nonzero if satisfied, exactly 0 if dissatisfied
+        V   verify: consumes its inputs from the top of the stack, continues if satisfied, cannot be dissatisfied
+        K   key: consumes its inputs from the top of the stack, pushes a public key to be checked by OP_CHECKSIG
+        W   wrapped: consumes its inputs from one below the top of the stack, then pushes the result as B on top
+
+    Properties:
+        z   zero_arg: consumes exactly 0 stack elements
+        o   one_arg: consumes exactly 1 stack element
+        n   non_zero: its satisfaction cannot be a zero top stack element
+        d   dissatisfiable: has a dissatisfaction (that does not need a signature)
+        u   unit: pushes exactly 1 if satisfied
+        s   signed: every satisfaction requires a signature
+        k   no_timelock_mix: no satisfaction requires both a height and a time lock of the same kind
+*/
+
+use crate::{psbt::constants::LOCKTIME_THRESHOLD, std_lib::std_result::StdResult};
+
+use super::fragment::Fragment;
+
+// Relative lock time in units of 512 seconds (BIP68)
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+
+// older(n) and after(n): 1 <= n < 2^31
+const MAX_TIMELOCK: u32 = 0x80000000;
+
+pub const MAX_MULTI_KEYS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Base {
+    B,
+    V,
+    K,
+    W,
+}
+
+// Kinds of timelock required by some satisfaction
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timelocks {
+    pub csv_height: bool,
+    pub csv_time: bool,
+    pub cltv_height: bool,
+    pub cltv_time: bool,
+}
+
+impl Timelocks {
+    fn union(self, other: Timelocks) -> Timelocks {
+        Timelocks {
+            csv_height: self.csv_height || other.csv_height,
+            csv_time: self.csv_time || other.csv_time,
+            cltv_height: self.cltv_height || other.cltv_height,
+            cltv_time: self.cltv_time || other.cltv_time,
+        }
+    }
+
+    // Both required: a height and a time lock of the same kind cannot be satisfied together
+    fn mix(&self, other: &Timelocks) -> bool {
+        (self.csv_height && other.csv_time)
+            || (self.csv_time && other.csv_height)
+            || (self.cltv_height && other.cltv_time)
+            || (self.cltv_time && other.cltv_height)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Type {
+    pub base: Base,
+    pub zero_arg: bool,
+    pub one_arg: bool,
+    pub non_zero: bool,
+    pub dissatisfiable: bool,
+    pub unit: bool,
+    pub signed: bool,
+    pub no_timelock_mix: bool,
+    pub timelocks: Timelocks,
+}
+
+impl Type {
+    fn new(base: Base) -> Self {
+        Self {
+            base,
+            zero_arg: false,
+            one_arg: false,
+            non_zero: false,
+            dissatisfiable: false,
+            unit: false,
+            signed: false,
+            no_timelock_mix: true,
+            timelocks: Timelocks::default(),
+        }
+    }
+
+    // A wrapper of `self`: signatures and timelocks do not change
+    fn wrapped(&self, base: Base) -> Self {
+        Self {
+            signed: self.signed,
+            no_timelock_mix: self.no_timelock_mix,
+            timelocks: self.timelocks,
+            ..Self::new(base)
+        }
+    }
+
+    // Both satisfied
+    fn and(&self, other: &Type, base: Base) -> Self {
+        Self {
+            signed: self.signed || other.signed,
+            no_timelock_mix: self.no_timelock_mix && other.no_timelock_mix && !self.timelocks.mix(&other.timelocks),
+            timelocks: self.timelocks.union(other.timelocks),
+            ..Self::new(base)
+        }
+    }
+
+    // Either satisfied
+    fn or(&self, other: &Type, base: Base) -> Self {
+        Self {
+            signed: self.signed && other.signed,
+            no_timelock_mix: self.no_timelock_mix && other.no_timelock_mix,
+            timelocks: self.timelocks.union(other.timelocks),
+            ..Self::new(base)
+        }
+    }
+
+    fn is(&self, base: Base) -> bool {
+        self.base == base
+    }
+
+    // Bdu
+    fn is_b_dissatisfiable_unit(&self) -> bool {
+        self.is(Base::B) && self.dissatisfiable && self.unit
+    }
+}
+
+pub fn type_of(fragment: &Fragment) -> StdResult<Type> {
+    let ty = match fragment {
+        Fragment::False => Type {
+            zero_arg: true,
+            unit: true,
+            dissatisfiable: true,
+            signed: true,
+            ..Type::new(Base::B)
+        },
+        Fragment::True => Type {
+            zero_arg: true,
+            unit: true,
+            ..Type::new(Base::B)
+        },
+        Fragment::PkK(_) => Type {
+            one_arg: true,
+            non_zero: true,
+            dissatisfiable: true,
+            unit: true,
+            signed: true,
+            ..Type::new(Base::K)
+        },
+        Fragment::PkH(_) => Type {
+            non_zero: true,
+            dissatisfiable: true,
+            unit: true,
+            signed: true,
+            ..Type::new(Base::K)
+        },
+        Fragment::Older(n) => {
+            check_timelock(*n)?;
+
+            let time = n & SEQUENCE_LOCKTIME_TYPE_FLAG != 0;
+            Type {
+                zero_arg: true,
+                timelocks: Timelocks {
+                    csv_height: !time,
+                    csv_time: time,
+                    ..Timelocks::default()
+                },
+                ..Type::new(Base::B)
+            }
+        }
+        Fragment::After(n) => {
+            check_timelock(*n)?;
+
+            let time = *n >= LOCKTIME_THRESHOLD;
+            Type {
+                zero_arg: true,
+                timelocks: Timelocks {
+                    cltv_height: !time,
+                    cltv_time: time,
+                    ..Timelocks::default()
+                },
+                ..Type::new(Base::B)
+            }
+        }
+        Fragment::Hash(..) => Type {
+            one_arg: true,
+            non_zero: true,
+            dissatisfiable: true,
+            unit: true,
+            ..Type::new(Base::B)
+        },
+        Fragment::AndOr(x, y, z) => {
+            let (x, y, z) = (type_of(x)?, type_of(y)?, type_of(z)?);
+            if !x.is_b_dissatisfiable_unit() || y.base != z.base || y.is(Base::W) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                zero_arg: x.zero_arg && y.zero_arg && z.zero_arg,
+                one_arg: (x.zero_arg && y.one_arg && z.one_arg) || (x.one_arg && y.zero_arg && z.zero_arg),
+                dissatisfiable: z.dissatisfiable,
+                unit: y.unit && z.unit,
+                ..x.and(&y, y.base).or(&z, y.base)
+            }
+        }
+        Fragment::AndV(x, y) => {
+            let (x, y) = (type_of(x)?, type_of(y)?);
+            if !x.is(Base::V) || y.is(Base::W) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                zero_arg: x.zero_arg && y.zero_arg,
+                one_arg: (x.zero_arg && y.one_arg) || (x.one_arg && y.zero_arg),
+                non_zero: x.non_zero || (x.zero_arg && y.non_zero),
+                unit: y.unit,
+                ..x.and(&y, y.base)
+            }
+        }
+        Fragment::AndB(x, y) => {
+            let (x, y) = (type_of(x)?, type_of(y)?);
+            if !x.is(Base::B) || !y.is(Base::W) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                zero_arg: x.zero_arg && y.zero_arg,
+                one_arg: (x.zero_arg && y.one_arg) || (x.one_arg && y.zero_arg),
+                non_zero: x.non_zero || (x.zero_arg && y.non_zero),
+                dissatisfiable: x.dissatisfiable && y.dissatisfiable,
+                unit: true,
+                ..x.and(&y, Base::B)
+            }
+        }
+        Fragment::OrB(x, z) => {
+            let (x, z) = (type_of(x)?, type_of(z)?);
+            if !x.is(Base::B) || !x.dissatisfiable || !z.is(Base::W) || !z.dissatisfiable {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                zero_arg: x.zero_arg && z.zero_arg,
+                one_arg: (x.zero_arg && z.one_arg) || (x.one_arg && z.zero_arg),
+                dissatisfiable: true,
+                unit: true,
+                ..x.or(&z, Base::B)
+            }
+        }
+        Fragment::OrC(x, z) => {
+            let (x, z) = (type_of(x)?, type_of(z)?);
+            if !x.is_b_dissatisfiable_unit() || !z.is(Base::V) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                zero_arg: x.zero_arg && z.zero_arg,
+                one_arg: x.one_arg && z.zero_arg,
+                ..x.or(&z, Base::V)
+            }
+        }
+        Fragment::OrD(x, z) => {
+            let (x, z) = (type_of(x)?, type_of(z)?);
+            if !x.is_b_dissatisfiable_unit() || !z.is(Base::B) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                zero_arg: x.zero_arg && z.zero_arg,
+                one_arg: x.one_arg && z.zero_arg,
+                dissatisfiable: z.dissatisfiable,
+                unit: z.unit,
+                ..x.or(&z, Base::B)
+            }
+        }
+        Fragment::OrI(x, z) => {
+            let (x, z) = (type_of(x)?, type_of(z)?);
+            if x.base != z.base || x.is(Base::W) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                one_arg: x.zero_arg && z.zero_arg,
+                dissatisfiable: x.dissatisfiable || z.dissatisfiable,
+                unit: x.unit && z.unit,
+                ..x.or(&z, x.base)
+            }
+        }
+        Fragment::Thresh(k, subs) => {
+            if *k == 0 || *k > subs.len() {
+                Err("invalid_thresh_threshold")?;
+            }
+
+            let types = subs.iter().map(type_of).collect::<StdResult<Vec<Type>>>()?;
+
+            for (i, ty) in types.iter().enumerate() {
+                let base = if i == 0 { Base::B } else { Base::W };
+                if !ty.is(base) || !ty.dissatisfiable || !ty.unit {
+                    Err("miniscript_type_mismatch")?;
+                }
+            }
+
+            let zero_args = types.iter().filter(|ty| ty.zero_arg).count();
+            let one_args = types.iter().filter(|ty| ty.one_arg).count();
+            let signed = types.iter().filter(|ty| ty.signed).count();
+
+            // with k > 1, any two subexpressions can be required together
+            let mix = *k > 1
+                && types
+                    .iter()
+                    .enumerate()
+                    .any(|(i, a)| types[i + 1..].iter().any(|b| a.timelocks.mix(&b.timelocks)));
+
+            Type {
+                zero_arg: zero_args == types.len(),
+                one_arg: zero_args == types.len() - 1 && one_args == 1,
+                dissatisfiable: true,
+                unit: true,
+                // a satisfaction without signatures requires at least k of the others
+                signed: signed > types.len() - k,
+                no_timelock_mix: types.iter().all(|ty| ty.no_timelock_mix) && !mix,
+                timelocks: types
+                    .iter()
+                    .fold(Timelocks::default(), |timelocks, ty| timelocks.union(ty.timelocks)),
+                ..Type::new(Base::B)
+            }
+        }
+        Fragment::Multi(k, keys) => {
+            if keys.is_empty() || keys.len() > MAX_MULTI_KEYS {
+                Err("invalid_multi_keys_count")?;
+            }
+
+            if *k == 0 || *k > keys.len() {
+                Err("invalid_multi_threshold")?;
+            }
+
+            Type {
+                non_zero: true,
+                dissatisfiable: true,
+                unit: true,
+                signed: true,
+                ..Type::new(Base::B)
+            }
+        }
+        Fragment::Alt(x) => {
+            let x = type_of(x)?;
+            if !x.is(Base::B) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                dissatisfiable: x.dissatisfiable,
+                unit: x.unit,
+                ..x.wrapped(Base::W)
+            }
+        }
+        Fragment::Swap(x) => {
+            let x = type_of(x)?;
+            if !x.is(Base::B) || !x.one_arg {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                dissatisfiable: x.dissatisfiable,
+                unit: x.unit,
+                ..x.wrapped(Base::W)
+            }
+        }
+        Fragment::Check(x) => {
+            let x = type_of(x)?;
+            if !x.is(Base::K) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                one_arg: x.one_arg,
+                non_zero: x.non_zero,
+                dissatisfiable: x.dissatisfiable,
+                unit: true,
+                ..x.wrapped(Base::B)
+            }
+        }
+        Fragment::DupIf(x) => {
+            let x = type_of(x)?;
+            if !x.is(Base::V) || !x.zero_arg {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            // not unit: OP_IF takes any nonzero value (MINIMALIF is consensus in tapscript only)
+            Type {
+                one_arg: true,
+                non_zero: true,
+                dissatisfiable: true,
+                ..x.wrapped(Base::B)
+            }
+        }
+        Fragment::Verify(x) => {
+            let x = type_of(x)?;
+            if !x.is(Base::B) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                zero_arg: x.zero_arg,
+                one_arg: x.one_arg,
+                non_zero: x.non_zero,
+                ..x.wrapped(Base::V)
+            }
+        }
+        Fragment::NonZero(x) => {
+            let x = type_of(x)?;
+            if !x.is(Base::B) || !x.non_zero {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                one_arg: x.one_arg,
+                non_zero: true,
+                dissatisfiable: true,
+                unit: x.unit,
+                ..x.wrapped(Base::B)
+            }
+        }
+        Fragment::ZeroNotEqual(x) => {
+            let x = type_of(x)?;
+            if !x.is(Base::B) {
+                Err("miniscript_type_mismatch")?;
+            }
+
+            Type {
+                zero_arg: x.zero_arg,
+                one_arg: x.one_arg,
+                non_zero: x.non_zero,
+                dissatisfiable: x.dissatisfiable,
+                unit: true,
+                ..x.wrapped(Base::B)
+            }
+        }
+    };
+
+    Ok(ty)
+}
+
+pub fn check_timelock(n: u32) -> StdResult<()> {
+    if n == 0 || n >= MAX_TIMELOCK {
+        Err("invalid_timelock")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod types_test {
+    use super::*;
+
+    const KEY_1: &str = "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+    const KEY_2: &str = "02C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5";
+    const HASH: &str = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+
+    fn ty(s: &str) -> StdResult<Type> {
+        type_of(&Fragment::parse(
+            &s.replace("KEY_1", KEY_1).replace("KEY_2", KEY_2).replace("HASH", HASH),
+        )?)
+    }
+
+    #[test]
+    fn valid() {
+        let cases = [
+            ("pk(KEY_1)", Base::B, "ondus"),
+            ("pk_k(KEY_1)", Base::K, "ondus"),
+            ("pkh(KEY_1)", Base::B, "ndus"),
+            ("v:pk(KEY_1)", Base::V, "ons"),
+            ("older(144)", Base::B, "z"),
+            ("sha256(HASH)", Base::B, "ondu"),
+            ("and_v(v:pk(KEY_1),pk(KEY_2))", Base::B, "nus"),
+            ("and_b(pk(KEY_1),s:pk(KEY_2))", Base::B, "ndus"),
+            ("or_b(pk(KEY_1),s:pk(KEY_2))", Base::B, "dus"),
+            ("or_d(pk(KEY_1),older(144))", Base::B, "o"),
+            ("or_i(pk(KEY_1),pkh(KEY_2))", Base::B, "dus"),
+            ("andor(pk(KEY_1),older(144),pk(KEY_2))", Base::B, "ds"),
+            ("thresh(1,pk(KEY_1),s:pk(KEY_2))", Base::B, "dus"),
+            ("thresh(2,pk(KEY_1),a:sha256(HASH))", Base::B, "dus"),
+            ("multi(1,KEY_1,KEY_2)", Base::B, "ndus"),
+            ("dv:older(144)", Base::B, "ond"),
+            ("j:pk(KEY_1)", Base::B, "ondus"),
+            ("t:or_c(pk(KEY_1),v:older(144))", Base::B, "ou"),
+        ];
+
+        for (s, base, properties) in cases {
+            let ty = ty(s).unwrap();
+            let flags = [
+                ('z', ty.zero_arg),
+                ('o', ty.one_arg),
+                ('n', ty.non_zero),
+                ('d', ty.dissatisfiable),
+                ('u', ty.unit),
+                ('s', ty.signed),
+            ];
+            let actual: String = flags.iter().filter(|(_, set)| *set).map(|(c, _)| c).collect();
+
+            assert_eq!(ty.base, base, "{s}");
+            assert_eq!(actual, properties, "{s}");
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let cases = [
+            ("and_v(pk(KEY_1),pk(KEY_2))", "miniscript_type_mismatch"),
+            ("and_b(pk(KEY_1),pk(KEY_2))", "miniscript_type_mismatch"),
+            ("or_d(older(144),pk(KEY_1))", "miniscript_type_mismatch"),
+            ("s:older(144)", "miniscript_type_mismatch"),
+            ("d:older(144)", "miniscript_type_mismatch"),
+            ("c:older(144)", "miniscript_type_mismatch"),
+            ("thresh(1,pk(KEY_1),pk(KEY_2))", "miniscript_type_mismatch"),
+            ("thresh(3,pk(KEY_1),s:pk(KEY_2))", "invalid_thresh_threshold"),
+            ("multi(0,KEY_1)", "invalid_multi_threshold"),
+            ("older(0)", "invalid_timelock"),
+            ("after(2147483648)", "invalid_timelock"),
+        ];
+
+        for (s, expected) in cases {
+            assert_eq!(ty(s).expect_err("Err").to_string(), expected, "{s}");
+        }
+    }
+
+    #[test]
+    fn timelock_mix() {
+        // blocks and seconds
+        let mixed = ty("and_v(v:older(144),older(4194305))").unwrap();
+        assert!(!mixed.no_timelock_mix);
+
+        let alternatives = ty("or_i(older(144),older(4194305))").unwrap();
+        assert!(alternatives.no_timelock_mix);
+
+        let different_kinds = ty("and_v(v:older(144),after(500000000))").unwrap();
+        assert!(different_kinds.no_timelock_mix);
+
+        let thresh = ty("thresh(2,pk(KEY_1),sndv:after(100),sndv:after(500000000))").unwrap();
+        assert!(!thresh.no_timelock_mix);
+    }
+}
@@ -1,8 +1,10 @@
 use std::fmt::{Display, Formatter};
 
+use crate::block::full_block::Block;
+
 use super::{
-    fee_filter::FeeFilter, get_header::GetHeader, headers::Headers, ping::Ping, pong::Pong, send_compact::SendCompact,
-    version::Version,
+    fee_filter::FeeFilter, get_data::GetData, get_header::GetHeader, headers::Headers, ping::Ping, pong::Pong,
+    send_compact::SendCompact, version::Version,
 };
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -26,6 +28,8 @@ pub enum Commands {
     // Ref: https://github.com/bitcoin/bips/blob/master/bip-0339.mediawiki
     WtxIdRelay,
     Headers(Headers),
+    GetData(GetData),
+    Block(Block),
 }
 
 impl Display for Command {
@@ -41,6 +45,8 @@ impl Display for Command {
             WTXID_RELAY_COMMAND => "WtxIdRelay",
             SENDADDRV2_COMMAND => "SendAddrV2",
             HEADERS_COMMAND => "Headers",
+            GET_DATA_COMMAND => "GetData",
+            BLOCK_COMMAND => "Block",
             _ => panic!("unknown_command"),
         };
 
@@ -89,3 +95,11 @@ pub const SENDADDRV2_COMMAND: Command = Command {
 pub const HEADERS_COMMAND: Command = Command {
     bytes: [0x68, 0x65, 0x61, 0x64, 0x65, 0x72, 0x73, 0x00, 0x00, 0x00, 0x00, 0x00],
 };
+
+pub const GET_DATA_COMMAND: Command = Command {
+    bytes: [0x67, 0x65, 0x74, 0x64, 0x61, 0x74, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00],
+};
+
+pub const BLOCK_COMMAND: Command = Command {
+    bytes: [0x62, 0x6C, 0x6F, 0x63, 0x6B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+};
@@ -0,0 +1,61 @@
+// https://en.bitcoin.it/wiki/Protocol_documentation#getdata
+
+use crate::{hashing::hash256::Hash256, std_lib::varint::encode};
+
+// Block with witnesses (BIP144)
+pub const MSG_WITNESS_BLOCK: u32 = 0x40000002;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inventory {
+    pub inventory_type: u32, // LE
+    pub hash: Hash256,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GetData {
+    pub inventory: Vec<Inventory>,
+}
+
+impl GetData {
+    pub fn new(inventory: Vec<Inventory>) -> Self {
+        Self { inventory }
+    }
+
+    pub fn blocks(hashes: &[Hash256]) -> Self {
+        let inventory = hashes
+            .iter()
+            .map(|hash| Inventory {
+                inventory_type: MSG_WITNESS_BLOCK,
+                hash: *hash,
+            })
+            .collect();
+
+        Self { inventory }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut v = encode(self.inventory.len() as u64);
+
+        for inventory in self.inventory.iter() {
+            v.extend_from_slice(&inventory.inventory_type.to_le_bytes());
+            v.extend_from_slice(&inventory.hash.0);
+        }
+
+        v
+    }
+}
+
+#[cfg(test)]
+mod get_data_test {
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let get_data = GetData::blocks(&[Hash256::new([0xAB; 32])]);
+        let serialized = get_data.serialize();
+
+        assert_eq!(serialized.len(), 1 + 4 + 32);
+        assert_eq!(serialized[..5], [0x01, 0x02, 0x00, 0x00, 0x40]);
+        assert_eq!(serialized[5..], [0xAB; 32]);
+    }
+}
@@ -1,6 +1,7 @@
 pub mod command;
 pub mod constants;
 pub mod fee_filter;
+pub mod get_data;
 pub mod get_header;
 pub mod headers;
 pub mod ip_address;
@@ -3,7 +3,10 @@
 use std::fmt::{Display, Formatter};
 
 use crate::{
-    flags::network_magic::NetworkMagic, hashing::hash256::Hash256, network::headers::Headers,
+    block::full_block::Block,
+    flags::{network::Network, network_magic::NetworkMagic},
+    hashing::hash256::Hash256,
+    network::headers::Headers,
     std_lib::std_result::StdResult,
 };
 
@@ -84,6 +87,15 @@ impl From<NetworkMessage> for StdResult<Commands> {
                 let payload = Headers::deserialize(&val.payload)?;
                 Ok(Commands::Headers(payload))
             }
+            BLOCK_COMMAND => {
+                let network = match val.magic {
+                    NetworkMagic::Mainnet => Network::Mainnet,
+                    _ => Network::Testnet,
+                };
+
+                let payload = Block::deserialize(&val.payload, network)?;
+                Ok(Commands::Block(payload))
+            }
             _ => panic!("unknown_command: {:?}", val.command),
         }
     }
@@ -0,0 +1,175 @@
+/*
+    Finalizer: the ScriptSig and the witness of each input from its partial signatures.
+        P2PK            ScriptSig: <signature>
+        P2PKH           ScriptSig: <signature> <public key>
+        P2WPKH          witness: <signature> <public key>
+        P2SH-P2WPKH     ScriptSig: <redeem script>, witness: <signature> <public key>
+        multisig        bare, P2SH, P2WSH, P2SH-P2WSH (see `transaction::multisig`), with m partial signatures
+        P2TR            witness: <signature> (key path, PSBT_IN_TAP_KEY_SIG)
+    Then only the UTXO, the final ScriptSig and witness and the unknown pairs are kept.
+    Inputs spending other scripts, or without the signatures needed, are left as they are.
+*/
+
+use crate::{
+    hashing::hash160::hash160,
+    scripting::{script_lang::ScriptLang, token::Token},
+    std_lib::std_result::StdResult,
+    transaction::{
+        multisig::{Multisig, MultisigType},
+        script::Script,
+    },
+};
+
+use super::{
+    constants::*,
+    map::{serialize_witness, PsbtMap},
+    partially_signed_tx::Psbt,
+};
+
+// Removed from a finalized input
+const NOT_FINAL_FIELDS: [u64; 15] = [
+    PSBT_IN_PARTIAL_SIG,
+    PSBT_IN_SIGHASH_TYPE,
+    PSBT_IN_REDEEM_SCRIPT,
+    PSBT_IN_WITNESS_SCRIPT,
+    PSBT_IN_BIP32_DERIVATION,
+    PSBT_IN_RIPEMD160,
+    PSBT_IN_SHA256,
+    PSBT_IN_HASH160,
+    PSBT_IN_HASH256,
+    PSBT_IN_TAP_KEY_SIG,
+    PSBT_IN_TAP_SCRIPT_SIG,
+    PSBT_IN_TAP_LEAF_SCRIPT,
+    PSBT_IN_TAP_BIP32_DERIVATION,
+    PSBT_IN_TAP_INTERNAL_KEY,
+    PSBT_IN_TAP_MERKLE_ROOT,
+];
+
+// ScriptSig and witness
+type FinalScripts = (Vec<u8>, Vec<Vec<u8>>);
+
+impl Psbt {
+    // Number of inputs finalized
+    pub fn finalize(&mut self) -> StdResult<usize> {
+        let mut finalized: usize = 0;
+
+        for index in 0..self.inputs.len() {
+            if self.is_input_finalized(index) {
+                continue;
+            }
+
+            let utxo = self.utxo(index)?;
+            let Some((script_sig, witness)) = final_scripts(&self.inputs[index], &utxo.script_pub_key)? else {
+                continue;
+            };
+
+            let input = &mut self.inputs[index];
+            for key_type in NOT_FINAL_FIELDS {
+                input.remove(key_type);
+            }
+
+            if !script_sig.is_empty() {
+                input.insert(PSBT_IN_FINAL_SCRIPTSIG, vec![], script_sig);
+            }
+
+            if !witness.is_empty() {
+                input.insert(PSBT_IN_FINAL_SCRIPTWITNESS, vec![], serialize_witness(&witness));
+            }
+
+            finalized += 1;
+        }
+
+        Ok(finalized)
+    }
+}
+
+// None if the script is not supported or a signature is missing
+fn final_scripts(input: &PsbtMap, script_pub_key: &Script) -> StdResult<Option<FinalScripts>> {
+    let raw = script_pub_key.raw.as_slice();
+
+    let final_scripts = match raw {
+        [0x21, public_key @ .., 0xAC] if raw.len() == 35 => p2pk(input, public_key)?,
+        [0x41, public_key @ .., 0xAC] if raw.len() == 67 => p2pk(input, public_key)?,
+        [0x76, 0xA9, 0x14, public_key_hash @ .., 0x88, 0xAC] if raw.len() == 25 => {
+            match signature_by_hash(input, public_key_hash) {
+                Some((public_key, signature)) => Some((push(&[signature, public_key])?, vec![])),
+                None => None,
+            }
+        }
+        [0x00, 0x14, public_key_hash @ ..] if raw.len() == 22 => signature_by_hash(input, public_key_hash)
+            .map(|(public_key, signature)| (vec![], vec![signature.to_vec(), public_key.to_vec()])),
+        [0xA9, 0x14, .., 0x87] if raw.len() == 23 => match input.get(PSBT_IN_REDEEM_SCRIPT) {
+            Some(redeem_script @ [0x00, 0x14, public_key_hash @ ..]) if redeem_script.len() == 22 => {
+                match signature_by_hash(input, public_key_hash) {
+                    Some((public_key, signature)) => {
+                        Some((push(&[redeem_script])?, vec![signature.to_vec(), public_key.to_vec()]))
+                    }
+                    None => None,
+                }
+            }
+            Some(redeem_script @ [0x00, 0x20, ..]) if redeem_script.len() == 34 => {
+                match input
+                    .get(PSBT_IN_WITNESS_SCRIPT)
+                    .and_then(|witness_script| multisig(input, witness_script, MultisigType::P2wsh))
+                {
+                    Some((_, witness)) => Some((push(&[redeem_script])?, witness)),
+                    None => None,
+                }
+            }
+            Some(redeem_script) => multisig(input, redeem_script, MultisigType::P2sh),
+            None => None,
+        },
+        [0x00, 0x20, ..] if raw.len() == 34 => input
+            .get(PSBT_IN_WITNESS_SCRIPT)
+            .and_then(|witness_script| multisig(input, witness_script, MultisigType::P2wsh)),
+        [0x51, 0x20, ..] if raw.len() == 34 => input
+            .get(PSBT_IN_TAP_KEY_SIG)
+            .map(|signature| (vec![], vec![signature.to_vec()])),
+        _ => multisig(input, raw, MultisigType::Bare),
+    };
+
+    Ok(final_scripts)
+}
+
+fn p2pk(input: &PsbtMap, public_key: &[u8]) -> StdResult<Option<FinalScripts>> {
+    match input.get_with_key(PSBT_IN_PARTIAL_SIG, public_key) {
+        Some(signature) => Ok(Some((push(&[signature])?, vec![]))),
+        None => Ok(None),
+    }
+}
+
+// Partial signatures of the first m keys that signed, if `redeem_script` is a multisig script
+fn multisig(input: &PsbtMap, redeem_script: &[u8], multisig_type: MultisigType) -> Option<FinalScripts> {
+    let multisig = Multisig::from_redeem_script(&Script::new_from_raw(redeem_script.to_vec()), multisig_type).ok()?;
+
+    let signatures: Vec<&[u8]> = multisig
+        .public_keys()
+        .iter()
+        .filter_map(|public_key| input.get_with_key(PSBT_IN_PARTIAL_SIG, public_key))
+        .take(multisig.m())
+        .collect();
+
+    if signatures.len() < multisig.m() {
+        return None;
+    }
+
+    let (script_sig, witness) = multisig.final_scripts(&signatures);
+
+    Some((script_sig.raw, witness))
+}
+
+// Public key and signature of the partial signature of the key with `public_key_hash`
+fn signature_by_hash<'a>(input: &'a PsbtMap, public_key_hash: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    input
+        .all(PSBT_IN_PARTIAL_SIG)
+        .into_iter()
+        .find(|pair| hash160(&pair.key_data) == public_key_hash)
+        .map(|pair| (pair.key_data.as_slice(), pair.value.as_slice()))
+}
+
+// Script pushing `elements`
+fn push(elements: &[&[u8]]) -> StdResult<Vec<u8>> {
+    let tokens: Vec<Token> = elements.iter().map(|e| Token::Element(e.to_vec())).collect();
+
+    ScriptLang::from_tokens(tokens).serialize()
+}
@@ -0,0 +1,301 @@
+/*
+    Key-value pairs of a PSBT map, terminated by 0x00:
+        <key length> <key type> <key data> <value length> <value>
+    Lengths and key type are compact sizes. Pairs are kept in the order they are read or inserted.
+*/
+
+use crate::std_lib::{
+    std_result::StdResult,
+    varint::{decode, encode},
+};
+
+const SEPARATOR: u8 = 0x00;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyValue {
+    pub key_type: u64,
+    pub key_data: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+impl KeyValue {
+    pub fn new(key_type: u64, key_data: Vec<u8>, value: Vec<u8>) -> Self {
+        Self {
+            key_type,
+            key_data,
+            value,
+        }
+    }
+
+    // Key type and key data
+    pub fn key(&self) -> Vec<u8> {
+        [encode(self.key_type), self.key_data.clone()].concat()
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let key = self.key();
+
+        [
+            encode(key.len() as u64).as_slice(),
+            key.as_slice(),
+            encode(self.value.len() as u64).as_slice(),
+            self.value.as_slice(),
+        ]
+        .concat()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PsbtMap(Vec<KeyValue>);
+
+impl PsbtMap {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    // The map from `cursor` to its separator, and the cursor after it
+    pub fn deserialize(data: &[u8], cursor: usize) -> StdResult<(Self, usize)> {
+        let mut cur = cursor;
+        let mut map = Self::new();
+
+        loop {
+            let (key_length, c) = read_compact_size(data, cur)?;
+            cur = c;
+
+            if key_length == 0 {
+                break;
+            }
+
+            let key = read_bytes(data, cur, key_length)?;
+            cur += key.len();
+
+            let (key_type, key_type_length) = read_compact_size(key, 0)?;
+            let key_data = key[key_type_length..].to_vec();
+
+            let (value_length, c) = read_compact_size(data, cur)?;
+            cur = c;
+
+            let value = read_bytes(data, cur, value_length)?.to_vec();
+            cur += value.len();
+
+            if map.get_with_key(key_type, &key_data).is_some() {
+                log::error!(
+                    "PSBT duplicate key type: {:#04X}, key data: {:02X?}",
+                    key_type,
+                    key_data
+                );
+                Err("psbt_duplicate_key")?;
+            }
+
+            map.0.push(KeyValue::new(key_type, key_data, value));
+        }
+
+        Ok((map, cur))
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let Self(pairs) = self;
+
+        let mut serialized: Vec<u8> = pairs.iter().flat_map(|p| p.serialize()).collect();
+        serialized.push(SEPARATOR);
+
+        serialized
+    }
+
+    // Value of a key without key data
+    pub fn get(&self, key_type: u64) -> Option<&[u8]> {
+        self.get_with_key(key_type, &[])
+    }
+
+    pub fn get_with_key(&self, key_type: u64, key_data: &[u8]) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|p| p.key_type == key_type && p.key_data == key_data)
+            .map(|p| p.value.as_slice())
+    }
+
+    // All the pairs of `key_type`, whatever the key data
+    pub fn all(&self, key_type: u64) -> Vec<&KeyValue> {
+        self.0.iter().filter(|p| p.key_type == key_type).collect()
+    }
+
+    pub fn contains(&self, key_type: u64) -> bool {
+        self.0.iter().any(|p| p.key_type == key_type)
+    }
+
+    pub fn pairs(&self) -> &[KeyValue] {
+        &self.0
+    }
+
+    // Replaces the value if the key is already in the map
+    pub fn insert(&mut self, key_type: u64, key_data: Vec<u8>, value: Vec<u8>) {
+        match self
+            .0
+            .iter_mut()
+            .find(|p| p.key_type == key_type && p.key_data == key_data)
+        {
+            Some(pair) => pair.value = value,
+            None => self.0.push(KeyValue::new(key_type, key_data, value)),
+        }
+    }
+
+    // Removes all the pairs of `key_type`
+    pub fn remove(&mut self, key_type: u64) {
+        self.0.retain(|p| p.key_type != key_type);
+    }
+
+    // Adds the keys of `other` not in the map, the values already in the map are kept
+    pub fn merge(&mut self, other: &PsbtMap) {
+        for pair in other.0.iter() {
+            if self.get_with_key(pair.key_type, &pair.key_data).is_none() {
+                self.0.push(pair.clone());
+            }
+        }
+    }
+}
+
+pub fn read_compact_size(data: &[u8], cursor: usize) -> StdResult<(u64, usize)> {
+    match decode(data, cursor) {
+        Ok(v) => Ok((v.value, cursor + v.length)),
+        Err(_) => Err("psbt_unexpected_end")?,
+    }
+}
+
+pub fn read_bytes(data: &[u8], cursor: usize, length: u64) -> StdResult<&[u8]> {
+    let end = usize::try_from(length)
+        .ok()
+        .and_then(|length| cursor.checked_add(length));
+
+    match end.and_then(|end| data.get(cursor..end)) {
+        Some(bytes) => Ok(bytes),
+        None => Err("psbt_unexpected_end")?,
+    }
+}
+
+// Little endian 32 bits value
+pub fn value_u32(value: &[u8]) -> StdResult<u32> {
+    match <[u8; 4]>::try_from(value) {
+        Ok(bytes) => Ok(u32::from_le_bytes(bytes)),
+        Err(_) => Err("psbt_invalid_value")?,
+    }
+}
+
+// Witness stack as in transactions: items count, then each item with its length
+pub fn serialize_witness(witness: &[Vec<u8>]) -> Vec<u8> {
+    let mut serialized = encode(witness.len() as u64);
+
+    for item in witness {
+        serialized.extend(encode(item.len() as u64));
+        serialized.extend(item);
+    }
+
+    serialized
+}
+
+pub fn deserialize_witness(value: &[u8]) -> StdResult<Vec<Vec<u8>>> {
+    let (count, mut cursor) = read_compact_size(value, 0)?;
+
+    let mut witness: Vec<Vec<u8>> = vec![];
+    for _ in 0..count {
+        let (length, c) = read_compact_size(value, cursor)?;
+        let item = read_bytes(value, c, length)?;
+        cursor = c + item.len();
+
+        witness.push(item.to_vec());
+    }
+
+    if cursor != value.len() {
+        Err("psbt_invalid_value")?;
+    }
+
+    Ok(witness)
+}
+
+#[cfg(test)]
+mod map_test {
+    use crate::std_lib::vector::hex_string_to_bytes;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_and_serialize() {
+        // <01 00> <02 aabb>, <03 06 0102> <01 cc>, separator, then what follows the map
+        let data = hex_string_to_bytes("010002aabb0306010201cc00ff").unwrap();
+
+        let (map, cursor) = PsbtMap::deserialize(&data, 0).unwrap();
+        assert_eq!(cursor, 12);
+        assert_eq!(map.get(0x00), Some([0xAA, 0xBB].as_slice()));
+        assert_eq!(map.get_with_key(0x06, &[0x01, 0x02]), Some([0xCC].as_slice()));
+        assert_eq!(map.get(0x06), None);
+        assert_eq!(map.all(0x06).len(), 1);
+        assert_eq!(map.serialize(), data[..12]);
+    }
+
+    #[test]
+    fn deserialize_compact_size_key_type() {
+        // key type 0xFD 0x0001 (256)
+        let data = hex_string_to_bytes("03fd00010000").unwrap();
+
+        let (map, _) = PsbtMap::deserialize(&data, 0).unwrap();
+        assert_eq!(map.get(0x100), Some([].as_slice()));
+        assert_eq!(map.serialize(), data);
+    }
+
+    #[test]
+    fn deserialize_duplicate_key() {
+        let data = hex_string_to_bytes("010001aa010001bb00").unwrap();
+
+        let err = PsbtMap::deserialize(&data, 0).expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_duplicate_key");
+    }
+
+    #[test]
+    fn deserialize_unexpected_end() {
+        for hex in ["", "0100", "010005aabb", "02", "0100fd"] {
+            let data = hex_string_to_bytes(hex).unwrap();
+
+            let err = PsbtMap::deserialize(&data, 0).expect_err("Err");
+            assert_eq!(err.to_string(), "psbt_unexpected_end");
+        }
+    }
+
+    #[test]
+    fn insert_and_merge() {
+        let mut map = PsbtMap::new();
+        map.insert(0x02, vec![0x01], vec![0xAA]);
+        map.insert(0x02, vec![0x01], vec![0xBB]);
+        assert_eq!(map.all(0x02).len(), 1);
+
+        let mut other = PsbtMap::new();
+        other.insert(0x02, vec![0x01], vec![0xCC]);
+        other.insert(0x02, vec![0x02], vec![0xDD]);
+
+        map.merge(&other);
+        assert_eq!(map.get_with_key(0x02, &[0x01]), Some([0xBB].as_slice()));
+        assert_eq!(map.get_with_key(0x02, &[0x02]), Some([0xDD].as_slice()));
+
+        map.remove(0x02);
+        assert!(!map.contains(0x02));
+    }
+
+    #[test]
+    fn witness() {
+        let witness = vec![vec![0xAA, 0xBB], vec![], vec![0xCC]];
+        let serialized = serialize_witness(&witness);
+
+        assert_eq!(serialized, hex_string_to_bytes("0302aabb0001cc").unwrap());
+        assert_eq!(deserialize_witness(&serialized).unwrap(), witness);
+
+        let err = deserialize_witness(&hex_string_to_bytes("0302aabb00").unwrap()).expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_unexpected_end");
+
+        let err = deserialize_witness(&hex_string_to_bytes("0100ff").unwrap()).expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_invalid_value");
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(value_u32(&[0x01, 0x00, 0x00, 0x00]).unwrap(), 1);
+        assert_eq!(value_u32(&[0x01]).expect_err("Err").to_string(), "psbt_invalid_value");
+    }
+}
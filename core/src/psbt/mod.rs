@@ -0,0 +1,6 @@
+pub mod constants;
+pub mod finalizer;
+pub mod map;
+pub mod partially_signed_tx;
+pub mod signer;
+pub mod validation;
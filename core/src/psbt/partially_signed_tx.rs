@@ -0,0 +1,534 @@
+/*
+    Partially Signed Bitcoin Transaction, version 0 (BIP174).
+
+    Serialized as the magic, the global map, a map for each input and a map for each output.
+    The global map holds the unsigned transaction. Version 2 (BIP370) is not supported.
+
+    Roles:
+        Creator     `from_unsigned_tx`
+        Updater     `set_non_witness_utxo`, `set_witness_utxo`, `set_redeem_script`, ...
+        Signer      `sign` (signer.rs)
+        Combiner    `combine`
+        Finalizer   `finalize` (finalizer.rs)
+        Extractor   `extract`
+*/
+
+use crate::{
+    flags::{network::Network, sighash::SigHash},
+    std_lib::{
+        base64::{base64_decode, base64_encode},
+        std_result::StdResult,
+    },
+    transaction::{script::Script, tx::Tx, tx_out::TxOut},
+    wallet::bip32::{DerivationPath, ExtendedPublicKey, Fingerprint},
+};
+
+use super::{
+    constants::*,
+    map::{deserialize_witness, value_u32, PsbtMap},
+    validation::{validate_global, validate_input, validate_non_witness_utxos, validate_output},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Psbt {
+    pub global: PsbtMap,
+    pub inputs: Vec<PsbtMap>,
+    pub outputs: Vec<PsbtMap>,
+    pub network: Network,
+}
+
+impl Psbt {
+    // Version 0 PSBT of `tx`, that must have no ScriptSig and no witness
+    pub fn from_unsigned_tx(tx: &Tx) -> StdResult<Self> {
+        for index in 0..tx.input_len() {
+            if !tx.input(index)?.script_sig.raw.is_empty() || !tx.input(index)?.witnesses.is_empty() {
+                Err("psbt_unsigned_tx_with_script_sig")?;
+            }
+        }
+
+        let mut global = PsbtMap::new();
+        global.insert(PSBT_GLOBAL_UNSIGNED_TX, vec![], tx.serialize());
+
+        Ok(Self {
+            global,
+            inputs: vec![PsbtMap::new(); tx.input_len()],
+            outputs: vec![PsbtMap::new(); tx.output_len()],
+            network: tx.network,
+        })
+    }
+
+    pub fn deserialize(data: &[u8], network: Network) -> StdResult<Self> {
+        if !data.starts_with(&PSBT_MAGIC) {
+            Err("psbt_invalid_magic")?;
+        }
+
+        let (global, mut cursor) = PsbtMap::deserialize(data, PSBT_MAGIC.len())?;
+
+        let version = match global.get(PSBT_GLOBAL_VERSION) {
+            Some(value) => value_u32(value)?,
+            None => PSBT_V0,
+        };
+
+        if version != PSBT_V0 {
+            log::error!("PSBT version: {}", version);
+            Err("psbt_unsupported_version")?;
+        }
+
+        validate_global(&global)?;
+
+        let tx = unsigned_tx(&global, network)?;
+
+        let mut inputs: Vec<PsbtMap> = vec![];
+        for _ in 0..tx.input_len() {
+            let (input, c) = PsbtMap::deserialize(data, cursor)?;
+            cursor = c;
+
+            validate_input(&input)?;
+            inputs.push(input);
+        }
+
+        let mut outputs: Vec<PsbtMap> = vec![];
+        for _ in 0..tx.output_len() {
+            let (output, c) = PsbtMap::deserialize(data, cursor)?;
+            cursor = c;
+
+            validate_output(&output)?;
+            outputs.push(output);
+        }
+
+        if cursor != data.len() {
+            Err("psbt_trailing_data")?;
+        }
+
+        let psbt = Self {
+            global,
+            inputs,
+            outputs,
+            network,
+        };
+
+        validate_non_witness_utxos(&psbt)?;
+
+        Ok(psbt)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut serialized = PSBT_MAGIC.to_vec();
+
+        serialized.extend(self.global.serialize());
+        for input in self.inputs.iter() {
+            serialized.extend(input.serialize());
+        }
+        for output in self.outputs.iter() {
+            serialized.extend(output.serialize());
+        }
+
+        serialized
+    }
+
+    pub fn from_base64(s: &str, network: Network) -> StdResult<Self> {
+        Self::deserialize(&base64_decode(s.trim())?, network)
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64_encode(&self.serialize())
+    }
+
+    // The transaction without signatures
+    pub fn unsigned_tx(&self) -> StdResult<Tx> {
+        unsigned_tx(&self.global, self.network)
+    }
+
+    // Output spent by input `index`, from the witness UTXO or the previous transaction
+    pub fn utxo(&self, index: usize) -> StdResult<TxOut> {
+        let input = self.input_map(index)?;
+
+        if let Some(value) = input.get(PSBT_IN_WITNESS_UTXO) {
+            let (tx_out, _) = TxOut::deserialize(value, 0)?;
+            return Ok(tx_out);
+        }
+
+        if let Some(value) = input.get(PSBT_IN_NON_WITNESS_UTXO) {
+            let previous_tx = Tx::deserialize(value, self.network)?;
+            let previous_transaction_index = self.unsigned_tx()?.input(index)?.previous_transaction_index;
+
+            return Ok(previous_tx.output(previous_transaction_index as usize)?.clone());
+        }
+
+        Err("psbt_missing_utxo")?
+    }
+
+    pub fn is_input_finalized(&self, index: usize) -> bool {
+        self.inputs
+            .get(index)
+            .is_some_and(|input| input.contains(PSBT_IN_FINAL_SCRIPTSIG) || input.contains(PSBT_IN_FINAL_SCRIPTWITNESS))
+    }
+
+    // Updater: the transaction of the output spent by input `index`
+    pub fn set_non_witness_utxo(&mut self, index: usize, previous_tx: &Tx) -> StdResult<()> {
+        self.input_map(index)?;
+
+        if previous_tx.id_as_integer() != self.unsigned_tx()?.input(index)?.previous_transaction_id {
+            Err("psbt_non_witness_utxo_mismatch")?;
+        }
+
+        self.input_map_mut(index)?
+            .insert(PSBT_IN_NON_WITNESS_UTXO, vec![], previous_tx.serialize());
+
+        Ok(())
+    }
+
+    // Updater: the output spent by input `index`
+    pub fn set_witness_utxo(&mut self, index: usize, utxo: &TxOut) -> StdResult<()> {
+        self.input_map_mut(index)?
+            .insert(PSBT_IN_WITNESS_UTXO, vec![], utxo.serialize());
+
+        Ok(())
+    }
+
+    pub fn set_redeem_script(&mut self, index: usize, redeem_script: &Script) -> StdResult<()> {
+        self.input_map_mut(index)?
+            .insert(PSBT_IN_REDEEM_SCRIPT, vec![], redeem_script.raw.clone());
+
+        Ok(())
+    }
+
+    pub fn set_witness_script(&mut self, index: usize, witness_script: &Script) -> StdResult<()> {
+        self.input_map_mut(index)?
+            .insert(PSBT_IN_WITNESS_SCRIPT, vec![], witness_script.raw.clone());
+
+        Ok(())
+    }
+
+    pub fn set_sighash_type(&mut self, index: usize, sighash: SigHash) -> StdResult<()> {
+        self.input_map_mut(index)?
+            .insert(PSBT_IN_SIGHASH_TYPE, vec![], (sighash as u32).to_le_bytes().to_vec());
+
+        Ok(())
+    }
+
+    // Updater: origin of a public key of the script spent by input `index`
+    pub fn add_bip32_derivation(
+        &mut self,
+        index: usize,
+        public_key_sec: &[u8],
+        fingerprint: Fingerprint,
+        path: &DerivationPath,
+    ) -> StdResult<()> {
+        self.input_map_mut(index)?.insert(
+            PSBT_IN_BIP32_DERIVATION,
+            public_key_sec.to_vec(),
+            serialize_key_origin(fingerprint, path),
+        );
+
+        Ok(())
+    }
+
+    pub fn set_output_redeem_script(&mut self, index: usize, redeem_script: &Script) -> StdResult<()> {
+        self.output_map_mut(index)?
+            .insert(PSBT_OUT_REDEEM_SCRIPT, vec![], redeem_script.raw.clone());
+
+        Ok(())
+    }
+
+    pub fn set_output_witness_script(&mut self, index: usize, witness_script: &Script) -> StdResult<()> {
+        self.output_map_mut(index)?
+            .insert(PSBT_OUT_WITNESS_SCRIPT, vec![], witness_script.raw.clone());
+
+        Ok(())
+    }
+
+    // Updater: origin of a public key of output `index` (e.g. the change)
+    pub fn add_output_bip32_derivation(
+        &mut self,
+        index: usize,
+        public_key_sec: &[u8],
+        fingerprint: Fingerprint,
+        path: &DerivationPath,
+    ) -> StdResult<()> {
+        self.output_map_mut(index)?.insert(
+            PSBT_OUT_BIP32_DERIVATION,
+            public_key_sec.to_vec(),
+            serialize_key_origin(fingerprint, path),
+        );
+
+        Ok(())
+    }
+
+    // Updater: `xpub` and the origin of it (fingerprint of the master key and path)
+    pub fn add_xpub(&mut self, xpub: &ExtendedPublicKey, fingerprint: Fingerprint, path: &DerivationPath) {
+        self.global.insert(
+            PSBT_GLOBAL_XPUB,
+            xpub.serialize(),
+            serialize_key_origin(fingerprint, path),
+        );
+    }
+
+    /*
+        Combiner: the pairs of `other` are added to the maps, the values already in the maps are kept.
+        Both must be of the same transaction.
+    */
+    pub fn combine(&mut self, other: &Psbt) -> StdResult<()> {
+        if self.inputs.len() != other.inputs.len()
+            || self.outputs.len() != other.outputs.len()
+            || self.unsigned_tx()?.id() != other.unsigned_tx()?.id()
+        {
+            Err("psbt_combine_different_tx")?;
+        }
+
+        self.global.merge(&other.global);
+        for (input, other_input) in self.inputs.iter_mut().zip(other.inputs.iter()) {
+            input.merge(other_input);
+        }
+        for (output, other_output) in self.outputs.iter_mut().zip(other.outputs.iter()) {
+            output.merge(other_output);
+        }
+
+        Ok(())
+    }
+
+    // Extractor: the signed transaction, every input must be finalized
+    pub fn extract(&self) -> StdResult<Tx> {
+        let mut tx = self.unsigned_tx()?;
+
+        for (index, input) in self.inputs.iter().enumerate() {
+            if !self.is_input_finalized(index) {
+                log::error!("PSBT input {} not finalized", index);
+                Err("psbt_input_not_finalized")?;
+            }
+
+            if let Some(script_sig) = input.get(PSBT_IN_FINAL_SCRIPTSIG) {
+                tx.substitute_script(index, Script::new_from_raw(script_sig.to_vec()));
+            }
+
+            if let Some(witness) = input.get(PSBT_IN_FINAL_SCRIPTWITNESS) {
+                tx.set_witnesses(index, deserialize_witness(witness)?);
+            }
+        }
+
+        Ok(tx)
+    }
+
+    pub fn input_map(&self, index: usize) -> StdResult<&PsbtMap> {
+        match self.inputs.get(index) {
+            Some(input) => Ok(input),
+            None => Err("psbt_input_index_out_of_bounds")?,
+        }
+    }
+
+    fn input_map_mut(&mut self, index: usize) -> StdResult<&mut PsbtMap> {
+        match self.inputs.get_mut(index) {
+            Some(input) => Ok(input),
+            None => Err("psbt_input_index_out_of_bounds")?,
+        }
+    }
+
+    fn output_map_mut(&mut self, index: usize) -> StdResult<&mut PsbtMap> {
+        match self.outputs.get_mut(index) {
+            Some(output) => Ok(output),
+            None => Err("psbt_output_index_out_of_bounds")?,
+        }
+    }
+}
+
+/*
+    The unsigned transaction of the global map: serialized without witnesses, with empty ScriptSigs.
+*/
+fn unsigned_tx(global: &PsbtMap, network: Network) -> StdResult<Tx> {
+    let value = match global.get(PSBT_GLOBAL_UNSIGNED_TX) {
+        Some(value) => value,
+        None => Err("psbt_missing_unsigned_tx")?,
+    };
+
+    let tx = Tx::deserialize(value, network)?;
+    if tx.serialize() != value {
+        Err("psbt_invalid_unsigned_tx")?;
+    }
+
+    for index in 0..tx.input_len() {
+        if !tx.input(index)?.script_sig.raw.is_empty() {
+            Err("psbt_unsigned_tx_with_script_sig")?;
+        }
+    }
+
+    Ok(tx)
+}
+
+// Fingerprint of the master key and the child numbers (little endian)
+pub fn serialize_key_origin(fingerprint: Fingerprint, path: &DerivationPath) -> Vec<u8> {
+    let mut serialized = fingerprint.to_vec();
+
+    for child_number in path.child_numbers() {
+        serialized.extend(child_number.to_le_bytes());
+    }
+
+    serialized
+}
+
+#[cfg(test)]
+mod partially_signed_tx_test {
+    use crate::{
+        flags::network::Network,
+        keys::key::Key,
+        std_lib::{integer_extended::IntegerExtended, vector::hex_string_to_bytes},
+        transaction::{
+            multisig::{Multisig, MultisigInput, MultisigType},
+            script::Script,
+            tx::Tx,
+            tx_in::TxIn,
+            tx_out::TxOut,
+        },
+    };
+    use rug::Integer;
+
+    use super::*;
+
+    // BIP143 native P2WPKH example: a P2PK input and a P2WPKH input
+    const SERIALIZED_SEGWIT_UNSIGNED: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const SERIALIZED_SEGWIT_SIGNED: &str = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+
+    // Version 2 transaction spending output `index` of a previous transaction, with an output of 90000
+    fn one_input_tx(index: u32) -> Tx {
+        let mut tx = Tx::new(Network::Testnet);
+        tx.set_version(2);
+        tx.add_input(TxIn::new(
+            Integer::from_hex_str("9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff"),
+            index,
+            Script::new_empty(),
+            0xFFFFFFFF,
+            Network::Testnet,
+        ));
+        tx.add_output(TxOut::new(90000, Script::new_from_raw(vec![0x51])));
+
+        tx
+    }
+
+    fn bip143_psbt() -> Psbt {
+        let tx = Tx::deserialize(
+            &hex_string_to_bytes(SERIALIZED_SEGWIT_UNSIGNED).unwrap(),
+            Network::Mainnet,
+        )
+        .unwrap();
+        let mut psbt = Psbt::from_unsigned_tx(&tx).unwrap();
+
+        let p2pk =
+            hex_string_to_bytes("2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac").unwrap();
+        let p2wpkh = hex_string_to_bytes("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap();
+        psbt.set_witness_utxo(0, &TxOut::new(625000000, Script::new_from_raw(p2pk)))
+            .unwrap();
+        psbt.set_witness_utxo(1, &TxOut::new(600000000, Script::new_from_raw(p2wpkh)))
+            .unwrap();
+
+        psbt
+    }
+
+    #[test]
+    fn sign_finalize_and_extract() {
+        let mut psbt = bip143_psbt();
+
+        let key0 = Key::new(Integer::from_hex_str(
+            "bbc27228ddcb9209d7fd6f36b02f7dfa6252af40bb2f1cbc7a557da8027ff866",
+        ));
+        let key1 = Key::new(Integer::from_hex_str(
+            "619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9",
+        ));
+
+        assert_eq!(psbt.sign(&key0).unwrap(), 1);
+        assert_eq!(psbt.sign(&key1).unwrap(), 1);
+
+        let signature = psbt.inputs[1].get_with_key(PSBT_IN_PARTIAL_SIG, &key1.public_key_sec());
+        assert_eq!(
+            signature,
+            Some(hex_string_to_bytes("304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01").unwrap().as_slice())
+        );
+
+        let err = psbt.extract().expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_input_not_finalized");
+
+        assert_eq!(psbt.finalize().unwrap(), 2);
+        assert!(!psbt.inputs[0].contains(PSBT_IN_PARTIAL_SIG));
+
+        let tx = psbt.extract().unwrap();
+        assert_eq!(
+            tx.serialize_with_witnesses(),
+            hex_string_to_bytes(SERIALIZED_SEGWIT_SIGNED).unwrap()
+        );
+    }
+
+    #[test]
+    fn base64_roundtrip() {
+        let psbt = bip143_psbt();
+
+        let decoded = Psbt::from_base64(&psbt.to_base64(), Network::Mainnet).unwrap();
+        assert_eq!(decoded, psbt);
+    }
+
+    #[test]
+    fn from_unsigned_tx_with_script_sig() {
+        let tx = Tx::deserialize(
+            &hex_string_to_bytes(SERIALIZED_SEGWIT_SIGNED).unwrap(),
+            Network::Mainnet,
+        )
+        .unwrap();
+
+        let err = Psbt::from_unsigned_tx(&tx).expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_unsigned_tx_with_script_sig");
+    }
+
+    #[test]
+    fn combine_different_tx() {
+        let mut psbt = bip143_psbt();
+
+        let other = Psbt::from_unsigned_tx(&one_input_tx(0)).unwrap();
+
+        let err = psbt.combine(&other).expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_combine_different_tx");
+    }
+
+    #[test]
+    fn sign_and_finalize_multisig() {
+        let keys: Vec<Key> = (1..=3).map(|k| Key::new(Integer::from(k * 1000))).collect();
+        let public_keys: Vec<Vec<u8>> = keys.iter().map(|k| k.public_key_sec()).collect();
+
+        for multisig_type in [MultisigType::Bare, MultisigType::P2sh, MultisigType::P2wsh] {
+            let multisig = Multisig::new_sorted(2, public_keys.clone(), multisig_type).unwrap();
+
+            let mut psbt = Psbt::from_unsigned_tx(&one_input_tx(0)).unwrap();
+            psbt.set_witness_utxo(0, &TxOut::new(100000, multisig.script_pub_key()))
+                .unwrap();
+            match multisig_type {
+                MultisigType::Bare => {}
+                MultisigType::P2sh => psbt.set_redeem_script(0, &multisig.redeem_script()).unwrap(),
+                MultisigType::P2wsh => psbt.set_witness_script(0, &multisig.redeem_script()).unwrap(),
+            }
+
+            assert_eq!(psbt.sign(&keys[0]).unwrap(), 1);
+            assert_eq!(psbt.finalize().unwrap(), 0);
+
+            assert_eq!(psbt.sign(&keys[2]).unwrap(), 1);
+            assert_eq!(psbt.finalize().unwrap(), 1);
+
+            // the same signatures as signing the transaction directly
+            let mut tx = psbt.unsigned_tx().unwrap();
+            let mut input = MultisigInput::new(&multisig, 0, 100000);
+            input.sign(&tx, &keys[0]).unwrap();
+            input.sign(&tx, &keys[2]).unwrap();
+            input.finalize(&mut tx).unwrap();
+
+            assert_eq!(
+                psbt.extract().unwrap().serialize_with_witnesses(),
+                tx.serialize_with_witnesses()
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut psbt = Psbt::from_unsigned_tx(&one_input_tx(0)).unwrap();
+        psbt.global
+            .insert(PSBT_GLOBAL_VERSION, vec![], 2u32.to_le_bytes().to_vec());
+
+        let err = Psbt::deserialize(&psbt.serialize(), Network::Testnet).expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_unsupported_version");
+    }
+}
@@ -0,0 +1,143 @@
+/*
+    Signer: a partial signature for each input spending a script that contains the public key (or its hash).
+
+    Script signed, by the ScriptPubKey spent:
+        P2SH            the redeem script (PSBT_IN_REDEEM_SCRIPT), that can be a witness program
+        P2WPKH          the P2PKH script of the public key hash (BIP143 script code)
+        P2WSH           the witness script (PSBT_IN_WITNESS_SCRIPT)
+        others          the ScriptPubKey
+    Witness v0 scripts are signed as BIP143, with the compressed public key only. Taproot inputs are not signed.
+*/
+
+use crate::{
+    flags::{compression::Compression, sighash::SigHash},
+    hashing::{hash160::hash160, sha256::sha256},
+    keys::key::Key,
+    scripting::{standard, token::Token},
+    std_lib::std_result::StdResult,
+    transaction::script::Script,
+};
+
+use super::{
+    constants::*,
+    map::{value_u32, PsbtMap},
+    partially_signed_tx::Psbt,
+};
+
+#[derive(Debug, Clone)]
+pub enum SpendingScript {
+    Legacy(Script),
+    // script code
+    WitnessV0(Script),
+    Taproot,
+}
+
+impl Psbt {
+    // Number of inputs signed by `key`
+    pub fn sign(&mut self, key: &Key) -> StdResult<usize> {
+        let tx = self.unsigned_tx()?;
+
+        let compressed = key.public_key_sec();
+        let uncompressed = key.public_key().serialize(Compression::Uncompressed);
+
+        let mut signed: usize = 0;
+
+        for index in 0..self.inputs.len() {
+            if self.is_input_finalized(index) {
+                continue;
+            }
+
+            let input = &self.inputs[index];
+            if let Some(sighash) = input.get(PSBT_IN_SIGHASH_TYPE) {
+                if value_u32(sighash)? != SigHash::All as u32 {
+                    Err("psbt_unsupported_sighash")?;
+                }
+            }
+
+            let utxo = self.utxo(index)?;
+
+            let (z, public_key) = match spending_script(input, &utxo.script_pub_key)? {
+                SpendingScript::Legacy(script) => {
+                    let Some(public_key) = [&compressed, &uncompressed]
+                        .into_iter()
+                        .find(|public_key| contains_public_key(&script, public_key))
+                    else {
+                        continue;
+                    };
+
+                    (tx.hash_signature(index, script), public_key)
+                }
+                SpendingScript::WitnessV0(script_code) => {
+                    if !contains_public_key(&script_code, &compressed) {
+                        continue;
+                    }
+
+                    (
+                        tx.hash_signature_witness_v0(index, &script_code, utxo.amount),
+                        &compressed,
+                    )
+                }
+                SpendingScript::Taproot => continue,
+            };
+
+            let signature = [key.sign(z).der(), vec![SigHash::All as u8]].concat();
+
+            self.inputs[index].insert(PSBT_IN_PARTIAL_SIG, public_key.clone(), signature);
+            signed += 1;
+        }
+
+        Ok(signed)
+    }
+}
+
+// The script signed for an input spending `script_pub_key`
+pub fn spending_script(input: &PsbtMap, script_pub_key: &Script) -> StdResult<SpendingScript> {
+    let script = match script_pub_key.raw.as_slice() {
+        [0xA9, 0x14, script_hash @ .., 0x87] if script_pub_key.raw.len() == 23 => {
+            let redeem_script = match input.get(PSBT_IN_REDEEM_SCRIPT) {
+                Some(redeem_script) => redeem_script,
+                None => Err("psbt_missing_redeem_script")?,
+            };
+
+            if hash160(redeem_script) != script_hash {
+                Err("psbt_redeem_script_mismatch")?;
+            }
+
+            redeem_script.to_vec()
+        }
+        raw => raw.to_vec(),
+    };
+
+    let spending_script = match script.as_slice() {
+        [0x00, 0x14, public_key_hash @ ..] if script.len() == 22 => {
+            let script_code = standard::p2pkh_script(public_key_hash);
+            SpendingScript::WitnessV0(Script::new_from_script_lang(&script_code))
+        }
+        [0x00, 0x20, script_hash @ ..] if script.len() == 34 => {
+            let witness_script = match input.get(PSBT_IN_WITNESS_SCRIPT) {
+                Some(witness_script) => witness_script,
+                None => Err("psbt_missing_witness_script")?,
+            };
+
+            if sha256(witness_script) != script_hash {
+                Err("psbt_witness_script_mismatch")?;
+            }
+
+            SpendingScript::WitnessV0(Script::new_from_raw(witness_script.to_vec()))
+        }
+        [0x51, 0x20, ..] if script.len() == 34 => SpendingScript::Taproot,
+        _ => SpendingScript::Legacy(Script::new_from_raw(script)),
+    };
+
+    Ok(spending_script)
+}
+
+// `public_key` or its hash is pushed by `script`
+fn contains_public_key(script: &Script, public_key: &[u8]) -> bool {
+    let public_key_hash = hash160(public_key);
+
+    script.script_lang.tokens().iter().any(|token| match token {
+        Token::Element(element) => *element == public_key || *element == public_key_hash,
+        _ => false,
+    })
+}
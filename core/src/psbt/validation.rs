@@ -0,0 +1,391 @@
+/*
+    Checks of the PSBT maps when deserialized: key data and value of the known key types, no field of version 2
+    (BIP370). Unknown key types are kept as they are.
+*/
+
+use crate::{
+    hashing::{hash160::hash160, hash256::Hash256, ripemd160::ripemd160, sha256::sha256},
+    keys::signature::Signature,
+    std_lib::std_result::StdResult,
+    transaction::{tx::Tx, tx_out::TxOut},
+};
+
+use super::{
+    constants::*,
+    map::{deserialize_witness, read_bytes, read_compact_size, value_u32, KeyValue, PsbtMap},
+    partially_signed_tx::Psbt,
+};
+
+const XPUB_LENGTH: usize = 78;
+const X_ONLY_PUBLIC_KEY_LENGTH: usize = 32;
+const TAPROOT_CONTROL_BLOCK_BASE_LENGTH: usize = 33;
+const TAPROOT_CONTROL_BLOCK_MAX_DEPTH: usize = 128;
+
+// Fields of version 2 only, excluded from version 0
+const V2_GLOBAL_FIELDS: [u64; 5] = [
+    PSBT_GLOBAL_TX_VERSION,
+    PSBT_GLOBAL_FALLBACK_LOCKTIME,
+    PSBT_GLOBAL_INPUT_COUNT,
+    PSBT_GLOBAL_OUTPUT_COUNT,
+    PSBT_GLOBAL_TX_MODIFIABLE,
+];
+const V2_INPUT_FIELDS: [u64; 5] = [
+    PSBT_IN_PREVIOUS_TXID,
+    PSBT_IN_OUTPUT_INDEX,
+    PSBT_IN_SEQUENCE,
+    PSBT_IN_REQUIRED_TIME_LOCKTIME,
+    PSBT_IN_REQUIRED_HEIGHT_LOCKTIME,
+];
+const V2_OUTPUT_FIELDS: [u64; 2] = [PSBT_OUT_AMOUNT, PSBT_OUT_SCRIPT];
+
+pub fn validate_global(map: &PsbtMap) -> StdResult<()> {
+    no_v2_fields(map, &V2_GLOBAL_FIELDS)?;
+
+    for pair in map.pairs() {
+        match pair.key_type {
+            PSBT_GLOBAL_UNSIGNED_TX => no_key_data(pair)?,
+            PSBT_GLOBAL_XPUB => {
+                key_data_length(pair, &[XPUB_LENGTH])?;
+                key_origin(&pair.value)?;
+            }
+            PSBT_GLOBAL_VERSION => {
+                no_key_data(pair)?;
+                value_u32(&pair.value)?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_input(map: &PsbtMap) -> StdResult<()> {
+    no_v2_fields(map, &V2_INPUT_FIELDS)?;
+
+    for pair in map.pairs() {
+        match pair.key_type {
+            PSBT_IN_NON_WITNESS_UTXO
+            | PSBT_IN_REDEEM_SCRIPT
+            | PSBT_IN_WITNESS_SCRIPT
+            | PSBT_IN_FINAL_SCRIPTSIG
+            | PSBT_IN_POR_COMMITMENT => no_key_data(pair)?,
+            PSBT_IN_WITNESS_UTXO => {
+                no_key_data(pair)?;
+                tx_out(&pair.value)?;
+            }
+            PSBT_IN_PARTIAL_SIG => {
+                public_key(pair)?;
+                if Signature::check_strict_der(&pair.value).is_err() {
+                    Err("psbt_invalid_value")?;
+                }
+            }
+            PSBT_IN_SIGHASH_TYPE => {
+                no_key_data(pair)?;
+                value_u32(&pair.value)?;
+            }
+            PSBT_IN_BIP32_DERIVATION => {
+                public_key(pair)?;
+                key_origin(&pair.value)?;
+            }
+            PSBT_IN_FINAL_SCRIPTWITNESS => {
+                no_key_data(pair)?;
+                deserialize_witness(&pair.value)?;
+            }
+            PSBT_IN_RIPEMD160 => preimage(pair, ripemd160(&pair.value))?,
+            PSBT_IN_SHA256 => preimage(pair, sha256(&pair.value))?,
+            PSBT_IN_HASH160 => preimage(pair, hash160(&pair.value))?,
+            PSBT_IN_HASH256 => preimage(pair, Hash256::calc(&pair.value).0.to_vec())?,
+            PSBT_IN_TAP_KEY_SIG => {
+                no_key_data(pair)?;
+                value_length(pair, &[64, 65])?;
+            }
+            PSBT_IN_TAP_SCRIPT_SIG => {
+                // x-only public key and leaf hash
+                key_data_length(pair, &[X_ONLY_PUBLIC_KEY_LENGTH + 32])?;
+                value_length(pair, &[64, 65])?;
+            }
+            PSBT_IN_TAP_LEAF_SCRIPT => {
+                control_block(pair)?;
+                // script and leaf version
+                if pair.value.is_empty() {
+                    Err("psbt_invalid_value")?;
+                }
+            }
+            PSBT_IN_TAP_BIP32_DERIVATION => {
+                key_data_length(pair, &[X_ONLY_PUBLIC_KEY_LENGTH])?;
+                tap_key_origin(&pair.value)?;
+            }
+            PSBT_IN_TAP_INTERNAL_KEY | PSBT_IN_TAP_MERKLE_ROOT => {
+                no_key_data(pair)?;
+                value_length(pair, &[32])?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_output(map: &PsbtMap) -> StdResult<()> {
+    no_v2_fields(map, &V2_OUTPUT_FIELDS)?;
+
+    for pair in map.pairs() {
+        match pair.key_type {
+            PSBT_OUT_REDEEM_SCRIPT | PSBT_OUT_WITNESS_SCRIPT => no_key_data(pair)?,
+            PSBT_OUT_BIP32_DERIVATION => {
+                public_key(pair)?;
+                key_origin(&pair.value)?;
+            }
+            PSBT_OUT_TAP_INTERNAL_KEY => {
+                no_key_data(pair)?;
+                value_length(pair, &[32])?;
+            }
+            PSBT_OUT_TAP_TREE => {
+                no_key_data(pair)?;
+                tap_tree(&pair.value)?;
+            }
+            PSBT_OUT_TAP_BIP32_DERIVATION => {
+                key_data_length(pair, &[X_ONLY_PUBLIC_KEY_LENGTH])?;
+                tap_key_origin(&pair.value)?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+// The previous transaction of each input must be the one of its outpoint
+pub fn validate_non_witness_utxos(psbt: &Psbt) -> StdResult<()> {
+    let tx = psbt.unsigned_tx()?;
+
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        let Some(value) = input.get(PSBT_IN_NON_WITNESS_UTXO) else {
+            continue;
+        };
+
+        let previous_tx = match Tx::deserialize(value, psbt.network) {
+            Ok(previous_tx) => previous_tx,
+            Err(_) => Err("psbt_invalid_value")?,
+        };
+
+        if previous_tx.id_as_integer() != tx.input(index)?.previous_transaction_id {
+            Err("psbt_non_witness_utxo_mismatch")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn no_v2_fields(map: &PsbtMap, v2_fields: &[u64]) -> StdResult<()> {
+    if let Some(pair) = map.pairs().iter().find(|p| v2_fields.contains(&p.key_type)) {
+        log::error!("PSBT v2 key type in v0: {:#04X}", pair.key_type);
+        Err("psbt_v2_field_in_v0")?;
+    }
+
+    Ok(())
+}
+
+fn no_key_data(pair: &KeyValue) -> StdResult<()> {
+    key_data_length(pair, &[0])
+}
+
+fn key_data_length(pair: &KeyValue, lengths: &[usize]) -> StdResult<()> {
+    if !lengths.contains(&pair.key_data.len()) {
+        log::error!(
+            "PSBT key type {:#04X}, invalid key data length: {}",
+            pair.key_type,
+            pair.key_data.len()
+        );
+        Err("psbt_invalid_key_data")?;
+    }
+
+    Ok(())
+}
+
+fn value_length(pair: &KeyValue, lengths: &[usize]) -> StdResult<()> {
+    if !lengths.contains(&pair.value.len()) {
+        log::error!(
+            "PSBT key type {:#04X}, invalid value length: {}",
+            pair.key_type,
+            pair.value.len()
+        );
+        Err("psbt_invalid_value")?;
+    }
+
+    Ok(())
+}
+
+// SEC public key as key data
+fn public_key(pair: &KeyValue) -> StdResult<()> {
+    match pair.key_data.as_slice() {
+        [0x02 | 0x03, ..] if pair.key_data.len() == 33 => Ok(()),
+        [0x04, ..] if pair.key_data.len() == 65 => Ok(()),
+        _ => Err("psbt_invalid_key_data")?,
+    }
+}
+
+// Key data is the hash of the value
+fn preimage(pair: &KeyValue, hash: Vec<u8>) -> StdResult<()> {
+    if pair.key_data != hash {
+        Err("psbt_invalid_preimage")?;
+    }
+
+    Ok(())
+}
+
+fn tx_out(value: &[u8]) -> StdResult<()> {
+    match TxOut::deserialize(value, 0) {
+        Ok((_, cursor)) if cursor == value.len() => Ok(()),
+        _ => Err("psbt_invalid_value")?,
+    }
+}
+
+// Fingerprint and child numbers
+fn key_origin(value: &[u8]) -> StdResult<()> {
+    if value.len() < 4 || !value.len().is_multiple_of(4) {
+        Err("psbt_invalid_value")?;
+    }
+
+    Ok(())
+}
+
+// Leaf hashes, then fingerprint and child numbers
+fn tap_key_origin(value: &[u8]) -> StdResult<()> {
+    let (count, cursor) = read_compact_size(value, 0)?;
+    let leaf_hashes = read_bytes(value, cursor, count.saturating_mul(32))?;
+
+    key_origin(&value[cursor + leaf_hashes.len()..])
+}
+
+// Key data: leaf version and parity, internal key and 0 to 128 hashes of the path
+fn control_block(pair: &KeyValue) -> StdResult<()> {
+    let length = pair.key_data.len();
+
+    if length < TAPROOT_CONTROL_BLOCK_BASE_LENGTH
+        || !(length - TAPROOT_CONTROL_BLOCK_BASE_LENGTH).is_multiple_of(32)
+        || (length - TAPROOT_CONTROL_BLOCK_BASE_LENGTH) / 32 > TAPROOT_CONTROL_BLOCK_MAX_DEPTH
+    {
+        Err("psbt_invalid_key_data")?;
+    }
+
+    Ok(())
+}
+
+// Leaves of the tree: depth, leaf version, script
+fn tap_tree(value: &[u8]) -> StdResult<()> {
+    if value.is_empty() {
+        Err("psbt_invalid_value")?;
+    }
+
+    let mut cursor: usize = 0;
+    while cursor < value.len() {
+        let header = read_bytes(value, cursor, 2)?;
+        if header[0] as usize > TAPROOT_CONTROL_BLOCK_MAX_DEPTH {
+            Err("psbt_invalid_value")?;
+        }
+
+        let (length, c) = read_compact_size(value, cursor + 2)?;
+        let script = read_bytes(value, c, length)?;
+        cursor = c + script.len();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod validation_test {
+    use super::*;
+
+    fn map(pairs: &[(u64, &[u8], &[u8])]) -> PsbtMap {
+        let mut map = PsbtMap::new();
+        for (key_type, key_data, value) in pairs {
+            map.insert(*key_type, key_data.to_vec(), value.to_vec());
+        }
+
+        map
+    }
+
+    macro_rules! invalid_input {
+        ($pairs:expr, $error:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let err = validate_input(&map(&$pairs)).expect_err("Err");
+                assert_eq!(err.to_string(), $error);
+            }
+        };
+    }
+
+    invalid_input!(
+        [(PSBT_IN_WITNESS_UTXO, &[0x00], &[0x00])],
+        "psbt_invalid_key_data",
+        input_key_data_not_expected
+    );
+    invalid_input!(
+        [(PSBT_IN_PARTIAL_SIG, &[0x05; 33], &[0x30])],
+        "psbt_invalid_key_data",
+        input_invalid_public_key
+    );
+    invalid_input!(
+        [(PSBT_IN_SIGHASH_TYPE, &[], &[0x01])],
+        "psbt_invalid_value",
+        input_invalid_sighash_length
+    );
+    invalid_input!(
+        [(PSBT_IN_SHA256, &[0x00; 32], b"preimage")],
+        "psbt_invalid_preimage",
+        input_invalid_preimage
+    );
+    invalid_input!(
+        [(PSBT_IN_OUTPUT_INDEX, &[], &[0x00; 4])],
+        "psbt_v2_field_in_v0",
+        input_v2_field_in_v0
+    );
+    invalid_input!(
+        [(PSBT_IN_TAP_LEAF_SCRIPT, &[0xC0; 34], &[0x51, 0xC0])],
+        "psbt_invalid_key_data",
+        input_invalid_control_block
+    );
+
+    #[test]
+    fn valid_input() {
+        let input = map(&[
+            (PSBT_IN_SHA256, &sha256(b"preimage"), b"preimage"),
+            (PSBT_IN_BIP32_DERIVATION, &[0x02; 33], &[0x00; 12]),
+            (PSBT_IN_TAP_LEAF_SCRIPT, &[0xC0; 65], &[0x51, 0xC0]),
+            (
+                PSBT_IN_TAP_BIP32_DERIVATION,
+                &[0x00; 32],
+                &[0x00, 0xAA, 0xBB, 0xCC, 0xDD],
+            ),
+            (0xF0, &[0x01, 0x02], &[]),
+        ]);
+
+        assert!(validate_input(&input).is_ok());
+    }
+
+    #[test]
+    fn global_v2_field_in_v0() {
+        let global = map(&[
+            (PSBT_GLOBAL_UNSIGNED_TX, &[], &[]),
+            (PSBT_GLOBAL_TX_VERSION, &[], &[0x02, 0x00, 0x00, 0x00]),
+        ]);
+
+        let err = validate_global(&global).expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_v2_field_in_v0");
+    }
+
+    #[test]
+    fn output_tap_tree() {
+        let valid = map(&[(
+            PSBT_OUT_TAP_TREE,
+            &[],
+            &[0x01, 0xC0, 0x01, 0x51, 0x01, 0xC0, 0x01, 0x52],
+        )]);
+        assert!(validate_output(&valid).is_ok());
+
+        let truncated = map(&[(PSBT_OUT_TAP_TREE, &[], &[0x01, 0xC0, 0x02, 0x51])]);
+        let err = validate_output(&truncated).expect_err("Err");
+        assert_eq!(err.to_string(), "psbt_unexpected_end");
+    }
+}
@@ -0,0 +1,435 @@
+/*
+    Script assembly language, to write scripts by hand.
+
+        # P2PKH
+        OP_DUP OP_HASH160 <pubkey_hash> OP_EQUALVERIFY
+        OP_CHECKSIG
+
+    Words are separated by spaces or new lines, `#` starts a comment until the end of the line:
+        - opcodes, with or without `OP_` (`OP_TRUE` and `OP_FALSE` are OP_1 and OP_0)
+        - decimal numbers, pushed as script numbers: -1, 0 and 1 ... 16 as OP_1NEGATE, OP_0 and OP_1 ... OP_16
+        - `0x...`: data, pushed with the shortest push opcode for its length
+        - `<name>`: data substituted with the value of the placeholder `name`, pushed as `0x...`
+        - `OP_PUSHBYTES_N`, `OP_PUSHDATA1`, `OP_PUSHDATA2`, `OP_PUSHDATA4` followed by data (`0x...` or `<name>`):
+          push with exactly that opcode
+        - `[...]`: hex bytes inserted as they are (e.g. a truncated push at the end of the script)
+
+    `disassemble` (and `pretty_print`) write any script so that `assemble` gives back the same bytes:
+    pushes with the shortest opcode are written as data only, the other ones with their opcode.
+*/
+
+use crate::std_lib::{
+    std_result::StdResult,
+    vector::{bytes_to_hex_string, hex_string_to_bytes},
+};
+
+use super::{
+    opcode::{
+        OpCode, OP_0, OP_1, OP_1NEGATE, OP_ELSE, OP_ENDIF, OP_IF, OP_NOTIF, OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4,
+        OP_TO_FN,
+    },
+    script_lang::ScriptLang,
+    script_num::ScriptNum,
+};
+
+const MAX_PUSH_BYTES: usize = OP_PUSHDATA1 - 1;
+
+/*
+    Raw bytes of the script written in `source`, `placeholders` are the values of the `<name>` words.
+*/
+pub fn assemble(source: &str, placeholders: &[(&str, &[u8])]) -> StdResult<Vec<u8>> {
+    let mut words = source
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split_whitespace());
+
+    let mut raw: Vec<u8> = vec![];
+
+    while let Some(word) = words.next() {
+        if let Some(op_code) = explicit_push_op_code(word)? {
+            let data = match words.next() {
+                Some(data) => parse_data(data, placeholders)?,
+                None => Err("asm_missing_push_data")?,
+            };
+
+            raw.extend(push_with(op_code, &data)?);
+        } else if word.starts_with("0x") || word.starts_with('<') {
+            let data = parse_data(word, placeholders)?;
+            raw.extend(push(&data));
+        } else if let Some(hex) = word.strip_prefix('[').and_then(|w| w.strip_suffix(']')) {
+            raw.extend(parse_hex(hex)?);
+        } else if is_number(word) {
+            let n: i64 = word.parse().map_err(|_| "asm_invalid_number")?;
+            raw.extend(push_number(n));
+        } else {
+            raw.push(op_code_by_name(word)? as u8);
+        }
+    }
+
+    Ok(raw)
+}
+
+/*
+    As `assemble`, for a script without truncated pushes.
+*/
+pub fn assemble_script(source: &str, placeholders: &[(&str, &[u8])]) -> StdResult<ScriptLang> {
+    let raw = assemble(source, placeholders)?;
+    ScriptLang::deserialize(&raw, raw.len() as u64, 0)
+}
+
+// On one line
+pub fn disassemble(raw: &[u8]) -> String {
+    disassemble_items(raw)
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// One item per line, branches indented
+pub fn pretty_print(raw: &[u8]) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut depth: usize = 0;
+
+    for (op_code, item) in disassemble_items(raw) {
+        if matches!(op_code, Some(OP_ELSE) | Some(OP_ENDIF)) {
+            depth = depth.saturating_sub(1);
+        }
+
+        lines.push(format!("{}{}", "    ".repeat(depth), item));
+
+        if matches!(op_code, Some(OP_IF) | Some(OP_NOTIF) | Some(OP_ELSE)) {
+            depth += 1;
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn disassemble_items(raw: &[u8]) -> Vec<(Option<OpCode>, String)> {
+    let mut items: Vec<(Option<OpCode>, String)> = vec![];
+    let mut i: usize = 0;
+
+    while i < raw.len() {
+        let op_code = raw[i] as OpCode;
+
+        if op_code == OP_0 {
+            items.push((Some(op_code), "OP_0".to_string()));
+            i += 1;
+            continue;
+        }
+
+        if op_code > OP_PUSHDATA4 {
+            let item = match OP_TO_FN[op_code].name {
+                "" => format!("[{}]", bytes_to_hex_string(&raw[i..i + 1])),
+                name => name.to_string(),
+            };
+
+            items.push((Some(op_code), item));
+            i += 1;
+            continue;
+        }
+
+        match ScriptLang::read_push(raw, i) {
+            Some((data, next)) => {
+                let data_hex = format!("0x{}", bytes_to_hex_string(data));
+
+                let item = if push_op_code(data.len()) == op_code {
+                    data_hex
+                } else {
+                    format!("{} {}", push_op_code_name(op_code), data_hex)
+                };
+
+                items.push((None, item));
+                i = next;
+            }
+            None => {
+                items.push((None, format!("[{}]", bytes_to_hex_string(&raw[i..]))));
+                break;
+            }
+        }
+    }
+
+    items
+}
+
+fn is_number(word: &str) -> bool {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+fn parse_hex(hex: &str) -> StdResult<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Err("asm_invalid_hex")?;
+    }
+
+    hex_string_to_bytes(hex)
+}
+
+// `0x...` or `<name>`
+fn parse_data(word: &str, placeholders: &[(&str, &[u8])]) -> StdResult<Vec<u8>> {
+    if let Some(hex) = word.strip_prefix("0x") {
+        return parse_hex(hex);
+    }
+
+    if let Some(name) = word.strip_prefix('<').and_then(|w| w.strip_suffix('>')) {
+        return match placeholders.iter().find(|(placeholder, _)| *placeholder == name) {
+            Some((_, value)) => Ok(value.to_vec()),
+            None => {
+                log::error!("Missing value for placeholder <{}>", name);
+                Err("asm_missing_placeholder")?
+            }
+        };
+    }
+
+    Err("asm_expected_push_data")?
+}
+
+fn op_code_by_name(word: &str) -> StdResult<OpCode> {
+    let name = match word {
+        "TRUE" | "OP_TRUE" => return Ok(OP_1),
+        "FALSE" | "OP_FALSE" => return Ok(OP_0),
+        _ if word.starts_with("OP_") => word.to_string(),
+        _ => format!("OP_{}", word),
+    };
+
+    match OP_TO_FN.iter().position(|op| op.name == name) {
+        Some(op_code) => Ok(op_code),
+        None => {
+            log::error!("Unknown opcode {}", word);
+            Err("asm_unknown_opcode")?
+        }
+    }
+}
+
+// `Some` if `word` is a push opcode that must be followed by its data
+fn explicit_push_op_code(word: &str) -> StdResult<Option<OpCode>> {
+    let word = word.strip_prefix("OP_").unwrap_or(word);
+
+    let op_code = match word {
+        "PUSHDATA1" => OP_PUSHDATA1,
+        "PUSHDATA2" => OP_PUSHDATA2,
+        "PUSHDATA4" => OP_PUSHDATA4,
+        _ => match word.strip_prefix("PUSHBYTES_") {
+            Some(n) => match n.parse::<usize>() {
+                Ok(n) if (1..=MAX_PUSH_BYTES).contains(&n) => n,
+                _ => Err("asm_invalid_push_bytes")?,
+            },
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Some(op_code))
+}
+
+fn push_op_code(length: usize) -> OpCode {
+    match length {
+        0..=MAX_PUSH_BYTES => length,
+        0x4C..=0xFF => OP_PUSHDATA1,
+        0x100..=0xFFFF => OP_PUSHDATA2,
+        _ => OP_PUSHDATA4,
+    }
+}
+
+fn push_op_code_name(op_code: OpCode) -> String {
+    match op_code {
+        OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4 => OP_TO_FN[op_code].name.to_string(),
+        n => format!("OP_PUSHBYTES_{}", n),
+    }
+}
+
+fn push(data: &[u8]) -> Vec<u8> {
+    // data is at most 4 GB: `push_with` cannot fail with the shortest opcode
+    push_with(push_op_code(data.len()), data).unwrap_or_default()
+}
+
+fn push_with(op_code: OpCode, data: &[u8]) -> StdResult<Vec<u8>> {
+    let length = data.len();
+
+    let length_bytes = match op_code {
+        OP_PUSHDATA1 if length <= 0xFF => vec![length as u8],
+        OP_PUSHDATA2 if length <= 0xFFFF => (length as u16).to_le_bytes().to_vec(),
+        OP_PUSHDATA4 if length <= 0xFFFFFFFF => (length as u32).to_le_bytes().to_vec(),
+        n if n == length && n <= MAX_PUSH_BYTES => vec![],
+        _ => Err("asm_push_size_mismatch")?,
+    };
+
+    Ok([vec![op_code as u8], length_bytes, data.to_vec()].concat())
+}
+
+fn push_number(n: i64) -> Vec<u8> {
+    match n {
+        0 => vec![OP_0 as u8],
+        -1 => vec![OP_1NEGATE as u8],
+        1..=16 => vec![(OP_1 as i64 + n - 1) as u8],
+        _ => push(&ScriptNum::new(n).encode()),
+    }
+}
+
+#[cfg(test)]
+mod asm_test {
+    use super::*;
+
+    use crate::scripting::{opcode::*, token::Token};
+
+    macro_rules! assemble {
+        ($source:expr, $raw:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let raw = assemble($source, &[]).unwrap();
+                assert_eq!(bytes_to_hex_string(&raw), $raw);
+            }
+        };
+    }
+
+    assemble!("OP_DUP OP_HASH160", "76A9", assemble_opcodes);
+    assemble!("DUP HASH160", "76A9", assemble_opcodes_without_prefix);
+    assemble!("OP_TRUE OP_FALSE", "5100", assemble_true_false);
+    assemble!("0 -1 1 16", "004F5160", assemble_small_numbers);
+    assemble!("17 -2 1000 -1000", "0111018202E80302E883", assemble_numbers);
+    assemble!("2147483648", "050000008000", assemble_five_bytes_number);
+    assemble!("0x 0xAABB", "0002AABB", assemble_data);
+    assemble!("OP_PUSHDATA1 0xAABB", "4C02AABB", assemble_push_data_1);
+    assemble!("OP_PUSHDATA2 0xAABB", "4D0200AABB", assemble_push_data_2);
+    assemble!("PUSHDATA4 0xAABB", "4E02000000AABB", assemble_push_data_4);
+    assemble!("OP_PUSHBYTES_2 0xaabb", "02AABB", assemble_push_bytes);
+    assemble!("OP_1 [4c05]", "514C05", assemble_raw_bytes);
+    assemble!(
+        "# 1 + 1 = 2\n  OP_1 OP_1 OP_ADD  # sum\n\n\tOP_2 OP_EQUAL # check\n",
+        "5151935287",
+        assemble_multi_line
+    );
+
+    macro_rules! assemble_error {
+        ($source:expr, $error:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let err = assemble($source, &[]).expect_err("Err");
+                assert_eq!(err.to_string(), $error);
+            }
+        };
+    }
+
+    assemble_error!("OP_FOO", "asm_unknown_opcode", assemble_unknown_opcode);
+    assemble_error!("0xAAB", "asm_invalid_hex", assemble_odd_hex);
+    assemble_error!("0xZZ", "asm_invalid_hex", assemble_invalid_hex);
+    assemble_error!("<pubkey>", "asm_missing_placeholder", assemble_missing_placeholder);
+    assemble_error!("OP_PUSHDATA1", "asm_missing_push_data", assemble_missing_push_data);
+    assemble_error!(
+        "OP_PUSHDATA1 OP_1",
+        "asm_expected_push_data",
+        assemble_push_data_not_data
+    );
+    assemble_error!(
+        "OP_PUSHBYTES_3 0xAABB",
+        "asm_push_size_mismatch",
+        assemble_push_bytes_mismatch
+    );
+    assemble_error!(
+        "OP_PUSHBYTES_76 0xAABB",
+        "asm_invalid_push_bytes",
+        assemble_push_bytes_too_long
+    );
+    assemble_error!("99999999999999999999", "asm_invalid_number", assemble_number_overflow);
+
+    #[test]
+    fn assemble_placeholders() {
+        let pubkey = hex_string_to_bytes("02a130c1e1ffa137cf50824ece45fb648ce88cb5570870dc10cfdc8c5f30946861").unwrap();
+        let signature = vec![0x30, 0x01];
+
+        let raw = assemble(
+            "<signature>\n<pubkey> OP_CHECKSIG",
+            &[("pubkey", &pubkey), ("signature", &signature)],
+        )
+        .unwrap();
+
+        assert_eq!(raw, [vec![0x02], signature, vec![0x21], pubkey, vec![0xAC]].concat());
+    }
+
+    #[test]
+    fn assemble_placeholder_explicit_push() {
+        let data = vec![0xAA];
+
+        let raw = assemble("OP_PUSHDATA2 <data>", &[("data", &data)]).unwrap();
+        assert_eq!(raw, vec![0x4D, 0x01, 0x00, 0xAA]);
+    }
+
+    #[test]
+    fn assemble_script_tokens() {
+        let script = assemble_script("OP_1 0xAABB 1000 OP_CHECKMULTISIG", &[]).unwrap();
+
+        assert_eq!(
+            script.tokens(),
+            vec![
+                Token::Command(OP_1),
+                Token::Element(vec![0xAA, 0xBB]),
+                Token::Element(vec![0xE8, 0x03]),
+                Token::Command(OP_CHECKMULTISIG)
+            ]
+        );
+    }
+
+    #[test]
+    fn assemble_script_truncated() {
+        let err = assemble_script("OP_1 [4c05]", &[]).expect_err("Err");
+        assert_eq!(err.to_string(), "truncated_push");
+    }
+
+    macro_rules! disassemble {
+        ($raw:literal, $source:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let raw = hex_string_to_bytes($raw).unwrap();
+
+                assert_eq!(disassemble(&raw), $source);
+                assert_eq!(assemble($source, &[]).unwrap(), raw);
+            }
+        };
+    }
+
+    disassemble!(
+        "76a914a17b1c6a5a1d3b7bf6b9b2a7e5e3b3c9ae8f19e888ac",
+        "OP_DUP OP_HASH160 0xA17B1C6A5A1D3B7BF6B9B2A7E5E3B3C9AE8F19E8 OP_EQUALVERIFY OP_CHECKSIG",
+        disassemble_p2pkh
+    );
+    disassemble!("00514f60", "OP_0 OP_1 OP_1NEGATE OP_16", disassemble_small_numbers);
+    disassemble!("4c01aa", "OP_PUSHDATA1 0xAA", disassemble_not_minimal_push_data_1);
+    disassemble!("4d0100aa", "OP_PUSHDATA2 0xAA", disassemble_not_minimal_push_data_2);
+    disassemble!("4e01000000aa", "OP_PUSHDATA4 0xAA", disassemble_not_minimal_push_data_4);
+    disassemble!("4c00", "OP_PUSHDATA1 0x", disassemble_empty_push_data_1);
+    disassemble!("514c05aa", "OP_1 [4C05AA]", disassemble_truncated);
+    disassemble!("51bb", "OP_1 [BB]", disassemble_unknown_opcode);
+
+    #[test]
+    fn disassemble_push_data_1() {
+        let data = vec![0x62; 0x4C];
+        let raw = [vec![0x4C, 0x4C], data.clone()].concat();
+
+        assert_eq!(disassemble(&raw), format!("0x{}", bytes_to_hex_string(&data)));
+        assert_eq!(assemble(&disassemble(&raw), &[]).unwrap(), raw);
+    }
+
+    #[test]
+    fn pretty_print_branches() {
+        let raw = assemble("OP_1 OP_IF OP_2 OP_NOTIF OP_3 OP_ENDIF OP_ELSE 0xAA OP_ENDIF", &[]).unwrap();
+
+        let expected = "OP_1\nOP_IF\n    OP_2\n    OP_NOTIF\n        OP_3\n    OP_ENDIF\nOP_ELSE\n    0xAA\nOP_ENDIF";
+        assert_eq!(pretty_print(&raw), expected);
+        assert_eq!(assemble(&pretty_print(&raw), &[]).unwrap(), raw);
+    }
+
+    #[test]
+    fn round_trip_with_serialize() {
+        let source = "OP_2 <a> <b> 0x OP_3 OP_CHECKMULTISIG 1000 -1000 OP_IF 0xAABBCC OP_ENDIF";
+        let a = vec![0x02; 33];
+        let b = vec![0x03; 300];
+        let placeholders: [(&str, &[u8]); 2] = [("a", &a), ("b", &b)];
+
+        let script = assemble_script(source, &placeholders).unwrap();
+        let serialized = script.serialize().unwrap();
+
+        assert_eq!(serialized, assemble(source, &placeholders).unwrap());
+        assert_eq!(assemble(&disassemble(&serialized), &[]).unwrap(), serialized);
+        assert_eq!(assemble(&pretty_print(&serialized), &[]).unwrap(), serialized);
+    }
+}
@@ -22,6 +22,14 @@ impl ConditionStack {
         self.false_pos == EMPTY
     }
 
+    /*
+        Value of each open branch, from the outermost: only the first false is stored (as in Bitcoin Core),
+        the branches inside it are not executed and are reported as false.
+    */
+    pub fn values(&self) -> Vec<bool> {
+        (0..self.size).map(|i| i < self.false_pos).collect()
+    }
+
     pub fn set_execute(&mut self, value: bool) {
         if self.false_pos == EMPTY && !value {
             self.false_pos = self.size;
@@ -1 +1,30 @@
 pub const MAX_RETURN_DATA_LENGTH: usize = 80;
+
+/*
+    Consensus limits: https://github.com/bitcoin/bitcoin/blob/master/src/script/script.h
+*/
+// bytes of a script
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+// bytes of an element pushed on the stack
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+// non push opcodes of a script (public keys of OP_CHECKMULTISIG included)
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+// elements on stack and alt stack together
+pub const MAX_STACK_SIZE: usize = 1_000;
+// public keys of OP_CHECKMULTISIG
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+/*
+    Timelocks: https://github.com/bitcoin/bitcoin/blob/master/src/script/script.h and primitives/transaction.h
+*/
+// locktimes below are block heights, the others UNIX timestamps
+pub const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+// bytes of the operand of OP_CHECKLOCKTIMEVERIFY and OP_CHECKSEQUENCEVERIFY (up to 2^39 - 1)
+pub const LOCKTIME_MAX_NUM_SIZE: usize = 5;
+// sequence of an input without relative timelock (and not enabling the locktime of the transaction)
+pub const SEQUENCE_FINAL: u32 = 0xFFFFFFFF;
+// BIP68: the sequence is not a relative timelock
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: i64 = 1 << 31;
+// BIP68: relative timelock in units of 512 seconds, otherwise in blocks
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: i64 = 1 << 22;
+pub const SEQUENCE_LOCKTIME_MASK: i64 = 0x0000FFFF;
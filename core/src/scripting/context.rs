@@ -1,9 +1,35 @@
 use rug::Integer;
-use std::collections::VecDeque;
-
-use crate::std_lib::std_result::StdResult;
+use std::collections::{HashMap, VecDeque};
+
+use crate::{flags::script_flags::ScriptFlags, std_lib::std_result::StdResult};
+
+use super::{
+    condition_stack::ConditionStack,
+    constants::MAX_OPS_PER_SCRIPT,
+    script_num::{ScriptNum, DEFAULT_MAX_NUM_SIZE},
+    token::Token,
+};
+
+/*
+    Fields of the spending transaction checked by OP_CHECKLOCKTIMEVERIFY (BIP65) and OP_CHECKSEQUENCEVERIFY (BIP112):
+    `sequence` is the one of the input being verified.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpendingTx {
+    pub version: u32,
+    pub locktime: u32,
+    pub sequence: u32,
+}
 
-use super::{condition_stack::ConditionStack, token::Token};
+/*
+    The kind of script evaluated, for the rules that apply to some of them only (e.g. MINIMALIF to witness scripts):
+    legacy (ScriptSig, ScriptPubKey and redeem script) or SegWit v0 (P2WPKH and the witness script of P2WSH).
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SigVersion {
+    Base,
+    WitnessV0,
+}
 
 #[derive(Debug)]
 pub struct Context {
@@ -11,16 +37,29 @@ pub struct Context {
     pub z: Integer,
     script_tokens_length: usize,
     script_tokens_position: usize,
+    op_count: usize,
 
     stack: VecDeque<Token>,
     alt_stack: VecDeque<Token>,
 
     condition_stack: ConditionStack,
     data: Option<Vec<u8>>,
+
+    flags: ScriptFlags,
+    spending_tx: Option<SpendingTx>,
+    sig_version: SigVersion,
+
+    // position of the last executed OP_CODESEPARATOR, and the z signed after each one of the script
+    codeseparator: Option<usize>,
+    codeseparator_z: HashMap<usize, Integer>,
 }
 
 impl Context {
     pub fn new(script_tokens: Vec<Token>, z: Integer) -> Self {
+        Self::new_with_flags(script_tokens, z, ScriptFlags::NONE)
+    }
+
+    pub fn new_with_flags(script_tokens: Vec<Token>, z: Integer, flags: ScriptFlags) -> Self {
         let script_tokens_length = script_tokens.len();
         let script_tokens_position = 0;
 
@@ -33,10 +72,71 @@ impl Context {
             z,
             script_tokens_length,
             script_tokens_position,
+            op_count: 0,
             stack,
             alt_stack: alternative_stack,
             condition_stack,
             data: None,
+            flags,
+            spending_tx: None,
+            sig_version: SigVersion::Base,
+            codeseparator: None,
+            codeseparator_z: HashMap::new(),
+        }
+    }
+
+    /*
+        Next script to evaluate (e.g. the ScriptPubKey after the ScriptSig): only the main stack is kept.
+    */
+    pub fn load_script(&mut self, script_tokens: Vec<Token>) {
+        self.script_tokens_length = script_tokens.len();
+        self.script_tokens = script_tokens;
+        self.script_tokens_position = 0;
+        self.op_count = 0;
+        self.alt_stack.clear();
+        self.condition_stack = ConditionStack::new();
+        self.codeseparator = None;
+        self.codeseparator_z.clear();
+    }
+
+    pub fn set_spending_tx(&mut self, spending_tx: SpendingTx) {
+        self.spending_tx = Some(spending_tx);
+    }
+
+    // Without it (e.g. a script evaluated alone), every timelock check fails
+    pub fn spending_tx(&self) -> Option<SpendingTx> {
+        self.spending_tx
+    }
+
+    pub fn set_sig_version(&mut self, sig_version: SigVersion) {
+        self.sig_version = sig_version;
+    }
+
+    pub fn sig_version(&self) -> SigVersion {
+        self.sig_version
+    }
+
+    /*
+        z of the script after each OP_CODESEPARATOR (by its position) of the loaded script: a signature checked after
+        an OP_CODESEPARATOR signs only the rest of the script (scriptCode).
+    */
+    pub fn set_codeseparator_z(&mut self, codeseparator_z: HashMap<usize, Integer>) {
+        self.codeseparator_z = codeseparator_z;
+    }
+
+    // The token just evaluated is an OP_CODESEPARATOR
+    pub fn execute_codeseparator(&mut self) {
+        self.codeseparator = Some(self.script_tokens_position - 1);
+    }
+
+    // z checked by the signature opcodes
+    pub fn signature_hash(&self) -> StdResult<&Integer> {
+        match self.codeseparator {
+            None => Ok(&self.z),
+            Some(position) => match self.codeseparator_z.get(&position) {
+                Some(z) => Ok(z),
+                None => Err("codeseparator_without_signature_hash")?,
+            },
         }
     }
 
@@ -44,6 +144,15 @@ impl Context {
         self.script_tokens_position >= self.script_tokens_length
     }
 
+    // Position of the next token to evaluate
+    pub fn position(&self) -> usize {
+        self.script_tokens_position
+    }
+
+    pub fn script_tokens(&self) -> &[Token] {
+        &self.script_tokens
+    }
+
     pub fn next_token(&mut self) -> &Token {
         assert!(self.script_tokens_position < self.script_tokens_length);
 
@@ -85,6 +194,14 @@ impl Context {
         self.stack.len()
     }
 
+    pub fn stack(&self) -> &VecDeque<Token> {
+        &self.stack
+    }
+
+    pub fn set_stack(&mut self, stack: VecDeque<Token>) {
+        self.stack = stack;
+    }
+
     pub fn stack_pop_as_element(&mut self) -> StdResult<Token> {
         assert!(!self.stack.is_empty());
 
@@ -97,6 +214,23 @@ impl Context {
         }
     }
 
+    /*
+        Operand of a numeric opcode: at most 4 bytes, minimally encoded if MINIMALDATA is set.
+    */
+    pub fn stack_pop_as_number(&mut self) -> StdResult<ScriptNum> {
+        let require_minimal = self.flags.contains(ScriptFlags::MINIMALDATA);
+
+        let bytes = self.stack_pop_as_element()?.as_bytes();
+        ScriptNum::decode(&bytes, require_minimal, DEFAULT_MAX_NUM_SIZE)
+    }
+
+    // Operand of OP_CHECKLOCKTIMEVERIFY and OP_CHECKSEQUENCEVERIFY, left on the stack
+    pub fn stack_top_as_number(&self, max_size: usize) -> StdResult<ScriptNum> {
+        let require_minimal = self.flags.contains(ScriptFlags::MINIMALDATA);
+
+        ScriptNum::decode(&self.top_stack().as_bytes(), require_minimal, max_size)
+    }
+
     pub fn stack_pop_as_command(&mut self) -> StdResult<Token> {
         assert!(!self.stack.is_empty());
 
@@ -119,24 +253,38 @@ impl Context {
         self.alt_stack.push_front(token)
     }
 
+    pub fn alt_stack(&self) -> &VecDeque<Token> {
+        &self.alt_stack
+    }
+
+    // elements on stack and alt stack
+    pub fn stacks_size(&self) -> usize {
+        self.stack.len() + self.alt_stack.len()
+    }
+
+    pub fn add_op_count(&mut self, count: usize) -> StdResult<()> {
+        self.op_count += count;
+
+        if self.op_count > MAX_OPS_PER_SCRIPT {
+            Err("op_count_exceeded")?;
+        }
+
+        Ok(())
+    }
+
     // See https://github.com/bitcoin/bitcoin/blob/d096743150fd35578b7ed71ef6bced2341927d43/src/script/interpreter.cpp#L1956C1-L1956C1
     // for reference validation
     /*
        Transactions valid:
            if the top result on the stack is TRUE (noted as {0x01}) or
-           any other non-zero value or
-           if the stack is empty
+           any other non-zero value
        Transactions are invalid:
+           if the stack is empty or
            if the top value on the stack is FALSE (a zero-length empty value, noted as {}) or
            if script execution is halted explicitly by an operator (such as OP_VERIFY, OP_RETURN, or a conditional terminator such as OP_ENDIF)
     */
     pub fn is_valid(&self) -> bool {
-        if self.stack.is_empty() {
-            return true;
-        }
-
-        let last = self.stack.len() - 1;
-        self.stack[last].as_bool()
+        self.stack.front().is_some_and(|top| top.as_bool())
     }
 
     pub fn stack_has_enough_items(&self, num: usize) -> bool {
@@ -175,6 +323,10 @@ impl Context {
         self.condition_stack.in_condition()
     }
 
+    pub fn condition_values(&self) -> Vec<bool> {
+        self.condition_stack.values()
+    }
+
     pub fn set_data(&mut self, data: Vec<u8>) {
         self.data = Some(data);
     }
@@ -182,4 +334,8 @@ impl Context {
     pub fn data(&self) -> &Option<Vec<u8>> {
         &self.data
     }
+
+    pub fn flags(&self) -> ScriptFlags {
+        self.flags
+    }
 }
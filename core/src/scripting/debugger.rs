@@ -0,0 +1,296 @@
+/*
+    Step-through evaluation of a script, to diagnose a failing spend.
+
+    Each step evaluates one token (as `ScriptLang::evaluate`) and records a snapshot of the stacks,
+    the whole trace stays available to look back at the previous steps.
+    Stacks are listed from the top.
+*/
+
+use std::collections::BTreeSet;
+
+use crate::std_lib::std_result::StdResult;
+
+use super::{context::Context, script_lang::ScriptLang, token::Token};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    // position of the evaluated token in the script
+    pub position: usize,
+    pub token: Token,
+    // false in a branch not executed
+    pub executed: bool,
+    pub stack: Vec<Token>,
+    pub alt_stack: Vec<Token>,
+    pub condition_stack: Vec<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebuggerState {
+    // next token at the position
+    Paused(usize),
+    // stopped before the token at the position
+    Breakpoint(usize),
+    Finished(bool),
+    Failed { position: usize, error: String },
+}
+
+#[derive(Debug)]
+pub struct Debugger {
+    context: Context,
+    breakpoints: BTreeSet<usize>,
+    trace: Vec<Snapshot>,
+    state: DebuggerState,
+}
+
+impl Snapshot {
+    fn new(position: usize, token: Token, executed: bool, context: &Context) -> Self {
+        Self {
+            position,
+            token,
+            executed,
+            stack: context.stack().iter().cloned().collect(),
+            alt_stack: context.alt_stack().iter().cloned().collect(),
+            condition_stack: context.condition_values(),
+        }
+    }
+}
+
+impl Debugger {
+    /*
+        `context` is created with the tokens of `script`, as for `script.evaluate(context)`.
+    */
+    pub fn new(script: &ScriptLang, context: Context) -> StdResult<Self> {
+        script.check_size()?;
+
+        let state = match context.tokens_are_over() {
+            true => DebuggerState::Finished(context.is_valid()),
+            false => DebuggerState::Paused(context.position()),
+        };
+
+        Ok(Self {
+            context,
+            breakpoints: BTreeSet::new(),
+            trace: vec![],
+            state,
+        })
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn state(&self) -> &DebuggerState {
+        &self.state
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self.state, DebuggerState::Finished(_) | DebuggerState::Failed { .. })
+    }
+
+    // Token evaluated by the next step
+    pub fn next_token(&self) -> Option<&Token> {
+        match self.is_over() {
+            true => None,
+            false => self.context.script_tokens().get(self.context.position()),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, position: usize) {
+        self.breakpoints.insert(position);
+    }
+
+    pub fn remove_breakpoint(&mut self, position: usize) -> bool {
+        self.breakpoints.remove(&position)
+    }
+
+    pub fn breakpoints(&self) -> Vec<usize> {
+        self.breakpoints.iter().copied().collect()
+    }
+
+    pub fn step(&mut self) -> &DebuggerState {
+        if self.is_over() {
+            return &self.state;
+        }
+
+        let position = self.context.position();
+        let token = self.context.script_tokens()[position].clone();
+
+        self.state = match ScriptLang::evaluate_next(&mut self.context) {
+            Ok(executed) => {
+                self.trace.push(Snapshot::new(position, token, executed, &self.context));
+
+                match self.context.tokens_are_over() {
+                    true => DebuggerState::Finished(self.context.is_valid()),
+                    false => DebuggerState::Paused(self.context.position()),
+                }
+            }
+            Err(e) => {
+                log::debug!("Evaluation failed at {}: {}", position, e);
+                DebuggerState::Failed {
+                    position,
+                    error: e.to_string(),
+                }
+            }
+        };
+
+        &self.state
+    }
+
+    /*
+        Step until the end of the script or a breakpoint, the token at the current position is always evaluated
+        (to continue from a breakpoint).
+    */
+    pub fn resume(&mut self) -> &DebuggerState {
+        self.step();
+
+        while !self.is_over() {
+            let position = self.context.position();
+
+            if self.breakpoints.contains(&position) {
+                self.state = DebuggerState::Breakpoint(position);
+                break;
+            }
+
+            self.step();
+        }
+
+        &self.state
+    }
+
+    pub fn trace(&self) -> &[Snapshot] {
+        &self.trace
+    }
+
+    // `back` steps before the last one (0 for the last one)
+    pub fn snapshot_back(&self, back: usize) -> Option<&Snapshot> {
+        let index = self.trace.len().checked_sub(back + 1)?;
+        self.trace.get(index)
+    }
+}
+
+#[cfg(test)]
+mod debugger_test {
+    use rug::Integer;
+
+    use super::*;
+    use crate::scripting::{asm::assemble_script, opcode::*};
+
+    fn debugger(source: &str) -> Debugger {
+        let script = assemble_script(source, &[]).unwrap();
+        let context = Context::new(script.tokens(), Integer::from(0));
+
+        Debugger::new(&script, context).unwrap()
+    }
+
+    fn element(n: u8) -> Token {
+        Token::Element(vec![n])
+    }
+
+    #[test]
+    fn step_stacks() {
+        let mut debugger = debugger("OP_2 OP_3 OP_TOALTSTACK OP_DUP");
+
+        assert_eq!(debugger.next_token(), Some(&Token::Command(OP_2)));
+        assert_eq!(debugger.step(), &DebuggerState::Paused(1));
+        assert_eq!(debugger.step(), &DebuggerState::Paused(2));
+        assert_eq!(debugger.step(), &DebuggerState::Paused(3));
+
+        let snapshot = debugger.snapshot_back(0).unwrap();
+        assert_eq!(snapshot.position, 2);
+        assert_eq!(snapshot.token, Token::Command(OP_TOALTSTACK));
+        assert_eq!(snapshot.stack, vec![element(2)]);
+        assert_eq!(snapshot.alt_stack, vec![element(3)]);
+
+        assert_eq!(debugger.step(), &DebuggerState::Finished(true));
+        assert_eq!(debugger.trace().last().unwrap().stack, vec![element(2), element(2)]);
+        assert_eq!(debugger.next_token(), None);
+
+        // nothing left to evaluate
+        assert_eq!(debugger.step(), &DebuggerState::Finished(true));
+        assert_eq!(debugger.trace().len(), 4);
+    }
+
+    #[test]
+    fn step_branches() {
+        let mut debugger = debugger("OP_0 OP_IF OP_2 OP_ELSE OP_1 OP_IF OP_3 OP_ENDIF OP_ENDIF");
+        while !debugger.is_over() {
+            debugger.step();
+        }
+
+        let executed: Vec<bool> = debugger.trace().iter().map(|s| s.executed).collect();
+        assert_eq!(executed, vec![true, true, false, true, true, true, true, true, true]);
+
+        let condition_stacks: Vec<Vec<bool>> = debugger.trace().iter().map(|s| s.condition_stack.clone()).collect();
+        assert_eq!(
+            condition_stacks,
+            vec![
+                vec![],
+                vec![false],
+                vec![false],
+                vec![true],
+                vec![true],
+                vec![true, true],
+                vec![true, true],
+                vec![true],
+                vec![]
+            ]
+        );
+
+        assert_eq!(debugger.state(), &DebuggerState::Finished(true));
+    }
+
+    #[test]
+    fn resume_breakpoints() {
+        let mut debugger = debugger("OP_1 OP_2 OP_ADD OP_3 OP_EQUAL");
+        debugger.add_breakpoint(2);
+        debugger.add_breakpoint(4);
+
+        assert_eq!(debugger.resume(), &DebuggerState::Breakpoint(2));
+        assert_eq!(debugger.next_token(), Some(&Token::Command(OP_ADD)));
+        assert_eq!(debugger.resume(), &DebuggerState::Breakpoint(4));
+        assert_eq!(debugger.snapshot_back(0).unwrap().stack, vec![element(3), element(3)]);
+
+        assert!(debugger.remove_breakpoint(4));
+        assert!(!debugger.remove_breakpoint(4));
+        assert_eq!(debugger.breakpoints(), vec![2]);
+
+        assert_eq!(debugger.resume(), &DebuggerState::Finished(true));
+    }
+
+    #[test]
+    fn resume_failure() {
+        let mut debugger = debugger("OP_1 OP_2 OP_EQUALVERIFY OP_1");
+
+        assert_eq!(
+            debugger.resume(),
+            &DebuggerState::Failed {
+                position: 2,
+                error: "exit_by_failed_verify".to_string()
+            }
+        );
+        assert!(debugger.is_over());
+        assert_eq!(debugger.trace().len(), 2);
+        assert_eq!(debugger.snapshot_back(1).unwrap().token, Token::Command(OP_1));
+        assert_eq!(debugger.snapshot_back(2), None);
+    }
+
+    #[test]
+    fn same_result_as_evaluate() {
+        let source = "OP_1 OP_1 OP_ADD OP_2 OP_EQUAL OP_NOTIF OP_0 OP_ENDIF";
+        let script = assemble_script(source, &[]).unwrap();
+
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let evaluated = script.evaluate(&mut context).unwrap();
+
+        let mut debugger = debugger(source);
+        assert_eq!(debugger.resume(), &DebuggerState::Finished(evaluated));
+        assert_eq!(debugger.context().stack(), context.stack());
+    }
+
+    #[test]
+    fn empty_script() {
+        // an empty stack is false
+        let debugger = debugger("");
+        assert_eq!(debugger.state(), &DebuggerState::Finished(false));
+    }
+}
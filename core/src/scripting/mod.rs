@@ -1,8 +1,11 @@
+pub mod asm;
 pub mod condition_stack;
 pub mod constants;
 pub mod context;
+pub mod debugger;
 pub mod opcode;
 pub mod opcode_fn;
 pub mod script_lang;
+pub mod script_num;
 pub mod standard;
 pub mod token;
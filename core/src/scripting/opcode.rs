@@ -248,14 +248,14 @@ pub static OP_TO_FN: Lazy<[OpCodeInfo; OPS_LENGTH]> = Lazy::new(|| {
     op2fn!(OP_SHA256, op_sha256);
     op2fn!(OP_HASH160, op_hash160);
     op2fn!(OP_HASH256, op_hash256);
-    op2fn!(OP_CODESEPARATOR, not_implemented);
+    op2fn!(OP_CODESEPARATOR, op_codeseparator);
     op2fn!(OP_CHECKSIG, op_checksig);
-    op2fn!(OP_CHECKSIGVERIFY, not_implemented);
+    op2fn!(OP_CHECKSIGVERIFY, op_checksigverify);
     op2fn!(OP_CHECKMULTISIG, op_checkmultisig);
-    op2fn!(OP_CHECKMULTISIGVERIFY, not_implemented);
+    op2fn!(OP_CHECKMULTISIGVERIFY, op_checkmultisigverify);
     op2fn!(OP_NOP1, ignored);
-    op2fn!(OP_CHECKLOCKTIMEVERIFY, not_implemented);
-    op2fn!(OP_CHECKSEQUENCEVERIFY, not_implemented);
+    op2fn!(OP_CHECKLOCKTIMEVERIFY, op_checklocktimeverify);
+    op2fn!(OP_CHECKSEQUENCEVERIFY, op_checksequenceverify);
     op2fn!(OP_NOP4, ignored);
     op2fn!(OP_NOP5, ignored);
     op2fn!(OP_NOP6, ignored);
@@ -263,22 +263,36 @@ pub static OP_TO_FN: Lazy<[OpCodeInfo; OPS_LENGTH]> = Lazy::new(|| {
     op2fn!(OP_NOP8, ignored);
     op2fn!(OP_NOP9, ignored);
     op2fn!(OP_NOP10, ignored);
-    op2fn!(OP_CHECKSIGADD, not_implemented);
+    // tapscript only (BIP342), not supported: outside tapscript it is an invalid opcode
+    op2fn!(OP_CHECKSIGADD, invalid);
     op2fn!(OP_PUBKEY, invalid);
     op2fn!(OP_PUBKEYHASH, invalid);
     op2fn!(OP_INVALIDOPCODE, invalid);
 
-    // implement deprecated op codes for testing only
-    // https://github.com/bitcoin/bitcoin/blob/d096743150fd35578b7ed71ef6bced2341927d43/src/script/interpreter.cpp#L456
-    #[cfg(test)]
-    fn implement_deprecated(op_to_fn: &mut [OpCodeInfo; OPS_LENGTH]) {
-        op_to_fn[OP_MUL] = OpCodeInfo::new("OP_MUL", op_mul);
-    }
-
-    #[cfg(not(test))]
-    fn implement_deprecated(_op_to_fn: &mut [OpCodeInfo; OPS_LENGTH]) {}
-
-    implement_deprecated(&mut op_to_fn);
-
     op_to_fn
 });
+
+/*
+    Disabled opcodes make the script fail even in a branch that is not executed.
+    https://github.com/bitcoin/bitcoin/blob/d096743150fd35578b7ed71ef6bced2341927d43/src/script/interpreter.cpp#L456
+*/
+pub fn is_disabled(op_code: OpCode) -> bool {
+    matches!(
+        op_code,
+        OP_CAT
+            | OP_SUBSTR
+            | OP_LEFT
+            | OP_RIGHT
+            | OP_INVERT
+            | OP_AND
+            | OP_OR
+            | OP_XOR
+            | OP_2MUL
+            | OP_2DIV
+            | OP_MUL
+            | OP_DIV
+            | OP_MOD
+            | OP_LSHIFT
+            | OP_RSHIFT
+    )
+}
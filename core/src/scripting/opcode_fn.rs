@@ -1,5 +1,4 @@
 /*
-    TODO:
     All these function are used to validate
         1) old transactions (txs already in the chain)
         2) new transactions (txs in pool wating for validation)
@@ -7,20 +6,29 @@
     in chain but not compliant with the new rules, in the second case validation should be more strict in order
     to reject new transactions that are not compliant with the new rules.
 
-    CURRENTLY WE ARE USING THE SAME VALIDATION RULES FOR BOTH CASES BUT, AT THE END OF THE DAY, WE SHOULD
-    SPLIT VALIDATION IN TWO DIFFERENT VERIFICATION APPROACHES.
+    The rules to apply are selected by the `ScriptFlags` of the context: `ScriptFlags::consensus` for a block
+    at a given height, `ScriptFlags::STANDARD` for new transactions.
 */
 
 use rug::Integer;
 
 use crate::{
     ecdsa::point::Point,
+    flags::script_flags::ScriptFlags,
     hashing::{hash160::hash160, hash256::Hash256, ripemd160::ripemd160, sha1::sha1, sha256::sha256},
     keys::{key::Key, signature::Signature},
     std_lib::std_result::StdResult,
 };
 
-use super::{constants::MAX_RETURN_DATA_LENGTH, context::Context, token::*};
+use super::{
+    constants::{
+        LOCKTIME_MAX_NUM_SIZE, LOCKTIME_THRESHOLD, MAX_PUBKEYS_PER_MULTISIG, MAX_RETURN_DATA_LENGTH, SEQUENCE_FINAL,
+        SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG,
+    },
+    context::{Context, SigVersion, SpendingTx},
+    script_num::ScriptNum,
+    token::*,
+};
 
 /*
    Ref: https://en.bitcoin.it/wiki/Script
@@ -28,7 +36,7 @@ use super::{constants::MAX_RETURN_DATA_LENGTH, context::Context, token::*};
 macro_rules! op_n {
     ($n:tt, $f:ident) => {
         pub fn $f(context: &mut Context) -> StdResult<bool> {
-            context.stack_push(Token::Element(element_encode($n)));
+            context.stack_push(Token::Element(ScriptNum::new($n).encode()));
 
             Ok(true)
         }
@@ -57,26 +65,57 @@ op_n!(16, op_16);
 
 fn element_value_by_result(res: bool) -> Vec<u8> {
     if res {
-        ELEMENT_ONE.to_vec()
+        ELEMENT_TRUE.to_vec()
     } else {
-        ELEMENT_ZERO.to_vec()
+        ELEMENT_FALSE.to_vec()
+    }
+}
+
+/*
+    Numeric opcodes: operands are popped as `ScriptNum` (see `Context::stack_pop_as_number`),
+    `a` is the element below the top of the stack, `b` the top.
+*/
+fn unary_number_op(context: &mut Context, op: impl Fn(i64) -> i64) -> StdResult<bool> {
+    if !context.stack_has_enough_items(1) {
+        Err("not_enough_items_in_stack")?;
     }
+
+    let n = context.stack_pop_as_number()?;
+    context.stack_push(Token::Element(ScriptNum::new(op(n.value())).encode()));
+
+    Ok(true)
+}
+
+fn binary_number_op(context: &mut Context, op: impl Fn(i64, i64) -> i64) -> StdResult<bool> {
+    if !context.stack_has_enough_items(2) {
+        Err("not_enough_items_in_stack")?;
+    }
+
+    let b = context.stack_pop_as_number()?;
+    let a = context.stack_pop_as_number()?;
+    context.stack_push(Token::Element(ScriptNum::new(op(a.value(), b.value())).encode()));
+
+    Ok(true)
+}
+
+fn binary_number_condition(context: &mut Context, condition: impl Fn(i64, i64) -> bool) -> StdResult<bool> {
+    binary_number_op(context, |a, b| condition(a, b) as i64)
 }
 
 pub fn op_0(context: &mut Context) -> StdResult<bool> {
-    context.stack_push(Token::Element(ELEMENT_ZERO.to_vec()));
+    context.stack_push(Token::Element(ELEMENT_EMPTY.to_vec()));
 
     Ok(true)
 }
 
 pub fn op_1(context: &mut Context) -> StdResult<bool> {
-    context.stack_push(Token::Element(element_encode(1)));
+    context.stack_push(Token::Element(ScriptNum::ONE.encode()));
 
     Ok(true)
 }
 
 pub fn op_1negate(context: &mut Context) -> StdResult<bool> {
-    context.stack_push(Token::Element(ELEMENT_ONE_NEGATE.to_vec()));
+    context.stack_push(Token::Element(ScriptNum::new(-1).encode()));
 
     Ok(true)
 }
@@ -128,6 +167,7 @@ pub fn op_if(context: &mut Context) -> StdResult<bool> {
         }
 
         let token = context.stack_pop_as_element()?;
+        check_minimal_if(&token, context)?;
         exec = token.as_bool();
     }
 
@@ -145,6 +185,7 @@ pub fn op_notif(context: &mut Context) -> StdResult<bool> {
         }
 
         let token = context.stack_pop_as_element()?;
+        check_minimal_if(&token, context)?;
         exec = !token.as_bool();
     }
 
@@ -153,6 +194,21 @@ pub fn op_notif(context: &mut Context) -> StdResult<bool> {
     Ok(true)
 }
 
+/*
+    MINIMALIF: the argument of OP_IF/OP_NOTIF of a witness script must be exactly empty (false) or [0x01] (true).
+    Legacy scripts take any value.
+*/
+fn check_minimal_if(token: &Token, context: &Context) -> StdResult<()> {
+    if context.sig_version() == SigVersion::Base || !context.flags().contains(ScriptFlags::MINIMALIF) {
+        return Ok(());
+    }
+
+    match token.as_bytes().as_slice() {
+        [] | [0x01] => Ok(()),
+        _ => Err("minimal_if")?,
+    }
+}
+
 pub fn op_endif(context: &mut Context) -> StdResult<bool> {
     if !context.in_condition() {
         Err("unexpected_end_if")?;
@@ -172,74 +228,11 @@ pub fn op_else(context: &mut Context) -> StdResult<bool> {
 }
 
 pub fn op_add(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
-
-    if let (Token::Element(a), Token::Element(b)) = (elem1, elem2) {
-        if a.len() > 4 || b.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let left = element_decode(a);
-        let right = element_decode(b);
-
-        let sum = left + right;
-        context.stack_push(Token::Element(element_encode(sum)));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    binary_number_op(context, |a, b| a + b)
 }
 
 pub fn op_sub(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let b = context.stack_pop_as_element()?;
-    let a = context.stack_pop_as_element()?;
-
-    if let (Token::Element(a), Token::Element(b)) = (a, b) {
-        if a.len() > 4 || b.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let left = element_decode(a);
-        let right = element_decode(b);
-
-        let sub = left.checked_sub(right).ok_or("overflow")?;
-        context.stack_push(Token::Element(element_encode(sub)));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
-}
-
-pub fn op_mul(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let a = context.stack_pop_as_element()?;
-    let b = context.stack_pop_as_element()?;
-
-    if let (Token::Element(a), Token::Element(b)) = (a, b) {
-        let left = element_decode(a);
-        let right = element_decode(b);
-
-        let sum = left * right;
-        context.stack_push(Token::Element(element_encode(sum)));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    binary_number_op(context, |a, b| a - b)
 }
 
 pub fn op_equal(context: &mut Context) -> StdResult<bool> {
@@ -251,8 +244,7 @@ pub fn op_equal(context: &mut Context) -> StdResult<bool> {
     let b = context.stack_pop_as_element()?;
 
     if let (Token::Element(left), Token::Element(right)) = (a, b) {
-        let equals = if left == right { ELEMENT_TRUE } else { ELEMENT_FALSE };
-        context.stack_push(Token::Element(equals.to_vec()));
+        context.stack_push(Token::Element(element_value_by_result(left == right)));
 
         return Ok(true);
     }
@@ -263,7 +255,7 @@ pub fn op_equal(context: &mut Context) -> StdResult<bool> {
 /*
    https://en.bitcoin.it/wiki/OP_CHECKSIG
 
-   TODO: consider OP_CODESEPARATOR
+   After an OP_CODESEPARATOR the signature signs only the rest of the script.
 */
 pub fn op_checksig(context: &mut Context) -> StdResult<bool> {
     if !context.stack_has_enough_items(2) {
@@ -275,10 +267,18 @@ pub fn op_checksig(context: &mut Context) -> StdResult<bool> {
 
     if let Token::Element(public_key) = pub_key {
         if let Token::Element(mut der) = sig {
+            check_signature_encoding(&der, context.flags())?;
+            let sig_is_empty = der.is_empty();
+
             // Removing last byte, that is the signature hash (SIGHASH): https://learn.saylor.org/mod/book/view.php?id=36341&chapterid=18919
             der.pop();
 
-            let res = signature_is_valid(&der, &public_key, &context.z);
+            let res = signature_is_valid(&der, &public_key, context.signature_hash()?, context.flags());
+
+            // BIP146: a failing signature must be empty
+            if !res && !sig_is_empty && context.flags().contains(ScriptFlags::NULLFAIL) {
+                Err("signature_null_fail")?;
+            }
 
             let element_value = element_value_by_result(res);
             context.stack_push(Token::Element(element_value));
@@ -313,13 +313,18 @@ pub fn op_checkmultisig(context: &mut Context) -> StdResult<bool> {
     /*
        n
     */
-    let elem_n = context.stack_pop_as_element()?;
-    let n = elem_n.as_number() as usize;
+    // 0-of-0 and 0-of-n are valid (and always succeed), as in Bitcoin Core
+    let n = context.stack_pop_as_number()?.value();
 
-    if n == 0 {
-        Err("expected_n_for_multisig")?;
+    if n < 0 || n > MAX_PUBKEYS_PER_MULTISIG as i64 {
+        Err("pubkey_count_exceeded")?;
     }
 
+    let n = n as usize;
+
+    // every public key counts as an opcode
+    context.add_op_count(n)?;
+
     if !context.stack_has_enough_items(n) {
         Err("not_enough_items_in_stack")?;
     }
@@ -336,65 +341,79 @@ pub fn op_checkmultisig(context: &mut Context) -> StdResult<bool> {
     /*
        m
     */
-    let elem_m = context.stack_pop_as_element()?;
-    let m = elem_m.as_number() as usize;
-
-    if m == 0 {
-        Err("expected_m_for_multisig")?;
+    if !context.stack_has_enough_items(1) {
+        Err("not_enough_items_in_stack")?;
     }
 
-    if m > n {
+    let m = context.stack_pop_as_number()?.value();
+
+    if m < 0 || m > n as i64 {
         Err("m_greater_than_n_for_multisig")?;
     }
 
+    let m = m as usize;
+
     if !context.stack_has_enough_items(m) {
         Err("not_enough_items_in_stack")?;
     }
 
-    let mut der_signatures = Vec::new();
+    // the encoding of a signature is checked only when it is compared (as in Bitcoin Core)
+    let mut signatures = Vec::new();
     for _ in 0..m {
-        let elem = context.stack_pop_as_element()?;
-        let mut der_signature = elem.as_bytes();
-        // Removing last byte, that is the signature hash (SIGHASH): https://learn.saylor.org/mod/book/view.php?id=36341&chapterid=18919
-        der_signature.pop();
-
-        der_signatures.push(der_signature);
+        signatures.push(context.stack_pop_as_element()?.as_bytes());
     }
 
     log::debug!("MS: m: {}", m);
 
     /*
-       one-of-error
+       one-of-error: the extra element popped by OP_CHECKMULTISIG, any value is consensus valid
     */
-    let token = context.stack_pop();
-    if !token.is_zero_or_empy() {
-        Err("expected_op0_for_multisig")?;
+    if !context.stack_has_enough_items(1) {
+        Err("not_enough_items_in_stack")?;
+    }
+
+    let dummy = context.stack_pop();
+
+    // BIP147: the dummy element must be the empty byte vector
+    if context.flags().contains(ScriptFlags::NULLDUMMY) && dummy != Token::Element(vec![]) {
+        Err("multisig_dummy_not_null")?;
     }
 
     /*
-       checking
+       checking: signatures and public keys in the same order, every key is used at most once
     */
     let mut pk_index: usize = 0;
     let mut valid_signatures: usize = 0;
+    let mut success = true;
 
-    for der in der_signatures {
-        while pk_index < sec_pub_keys.len() {
-            let public_key = &sec_pub_keys[pk_index];
-            let res = signature_is_valid(&der, public_key, &context.z);
+    while success && valid_signatures < m {
+        let signature = &signatures[valid_signatures];
+        check_signature_encoding(signature, context.flags())?;
 
-            if res {
-                valid_signatures += 1;
-                break;
-            }
+        // Removing last byte, that is the signature hash (SIGHASH): https://learn.saylor.org/mod/book/view.php?id=36341&chapterid=18919
+        let der = &signature[..signature.len().saturating_sub(1)];
 
-            pk_index += 1;
+        if signature_is_valid(der, &sec_pub_keys[pk_index], context.signature_hash()?, context.flags()) {
+            valid_signatures += 1;
         }
+
+        pk_index += 1;
+
+        // not enough public keys left for the remaining signatures
+        success = m - valid_signatures <= n - pk_index;
     }
 
     log::debug!("MS: valid signature: {} (should match with m)", valid_signatures);
 
+    let signatures_are_empty = signatures.iter().all(|signature| signature.is_empty());
+
+    // BIP146: if the check fails all the signatures must be empty
+    if !success && !signatures_are_empty && context.flags().contains(ScriptFlags::NULLFAIL) {
+        Err("signature_null_fail")?;
+    }
+
     // In other words, all provided signatures must match at least one of the public keys in the redeem script.
-    let element_value = element_value_by_result(valid_signatures == m);
+    let element_value = element_value_by_result(success);
     context.stack_push(Token::Element(element_value));
 
     log::debug!("MS: Multisignature check end");
@@ -402,8 +421,54 @@ pub fn op_checkmultisig(context: &mut Context) -> StdResult<bool> {
     Ok(true)
 }
 
-fn signature_is_valid(der: &[u8], public_key: &[u8], z: &Integer) -> bool {
-    let signature = match Signature::new_from_der(der.to_vec()) {
+pub fn op_checksigverify(context: &mut Context) -> StdResult<bool> {
+    op_checksig(context)?;
+    verify(context, "exit_by_failed_checksigverify")
+}
+
+pub fn op_checkmultisigverify(context: &mut Context) -> StdResult<bool> {
+    op_checkmultisig(context)?;
+    verify(context, "exit_by_failed_checkmultisigverify")
+}
+
+/*
+    Signature encoding rules, `sig` includes the SIGHASH byte.
+    An empty signature is always allowed: it is the standard way to make OP_CHECKSIG fail without failing the script.
+
+    Ref: https://github.com/bitcoin/bitcoin/blob/master/src/script/interpreter.cpp (CheckSignatureEncoding)
+*/
+fn check_signature_encoding(sig: &[u8], flags: ScriptFlags) -> StdResult<()> {
+    if sig.is_empty() {
+        return Ok(());
+    }
+
+    if flags.intersects(ScriptFlags::DERSIG | ScriptFlags::LOW_S) {
+        if let Err(e) = Signature::check_strict_der(sig) {
+            log::debug!("Non strict DER signature: {}", e);
+            Err("signature_not_strict_der")?;
+        }
+    }
+
+    if flags.contains(ScriptFlags::LOW_S) {
+        let signature = Signature::new_from_der(sig[..sig.len() - 1].to_vec())?;
+
+        if !signature.is_low_s() {
+            Err("signature_high_s")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn signature_is_valid(der: &[u8], public_key: &[u8], z: &Integer, flags: ScriptFlags) -> bool {
+    // before BIP66 any signature accepted by OpenSSL was valid
+    let parsed = if flags.contains(ScriptFlags::DERSIG) {
+        Signature::new_from_der(der.to_vec())
+    } else {
+        Signature::new_from_der_lax(der)
+    };
+
+    let signature = match parsed {
         Ok(signature) => signature,
         Err(_) => {
             log::debug!("Invalid DER signature during OP_ checking");
@@ -411,8 +476,14 @@ fn signature_is_valid(der: &[u8], public_key: &[u8], z: &Integer) -> bool {
         }
     };
 
-    let point = Point::deserialize(public_key.to_vec());
-    Key::verify_signature(&point, z, &signature)
+    // any bytes can be pushed as a public key: an invalid one makes the check fail
+    match Point::try_deserialize(public_key) {
+        Ok(point) => Key::verify_signature(&point, z, &signature),
+        Err(_) => {
+            log::debug!("Invalid public key during OP_ checking");
+            false
+        }
+    }
 }
 
 pub fn op_dup(context: &mut Context) -> StdResult<bool> {
@@ -552,6 +623,11 @@ pub fn op_2rot(context: &mut Context) -> StdResult<bool> {
 }
 
 pub fn op_verify(context: &mut Context) -> StdResult<bool> {
+    verify(context, "exit_by_failed_verify")
+}
+
+// Pop the top element and fail with `error` if false (OP_VERIFY and the *VERIFY opcodes)
+fn verify(context: &mut Context, error: &'static str) -> StdResult<bool> {
     if !context.stack_has_enough_items(1) {
         Err("not_enough_items_in_stack")?;
     }
@@ -562,7 +638,7 @@ pub fn op_verify(context: &mut Context) -> StdResult<bool> {
         return Ok(true);
     }
 
-    Err("exit_by_failed_verify")?
+    Err(error)?
 }
 
 pub fn op_equalverify(context: &mut Context) -> StdResult<bool> {
@@ -651,26 +727,7 @@ pub fn op_sha1(context: &mut Context) -> StdResult<bool> {
 }
 
 pub fn op_not(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(1) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let e = context.stack_pop();
-
-    if let Token::Element(bytes) = e {
-        if bytes.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        if bytes == ELEMENT_ZERO {
-            context.stack_push(Token::Element(ELEMENT_ONE.to_vec()));
-            return Ok(true);
-        }
-    }
-
-    context.stack_push(Token::Element(ELEMENT_ZERO.to_vec()));
-
-    Ok(true)
+    unary_number_op(context, |n| (n == 0) as i64)
 }
 
 pub fn op_toaltstack(context: &mut Context) -> StdResult<bool> {
@@ -745,7 +802,7 @@ pub fn op_ifdup(context: &mut Context) -> StdResult<bool> {
 
 pub fn op_depth(context: &mut Context) -> StdResult<bool> {
     let len = context.stack_len();
-    context.stack_push(Token::Element(element_encode(len as i64)));
+    context.stack_push(Token::Element(ScriptNum::new(len as i64).encode()));
 
     Ok(true)
 }
@@ -789,7 +846,7 @@ pub fn op_size(context: &mut Context) -> StdResult<bool> {
     context.stack_push(elem.clone());
 
     if let Token::Element(v) = elem {
-        context.stack_push(Token::Element(element_encode(v.len() as i64)));
+        context.stack_push(Token::Element(ScriptNum::new(v.len() as i64).encode()));
 
         return Ok(true);
     }
@@ -802,22 +859,16 @@ pub fn op_pick(context: &mut Context) -> StdResult<bool> {
         Err("not_enough_items_in_stack")?;
     }
 
-    let e = context.stack_pop_as_element()?;
-    if let Token::Element(v) = e {
-        let n = element_decode(v);
-        let un = n as usize;
-
-        if !context.stack_has_enough_items(un + 1) {
-            Err("not_enough_items_in_stack")?;
-        }
-
-        let elem = context.stack_get_at(un);
-        context.stack_push(elem.clone());
-
-        return Ok(true);
+    let n = context.stack_pop_as_number()?.value();
+    if n < 0 || !context.stack_has_enough_items(n as usize + 1) {
+        Err("not_enough_items_in_stack")?;
     }
 
-    Err("not_an_element")?
+    let n = n as usize;
+    let elem = context.stack_get_at(n);
+    context.stack_push(elem.clone());
+
+    Ok(true)
 }
 
 pub fn op_roll(context: &mut Context) -> StdResult<bool> {
@@ -825,204 +876,48 @@ pub fn op_roll(context: &mut Context) -> StdResult<bool> {
         Err("not_enough_items_in_stack")?;
     }
 
-    let e = context.stack_pop_as_element()?;
-    if let Token::Element(bytes) = e {
-        let n = element_decode(bytes);
-        let un = n as usize;
-
-        if !context.stack_has_enough_items(un + 1) {
-            Err("not_enough_items_in_stack")?;
-        }
-
-        let elem = context.stack_remove_at(un);
-        context.stack_push(elem);
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
-}
-
-pub fn op_1add(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(1) {
+    let n = context.stack_pop_as_number()?.value();
+    if n < 0 || !context.stack_has_enough_items(n as usize + 1) {
         Err("not_enough_items_in_stack")?;
     }
 
-    let elem = context.stack_pop_as_element()?;
-    if let Token::Element(bytes) = elem {
-        if bytes.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let n = element_decode(bytes);
-        context.stack_push(Token::Element(element_encode(n + 1)));
+    let n = n as usize;
+    let elem = context.stack_remove_at(n);
+    context.stack_push(elem);
 
-        return Ok(true);
-    }
+    Ok(true)
+}
 
-    Err("not_an_element")?
+pub fn op_1add(context: &mut Context) -> StdResult<bool> {
+    unary_number_op(context, |n| n + 1)
 }
 
 pub fn op_1sub(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(1) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem = context.stack_pop_as_element()?;
-    if let Token::Element(bytes) = elem {
-        if bytes.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let n = element_decode(bytes);
-        context.stack_push(Token::Element(element_encode(n - 1)));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    unary_number_op(context, |n| n - 1)
 }
 
 pub fn op_negate(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(1) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem = context.stack_pop_as_element()?;
-    if let Token::Element(bytes) = elem {
-        if bytes.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let n = element_decode(bytes);
-        context.stack_push(Token::Element(element_encode(-n)));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    unary_number_op(context, |n| -n)
 }
 
 pub fn op_abs(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(1) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem = context.stack_pop_as_element()?;
-    if let Token::Element(bytes) = elem {
-        if bytes.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let n = element_decode(bytes);
-        context.stack_push(Token::Element(element_encode(n.abs())));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    unary_number_op(context, |n| n.abs())
 }
 
 pub fn op_booland(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
-
-    let elem1_bool = elem1.as_bool();
-    let elem2_bool = elem2.as_bool();
-
-    if let (Token::Element(left), Token::Element(right)) = (elem1, elem2) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let booland = if elem1_bool && elem2_bool {
-            ELEMENT_TRUE
-        } else {
-            ELEMENT_FALSE
-        };
-        context.stack_push(Token::Element(booland.to_vec()));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    binary_number_condition(context, |a, b| a != 0 && b != 0)
 }
 
 pub fn op_boolor(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
-
-    let elem1_bool = elem1.as_bool();
-    let elem2_bool = elem2.as_bool();
-
-    if let (Token::Element(left), Token::Element(right)) = (elem1, elem2) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let boolean = if elem1_bool || elem2_bool {
-            ELEMENT_TRUE
-        } else {
-            ELEMENT_FALSE
-        };
-        context.stack_push(Token::Element(boolean.to_vec()));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    binary_number_condition(context, |a, b| a != 0 || b != 0)
 }
 
 pub fn op_0notequal(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(1) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem = context.stack_pop_as_element()?;
-    if let Token::Element(bytes) = elem {
-        if bytes.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let n = element_decode(bytes);
-        let val = if n != 0 { ELEMENT_ONE } else { ELEMENT_ZERO };
-
-        context.stack_push(Token::Element(val.to_vec()));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    unary_number_op(context, |n| (n != 0) as i64)
 }
 
 pub fn op_numequal(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
-
-    if let (Token::Element(left), Token::Element(right)) = (elem1, elem2) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let boolean = if left == right { ELEMENT_TRUE } else { ELEMENT_FALSE };
-        context.stack_push(Token::Element(boolean.to_vec()));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    binary_number_condition(context, |a, b| a == b)
 }
 
 pub fn op_numequalverify(context: &mut Context) -> StdResult<bool> {
@@ -1031,190 +926,138 @@ pub fn op_numequalverify(context: &mut Context) -> StdResult<bool> {
 }
 
 pub fn op_numnotequal(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
-
-    if let (Token::Element(left), Token::Element(right)) = (elem1, elem2) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let boolean = if left != right { ELEMENT_TRUE } else { ELEMENT_FALSE };
-        context.stack_push(Token::Element(boolean.to_vec()));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    binary_number_condition(context, |a, b| a != b)
 }
 
 pub fn op_lessthan(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
-
-    if let (Token::Element(left), Token::Element(right)) = (elem2, elem1) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let boolean = if left < right { ELEMENT_TRUE } else { ELEMENT_FALSE };
-        context.stack_push(Token::Element(boolean.to_vec()));
-
-        return Ok(true);
-    }
-
-    Err("not_an_element")?
+    binary_number_condition(context, |a, b| a < b)
 }
 
 pub fn op_lessthanorequal(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
-
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
+    binary_number_condition(context, |a, b| a <= b)
+}
 
-    if let (Token::Element(left), Token::Element(right)) = (elem2, elem1) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
+pub fn op_greaterthan(context: &mut Context) -> StdResult<bool> {
+    binary_number_condition(context, |a, b| a > b)
+}
 
-        let boolean = if left <= right { ELEMENT_TRUE } else { ELEMENT_FALSE };
-        context.stack_push(Token::Element(boolean.to_vec()));
+pub fn op_greaterthanorequal(context: &mut Context) -> StdResult<bool> {
+    binary_number_condition(context, |a, b| a >= b)
+}
 
-        return Ok(true);
-    }
+pub fn op_min(context: &mut Context) -> StdResult<bool> {
+    binary_number_op(context, |a, b| a.min(b))
+}
 
-    Err("not_an_element")?
+pub fn op_max(context: &mut Context) -> StdResult<bool> {
+    binary_number_op(context, |a, b| a.max(b))
 }
 
-pub fn op_greaterthan(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
+/*
+    x min max OP_WITHIN: 1 if x is in [min, max)
+*/
+pub fn op_within(context: &mut Context) -> StdResult<bool> {
+    if !context.stack_has_enough_items(3) {
         Err("not_enough_items_in_stack")?;
     }
 
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
+    let max = context.stack_pop_as_number()?;
+    let min = context.stack_pop_as_number()?;
+    let x = context.stack_pop_as_number()?;
 
-    if let (Token::Element(left), Token::Element(right)) = (elem2, elem1) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let boolean = if left > right { ELEMENT_TRUE } else { ELEMENT_FALSE };
-        context.stack_push(Token::Element(boolean.to_vec()));
-
-        return Ok(true);
-    }
+    context.stack_push(Token::Element(element_value_by_result(min <= x && x < max)));
 
-    Err("not_an_element")?
+    Ok(true)
 }
 
-pub fn op_greaterthanorequal(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
+/*
+    Before BIP65/BIP112 activation OP_CHECKLOCKTIMEVERIFY and OP_CHECKSEQUENCEVERIFY were OP_NOP2 and OP_NOP3.
+*/
+/*
+    BIP65: fails unless the locktime of the spending transaction is at least the operand (left on the stack),
+    both block heights or both timestamps, and enabled by a non final sequence of the input.
+*/
+pub fn op_checklocktimeverify(context: &mut Context) -> StdResult<bool> {
+    if !context.flags().contains(ScriptFlags::CHECKLOCKTIMEVERIFY) {
+        return op_nop(context);
     }
 
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
-
-    if let (Token::Element(left), Token::Element(right)) = (elem2, elem1) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
-
-        let boolean = if left >= right { ELEMENT_TRUE } else { ELEMENT_FALSE };
-        context.stack_push(Token::Element(boolean.to_vec()));
+    let locktime = locktime_operand(context)?;
 
-        return Ok(true);
+    let satisfied = context.spending_tx().is_some_and(|tx| check_locktime(locktime, &tx));
+    if !satisfied {
+        Err("unsatisfied_locktime")?;
     }
 
-    Err("not_an_element")?
+    Ok(true)
 }
 
-pub fn op_min(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
-    }
+fn check_locktime(locktime: i64, tx: &SpendingTx) -> bool {
+    let tx_locktime = tx.locktime as i64;
 
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
+    let same_type = (locktime < LOCKTIME_THRESHOLD) == (tx_locktime < LOCKTIME_THRESHOLD);
 
-    if let (Token::Element(left), Token::Element(right)) = (elem1, elem2) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
+    same_type && locktime <= tx_locktime && tx.sequence != SEQUENCE_FINAL
+}
 
-        if left < right {
-            context.stack_push(Token::Element(left))
-        } else {
-            context.stack_push(Token::Element(right))
-        };
+/*
+    BIP112: fails unless the relative timelock (BIP68) of the input is at least the operand (left on the stack),
+    both in blocks or both in units of 512 seconds. An operand with the disable flag is a NOP.
+*/
+pub fn op_checksequenceverify(context: &mut Context) -> StdResult<bool> {
+    if !context.flags().contains(ScriptFlags::CHECKSEQUENCEVERIFY) {
+        return op_nop(context);
+    }
 
+    let sequence = locktime_operand(context)?;
+    if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
         return Ok(true);
     }
 
-    Err("not_an_element")?
-}
-
-pub fn op_max(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(2) {
-        Err("not_enough_items_in_stack")?;
+    let satisfied = context.spending_tx().is_some_and(|tx| check_sequence(sequence, &tx));
+    if !satisfied {
+        Err("unsatisfied_locktime")?;
     }
 
-    let elem1 = context.stack_pop_as_element()?;
-    let elem2 = context.stack_pop_as_element()?;
-
-    if let (Token::Element(left), Token::Element(right)) = (elem1, elem2) {
-        if left.len() > 4 || right.len() > 4 {
-            Err("input_length_too_long")?;
-        }
+    Ok(true)
+}
 
-        if left > right {
-            context.stack_push(Token::Element(left))
-        } else {
-            context.stack_push(Token::Element(right))
-        };
+fn check_sequence(sequence: i64, tx: &SpendingTx) -> bool {
+    let tx_sequence = tx.sequence as i64;
 
-        return Ok(true);
+    // relative timelocks are only enforced from version 2 (signed, as in Bitcoin Core)
+    if (tx.version as i32) < 2 || tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+        return false;
     }
 
-    Err("not_an_element")?
+    let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+    let tx_sequence_masked = tx_sequence & mask;
+    let sequence_masked = sequence & mask;
+
+    let same_type =
+        (sequence_masked < SEQUENCE_LOCKTIME_TYPE_FLAG) == (tx_sequence_masked < SEQUENCE_LOCKTIME_TYPE_FLAG);
+
+    same_type && sequence_masked <= tx_sequence_masked
 }
 
-pub fn op_within(context: &mut Context) -> StdResult<bool> {
-    if !context.stack_has_enough_items(3) {
+fn locktime_operand(context: &Context) -> StdResult<i64> {
+    if !context.stack_has_enough_items(1) {
         Err("not_enough_items_in_stack")?;
     }
 
-    let elem_min = context.stack_pop_as_element()?;
-    let elem_max = context.stack_pop_as_element()?;
-    let elem = context.stack_pop_as_element()?;
-
-    if let (Token::Element(min), Token::Element(max), Token::Element(e)) = (elem_min, elem_max, elem) {
-        if min.len() > 4 || max.len() > 4 || e.len() > 4 {
-            Err("input_length_too_long")?;
-        }
+    let operand = context.stack_top_as_number(LOCKTIME_MAX_NUM_SIZE)?.value();
+    if operand < 0 {
+        Err("negative_locktime")?;
+    }
 
-        let boolean = if e >= min && e < max {
-            ELEMENT_TRUE
-        } else {
-            ELEMENT_FALSE
-        };
-        context.stack_push(Token::Element(boolean.to_vec()));
+    Ok(operand)
+}
 
-        return Ok(true);
-    }
+// Signatures checked after it sign only the script that follows it
+pub fn op_codeseparator(context: &mut Context) -> StdResult<bool> {
+    context.execute_codeseparator();
 
-    Err("not_an_element")?
+    Ok(true)
 }
 
 pub fn not_implemented(_context: &mut Context) -> StdResult<bool> {
@@ -1,82 +1,112 @@
 use std::fmt::{Display, Formatter};
 
-use crate::std_lib::{
-    std_result::StdResult,
-    vector::{bytes_to_hex_string, hex_string_to_bytes},
+use crate::{
+    flags::script_flags::ScriptFlags,
+    std_lib::{
+        std_result::StdResult,
+        vector::{bytes_to_hex_string, hex_string_to_bytes},
+    },
 };
 
-use super::{context::Context, opcode::*, token::Token};
+use super::{
+    constants::{MAX_SCRIPT_ELEMENT_SIZE, MAX_SCRIPT_SIZE},
+    context::Context,
+    opcode::*,
+    token::{Token, TRUNCATED_REPRESENTATION},
+};
 
 #[derive(Debug, Clone)]
 pub struct ScriptLang(Vec<Token>);
 
 impl ScriptLang {
-    // TODO: refactor
+    /*
+        Parse `length` bytes of `data` from `offset`.
+        A push whose data goes beyond the end of the script is an error (see `from_raw` to keep it).
+    */
     pub fn deserialize(data: &[u8], length: u64, offset: usize) -> StdResult<Self> {
-        let mut tokens: Vec<Token> = vec![];
+        let end = match usize::try_from(length)
+            .ok()
+            .and_then(|length| offset.checked_add(length))
+        {
+            Some(end) if end <= data.len() => end,
+            _ => Err("script_out_of_bounds")?,
+        };
 
-        let mut i = offset as u64;
-        let max = length + offset as u64;
+        let script = ScriptLang::from_raw(&data[offset..end]);
+        if script.is_truncated() {
+            Err("truncated_push")?;
+        }
 
-        while i < max {
-            let first = data[i as usize];
-            if OP_ELEMENTS_RANGE.contains(&(first as OpCode)) {
-                i += 1;
+        Ok(script)
+    }
 
-                let start = i as usize;
-                let end = start + first as usize;
+    /*
+        As in consensus any sequence of bytes is a script: a push whose data goes beyond the end of the script
+        is kept (with the remaining bytes) as `Token::Truncated`, the evaluation fails only when it is reached.
+    */
+    pub fn from_raw(raw: &[u8]) -> Self {
+        let mut tokens: Vec<Token> = vec![];
+        let mut i: usize = 0;
 
-                let bytes = &data[start..end];
-                tokens.push(Token::Element(bytes.to_vec()));
+        while i < raw.len() {
+            let op_code = raw[i] as OpCode;
 
-                i += first as u64;
-            } else if first == OP_PUSHDATA1 as u8 {
-                // TODO: NOT TESTED
+            if !OP_ELEMENTS_RANGE.contains(&op_code) && !matches!(op_code, OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4) {
+                tokens.push(Token::Command(op_code));
                 i += 1;
-                let len = data[i as usize];
-
-                i += 1;
-                let start = i as usize;
-                let end = start + len as usize;
-
-                let bytes = &data[start..end];
-                tokens.push(Token::Element(bytes.to_vec()));
-
-                i += len as u64;
-            } else if first == OP_PUSHDATA2 as u8 {
-                // TODO: NOT TESTED
-                let len_bytes = &data[(i + 1) as usize..(i + 3) as usize];
-                let len = u16::from_le_bytes([len_bytes[0], len_bytes[1]]);
+                continue;
+            }
 
-                i += 2;
+            match ScriptLang::read_push(raw, i) {
+                Some((bytes, next)) if op_code == push_opcode(bytes.len()) => {
+                    tokens.push(Token::Element(bytes.to_vec()));
+                    i = next;
+                }
+                Some((bytes, next)) => {
+                    tokens.push(Token::Push(op_code, bytes.to_vec()));
+                    i = next;
+                }
+                None => {
+                    tokens.push(Token::Truncated(raw[i..].to_vec()));
+                    break;
+                }
+            }
+        }
 
-                let start = (i + 1) as usize;
-                let end = start + len as usize;
+        ScriptLang(tokens)
+    }
 
-                let bytes = &data[start..end];
-                tokens.push(Token::Element(bytes.to_vec()));
+    /*
+        Data of the push starting at `i`: OP_PUSHBYTES_N (N bytes), OP_PUSHDATA1/2/4 (length in the next 1, 2 or 4 bytes, little endian).
+        Returns the data and the position of the next token, `None` if the script ends before.
+    */
+    pub(crate) fn read_push(raw: &[u8], i: usize) -> Option<(&[u8], usize)> {
+        let op_code = raw[i] as OpCode;
 
-                i += 1 + len as u64;
-            } else if first == OP_PUSHDATA4 as u8 {
-                // TODO: NOT TESTED
-                let len_bytes = &data[(i + 1) as usize..(i + 5) as usize];
-                let len = u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]);
+        let length_size = match op_code {
+            OP_PUSHDATA1 => 1,
+            OP_PUSHDATA2 => 2,
+            OP_PUSHDATA4 => 4,
+            _ => 0,
+        };
 
-                i += 4;
+        let start = i + 1 + length_size;
+        let length = match raw.get(i + 1..start)? {
+            [] => op_code,
+            length_bytes => length_bytes
+                .iter()
+                .rev()
+                .fold(0usize, |length, byte| (length << 8) | *byte as usize),
+        };
 
-                let start = (i + 1) as usize;
-                let end = start + len as usize;
+        let end = start.checked_add(length)?;
+        raw.get(start..end).map(|bytes| (bytes, end))
+    }
 
-                let bytes = &data[start..end];
-                tokens.push(Token::Element(bytes.to_vec()));
+    pub fn is_truncated(&self) -> bool {
+        let Self(tokens) = self;
 
-                i += 1 + len as u64;
-            } else {
-                tokens.push(Token::Command(first as OpCode));
-                i += 1;
-            }
-        }
-        Ok(ScriptLang(tokens))
+        matches!(tokens.last(), Some(Token::Truncated(_)))
     }
 
     pub fn from_tokens(tokens: Vec<Token>) -> Self {
@@ -122,13 +152,16 @@ impl ScriptLang {
         let mut repr = String::new();
         for item in items {
             match item {
-                Token::Element(bytes) => {
+                Token::Element(bytes) | Token::Push(_, bytes) => {
                     let e = bytes_to_hex_string(bytes);
                     repr.push_str(&e);
                 }
                 Token::Command(op_code) => {
                     repr.push_str((*OP_TO_FN)[*op_code].name);
                 }
+                Token::Truncated(_) => {
+                    repr.push_str(TRUNCATED_REPRESENTATION);
+                }
             }
             repr.push(' ');
         }
@@ -144,32 +177,72 @@ impl ScriptLang {
     }
 
     pub fn evaluate<'a>(&'a self, context: &'a mut Context) -> StdResult<bool> {
+        self.check_size()?;
+
         while !context.tokens_are_over() {
-            let executing = context.executing();
+            ScriptLang::evaluate_next(context)?;
+        }
+
+        Ok(context.is_valid())
+    }
 
-            let token = context.next_token();
-            log::debug!("Token (exec: {}): {:}", executing, token);
+    pub fn check_size(&self) -> StdResult<()> {
+        if self.serialize()?.len() > MAX_SCRIPT_SIZE {
+            Err("script_size_exceeded")?;
+        }
 
-            if !executing && !token.is_op_branch_condition() {
-                continue;
+        Ok(())
+    }
+
+    /*
+        Evaluate the next token of the context (skipped in a branch not executed, but still checked against
+        the consensus limits). Returns whether it was executed.
+    */
+    pub fn evaluate_next(context: &mut Context) -> StdResult<bool> {
+        let executing = context.executing();
+
+        let token = context.next_token().clone();
+        log::debug!("Token (exec: {}): {:}", executing, token);
+
+        // consensus limits apply to not executed branches too
+        match &token {
+            Token::Truncated(_) => Err("truncated_push")?,
+            Token::Element(bytes) | Token::Push(_, bytes) if bytes.len() > MAX_SCRIPT_ELEMENT_SIZE => {
+                Err("element_size_exceeded")?
             }
+            Token::Command(op_code) if is_disabled(*op_code) => Err("deprecated_opcode")?,
+            Token::Command(op_code) if *op_code > OP_16 => context.add_op_count(1)?,
+            _ => {}
+        }
 
-            match token {
-                Token::Element(bytes) => {
-                    let e = Token::Element(bytes.to_vec());
-                    context.stack_push(e);
-                }
-                Token::Command(op_code) => {
-                    if *op_code > OPS_LENGTH {
-                        Err("invalid_opcode")?;
-                    }
+        if !executing && !token.is_op_branch_condition() {
+            return Ok(false);
+        }
 
-                    ((*OP_TO_FN)[*op_code].exec)(context)?;
+        match token {
+            Token::Element(bytes) => {
+                check_minimal_push(context, push_opcode(bytes.len()), &bytes)?;
+                context.stack_push(Token::Element(bytes));
+            }
+            Token::Push(op_code, bytes) => {
+                check_minimal_push(context, op_code, &bytes)?;
+                context.stack_push(Token::Element(bytes));
+            }
+            Token::Command(op_code) => {
+                if op_code > OPS_LENGTH {
+                    Err("invalid_opcode")?;
                 }
+
+                ((*OP_TO_FN)[op_code].exec)(context)?;
             }
+            Token::Truncated(_) => unreachable!("truncated push checked before execution"),
         }
 
-        Ok(context.is_valid())
+        if context.stacks_size() > MAX_STACK_SIZE {
+            Err("stack_size_exceeded")?;
+        }
+
+        Ok(true)
     }
 
     pub fn combine(left: Self, right: Self) -> Self {
@@ -179,32 +252,75 @@ impl ScriptLang {
         ScriptLang([left_items, right_items].concat())
     }
 
+    /*
+        Only data pushes and OP_0 ... OP_16 (required for the ScriptSig spending a P2SH output, BIP16).
+    */
+    pub fn is_push_only(&self) -> bool {
+        let Self(tokens) = self;
+
+        tokens.iter().all(|token| match token {
+            Token::Element(_) | Token::Push(_, _) => true,
+            Token::Command(op_code) => *op_code <= OP_16,
+            Token::Truncated(_) => false,
+        })
+    }
+
+    /*
+        OP_HASH160 <20 bytes script hash> OP_EQUAL
+    */
+    pub fn is_pay_to_script_hash(&self) -> bool {
+        let Self(tokens) = self;
+
+        matches!(
+            tokens.as_slice(),
+            [Token::Command(OP_HASH160), Token::Element(hash), Token::Command(OP_EQUAL)] if hash.len() == 20
+        )
+    }
+
+    /*
+        BIP141: a version (OP_0, OP_1 ... OP_16) and a single push of 2 to 40 bytes, the program.
+        Returns the version (0 to 16) and the program.
+    */
+    pub fn witness_program(&self) -> Option<(u8, Vec<u8>)> {
+        let raw = self.serialize().ok()?;
+
+        if raw.len() < 4 || raw.len() > 42 || raw[1] as usize != raw.len() - 2 {
+            return None;
+        }
+
+        let version = match raw[0] as OpCode {
+            OP_0 => 0,
+            op_code @ OP_1..=OP_16 => (op_code - OP_1 + 1) as u8,
+            _ => return None,
+        };
+
+        Some((version, raw[2..].to_vec()))
+    }
+
     fn raw_serialize(tokens: &[Token]) -> StdResult<Vec<u8>> {
         let mut raw: Vec<u8> = vec![];
 
         for token in tokens {
             match token {
                 Token::Element(bytes) => {
-                    let len = bytes.len();
-                    if len <= 75 {
-                        raw.push(len as u8);
-                    } else if len <= 0xFF {
-                        raw.push(OP_PUSHDATA1 as u8);
-                        raw.push(len as u8);
-                    } else if len <= 0x208 {
-                        raw.push(OP_PUSHDATA2 as u8);
-                        raw.extend(len.to_le_bytes().iter());
-                    } else if len < 0x100000000 {
-                        Err("push_data_4_is_deprecated")?;
-                    } else {
+                    if bytes.len() > 0xFFFFFFFF {
                         Err("element_too_long")?;
                     }
 
+                    // OP_PUSHDATA4 is not valid in consensus (MAX_SCRIPT_ELEMENT_SIZE) but still serializable
+                    raw.extend(push_prefix(push_opcode(bytes.len()), bytes.len()));
+                    raw.extend(bytes);
+                }
+                Token::Push(op_code, bytes) => {
+                    raw.extend(push_prefix(*op_code, bytes.len()));
                     raw.extend(bytes);
                 }
                 Token::Command(op_code) => {
                     raw.push(*op_code as u8);
                 }
+                Token::Truncated(bytes) => {
+                    raw.extend(bytes);
+                }
             }
         }
 
@@ -212,6 +328,46 @@ impl ScriptLang {
     }
 }
 
+// The shortest push of `length` bytes (OP_0 for none)
+fn push_opcode(length: usize) -> OpCode {
+    match length {
+        0..=0x4B => length as OpCode,
+        0x4C..=0xFF => OP_PUSHDATA1,
+        0x100..=0xFFFF => OP_PUSHDATA2,
+        _ => OP_PUSHDATA4,
+    }
+}
+
+// Opcode and length of a push of `length` bytes with `op_code`
+fn push_prefix(op_code: OpCode, length: usize) -> Vec<u8> {
+    match op_code {
+        OP_PUSHDATA1 => vec![OP_PUSHDATA1 as u8, length as u8],
+        OP_PUSHDATA2 => [vec![OP_PUSHDATA2 as u8], (length as u16).to_le_bytes().to_vec()].concat(),
+        OP_PUSHDATA4 => [vec![OP_PUSHDATA4 as u8], (length as u32).to_le_bytes().to_vec()].concat(),
+        _ => vec![op_code as u8],
+    }
+}
+
+/*
+    MINIMALDATA: a push must use the shortest encoding (`CheckMinimalPush` of Bitcoin Core),
+    OP_0, OP_1NEGATE and OP_1 ... OP_16 included: [0x05] must be pushed by OP_5, not by 0x01 0x05.
+*/
+pub fn is_minimal_push(op_code: OpCode, bytes: &[u8]) -> bool {
+    match bytes {
+        [] => op_code == OP_0,
+        [1..=16] | [0x81] => false,
+        _ => op_code == push_opcode(bytes.len()),
+    }
+}
+
+fn check_minimal_push(context: &Context, op_code: OpCode, bytes: &[u8]) -> StdResult<()> {
+    if context.flags().contains(ScriptFlags::MINIMALDATA) && !is_minimal_push(op_code, bytes) {
+        Err("push_not_minimal")?;
+    }
+
+    Ok(())
+}
+
 impl Display for ScriptLang {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:}", self.representation())
@@ -221,15 +377,21 @@ impl Display for ScriptLang {
 #[cfg(test)]
 mod script_test {
     use crate::{
-        flags::{network::Network, sighash::SigHash},
+        flags::{network::Network, script_flags::ScriptFlags, sighash::SigHash},
         hashing::hash160::hash160,
-        scripting::{opcode::*, standard, token::*},
+        scripting::{
+            context::{SigVersion, SpendingTx},
+            opcode::*,
+            standard,
+            token::*,
+        },
         std_lib::varint::{decode, encode},
         std_lib::{integer_extended::IntegerExtended, vector::hex_string_to_bytes},
         wallet::key::new,
     };
 
     use rug::{integer::Order, Integer};
+    use std::collections::HashMap;
 
     use super::*;
 
@@ -293,6 +455,156 @@ mod script_test {
         assert_eq!(tokens[2], Token::Command(OP_CHECKSIG));
     }
 
+    macro_rules! deserialize_push {
+        ($raw:literal, $expected:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let raw = hex_string_to_bytes($raw).unwrap();
+                let script = ScriptLang::deserialize(&raw, raw.len() as u64, 0).unwrap();
+
+                let ScriptLang(tokens) = script.clone();
+                assert_eq!(
+                    tokens,
+                    vec![
+                        Token::Element(hex_string_to_bytes($expected).unwrap()),
+                        Token::Command(OP_1)
+                    ]
+                );
+                assert!(!script.is_truncated());
+            }
+        };
+    }
+
+    deserialize_push!("0051", "", deserialize_op_0);
+    deserialize_push!("0301020351", "010203", deserialize_push_bytes);
+    deserialize_push!("4c0301020351", "010203", deserialize_push_data_1);
+    deserialize_push!("4d030001020351", "010203", deserialize_push_data_2);
+    deserialize_push!("4e0300000001020351", "010203", deserialize_push_data_4);
+    deserialize_push!("4c0051", "", deserialize_push_data_1_empty);
+
+    macro_rules! deserialize_truncated {
+        ($raw:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let raw = hex_string_to_bytes($raw).unwrap();
+
+                let err = ScriptLang::deserialize(&raw, raw.len() as u64, 0).expect_err("Err");
+                assert_eq!(err.to_string(), "truncated_push");
+
+                // consensus: the script is still valid until the truncated push is reached
+                let script = ScriptLang::from_raw(&raw);
+                let ScriptLang(tokens) = script.clone();
+                assert_eq!(tokens[0], Token::Command(OP_1));
+                assert_eq!(tokens[1], Token::Truncated(raw[1..].to_vec()));
+                assert!(script.is_truncated());
+                assert_eq!(script.serialize().unwrap(), raw);
+            }
+        };
+    }
+
+    deserialize_truncated!("5105010203", deserialize_truncated_push_bytes);
+    deserialize_truncated!("514c", deserialize_truncated_push_data_1_length);
+    deserialize_truncated!("514c050102", deserialize_truncated_push_data_1);
+    deserialize_truncated!("514d03", deserialize_truncated_push_data_2_length);
+    deserialize_truncated!("514d030001", deserialize_truncated_push_data_2);
+    deserialize_truncated!("514e030000", deserialize_truncated_push_data_4_length);
+    deserialize_truncated!("514e0300000001", deserialize_truncated_push_data_4);
+    deserialize_truncated!("514effffffff01", deserialize_truncated_push_data_4_max_length);
+
+    #[test]
+    fn deserialize_out_of_bounds() {
+        let raw = hex_string_to_bytes("5151").unwrap();
+
+        let err = ScriptLang::deserialize(&raw, 3, 0).expect_err("Err");
+        assert_eq!(err.to_string(), "script_out_of_bounds");
+
+        let err = ScriptLang::deserialize(&raw, u64::MAX, 1).expect_err("Err");
+        assert_eq!(err.to_string(), "script_out_of_bounds");
+
+        let ScriptLang(tokens) = ScriptLang::deserialize(&raw, 1, 1).unwrap();
+        assert_eq!(tokens, vec![Token::Command(OP_1)]);
+    }
+
+    #[test]
+    fn truncated_representation() {
+        let script = ScriptLang::from_raw(&hex_string_to_bytes("514c05").unwrap());
+
+        assert_eq!(script.representation(), "OP_1 [error]");
+        assert!(!script.is_push_only());
+    }
+
+    #[test]
+    fn evaluate_truncated() {
+        let script = ScriptLang::from_raw(&hex_string_to_bytes("516351684c05").unwrap());
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+
+        assert_eq!(
+            script.evaluate(&mut context).expect_err("Err").to_string(),
+            "truncated_push"
+        );
+    }
+
+    #[test]
+    fn evaluate_truncated_in_not_executed_branch() {
+        let script = ScriptLang::from_raw(&hex_string_to_bytes("0063514c05").unwrap());
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+
+        assert_eq!(
+            script.evaluate(&mut context).expect_err("Err").to_string(),
+            "truncated_push"
+        );
+    }
+
+    #[test]
+    fn push_only() {
+        let script = ScriptLang::from_tokens(vec![
+            Token::Command(OP_0),
+            Token::Element(vec![0x01, 0x02]),
+            Token::Command(OP_16),
+        ]);
+        assert!(script.is_push_only());
+
+        let script = ScriptLang::from_tokens(vec![Token::Element(vec![0x01]), Token::Command(OP_DUP)]);
+        assert!(!script.is_push_only());
+    }
+
+    #[test]
+    fn pay_to_script_hash() {
+        let script = ScriptLang::from_tokens(vec![
+            Token::Command(OP_HASH160),
+            Token::Element(hex_string_to_bytes("081fbb6ec9d83104367eb1a6a59e2a92417d7929").unwrap()),
+            Token::Command(OP_EQUAL),
+        ]);
+        assert!(script.is_pay_to_script_hash());
+
+        let script = ScriptLang::from_tokens(vec![
+            Token::Command(OP_HASH160),
+            Token::Element(hex_string_to_bytes("081fbb6ec9d83104367eb1a6a59e2a92417d79").unwrap()),
+            Token::Command(OP_EQUAL),
+        ]);
+        assert!(!script.is_pay_to_script_hash());
+    }
+
+    #[test]
+    fn witness_program() {
+        let program = hex_string_to_bytes("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+
+        let script = ScriptLang::from_raw(&[[0x00, 0x14].as_slice(), &program].concat());
+        assert_eq!(script.witness_program(), Some((0, program.clone())));
+
+        let script = ScriptLang::from_raw(&[[0x60, 0x14].as_slice(), &program].concat());
+        assert_eq!(script.witness_program(), Some((16, program.clone())));
+
+        // not a version opcode, a push not of the whole program, too short
+        for raw in [
+            [[0x4F, 0x14].as_slice(), &program].concat(),
+            [[0x00, 0x13].as_slice(), &program].concat(),
+            vec![0x51, 0x01, 0x01],
+        ] {
+            assert_eq!(ScriptLang::from_raw(&raw).witness_program(), None);
+        }
+    }
+
     #[test]
     fn evaluate_odd_number() {
         let script = ScriptLang::from_representation("F").unwrap();
@@ -321,6 +633,64 @@ mod script_test {
         assert!(script.evaluate(&mut context).unwrap());
     }
 
+    macro_rules! evaluate_checksig_with_flags {
+        ($signature:literal, $flags:expr, $expected:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let z: Integer = Integer::from_hex_str("7C076FF316692A3D7EB3C3BB0F8B1488CF72E1AFCD929E29307032997A838A3D");
+                let pubkey = hex_string_to_bytes("04887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34").unwrap();
+                let signature = hex_string_to_bytes($signature).unwrap();
+
+                let script = ScriptLang::from_tokens(vec![
+                    Token::Element(signature),
+                    Token::Element(pubkey),
+                    Token::Command(OP_CHECKSIG),
+                ]);
+                let mut context = Context::new_with_flags(script.tokens(), z, $flags);
+
+                let expected: Result<bool, &str> = $expected;
+                match script.evaluate(&mut context) {
+                    Ok(valid) => assert_eq!(Ok(valid), expected),
+                    Err(e) => assert_eq!(Err(e.to_string().as_str()), expected),
+                }
+            }
+        };
+    }
+
+    // strict DER, high S
+    evaluate_checksig_with_flags!(
+        "3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601",
+        ScriptFlags::DERSIG,
+        Ok(true),
+        evaluate_checksig_dersig
+    );
+    evaluate_checksig_with_flags!(
+        "3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601",
+        ScriptFlags::DERSIG | ScriptFlags::LOW_S,
+        Err("signature_high_s"),
+        evaluate_checksig_high_s
+    );
+    // r with a superfluous leading zero
+    evaluate_checksig_with_flags!(
+        "304602210000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601",
+        ScriptFlags::NONE,
+        Ok(true),
+        evaluate_checksig_non_strict_der_before_bip66
+    );
+    evaluate_checksig_with_flags!(
+        "304602210000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601",
+        ScriptFlags::DERSIG,
+        Err("signature_not_strict_der"),
+        evaluate_checksig_non_strict_der
+    );
+    // an empty signature makes OP_CHECKSIG fail, not the script
+    evaluate_checksig_with_flags!(
+        "",
+        ScriptFlags::DERSIG | ScriptFlags::LOW_S,
+        Ok(false),
+        evaluate_checksig_empty_signature
+    );
+
     #[test]
     fn evaluate_checkmultisig_1_to_1() {
         let z: Integer = Integer::from_hex_str("6CD7818C2ED773A1B19348FEACA92AD664B45CD0");
@@ -343,6 +713,571 @@ mod script_test {
         assert!(script.evaluate(&mut context).unwrap());
     }
 
+    #[test]
+    fn evaluate_checkmultisig_null_dummy() {
+        let z: Integer = Integer::from_hex_str("6CD7818C2ED773A1B19348FEACA92AD664B45CD0");
+        let pubkey = hex_string_to_bytes("02a130c1e1ffa137cf50824ece45fb648ce88cb5570870dc10cfdc8c5f30946861").unwrap();
+        let signature = hex_string_to_bytes("3045022100bebe0c00a59a6c01231790fe8034508c06904289de0e3ddccb897d9cf5794b0202205e1ff2d6f060524bd7da2a598f5205759ef0911a695407999965527ba9629a2501").unwrap();
+
+        // zero, but not the empty byte vector, and not zero: any value without NULLDUMMY
+        for dummy in [vec![0x00], vec![0x01]] {
+            let script_sig = ScriptLang::from_tokens(vec![Token::Element(dummy), Token::Element(signature.clone())]);
+            let script_multisig = ScriptLang::from_tokens(vec![
+                Token::Command(OP_1),
+                Token::Element(pubkey.clone()),
+                Token::Command(OP_1),
+                Token::Command(OP_CHECKMULTISIG),
+            ]);
+            let script = ScriptLang::combine(script_sig, script_multisig);
+
+            let mut context = Context::new(script.tokens(), z.clone());
+            assert!(script.evaluate(&mut context).unwrap());
+
+            let mut context = Context::new_with_flags(script.tokens(), z.clone(), ScriptFlags::NULLDUMMY);
+            assert_eq!(
+                "multisig_dummy_not_null",
+                script.evaluate(&mut context).expect_err("Err").to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_checkmultisig_op_0_dummy() {
+        let z: Integer = Integer::from_hex_str("6CD7818C2ED773A1B19348FEACA92AD664B45CD0");
+        let pubkey = hex_string_to_bytes("02a130c1e1ffa137cf50824ece45fb648ce88cb5570870dc10cfdc8c5f30946861").unwrap();
+        let signature = hex_string_to_bytes("3045022100bebe0c00a59a6c01231790fe8034508c06904289de0e3ddccb897d9cf5794b0202205e1ff2d6f060524bd7da2a598f5205759ef0911a695407999965527ba9629a2501").unwrap();
+
+        let script_sig = ScriptLang::from_tokens(vec![Token::Command(OP_0), Token::Element(signature)]);
+        let script_multisig = ScriptLang::from_tokens(vec![
+            Token::Command(OP_1),
+            Token::Element(pubkey),
+            Token::Command(OP_1),
+            Token::Command(OP_CHECKMULTISIG),
+        ]);
+        let script = ScriptLang::combine(script_sig, script_multisig);
+
+        let mut context = Context::new_with_flags(script.tokens(), z, ScriptFlags::NULLDUMMY);
+        assert!(script.evaluate(&mut context).unwrap());
+    }
+
+    // 2-of-3 with the signature of the same key twice: a key matches one signature only
+    #[test]
+    fn evaluate_checkmultisig_same_signature_twice() {
+        let z: Integer = Integer::from_hex_str("6CD7818C2ED773A1B19348FEACA92AD664B45CD0");
+        let pubkey = hex_string_to_bytes("02a130c1e1ffa137cf50824ece45fb648ce88cb5570870dc10cfdc8c5f30946861").unwrap();
+        let signature = hex_string_to_bytes("3045022100bebe0c00a59a6c01231790fe8034508c06904289de0e3ddccb897d9cf5794b0202205e1ff2d6f060524bd7da2a598f5205759ef0911a695407999965527ba9629a2501").unwrap();
+        // 1 * G, 2 * G
+        let other_1 =
+            hex_string_to_bytes("0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798").unwrap();
+        let other_2 =
+            hex_string_to_bytes("02C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5").unwrap();
+
+        let script = ScriptLang::from_tokens(vec![
+            Token::Command(OP_0),
+            Token::Element(signature.clone()),
+            Token::Element(signature),
+            Token::Command(OP_2),
+            Token::Element(pubkey),
+            Token::Element(other_1),
+            Token::Element(other_2),
+            Token::Command(OP_3),
+            Token::Command(OP_CHECKMULTISIG),
+        ]);
+
+        let mut context = Context::new(script.tokens(), z);
+        assert!(!script.evaluate(&mut context).unwrap());
+    }
+
+    // The first compared signature does not match, so the other one (not DER) is never checked
+    #[test]
+    fn evaluate_checkmultisig_signature_encoding_checked_when_compared() {
+        let z: Integer = Integer::from_hex_str("6CD7818C2ED773A1B19348FEACA92AD664B45CD0");
+        let pubkey = hex_string_to_bytes("02a130c1e1ffa137cf50824ece45fb648ce88cb5570870dc10cfdc8c5f30946861").unwrap();
+        let signature = hex_string_to_bytes("3045022100bebe0c00a59a6c01231790fe8034508c06904289de0e3ddccb897d9cf5794b0202205e1ff2d6f060524bd7da2a598f5205759ef0911a695407999965527ba9629a2501").unwrap();
+        let other = hex_string_to_bytes("0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798").unwrap();
+
+        let script = ScriptLang::from_tokens(vec![
+            Token::Command(OP_0),
+            Token::Element(vec![0x30, 0x01, 0x01]),
+            Token::Element(signature),
+            Token::Command(OP_2),
+            Token::Element(pubkey),
+            Token::Element(other),
+            Token::Command(OP_2),
+            Token::Command(OP_CHECKMULTISIG),
+        ]);
+
+        let mut context = Context::new_with_flags(script.tokens(), z, ScriptFlags::DERSIG);
+        assert!(!script.evaluate(&mut context).unwrap());
+    }
+
+    // Any bytes can be pushed as a public key: the check fails, the interpreter does not panic
+    #[test]
+    fn evaluate_checksig_invalid_public_key() {
+        let z: Integer = Integer::from_hex_str("7C076FF316692A3D7EB3C3BB0F8B1488CF72E1AFCD929E29307032997A838A3D");
+        let signature = hex_string_to_bytes("3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601").unwrap();
+
+        for pubkey in [
+            vec![],
+            vec![0x02],
+            vec![0x05; 33],
+            [vec![0x02], vec![0xFF; 32]].concat(),
+        ] {
+            let script = ScriptLang::from_tokens(vec![
+                Token::Element(signature.clone()),
+                Token::Element(pubkey),
+                Token::Command(OP_CHECKSIG),
+            ]);
+            let mut context = Context::new(script.tokens(), z.clone());
+
+            assert!(!script.evaluate(&mut context).unwrap());
+        }
+    }
+
+    #[test]
+    fn evaluate_checksigverify() {
+        let z: Integer = Integer::from_hex_str("7C076FF316692A3D7EB3C3BB0F8B1488CF72E1AFCD929E29307032997A838A3D");
+        let pubkey = hex_string_to_bytes("04887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34").unwrap();
+        let signature = hex_string_to_bytes("3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601").unwrap();
+
+        let script = ScriptLang::from_tokens(vec![
+            Token::Element(signature),
+            Token::Element(pubkey),
+            Token::Command(OP_CHECKSIGVERIFY),
+            Token::Command(OP_1),
+        ]);
+
+        let mut context = Context::new(script.tokens(), z);
+        assert!(script.evaluate(&mut context).unwrap());
+        assert_eq!(context.stack_len(), 1);
+
+        // another message
+        let mut context = Context::new(script.tokens(), Integer::from(1));
+        assert_eq!(
+            "exit_by_failed_checksigverify",
+            script.evaluate(&mut context).expect_err("Err").to_string()
+        );
+    }
+
+    #[test]
+    fn evaluate_checkmultisigverify() {
+        let z: Integer = Integer::from_hex_str("6CD7818C2ED773A1B19348FEACA92AD664B45CD0");
+        let pubkey = hex_string_to_bytes("02a130c1e1ffa137cf50824ece45fb648ce88cb5570870dc10cfdc8c5f30946861").unwrap();
+        let signature = hex_string_to_bytes("3045022100bebe0c00a59a6c01231790fe8034508c06904289de0e3ddccb897d9cf5794b0202205e1ff2d6f060524bd7da2a598f5205759ef0911a695407999965527ba9629a2501").unwrap();
+
+        let script = ScriptLang::from_tokens(vec![
+            Token::Command(OP_0),
+            Token::Element(signature),
+            Token::Command(OP_1),
+            Token::Element(pubkey),
+            Token::Command(OP_1),
+            Token::Command(OP_CHECKMULTISIGVERIFY),
+            Token::Command(OP_1),
+        ]);
+
+        let mut context = Context::new(script.tokens(), z);
+        assert!(script.evaluate(&mut context).unwrap());
+        assert_eq!(context.stack_len(), 1);
+
+        // another message
+        let mut context = Context::new(script.tokens(), Integer::from(1));
+        assert_eq!(
+            "exit_by_failed_checkmultisigverify",
+            script.evaluate(&mut context).expect_err("Err").to_string()
+        );
+    }
+
+    macro_rules! evaluate_with_flags {
+        ($script:literal, $flags:expr, $expected:expr, $f:ident) => {
+            evaluate_with_flags!($script, $flags, SigVersion::Base, $expected, $f);
+        };
+        ($script:literal, $flags:expr, $sig_version:expr, $expected:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let script = ScriptLang::from_representation($script).unwrap();
+                let mut context = Context::new_with_flags(script.tokens(), Integer::from(0), $flags);
+                context.set_sig_version($sig_version);
+
+                let expected: Result<bool, &str> = $expected;
+                match script.evaluate(&mut context) {
+                    Ok(valid) => assert_eq!(Ok(valid), expected),
+                    Err(e) => assert_eq!(Err(e.to_string().as_str()), expected),
+                }
+            }
+        };
+    }
+
+    // numbers
+    evaluate_with_flags!("0100 OP_1ADD", ScriptFlags::NONE, Ok(true), evaluate_not_minimal_number);
+    evaluate_with_flags!(
+        "0100 OP_1ADD",
+        ScriptFlags::MINIMALDATA,
+        Err("script_num_not_minimal"),
+        evaluate_not_minimal_number_with_minimaldata
+    );
+
+    // pushes
+    evaluate_with_flags!("05 OP_DROP OP_1", ScriptFlags::NONE, Ok(true), evaluate_push_of_5);
+    evaluate_with_flags!(
+        "05 OP_DROP OP_1",
+        ScriptFlags::MINIMALDATA,
+        Err("push_not_minimal"),
+        evaluate_push_of_5_with_minimaldata
+    );
+    evaluate_with_flags!(
+        "81 OP_DROP OP_1",
+        ScriptFlags::MINIMALDATA,
+        Err("push_not_minimal"),
+        evaluate_push_of_1negate_with_minimaldata
+    );
+    evaluate_with_flags!(
+        "11 OP_DROP OP_1",
+        ScriptFlags::MINIMALDATA,
+        Ok(true),
+        evaluate_push_of_17_with_minimaldata
+    );
+    evaluate_with_flags!(
+        "OP_0 OP_IF 05 OP_ENDIF OP_1",
+        ScriptFlags::MINIMALDATA,
+        Ok(true),
+        evaluate_push_not_minimal_in_not_executed_branch
+    );
+
+    macro_rules! evaluate_raw_with_flags {
+        ($raw:literal, $flags:expr, $expected:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let script = ScriptLang::from_raw(&hex_string_to_bytes($raw).unwrap());
+                let mut context = Context::new_with_flags(script.tokens(), Integer::from(0), $flags);
+
+                let expected: Result<bool, &str> = $expected;
+                match script.evaluate(&mut context) {
+                    Ok(valid) => assert_eq!(Ok(valid), expected),
+                    Err(e) => assert_eq!(Err(e.to_string().as_str()), expected),
+                }
+            }
+        };
+    }
+
+    // OP_PUSHDATA1 0x01 0xAA OP_DROP OP_1
+    evaluate_raw_with_flags!("4C01AA7551", ScriptFlags::NONE, Ok(true), evaluate_pushdata1_of_1_byte);
+    evaluate_raw_with_flags!(
+        "4C01AA7551",
+        ScriptFlags::MINIMALDATA,
+        Err("push_not_minimal"),
+        evaluate_pushdata1_of_1_byte_with_minimaldata
+    );
+    // OP_PUSHDATA2 0x0000 (empty) OP_DROP OP_1
+    evaluate_raw_with_flags!(
+        "4D00007551",
+        ScriptFlags::MINIMALDATA,
+        Err("push_not_minimal"),
+        evaluate_pushdata2_empty_with_minimaldata
+    );
+    evaluate_raw_with_flags!(
+        "01AA7551",
+        ScriptFlags::MINIMALDATA,
+        Ok(true),
+        evaluate_push_of_1_byte_with_minimaldata
+    );
+
+    #[test]
+    fn not_minimal_push_serialized_as_it_was() {
+        let raw = hex_string_to_bytes("4C01AA4D0000010575").unwrap();
+
+        let script = ScriptLang::from_raw(&raw);
+
+        assert_eq!(
+            script.tokens(),
+            vec![
+                Token::Push(OP_PUSHDATA1, vec![0xAA]),
+                Token::Push(OP_PUSHDATA2, vec![]),
+                Token::Element(vec![0x05]),
+                Token::Command(OP_DROP),
+            ]
+        );
+        assert_eq!(script.serialize().unwrap(), raw);
+    }
+
+    #[test]
+    fn minimal_push() {
+        assert!(is_minimal_push(OP_0, &[]));
+        assert!(!is_minimal_push(OP_PUSHDATA1, &[]));
+        assert!(!is_minimal_push(0x01, &[0x10]));
+        assert!(is_minimal_push(0x01, &[0x11]));
+        assert!(is_minimal_push(0x4B, &[0xAA; 75]));
+        assert!(!is_minimal_push(OP_PUSHDATA1, &[0xAA; 75]));
+        assert!(is_minimal_push(OP_PUSHDATA1, &[0xAA; 76]));
+        assert!(!is_minimal_push(OP_PUSHDATA2, &[0xAA; 255]));
+        assert!(is_minimal_push(OP_PUSHDATA2, &[0xAA; 256]));
+    }
+    evaluate_with_flags!(
+        "80 OP_0NOTEQUAL OP_NOT",
+        ScriptFlags::NONE,
+        Ok(true),
+        evaluate_negative_zero
+    );
+    evaluate_with_flags!(
+        "80 OP_VERIFY OP_1",
+        ScriptFlags::NONE,
+        Err("exit_by_failed_verify"),
+        evaluate_negative_zero_is_false
+    );
+    evaluate_with_flags!(
+        "OP_1 OP_1 OP_SUB OP_0 OP_EQUAL",
+        ScriptFlags::NONE,
+        Ok(true),
+        evaluate_zero_result_is_empty
+    );
+    evaluate_with_flags!(
+        "FFFFFF7F OP_1ADD 0000008000 OP_EQUAL",
+        ScriptFlags::NONE,
+        Ok(true),
+        evaluate_result_bigger_than_4_bytes
+    );
+    evaluate_with_flags!(
+        "FFFFFF7F OP_1ADD OP_1ADD",
+        ScriptFlags::NONE,
+        Err("input_length_too_long"),
+        evaluate_result_bigger_than_4_bytes_as_operand
+    );
+    evaluate_with_flags!(
+        "FF OP_8 OP_LESSTHAN",
+        ScriptFlags::NONE,
+        Ok(true),
+        evaluate_lessthan_negative
+    );
+
+    // minimal if
+    evaluate_with_flags!(
+        "02 OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF",
+        ScriptFlags::NONE,
+        Ok(true),
+        evaluate_if_not_minimal
+    );
+    // MINIMALIF applies to witness scripts only
+    evaluate_with_flags!(
+        "02 OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF",
+        ScriptFlags::MINIMALIF,
+        Ok(true),
+        evaluate_if_not_minimal_in_legacy_script_with_minimalif
+    );
+    evaluate_with_flags!(
+        "02 OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF",
+        ScriptFlags::MINIMALIF,
+        SigVersion::WitnessV0,
+        Err("minimal_if"),
+        evaluate_if_not_minimal_with_minimalif
+    );
+    evaluate_with_flags!(
+        "01 OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF",
+        ScriptFlags::MINIMALIF,
+        SigVersion::WitnessV0,
+        Ok(true),
+        evaluate_if_minimal_with_minimalif
+    );
+    evaluate_with_flags!(
+        "00 OP_NOTIF OP_1 OP_ELSE OP_0 OP_ENDIF",
+        ScriptFlags::MINIMALIF,
+        SigVersion::WitnessV0,
+        Err("minimal_if"),
+        evaluate_notif_not_minimal_with_minimalif
+    );
+    evaluate_with_flags!(
+        "OP_0 OP_NOTIF OP_1 OP_ELSE OP_0 OP_ENDIF",
+        ScriptFlags::MINIMALIF,
+        SigVersion::WitnessV0,
+        Ok(true),
+        evaluate_notif_minimal_with_minimalif
+    );
+
+    // valid signature of another message
+    evaluate_checksig_with_flags!(
+        "3045022100bebe0c00a59a6c01231790fe8034508c06904289de0e3ddccb897d9cf5794b0202205e1ff2d6f060524bd7da2a598f5205759ef0911a695407999965527ba9629a2501",
+        ScriptFlags::NONE,
+        Ok(false),
+        evaluate_checksig_failing
+    );
+    evaluate_checksig_with_flags!(
+        "3045022100bebe0c00a59a6c01231790fe8034508c06904289de0e3ddccb897d9cf5794b0202205e1ff2d6f060524bd7da2a598f5205759ef0911a695407999965527ba9629a2501",
+        ScriptFlags::NULLFAIL,
+        Err("signature_null_fail"),
+        evaluate_checksig_null_fail
+    );
+    evaluate_checksig_with_flags!(
+        "",
+        ScriptFlags::NULLFAIL,
+        Ok(false),
+        evaluate_checksig_null_fail_empty_signature
+    );
+
+    #[test]
+    fn evaluate_checklocktimeverify_before_bip65() {
+        let script = ScriptLang::from_tokens(vec![
+            Token::Command(OP_1),
+            Token::Command(OP_CHECKLOCKTIMEVERIFY),
+            Token::Command(OP_CHECKSEQUENCEVERIFY),
+        ]);
+        let mut context = Context::new_with_flags(
+            script.tokens(),
+            Integer::from(0),
+            ScriptFlags::consensus(Network::Mainnet, 388_380),
+        );
+
+        assert!(script.evaluate(&mut context).unwrap());
+        assert_eq!(context.stack_len(), 1);
+    }
+
+    macro_rules! evaluate_timelock {
+        ($script:literal, $flags:expr, $spending_tx:expr, $expected:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let script = ScriptLang::from_representation($script).unwrap();
+                let mut context = Context::new_with_flags(script.tokens(), Integer::from(0), $flags);
+                let spending_tx: Option<SpendingTx> = $spending_tx;
+                if let Some(spending_tx) = spending_tx {
+                    context.set_spending_tx(spending_tx);
+                }
+
+                let expected: Result<bool, &str> = $expected;
+                match script.evaluate(&mut context) {
+                    Ok(valid) => assert_eq!(Ok(valid), expected),
+                    Err(e) => assert_eq!(Err(e.to_string().as_str()), expected),
+                }
+            }
+        };
+    }
+
+    fn spending_tx(version: u32, locktime: u32, sequence: u32) -> Option<SpendingTx> {
+        Some(SpendingTx {
+            version,
+            locktime,
+            sequence,
+        })
+    }
+
+    // BIP65
+    evaluate_timelock!(
+        "0A OP_CHECKLOCKTIMEVERIFY",
+        ScriptFlags::CHECKLOCKTIMEVERIFY,
+        spending_tx(1, 10, 0xFFFFFFFE),
+        Ok(true),
+        evaluate_checklocktimeverify
+    );
+    evaluate_timelock!(
+        "0B OP_CHECKLOCKTIMEVERIFY",
+        ScriptFlags::CHECKLOCKTIMEVERIFY,
+        spending_tx(1, 10, 0xFFFFFFFE),
+        Err("unsatisfied_locktime"),
+        evaluate_checklocktimeverify_not_reached
+    );
+    evaluate_timelock!(
+        "0A OP_CHECKLOCKTIMEVERIFY",
+        ScriptFlags::CHECKLOCKTIMEVERIFY,
+        spending_tx(1, 10, 0xFFFFFFFF),
+        Err("unsatisfied_locktime"),
+        evaluate_checklocktimeverify_final_sequence
+    );
+    // 500000000: a timestamp, the locktime of the transaction is a height
+    evaluate_timelock!(
+        "0065CD1D OP_CHECKLOCKTIMEVERIFY",
+        ScriptFlags::CHECKLOCKTIMEVERIFY,
+        spending_tx(1, 10, 0),
+        Err("unsatisfied_locktime"),
+        evaluate_checklocktimeverify_another_type
+    );
+    // 5 bytes operand
+    evaluate_timelock!(
+        "FFFFFFFF00 OP_CHECKLOCKTIMEVERIFY",
+        ScriptFlags::CHECKLOCKTIMEVERIFY,
+        spending_tx(1, 0xFFFFFFFF, 0),
+        Ok(true),
+        evaluate_checklocktimeverify_max_timestamp
+    );
+    evaluate_timelock!(
+        "81 OP_CHECKLOCKTIMEVERIFY",
+        ScriptFlags::CHECKLOCKTIMEVERIFY,
+        spending_tx(1, 10, 0),
+        Err("negative_locktime"),
+        evaluate_checklocktimeverify_negative
+    );
+    evaluate_timelock!(
+        "OP_CHECKLOCKTIMEVERIFY",
+        ScriptFlags::CHECKLOCKTIMEVERIFY,
+        spending_tx(1, 10, 0),
+        Err("not_enough_items_in_stack"),
+        evaluate_checklocktimeverify_empty_stack
+    );
+    evaluate_timelock!(
+        "00 OP_CHECKLOCKTIMEVERIFY",
+        ScriptFlags::CHECKLOCKTIMEVERIFY,
+        None,
+        Err("unsatisfied_locktime"),
+        evaluate_checklocktimeverify_without_transaction
+    );
+
+    // BIP112
+    evaluate_timelock!(
+        "0A OP_CHECKSEQUENCEVERIFY",
+        ScriptFlags::CHECKSEQUENCEVERIFY,
+        spending_tx(2, 0, 10),
+        Ok(true),
+        evaluate_checksequenceverify
+    );
+    evaluate_timelock!(
+        "0A OP_CHECKSEQUENCEVERIFY",
+        ScriptFlags::CHECKSEQUENCEVERIFY,
+        spending_tx(2, 0, 9),
+        Err("unsatisfied_locktime"),
+        evaluate_checksequenceverify_not_reached
+    );
+    evaluate_timelock!(
+        "0A OP_CHECKSEQUENCEVERIFY",
+        ScriptFlags::CHECKSEQUENCEVERIFY,
+        spending_tx(1, 0, 10),
+        Err("unsatisfied_locktime"),
+        evaluate_checksequenceverify_version_1
+    );
+    // relative timelock disabled in the input
+    evaluate_timelock!(
+        "0A OP_CHECKSEQUENCEVERIFY",
+        ScriptFlags::CHECKSEQUENCEVERIFY,
+        spending_tx(2, 0, 0x8000000A),
+        Err("unsatisfied_locktime"),
+        evaluate_checksequenceverify_disabled_sequence
+    );
+    // 0x0040000A: 10 units of 512 seconds, the input has 10 blocks
+    evaluate_timelock!(
+        "0A004000 OP_CHECKSEQUENCEVERIFY",
+        ScriptFlags::CHECKSEQUENCEVERIFY,
+        spending_tx(2, 0, 10),
+        Err("unsatisfied_locktime"),
+        evaluate_checksequenceverify_another_type
+    );
+    // bits out of the mask are ignored
+    evaluate_timelock!(
+        "0A000100 OP_CHECKSEQUENCEVERIFY",
+        ScriptFlags::CHECKSEQUENCEVERIFY,
+        spending_tx(2, 0, 10),
+        Ok(true),
+        evaluate_checksequenceverify_masked
+    );
+    // 0x80000000: the disable flag makes it a NOP, even for version 1
+    evaluate_timelock!(
+        "0000008000 OP_CHECKSEQUENCEVERIFY",
+        ScriptFlags::CHECKSEQUENCEVERIFY,
+        spending_tx(1, 0, 0),
+        Ok(true),
+        evaluate_checksequenceverify_disabled_operand
+    );
+    evaluate_timelock!(
+        "81 OP_CHECKSEQUENCEVERIFY",
+        ScriptFlags::CHECKSEQUENCEVERIFY,
+        spending_tx(2, 0, 10),
+        Err("negative_locktime"),
+        evaluate_checksequenceverify_negative
+    );
+
     //
     // OP_X
     //
@@ -386,7 +1321,7 @@ mod script_test {
 
         let op = context.stack_pop_as_element().unwrap();
 
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_EMPTY.to_vec()));
     }
 
     #[test]
@@ -521,21 +1456,6 @@ mod script_test {
     //     assert_eq!("overflow", valid.expect_err("Err").to_string());
     // }
 
-    #[test]
-    fn evaluate_mul() {
-        let script = ScriptLang::from_tokens(vec![
-            Token::Element(vec![0x02]),
-            Token::Element(vec![0x02]),
-            Token::Command(OP_MUL),
-        ]);
-        let mut context = Context::new(script.tokens(), Integer::from(0));
-        let _valid = script.evaluate(&mut context).unwrap();
-
-        let op = context.stack_pop_as_element().unwrap();
-
-        assert_eq!(op, Token::Element(vec![0x04]));
-    }
-
     #[test]
     fn evaluate_equal_true() {
         let script = ScriptLang::from_tokens(vec![
@@ -1009,7 +1929,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -1022,7 +1942,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -1840,7 +2760,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -1864,7 +2784,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -1888,7 +2808,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -1912,7 +2832,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -1924,7 +2844,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -1948,7 +2868,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2020,7 +2940,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2032,7 +2952,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2068,7 +2988,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2080,7 +3000,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2124,7 +3044,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2136,7 +3056,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2161,7 +3081,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2173,7 +3093,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2198,7 +3118,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2235,7 +3155,7 @@ mod script_test {
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2252,7 +3172,7 @@ mod script_test {
 
     #[test]
     fn evaluate_generic_script_1() {
-        let script = ScriptLang::from_representation("02 OP_DUP OP_DUP OP_MUL OP_ADD OP_6 OP_EQUAL").unwrap();
+        let script = ScriptLang::from_representation("02 OP_DUP OP_DUP OP_ADD OP_ADD OP_6 OP_EQUAL").unwrap();
         let mut context = Context::new(script.tokens(), Integer::from(0));
         let _valid = script.evaluate(&mut context).unwrap();
 
@@ -2340,7 +3260,7 @@ mod script_test {
 
     #[test]
     fn evaluate_within() {
-        let script = ScriptLang::from_representation("05 06 03 OP_WITHIN").unwrap();
+        let script = ScriptLang::from_representation("05 03 06 OP_WITHIN").unwrap();
         let mut context = Context::new(script.tokens(), Integer::from(0));
         let _valid = script.evaluate(&mut context);
 
@@ -2353,7 +3273,7 @@ mod script_test {
 
     #[test]
     fn evaluate_within_left() {
-        let script = ScriptLang::from_representation("03 06 03 OP_WITHIN").unwrap();
+        let script = ScriptLang::from_representation("03 03 06 OP_WITHIN").unwrap();
         let mut context = Context::new(script.tokens(), Integer::from(0));
         let _valid = script.evaluate(&mut context);
 
@@ -2366,38 +3286,38 @@ mod script_test {
 
     #[test]
     fn evaluate_within_out_left() {
-        let script = ScriptLang::from_representation("02 06 03 OP_WITHIN").unwrap();
+        let script = ScriptLang::from_representation("02 03 06 OP_WITHIN").unwrap();
         let mut context = Context::new(script.tokens(), Integer::from(0));
         let _valid = script.evaluate(&mut context);
 
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
     fn evaluate_within_right() {
-        let script = ScriptLang::from_representation("06 06 03 OP_WITHIN").unwrap();
+        let script = ScriptLang::from_representation("06 03 06 OP_WITHIN").unwrap();
         let mut context = Context::new(script.tokens(), Integer::from(0));
         let _valid = script.evaluate(&mut context);
 
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
     fn evaluate_within_out_right() {
-        let script = ScriptLang::from_representation("07 06 03 OP_WITHIN").unwrap();
+        let script = ScriptLang::from_representation("07 03 06 OP_WITHIN").unwrap();
         let mut context = Context::new(script.tokens(), Integer::from(0));
         let _valid = script.evaluate(&mut context);
 
         assert!(context.stack_has_items(1));
 
         let op = context.stack_pop_as_element().unwrap();
-        assert_eq!(op, Token::Element(ELEMENT_ZERO.to_vec()));
+        assert_eq!(op, Token::Element(ELEMENT_FALSE.to_vec()));
     }
 
     #[test]
@@ -2507,6 +3427,54 @@ mod script_test {
     evaluate_op_reserved!("OP_VERNOTIF", evaluate_op_vernotif);
     evaluate_op_reserved!("OP_RESERVED1", evaluate_op_reserved1);
     evaluate_op_reserved!("OP_RESERVED2", evaluate_op_reserved2);
+    evaluate_op_reserved!(
+        "OP_0 OP_IF OP_VERIF OP_ENDIF OP_1",
+        evaluate_op_verif_in_not_executed_branch
+    );
+    evaluate_op_reserved!(
+        "OP_0 OP_IF OP_VERNOTIF OP_ENDIF OP_1",
+        evaluate_op_vernotif_in_not_executed_branch
+    );
+
+    #[test]
+    fn evaluate_op_checksigadd() {
+        let script = ScriptLang::from_representation("OP_0 OP_0 OP_0 OP_CHECKSIGADD").unwrap();
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+
+        assert_eq!("invalid_opcode", valid.expect_err("Err").to_string());
+    }
+
+    // after an OP_CODESEPARATOR the signature signs the rest of the script only, with its own z
+    #[test]
+    fn evaluate_checksig_after_codeseparator() {
+        let z: Integer = Integer::from_hex_str("7C076FF316692A3D7EB3C3BB0F8B1488CF72E1AFCD929E29307032997A838A3D");
+        let pubkey = "04887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34";
+        let signature = "3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601";
+
+        let script =
+            ScriptLang::from_representation(&format!("{} OP_CODESEPARATOR {} OP_CHECKSIG", signature, pubkey)).unwrap();
+
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+        assert_eq!(
+            "codeseparator_without_signature_hash",
+            valid.expect_err("Err").to_string()
+        );
+
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        context.set_codeseparator_z(HashMap::from([(1, z)]));
+        assert!(script.evaluate(&mut context).unwrap());
+
+        // an OP_CODESEPARATOR not executed is ignored
+        let script = ScriptLang::from_representation(&format!(
+            "{} OP_0 OP_IF OP_CODESEPARATOR OP_ENDIF {} OP_CHECKSIG",
+            signature, pubkey
+        ))
+        .unwrap();
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        assert!(!script.evaluate(&mut context).unwrap());
+    }
 
     //
     // Deprecated
@@ -2524,7 +3492,7 @@ mod script_test {
         };
     }
 
-    // evaluate_op_deprecated!("OP_MUL", evaluate_op_mul);
+    evaluate_op_deprecated!("OP_MUL", evaluate_op_mul);
     evaluate_op_deprecated!("OP_CAT", evaluate_op_cat);
     evaluate_op_deprecated!("OP_SUBSTR", evaluate_op_substr);
     evaluate_op_deprecated!("OP_LEFT", evaluate_op_left);
@@ -2540,6 +3508,132 @@ mod script_test {
     evaluate_op_deprecated!("OP_LSHIFT", evaluate_op_lshift);
     evaluate_op_deprecated!("OP_RSHIFT", evaluate_op_rshift);
 
+    #[test]
+    fn evaluate_deprecated_in_not_executed_branch() {
+        let script = ScriptLang::from_representation("OP_0 OP_IF OP_CAT OP_ENDIF OP_1").unwrap();
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+
+        assert_eq!("deprecated_opcode", valid.expect_err("Err").to_string());
+    }
+
+    //
+    // Consensus limits
+    //
+    #[test]
+    fn evaluate_script_size_limit() {
+        // 19 pushes of 520 bytes (OP_PUSHDATA2) and one of 99 bytes (OP_PUSHDATA1): 10,039 bytes
+        let mut tokens = vec![Token::Element(vec![0x01; 520]); 19];
+        tokens.push(Token::Element(vec![0x01; 99]));
+        tokens.push(Token::Command(OP_1));
+        let script = ScriptLang::from_tokens(tokens);
+        let size = script.serialize().unwrap().len();
+
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+
+        assert!(size > MAX_SCRIPT_SIZE);
+        assert_eq!("script_size_exceeded", valid.expect_err("Err").to_string());
+    }
+
+    #[test]
+    fn evaluate_element_size_limit() {
+        let script = ScriptLang::from_tokens(vec![Token::Element(vec![0x01; 520])]);
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        assert!(script.evaluate(&mut context).unwrap());
+
+        let script = ScriptLang::from_tokens(vec![
+            Token::Command(OP_0),
+            Token::Command(OP_IF),
+            Token::Element(vec![0x01; 521]),
+            Token::Command(OP_ENDIF),
+        ]);
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+
+        assert_eq!("element_size_exceeded", valid.expect_err("Err").to_string());
+    }
+
+    #[test]
+    fn evaluate_op_count_limit() {
+        let mut tokens = vec![Token::Command(OP_1)];
+        tokens.extend(vec![Token::Command(OP_NOP); 201]);
+        let script = ScriptLang::from_tokens(tokens.clone());
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        assert!(script.evaluate(&mut context).unwrap());
+
+        // push opcodes are not counted
+        tokens.push(Token::Command(OP_16));
+        tokens.push(Token::Command(OP_NOP));
+        let script = ScriptLang::from_tokens(tokens);
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+
+        assert_eq!("op_count_exceeded", valid.expect_err("Err").to_string());
+    }
+
+    #[test]
+    fn evaluate_op_count_limit_with_multisig_keys() {
+        // 200 OP_NOP + OP_CHECKMULTISIG are 201 opcodes, its public key is the 202nd
+        let mut tokens = vec![Token::Command(OP_0), Token::Command(OP_0)];
+        tokens.extend(vec![Token::Command(OP_NOP); 200]);
+        tokens.extend(vec![
+            Token::Command(OP_1),
+            Token::Element(vec![0x02; 33]),
+            Token::Command(OP_1),
+            Token::Command(OP_CHECKMULTISIG),
+        ]);
+        let script = ScriptLang::from_tokens(tokens);
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+
+        assert_eq!("op_count_exceeded", valid.expect_err("Err").to_string());
+    }
+
+    #[test]
+    fn evaluate_multisig_pubkey_count_limit() {
+        let mut tokens = vec![Token::Command(OP_0), Token::Command(OP_0), Token::Command(OP_1)];
+        tokens.extend(vec![Token::Element(vec![0x02; 33]); 21]);
+        tokens.push(Token::Element(vec![21]));
+        tokens.push(Token::Command(OP_CHECKMULTISIG));
+        let script = ScriptLang::from_tokens(tokens);
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+
+        assert_eq!("pubkey_count_exceeded", valid.expect_err("Err").to_string());
+    }
+
+    // no signature required: 0-of-0 and 0-of-n succeed
+    #[test]
+    fn evaluate_checkmultisig_zero_signatures() {
+        for script in [
+            "OP_0 OP_0 OP_0 OP_CHECKMULTISIG",
+            "OP_0 OP_0 02A130C1E1FFA137CF50824ECE45FB648CE88CB5570870DC10CFDC8C5F30946861 OP_1 OP_CHECKMULTISIG",
+        ] {
+            let script = ScriptLang::from_representation(script).unwrap();
+            let mut context = Context::new(script.tokens(), Integer::from(0));
+
+            assert!(script.evaluate(&mut context).unwrap());
+        }
+    }
+
+    #[test]
+    fn evaluate_stack_size_limit() {
+        let script = ScriptLang::from_tokens(vec![Token::Command(OP_1); 1000]);
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        assert!(script.evaluate(&mut context).unwrap());
+
+        // stack and alt stack together
+        let mut tokens = vec![Token::Command(OP_1); 1000];
+        tokens.insert(500, Token::Command(OP_TOALTSTACK));
+        tokens.push(Token::Command(OP_1));
+        let script = ScriptLang::from_tokens(tokens);
+        let mut context = Context::new(script.tokens(), Integer::from(0));
+        let valid = script.evaluate(&mut context);
+
+        assert_eq!("stack_size_exceeded", valid.expect_err("Err").to_string());
+    }
+
     //
     // Invalid
     //
@@ -2603,7 +3697,7 @@ mod script_test {
     fn evaluate_p2pkh_2() {
         // generate a key
         let key = new(Network::Testnet);
-        let pub_key = key.pubkey;
+        let pub_key = key.pubkey.clone();
         let pub_key_hash = hash160(&pub_key);
 
         let message_bytes = vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]; //  this represents the serialized transaction
@@ -2634,7 +3728,7 @@ mod script_test {
     fn evaluate_p2ms_1_to_1() {
         // generate a key
         let key = new(Network::Testnet);
-        let pub_key = key.pubkey;
+        let pub_key = key.pubkey.clone();
 
         let message_bytes = vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]; //  this represents the serialized transaction
         let message_hash = hash160(&message_bytes);
@@ -2661,10 +3755,10 @@ mod script_test {
     fn evaluate_p2ms_1_to_2() {
         // generate two keys
         let key1 = new(Network::Testnet);
-        let pub_key1 = key1.pubkey;
+        let pub_key1 = key1.pubkey.clone();
 
         let key2 = new(Network::Testnet);
-        let pub_key2 = key2.pubkey;
+        let pub_key2 = key2.pubkey.clone();
 
         let message_bytes = vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]; //  this represents the serialized transaction
         let message_hash = hash160(&message_bytes);
@@ -2691,10 +3785,10 @@ mod script_test {
     fn evaluate_p2ms_2_to_2() {
         // generate two keys
         let key1 = new(Network::Testnet);
-        let pub_key1 = key1.pubkey;
+        let pub_key1 = key1.pubkey.clone();
 
         let key2 = new(Network::Testnet);
-        let pub_key2 = key2.pubkey;
+        let pub_key2 = key2.pubkey.clone();
 
         let message_bytes = vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]; //  this represents the serialized transaction
         let message_hash = hash160(&message_bytes);
@@ -2729,10 +3823,10 @@ mod script_test {
     fn evaluate_p2ms_2_to_2_but_inverted_pubkeys() {
         // generate two keys
         let key1 = new(Network::Testnet);
-        let pub_key1 = key1.pubkey;
+        let pub_key1 = key1.pubkey.clone();
 
         let key2 = new(Network::Testnet);
-        let pub_key2 = key2.pubkey;
+        let pub_key2 = key2.pubkey.clone();
 
         let message_bytes = vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]; //  this represents the serialized transaction
         let message_hash = hash160(&message_bytes);
@@ -0,0 +1,195 @@
+use crate::std_lib::std_result::StdResult;
+
+/*
+    Numbers in scripts: little endian, sign and magnitude (the highest bit of the last byte is the sign), zero is empty.
+        0 -> []
+        1 -> [0x01]      -1 -> [0x81]
+        127 -> [0x7F]    -127 -> [0xFF]
+        128 -> [0x80, 0x00]    -128 -> [0x80, 0x80]
+
+    Numeric opcodes only accept 4 bytes operands, but their results can be 5 bytes long (e.g. 2^31 - 1 + 1):
+    a result can be pushed on the stack but cannot be used as operand of another numeric opcode.
+
+    Ref: https://github.com/bitcoin/bitcoin/blob/master/src/script/script.h (CScriptNum)
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ScriptNum(i64);
+
+// bytes of a numeric operand
+pub const DEFAULT_MAX_NUM_SIZE: usize = 4;
+
+impl ScriptNum {
+    pub const ZERO: ScriptNum = ScriptNum(0);
+    pub const ONE: ScriptNum = ScriptNum(1);
+
+    pub fn new(value: i64) -> Self {
+        ScriptNum(value)
+    }
+
+    /*
+        `require_minimal` (MINIMALDATA flag): reject encodings with a superfluous last byte (e.g. [0x01, 0x00] for 1).
+    */
+    pub fn decode(bytes: &[u8], require_minimal: bool, max_size: usize) -> StdResult<Self> {
+        if bytes.len() > max_size {
+            Err("input_length_too_long")?;
+        }
+
+        if require_minimal && !Self::is_minimally_encoded(bytes) {
+            Err("script_num_not_minimal")?;
+        }
+
+        if bytes.is_empty() {
+            return Ok(ScriptNum::ZERO);
+        }
+
+        let mut value: i64 = 0;
+        for (i, byte) in bytes.iter().enumerate() {
+            value |= (*byte as i64) << (8 * i);
+        }
+
+        // sign bit
+        let last = bytes.len() - 1;
+        if bytes[last] & 0x80 != 0 {
+            let magnitude = value & !(0x80i64 << (8 * last));
+            return Ok(ScriptNum(-magnitude));
+        }
+
+        Ok(ScriptNum(value))
+    }
+
+    /*
+        The last byte can be 0x00 or 0x80 only if the highest bit of the previous one is set (it is the sign):
+        [0x80, 0x00] is 128, [0x00] and [0x80] (negative zero) are not minimal.
+    */
+    pub fn is_minimally_encoded(bytes: &[u8]) -> bool {
+        match bytes {
+            [] => true,
+            [.., last] if last & 0x7F != 0 => true,
+            [_] => false,
+            [.., previous, _] => previous & 0x80 != 0,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        if self.0 == 0 {
+            return vec![];
+        }
+
+        let negative = self.0 < 0;
+        let mut magnitude = self.0.unsigned_abs();
+
+        let mut res = Vec::with_capacity(9);
+        while magnitude > 0 {
+            res.push((magnitude & 0xFF) as u8);
+            magnitude >>= 8;
+        }
+
+        // highest bit already used: one more byte for the sign
+        let last = res.len() - 1;
+        if res[last] & 0x80 != 0 {
+            res.push(if negative { 0x80 } else { 0x00 });
+        } else if negative {
+            res[last] |= 0x80;
+        }
+
+        res
+    }
+
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+
+    // Clamped to the i32 range (as `CScriptNum::getint`)
+    pub fn as_i32(&self) -> i32 {
+        self.0.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+#[cfg(test)]
+mod script_num_test {
+    use super::*;
+    use crate::std_lib::vector::hex_string_to_bytes;
+
+    macro_rules! encoding {
+        ($value:expr, $hex:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let bytes = hex_string_to_bytes($hex).unwrap();
+
+                assert_eq!(ScriptNum::new($value).encode(), bytes);
+                assert_eq!(
+                    ScriptNum::decode(&bytes, true, 5).unwrap(),
+                    ScriptNum::new($value)
+                );
+            }
+        };
+    }
+
+    encoding!(0, "", encode_zero);
+    encoding!(1, "01", encode_one);
+    encoding!(-1, "81", encode_minus_one);
+    encoding!(127, "7f", encode_127);
+    encoding!(-127, "ff", encode_minus_127);
+    encoding!(128, "8000", encode_128);
+    encoding!(-128, "8080", encode_minus_128);
+    encoding!(255, "ff00", encode_255);
+    encoding!(256, "0001", encode_256);
+    encoding!(-256, "0081", encode_minus_256);
+    encoding!(32767, "ff7f", encode_32767);
+    encoding!(32768, "008000", encode_32768);
+    encoding!(2147483647, "ffffff7f", encode_i32_max);
+    encoding!(-2147483647, "ffffffff", encode_minus_i32_max);
+    encoding!(2147483648, "0000008000", encode_i32_max_plus_one);
+    encoding!(-2147483648, "0000008080", encode_i32_min);
+
+    macro_rules! not_minimal {
+        ($hex:literal, $value:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let bytes = hex_string_to_bytes($hex).unwrap();
+
+                assert!(!ScriptNum::is_minimally_encoded(&bytes));
+                assert_eq!(
+                    ScriptNum::decode(&bytes, true, 4).err().unwrap().to_string(),
+                    "script_num_not_minimal"
+                );
+                assert_eq!(
+                    ScriptNum::decode(&bytes, false, 4).unwrap(),
+                    ScriptNum::new($value)
+                );
+            }
+        };
+    }
+
+    not_minimal!("00", 0, not_minimal_zero);
+    not_minimal!("80", 0, not_minimal_negative_zero);
+    not_minimal!("0100", 1, not_minimal_padded_one);
+    not_minimal!("0180", -1, not_minimal_padded_minus_one);
+    not_minimal!("7f0000", 127, not_minimal_two_padding_bytes);
+    not_minimal!("00000000", 0, not_minimal_four_bytes_zero);
+
+    #[test]
+    fn decode_overflow() {
+        let bytes = hex_string_to_bytes("0000008000").unwrap();
+
+        assert_eq!(
+            ScriptNum::decode(&bytes, false, DEFAULT_MAX_NUM_SIZE)
+                .err()
+                .unwrap()
+                .to_string(),
+            "input_length_too_long"
+        );
+        assert_eq!(ScriptNum::decode(&bytes, false, 5).unwrap(), ScriptNum::new(2147483648));
+    }
+
+    #[test]
+    fn clamp_to_i32() {
+        assert_eq!(ScriptNum::new(2147483648).as_i32(), i32::MAX);
+        assert_eq!(ScriptNum::new(-2147483649).as_i32(), i32::MIN);
+        assert_eq!(ScriptNum::new(-5).as_i32(), -5);
+    }
+}
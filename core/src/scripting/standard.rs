@@ -97,6 +97,35 @@ pub fn p2ms_script(m: usize, pub_keys: &[&[u8]]) -> ScriptLang {
     ScriptLang::from_representation(&script_repr).unwrap()
 }
 
+pub fn p2sh_script(h160: &[u8]) -> ScriptLang {
+    let hash_str = bytes_to_hex_string(h160);
+    let script_repr = format!("OP_HASH160 {hash_str} OP_EQUAL");
+
+    ScriptLang::from_representation(&script_repr).unwrap()
+}
+
+pub fn p2wsh_script(script_hash: &[u8]) -> ScriptLang {
+    let hash_str = bytes_to_hex_string(script_hash);
+    let script_repr = format!("OP_0 {hash_str}");
+
+    ScriptLang::from_representation(&script_repr).unwrap()
+}
+
+pub fn p2wpkh_script(h160: &[u8]) -> ScriptLang {
+    let hash_str = bytes_to_hex_string(h160);
+    let script_repr = format!("OP_0 {hash_str}");
+
+    ScriptLang::from_representation(&script_repr).unwrap()
+}
+
+// `output_key`: x-only tweaked public key (BIP341)
+pub fn p2tr_script(output_key: &[u8]) -> ScriptLang {
+    let key_str = bytes_to_hex_string(output_key);
+    let script_repr = format!("OP_1 {key_str}");
+
+    ScriptLang::from_representation(&script_repr).unwrap()
+}
+
 #[cfg(test)]
 mod standard_test {
     use super::*;
@@ -142,4 +171,26 @@ mod standard_test {
         let script_type = standard_type(&script);
         assert_eq!(script_type, StandardType::P2ms)
     }
+
+    #[test]
+    fn test_p2sh_and_p2wsh_script() {
+        let script = p2sh_script(&[0xAA; 20]);
+        assert_eq!(
+            script.serialize().unwrap(),
+            [vec![0xA9, 0x14], vec![0xAA; 20], vec![0x87]].concat()
+        );
+        assert!(script.is_pay_to_script_hash());
+
+        let script = p2wsh_script(&[0xBB; 32]);
+        assert_eq!(script.serialize().unwrap(), [vec![0x00, 0x20], vec![0xBB; 32]].concat());
+    }
+
+    #[test]
+    fn test_p2wpkh_and_p2tr_script() {
+        let script = p2wpkh_script(&[0xAA; 20]);
+        assert_eq!(script.serialize().unwrap(), [vec![0x00, 0x14], vec![0xAA; 20]].concat());
+
+        let script = p2tr_script(&[0xBB; 32]);
+        assert_eq!(script.serialize().unwrap(), [vec![0x51, 0x20], vec![0xBB; 32]].concat());
+    }
 }
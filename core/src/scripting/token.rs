@@ -11,20 +11,23 @@ use super::opcode::OpCode;
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Element(Vec<u8>),
+    // A push with another opcode than the one `ScriptLang::serialize` picks for its data (e.g. OP_PUSHDATA1 for
+    // 5 bytes): kept to serialize the script as it was and to check MINIMALDATA. Its data is pushed as an `Element`.
+    Push(OpCode, Vec<u8>),
     Command(OpCode),
+    // A push with less bytes than expected at the end of a script, with all its remaining bytes
+    Truncated(Vec<u8>),
 }
 
 impl Token {
     pub fn as_bool(&self) -> bool {
         match self {
             Token::Element(value) => {
-                for i in value {
-                    // TODO: can be negative zero
-                    // if (i == vch.size()-1 && vch[i] == 0x80)
-                    // return false;
-                    // see: https://github.com/bitcoin/bitcoin/blob/a4ca4975880c4f870c6047065c70610af2529e74/src/script/interpreter.cpp#L42
-                    if *i != 0 {
-                        return true;
+                for (i, byte) in value.iter().enumerate() {
+                    if *byte != 0 {
+                        // negative zero (0x80 as last byte) is false
+                        // see: https://github.com/bitcoin/bitcoin/blob/a4ca4975880c4f870c6047065c70610af2529e74/src/script/interpreter.cpp#L42
+                        return !(i == value.len() - 1 && *byte == 0x80);
                     }
                 }
 
@@ -36,42 +39,49 @@ impl Token {
 
     pub fn as_number(&self) -> i64 {
         match self {
-            Token::Element(value) => {
+            Token::Element(value) | Token::Push(_, value) => {
                 if value.is_empty() {
                     return 0;
                 }
                 element_decode(value.to_vec())
             }
             Token::Command(cmd) => *cmd as i64,
+            Token::Truncated(_) => 0,
         }
     }
 
     pub fn is_zero_or_empy(&self) -> bool {
         match self {
-            Token::Element(value) => value == &ELEMENT_ZERO || value == &ELEMENT_EMPTY,
+            Token::Element(value) | Token::Push(_, value) => value == &ELEMENT_ZERO || value == &ELEMENT_EMPTY,
             Token::Command(cmd) => cmd == &opcode::OP_0,
+            Token::Truncated(_) => false,
         }
     }
 
     pub fn as_bytes(&self) -> Vec<u8> {
         match self {
-            Token::Element(value) => value.to_vec(),
+            Token::Element(value) | Token::Push(_, value) => value.to_vec(),
             Token::Command(_cmd) => vec![], // TODO: better throw an error
+            Token::Truncated(_) => vec![],
         }
     }
 
     pub fn as_op(&self) -> OpCode {
         match self {
-            Token::Element(_value) => opcode::OP_1, // TODO: better throw an error
+            Token::Element(_value) | Token::Push(_, _value) => opcode::OP_1, // TODO: better throw an error
             Token::Command(cmd) => *cmd,
+            Token::Truncated(_) => opcode::OP_INVALIDOPCODE,
         }
     }
 
+    // OP_IF ... OP_ENDIF: evaluated in branches not executed too (OP_VERIF and OP_VERNOTIF to fail there)
     pub fn is_op_branch_condition(&self) -> bool {
         matches!(
             self,
             Token::Command(opcode::OP_IF)
                 | Token::Command(opcode::OP_NOTIF)
+                | Token::Command(opcode::OP_VERIF)
+                | Token::Command(opcode::OP_VERNOTIF)
                 | Token::Command(opcode::OP_ELSE)
                 | Token::Command(opcode::OP_ENDIF)
         )
@@ -93,20 +103,24 @@ impl Token {
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let s = match &self {
-            Token::Element(bytes) => bytes_to_hex_string(bytes),
+            Token::Element(bytes) | Token::Push(_, bytes) => bytes_to_hex_string(bytes),
             Token::Command(op_code) => (*OP_TO_FN)[*op_code].name.to_string(),
+            Token::Truncated(_) => TRUNCATED_REPRESENTATION.to_string(),
         };
 
         write!(f, "{:}", s)
     }
 }
 
+// as Bitcoin Core (`ScriptToAsmStr`)
+pub const TRUNCATED_REPRESENTATION: &str = "[error]";
+
 pub static ELEMENT_EMPTY: [u8; 0] = [];
 pub static ELEMENT_ZERO: [u8; 1] = [0x00];
 pub static ELEMENT_ONE: [u8; 1] = [0x01];
 pub static ELEMENT_ONE_NEGATE: [u8; 1] = [0x81];
 pub static ELEMENT_TRUE: [u8; 1] = ELEMENT_ONE;
-pub static ELEMENT_FALSE: [u8; 1] = ELEMENT_ZERO;
+pub static ELEMENT_FALSE: [u8; 0] = ELEMENT_EMPTY;
 
 pub fn element_encode(num: i64) -> Vec<u8> {
     if num == 0 {
@@ -68,8 +68,20 @@ pub fn base58_encode_with_checksum(b: &[u8]) -> String {
     base58_encode(&bin)
 }
 
+// As `base58_decode`, with the leading zero bytes and an error for invalid characters
+pub fn base58_decode_binary(s: &str) -> StdResult<Vec<u8>> {
+    if s.chars().any(|c| !(*BASE58_ALPHABET).contains(&c)) {
+        Err("invalid_character")?;
+    }
+
+    // leading '1's are leading zero bytes, lost by the number
+    let zeros = s.chars().take_while(|c| *c == '1').count();
+
+    Ok([vec![0x00; zeros], base58_decode(s).to_digits(Order::Msf)].concat())
+}
+
 pub fn base58_decode_with_checksum(s: &str) -> StdResult<Vec<u8>> {
-    let d = base58_decode(s).to_digits(Order::Msf);
+    let d = base58_decode_binary(s)?;
 
     if d.len() < 4 {
         Err("invalid_length")?;
@@ -92,7 +104,9 @@ mod base58_test {
 
     use crate::std_lib::integer_extended::IntegerExtended;
 
-    use super::{base58_decode, base58_decode_with_checksum, base58_encode, base58_encode_with_checksum};
+    use super::{
+        base58_decode, base58_decode_binary, base58_decode_with_checksum, base58_encode, base58_encode_with_checksum,
+    };
 
     #[test]
     fn encode_1() {
@@ -187,6 +201,13 @@ mod base58_test {
         )
     }
 
+    #[test]
+    fn decode_checksum_leading_zeros() {
+        let res = base58_decode_with_checksum("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").unwrap();
+        assert_eq!(res.len(), 21);
+        assert_eq!(res[0], 0x00);
+    }
+
     #[test]
     fn decode_checksum_invalid_checksum() {
         let res = base58_decode_with_checksum("SFyVFVE84dMDxTAX88Rq8UJA2mWVNASRdWNorzbCAP22Qums1CuoZcPKU7xkjpBe");
@@ -198,4 +219,22 @@ mod base58_test {
         let res = base58_decode_with_checksum("a");
         assert_eq!("invalid_length", res.err().unwrap().to_string())
     }
+
+    #[test]
+    fn decode_checksum_invalid_character() {
+        let res = base58_decode_with_checksum("RVnPfpC0");
+        assert_eq!("invalid_character", res.err().unwrap().to_string())
+    }
+
+    #[test]
+    fn decode_binary() {
+        assert_eq!(
+            base58_decode_binary("11Ldp").unwrap(),
+            vec![0x00, 0x00, 0x01, 0x02, 0x03]
+        );
+        assert_eq!(base58_decode_binary("").unwrap(), Vec::<u8>::new());
+
+        let res = base58_decode_binary("RVnPfpC0");
+        assert_eq!("invalid_character", res.err().unwrap().to_string())
+    }
 }
@@ -0,0 +1,118 @@
+/*
+    Base64 with the standard alphabet and padding (RFC 4648), e.g. for PSBT (BIP174).
+*/
+
+use super::std_result::StdResult;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const PADDING: u8 = b'=';
+
+pub fn base64_encode(binary: &[u8]) -> String {
+    let mut result = String::with_capacity(binary.len().div_ceil(3) * 4);
+
+    for chunk in binary.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0x3F;
+                result.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                result.push(PADDING as char);
+            }
+        }
+    }
+
+    result
+}
+
+pub fn base64_decode(s: &str) -> StdResult<Vec<u8>> {
+    let encoded = s.as_bytes();
+    if !encoded.len().is_multiple_of(4) {
+        Err("invalid_base64_length")?;
+    }
+
+    let mut result: Vec<u8> = Vec::with_capacity(encoded.len() / 4 * 3);
+
+    for (n, chunk) in encoded.chunks(4).enumerate() {
+        let last_chunk = (n + 1) * 4 == encoded.len();
+        let padding = chunk.iter().rev().take_while(|c| **c == PADDING).count();
+
+        if padding > 2 || (padding > 0 && !last_chunk) {
+            Err("invalid_base64_padding")?;
+        }
+
+        let mut group: u32 = 0;
+        for c in &chunk[..4 - padding] {
+            let value = match BASE64_ALPHABET.iter().position(|a| a == c) {
+                Some(value) => value as u32,
+                None => Err("invalid_base64_character")?,
+            };
+
+            group = (group << 6) | value;
+        }
+        group <<= 6 * padding;
+
+        let bytes = group.to_be_bytes();
+        result.extend(&bytes[1..4 - padding]);
+
+        // bits not used must be 0 (canonical encoding)
+        let unused = group & ((1 << (8 * padding)) - 1);
+        if unused != 0 {
+            Err("invalid_base64_padding")?;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod base64_test {
+    use super::{base64_decode, base64_encode};
+
+    // RFC 4648 test vectors
+    macro_rules! base64 {
+        ($binary:literal, $encoded:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                assert_eq!(base64_encode($binary.as_bytes()), $encoded);
+                assert_eq!(base64_decode($encoded).unwrap(), $binary.as_bytes());
+            }
+        };
+    }
+
+    base64!("", "", base64_empty);
+    base64!("f", "Zg==", base64_f);
+    base64!("fo", "Zm8=", base64_fo);
+    base64!("foo", "Zm9v", base64_foo);
+    base64!("foob", "Zm9vYg==", base64_foob);
+    base64!("fooba", "Zm9vYmE=", base64_fooba);
+    base64!("foobar", "Zm9vYmFy", base64_foobar);
+
+    #[test]
+    fn base64_all_bytes() {
+        let binary: Vec<u8> = (0..=255).collect();
+        assert_eq!(base64_decode(&base64_encode(&binary)).unwrap(), binary);
+    }
+
+    macro_rules! base64_decode_error {
+        ($encoded:literal, $error:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let err = base64_decode($encoded).expect_err("Err");
+                assert_eq!(err.to_string(), $error);
+            }
+        };
+    }
+
+    base64_decode_error!("Zg=", "invalid_base64_length", base64_decode_length);
+    base64_decode_error!(
+        "Zg==Zg==",
+        "invalid_base64_padding",
+        base64_decode_padding_in_the_middle
+    );
+    base64_decode_error!("Z===", "invalid_base64_padding", base64_decode_too_much_padding);
+    base64_decode_error!("Zh==", "invalid_base64_padding", base64_decode_not_canonical);
+    base64_decode_error!("Zm9*", "invalid_base64_character", base64_decode_character);
+}
@@ -0,0 +1,694 @@
+//! Bech32 and Bech32m encoding.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+//!
+//! A bech32 string is made by
+//!     `hrp` (human readable part) + `1` (separator) + `data` (5 bits values) + `checksum` (6 values)
+//! Bech32m only differs for the constant used in checksum and it is used for witness version 1 and later.
+
+use std::fmt::{Display, Formatter};
+
+use crate::flags::network::Network;
+
+use super::std_result::StdResult;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3B6A57B2, 0x26508E6D, 0x1EA119FA, 0x3D4233DD, 0x2A1462B3];
+
+const BECH32_CONST: u32 = 0x01;
+const BECH32M_CONST: u32 = 0x2BC830A3;
+
+const SEPARATOR: char = '1';
+const CHECKSUM_LENGTH: usize = 6;
+const MAX_LENGTH: usize = 90;
+
+const HRP_MAINNET: &str = "bc";
+const HRP_TESTNET: &str = "tb";
+
+const MIN_PROGRAM_LENGTH: usize = 2;
+const MAX_PROGRAM_LENGTH: usize = 40;
+const MAX_WITNESS_VERSION: u8 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bech32Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Bech32Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Bech32Variant::Bech32 => BECH32_CONST,
+            Bech32Variant::Bech32m => BECH32M_CONST,
+        }
+    }
+}
+
+/// Decoding error: `reason` is shown as the error message, `position` (when available)
+/// is the index of the offending character in the input string.
+#[derive(Debug, PartialEq)]
+pub struct Bech32Error {
+    reason: &'static str,
+    position: Option<usize>,
+}
+
+impl Bech32Error {
+    fn new(reason: &'static str, position: Option<usize>) -> Self {
+        Bech32Error { reason, position }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+}
+
+impl Display for Bech32Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for Bech32Error {}
+
+/// Human readable part for segwit addresses.
+pub fn network_hrp(network: Network) -> &'static str {
+    match network {
+        Network::Mainnet => HRP_MAINNET,
+        Network::Testnet => HRP_TESTNET,
+    }
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+
+    for v in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1FFFFFF) << 5) ^ (*v as u32);
+
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let bytes = hrp.as_bytes();
+
+    let mut res: Vec<u8> = bytes.iter().map(|b| b >> 5).collect();
+    res.push(0);
+    res.extend(bytes.iter().map(|b| b & 0x1F));
+
+    res
+}
+
+fn create_checksum(hrp: &str, data: &[u8], variant: Bech32Variant) -> [u8; CHECKSUM_LENGTH] {
+    let values = [hrp_expand(hrp).as_slice(), data, &[0u8; CHECKSUM_LENGTH]].concat();
+    let pm = polymod(&values) ^ variant.constant();
+
+    let mut checksum = [0u8; CHECKSUM_LENGTH];
+    for (i, c) in checksum.iter_mut().enumerate() {
+        *c = ((pm >> (5 * (5 - i))) & 0x1F) as u8;
+    }
+
+    checksum
+}
+
+fn verify_checksum(hrp: &str, data: &[u8]) -> Option<Bech32Variant> {
+    let values = [hrp_expand(hrp).as_slice(), data].concat();
+
+    match polymod(&values) {
+        BECH32_CONST => Some(Bech32Variant::Bech32),
+        BECH32M_CONST => Some(Bech32Variant::Bech32m),
+        _ => None,
+    }
+}
+
+/// Encode `data` (5 bits values) with `hrp`.
+pub fn bech32_encode(hrp: &str, data: &[u8], variant: Bech32Variant) -> StdResult<String> {
+    if hrp.is_empty() {
+        Err(Bech32Error::new("empty_hrp", None))?;
+    }
+
+    for (i, c) in hrp.chars().enumerate() {
+        if !(33..=126).contains(&(c as u32)) {
+            Err(Bech32Error::new("invalid_hrp_character", Some(i)))?;
+        }
+    }
+
+    if data.iter().any(|d| *d > 31) {
+        Err(Bech32Error::new("invalid_data_value", None))?;
+    }
+
+    if hrp.len() + 1 + data.len() + CHECKSUM_LENGTH > MAX_LENGTH {
+        Err(Bech32Error::new("invalid_length", None))?;
+    }
+
+    // checksum is always calculated on lower case hrp
+    let hrp = hrp.to_lowercase();
+    let checksum = create_checksum(&hrp, data, variant);
+
+    let mut res = format!("{}{}", hrp, SEPARATOR);
+    for d in data.iter().chain(checksum.iter()) {
+        res.push(CHARSET[*d as usize] as char);
+    }
+
+    Ok(res)
+}
+
+/// Decode a bech32 or bech32m string.
+/// Return lower case `hrp`, `data` (5 bits values, without checksum) and the detected variant.
+pub fn bech32_decode(s: &str) -> StdResult<(String, Vec<u8>, Bech32Variant)> {
+    if s.len() > MAX_LENGTH {
+        Err(Bech32Error::new("invalid_length", None))?;
+    }
+
+    let mut has_lower = false;
+    let mut has_upper = false;
+
+    for (i, c) in s.chars().enumerate() {
+        if !(33..=126).contains(&(c as u32)) {
+            Err(Bech32Error::new("invalid_character", Some(i)))?;
+        }
+
+        has_lower |= c.is_ascii_lowercase();
+        has_upper |= c.is_ascii_uppercase();
+
+        if has_lower && has_upper {
+            Err(Bech32Error::new("mixed_case", Some(i)))?;
+        }
+    }
+
+    let lower = s.to_lowercase();
+
+    let separator = match lower.rfind(SEPARATOR) {
+        Some(pos) => pos,
+        None => Err(Bech32Error::new("missing_separator", None))?,
+    };
+
+    if separator == 0 {
+        Err(Bech32Error::new("empty_hrp", Some(0)))?;
+    }
+
+    if separator + 1 + CHECKSUM_LENGTH > lower.len() {
+        Err(Bech32Error::new("invalid_checksum_length", Some(separator)))?;
+    }
+
+    let (hrp, rest) = lower.split_at(separator);
+
+    let mut data: Vec<u8> = Vec::with_capacity(rest.len() - 1);
+    for (i, c) in rest.bytes().enumerate().skip(1) {
+        match CHARSET.iter().position(|v| *v == c) {
+            Some(value) => data.push(value as u8),
+            None => Err(Bech32Error::new("invalid_character", Some(separator + i)))?,
+        }
+    }
+
+    let variant = match verify_checksum(hrp, &data) {
+        Some(variant) => variant,
+        None => Err(Bech32Error::new("invalid_checksum", None))?,
+    };
+
+    data.truncate(data.len() - CHECKSUM_LENGTH);
+
+    Ok((hrp.to_string(), data, variant))
+}
+
+/// General power-of-2 base conversion (e.g. from 8 bits bytes to 5 bits values and vice versa).
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> StdResult<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max_value: u32 = (1 << to) - 1;
+    let max_acc: u32 = (1 << (from + to - 1)) - 1;
+
+    let mut res: Vec<u8> = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+
+    for value in data {
+        let v = *value as u32;
+        if (v >> from) != 0 {
+            Err("invalid_data_value")?;
+        }
+
+        acc = ((acc << from) | v) & max_acc;
+        bits += from;
+
+        while bits >= to {
+            bits -= to;
+            res.push(((acc >> bits) & max_value) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            res.push(((acc << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max_value) != 0 {
+        Err("invalid_padding")?;
+    }
+
+    Ok(res)
+}
+
+fn witness_variant(version: u8) -> Bech32Variant {
+    if version == 0 {
+        Bech32Variant::Bech32
+    } else {
+        Bech32Variant::Bech32m
+    }
+}
+
+fn verify_witness_program(version: u8, program: &[u8]) -> StdResult<()> {
+    if version > MAX_WITNESS_VERSION {
+        Err("invalid_witness_version")?;
+    }
+
+    if program.len() < MIN_PROGRAM_LENGTH || program.len() > MAX_PROGRAM_LENGTH {
+        Err("invalid_program_length")?;
+    }
+
+    // P2WPKH (20 bytes) or P2WSH (32 bytes)
+    if version == 0 && program.len() != 20 && program.len() != 32 {
+        Err("invalid_program_length_for_witness_v0")?;
+    }
+
+    Ok(())
+}
+
+/// Encode a segwit address from witness `version` and `program`.
+pub fn segwit_encode(network: Network, version: u8, program: &[u8]) -> StdResult<String> {
+    verify_witness_program(version, program)?;
+
+    let mut data: Vec<u8> = vec![version];
+    data.extend(convert_bits(program, 8, 5, true)?);
+
+    bech32_encode(network_hrp(network), &data, witness_variant(version))
+}
+
+/// Decode a segwit address.
+/// Return witness version and program.
+pub fn segwit_decode(network: Network, address: &str) -> StdResult<(u8, Vec<u8>)> {
+    let (hrp, data, variant) = bech32_decode(address)?;
+
+    if hrp != network_hrp(network) {
+        Err("invalid_hrp")?;
+    }
+
+    if data.is_empty() {
+        Err("missing_witness_version")?;
+    }
+
+    let version = data[0];
+    let program = convert_bits(&data[1..], 5, 8, false)?;
+
+    verify_witness_program(version, &program)?;
+
+    if variant != witness_variant(version) {
+        Err("invalid_variant_for_witness_version")?;
+    }
+
+    Ok((version, program))
+}
+
+#[cfg(test)]
+mod bech32_test {
+    use super::*;
+    use crate::std_lib::vector::{bytes_to_hex_string, hex_string_to_bytes};
+
+    fn error_reason(s: &str) -> String {
+        bech32_decode(s).err().unwrap().to_string()
+    }
+
+    macro_rules! valid_bech32 {
+        ($s:literal, $variant:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let (hrp, data, variant) = bech32_decode($s).unwrap();
+                assert_eq!(variant, $variant);
+
+                let encoded = bech32_encode(&hrp, &data, variant).unwrap();
+                assert_eq!(encoded, $s.to_lowercase());
+            }
+        };
+    }
+
+    valid_bech32!("A12UEL5L", Bech32Variant::Bech32, valid_bech32_1);
+    valid_bech32!("a12uel5l", Bech32Variant::Bech32, valid_bech32_2);
+    valid_bech32!(
+        "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+        Bech32Variant::Bech32,
+        valid_bech32_3
+    );
+    valid_bech32!(
+        "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+        Bech32Variant::Bech32,
+        valid_bech32_4
+    );
+    valid_bech32!(
+        "11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+        Bech32Variant::Bech32,
+        valid_bech32_5
+    );
+    valid_bech32!(
+        "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+        Bech32Variant::Bech32,
+        valid_bech32_6
+    );
+    valid_bech32!("?1ezyfcl", Bech32Variant::Bech32, valid_bech32_7);
+
+    valid_bech32!("A1LQFN3A", Bech32Variant::Bech32m, valid_bech32m_1);
+    valid_bech32!("a1lqfn3a", Bech32Variant::Bech32m, valid_bech32m_2);
+    valid_bech32!(
+        "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+        Bech32Variant::Bech32m,
+        valid_bech32m_3
+    );
+    valid_bech32!(
+        "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+        Bech32Variant::Bech32m,
+        valid_bech32m_4
+    );
+    valid_bech32!(
+        "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+        Bech32Variant::Bech32m,
+        valid_bech32m_5
+    );
+    valid_bech32!(
+        "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+        Bech32Variant::Bech32m,
+        valid_bech32m_6
+    );
+    valid_bech32!("?1v759aa", Bech32Variant::Bech32m, valid_bech32m_7);
+
+    macro_rules! invalid_bech32 {
+        ($s:expr, $reason:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                assert_eq!(error_reason($s), $reason);
+            }
+        };
+    }
+
+    invalid_bech32!(
+        "\x201nwldj5",
+        "invalid_character",
+        invalid_bech32_hrp_character_out_of_range_1
+    );
+    invalid_bech32!(
+        "\x7F1axkwrx",
+        "invalid_character",
+        invalid_bech32_hrp_character_out_of_range_2
+    );
+    invalid_bech32!(
+        "\u{80}1eym55h",
+        "invalid_character",
+        invalid_bech32_hrp_character_out_of_range_3
+    );
+    invalid_bech32!(
+        "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+        "invalid_length",
+        invalid_bech32_overall_max_length_exceeded
+    );
+    invalid_bech32!("pzry9x0s0muk", "missing_separator", invalid_bech32_no_separator);
+    invalid_bech32!("1pzry9x0s0muk", "empty_hrp", invalid_bech32_empty_hrp_1);
+    invalid_bech32!("x1b4n0q5v", "invalid_character", invalid_bech32_invalid_data_character);
+    invalid_bech32!("li1dgmt3", "invalid_checksum_length", invalid_bech32_too_short_checksum);
+    invalid_bech32!(
+        "de1lg7wt\u{FF}",
+        "invalid_character",
+        invalid_bech32_invalid_checksum_character
+    );
+    invalid_bech32!(
+        "A1G7SGD8",
+        "invalid_checksum",
+        invalid_bech32_checksum_with_upper_case_hrp
+    );
+    invalid_bech32!("10a06t8", "empty_hrp", invalid_bech32_empty_hrp_2);
+    invalid_bech32!("1qzzfhee", "empty_hrp", invalid_bech32_empty_hrp_3);
+
+    invalid_bech32!("qyrz8wqd2c9m", "missing_separator", invalid_bech32m_no_separator);
+    invalid_bech32!("y1b0jsk6g", "invalid_character", invalid_bech32m_invalid_data_character);
+    invalid_bech32!(
+        "lt1igcx5c0",
+        "invalid_character",
+        invalid_bech32m_invalid_data_character_2
+    );
+    invalid_bech32!(
+        "in1muywd",
+        "invalid_checksum_length",
+        invalid_bech32m_too_short_checksum
+    );
+    invalid_bech32!(
+        "mm1crxm3i",
+        "invalid_character",
+        invalid_bech32m_invalid_checksum_character
+    );
+    invalid_bech32!(
+        "au1s5cgom",
+        "invalid_character",
+        invalid_bech32m_invalid_checksum_character_2
+    );
+    invalid_bech32!(
+        "M1VUXWEZ",
+        "invalid_checksum",
+        invalid_bech32m_checksum_with_upper_case_hrp
+    );
+    invalid_bech32!("16plkw9", "empty_hrp", invalid_bech32m_empty_hrp_1);
+    invalid_bech32!("1p2gdwpf", "empty_hrp", invalid_bech32m_empty_hrp_2);
+
+    #[test]
+    fn invalid_character_position() {
+        let err = bech32_decode("x1b4n0q5v").err().unwrap();
+        let bech32_err = err.downcast_ref::<Bech32Error>().unwrap();
+
+        assert_eq!(bech32_err.reason(), "invalid_character");
+        assert_eq!(bech32_err.position(), Some(2));
+    }
+
+    #[test]
+    fn mixed_case_position() {
+        let err = bech32_decode("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sL5k7")
+            .err()
+            .unwrap();
+        let bech32_err = err.downcast_ref::<Bech32Error>().unwrap();
+
+        assert_eq!(bech32_err.reason(), "mixed_case");
+        assert_eq!(bech32_err.position(), Some(58));
+    }
+
+    #[test]
+    fn encode_too_long() {
+        let data = vec![0u8; 83];
+        let res = bech32_encode("a", &data, Bech32Variant::Bech32);
+
+        assert_eq!(res.err().unwrap().to_string(), "invalid_length");
+    }
+
+    #[test]
+    fn encode_invalid_data_value() {
+        let res = bech32_encode("a", &[32], Bech32Variant::Bech32);
+
+        assert_eq!(res.err().unwrap().to_string(), "invalid_data_value");
+    }
+
+    #[test]
+    fn convert_bits_roundtrip() {
+        let bytes = hex_string_to_bytes("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+
+        let five = convert_bits(&bytes, 8, 5, true).unwrap();
+        let eight = convert_bits(&five, 5, 8, false).unwrap();
+
+        assert_eq!(eight, bytes);
+    }
+
+    #[test]
+    fn convert_bits_invalid_padding() {
+        let res = convert_bits(&[0x1F, 0x1F], 5, 8, false);
+
+        assert_eq!(res.err().unwrap().to_string(), "invalid_padding");
+    }
+
+    #[test]
+    fn hrp_by_network() {
+        assert_eq!(network_hrp(Network::Mainnet), "bc");
+        assert_eq!(network_hrp(Network::Testnet), "tb");
+    }
+
+    fn script_pub_key(version: u8, program: &[u8]) -> String {
+        let op_version = if version == 0 { 0 } else { version + 0x50 };
+        let s = [[op_version, program.len() as u8].as_slice(), program].concat();
+
+        bytes_to_hex_string(&s)
+    }
+
+    macro_rules! valid_segwit {
+        ($addr:literal, $network:expr, $spk:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let (version, program) = segwit_decode($network, $addr).unwrap();
+                assert_eq!(script_pub_key(version, &program), $spk.to_uppercase());
+
+                let encoded = segwit_encode($network, version, &program).unwrap();
+                assert_eq!(encoded, $addr.to_lowercase());
+            }
+        };
+    }
+
+    valid_segwit!(
+        "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+        Network::Mainnet,
+        "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+        valid_segwit_1
+    );
+    valid_segwit!(
+        "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+        Network::Testnet,
+        "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+        valid_segwit_2
+    );
+    valid_segwit!(
+        "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+        Network::Mainnet,
+        "5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6",
+        valid_segwit_3
+    );
+    valid_segwit!("BC1SW50QGDZ25J", Network::Mainnet, "6002751e", valid_segwit_4);
+    valid_segwit!(
+        "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+        Network::Mainnet,
+        "5210751e76e8199196d454941c45d1b3a323",
+        valid_segwit_5
+    );
+    valid_segwit!(
+        "tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy",
+        Network::Testnet,
+        "0020000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+        valid_segwit_6
+    );
+    valid_segwit!(
+        "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c",
+        Network::Testnet,
+        "5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+        valid_segwit_7
+    );
+    valid_segwit!(
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+        Network::Mainnet,
+        "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        valid_segwit_8
+    );
+
+    macro_rules! invalid_segwit {
+        ($addr:literal, $network:expr, $reason:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let res = segwit_decode($network, $addr);
+                assert_eq!(res.err().unwrap().to_string(), $reason);
+            }
+        };
+    }
+
+    invalid_segwit!(
+        "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+        Network::Testnet,
+        "invalid_hrp",
+        invalid_segwit_hrp
+    );
+    invalid_segwit!(
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+        Network::Mainnet,
+        "invalid_variant_for_witness_version",
+        invalid_segwit_bech32_instead_of_bech32m_1
+    );
+    invalid_segwit!(
+        "tb1z0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqglt7rf",
+        Network::Testnet,
+        "invalid_variant_for_witness_version",
+        invalid_segwit_bech32_instead_of_bech32m_2
+    );
+    invalid_segwit!(
+        "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+        Network::Mainnet,
+        "invalid_variant_for_witness_version",
+        invalid_segwit_bech32_instead_of_bech32m_3
+    );
+    invalid_segwit!(
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+        Network::Mainnet,
+        "invalid_variant_for_witness_version",
+        invalid_segwit_bech32m_instead_of_bech32_1
+    );
+    invalid_segwit!(
+        "tb1q0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq24jc47",
+        Network::Testnet,
+        "invalid_variant_for_witness_version",
+        invalid_segwit_bech32m_instead_of_bech32_2
+    );
+    invalid_segwit!(
+        "bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+        Network::Mainnet,
+        "invalid_character",
+        invalid_segwit_character
+    );
+    invalid_segwit!(
+        "BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R",
+        Network::Mainnet,
+        "invalid_witness_version",
+        invalid_segwit_version
+    );
+    invalid_segwit!(
+        "bc1pw5dgrnzv",
+        Network::Mainnet,
+        "invalid_program_length",
+        invalid_segwit_program_too_short
+    );
+    invalid_segwit!(
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav",
+        Network::Mainnet,
+        "invalid_program_length",
+        invalid_segwit_program_too_long
+    );
+    invalid_segwit!(
+        "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+        Network::Mainnet,
+        "invalid_program_length_for_witness_v0",
+        invalid_segwit_program_length_for_v0
+    );
+    invalid_segwit!(
+        "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq",
+        Network::Testnet,
+        "mixed_case",
+        invalid_segwit_mixed_case
+    );
+    invalid_segwit!(
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf",
+        Network::Mainnet,
+        "invalid_padding",
+        invalid_segwit_zero_padding_more_than_4_bits
+    );
+    invalid_segwit!(
+        "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j",
+        Network::Testnet,
+        "invalid_padding",
+        invalid_segwit_non_zero_padding
+    );
+    invalid_segwit!(
+        "bc1gmk9yu",
+        Network::Mainnet,
+        "missing_witness_version",
+        invalid_segwit_empty_data
+    );
+
+    #[test]
+    fn encode_segwit_invalid_version() {
+        let res = segwit_encode(Network::Mainnet, 17, &[0u8; 20]);
+        assert_eq!(res.err().unwrap().to_string(), "invalid_witness_version");
+    }
+}
@@ -13,6 +13,7 @@ pub trait IntegerExtended {
     fn from_little_endian_bytes(bytes: &[u8]) -> Self;
     fn to_little_endian_bytes(&self, length: usize) -> Vec<u8>;
     fn to_vec(&self) -> Vec<u8>;
+    fn zeroize(&mut self);
 }
 
 impl IntegerExtended for Integer {
@@ -57,6 +58,21 @@ impl IntegerExtended for Integer {
     fn to_vec(&self) -> Vec<u8> {
         self.to_digits::<u8>(Order::Lsf)
     }
+
+    /// Overwrite all the allocated limbs with zeros (for secrets), the value becomes 0.
+    /// Copies made by previous operations (e.g. `clone()`) are not affected.
+    fn zeroize(&mut self) {
+        use zeroize::Zeroize;
+
+        // SAFETY: `d` points to `alloc` limbs owned by this Integer (GMP `mpz_t`),
+        // and a `size` of 0 is the canonical representation of 0 whatever the limbs are.
+        unsafe {
+            let raw = self.as_raw_mut();
+            let limbs = std::slice::from_raw_parts_mut((*raw).d.as_ptr(), (*raw).alloc as usize);
+            limbs.zeroize();
+            (*raw).size = 0;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -77,4 +93,17 @@ mod integer_ex_test {
         let bytes = n.to_little_endian_bytes(2);
         assert_eq!(bytes, [0x39, 0x30]);
     }
+
+    #[test]
+    fn zeroize() {
+        let mut n = Integer::from_hex_str("A23B7C91D3E5F10243B5C3A3E8D0F6A9987654321FEDCBA0123456789ABCDEF0");
+        n.zeroize();
+
+        assert_eq!(n, 0);
+        assert!(n.as_limbs().is_empty());
+
+        // still usable
+        n += 5;
+        assert_eq!(n, 5);
+    }
 }
@@ -1,4 +1,6 @@
 pub mod base58;
+pub mod base64;
+pub mod bech32;
 // TODO: 'fixture' should be used in test only: remove with compliation flag '#[cfg(test)]' when data can be read from Bitcoin_rules! database.
 pub mod fixture;
 pub mod integer_extended;
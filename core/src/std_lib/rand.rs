@@ -1,4 +1,9 @@
+use crate::hashing::sha256::sha256;
+
+use super::std_result::StdResult;
+
 // Some other ideas at https://blog.orhun.dev/zero-deps-random-in-rust/
+// Not suitable for secrets: use an `EntropySource` instead.
 pub fn generate_rand_32() -> u32 {
     generate_rand_64() as u32
 }
@@ -9,3 +14,94 @@ pub fn generate_rand_64() -> u64 {
 
     RandomState::new().build_hasher().finish()
 }
+
+/// Source of random bytes for secrets (private keys, mnemonic entropy, ...).
+pub trait EntropySource {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> StdResult<()>;
+}
+
+/// Cryptographically secure random bytes from the operating system.
+#[derive(Debug, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> StdResult<()> {
+        getrandom::fill(dest).map_err(|_| "os_entropy_unavailable")?;
+
+        Ok(())
+    }
+}
+
+/// Reproducible sequence of bytes from a seed: sha256(seed || counter) blocks.
+/// For tests only: NEVER use it for real secrets.
+#[derive(Debug)]
+pub struct DeterministicEntropy {
+    seed: Vec<u8>,
+    counter: u64,
+}
+
+impl DeterministicEntropy {
+    pub fn new(seed: &[u8]) -> DeterministicEntropy {
+        DeterministicEntropy {
+            seed: seed.to_vec(),
+            counter: 0,
+        }
+    }
+}
+
+impl EntropySource for DeterministicEntropy {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> StdResult<()> {
+        for chunk in dest.chunks_mut(32) {
+            let block = sha256(&[self.seed.as_slice(), &self.counter.to_be_bytes()].concat());
+            chunk.copy_from_slice(&block[..chunk.len()]);
+
+            self.counter += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod rand_test {
+    use super::*;
+    use crate::std_lib::vector::bytes_to_hex_string;
+
+    #[test]
+    fn os_entropy() {
+        let mut a = [0u8; 32];
+        let mut b = [0u8; 32];
+
+        OsEntropy.fill_bytes(&mut a).unwrap();
+        OsEntropy.fill_bytes(&mut b).unwrap();
+
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn deterministic_entropy() {
+        let mut entropy = DeterministicEntropy::new(b"bitcoin_rules");
+
+        let mut first = [0u8; 32];
+        entropy.fill_bytes(&mut first).unwrap();
+        assert_eq!(
+            bytes_to_hex_string(&first),
+            "43618508B9039CFB3E7ACC92A1D631F4507842A61D66AB28B5C271D5BB410A7D"
+        );
+
+        let mut second = [0u8; 16];
+        entropy.fill_bytes(&mut second).unwrap();
+        assert_eq!(bytes_to_hex_string(&second), "59BE87BEEB5AC02EC080DD82FE09BAC1");
+    }
+
+    #[test]
+    fn deterministic_entropy_is_reproducible() {
+        let mut a = [0u8; 48];
+        let mut b = [0u8; 48];
+
+        DeterministicEntropy::new(b"seed").fill_bytes(&mut a).unwrap();
+        DeterministicEntropy::new(b"seed").fill_bytes(&mut b).unwrap();
+
+        assert_eq!(a, b);
+    }
+}
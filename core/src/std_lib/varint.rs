@@ -38,23 +38,24 @@ pub fn decode(v: &[u8], from: usize) -> StdResult<VarInt> {
         Err("invalid_from")?;
     }
 
-    let (v, length) = match v[from] {
-        0xFD => {
-            let mut b: [u8; 2] = [0; 2];
-            b.copy_from_slice(&v[(from + 1)..(from + 3)]);
-            (u16::from_le_bytes(b) as u64, 3)
-        } // 2 bytes + 1 byte marker
-        0xFE => {
-            let mut b: [u8; 4] = [0; 4];
-            b.copy_from_slice(&v[(from + 1)..(from + 5)]);
-            (u32::from_le_bytes(b) as u64, 5)
-        } // 4 bytes + 1 byte marker
-        0xFF => {
-            let mut b: [u8; 8] = [0; 8];
-            b.copy_from_slice(&v[(from + 1)..(from + 9)]);
-            (u64::from_le_bytes(b), 9)
-        } // 8 bytes + 1 byte marker
-        value => (value as u64, 1),
+    let length = match v[from] {
+        0xFD => 3, // 2 bytes + 1 byte marker
+        0xFE => 5, // 4 bytes + 1 byte marker
+        0xFF => 9, // 8 bytes + 1 byte marker
+        _ => 1,
+    };
+
+    let bytes = match v.get(from + 1..from + length) {
+        Some(bytes) => bytes,
+        None => {
+            log::error!("varint_decode: missing bytes, from: {}, v len: {}", from, v.len());
+            Err("invalid_length")?
+        }
+    };
+
+    let v = match length {
+        1 => v[from] as u64,
+        _ => bytes.iter().rev().fold(0u64, |value, byte| (value << 8) | *byte as u64),
     };
 
     Ok(VarInt::new(v, length))
@@ -202,4 +203,16 @@ mod tests {
     fn decode_invalid_from() {
         assert_eq!(decode(&vec![0x00], 1).err().unwrap().to_string(), "invalid_from");
     }
+
+    #[test]
+    fn decode_truncated() {
+        assert_eq!(
+            decode(&vec![0xFD, 0xFF], 0).err().unwrap().to_string(),
+            "invalid_length"
+        );
+        assert_eq!(
+            decode(&vec![0xFF, 0x00, 0x00], 0).err().unwrap().to_string(),
+            "invalid_length"
+        );
+    }
 }
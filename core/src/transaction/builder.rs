@@ -0,0 +1,727 @@
+/*
+    Signed transaction from a set of spendable UTXOs, the recipients and a feerate (satoshis per virtual byte):
+        1. coin selection of the UTXOs to spend, see `coin_selection`
+        2. change output back to the change ScriptPubKey, if worth spending (otherwise it goes to fees)
+        3. signature of each input: ScriptSig for P2PK and P2PKH, witness (BIP143) for P2WPKH and P2SH-P2WPKH,
+       key path witness (BIP341, no script tree) for P2TR
+
+    The fee is estimated on the signed size of each input type (worst case, 72 bytes DER signatures).
+    Watch-only UTXOs (without private key, e.g. from `wallet::watch_only`) can only be spent by `build_unsigned`.
+*/
+
+use rug::Integer;
+
+use crate::{
+    descriptor::taproot::tweak_private_key,
+    flags::{network::Network, sighash::SigHash},
+    hashing::hash160::hash160,
+    keys::key::Key,
+    scripting::{script_lang::ScriptLang, standard, token::Token},
+    std_lib::{
+        rand::{EntropySource, OsEntropy},
+        std_result::StdResult,
+        varint::encode,
+    },
+};
+
+use super::{
+    coin_selection::{select_coins, Candidate},
+    script::Script,
+    signing::{generate_input_signature, generate_signature},
+    tx::Tx,
+    tx_in::TxIn,
+    tx_out::TxOut,
+};
+
+const WITNESS_SCALE_FACTOR: u64 = 4;
+
+// Bitcoin Core default dust relay fee: 3000 satoshis per kvB
+const DUST_RELAY_FEERATE: u64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputType {
+    P2pk,
+    P2pkh,
+    // the only P2SH estimated
+    P2shP2wpkh,
+    P2wpkh,
+    // key path spending
+    P2tr,
+}
+
+impl InputType {
+    pub fn from_script_pub_key(script_pub_key: &Script) -> StdResult<Self> {
+        let input_type = match script_pub_key.raw.as_slice() {
+            [0x21, .., 0xAC] if script_pub_key.raw.len() == 35 => InputType::P2pk,
+            [0x41, .., 0xAC] if script_pub_key.raw.len() == 67 => InputType::P2pk,
+            [0x76, 0xA9, 0x14, .., 0x88, 0xAC] if script_pub_key.raw.len() == 25 => InputType::P2pkh,
+            [0xA9, 0x14, .., 0x87] if script_pub_key.raw.len() == 23 => InputType::P2shP2wpkh,
+            [0x00, 0x14, ..] if script_pub_key.raw.len() == 22 => InputType::P2wpkh,
+            [0x51, 0x20, ..] if script_pub_key.raw.len() == 34 => InputType::P2tr,
+            _ => Err("unsupported_script_pub_key")?,
+        };
+
+        Ok(input_type)
+    }
+
+    /*
+        Bytes of the signed input: outpoint (36) + ScriptSig length (1) + ScriptSig + sequence (4), and witness.
+            P2PK        ScriptSig <signature (73)>
+            P2PKH       ScriptSig <signature (73)> <compressed public key (34)>
+            P2SH-P2WPKH ScriptSig <0 <20 bytes hash> (23)>, witness as P2WPKH
+            P2WPKH      witness: items count (1) <signature (73)> <compressed public key (34)>
+            P2TR        witness: items count (1) <schnorr signature (65)>
+    */
+    fn sizes(&self) -> (u64, u64) {
+        match self {
+            InputType::P2pk => (41 + 73, 0),
+            InputType::P2pkh => (41 + 107, 0),
+            InputType::P2shP2wpkh => (41 + 23, 108),
+            InputType::P2wpkh => (41, 108),
+            InputType::P2tr => (41, 66),
+        }
+    }
+
+    pub fn weight(&self) -> u64 {
+        let (size, witness_size) = self.sizes();
+        size * WITNESS_SCALE_FACTOR + witness_size
+    }
+
+    pub fn is_witness(&self) -> bool {
+        self.sizes().1 > 0
+    }
+}
+
+/*
+    Weight of the signed transaction: 4 units per byte, 1 unit per witness byte (BIP141).
+*/
+pub fn estimate_weight(inputs: &[InputType], outputs: &[TxOut]) -> u64 {
+    // version, inputs and outputs count, locktime
+    let overhead = 4 + encode(inputs.len() as u64).len() + encode(outputs.len() as u64).len() + 4;
+    let outputs_size: usize = outputs.iter().map(|o| o.serialize().len()).sum();
+
+    let mut weight = (overhead + outputs_size) as u64 * WITNESS_SCALE_FACTOR;
+    weight += inputs.iter().map(|i| i.weight()).sum::<u64>();
+
+    if inputs.iter().any(|i| i.is_witness()) {
+        // marker and flag, empty witness of the legacy inputs
+        weight += 2 + inputs.iter().filter(|i| !i.is_witness()).count() as u64;
+    }
+
+    weight
+}
+
+pub fn estimate_vsize(inputs: &[InputType], outputs: &[TxOut]) -> u64 {
+    estimate_weight(inputs, outputs).div_ceil(WITNESS_SCALE_FACTOR)
+}
+
+/*
+    Below this amount an output costs more to spend than it is worth (Bitcoin Core `GetDustThreshold`),
+    0 for unspendable outputs (OP_RETURN).
+*/
+pub fn dust_threshold(script_pub_key: &Script) -> u64 {
+    if script_pub_key.raw.first() == Some(&0x6A) {
+        return 0;
+    }
+
+    let output_size = TxOut::new(0, script_pub_key.clone()).serialize().len() as u64;
+
+    // size of the input spending it, witness discounted
+    let spending_size = match script_pub_key.raw.as_slice() {
+        [0x00 | 0x51..=0x60, ..] => 32 + 4 + 1 + 107 / WITNESS_SCALE_FACTOR + 4,
+        _ => 32 + 4 + 1 + 107 + 4,
+    };
+
+    (output_size + spending_size) * DUST_RELAY_FEERATE
+}
+
+#[derive(Clone)]
+pub struct Spendable {
+    pub previous_transaction_id: String,
+    pub previous_transaction_index: u32,
+    pub amount: u64,
+    pub script_pub_key: Script,
+    pub input_type: InputType,
+    // None for a watch-only UTXO (unsigned transactions only)
+    private_key: Option<Integer>,
+}
+
+impl Spendable {
+    pub fn new(
+        previous_transaction_id: &str,
+        previous_transaction_index: u32,
+        amount: u64,
+        script_pub_key: Script,
+        private_key: Integer,
+    ) -> StdResult<Self> {
+        let input_type = InputType::from_script_pub_key(&script_pub_key)?;
+
+        Ok(Self {
+            previous_transaction_id: previous_transaction_id.to_string(),
+            previous_transaction_index,
+            amount,
+            script_pub_key,
+            input_type,
+            private_key: Some(private_key),
+        })
+    }
+
+    pub fn new_watch_only(
+        previous_transaction_id: &str,
+        previous_transaction_index: u32,
+        amount: u64,
+        script_pub_key: Script,
+    ) -> StdResult<Self> {
+        let input_type = InputType::from_script_pub_key(&script_pub_key)?;
+
+        Ok(Self {
+            previous_transaction_id: previous_transaction_id.to_string(),
+            previous_transaction_index,
+            amount,
+            script_pub_key,
+            input_type,
+            private_key: None,
+        })
+    }
+
+    pub fn is_watch_only(&self) -> bool {
+        self.private_key.is_none()
+    }
+}
+
+pub struct TxBuilder {
+    network: Network,
+    // satoshis per virtual byte
+    feerate: u64,
+    utxos: Vec<Spendable>,
+    recipients: Vec<TxOut>,
+    change_script_pub_key: Option<Script>,
+}
+
+impl TxBuilder {
+    pub fn new(network: Network, feerate: u64) -> Self {
+        Self {
+            network,
+            feerate,
+            utxos: vec![],
+            recipients: vec![],
+            change_script_pub_key: None,
+        }
+    }
+
+    pub fn add_utxo(&mut self, utxo: Spendable) {
+        self.utxos.push(utxo);
+    }
+
+    pub fn add_recipient(&mut self, amount: u64, script_pub_key: Script) {
+        self.recipients.push(TxOut::new(amount, script_pub_key));
+    }
+
+    pub fn set_change_script_pub_key(&mut self, script_pub_key: Script) {
+        self.change_script_pub_key = Some(script_pub_key);
+    }
+
+    pub fn build(&self) -> StdResult<Tx> {
+        let (mut tx, inputs) = self.select()?;
+
+        // BIP341 signatures commit to all the spent outputs
+        let spent_outputs: Vec<TxOut> = inputs
+            .iter()
+            .map(|utxo| TxOut::new(utxo.amount, utxo.script_pub_key.clone()))
+            .collect();
+
+        for (input_index, utxo) in inputs.iter().enumerate() {
+            let private_key = match &utxo.private_key {
+                Some(private_key) => private_key,
+                None => Err("missing_private_key")?,
+            };
+
+            match utxo.input_type {
+                InputType::P2pkh => {
+                    let script_sig =
+                        generate_input_signature(&tx, input_index, private_key, utxo.script_pub_key.clone())?;
+                    tx.substitute_script(input_index, script_sig);
+                }
+                InputType::P2pk => {
+                    let sig = generate_signature(&tx, input_index, private_key, utxo.script_pub_key.clone());
+                    let script_sig = ScriptLang::from_tokens(vec![Token::Element(sig)]);
+                    tx.substitute_script(input_index, Script::new_from_script_lang(&script_sig));
+                }
+                InputType::P2wpkh | InputType::P2shP2wpkh => {
+                    let key = Key::new(private_key.clone());
+                    let key_hash = hash160(&key.public_key_sec());
+
+                    if utxo.input_type == InputType::P2shP2wpkh {
+                        // the ScriptSig pushes the redeem script, the P2WPKH ScriptPubKey of the key
+                        let redeem_script = Script::new_from_script_lang(&standard::p2wpkh_script(&key_hash)).raw;
+                        if utxo.script_pub_key.raw[2..22] != hash160(&redeem_script) {
+                            Err("key_does_not_match_script_pub_key")?;
+                        }
+
+                        let script_sig = ScriptLang::from_tokens(vec![Token::Element(redeem_script)]);
+                        tx.substitute_script(input_index, Script::new_from_script_lang(&script_sig));
+                    } else if utxo.script_pub_key.raw[2..] != key_hash {
+                        Err("key_does_not_match_script_pub_key")?;
+                    }
+
+                    let script_code = Script::new_from_script_lang(&standard::p2pkh_script(&key_hash));
+                    let z = tx.hash_signature_witness_v0(input_index, &script_code, utxo.amount);
+                    let signature = [key.sign(z).der(), vec![SigHash::All as u8]].concat();
+
+                    tx.set_witnesses(input_index, vec![signature, key.public_key_sec()]);
+                }
+                InputType::P2tr => {
+                    let tweaked = tweak_private_key(&Key::new(private_key.clone()), None)?;
+                    if utxo.script_pub_key.raw[2..] != tweaked.x_only_public_key() {
+                        Err("key_does_not_match_script_pub_key")?;
+                    }
+
+                    let sighash = tx.hash_signature_taproot(input_index, &spent_outputs, SigHash::Default as u8)?;
+                    let mut aux_rand = [0u8; 32];
+                    OsEntropy.fill_bytes(&mut aux_rand)?;
+
+                    let signature = tweaked.sign_schnorr(&sighash, &aux_rand)?;
+                    tx.set_witnesses(input_index, vec![signature.to_vec()]);
+                }
+            }
+        }
+
+        Ok(tx)
+    }
+
+    // Inputs without ScriptSig
+    pub fn build_unsigned(&self) -> StdResult<Tx> {
+        let (tx, _) = self.select()?;
+        Ok(tx)
+    }
+
+    fn select(&self) -> StdResult<(Tx, Vec<&Spendable>)> {
+        if self.recipients.is_empty() {
+            Err("missing_recipients")?;
+        }
+
+        if self
+            .recipients
+            .iter()
+            .any(|r| r.amount < dust_threshold(&r.script_pub_key))
+        {
+            Err("dust_output")?;
+        }
+
+        let recipients_amount: u64 = self.recipients.iter().map(|r| r.amount).sum();
+
+        let mut base_weight = estimate_weight(&[], &self.recipients);
+        if self.utxos.iter().any(|u| u.input_type.is_witness()) {
+            base_weight += 2;
+        }
+        let target = recipients_amount + self.fee(base_weight);
+
+        // the change output, and the input spending it later
+        let change_script_pub_key = self.change_script_pub_key.clone().unwrap_or_else(Script::new_empty);
+        let change_output = TxOut::new(0, change_script_pub_key.clone());
+        let change_output_fee = self.fee(change_output.serialize().len() as u64 * WITNESS_SCALE_FACTOR);
+        let change_input_type = InputType::from_script_pub_key(&change_script_pub_key).unwrap_or(InputType::P2pkh);
+        let change_spending_fee = self.fee(change_input_type.weight());
+        let cost_of_change = change_output_fee + change_spending_fee;
+        // a smaller change would cost more to spend than it is worth
+        let min_viable_change = dust_threshold(&change_script_pub_key).max(change_spending_fee + 1);
+
+        let candidates: Vec<Candidate> = self
+            .utxos
+            .iter()
+            .map(|u| Candidate::new(u.amount, self.fee(u.input_type.weight())))
+            .collect();
+
+        let selection = select_coins(
+            &candidates,
+            target,
+            cost_of_change,
+            change_output_fee + min_viable_change,
+        )?;
+        let inputs: Vec<&Spendable> = selection.iter().map(|i| &self.utxos[*i]).collect();
+        let input_types: Vec<InputType> = inputs.iter().map(|i| i.input_type).collect();
+        let inputs_amount: u64 = inputs.iter().map(|i| i.amount).sum();
+
+        let mut outputs = self.recipients.clone();
+
+        let fee = self.fee(estimate_weight(&input_types, &outputs));
+        if inputs_amount < recipients_amount + fee {
+            Err("insufficient_funds")?;
+        }
+
+        let with_change = [outputs.clone(), vec![change_output]].concat();
+        let change =
+            (inputs_amount - recipients_amount).checked_sub(self.fee(estimate_weight(&input_types, &with_change)));
+
+        match (change, &self.change_script_pub_key) {
+            (Some(change), Some(script_pub_key)) if change >= min_viable_change => {
+                outputs.push(TxOut::new(change, script_pub_key.clone()));
+            }
+            (Some(change), None) if change >= min_viable_change => Err("missing_change_script_pub_key")?,
+            // the excess goes to fees
+            _ => {}
+        }
+
+        let mut tx = Tx::new(self.network);
+
+        for input in inputs.iter() {
+            tx.add_input(TxIn::new_with_previous_transaction(
+                &input.previous_transaction_id,
+                input.previous_transaction_index,
+                self.network,
+            ));
+        }
+
+        for output in outputs {
+            tx.add_output(output);
+        }
+
+        Ok((tx, inputs))
+    }
+
+    fn fee(&self, weight: u64) -> u64 {
+        (weight * self.feerate).div_ceil(WITNESS_SCALE_FACTOR)
+    }
+}
+
+#[cfg(test)]
+mod builder_test {
+    use rug::Integer;
+
+    use super::*;
+    use crate::{
+        flags::script_flags::ScriptFlags,
+        keys::{schnorr, signature::Signature},
+        std_lib::vector::hex_string_to_bytes,
+        validate::tx::verify_input_script,
+    };
+
+    const FEERATE: u64 = 10;
+
+    fn p2pkh(private_key: u32) -> Script {
+        let key = Key::new(Integer::from(private_key));
+        Script::new_from_script_lang(&standard::p2pkh_script(&hash160(&key.public_key_sec())))
+    }
+
+    fn p2pk(private_key: u32) -> Script {
+        let key = Key::new(Integer::from(private_key));
+        Script::new_from_script_lang(&standard::p2pk_script(&key.public_key_sec()))
+    }
+
+    fn p2wpkh(private_key: u32) -> Script {
+        let key = Key::new(Integer::from(private_key));
+        Script::new_from_script_lang(&standard::p2wpkh_script(&hash160(&key.public_key_sec())))
+    }
+
+    fn p2tr(private_key: u32) -> Script {
+        let tweaked = tweak_private_key(&Key::new(Integer::from(private_key)), None).unwrap();
+        Script::new_from_script_lang(&standard::p2tr_script(&tweaked.x_only_public_key()))
+    }
+
+    fn script(hex: &str) -> Script {
+        Script::new_from_raw(hex_string_to_bytes(hex).unwrap())
+    }
+
+    fn utxo(index: u32, amount: u64, private_key: u32) -> Spendable {
+        Spendable::new(
+            &"AB".repeat(32),
+            index,
+            amount,
+            p2pkh(private_key),
+            Integer::from(private_key),
+        )
+        .unwrap()
+    }
+
+    fn builder(utxos: Vec<Spendable>, recipients: &[u64]) -> TxBuilder {
+        let mut builder = TxBuilder::new(Network::Testnet, FEERATE);
+
+        for utxo in utxos {
+            builder.add_utxo(utxo);
+        }
+
+        for amount in recipients {
+            builder.add_recipient(*amount, p2pkh(100));
+        }
+
+        builder.set_change_script_pub_key(p2pkh(200));
+        builder
+    }
+
+    fn spent_amount(tx: &Tx, utxos: &[(u32, u64)]) -> u64 {
+        (0..tx.input_len())
+            .map(|i| {
+                let index = tx.input(i).unwrap().previous_transaction_index;
+                utxos.iter().find(|(n, _)| *n == index).unwrap().1
+            })
+            .sum()
+    }
+
+    macro_rules! input_type {
+        ($script:expr, $expected:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                assert_eq!(InputType::from_script_pub_key(&$script).unwrap(), $expected);
+            }
+        };
+    }
+
+    input_type!(p2pk(1), InputType::P2pk, input_type_p2pk);
+    input_type!(p2pkh(1), InputType::P2pkh, input_type_p2pkh);
+    input_type!(
+        script("A914748284390F9E263A4B766A75D0633C50426EB87587"),
+        InputType::P2shP2wpkh,
+        input_type_p2sh
+    );
+    input_type!(
+        script("0014751E76E8199196D454941C45D1B3A323F1433BD6"),
+        InputType::P2wpkh,
+        input_type_p2wpkh
+    );
+    input_type!(
+        script("5120A60869F0DBCF1DC659C9CECBAF8050135EA9E8CDC487053F1DC6880949DC684C"),
+        InputType::P2tr,
+        input_type_p2tr
+    );
+
+    #[test]
+    fn input_type_unsupported() {
+        let err = InputType::from_script_pub_key(&script("6A0100")).expect_err("Err");
+        assert_eq!(err.to_string(), "unsupported_script_pub_key");
+    }
+
+    #[test]
+    fn vsize_legacy() {
+        let outputs = vec![TxOut::new(0, p2pkh(1)), TxOut::new(0, p2pkh(2))];
+        assert_eq!(estimate_vsize(&[InputType::P2pkh], &outputs), 226);
+    }
+
+    #[test]
+    fn vsize_segwit() {
+        let p2wpkh = script("0014751E76E8199196D454941C45D1B3A323F1433BD6");
+        let outputs = vec![TxOut::new(0, p2wpkh.clone()), TxOut::new(0, p2wpkh)];
+
+        assert_eq!(estimate_weight(&[InputType::P2wpkh], &outputs), 562);
+        assert_eq!(estimate_vsize(&[InputType::P2wpkh], &outputs), 141);
+        assert_eq!(estimate_vsize(&[InputType::P2tr], &outputs), 130);
+        assert_eq!(
+            estimate_weight(&[InputType::P2wpkh, InputType::P2pkh], &outputs),
+            562 + 592 + 1
+        );
+    }
+
+    #[test]
+    fn dust() {
+        assert_eq!(dust_threshold(&p2pkh(1)), 546);
+        assert_eq!(
+            dust_threshold(&script("0014751E76E8199196D454941C45D1B3A323F1433BD6")),
+            294
+        );
+        assert_eq!(dust_threshold(&script("6A0100")), 0);
+    }
+
+    #[test]
+    fn build_with_change() {
+        let utxos = [(0, 50_000), (1, 30_000), (2, 20_000)];
+        let builder = builder(utxos.iter().map(|(i, a)| utxo(*i, *a, 1)).collect(), &[60_000]);
+
+        let tx = builder.build().unwrap();
+
+        assert_eq!(tx.output_len(), 2);
+        assert_eq!(tx.output(0).unwrap().amount, 60_000);
+        assert_eq!(tx.output(1).unwrap().script_pub_key.raw, p2pkh(200).raw);
+
+        let fee = spent_amount(&tx, &utxos) - tx.output_amount();
+        let vsize = tx.serialize().len() as u64;
+        let input_types = vec![InputType::P2pkh; tx.input_len()];
+        let outputs: Vec<TxOut> = (0..tx.output_len()).map(|i| tx.output(i).unwrap().clone()).collect();
+
+        assert_eq!(fee, estimate_vsize(&input_types, &outputs) * FEERATE);
+        assert!(vsize <= fee / FEERATE);
+        assert!(vsize + tx.input_len() as u64 * 2 >= fee / FEERATE);
+    }
+
+    #[test]
+    fn build_signed() {
+        let utxos = vec![utxo(0, 10_000, 1), utxo(1, 10_000, 2)];
+        let builder = builder(utxos.clone(), &[15_000]);
+
+        let tx = builder.build().unwrap();
+        assert_eq!(tx.input_len(), 2);
+
+        assert_inputs_verified(&tx, &utxos);
+    }
+
+    // The outputs spent by the inputs of `tx`, in the same order
+    fn spent_outputs(tx: &Tx, utxos: &[Spendable]) -> Vec<TxOut> {
+        (0..tx.input_len())
+            .map(|i| {
+                let index = tx.input(i).unwrap().previous_transaction_index;
+                let utxo = utxos.iter().find(|u| u.previous_transaction_index == index).unwrap();
+                TxOut::new(utxo.amount, utxo.script_pub_key.clone())
+            })
+            .collect()
+    }
+
+    fn assert_inputs_verified(tx: &Tx, utxos: &[Spendable]) {
+        let spent_outputs = spent_outputs(tx, utxos);
+
+        for i in 0..tx.input_len() {
+            assert!(verify_input_script(tx, i, &spent_outputs, ScriptFlags::STANDARD).unwrap());
+        }
+    }
+
+    #[test]
+    fn build_signed_p2pk() {
+        let utxo = Spendable::new(&"CD".repeat(32), 0, 10_000, p2pk(3), Integer::from(3)).unwrap();
+        let builder = builder(vec![utxo], &[5_000]);
+
+        let tx = builder.build().unwrap();
+        assert!(verify_input_script(&tx, 0, &[TxOut::new(10_000, p2pk(3))], ScriptFlags::STANDARD).unwrap());
+    }
+
+    #[test]
+    fn build_without_change() {
+        // exact match, fee included: no change (Branch and Bound)
+        let fee = estimate_vsize(&[InputType::P2pkh], &[TxOut::new(0, p2pkh(100))]) * FEERATE;
+        let builder = builder(vec![utxo(0, 50_000 + fee + 100, 1), utxo(1, 80_000, 1)], &[50_000]);
+
+        let tx = builder.build_unsigned().unwrap();
+
+        assert_eq!(tx.input_len(), 1);
+        assert_eq!(tx.input(0).unwrap().previous_transaction_index, 0);
+        assert_eq!(tx.output_len(), 1);
+    }
+
+    #[test]
+    fn build_small_change_to_fees() {
+        let fee = estimate_vsize(&[InputType::P2pkh], &[TxOut::new(0, p2pkh(100))]) * FEERATE;
+        let builder = builder(vec![utxo(0, 50_000 + fee + 1_000, 1)], &[50_000]);
+
+        let tx = builder.build_unsigned().unwrap();
+        assert_eq!(tx.output_len(), 1);
+    }
+
+    #[test]
+    fn build_insufficient_funds() {
+        let builder = builder(vec![utxo(0, 10_000, 1), utxo(1, 5_000, 1)], &[15_000]);
+
+        let err = builder.build().expect_err("Err");
+        assert_eq!(err.to_string(), "insufficient_funds");
+    }
+
+    #[test]
+    fn build_dust_recipient() {
+        let builder = builder(vec![utxo(0, 10_000, 1)], &[500]);
+
+        let err = builder.build().expect_err("Err");
+        assert_eq!(err.to_string(), "dust_output");
+    }
+
+    #[test]
+    fn build_missing_change_script_pub_key() {
+        let mut builder = TxBuilder::new(Network::Testnet, FEERATE);
+        builder.add_utxo(utxo(0, 100_000, 1));
+        builder.add_recipient(10_000, p2pkh(100));
+
+        let err = builder.build().expect_err("Err");
+        assert_eq!(err.to_string(), "missing_change_script_pub_key");
+    }
+
+    // the BIP143 signature and the public key of the witness
+    fn assert_witness_v0_signed(tx: &Tx, input_index: usize, private_key: u32, amount: u64) {
+        let key = Key::new(Integer::from(private_key));
+        let key_hash = hash160(&key.public_key_sec());
+
+        let witnesses = &tx.input(input_index).unwrap().witnesses;
+        assert_eq!(witnesses.len(), 2);
+        assert_eq!(witnesses[1], key.public_key_sec());
+
+        let (der, hash_type) = witnesses[0].split_at(witnesses[0].len() - 1);
+        assert_eq!(hash_type, [SigHash::All as u8]);
+
+        let script_code = Script::new_from_script_lang(&standard::p2pkh_script(&key_hash));
+        let z = tx.hash_signature_witness_v0(input_index, &script_code, amount);
+        assert!(key.verify(&z, &Signature::new_from_der(der.to_vec()).unwrap()));
+    }
+
+    #[test]
+    fn build_signed_p2wpkh() {
+        let utxo = Spendable::new(&"EF".repeat(32), 0, 10_000, p2wpkh(4), Integer::from(4)).unwrap();
+        let builder = builder(vec![utxo.clone()], &[5_000]);
+
+        let tx = builder.build().unwrap();
+
+        assert!(tx.input(0).unwrap().script_sig.raw.is_empty());
+        assert_witness_v0_signed(&tx, 0, 4, 10_000);
+        assert_inputs_verified(&tx, &[utxo]);
+    }
+
+    #[test]
+    fn build_signed_p2sh_p2wpkh() {
+        let redeem_script = p2wpkh(5).raw;
+        let p2sh = Script::new_from_script_lang(&standard::p2sh_script(&hash160(&redeem_script)));
+        let utxo = Spendable::new(&"EF".repeat(32), 1, 10_000, p2sh, Integer::from(5)).unwrap();
+        let builder = builder(vec![utxo.clone()], &[5_000]);
+
+        let tx = builder.build().unwrap();
+
+        assert_eq!(
+            tx.input(0).unwrap().script_sig.raw,
+            [vec![redeem_script.len() as u8], redeem_script].concat()
+        );
+        assert_witness_v0_signed(&tx, 0, 5, 10_000);
+        assert_inputs_verified(&tx, &[utxo]);
+    }
+
+    #[test]
+    fn build_signed_p2tr() {
+        let p2tr = p2tr(6);
+        let utxos = vec![
+            Spendable::new(&"EF".repeat(32), 2, 10_000, p2tr.clone(), Integer::from(6)).unwrap(),
+            utxo(3, 10_000, 1),
+        ];
+        let builder = builder(utxos.clone(), &[15_000]);
+
+        let tx = builder.build().unwrap();
+        assert_inputs_verified(&tx, &utxos);
+
+        let spent_outputs = spent_outputs(&tx, &utxos);
+        for i in 0..tx.input_len() {
+            if spent_outputs[i].script_pub_key.raw != p2tr.raw {
+                continue;
+            }
+
+            let witnesses = &tx.input(i).unwrap().witnesses;
+            assert_eq!(witnesses.len(), 1);
+
+            let sighash = tx
+                .hash_signature_taproot(i, &spent_outputs, SigHash::Default as u8)
+                .unwrap();
+            assert!(schnorr::verify(&p2tr.raw[2..], &sighash, &witnesses[0]));
+        }
+    }
+
+    #[test]
+    fn build_key_does_not_match() {
+        let utxo = Spendable::new(&"EF".repeat(32), 0, 10_000, p2wpkh(4), Integer::from(7)).unwrap();
+        let builder = builder(vec![utxo], &[5_000]);
+
+        assert_eq!(builder.build_unsigned().unwrap().input_len(), 1);
+
+        let err = builder.build().expect_err("Err");
+        assert_eq!(err.to_string(), "key_does_not_match_script_pub_key");
+    }
+
+    #[test]
+    fn build_watch_only() {
+        let utxo = Spendable::new_watch_only(&"AB".repeat(32), 0, 10_000, p2pkh(1)).unwrap();
+        assert!(utxo.is_watch_only());
+
+        let builder = builder(vec![utxo], &[5_000]);
+        assert_eq!(builder.build_unsigned().unwrap().input_len(), 1);
+
+        let err = builder.build().expect_err("Err");
+        assert_eq!(err.to_string(), "missing_private_key");
+    }
+}
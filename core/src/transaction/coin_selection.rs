@@ -0,0 +1,352 @@
+/*
+    Coin selection: which UTXOs to spend to pay a target amount (recipients and fees of the transaction without inputs).
+
+    The amount of each UTXO is its effective value, the value minus the fee to spend it, so that selecting more
+    UTXOs also pays for them.
+    As in Bitcoin Core: Branch and Bound first, looking for a selection without change, then knapsack.
+    Ref. https://github.com/bitcoin/bitcoin/blob/v27.0/src/wallet/coinselection.cpp
+*/
+
+use crate::std_lib::{rand::generate_rand_64, std_result::StdResult};
+
+// Branch and Bound attempts
+const TOTAL_TRIES: usize = 100_000;
+// Knapsack random subsets
+const KNAPSACK_ITERATIONS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub value: u64,
+    // fee to spend it at the target feerate
+    pub fee: u64,
+}
+
+impl Candidate {
+    pub fn new(value: u64, fee: u64) -> Self {
+        Self { value, fee }
+    }
+
+    pub fn effective_value(&self) -> u64 {
+        self.value.saturating_sub(self.fee)
+    }
+}
+
+/*
+    Indexes of the selected `candidates` with an effective value of at least `target`.
+    `cost_of_change` is the cost of adding a change output and spending it later: Branch and Bound looks for a
+    selection exceeding `target` by less than it (the excess goes to fees), otherwise knapsack leaves at least
+    `min_change` for the change.
+*/
+pub fn select_coins(
+    candidates: &[Candidate],
+    target: u64,
+    cost_of_change: u64,
+    min_change: u64,
+) -> StdResult<Vec<usize>> {
+    if let Some(selection) = branch_and_bound(candidates, target, cost_of_change) {
+        log::debug!("Branch and Bound selection: {:?}", selection);
+        return Ok(selection);
+    }
+
+    if let Some(selection) = knapsack(candidates, target, min_change) {
+        log::debug!("Knapsack selection: {:?}", selection);
+        return Ok(selection);
+    }
+
+    Err("insufficient_funds")?
+}
+
+/*
+    Depth first search of the selection with the least excess over `target`, within `target + cost_of_change`.
+    UTXOs are explored from the largest: each one is first included and then excluded.
+*/
+pub fn branch_and_bound(candidates: &[Candidate], target: u64, cost_of_change: u64) -> Option<Vec<usize>> {
+    let pool = spendable_by_value_desc(candidates);
+    let values: Vec<u64> = pool.iter().map(|i| candidates[*i].effective_value()).collect();
+
+    let mut available: u64 = values.iter().sum();
+    if available < target {
+        return None;
+    }
+
+    let mut value: u64 = 0;
+    let mut selection: Vec<usize> = vec![];
+    let mut best: Option<(Vec<usize>, u64)> = None;
+
+    let mut i: usize = 0;
+    for _ in 0..TOTAL_TRIES {
+        let mut backtrack = false;
+
+        if value + available < target || value > target + cost_of_change {
+            backtrack = true;
+        } else if value >= target {
+            let excess = value - target;
+            if best.as_ref().is_none_or(|(_, best_excess)| excess <= *best_excess) {
+                best = Some((selection.clone(), excess));
+            }
+
+            backtrack = true;
+        }
+
+        if backtrack {
+            let Some(&last) = selection.last() else {
+                break;
+            };
+
+            // the UTXOs after the last included are available again, the last included is excluded
+            i -= 1;
+            while i > last {
+                available += values[i];
+                i -= 1;
+            }
+
+            value -= values[i];
+            selection.pop();
+        } else {
+            available -= values[i];
+
+            // excluding a UTXO and including one equal to it is the same selection: skipped
+            if selection.last().is_none_or(|last| *last == i - 1) || values[i] != values[i - 1] {
+                selection.push(i);
+                value += values[i];
+            }
+        }
+
+        i += 1;
+    }
+
+    best.map(|(selection, _)| selection.iter().map(|i| pool[*i]).collect())
+}
+
+/*
+    An exact match, all the smaller UTXOs, or the closest random subset of the smaller ones,
+    unless the smallest UTXO larger than `target + min_change` is closer.
+*/
+pub fn knapsack(candidates: &[Candidate], target: u64, min_change: u64) -> Option<Vec<usize>> {
+    let mut lowest_larger: Option<usize> = None;
+    let mut lower: Vec<usize> = vec![];
+    let mut total_lower: u64 = 0;
+
+    for i in spendable_by_value_desc(candidates) {
+        let value = candidates[i].effective_value();
+
+        if value == target {
+            return Some(vec![i]);
+        }
+
+        if value < target + min_change {
+            lower.push(i);
+            total_lower += value;
+        } else if lowest_larger.is_none_or(|l| value < candidates[l].effective_value()) {
+            lowest_larger = Some(i);
+        }
+    }
+
+    if total_lower == target {
+        return Some(lower);
+    }
+
+    if total_lower < target {
+        return lowest_larger.map(|l| vec![l]);
+    }
+
+    let values: Vec<u64> = lower.iter().map(|i| candidates[*i].effective_value()).collect();
+
+    let (mut best, mut best_value) = approximate_best_subset(&values, total_lower, target);
+    if best_value != target && total_lower >= target + min_change {
+        (best, best_value) = approximate_best_subset(&values, total_lower, target + min_change);
+    }
+
+    if let Some(l) = lowest_larger {
+        let larger_value = candidates[l].effective_value();
+
+        if (best_value != target && best_value < target + min_change) || larger_value <= best_value {
+            return Some(vec![l]);
+        }
+    }
+
+    Some(
+        lower
+            .iter()
+            .zip(best)
+            .filter_map(|(i, included)| included.then_some(*i))
+            .collect(),
+    )
+}
+
+// Subset of `values` (sorted from the largest) with the least total of at least `target`
+fn approximate_best_subset(values: &[u64], total: u64, target: u64) -> (Vec<bool>, u64) {
+    let mut best = vec![true; values.len()];
+    let mut best_value = total;
+
+    let mut random = XorShift::new(generate_rand_64());
+
+    for _ in 0..KNAPSACK_ITERATIONS {
+        if best_value == target {
+            break;
+        }
+
+        let mut included = vec![false; values.len()];
+        let mut value: u64 = 0;
+        let mut reached = false;
+
+        // random UTXOs first, then all the others
+        for pass in 0..2 {
+            if reached {
+                break;
+            }
+
+            for (i, v) in values.iter().enumerate() {
+                let include = match pass {
+                    0 => random.next_bool(),
+                    _ => !included[i],
+                };
+
+                if !include {
+                    continue;
+                }
+
+                value += v;
+                included[i] = true;
+
+                if value >= target {
+                    reached = true;
+
+                    if value < best_value {
+                        best_value = value;
+                        best = included.clone();
+                    }
+
+                    value -= v;
+                    included[i] = false;
+                }
+            }
+        }
+    }
+
+    (best, best_value)
+}
+
+// Indexes of the candidates worth spending, from the largest effective value
+fn spendable_by_value_desc(candidates: &[Candidate]) -> Vec<usize> {
+    let mut pool: Vec<usize> = (0..candidates.len())
+        .filter(|i| candidates[*i].effective_value() > 0)
+        .collect();
+    pool.sort_by_key(|i| std::cmp::Reverse(candidates[*i].effective_value()));
+
+    pool
+}
+
+// Not suitable for secrets
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        Self(seed | 1)
+    }
+
+    fn next_bool(&mut self) -> bool {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        self.0 & 1 == 1
+    }
+}
+
+#[cfg(test)]
+mod coin_selection_test {
+    use super::*;
+
+    fn candidates(values: &[u64]) -> Vec<Candidate> {
+        values.iter().map(|v| Candidate::new(*v, 0)).collect()
+    }
+
+    fn selected_values(candidates: &[Candidate], selection: &[usize]) -> Vec<u64> {
+        let mut values: Vec<u64> = selection.iter().map(|i| candidates[*i].value).collect();
+        values.sort();
+        values
+    }
+
+    macro_rules! branch_and_bound {
+        ($values:expr, $target:expr, $cost_of_change:expr, $expected:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let candidates = candidates(&$values);
+                let selection = branch_and_bound(&candidates, $target, $cost_of_change)
+                    .map(|selection| selected_values(&candidates, &selection));
+
+                assert_eq!(selection, $expected);
+            }
+        };
+    }
+
+    branch_and_bound!([1, 2, 3, 4], 10, 0, Some(vec![1, 2, 3, 4]), bnb_all);
+    branch_and_bound!([1, 2, 4, 8], 5, 0, Some(vec![1, 4]), bnb_exact);
+    branch_and_bound!([4, 6, 9], 8, 0, None, bnb_no_exact_match);
+    branch_and_bound!([4, 6, 9], 8, 1, Some(vec![9]), bnb_within_cost_of_change);
+    branch_and_bound!([4, 6, 9], 8, 2, Some(vec![9]), bnb_least_excess);
+    branch_and_bound!([1, 2, 3, 4], 11, 5, None, bnb_insufficient);
+    branch_and_bound!([5, 5, 5, 5, 5], 15, 0, Some(vec![5, 5, 5]), bnb_equal_values);
+    branch_and_bound!([], 1, 0, None, bnb_empty);
+
+    #[test]
+    fn bnb_effective_value() {
+        // the fee of the second UTXO makes it not worth spending
+        let candidates = vec![
+            Candidate::new(1000, 100),
+            Candidate::new(50, 60),
+            Candidate::new(500, 100),
+        ];
+
+        let selection = branch_and_bound(&candidates, 1300, 0).unwrap();
+        assert_eq!(selected_values(&candidates, &selection), vec![500, 1000]);
+
+        assert_eq!(branch_and_bound(&candidates, 1301, 0), None);
+    }
+
+    macro_rules! knapsack {
+        ($values:expr, $target:expr, $min_change:expr, $expected:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let candidates = candidates(&$values);
+                let selection = knapsack(&candidates, $target, $min_change)
+                    .map(|selection| selected_values(&candidates, &selection));
+
+                assert_eq!(selection, $expected);
+            }
+        };
+    }
+
+    knapsack!([1, 5, 10], 5, 3, Some(vec![5]), knapsack_exact_match);
+    knapsack!([1, 2, 20], 3, 5, Some(vec![1, 2]), knapsack_all_lower);
+    knapsack!([1, 2, 20], 4, 5, Some(vec![20]), knapsack_lowest_larger);
+    knapsack!([1, 2], 4, 5, None, knapsack_insufficient);
+    knapsack!([6, 7, 8, 100], 13, 0, Some(vec![6, 7]), knapsack_best_subset);
+    knapsack!([6, 8, 14], 13, 1, Some(vec![14]), knapsack_lowest_larger_closer);
+
+    #[test]
+    fn knapsack_exact_subset() {
+        let candidates = candidates(&[30, 20, 15, 10, 5, 1000]);
+
+        let selection = knapsack(&candidates, 40, 5).unwrap();
+        let total: u64 = selected_values(&candidates, &selection).iter().sum();
+        assert_eq!(total, 40);
+    }
+
+    #[test]
+    fn select_coins_fallback_to_knapsack() {
+        let candidates = candidates(&[4, 6, 9]);
+
+        let selection = select_coins(&candidates, 8, 0, 0).unwrap();
+        assert_eq!(selected_values(&candidates, &selection), vec![9]);
+    }
+
+    #[test]
+    fn select_coins_insufficient_funds() {
+        let candidates = candidates(&[4, 6, 9]);
+
+        let err = select_coins(&candidates, 20, 0, 0).expect_err("Err");
+        assert_eq!(err.to_string(), "insufficient_funds");
+    }
+}
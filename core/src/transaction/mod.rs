@@ -1,3 +1,6 @@
+pub mod builder;
+pub mod coin_selection;
+pub mod multisig;
 pub mod script;
 pub mod signing;
 pub mod tx;
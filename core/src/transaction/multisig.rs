@@ -0,0 +1,510 @@
+/*
+    m-of-n multisig, the redeem script is `OP_m <public key 1> ... <public key n> OP_n OP_CHECKMULTISIG`:
+        Bare    ScriptPubKey: the redeem script
+                ScriptSig: OP_0 <signature 1> ... <signature m>
+        P2SH    ScriptPubKey: OP_HASH160 <hash160 of the redeem script> OP_EQUAL
+                ScriptSig: OP_0 <signature 1> ... <signature m> <redeem script>
+        P2WSH   ScriptPubKey: OP_0 <sha256 of the redeem script> (witness script)
+                witness: <> <signature 1> ... <signature m> <witness script>
+
+    OP_0 is the dummy element popped by OP_CHECKMULTISIG (it must be empty, BIP147).
+    Signatures are in the same order of their public keys in the script.
+    BIP67 sorts the (compressed) public keys, so that the script does not depend on the order they are given.
+
+    Signatures are collected for each input by `MultisigInput`, from the keys owned or from the other signers.
+*/
+
+use rug::Integer;
+
+use crate::{
+    ecdsa::point::Point,
+    flags::{compression::Compression, network::Network, sighash::SigHash},
+    hashing::{hash160::hash160, sha256::sha256},
+    keys::{key::Key, signature::Signature},
+    scripting::{
+        opcode::{OpCode, OP_0, OP_1, OP_16, OP_CHECKMULTISIG},
+        script_lang::ScriptLang,
+        standard::{p2ms_script, p2sh_script, p2wsh_script},
+        token::Token,
+    },
+    std_lib::{base58::base58_encode_with_checksum, bech32::segwit_encode, std_result::StdResult},
+};
+
+use super::{script::Script, tx::Tx};
+
+// OP_1 ... OP_16
+const MAX_PUBLIC_KEYS: usize = 16;
+
+// P2SH redeem script, pushed by the ScriptSig
+const MAX_REDEEM_SCRIPT_SIZE: usize = 520;
+
+const P2SH_MAINNET: u8 = 0x05;
+const P2SH_TESTNET: u8 = 0xC4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MultisigType {
+    Bare,
+    P2sh,
+    P2wsh,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multisig {
+    m: usize,
+    public_keys: Vec<Vec<u8>>,
+    multisig_type: MultisigType,
+}
+
+impl Multisig {
+    // `public_keys` in the order given (SEC format, compressed or uncompressed)
+    pub fn new(m: usize, public_keys: Vec<Vec<u8>>, multisig_type: MultisigType) -> StdResult<Self> {
+        if public_keys.is_empty() || public_keys.len() > MAX_PUBLIC_KEYS {
+            Err("invalid_multisig_public_keys_count")?;
+        }
+
+        if m == 0 || m > public_keys.len() {
+            Err("invalid_multisig_m")?;
+        }
+
+        for public_key in public_keys.iter() {
+            match public_key.as_slice() {
+                [0x02 | 0x03, ..] if public_key.len() == 33 => {}
+                [0x04, ..] if public_key.len() == 65 => {}
+                _ => Err("invalid_public_key")?,
+            }
+        }
+
+        let multisig = Self {
+            m,
+            public_keys,
+            multisig_type,
+        };
+
+        if multisig_type == MultisigType::P2sh && multisig.redeem_script().raw.len() > MAX_REDEEM_SCRIPT_SIZE {
+            Err("redeem_script_too_large")?;
+        }
+
+        Ok(multisig)
+    }
+
+    // BIP67: compressed public keys only, sorted in lexicographic order
+    pub fn new_sorted(m: usize, mut public_keys: Vec<Vec<u8>>, multisig_type: MultisigType) -> StdResult<Self> {
+        if public_keys.iter().any(|public_key| public_key.len() != 33) {
+            Err("bip67_uncompressed_public_key")?;
+        }
+
+        public_keys.sort();
+
+        Self::new(m, public_keys, multisig_type)
+    }
+
+    // From the redeem script (bare: the ScriptPubKey, P2WSH: the witness script)
+    pub fn from_redeem_script(redeem_script: &Script, multisig_type: MultisigType) -> StdResult<Self> {
+        let tokens = redeem_script.script_lang.tokens();
+
+        let (m, public_keys, n) = match tokens.as_slice() {
+            [Token::Command(m), public_keys @ .., Token::Command(n), Token::Command(OP_CHECKMULTISIG)] => {
+                (small_number(*m)?, public_keys, small_number(*n)?)
+            }
+            _ => Err("not_multisig_script")?,
+        };
+
+        let public_keys: Vec<Vec<u8>> = public_keys
+            .iter()
+            .map(|token| match token {
+                Token::Element(public_key) => Ok(public_key.clone()),
+                _ => Err("not_multisig_script"),
+            })
+            .collect::<Result<_, _>>()?;
+
+        if public_keys.len() != n {
+            Err("not_multisig_script")?;
+        }
+
+        let multisig = Self::new(m, public_keys, multisig_type)?;
+
+        // the script must be the one this multisig would build (e.g. minimal pushes)
+        if multisig.redeem_script().raw != redeem_script.raw {
+            Err("not_multisig_script")?;
+        }
+
+        Ok(multisig)
+    }
+
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    pub fn n(&self) -> usize {
+        self.public_keys.len()
+    }
+
+    pub fn public_keys(&self) -> &[Vec<u8>] {
+        &self.public_keys
+    }
+
+    pub fn multisig_type(&self) -> MultisigType {
+        self.multisig_type
+    }
+
+    pub fn redeem_script(&self) -> Script {
+        let public_keys: Vec<&[u8]> = self.public_keys.iter().map(|p| p.as_slice()).collect();
+
+        Script::new_from_script_lang(&p2ms_script(self.m, &public_keys))
+    }
+
+    pub fn script_pub_key(&self) -> Script {
+        let redeem_script = self.redeem_script();
+
+        match self.multisig_type {
+            MultisigType::Bare => redeem_script,
+            MultisigType::P2sh => Script::new_from_script_lang(&p2sh_script(&hash160(&redeem_script.raw))),
+            MultisigType::P2wsh => Script::new_from_script_lang(&p2wsh_script(&sha256(&redeem_script.raw))),
+        }
+    }
+
+    // Bare multisig has no address
+    pub fn address(&self, network: Network) -> StdResult<String> {
+        let redeem_script = self.redeem_script();
+
+        match self.multisig_type {
+            MultisigType::Bare => Err("bare_multisig_without_address")?,
+            MultisigType::P2sh => {
+                let version = match network {
+                    Network::Mainnet => P2SH_MAINNET,
+                    Network::Testnet => P2SH_TESTNET,
+                };
+
+                let data = [vec![version], hash160(&redeem_script.raw)].concat();
+                Ok(base58_encode_with_checksum(&data))
+            }
+            MultisigType::P2wsh => segwit_encode(network, 0, &sha256(&redeem_script.raw)),
+        }
+    }
+
+    // Position of `public_key` in the script
+    fn position(&self, public_key: &[u8]) -> Option<usize> {
+        self.public_keys.iter().position(|p| p == public_key)
+    }
+
+    // Signature hash of input `input_index`, `amount` is the amount spent (signed by P2WSH only)
+    fn hash_signature(&self, tx: &Tx, input_index: usize, amount: u64) -> Integer {
+        let redeem_script = self.redeem_script();
+
+        match self.multisig_type {
+            MultisigType::Bare | MultisigType::P2sh => tx.hash_signature(input_index, redeem_script),
+            MultisigType::P2wsh => tx.hash_signature_witness_v0(input_index, &redeem_script, amount),
+        }
+    }
+
+    // ScriptSig and witness from `signatures`, m signatures in the order of the public keys
+    pub fn final_scripts(&self, signatures: &[&[u8]]) -> (Script, Vec<Vec<u8>>) {
+        let redeem_script = self.redeem_script();
+
+        let mut tokens = vec![Token::Command(OP_0)];
+        tokens.extend(signatures.iter().map(|s| Token::Element(s.to_vec())));
+
+        match self.multisig_type {
+            MultisigType::Bare => (Script::new_from_script_lang(&ScriptLang::from_tokens(tokens)), vec![]),
+            MultisigType::P2sh => {
+                tokens.push(Token::Element(redeem_script.raw));
+                (Script::new_from_script_lang(&ScriptLang::from_tokens(tokens)), vec![])
+            }
+            MultisigType::P2wsh => {
+                let mut witness: Vec<Vec<u8>> = vec![vec![]];
+                witness.extend(signatures.iter().map(|s| s.to_vec()));
+                witness.push(redeem_script.raw);
+
+                (Script::new_empty(), witness)
+            }
+        }
+    }
+}
+
+// The signatures of an input spending a multisig output
+#[derive(Debug, Clone)]
+pub struct MultisigInput {
+    multisig: Multisig,
+    input_index: usize,
+    amount: u64,
+    // by position of the public key
+    signatures: Vec<Option<Vec<u8>>>,
+}
+
+impl MultisigInput {
+    // `amount` of the output spent
+    pub fn new(multisig: &Multisig, input_index: usize, amount: u64) -> Self {
+        Self {
+            multisig: multisig.clone(),
+            input_index,
+            amount,
+            signatures: vec![None; multisig.n()],
+        }
+    }
+
+    // Signs with `key`, whose public key (compressed or uncompressed) must be in the script
+    pub fn sign(&mut self, tx: &Tx, key: &Key) -> StdResult<()> {
+        let public_key = key.public_key();
+
+        let position = match [Compression::Compressed, Compression::Uncompressed]
+            .into_iter()
+            .find_map(|compression| self.multisig.position(&public_key.serialize(compression)))
+        {
+            Some(position) => position,
+            None => Err("key_not_in_multisig")?,
+        };
+
+        let z = self.multisig.hash_signature(tx, self.input_index, self.amount);
+        let signature = [key.sign(z).der(), vec![SigHash::All as u8]].concat();
+
+        self.signatures[position] = Some(signature);
+
+        Ok(())
+    }
+
+    // Signature of another signer, checked against the public key
+    pub fn add_signature(&mut self, tx: &Tx, public_key: &[u8], signature: &[u8]) -> StdResult<()> {
+        let position = match self.multisig.position(public_key) {
+            Some(position) => position,
+            None => Err("key_not_in_multisig")?,
+        };
+
+        let (der, sighash) = match signature.split_last() {
+            Some((sighash, der)) => (der, *sighash),
+            None => Err("invalid_signature")?,
+        };
+
+        if sighash != SigHash::All as u8 {
+            Err("unsupported_sighash")?;
+        }
+
+        let z = self.multisig.hash_signature(tx, self.input_index, self.amount);
+        let point = Point::deserialize(public_key.to_vec());
+        if !Key::verify_signature(&point, &z, &Signature::new_from_der(der.to_vec())?) {
+            Err("invalid_signature")?;
+        }
+
+        self.signatures[position] = Some(signature.to_vec());
+
+        Ok(())
+    }
+
+    // (public key, signature) of the signatures collected
+    pub fn signatures(&self) -> Vec<(&[u8], &[u8])> {
+        self.multisig
+            .public_keys
+            .iter()
+            .zip(self.signatures.iter())
+            .filter_map(|(public_key, signature)| signature.as_ref().map(|s| (public_key.as_slice(), s.as_slice())))
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signatures().len() >= self.multisig.m
+    }
+
+    // Sets the ScriptSig and the witness of the input with the first m signatures
+    pub fn finalize(&self, tx: &mut Tx) -> StdResult<()> {
+        if !self.is_complete() {
+            Err("multisig_not_enough_signatures")?;
+        }
+
+        let signatures: Vec<&[u8]> = self
+            .signatures()
+            .into_iter()
+            .map(|(_, signature)| signature)
+            .take(self.multisig.m)
+            .collect();
+
+        let (script_sig, witness) = self.multisig.final_scripts(&signatures);
+
+        // out of bounds index
+        tx.input(self.input_index)?;
+        tx.substitute_script(self.input_index, script_sig);
+        tx.set_witnesses(self.input_index, witness);
+
+        Ok(())
+    }
+}
+
+fn small_number(op_code: OpCode) -> StdResult<usize> {
+    if !(OP_1..=OP_16).contains(&op_code) {
+        Err("not_multisig_script")?;
+    }
+
+    Ok((op_code - OP_1 + 1) as usize)
+}
+
+#[cfg(test)]
+mod multisig_test {
+    use rug::Integer;
+
+    use crate::{
+        flags::{network::Network, script_flags::ScriptFlags},
+        keys::key::Key,
+        std_lib::vector::hex_string_to_bytes,
+        transaction::{script::Script, tx::Tx, tx_in::TxIn, tx_out::TxOut},
+        validate::tx::verify_input_script,
+    };
+
+    use super::*;
+
+    fn keys() -> Vec<Key> {
+        (1..=3).map(|k| Key::new(Integer::from(k * 1000))).collect()
+    }
+
+    fn spending_tx() -> Tx {
+        let mut tx = Tx::new(Network::Testnet);
+        tx.add_input(TxIn::new_with_previous_transaction(
+            "9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff",
+            0,
+            Network::Testnet,
+        ));
+        tx.add_output(TxOut::new(90000, Script::new_from_raw(vec![0x51])));
+
+        tx
+    }
+
+    #[test]
+    fn bip67_sorted() {
+        let public_keys = vec![
+            hex_string_to_bytes("02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8").unwrap(),
+            hex_string_to_bytes("02fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f").unwrap(),
+        ];
+
+        let multisig = Multisig::new_sorted(2, public_keys, MultisigType::P2sh).unwrap();
+        assert_eq!(
+            multisig.redeem_script().raw,
+            hex_string_to_bytes("522102fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f2102ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f852ae").unwrap()
+        );
+        assert_eq!(
+            multisig.address(Network::Mainnet).unwrap(),
+            "39bgKC7RFbpoCRbtD5KEdkYKtNyhpsNa3Z"
+        );
+
+        let err = Multisig::new_sorted(1, vec![vec![0x04; 65]], MultisigType::P2sh).expect_err("Err");
+        assert_eq!(err.to_string(), "bip67_uncompressed_public_key");
+    }
+
+    #[test]
+    fn invalid_multisig() {
+        let public_keys: Vec<Vec<u8>> = keys().iter().map(|k| k.public_key_sec()).collect();
+
+        let err = Multisig::new(0, public_keys.clone(), MultisigType::Bare).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_multisig_m");
+
+        let err = Multisig::new(4, public_keys, MultisigType::Bare).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_multisig_m");
+
+        let err = Multisig::new(1, vec![vec![0x02; 33]; 17], MultisigType::Bare).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_multisig_public_keys_count");
+
+        let err = Multisig::new(1, vec![vec![0x05; 33]], MultisigType::Bare).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_public_key");
+
+        // 16 uncompressed keys: 1 + 16 * 66 + 2 bytes
+        let err = Multisig::new(1, vec![vec![0x04; 65]; 16], MultisigType::P2sh).expect_err("Err");
+        assert_eq!(err.to_string(), "redeem_script_too_large");
+    }
+
+    #[test]
+    fn from_redeem_script() {
+        let public_keys: Vec<Vec<u8>> = keys().iter().map(|k| k.public_key_sec()).collect();
+        let multisig = Multisig::new(2, public_keys, MultisigType::P2wsh).unwrap();
+
+        let parsed = Multisig::from_redeem_script(&multisig.redeem_script(), MultisigType::P2wsh).unwrap();
+        assert_eq!(parsed, multisig);
+        assert_eq!((parsed.m(), parsed.n()), (2, 3));
+
+        let err = Multisig::from_redeem_script(&Script::new_from_raw(vec![0x52, 0x51, 0xAE]), MultisigType::Bare)
+            .expect_err("Err");
+        assert_eq!(err.to_string(), "not_multisig_script");
+    }
+
+    #[test]
+    fn sign_and_finalize_bare_and_p2sh() {
+        let keys = keys();
+        let public_keys: Vec<Vec<u8>> = keys.iter().map(|k| k.public_key_sec()).collect();
+
+        for multisig_type in [MultisigType::Bare, MultisigType::P2sh] {
+            let multisig = Multisig::new(2, public_keys.clone(), multisig_type).unwrap();
+            let mut tx = spending_tx();
+
+            let mut input = MultisigInput::new(&multisig, 0, 100000);
+            input.sign(&tx, &keys[2]).unwrap();
+            assert!(!input.is_complete());
+
+            let err = input.finalize(&mut tx).expect_err("Err");
+            assert_eq!(err.to_string(), "multisig_not_enough_signatures");
+
+            input.sign(&tx, &keys[0]).unwrap();
+            assert!(input.is_complete());
+            input.finalize(&mut tx).unwrap();
+
+            // OP_0, then the signatures in the order of the keys
+            let tokens = tx.input(0).unwrap().script_sig.script_lang.tokens();
+            assert_eq!(tokens[1], Token::Element(input.signatures()[0].1.to_vec()));
+
+            let valid = verify_input_script(
+                &tx,
+                0,
+                &[TxOut::new(100000, multisig.script_pub_key())],
+                ScriptFlags::P2SH | ScriptFlags::NULLDUMMY,
+            );
+            assert!(valid.unwrap());
+        }
+    }
+
+    #[test]
+    fn sign_and_finalize_p2wsh() {
+        let keys = keys();
+        let public_keys: Vec<Vec<u8>> = keys.iter().map(|k| k.public_key_sec()).collect();
+        let multisig = Multisig::new_sorted(2, public_keys, MultisigType::P2wsh).unwrap();
+        assert_eq!(multisig.script_pub_key().raw[..2], [0x00, 0x20]);
+        assert!(multisig.address(Network::Testnet).unwrap().starts_with("tb1q"));
+
+        let mut tx = spending_tx();
+
+        // each signer signs its own copy, then the signatures are collected
+        let mut signer = MultisigInput::new(&multisig, 0, 100000);
+        signer.sign(&tx, &keys[1]).unwrap();
+
+        let mut input = MultisigInput::new(&multisig, 0, 100000);
+        input.sign(&tx, &keys[0]).unwrap();
+        for (public_key, signature) in signer.signatures() {
+            input.add_signature(&tx, public_key, signature).unwrap();
+        }
+
+        // signed for another amount
+        let mut other = MultisigInput::new(&multisig, 0, 1);
+        other.sign(&tx, &keys[2]).unwrap();
+        let (public_key, signature) = other.signatures()[0];
+        let err = input.add_signature(&tx, public_key, signature).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_signature");
+
+        input.finalize(&mut tx).unwrap();
+
+        let tx_in = tx.input(0).unwrap();
+        assert!(tx_in.script_sig.raw.is_empty());
+        assert_eq!(tx_in.witnesses.len(), 4);
+        assert!(tx_in.witnesses[0].is_empty());
+        assert_eq!(tx_in.witnesses[3], multisig.redeem_script().raw);
+
+        let spent_outputs = [TxOut::new(100000, multisig.script_pub_key())];
+        assert!(verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD).unwrap());
+
+        // the amount is signed
+        let spent_outputs = [TxOut::new(1, multisig.script_pub_key())];
+        assert!(!verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::WITNESS).unwrap());
+    }
+
+    #[test]
+    fn key_not_in_multisig() {
+        let keys = keys();
+        let multisig = Multisig::new(1, vec![keys[0].public_key_sec()], MultisigType::Bare).unwrap();
+
+        let mut input = MultisigInput::new(&multisig, 0, 0);
+        let err = input.sign(&spending_tx(), &keys[1]).expect_err("Err");
+        assert_eq!(err.to_string(), "key_not_in_multisig");
+    }
+}
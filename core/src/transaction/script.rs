@@ -14,8 +14,11 @@ pub struct Script {
 }
 
 impl Script {
+    /*
+        `raw` is kept as it is: it is what is serialized and signed, even if it has not minimal pushes or a truncated one.
+    */
     pub fn new_from_raw(raw: Vec<u8>) -> Self {
-        let script_lang = ScriptLang::deserialize(&raw, raw.len() as u64, 0).unwrap();
+        let script_lang = ScriptLang::from_raw(&raw);
 
         Script { raw, script_lang }
     }
@@ -39,10 +42,16 @@ impl Script {
         let scriptsig_length = varint_decode(serialized, cur)?;
         cur += scriptsig_length.length;
 
-        let scriptsig_content_serialized = &serialized[cur..cur + scriptsig_length.value as usize];
-        let script_sig = Script::new_from_raw(scriptsig_content_serialized.to_vec());
+        let end = match usize::try_from(scriptsig_length.value)
+            .ok()
+            .and_then(|length| cur.checked_add(length))
+        {
+            Some(end) if end <= serialized.len() => end,
+            _ => Err("script_out_of_bounds")?,
+        };
 
-        cur += scriptsig_length.value as usize;
+        let script_sig = Script::new_from_raw(serialized[cur..end].to_vec());
+        cur = end;
 
         Ok((script_sig, cur))
     }
@@ -59,3 +68,37 @@ impl Display for Script {
         write!(f, "{:}", s)
     }
 }
+
+#[cfg(test)]
+mod script_test {
+    use crate::{scripting::token::Token, std_lib::vector::hex_string_to_bytes};
+
+    use super::*;
+
+    #[test]
+    fn new_from_raw_keeps_raw_bytes() {
+        // PUSHDATA1 for 1 byte: not minimal, re-serialized as it is
+        let raw = hex_string_to_bytes("4c0101").unwrap();
+        let script = Script::new_from_raw(raw.clone());
+
+        assert_eq!(script.script_lang.tokens(), vec![Token::Element(vec![0x01])]);
+        assert_eq!(script.serialize(), [vec![0x03], raw].concat());
+    }
+
+    #[test]
+    fn new_from_raw_truncated() {
+        let raw = hex_string_to_bytes("514c05").unwrap();
+        let script = Script::new_from_raw(raw.clone());
+
+        assert!(script.script_lang.is_truncated());
+        assert_eq!(script.serialize(), [vec![0x03], raw].concat());
+    }
+
+    #[test]
+    fn deserialize_out_of_bounds() {
+        let serialized = hex_string_to_bytes("055151").unwrap();
+
+        let err = Script::deserialize(&serialized, 0).expect_err("Err");
+        assert_eq!(err.to_string(), "script_out_of_bounds");
+    }
+}
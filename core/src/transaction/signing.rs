@@ -15,18 +15,24 @@ pub fn generate_input_signature(
     private_key: &Integer,
     script: Script,
 ) -> StdResult<Script> {
+    let sig = generate_signature(tx, input_index, private_key, script);
+    let sec = Key::new(private_key.clone()).public_key_sec();
+
+    let signature_script = ScriptLang::from_tokens(vec![Token::Element(sig), Token::Element(sec)]);
+
+    Ok(Script::new_from_script_lang(&signature_script))
+}
+
+/*
+    DER signature of input `input_index` followed by the hash type, `script` is the ScriptPubKey it spends.
+*/
+pub fn generate_signature(tx: &Tx, input_index: usize, private_key: &Integer, script: Script) -> Vec<u8> {
     let z = tx.hash_signature(input_index, script);
 
     let key = Key::new(private_key.clone());
     let signature = key.sign(z);
     let der = signature.der();
 
-    let sec = key.public_key_sec();
-
     let hash_type = [SigHash::All as u8].to_vec(); //TODO parametrize SIGHASH
-    let sig = [der, hash_type].concat();
-
-    let signature_script = ScriptLang::from_tokens(vec![Token::Element(sig), Token::Element(sec)]);
-
-    Ok(Script::new_from_script_lang(&signature_script))
+    [der, hash_type].concat()
 }
@@ -6,7 +6,7 @@ use std::fmt::{Display, Formatter};
 
 use crate::{
     flags::{network::Network, sighash::SigHash},
-    hashing::hash256::Hash256,
+    hashing::{hash256::Hash256, sha256::sha256, tagged_hash::tagged_hash},
     std_lib::{std_result::StdResult, varint::encode},
 };
 
@@ -74,10 +74,35 @@ impl Tx {
         self.inputs.substitute_script(index, script);
     }
 
+    pub fn set_witnesses(&mut self, index: usize, witnesses: Vec<Vec<u8>>) {
+        self.inputs.set_witnesses(index, witnesses);
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: u32) {
+        self.version = version;
+    }
+
+    pub fn locktime(&self) -> u32 {
+        self.locktime
+    }
+
+    pub fn set_locktime(&mut self, locktime: u32) {
+        self.locktime = locktime;
+    }
+
     pub fn id(&self) -> String {
         format!("{:064X}", Self::hash(&self.serialize()))
     }
 
+    // As `TxIn::previous_transaction_id`
+    pub fn id_as_integer(&self) -> Integer {
+        Self::hash(&self.serialize())
+    }
+
     pub fn outputs(&self, index: usize) -> &TxOut {
         &self.outputs[index]
     }
@@ -117,18 +142,35 @@ impl Tx {
 
     // TODO: implement with stream
     pub fn deserialize(serialized: &[u8], network: Network) -> StdResult<Self> {
-        if serialized.len() < 5 {
+        let (tx, cursor) = Self::deserialize_from(serialized, 0, network)?;
+
+        // final verification
+        if cursor != serialized.len() {
+            log::error!(
+                "Transaction partially read. Cursor: {:?}, Serialized length: {:?}",
+                cursor,
+                serialized.len()
+            );
+            Err("partially_read_transaction")?;
+        }
+
+        Ok(tx)
+    }
+
+    // The transaction from `from` (e.g. in a block), and the cursor after it
+    pub fn deserialize_from(serialized: &[u8], from: usize, network: Network) -> StdResult<(Self, usize)> {
+        if serialized.len() < from.saturating_add(5) {
             Err("invalid_transaction_length")?;
         }
 
-        let mut cursor: usize = 0;
+        let mut cursor: usize = from;
 
         // Version
         let version = le_bytes_to_u32(serialized, cursor)?;
         cursor += 4;
 
         // Ref: https://en.bitcoin.it/wiki/Protocol_documentation#Message_structure
-        let has_witness = serialized[cursor] == 0x00 && serialized[cursor + 1] == 0x01;
+        let has_witness = serialized.get(cursor..cursor + 2) == Some([0x00, 0x01].as_slice());
         if has_witness {
             cursor += 2;
         }
@@ -162,18 +204,10 @@ impl Tx {
         // TODO: refactor when implementing SegWit
         if has_witness {
             for tx_in in txs_in.iter_mut() {
-                let witness_count = varint_decode(serialized, cursor)?;
-                cursor += witness_count.length;
-
-                for _ in 0..witness_count.value {
-                    let witness_length = varint_decode(serialized, cursor)?;
-                    cursor += witness_length.length;
+                let (witnesses, c) = TxIn::deserialize_witnesses(serialized, cursor)?;
+                cursor = c;
 
-                    let witness = &serialized[cursor..cursor + witness_length.value as usize];
-                    cursor += witness_length.value as usize;
-
-                    tx_in.witnesses.push(witness.to_vec());
-                }
+                tx_in.witnesses = witnesses;
             }
         }
 
@@ -181,27 +215,19 @@ impl Tx {
         let locktime = le_bytes_to_u32(serialized, cursor)?;
         cursor += 4;
 
-        // final verification
-        if cursor != serialized.len() {
-            log::error!(
-                "Transaction partially read. Cursor: {:?}, Serialized length: {:?}",
-                cursor,
-                serialized.len()
-            );
-            Err("partially_read_transaction")?;
-        }
-
         let inputs = TxIns::new(txs_in);
         let outputs = TxOuts::new(txs_out);
 
         // Result transaction
-        Ok(Tx {
+        let tx = Tx {
             version,
             inputs,
             outputs,
             locktime,
             network,
-        })
+        };
+
+        Ok((tx, cursor))
     }
 
     pub fn serialize(&self) -> Vec<u8> {
@@ -223,6 +249,37 @@ impl Tx {
         .concat()
     }
 
+    // BIP144: with marker, flag and the witnesses of each input (as `serialize` if there are none)
+    pub fn serialize_with_witnesses(&self) -> Vec<u8> {
+        if !self.has_witnesses() {
+            return self.serialize();
+        }
+
+        let version_serialized = self.version.to_le_bytes();
+        let inputs_length = encode(self.inputs.len() as u64);
+        let inputs_serialized: Vec<u8> = self.inputs.serialize();
+        let outputs_length = encode(self.outputs.len() as u64);
+        let outputs_serialized: Vec<u8> = self.outputs.serialize();
+        let witnesses_serialized: Vec<u8> = self.inputs.serialize_witnesses();
+        let locktime_serialized = self.locktime.to_le_bytes();
+
+        [
+            version_serialized.as_slice(),
+            &[0x00, 0x01],
+            inputs_length.as_slice(),
+            inputs_serialized.as_slice(),
+            outputs_length.as_slice(),
+            outputs_serialized.as_slice(),
+            witnesses_serialized.as_slice(),
+            locktime_serialized.as_slice(),
+        ]
+        .concat()
+    }
+
+    pub fn has_witnesses(&self) -> bool {
+        (0..self.inputs.len()).any(|i| !self.inputs[i].witnesses.is_empty())
+    }
+
     /*
        Validating a transaction involve validating the signature of each input.
        The signature of each input is calculated as follows:
@@ -258,6 +315,95 @@ impl Tx {
         Integer::from_digits(&tx_hash.0, Order::Msf)
     }
 
+    /*
+       BIP143 signature hash of the SegWit v0 input `input_index`, committing to the amount it spends:
+        1. version
+        2. hash256 of all the outpoints, hash256 of all the sequences
+        3. outpoint and `script_code` (for P2WPKH the P2PKH script of the key hash, for P2WSH the witness script)
+        4. amount and sequence of the input
+        5. hash256 of all the outputs
+        6. locktime and hash type
+    */
+    pub fn hash_signature_witness_v0(&self, input_index: usize, script_code: &Script, amount: u64) -> Integer {
+        let outpoints: Vec<u8> = (0..self.inputs.len())
+            .flat_map(|i| self.inputs[i].serialize_outpoint())
+            .collect();
+        let sequences: Vec<u8> = (0..self.inputs.len())
+            .flat_map(|i| self.inputs[i].sequence.to_le_bytes())
+            .collect();
+
+        let input = &self.inputs[input_index];
+        let hash_type = (SigHash::All as u32).to_le_bytes(); //TODO parametrize SIGHASH
+
+        let preimage = [
+            self.version.to_le_bytes().as_slice(),
+            &Hash256::calc(&outpoints).0,
+            &Hash256::calc(&sequences).0,
+            &input.serialize_outpoint(),
+            &script_code.serialize(),
+            &amount.to_le_bytes(),
+            &input.sequence.to_le_bytes(),
+            &Hash256::calc(&self.outputs.serialize()).0,
+            &self.locktime.to_le_bytes(),
+            &hash_type,
+        ]
+        .concat();
+
+        let tx_hash = Hash256::calc(&preimage);
+
+        Integer::from_digits(&tx_hash.0, Order::Msf)
+    }
+
+    /*
+       BIP341 signature hash of the Taproot key path input `input_index` (SIGHASH_DEFAULT or SIGHASH_ALL, no annex),
+       committing to all the outputs spent by the transaction:
+        tagged_hash("TapSighash", epoch (0x00) || hash type || version || locktime
+            || sha256 of all the outpoints, amounts, ScriptPubKeys and sequences || sha256 of all the outputs
+            || spend type (0x00, key path without annex) || input index)
+    */
+    pub fn hash_signature_taproot(
+        &self,
+        input_index: usize,
+        spent_outputs: &[TxOut],
+        hash_type: u8,
+    ) -> StdResult<Vec<u8>> {
+        if hash_type != SigHash::Default as u8 && hash_type != SigHash::All as u8 {
+            Err("unsupported_sighash_type")?;
+        }
+
+        if input_index >= self.inputs.len() || spent_outputs.len() != self.inputs.len() {
+            Err("invalid_spent_outputs")?;
+        }
+
+        let outpoints: Vec<u8> = (0..self.inputs.len())
+            .flat_map(|i| self.inputs[i].serialize_outpoint())
+            .collect();
+        let amounts: Vec<u8> = spent_outputs.iter().flat_map(|o| o.amount.to_le_bytes()).collect();
+        let script_pub_keys: Vec<u8> = spent_outputs
+            .iter()
+            .flat_map(|o| o.script_pub_key.serialize())
+            .collect();
+        let sequences: Vec<u8> = (0..self.inputs.len())
+            .flat_map(|i| self.inputs[i].sequence.to_le_bytes())
+            .collect();
+
+        let message = [
+            [0x00, hash_type].as_slice(),
+            &self.version.to_le_bytes(),
+            &self.locktime.to_le_bytes(),
+            &sha256(&outpoints),
+            &sha256(&amounts),
+            &sha256(&script_pub_keys),
+            &sha256(&sequences),
+            &sha256(&self.outputs.serialize()),
+            &[0x00],
+            &(input_index as u32).to_le_bytes(),
+        ]
+        .concat();
+
+        Ok(tagged_hash("TapSighash", &message))
+    }
+
     pub fn is_coinbase(&self) -> bool {
         self.inputs.len() == 1 && self.inputs[0].is_coinbase()
     }
@@ -387,6 +533,76 @@ mod tx_test {
         assert_eq!(fee(&transaction).unwrap(), 140500);
     }
 
+    // BIP143 native P2WPKH example
+    pub const SERIALIZED_SEGWIT_UNSIGNED: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+    #[test]
+    fn hash_signature_witness_v0() {
+        let transaction: Vec<u8> = hex_string_to_bytes(SERIALIZED_SEGWIT_UNSIGNED).unwrap();
+        let tx = Tx::deserialize(&transaction, Network::Mainnet).unwrap();
+
+        let script_code =
+            Script::new_from_raw(hex_string_to_bytes("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap());
+        let z = tx.hash_signature_witness_v0(1, &script_code, 600000000);
+
+        assert_eq!(
+            z,
+            Integer::from_hex_str("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670")
+        );
+    }
+
+    #[test]
+    fn hash_signature_taproot() {
+        let transaction: Vec<u8> = hex_string_to_bytes(SERIALIZED_SEGWIT_UNSIGNED).unwrap();
+        let tx = Tx::deserialize(&transaction, Network::Mainnet).unwrap();
+
+        let spent_outputs = [
+            TxOut::new(
+                625000000,
+                Script::new_from_raw(
+                    hex_string_to_bytes("5120A60869F0DBCF1DC659C9CECBAF8050135EA9E8CDC487053F1DC6880949DC684C")
+                        .unwrap(),
+                ),
+            ),
+            TxOut::new(
+                600000000,
+                Script::new_from_raw(hex_string_to_bytes("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap()),
+            ),
+        ];
+
+        assert_eq!(
+            vector::bytes_to_hex_string(&tx.hash_signature_taproot(0, &spent_outputs, 0x00).unwrap()),
+            "883FD8716CA1C04E822342821EABD100E0DC39538F88AE6E519597987A0B6F09"
+        );
+        assert_eq!(
+            vector::bytes_to_hex_string(&tx.hash_signature_taproot(0, &spent_outputs, 0x01).unwrap()),
+            "5F0C0A1152AEB04010BE75B2A7E18590C20E9B5A7CA8464102663DA84EA2DB6D"
+        );
+
+        let err = tx.hash_signature_taproot(0, &spent_outputs, 0x03).expect_err("Err");
+        assert_eq!(err.to_string(), "unsupported_sighash_type");
+
+        let err = tx
+            .hash_signature_taproot(0, &spent_outputs[..1], 0x00)
+            .expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_spent_outputs");
+    }
+
+    #[test]
+    fn deserialize_and_serialize_with_witnesses() {
+        let serialized = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000202aabb01cc11000000";
+        let transaction: Vec<u8> = hex_string_to_bytes(serialized).unwrap();
+
+        let mut tx = Tx::deserialize(&transaction, Network::Mainnet).unwrap();
+        assert!(tx.has_witnesses());
+        assert_eq!(tx.serialize_with_witnesses(), transaction);
+        assert_eq!(tx.serialize(), hex_string_to_bytes(SERIALIZED_SEGWIT_UNSIGNED).unwrap());
+
+        tx.set_witnesses(1, vec![]);
+        assert!(!tx.has_witnesses());
+        assert_eq!(tx.serialize_with_witnesses(), tx.serialize());
+    }
+
     #[test]
     fn new_p2pkh_transaction_one_input_two_outputs_1() {
         let network = Network::Testnet;
@@ -603,7 +819,7 @@ mod tx_test {
 
         tx.add_output(tx_out);
 
-        let script = signing::generate_input_signature(&tx, 0, &key.private_key(), script_pub_key).unwrap();
+        let script = signing::generate_input_signature(&tx, 0, key.private_key(), script_pub_key).unwrap();
         tx.substitute_script(0, script);
 
         let res = vector::bytes_to_hex_string(&tx.serialize());
@@ -4,14 +4,14 @@ use rug::Integer;
 
 use crate::{
     flags::network::Network,
-    std_lib::{integer_extended::IntegerExtended, std_result::StdResult},
+    std_lib::{integer_extended::IntegerExtended, std_result::StdResult, varint::encode},
     transaction::{
         script::Script,
         tx_lib::{integer_to_le_32_bytes, u32_to_le_bytes},
     },
 };
 
-use super::tx_lib::{le_32_bytes_to_integer, le_bytes_to_u32};
+use super::tx_lib::{le_32_bytes_to_integer, le_bytes_to_u32, varint_decode};
 
 static COINBASE_PREVIOUS_TX: u32 = 0;
 static COINBASE_INDEX: u32 = 0xFFFFFFFF;
@@ -116,6 +116,54 @@ impl TxIn {
         .concat()
     }
 
+    // Previous transaction id and index
+    pub fn serialize_outpoint(&self) -> Vec<u8> {
+        let previous_transaction_id_serialized = integer_to_le_32_bytes(&self.previous_transaction_id);
+        let previous_transaction_index_serialized = u32_to_le_bytes(self.previous_transaction_index);
+
+        [
+            previous_transaction_id_serialized.as_slice(),
+            previous_transaction_index_serialized.as_slice(),
+        ]
+        .concat()
+    }
+
+    // Items count, then each item with its length
+    pub fn serialize_witnesses(&self) -> Vec<u8> {
+        let mut serialized = encode(self.witnesses.len() as u64);
+
+        for witness in self.witnesses.iter() {
+            serialized.extend(encode(witness.len() as u64));
+            serialized.extend(witness);
+        }
+
+        serialized
+    }
+
+    // The witness stack, as serialized by `serialize_witnesses`
+    pub fn deserialize_witnesses(serialized: &[u8], cursor: usize) -> StdResult<(Vec<Vec<u8>>, usize)> {
+        let mut cur = cursor;
+
+        let witness_count = varint_decode(serialized, cur)?;
+        cur += witness_count.length;
+
+        let mut witnesses = vec![];
+        for _ in 0..witness_count.value {
+            let witness_length = varint_decode(serialized, cur)?;
+            cur += witness_length.length;
+
+            let witness = match serialized.get(cur..cur.saturating_add(witness_length.value as usize)) {
+                Some(witness) => witness,
+                None => Err("witness_out_of_bounds")?,
+            };
+            cur += witness.len();
+
+            witnesses.push(witness.to_vec());
+        }
+
+        Ok((witnesses, cur))
+    }
+
     pub fn is_coinbase(&self) -> bool {
         self.previous_transaction_id == COINBASE_PREVIOUS_TX && self.previous_transaction_index == COINBASE_INDEX
     }
@@ -33,6 +33,23 @@ impl TxIns {
         inputs.iter().flat_map(|i| i.serialize()).collect()
     }
 
+    // Witnesses of each input, an empty stack for the inputs without witnesses
+    pub fn serialize_witnesses(&self) -> Vec<u8> {
+        let Self(inputs) = self;
+        inputs.iter().flat_map(|i| i.serialize_witnesses()).collect()
+    }
+
+    pub fn set_witnesses(&mut self, index: usize, witnesses: Vec<Vec<u8>>) {
+        let Self(inputs) = self;
+
+        if index >= inputs.len() {
+            log::error!("input_index out of bounds");
+            return;
+        }
+
+        inputs[index].witnesses = witnesses;
+    }
+
     pub fn remove_script(&mut self) {
         let Self(inputs) = self;
 
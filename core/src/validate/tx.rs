@@ -1,18 +1,28 @@
 use rug::Integer;
+use std::collections::{HashMap, VecDeque};
 
 use crate::{
     chain::transaction::get_transaction,
+    descriptor::taproot::{self, LEAF_VERSION_MASK, TAPSCRIPT_LEAF_VERSION},
+    flags::{script_flags::ScriptFlags, sighash::SigHash},
+    hashing::sha256::sha256,
+    keys::schnorr,
     scripting::{
-        context::Context,
+        constants::MAX_SCRIPT_ELEMENT_SIZE,
+        context::{Context, SigVersion, SpendingTx},
+        opcode::OP_CODESEPARATOR,
         script_lang::ScriptLang,
-        standard::{standard_type, StandardType},
+        standard::{p2pkh_script, standard_type, StandardType},
+        token::Token,
     },
     std_lib::std_result::StdResult,
-    transaction::{tx::Tx, tx_out::TxOut},
+    transaction::{script::Script, tx::Tx, tx_in::TxIn, tx_out::TxOut},
 };
 
 const MIN_COINBASE_LENGTH: usize = 2;
 const MAX_COINBASE_LENGTH: usize = 100;
+// BIP341: first byte of the optional last witness element (annex)
+const ANNEX_TAG: u8 = 0x50;
 
 #[derive(Debug, Clone)]
 pub struct Output {
@@ -27,60 +37,349 @@ pub struct AnalysisResult {
     pub outputs: Vec<Output>,
 }
 
-fn verify_input(tx: &Tx, input_index: usize) -> StdResult<bool> {
+fn verify_input(tx: &Tx, input_index: usize, flags: ScriptFlags) -> StdResult<bool> {
     if tx.input_len() <= input_index {
         Err("input_index_out_of_bounds")?;
     }
 
+    verify_input_script(tx, input_index, &spent_outputs(tx)?, flags)
+}
+
+// The outputs spent by the inputs of `tx`, in the same order
+fn spent_outputs(tx: &Tx) -> StdResult<Vec<TxOut>> {
+    let mut outputs = Vec::new();
+
+    for i in 0..tx.input_len() {
+        let input_transaction = tx.input(i)?;
+        let previous_transaction = match get_transaction(&input_transaction.previous_transaction_id, tx.network) {
+            Ok(tx) => tx,
+            Err(_e) => Err("transaction_not_found_in_chain")?,
+        };
+
+        let output_index = input_transaction.previous_transaction_index as usize;
+        if previous_transaction.output_len() <= output_index {
+            Err("output_index_out_of_bounds")?;
+        }
+
+        outputs.push(previous_transaction.output(output_index)?.clone());
+    }
+
+    Ok(outputs)
+}
+
+/*
+    Verify the ScriptSig (and the witness) of input `input_index` against the ScriptPubKey of the output it spends,
+    without looking up the previous transactions: `spent_outputs` are the outputs spent by all the inputs of `tx`
+    (the amounts are signed by SegWit inputs, all of them by Taproot inputs).
+*/
+pub fn verify_input_script(
+    tx: &Tx,
+    input_index: usize,
+    spent_outputs: &[TxOut],
+    flags: ScriptFlags,
+) -> StdResult<bool> {
     let input_transaction = tx.input(input_index)?;
-    let previous_transaction = match get_transaction(&input_transaction.previous_transaction_id, tx.network) {
-        Ok(tx) => tx,
-        Err(_e) => Err("transaction_not_found_in_chain")?,
-    };
+    if spent_outputs.len() != tx.input_len() {
+        Err("invalid_spent_outputs")?;
+    }
 
+    let spent_script_pub_key = &spent_outputs[input_index].script_pub_key;
     let script_sig = &input_transaction.script_sig.script_lang;
+    let script_pub_key = &spent_script_pub_key.script_lang;
+
+    let z = tx.hash_signature(input_index, spent_script_pub_key.clone());
+    let mut context = Context::new_with_flags(script_sig.tokens(), z, flags);
+    context.set_spending_tx(spending_tx(tx, input_index)?);
+    context.set_codeseparator_z(legacy_codeseparator_z(tx, input_index, script_sig));
+
+    // ScriptSig and ScriptPubKey are evaluated one after the other (each one with its own limits) on the same stack
+    evaluate_script(script_sig, &mut context)?;
+    let script_sig_stack = context.stack().clone();
 
-    let output_index = input_transaction.previous_transaction_index as usize;
-    if previous_transaction.output_len() <= output_index {
-        Err("output_index_out_of_bounds")?;
+    context.load_script(script_pub_key.tokens());
+    context.set_codeseparator_z(legacy_codeseparator_z(tx, input_index, script_pub_key));
+    if !evaluate_script(script_pub_key, &mut context)? {
+        return Ok(false);
     }
 
-    let output_transaction = previous_transaction.output(output_index)?;
+    if flags.contains(ScriptFlags::WITNESS) {
+        if let Some((version, program)) = script_pub_key.witness_program() {
+            // BIP141: the witness only, a ScriptSig could be changed by anyone
+            if !script_sig.tokens().is_empty() {
+                Err("witness_malleated")?;
+            }
 
-    let script_pub_key = &output_transaction.script_pub_key.script_lang;
+            return verify_witness_program(tx, input_index, spent_outputs, version, &program, false, flags);
+        }
+    }
 
-    let z = tx.hash_signature(input_index, output_transaction.script_pub_key.clone());
-    let complete_script = ScriptLang::combine(script_sig.clone(), script_pub_key.clone());
+    if flags.contains(ScriptFlags::P2SH) && script_pub_key.is_pay_to_script_hash() {
+        return verify_redeem_script(
+            tx,
+            input_index,
+            spent_outputs,
+            script_sig,
+            script_sig_stack,
+            &mut context,
+        );
+    }
 
-    let mut context = Context::new(complete_script.tokens(), z);
+    check_no_witness(input_transaction, flags)?;
 
-    match complete_script.evaluate(&mut context) {
-        Err(e) => {
-            log::debug!("Script error: {:?}", e);
-            Err("script_error")?
+    Ok(true)
+}
+
+/*
+    BIP16: the ScriptPubKey only checks the hash of the redeem script, the last element pushed by the ScriptSig.
+    The redeem script is then evaluated on the other elements (and signed in place of the ScriptPubKey).
+    BIP141: a redeem script can be a witness program (P2SH-P2WPKH, P2SH-P2WSH).
+*/
+fn verify_redeem_script(
+    tx: &Tx,
+    input_index: usize,
+    spent_outputs: &[TxOut],
+    script_sig: &ScriptLang,
+    mut stack: VecDeque<Token>,
+    context: &mut Context,
+) -> StdResult<bool> {
+    if !script_sig.is_push_only() {
+        Err("script_sig_not_push_only")?;
+    }
+
+    let redeem_script = match stack.pop_front() {
+        Some(Token::Element(redeem_script)) => redeem_script,
+        _ => Err("missing_redeem_script")?,
+    };
+    // signed as it is: a redeem script with a truncated push fails only when evaluated
+    let redeem_script = Script::new_from_raw(redeem_script);
+
+    context.z = tx.hash_signature(input_index, redeem_script.clone());
+    context.set_stack(stack);
+    context.load_script(redeem_script.script_lang.tokens());
+    context.set_codeseparator_z(legacy_codeseparator_z(tx, input_index, &redeem_script.script_lang));
+
+    if !evaluate_script(&redeem_script.script_lang, context)? {
+        return Ok(false);
+    }
+
+    let flags = context.flags();
+    if flags.contains(ScriptFlags::WITNESS) {
+        if let Some((version, program)) = redeem_script.script_lang.witness_program() {
+            // the ScriptSig must be the push of the redeem script only
+            if script_sig.tokens() != vec![Token::Element(redeem_script.raw.clone())] {
+                Err("witness_malleated_p2sh")?;
+            }
+
+            return verify_witness_program(tx, input_index, spent_outputs, version, &program, true, flags);
         }
-        Ok(val) => Ok(val),
     }
+
+    check_no_witness(tx.input(input_index)?, flags)?;
+
+    Ok(true)
 }
 
-pub fn fee(tx: &Tx) -> StdResult<i128> {
-    let mut input_amount: i128 = 0;
+// BIP141: an input not spending a witness program can not have a witness
+fn check_no_witness(input: &TxIn, flags: ScriptFlags) -> StdResult<()> {
+    if flags.contains(ScriptFlags::WITNESS) && !input.witnesses.is_empty() {
+        Err("witness_unexpected")?;
+    }
 
-    for i in 0..tx.input_len() {
-        let input_transaction = tx.input(i)?;
-        let previous_transaction = match get_transaction(&input_transaction.previous_transaction_id, tx.network) {
-            Ok(tx) => tx,
-            Err(_e) => Err("transaction_not_found_in_chain")?,
-        };
+    Ok(())
+}
 
-        let output_index = input_transaction.previous_transaction_index as usize;
-        if previous_transaction.output_len() <= output_index {
-            Err("output_index_out_of_bounds")?;
+/*
+    BIP141: the witness of the input unlocks the witness program of the spent output.
+        v0, 20 bytes (P2WPKH)   the witness is [signature, public key], checked as P2PKH of the program
+        v0, 32 bytes (P2WSH)    the last witness element is the script (its sha256 is the program), run on the others
+        v1, 32 bytes (P2TR)     BIP341, only if not in P2SH
+    Any other program is left to future soft forks (anyone can spend it): it is not verified, so it is an error.
+*/
+fn verify_witness_program(
+    tx: &Tx,
+    input_index: usize,
+    spent_outputs: &[TxOut],
+    version: u8,
+    program: &[u8],
+    is_p2sh: bool,
+    flags: ScriptFlags,
+) -> StdResult<bool> {
+    let witness = &tx.input(input_index)?.witnesses;
+
+    match (version, program.len()) {
+        (0, 20) => {
+            if witness.len() != 2 {
+                Err("witness_program_mismatch")?;
+            }
+
+            let script = Script::new_from_script_lang(&p2pkh_script(program));
+            execute_witness_script(tx, input_index, spent_outputs, &script, witness, flags)
+        }
+        (0, 32) => {
+            let (witness_script, stack) = match witness.split_last() {
+                Some(split) => split,
+                None => Err("witness_program_witness_empty")?,
+            };
+
+            if sha256(witness_script) != program {
+                Err("witness_program_mismatch")?;
+            }
+
+            let script = Script::new_from_raw(witness_script.clone());
+            execute_witness_script(tx, input_index, spent_outputs, &script, stack, flags)
+        }
+        (0, _) => Err("witness_program_wrong_length")?,
+        (1, 32) if !is_p2sh && flags.contains(ScriptFlags::TAPROOT) => {
+            verify_taproot(tx, input_index, spent_outputs, program, witness)
+        }
+        _ => Err("witness_program_not_supported")?,
+    }
+}
+
+/*
+    SegWit v0 script (P2PKH of the key hash, or the witness script) with the witness elements as the initial stack,
+    signed as BIP143. It must leave a single true element.
+*/
+fn execute_witness_script(
+    tx: &Tx,
+    input_index: usize,
+    spent_outputs: &[TxOut],
+    script: &Script,
+    stack: &[Vec<u8>],
+    flags: ScriptFlags,
+) -> StdResult<bool> {
+    if stack.iter().any(|element| element.len() > MAX_SCRIPT_ELEMENT_SIZE) {
+        Err("element_size_exceeded")?;
+    }
+
+    let amount = spent_outputs[input_index].amount;
+    let z = tx.hash_signature_witness_v0(input_index, script, amount);
+    let mut context = Context::new_with_flags(script.script_lang.tokens(), z, flags);
+    context.set_spending_tx(spending_tx(tx, input_index)?);
+    context.set_sig_version(SigVersion::WitnessV0);
+    context.set_codeseparator_z(codeseparator_z(&script.script_lang, false, |script_code| {
+        tx.hash_signature_witness_v0(input_index, &script_code, amount)
+    }));
+    // the first witness element is the bottom of the stack
+    context.set_stack(
+        stack
+            .iter()
+            .rev()
+            .map(|element| Token::Element(element.clone()))
+            .collect(),
+    );
+
+    let valid = evaluate_script(&script.script_lang, &mut context)?;
+
+    // checked before the result, as Bitcoin Core
+    if context.stack().len() != 1 {
+        Err("witness_clean_stack")?;
+    }
+
+    Ok(valid)
+}
+
+/*
+    BIP341: a single witness element is the signature of the output key (key path), otherwise the last two are
+    the control block and a script of the tree committed in the output key (script path).
+    The annex and the script path (tapscript, BIP342) are not supported: they are errors, not valid spends.
+*/
+fn verify_taproot(
+    tx: &Tx,
+    input_index: usize,
+    spent_outputs: &[TxOut],
+    output_key: &[u8],
+    witness: &[Vec<u8>],
+) -> StdResult<bool> {
+    if witness.len() >= 2 && witness[witness.len() - 1].first() == Some(&ANNEX_TAG) {
+        Err("taproot_annex_not_supported")?;
+    }
+
+    match witness {
+        [] => Err("witness_program_witness_empty")?,
+        [signature] => {
+            // 64 bytes for SIGHASH_DEFAULT, the hash type is appended otherwise (and it can not be 0x00)
+            let hash_type = match signature.len() {
+                64 => SigHash::Default as u8,
+                65 if signature[64] != SigHash::Default as u8 => signature[64],
+                _ => Err("invalid_schnorr_signature")?,
+            };
+
+            let sighash = tx.hash_signature_taproot(input_index, spent_outputs, hash_type)?;
+            if !schnorr::verify(output_key, &sighash, &signature[..64]) {
+                Err("invalid_schnorr_signature")?;
+            }
+
+            Ok(true)
         }
+        [.., script, control_block] => {
+            if !taproot::verify_script_path(output_key, control_block, script)? {
+                Err("witness_program_mismatch")?;
+            }
+
+            // unknown leaf versions are left to future soft forks, as the other witness programs
+            let error = if control_block[0] & LEAF_VERSION_MASK == TAPSCRIPT_LEAF_VERSION {
+                "tapscript_not_supported"
+            } else {
+                "taproot_leaf_version_not_supported"
+            };
 
-        let output_transaction = previous_transaction.output(output_index)?;
-        input_amount += output_transaction.amount as i128;
+            Err(error)?
+        }
     }
+}
+
+fn legacy_codeseparator_z(tx: &Tx, input_index: usize, script: &ScriptLang) -> HashMap<usize, Integer> {
+    codeseparator_z(script, true, |script_code| tx.hash_signature(input_index, script_code))
+}
+
+/*
+    z of the script after each OP_CODESEPARATOR of `script`, by its position: the script signed (scriptCode) starts
+    after the last OP_CODESEPARATOR executed. Legacy scripts are signed without the other OP_CODESEPARATORs.
+*/
+fn codeseparator_z(script: &ScriptLang, legacy: bool, hash: impl Fn(Script) -> Integer) -> HashMap<usize, Integer> {
+    let tokens = script.tokens();
+    let codeseparator = Token::Command(OP_CODESEPARATOR);
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| **token == codeseparator)
+        .map(|(position, _)| {
+            let script_code: Vec<Token> = tokens[position + 1..]
+                .iter()
+                .filter(|token| !legacy || **token != codeseparator)
+                .cloned()
+                .collect();
+
+            (
+                position,
+                hash(Script::new_from_script_lang(&ScriptLang::from_tokens(script_code))),
+            )
+        })
+        .collect()
+}
+
+fn spending_tx(tx: &Tx, input_index: usize) -> StdResult<SpendingTx> {
+    Ok(SpendingTx {
+        version: tx.version(),
+        locktime: tx.locktime(),
+        sequence: tx.input(input_index)?.sequence,
+    })
+}
+
+fn evaluate_script(script: &ScriptLang, context: &mut Context) -> StdResult<bool> {
+    match script.evaluate(context) {
+        Err(e) => {
+            log::debug!("Script error: {:?}", e);
+            Err(e)
+        }
+        Ok(val) => Ok(val),
+    }
+}
+
+pub fn fee(tx: &Tx) -> StdResult<i128> {
+    let input_amount: i128 = spent_outputs(tx)?.iter().map(|output| output.amount as i128).sum();
 
     let mut output_amount: i128 = 0;
 
@@ -120,8 +419,22 @@ pub fn verify_coinbase(tx: &Tx) -> bool {
     When all validations are implemented, this function will be refactored.
 
     Refer to [Protocol rules](https://en.bitcoin.it/wiki/Protocol_rules) for complete list of rules.
+
+    Scripts are verified without any of the rules added by soft forks (as the first blocks of the chain):
+    see `analyze_at_height` for a transaction included in a block, `analyze_with_flags` with `ScriptFlags::STANDARD` for a new one.
 */
 pub fn analyze(tx: &Tx) -> StdResult<AnalysisResult> {
+    analyze_with_flags(tx, ScriptFlags::NONE)
+}
+
+/*
+    Analyze and validate a transaction included in the block at `height`, with the consensus rules in force there.
+*/
+pub fn analyze_at_height(tx: &Tx, height: u32) -> StdResult<AnalysisResult> {
+    analyze_with_flags(tx, ScriptFlags::consensus(tx.network, height))
+}
+
+pub fn analyze_with_flags(tx: &Tx, flags: ScriptFlags) -> StdResult<AnalysisResult> {
     let mut tx_fee: i128 = 0;
 
     if tx.is_coinbase() {
@@ -142,8 +455,9 @@ pub fn analyze(tx: &Tx) -> StdResult<AnalysisResult> {
         }
 
         // * The ScriptSig in the input successfully unlocks the previous ScriptPubKey of the outputs.
+        let spent_outputs = spent_outputs(tx)?;
         for i in 0..tx.input_len() {
-            if !verify_input(tx, i)? {
+            if !verify_input_script(tx, i, &spent_outputs, flags)? {
                 Err("script_verification_failed")?;
             }
         }
@@ -200,7 +514,14 @@ mod verification_test {
     use rug::Integer;
 
     use crate::{
-        chain::transaction::get_transaction, flags::network::Network, scripting::token::Token,
+        chain::transaction::get_transaction,
+        flags::network::Network,
+        keys::key::Key,
+        scripting::{
+            opcode::{OP_1, OP_2, OP_CHECKSIG, OP_ENDIF, OP_IF},
+            standard::p2wsh_script,
+            token::Token,
+        },
         std_lib::integer_extended::IntegerExtended,
     };
 
@@ -212,7 +533,7 @@ mod verification_test {
             Integer::from_hex_str("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16");
         let satoshi_transaction = get_transaction(&satoshi_transaction_id, Network::Mainnet).unwrap();
 
-        let res = verify_input(satoshi_transaction, 0).unwrap();
+        let res = verify_input(satoshi_transaction, 0, ScriptFlags::NONE).unwrap();
         assert!(res);
     }
 
@@ -222,7 +543,7 @@ mod verification_test {
             Integer::from_hex_str("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16");
         let transaction = get_transaction(&transaction_id, Network::Mainnet).unwrap();
 
-        let res = verify_input(transaction, 1);
+        let res = verify_input(transaction, 1, ScriptFlags::NONE);
         assert_eq!("input_index_out_of_bounds", res.expect_err("Err").to_string());
     }
 
@@ -291,6 +612,26 @@ mod verification_test {
         assert_eq!(res.outputs[0].standard, StandardType::P2pkh);
     }
 
+    #[test]
+    fn verify_first_transaction_ever_at_its_height() {
+        let transaction_id: Integer =
+            Integer::from_hex_str("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16");
+        let transaction = get_transaction(&transaction_id, Network::Mainnet).unwrap();
+
+        let res = analyze_at_height(transaction, 170).unwrap();
+
+        assert!(res.valid);
+    }
+
+    #[test]
+    fn verify_transaction_p2pkh_with_current_rules() {
+        let transaction_id: Integer =
+            Integer::from_hex_str("c843441a5e6d6a3b47a686cafa862951d649fea242f016d486dc20d74fa9f61c");
+        let transaction = get_transaction(&transaction_id, Network::Testnet).unwrap();
+
+        assert!(analyze_at_height(transaction, u32::MAX).unwrap().valid);
+    }
+
     #[test]
     fn verify_transaction_is_coinbase() {
         let transaction_id: Integer =
@@ -326,4 +667,121 @@ mod verification_test {
             std::str::from_utf8(bytes).unwrap()
         );
     }
+
+    // A transaction spending a single output with `script_sig` and `witnesses`
+    fn spending(script_sig: Script, witnesses: Vec<Vec<u8>>) -> Tx {
+        let mut tx = Tx::new(Network::Testnet);
+        tx.add_input(TxIn::new(Integer::from(1), 0, script_sig, 0xFFFFFFFF, Network::Testnet));
+        tx.add_output(TxOut::new(1000, Script::new_empty()));
+        tx.set_witnesses(0, witnesses);
+
+        tx
+    }
+
+    #[test]
+    fn verify_p2wsh_input() {
+        let witness_script = vec![OP_1 as u8];
+        let spent_outputs = [TxOut::new(
+            2000,
+            Script::new_from_script_lang(&p2wsh_script(&sha256(&witness_script))),
+        )];
+
+        let tx = spending(Script::new_empty(), vec![witness_script.clone()]);
+        assert!(verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD).unwrap());
+
+        let tx = spending(Script::new_empty(), vec![vec![OP_2 as u8]]);
+        let res = verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD);
+        assert_eq!("witness_program_mismatch", res.expect_err("Err").to_string());
+
+        let tx = spending(Script::new_empty(), vec![]);
+        let res = verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD);
+        assert_eq!("witness_program_witness_empty", res.expect_err("Err").to_string());
+
+        // the witness script must leave a single element
+        let tx = spending(Script::new_empty(), vec![vec![0x01], witness_script.clone()]);
+        let res = verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD);
+        assert_eq!("witness_clean_stack", res.expect_err("Err").to_string());
+
+        // a ScriptSig is not allowed for a native witness program
+        let tx = spending(Script::new_from_raw(vec![OP_1 as u8]), vec![witness_script]);
+        let res = verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD);
+        assert_eq!("witness_malleated", res.expect_err("Err").to_string());
+    }
+
+    // the signature of a witness script after OP_CODESEPARATOR signs only the rest of the script (BIP143)
+    #[test]
+    fn verify_p2wsh_input_with_codeseparator() {
+        let key = Key::new(Integer::from(12345));
+        let script_code = [vec![0x21], key.public_key_sec(), vec![OP_CHECKSIG as u8]].concat();
+        let witness_script = [vec![OP_CODESEPARATOR as u8], script_code.clone()].concat();
+        let spent_outputs = [TxOut::new(
+            2000,
+            Script::new_from_script_lang(&p2wsh_script(&sha256(&witness_script))),
+        )];
+
+        for (signed_script, valid) in [(script_code, true), (witness_script.clone(), false)] {
+            let mut tx = spending(Script::new_empty(), vec![]);
+            let z = tx.hash_signature_witness_v0(0, &Script::new_from_raw(signed_script), 2000);
+            let signature = [key.sign(z).der(), vec![SigHash::All as u8]].concat();
+            tx.set_witnesses(0, vec![signature, witness_script.clone()]);
+
+            assert_eq!(
+                verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::WITNESS).unwrap(),
+                valid
+            );
+        }
+    }
+
+    // MINIMALIF applies to the witness script only, a legacy script takes any true argument
+    #[test]
+    fn verify_p2wsh_input_minimal_if() {
+        let witness_script = vec![OP_IF as u8, OP_1 as u8, OP_ENDIF as u8];
+        let spent_outputs = [TxOut::new(
+            2000,
+            Script::new_from_script_lang(&p2wsh_script(&sha256(&witness_script))),
+        )];
+
+        let tx = spending(Script::new_empty(), vec![vec![0x01], witness_script.clone()]);
+        assert!(verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD).unwrap());
+
+        let tx = spending(Script::new_empty(), vec![vec![0x02], witness_script.clone()]);
+        let res = verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD);
+        assert_eq!("minimal_if", res.expect_err("Err").to_string());
+
+        let spent_outputs = [TxOut::new(2000, Script::new_from_raw(witness_script))];
+        let tx = spending(Script::new_from_raw(vec![0x02, 0x02, 0x00]), vec![]);
+        assert!(verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD).unwrap());
+    }
+
+    #[test]
+    fn verify_input_with_unexpected_witness() {
+        let spent_outputs = [TxOut::new(2000, Script::new_from_raw(vec![OP_1 as u8]))];
+        let tx = spending(Script::new_empty(), vec![vec![0x01]]);
+
+        assert!(verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::NONE).unwrap());
+
+        let res = verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::WITNESS);
+        assert_eq!("witness_unexpected", res.expect_err("Err").to_string());
+    }
+
+    #[test]
+    fn verify_input_with_unknown_witness_program() {
+        // version 2, left to future soft forks
+        let spent_outputs = [TxOut::new(
+            2000,
+            Script::new_from_raw([vec![OP_2 as u8, 32], vec![0x01; 32]].concat()),
+        )];
+        let tx = spending(Script::new_empty(), vec![]);
+
+        let res = verify_input_script(&tx, 0, &spent_outputs, ScriptFlags::STANDARD);
+        assert_eq!("witness_program_not_supported", res.expect_err("Err").to_string());
+    }
+
+    #[test]
+    fn verify_input_with_invalid_spent_outputs() {
+        let tx = spending(Script::new_empty(), vec![]);
+
+        let res = verify_input_script(&tx, 0, &[], ScriptFlags::STANDARD);
+        assert_eq!("invalid_spent_outputs", res.expect_err("Err").to_string());
+    }
 }
@@ -0,0 +1,132 @@
+/*
+    Addresses of the standard ScriptPubKeys:
+        P2PKH       base58check: network prefix (0x00 mainnet, 0x6F testnet) <public key hash>
+        P2SH        base58check: 0x05 mainnet, 0xC4 testnet <script hash>
+        segwit      bech32 (v0) or bech32m (v1+): witness version and program (BIP173, BIP350)
+*/
+
+use crate::{
+    flags::network::Network,
+    scripting::standard::{p2pkh_script, p2sh_script},
+    std_lib::{
+        base58::{base58_decode_with_checksum, base58_encode_with_checksum},
+        bech32::{segwit_decode, segwit_encode},
+        std_result::StdResult,
+    },
+    transaction::script::Script,
+};
+
+const P2SH_MAINNET: u8 = 0x05;
+const P2SH_TESTNET: u8 = 0xC4;
+
+fn p2sh_prefix(network: Network) -> u8 {
+    match network {
+        Network::Mainnet => P2SH_MAINNET,
+        Network::Testnet => P2SH_TESTNET,
+    }
+}
+
+pub fn address(script_pub_key: &Script, network: Network) -> StdResult<String> {
+    let raw = script_pub_key.raw.as_slice();
+
+    let address = match raw {
+        [0x76, 0xA9, 0x14, public_key_hash @ .., 0x88, 0xAC] if raw.len() == 25 => {
+            base58_encode_with_checksum(&[&[network as u8][..], public_key_hash].concat())
+        }
+        [0xA9, 0x14, script_hash @ .., 0x87] if raw.len() == 23 => {
+            base58_encode_with_checksum(&[&[p2sh_prefix(network)][..], script_hash].concat())
+        }
+        [0x00, length, program @ ..] if (*length == 0x14 || *length == 0x20) && program.len() == *length as usize => {
+            segwit_encode(network, 0, program)?
+        }
+        [version @ 0x51..=0x60, length, program @ ..] if program.len() == *length as usize => {
+            segwit_encode(network, version - 0x50, program)?
+        }
+        _ => Err("script_without_address")?,
+    };
+
+    Ok(address)
+}
+
+pub fn script_pub_key(address: &str, network: Network) -> StdResult<Script> {
+    if let Ok((version, program)) = segwit_decode(network, address) {
+        let version_opcode = if version == 0 { 0x00 } else { 0x50 + version };
+        let raw = [vec![version_opcode, program.len() as u8], program].concat();
+
+        return Ok(Script::new_from_raw(raw));
+    }
+
+    let decoded = match base58_decode_with_checksum(address) {
+        Ok(decoded) if decoded.len() == 21 => decoded,
+        _ => Err("invalid_address")?,
+    };
+
+    let script_pub_key = match decoded[0] {
+        prefix if prefix == network as u8 => p2pkh_script(&decoded[1..]),
+        prefix if prefix == p2sh_prefix(network) => p2sh_script(&decoded[1..]),
+        _ => Err("incongruent_network")?,
+    };
+
+    Ok(Script::new_from_script_lang(&script_pub_key))
+}
+
+#[cfg(test)]
+mod address_test {
+    use super::*;
+    use crate::std_lib::vector::hex_string_to_bytes;
+
+    macro_rules! address {
+        ($script:expr, $network:expr, $address:expr, $f:ident) => {
+            #[test]
+            fn $f() {
+                let script = Script::new_from_raw(hex_string_to_bytes($script).unwrap());
+
+                assert_eq!(address(&script, $network).unwrap(), $address);
+                assert_eq!(script_pub_key($address, $network).unwrap().raw, script.raw);
+            }
+        };
+    }
+
+    address!(
+        "76A914751E76E8199196D454941C45D1B3A323F1433BD688AC",
+        Network::Mainnet,
+        "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+        p2pkh_mainnet
+    );
+    address!(
+        "A914748284390F9E263A4B766A75D0633C50426EB87587",
+        Network::Mainnet,
+        "3CK4fEwbMP7heJarmU4eqA3sMbVJyEnU3V",
+        p2sh_mainnet
+    );
+    address!(
+        "0014751E76E8199196D454941C45D1B3A323F1433BD6",
+        Network::Mainnet,
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        p2wpkh_mainnet
+    );
+    address!(
+        "5120A60869F0DBCF1DC659C9CECBAF8050135EA9E8CDC487053F1DC6880949DC684C",
+        Network::Mainnet,
+        "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+        p2tr_mainnet
+    );
+    address!(
+        "0014751E76E8199196D454941C45D1B3A323F1433BD6",
+        Network::Testnet,
+        "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+        p2wpkh_testnet
+    );
+
+    #[test]
+    fn invalid() {
+        let err = address(&Script::new_from_raw(vec![0x6A, 0x01, 0x00]), Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "script_without_address");
+
+        let err = script_pub_key("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH", Network::Testnet).expect_err("Err");
+        assert_eq!(err.to_string(), "incongruent_network");
+
+        let err = script_pub_key("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMh", Network::Mainnet).expect_err("Err");
+        assert_eq!(err.to_string(), "invalid_address");
+    }
+}
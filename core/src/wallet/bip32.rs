@@ -0,0 +1,905 @@
+//! BIP32 hierarchical deterministic wallets.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+//!
+//! Serialized extended key (78 bytes, then base58check):
+//!     version (4) + depth (1) + parent fingerprint (4) + child number (4) + chain code (32) + key data (33)
+//! Key data is `0x00 || secret` for private keys and the compressed SEC public key for public keys.
+
+use std::fmt::{Debug, Display, Formatter};
+
+use rug::{integer::Order, Integer};
+
+use crate::{
+    bitcoin::{
+        address_prefix::AddressPrefix,
+        ecdsa::{N, P},
+    },
+    ecdsa::point::Point,
+    flags::{compression::Compression, network::Network},
+    hashing::{hash160::hash160, hmac_sha512::hmac_sha512},
+    keys::key::Key,
+    std_lib::{base58, std_result::StdResult, vector::padding_left},
+};
+
+/// Child numbers from `HARDENED_OFFSET` (2^31) on are hardened.
+pub const HARDENED_OFFSET: u32 = 0x80000000;
+
+const MASTER_HMAC_KEY: &[u8] = b"Bitcoin seed";
+
+const MIN_SEED_LENGTH: usize = 16;
+const MAX_SEED_LENGTH: usize = 64;
+
+const SERIALIZED_LENGTH: usize = 78;
+
+pub type ChainCode = [u8; 32];
+pub type Fingerprint = [u8; 4];
+
+/// Sequence of child numbers, e.g. `m/84'/0'/0'/0/5`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Parse a derivation path.
+    /// Hardened child numbers are marked by `'` (or `h`/`H`).
+    pub fn parse(path: &str) -> StdResult<DerivationPath> {
+        let mut parts = path.trim().split('/');
+
+        if parts.next() != Some("m") {
+            Err("invalid_derivation_path")?;
+        }
+
+        let mut child_numbers: Vec<u32> = vec![];
+        for part in parts {
+            let (number, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+                Some(number) => (number, true),
+                None => (part, false),
+            };
+
+            if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                Err("invalid_child_number")?;
+            }
+
+            let index: u32 = number.parse().map_err(|_| "invalid_child_number")?;
+            if index >= HARDENED_OFFSET {
+                Err("invalid_child_number")?;
+            }
+
+            child_numbers.push(if hardened { index + HARDENED_OFFSET } else { index });
+        }
+
+        Ok(DerivationPath(child_numbers))
+    }
+
+    pub fn child_numbers(&self) -> &[u32] {
+        &self.0
+    }
+
+    /// New `DerivationPath` extended by `child_number`.
+    pub fn child(&self, child_number: u32) -> DerivationPath {
+        let mut child_numbers = self.0.clone();
+        child_numbers.push(child_number);
+
+        DerivationPath(child_numbers)
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "m")?;
+
+        for child_number in &self.0 {
+            if *child_number >= HARDENED_OFFSET {
+                write!(f, "/{}'", child_number - HARDENED_OFFSET)?;
+            } else {
+                write!(f, "/{}", child_number)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Extended private key (`xprv` on mainnet, `tprv` on testnet).
+#[derive(Clone)]
+pub struct ExtendedPrivateKey {
+    key: Key,
+    chain_code: ChainCode,
+    depth: u8,
+    parent_fingerprint: Fingerprint,
+    child_number: u32,
+    network: Network,
+}
+
+/// Extended public key (`xpub` on mainnet, `tpub` on testnet).
+#[derive(Debug, Clone)]
+pub struct ExtendedPublicKey {
+    public_key: Point,
+    chain_code: ChainCode,
+    depth: u8,
+    parent_fingerprint: Fingerprint,
+    child_number: u32,
+    network: Network,
+}
+
+impl ExtendedPrivateKey {
+    /// Master key from `seed` (between 128 and 512 bits).
+    /// Algorithm:
+    ///    I = hmac_sha512("Bitcoin seed", seed); secret = I[0..32], chain code = I[32..64]
+    pub fn new_master(seed: &[u8], network: Network) -> StdResult<ExtendedPrivateKey> {
+        if seed.len() < MIN_SEED_LENGTH || seed.len() > MAX_SEED_LENGTH {
+            Err("invalid_seed_length")?;
+        }
+
+        let (il, ir) = split_hmac(MASTER_HMAC_KEY, seed);
+
+        let secret = Integer::from_digits(&il, Order::Msf);
+        if secret == 0 || secret >= *N {
+            Err("invalid_master_key")?;
+        }
+
+        Ok(ExtendedPrivateKey {
+            key: Key::new(secret),
+            chain_code: ir,
+            depth: 0,
+            parent_fingerprint: [0u8; 4],
+            child_number: 0,
+            network,
+        })
+    }
+
+    /// Private parent key -> private child key.
+    /// Hardened children (`child_number` >= 2^31) are derived from the secret,
+    /// the others from the public key (so they can also be derived from the `ExtendedPublicKey`).
+    pub fn derive_child(&self, child_number: u32) -> StdResult<ExtendedPrivateKey> {
+        if self.depth == u8::MAX {
+            Err("max_depth_exceeded")?;
+        }
+
+        let data = if child_number >= HARDENED_OFFSET {
+            [[0u8].as_slice(), &secret_bytes(&self.key), &child_number.to_be_bytes()].concat()
+        } else {
+            [self.key.public_key_sec().as_slice(), &child_number.to_be_bytes()].concat()
+        };
+
+        let (il, ir) = split_hmac(&self.chain_code, &data);
+
+        let tweak = Integer::from_digits(&il, Order::Msf);
+        if tweak >= *N {
+            Err("invalid_child_key")?;
+        }
+
+        let (_q, secret) = (tweak + self.key.private_key()).div_rem_euc((*N).clone());
+        if secret == 0 {
+            Err("invalid_child_key")?;
+        }
+
+        Ok(ExtendedPrivateKey {
+            key: Key::new(secret),
+            chain_code: ir,
+            depth: self.depth + 1,
+            parent_fingerprint: self.fingerprint(),
+            child_number,
+            network: self.network,
+        })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> StdResult<ExtendedPrivateKey> {
+        path.child_numbers()
+            .iter()
+            .try_fold(self.clone(), |parent, child_number| parent.derive_child(*child_number))
+    }
+
+    /// Neutered version of the key: derivation of non-hardened children only.
+    pub fn extended_public_key(&self) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            public_key: self.key.public_key(),
+            chain_code: self.chain_code,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+            network: self.network,
+        }
+    }
+
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    pub fn chain_code(&self) -> ChainCode {
+        self.chain_code
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn parent_fingerprint(&self) -> Fingerprint {
+        self.parent_fingerprint
+    }
+
+    pub fn child_number(&self) -> u32 {
+        self.child_number
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// First 4 bytes of the key identifier (hash160 of the compressed public key).
+    pub fn fingerprint(&self) -> Fingerprint {
+        fingerprint(&self.key.public_key_sec())
+    }
+
+    /// `xprv...` or `tprv...`
+    pub fn to_base58(&self) -> String {
+        let version = match self.network {
+            Network::Mainnet => AddressPrefix::PrivateKeyBip32Mainnet,
+            Network::Testnet => AddressPrefix::PrivateKeyBip32Testnet,
+        };
+
+        let key_data = [[0u8].as_slice(), &secret_bytes(&self.key)].concat();
+
+        encode(
+            version as u32,
+            self.depth,
+            &self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &key_data,
+        )
+    }
+
+    pub fn from_base58(s: &str) -> StdResult<ExtendedPrivateKey> {
+        let decoded = decode(s)?;
+
+        let network = match decoded.version {
+            v if v == AddressPrefix::PrivateKeyBip32Mainnet as u32 => Network::Mainnet,
+            v if v == AddressPrefix::PrivateKeyBip32Testnet as u32 => Network::Testnet,
+            _ => Err("invalid_version")?,
+        };
+
+        if decoded.key_data[0] != 0x00 {
+            Err("invalid_private_key_prefix")?;
+        }
+
+        let secret = Integer::from_digits(&decoded.key_data[1..], Order::Msf);
+        if secret == 0 || secret >= *N {
+            Err("invalid_private_key")?;
+        }
+
+        Ok(ExtendedPrivateKey {
+            key: Key::new(secret),
+            chain_code: decoded.chain_code,
+            depth: decoded.depth,
+            parent_fingerprint: decoded.parent_fingerprint,
+            child_number: decoded.child_number,
+            network,
+        })
+    }
+}
+
+// The secret and the chain code are never printed
+impl Debug for ExtendedPrivateKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExtendedPrivateKey")
+            .field("key", &self.key)
+            .field("depth", &self.depth)
+            .field("parent_fingerprint", &self.parent_fingerprint)
+            .field("child_number", &self.child_number)
+            .field("network", &self.network)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExtendedPublicKey {
+    /// Public parent key -> public child key.
+    /// Only non-hardened children can be derived.
+    pub fn derive_child(&self, child_number: u32) -> StdResult<ExtendedPublicKey> {
+        if child_number >= HARDENED_OFFSET {
+            Err("hardened_derivation_from_public_key")?;
+        }
+
+        if self.depth == u8::MAX {
+            Err("max_depth_exceeded")?;
+        }
+
+        let data = [self.public_key_sec().as_slice(), &child_number.to_be_bytes()].concat();
+
+        let (il, ir) = split_hmac(&self.chain_code, &data);
+
+        let tweak = Integer::from_digits(&il, Order::Msf);
+        if tweak == 0 || tweak >= *N {
+            Err("invalid_child_key")?;
+        }
+
+        let public_key = Point::mul_generator_add(&tweak, &self.public_key, &Integer::from(1));
+        if public_key.is_infinite() {
+            Err("invalid_child_key")?;
+        }
+
+        Ok(ExtendedPublicKey {
+            public_key,
+            chain_code: ir,
+            depth: self.depth + 1,
+            parent_fingerprint: self.fingerprint(),
+            child_number,
+            network: self.network,
+        })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> StdResult<ExtendedPublicKey> {
+        path.child_numbers()
+            .iter()
+            .try_fold(self.clone(), |parent, child_number| parent.derive_child(*child_number))
+    }
+
+    pub fn public_key(&self) -> &Point {
+        &self.public_key
+    }
+
+    pub fn public_key_sec(&self) -> Vec<u8> {
+        self.public_key.serialize(Compression::Compressed)
+    }
+
+    pub fn chain_code(&self) -> ChainCode {
+        self.chain_code
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn parent_fingerprint(&self) -> Fingerprint {
+        self.parent_fingerprint
+    }
+
+    pub fn child_number(&self) -> u32 {
+        self.child_number
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn fingerprint(&self) -> Fingerprint {
+        fingerprint(&self.public_key_sec())
+    }
+
+    /// `xpub...` or `tpub...`
+    pub fn to_base58(&self) -> String {
+        base58::base58_encode_with_checksum(&self.serialize())
+    }
+
+    /// The 78 bytes before base58check (e.g. the key of a PSBT global xpub).
+    pub fn serialize(&self) -> Vec<u8> {
+        let version = match self.network {
+            Network::Mainnet => AddressPrefix::PublicKeyBip32Mainnet,
+            Network::Testnet => AddressPrefix::PublicKeyBip32Testnet,
+        };
+
+        serialize(
+            version as u32,
+            self.depth,
+            &self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &self.public_key_sec(),
+        )
+    }
+
+    pub fn from_base58(s: &str) -> StdResult<ExtendedPublicKey> {
+        let decoded = decode(s)?;
+
+        let network = match decoded.version {
+            v if v == AddressPrefix::PublicKeyBip32Mainnet as u32 => Network::Mainnet,
+            v if v == AddressPrefix::PublicKeyBip32Testnet as u32 => Network::Testnet,
+            _ => Err("invalid_version")?,
+        };
+
+        if decoded.key_data[0] != 0x02 && decoded.key_data[0] != 0x03 {
+            Err("invalid_public_key_prefix")?;
+        }
+
+        let x = Integer::from_digits(&decoded.key_data[1..], Order::Msf);
+        if x >= *P {
+            Err("invalid_public_key")?;
+        }
+
+        let public_key = Point::deserialize(decoded.key_data.to_vec());
+        if !public_key.is_on_curve() {
+            Err("invalid_public_key")?;
+        }
+
+        Ok(ExtendedPublicKey {
+            public_key,
+            chain_code: decoded.chain_code,
+            depth: decoded.depth,
+            parent_fingerprint: decoded.parent_fingerprint,
+            child_number: decoded.child_number,
+            network,
+        })
+    }
+}
+
+impl Display for ExtendedPublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_base58())
+    }
+}
+
+struct DecodedExtendedKey {
+    version: u32,
+    depth: u8,
+    parent_fingerprint: Fingerprint,
+    child_number: u32,
+    chain_code: ChainCode,
+    key_data: [u8; 33],
+}
+
+fn split_hmac(key: &[u8], data: &[u8]) -> ([u8; 32], ChainCode) {
+    let i = hmac_sha512(key, data);
+
+    let mut il = [0u8; 32];
+    let mut ir = [0u8; 32];
+    il.copy_from_slice(&i[..32]);
+    ir.copy_from_slice(&i[32..]);
+
+    (il, ir)
+}
+
+fn secret_bytes(key: &Key) -> Vec<u8> {
+    padding_left(&key.private_key().to_digits::<u8>(Order::Msf), 32, 0)
+}
+
+fn fingerprint(public_key_sec: &[u8]) -> Fingerprint {
+    let mut res = [0u8; 4];
+    res.copy_from_slice(&hash160(public_key_sec)[..4]);
+
+    res
+}
+
+fn encode(
+    version: u32,
+    depth: u8,
+    parent_fingerprint: &Fingerprint,
+    child_number: u32,
+    chain_code: &ChainCode,
+    key_data: &[u8],
+) -> String {
+    let data = serialize(version, depth, parent_fingerprint, child_number, chain_code, key_data);
+
+    base58::base58_encode_with_checksum(&data)
+}
+
+fn serialize(
+    version: u32,
+    depth: u8,
+    parent_fingerprint: &Fingerprint,
+    child_number: u32,
+    chain_code: &ChainCode,
+    key_data: &[u8],
+) -> Vec<u8> {
+    [
+        version.to_be_bytes().as_slice(),
+        &[depth],
+        parent_fingerprint,
+        &child_number.to_be_bytes(),
+        chain_code,
+        key_data,
+    ]
+    .concat()
+}
+
+fn decode(s: &str) -> StdResult<DecodedExtendedKey> {
+    let data = base58::base58_decode_with_checksum(s)?;
+
+    if data.len() != SERIALIZED_LENGTH {
+        Err("invalid_length")?;
+    }
+
+    let mut version = [0u8; 4];
+    let mut parent_fingerprint = [0u8; 4];
+    let mut child_number = [0u8; 4];
+    let mut chain_code = [0u8; 32];
+    let mut key_data = [0u8; 33];
+
+    version.copy_from_slice(&data[0..4]);
+    let depth = data[4];
+    parent_fingerprint.copy_from_slice(&data[5..9]);
+    child_number.copy_from_slice(&data[9..13]);
+    chain_code.copy_from_slice(&data[13..45]);
+    key_data.copy_from_slice(&data[45..78]);
+
+    let child_number = u32::from_be_bytes(child_number);
+
+    if depth == 0 && parent_fingerprint != [0u8; 4] {
+        Err("invalid_parent_fingerprint")?;
+    }
+
+    if depth == 0 && child_number != 0 {
+        Err("invalid_child_number")?;
+    }
+
+    Ok(DecodedExtendedKey {
+        version: u32::from_be_bytes(version),
+        depth,
+        parent_fingerprint,
+        child_number,
+        chain_code,
+        key_data,
+    })
+}
+
+#[cfg(test)]
+mod bip32_test {
+    use super::*;
+    use crate::std_lib::vector::{bytes_to_hex_string, hex_string_to_bytes};
+
+    const SEED_1: &str = "000102030405060708090a0b0c0d0e0f";
+    const SEED_2: &str = "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542";
+    const SEED_3: &str = "4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be";
+    const SEED_4: &str = "3ddd5602285899a946114506157c7997e5444528f3003f6134712147db19b678";
+
+    fn master(seed: &str) -> ExtendedPrivateKey {
+        ExtendedPrivateKey::new_master(&hex_string_to_bytes(seed).unwrap(), Network::Mainnet).unwrap()
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vectors
+    macro_rules! bip32_vector {
+        ($seed:expr, $path:literal, $xpub:literal, $xprv:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let path = DerivationPath::parse($path).unwrap();
+                let xprv = master($seed).derive_path(&path).unwrap();
+
+                assert_eq!(xprv.to_base58(), $xprv);
+                assert_eq!(xprv.extended_public_key().to_base58(), $xpub);
+
+                assert_eq!(ExtendedPrivateKey::from_base58($xprv).unwrap().to_base58(), $xprv);
+                assert_eq!(ExtendedPublicKey::from_base58($xpub).unwrap().to_base58(), $xpub);
+            }
+        };
+    }
+
+    bip32_vector!(
+        SEED_1,
+        "m",
+        "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+        "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+        vector_1_m
+    );
+    bip32_vector!(
+        SEED_1,
+        "m/0'",
+        "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+        "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+        vector_1_m_0h
+    );
+    bip32_vector!(
+        SEED_1,
+        "m/0'/1",
+        "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+        "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+        vector_1_m_0h_1
+    );
+    bip32_vector!(
+        SEED_1,
+        "m/0'/1/2'",
+        "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+        "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+        vector_1_m_0h_1_2h
+    );
+    bip32_vector!(
+        SEED_1,
+        "m/0'/1/2'/2",
+        "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+        "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334",
+        vector_1_m_0h_1_2h_2
+    );
+    bip32_vector!(
+        SEED_1,
+        "m/0'/1/2'/2/1000000000",
+        "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+        "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+        vector_1_m_0h_1_2h_2_1000000000
+    );
+
+    bip32_vector!(
+        SEED_2,
+        "m",
+        "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+        "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U",
+        vector_2_m
+    );
+    bip32_vector!(
+        SEED_2,
+        "m/0",
+        "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+        "xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt",
+        vector_2_m_0
+    );
+    bip32_vector!(
+        SEED_2,
+        "m/0/2147483647'",
+        "xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a",
+        "xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9",
+        vector_2_m_0_2147483647h
+    );
+    bip32_vector!(
+        SEED_2,
+        "m/0/2147483647'/1",
+        "xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon",
+        "xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef",
+        vector_2_m_0_2147483647h_1
+    );
+    bip32_vector!(
+        SEED_2,
+        "m/0/2147483647'/1/2147483646'",
+        "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+        "xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc",
+        vector_2_m_0_2147483647h_1_2147483646h
+    );
+    bip32_vector!(
+        SEED_2,
+        "m/0/2147483647'/1/2147483646'/2",
+        "xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt",
+        "xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j",
+        vector_2_m_0_2147483647h_1_2147483646h_2
+    );
+
+    // retention of leading zeros
+    bip32_vector!(
+        SEED_3,
+        "m",
+        "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+        "xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6",
+        vector_3_m
+    );
+    bip32_vector!(
+        SEED_3,
+        "m/0'",
+        "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+        "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L",
+        vector_3_m_0h
+    );
+
+    bip32_vector!(
+        SEED_4,
+        "m",
+        "xpub661MyMwAqRbcGczjuMoRm6dXaLDEhW1u34gKenbeYqAix21mdUKJyuyu5F1rzYGVxyL6tmgBUAEPrEz92mBXjByMRiJdba9wpnN37RLLAXa",
+        "xprv9s21ZrQH143K48vGoLGRPxgo2JNkJ3J3fqkirQC2zVdk5Dgd5w14S7fRDyHH4dWNHUgkvsvNDCkvAwcSHNAQwhwgNMgZhLtQC63zxwhQmRv",
+        vector_4_m
+    );
+    bip32_vector!(
+        SEED_4,
+        "m/0'",
+        "xpub69AUMk3qDBi3uW1sXgjCmVjJ2G6WQoYSnNHyzkmdCHEhSZ4tBok37xfFEqHd2AddP56Tqp4o56AePAgCjYdvpW2PU2jbUPFKsav5ut6Ch1m",
+        "xprv9vB7xEWwNp9kh1wQRfCCQMnZUEG21LpbR9NPCNN1dwhiZkjjeGRnaALmPXCX7SgjFTiCTT6bXes17boXtjq3xLpcDjzEuGLQBM5ohqkao9G",
+        vector_4_m_0h
+    );
+    bip32_vector!(
+        SEED_4,
+        "m/0'/1'",
+        "xpub6BJA1jSqiukeaesWfxe6sNK9CCGaujFFSJLomWHprUL9DePQ4JDkM5d88n49sMGJxrhpjazuXYWdMf17C9T5XnxkopaeS7jGk1GyyVziaMt",
+        "xprv9xJocDuwtYCMNAo3Zw76WENQeAS6WGXQ55RCy7tDJ8oALr4FWkuVoHJeHVAcAqiZLE7Je3vZJHxspZdFHfnBEjHqU5hG1Jaj32dVoS6XLT1",
+        vector_4_m_0h_1h
+    );
+
+    #[test]
+    fn public_derivation_matches_private_derivation() {
+        let xprv = master(SEED_1)
+            .derive_path(&DerivationPath::parse("m/0'/1/2'/2").unwrap())
+            .unwrap();
+
+        let child_from_xpub = xprv.extended_public_key().derive_child(1000000000).unwrap();
+        let child_from_xprv = xprv.derive_child(1000000000).unwrap().extended_public_key();
+
+        assert_eq!(child_from_xpub.to_base58(), child_from_xprv.to_base58());
+    }
+
+    #[test]
+    fn public_derivation_of_a_path() {
+        let xpub = master(SEED_2).extended_public_key();
+        let child = xpub.derive_path(&DerivationPath::parse("m/0").unwrap()).unwrap();
+
+        assert_eq!(
+            child.to_base58(),
+            "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH"
+        );
+    }
+
+    #[test]
+    fn public_derivation_from_a_deserialized_key() {
+        let xpub = ExtendedPublicKey::from_base58(
+            "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+        )
+        .unwrap();
+        let child = xpub.derive_child(1000000000).unwrap();
+
+        assert_eq!(
+            child.to_base58(),
+            "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy"
+        );
+    }
+
+    #[test]
+    fn hardened_derivation_from_public_key() {
+        let xpub = master(SEED_1).extended_public_key();
+        let res = xpub.derive_child(HARDENED_OFFSET);
+
+        assert_eq!(res.err().unwrap().to_string(), "hardened_derivation_from_public_key");
+    }
+
+    #[test]
+    fn fingerprints() {
+        let m = master(SEED_1);
+        let m_0h = m.derive_child(HARDENED_OFFSET).unwrap();
+
+        assert_eq!(bytes_to_hex_string(&m.fingerprint()), "3442193E");
+        assert_eq!(m_0h.parent_fingerprint(), m.fingerprint());
+        assert_eq!(m_0h.depth(), 1);
+        assert_eq!(m_0h.child_number(), HARDENED_OFFSET);
+        assert_eq!(m.extended_public_key().fingerprint(), m.fingerprint());
+    }
+
+    #[test]
+    fn testnet_serialization() {
+        let seed = hex_string_to_bytes(SEED_1).unwrap();
+        let tprv = ExtendedPrivateKey::new_master(&seed, Network::Testnet).unwrap();
+
+        let s = tprv.to_base58();
+        assert!(s.starts_with("tprv"));
+        assert!(tprv.extended_public_key().to_base58().starts_with("tpub"));
+
+        let decoded = ExtendedPrivateKey::from_base58(&s).unwrap();
+        assert_eq!(decoded.network(), Network::Testnet);
+        assert_eq!(decoded.key().private_key(), tprv.key().private_key());
+    }
+
+    #[test]
+    fn secret_is_not_printed() {
+        let xprv = master(SEED_1);
+
+        let printed = format!("{:?}", xprv);
+        assert!(!printed.contains(&xprv.key().private_key().to_string()));
+        assert!(!printed.contains(&format!("{:?}", xprv.chain_code())));
+    }
+
+    #[test]
+    fn invalid_seed_length() {
+        let res = ExtendedPrivateKey::new_master(&[0u8; 15], Network::Mainnet);
+        assert_eq!(res.err().unwrap().to_string(), "invalid_seed_length");
+
+        let res = ExtendedPrivateKey::new_master(&[0u8; 65], Network::Mainnet);
+        assert_eq!(res.err().unwrap().to_string(), "invalid_seed_length");
+    }
+
+    // Re-encode a valid master xprv changing some bytes
+    fn tampered(f: fn(&mut Vec<u8>)) -> String {
+        let xprv = master(SEED_1).to_base58();
+        let mut data = base58::base58_decode_with_checksum(&xprv).unwrap();
+        f(&mut data);
+
+        base58::base58_encode_with_checksum(&data)
+    }
+
+    #[test]
+    fn invalid_private_key_version() {
+        let xpub = master(SEED_1).extended_public_key().to_base58();
+        let res = ExtendedPrivateKey::from_base58(&xpub);
+
+        assert_eq!(res.err().unwrap().to_string(), "invalid_version");
+    }
+
+    #[test]
+    fn invalid_public_key_version() {
+        let xprv = master(SEED_1).to_base58();
+        let res = ExtendedPublicKey::from_base58(&xprv);
+
+        assert_eq!(res.err().unwrap().to_string(), "invalid_version");
+    }
+
+    #[test]
+    fn invalid_private_key_prefix() {
+        let res = ExtendedPrivateKey::from_base58(&tampered(|data| data[45] = 0x04));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_private_key_prefix");
+    }
+
+    #[test]
+    fn invalid_private_key_zero() {
+        let res = ExtendedPrivateKey::from_base58(&tampered(|data| data[46..78].copy_from_slice(&[0u8; 32])));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_private_key");
+    }
+
+    #[test]
+    fn invalid_private_key_out_of_range() {
+        let res = ExtendedPrivateKey::from_base58(&tampered(|data| data[46..78].copy_from_slice(&[0xFFu8; 32])));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_private_key");
+    }
+
+    #[test]
+    fn invalid_parent_fingerprint_for_master() {
+        let res = ExtendedPrivateKey::from_base58(&tampered(|data| data[5] = 0x01));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_parent_fingerprint");
+    }
+
+    #[test]
+    fn invalid_child_number_for_master() {
+        let res = ExtendedPrivateKey::from_base58(&tampered(|data| data[12] = 0x01));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_child_number");
+    }
+
+    #[test]
+    fn invalid_extended_key_length() {
+        let res = ExtendedPrivateKey::from_base58(&tampered(|data| data.push(0x00)));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_length");
+    }
+
+    #[test]
+    fn invalid_public_key_prefix() {
+        let xpub = master(SEED_1).extended_public_key().to_base58();
+        let mut data = base58::base58_decode_with_checksum(&xpub).unwrap();
+        data[45] = 0x04;
+
+        let res = ExtendedPublicKey::from_base58(&base58::base58_encode_with_checksum(&data));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_public_key_prefix");
+    }
+
+    #[test]
+    fn invalid_public_key_not_in_curve() {
+        let xpub = master(SEED_1).extended_public_key().to_base58();
+        let mut data = base58::base58_decode_with_checksum(&xpub).unwrap();
+        // x = 5 is not a valid x coordinate in secp256k1 (5^3 + 7 is not a square)
+        data[46..78].copy_from_slice(&padding_left(&[5u8], 32, 0));
+
+        let res = ExtendedPublicKey::from_base58(&base58::base58_encode_with_checksum(&data));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_public_key");
+    }
+
+    #[test]
+    fn parse_derivation_path() {
+        let path = DerivationPath::parse("m/84'/0'/0'/0/5").unwrap();
+
+        assert_eq!(
+            path.child_numbers(),
+            &[84 + HARDENED_OFFSET, HARDENED_OFFSET, HARDENED_OFFSET, 0, 5]
+        );
+        assert_eq!(path.to_string(), "m/84'/0'/0'/0/5");
+    }
+
+    #[test]
+    fn parse_derivation_path_with_h() {
+        let path = DerivationPath::parse("m/44h/1H/0").unwrap();
+        assert_eq!(path.to_string(), "m/44'/1'/0");
+    }
+
+    #[test]
+    fn parse_master_derivation_path() {
+        let path = DerivationPath::parse("m").unwrap();
+        assert!(path.child_numbers().is_empty());
+        assert_eq!(path.child(3).to_string(), "m/3");
+    }
+
+    macro_rules! invalid_derivation_path {
+        ($path:literal, $reason:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let res = DerivationPath::parse($path);
+                assert_eq!(res.err().unwrap().to_string(), $reason);
+            }
+        };
+    }
+
+    invalid_derivation_path!("84'/0'", "invalid_derivation_path", invalid_derivation_path_without_m);
+    invalid_derivation_path!("", "invalid_derivation_path", invalid_derivation_path_empty);
+    invalid_derivation_path!("m/", "invalid_child_number", invalid_derivation_path_empty_child);
+    invalid_derivation_path!("m/a", "invalid_child_number", invalid_derivation_path_not_a_number);
+    invalid_derivation_path!("m/-1", "invalid_child_number", invalid_derivation_path_negative);
+    invalid_derivation_path!(
+        "m/2147483648",
+        "invalid_child_number",
+        invalid_derivation_path_out_of_range
+    );
+    invalid_derivation_path!("m/1''", "invalid_child_number", invalid_derivation_path_double_hardened);
+}
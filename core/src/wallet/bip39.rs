@@ -0,0 +1,448 @@
+//! BIP39 mnemonic code for generating deterministic keys.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+//!
+//! Entropy (128 to 256 bits) is extended with a checksum (first `entropy bits / 32` bits of its sha256),
+//! then split in groups of 11 bits: every group is the index of a word in the wordlist.
+//! The seed is obtained from the mnemonic sentence by PBKDF2-HMAC-SHA512.
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    hashing::{hmac_sha512::hmac_sha512, sha256::sha256},
+    std_lib::{rand::EntropySource, std_result::StdResult},
+};
+
+static ENGLISH_WORDLIST: Lazy<Vec<&'static str>> = Lazy::new(|| include_str!("bip39_english.txt").lines().collect());
+
+const BITS_PER_WORD: usize = 11;
+const PBKDF2_ROUNDS: u32 = 2048;
+const SALT_PREFIX: &str = "mnemonic";
+
+/// Mnemonic sentence backed by its entropy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mnemonic {
+    entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+    /// New `Mnemonic` from `entropy` (16, 20, 24, 28 or 32 bytes).
+    pub fn from_entropy(entropy: &[u8]) -> StdResult<Mnemonic> {
+        if entropy.len() < 16 || entropy.len() > 32 || !entropy.len().is_multiple_of(4) {
+            Err("invalid_entropy_length")?;
+        }
+
+        Ok(Mnemonic {
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    /// New random `Mnemonic` with `entropy_length` bytes of entropy (16 for 12 words, ..., 32 for 24 words).
+    pub fn generate(entropy_length: usize, entropy: &mut dyn EntropySource) -> StdResult<Mnemonic> {
+        let mut bytes = vec![0u8; entropy_length];
+        entropy.fill_bytes(&mut bytes)?;
+
+        Mnemonic::from_entropy(&bytes)
+    }
+
+    /// Parse and validate a mnemonic sentence (words separated by whitespaces).
+    pub fn from_phrase(phrase: &str) -> StdResult<Mnemonic> {
+        let words: Vec<&str> = phrase.split_whitespace().collect();
+
+        if words.len() < 12 || words.len() > 24 || !words.len().is_multiple_of(3) {
+            Err("invalid_word_count")?;
+        }
+
+        let mut bits: Vec<bool> = Vec::with_capacity(words.len() * BITS_PER_WORD);
+        for word in words {
+            let index = match ENGLISH_WORDLIST.binary_search(&word) {
+                Ok(index) => index,
+                Err(_) => Err("invalid_word")?,
+            };
+
+            for i in (0..BITS_PER_WORD).rev() {
+                bits.push((index >> i) & 1 == 1);
+            }
+        }
+
+        // total bits = entropy bits + entropy bits / 32
+        let checksum_length = bits.len() / 33;
+        let (entropy_bits, checksum_bits) = bits.split_at(bits.len() - checksum_length);
+
+        let entropy: Vec<u8> = entropy_bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, bit| (acc << 1) | (*bit as u8)))
+            .collect();
+
+        if checksum_bits != checksum(&entropy).as_slice() {
+            Err("invalid_checksum")?;
+        }
+
+        Ok(Mnemonic { entropy })
+    }
+
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    /// Mnemonic sentence: words separated by a single space.
+    pub fn phrase(&self) -> String {
+        let mut bits: Vec<bool> = Vec::with_capacity(self.entropy.len() * 8 + self.entropy.len() / 4);
+        for byte in &self.entropy {
+            for i in (0..8).rev() {
+                bits.push((byte >> i) & 1 == 1);
+            }
+        }
+        bits.extend(checksum(&self.entropy));
+
+        let words: Vec<&str> = bits
+            .chunks(BITS_PER_WORD)
+            .map(|group| group.iter().fold(0usize, |acc, bit| (acc << 1) | (*bit as usize)))
+            .map(|index| ENGLISH_WORDLIST[index])
+            .collect();
+
+        words.join(" ")
+    }
+
+    /// 512 bits seed (see `wallet::bip32::ExtendedPrivateKey::new_master`).
+    /// Algorithm:
+    ///    PBKDF2(HMAC-SHA512, password = mnemonic sentence, salt = "mnemonic" + passphrase, 2048 rounds)
+    /// Note: the passphrase is used as it is (BIP39 requires it in UTF-8 NFKD form).
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let salt = format!("{}{}", SALT_PREFIX, passphrase);
+
+        pbkdf2_hmac_sha512(self.phrase().as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS)
+    }
+}
+
+/// First `entropy bits / 32` bits of sha256(entropy).
+fn checksum(entropy: &[u8]) -> Vec<bool> {
+    let hashed = sha256(entropy);
+    let length = entropy.len() / 4;
+
+    (0..length).map(|i| (hashed[i / 8] >> (7 - i % 8)) & 1 == 1).collect()
+}
+
+/// https://www.rfc-editor.org/rfc/rfc8018#section-5.2
+/// Derived key length is the same of the HMAC output (64 bytes): only a single block is needed.
+fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], rounds: u32) -> [u8; 64] {
+    let block_index: u32 = 1;
+
+    let mut u = hmac_sha512(password, &[salt, &block_index.to_be_bytes()].concat());
+    let mut res = u;
+
+    for _ in 1..rounds {
+        u = hmac_sha512(password, &u);
+        res.iter_mut().zip(u.iter()).for_each(|(r, v)| *r ^= v);
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod bip39_test {
+    use super::*;
+    use crate::{
+        flags::network::Network,
+        std_lib::{
+            rand::{DeterministicEntropy, OsEntropy},
+            vector::hex_string_to_bytes,
+        },
+        wallet::bip32::ExtendedPrivateKey,
+    };
+
+    #[test]
+    fn english_wordlist() {
+        assert_eq!(ENGLISH_WORDLIST.len(), 2048);
+        assert_eq!(ENGLISH_WORDLIST[0], "abandon");
+        assert_eq!(ENGLISH_WORDLIST[2047], "zoo");
+        assert!(ENGLISH_WORDLIST.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    // https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+    macro_rules! bip39_vector {
+        ($entropy:literal, $phrase:literal, $seed:literal, $xprv:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let entropy = hex_string_to_bytes($entropy).unwrap();
+
+                let mnemonic = Mnemonic::from_entropy(&entropy).unwrap();
+                assert_eq!(mnemonic.phrase(), $phrase);
+
+                let parsed = Mnemonic::from_phrase($phrase).unwrap();
+                assert_eq!(parsed.entropy(), entropy.as_slice());
+
+                let seed = parsed.to_seed("TREZOR");
+                assert_eq!(seed.to_vec(), hex_string_to_bytes($seed).unwrap());
+
+                let xprv = ExtendedPrivateKey::new_master(&seed, Network::Mainnet).unwrap();
+                assert_eq!(xprv.to_base58(), $xprv);
+            }
+        };
+    }
+
+    bip39_vector!(
+        "00000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF",
+        vector_1
+    );
+    bip39_vector!(
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+        "xprv9s21ZrQH143K2gA81bYFHqU68xz1cX2APaSq5tt6MFSLeXnCKV1RVUJt9FWNTbrrryem4ZckN8k4Ls1H6nwdvDTvnV7zEXs2HgPezuVccsq",
+        vector_2
+    );
+    bip39_vector!(
+        "80808080808080808080808080808080",
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+        "d71de856f81a8acc65e6fc851a38d4d7ec216fd0796d0a6827a3ad6ed5511a30fa280f12eb2e47ed2ac03b5c462a0358d18d69fe4f985ec81778c1b370b652a8",
+        "xprv9s21ZrQH143K2shfP28KM3nr5Ap1SXjz8gc2rAqqMEynmjt6o1qboCDpxckqXavCwdnYds6yBHZGKHv7ef2eTXy461PXUjBFQg6PrwY4Gzq",
+        vector_3
+    );
+    bip39_vector!(
+        "ffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+        "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+        "xprv9s21ZrQH143K2V4oox4M8Zmhi2Fjx5XK4Lf7GKRvPSgydU3mjZuKGCTg7UPiBUD7ydVPvSLtg9hjp7MQTYsW67rZHAXeccqYqrsx8LcXnyd",
+        vector_4
+    );
+    bip39_vector!(
+        "000000000000000000000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent",
+        "035895f2f481b1b0f01fcf8c289c794660b289981a78f8106447707fdd9666ca06da5a9a565181599b79f53b844d8a71dd9f439c52a3d7b3e8a79c906ac845fa",
+        "xprv9s21ZrQH143K3mEDrypcZ2usWqFgzKB6jBBx9B6GfC7fu26X6hPRzVjzkqkPvDqp6g5eypdk6cyhGnBngbjeHTe4LsuLG1cCmKJka5SMkmU",
+        vector_5
+    );
+    bip39_vector!(
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal will",
+        "f2b94508732bcbacbcc020faefecfc89feafa6649a5491b8c952cede496c214a0c7b3c392d168748f2d4a612bada0753b52a1c7ac53c1e93abd5c6320b9e95dd",
+        "xprv9s21ZrQH143K3Lv9MZLj16np5GzLe7tDKQfVusBni7toqJGcnKRtHSxUwbKUyUWiwpK55g1DUSsw76TF1T93VT4gz4wt5RM23pkaQLnvBh7",
+        vector_6
+    );
+    bip39_vector!(
+        "808080808080808080808080808080808080808080808080",
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter always",
+        "107d7c02a5aa6f38c58083ff74f04c607c2d2c0ecc55501dadd72d025b751bc27fe913ffb796f841c49b1d33b610cf0e91d3aa239027f5e99fe4ce9e5088cd65",
+        "xprv9s21ZrQH143K3VPCbxbUtpkh9pRG371UCLDz3BjceqP1jz7XZsQ5EnNkYAEkfeZp62cDNj13ZTEVG1TEro9sZ9grfRmcYWLBhCocViKEJae",
+        vector_7
+    );
+    bip39_vector!(
+        "ffffffffffffffffffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo when",
+        "0cd6e5d827bb62eb8fc1e262254223817fd068a74b5b449cc2f667c3f1f985a76379b43348d952e2265b4cd129090758b3e3c2c49103b5051aac2eaeb890a528",
+        "xprv9s21ZrQH143K36Ao5jHRVhFGDbLP6FCx8BEEmpru77ef3bmA928BxsqvVM27WnvvyfWywiFN8K6yToqMaGYfzS6Db1EHAXT5TuyCLBXUfdm",
+        vector_8
+    );
+    bip39_vector!(
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+        "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+        "xprv9s21ZrQH143K32qBagUJAMU2LsHg3ka7jqMcV98Y7gVeVyNStwYS3U7yVVoDZ4btbRNf4h6ibWpY22iRmXq35qgLs79f312g2kj5539ebPM",
+        vector_9
+    );
+    bip39_vector!(
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth title",
+        "bc09fca1804f7e69da93c2f2028eb238c227f2e9dda30cd63699232578480a4021b146ad717fbb7e451ce9eb835f43620bf5c514db0f8add49f5d121449d3e87",
+        "xprv9s21ZrQH143K3Y1sd2XVu9wtqxJRvybCfAetjUrMMco6r3v9qZTBeXiBZkS8JxWbcGJZyio8TrZtm6pkbzG8SYt1sxwNLh3Wx7to5pgiVFU",
+        vector_10
+    );
+    bip39_vector!(
+        "8080808080808080808080808080808080808080808080808080808080808080",
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic bless",
+        "c0c519bd0e91a2ed54357d9d1ebef6f5af218a153624cf4f2da911a0ed8f7a09e2ef61af0aca007096df430022f7a2b6fb91661a9589097069720d015e4e982f",
+        "xprv9s21ZrQH143K3CSnQNYC3MqAAqHwxeTLhDbhF43A4ss4ciWNmCY9zQGvAKUSqVUf2vPHBTSE1rB2pg4avopqSiLVzXEU8KziNnVPauTqLRo",
+        vector_11
+    );
+    bip39_vector!(
+        "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+        "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad",
+        "xprv9s21ZrQH143K2WFF16X85T2QCpndrGwx6GueB72Zf3AHwHJaknRXNF37ZmDrtHrrLSHvbuRejXcnYxoZKvRquTPyp2JiNG3XcjQyzSEgqCB",
+        vector_12
+    );
+    bip39_vector!(
+        "9e885d952ad362caeb4efe34a8e91bd2",
+        "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+        "274ddc525802f7c828d8ef7ddbcdc5304e87ac3535913611fbbfa986d0c9e5476c91689f9c8a54fd55bd38606aa6a8595ad213d4c9c9f9aca3fb217069a41028",
+        "xprv9s21ZrQH143K2oZ9stBYpoaZ2ktHj7jLz7iMqpgg1En8kKFTXJHsjxry1JbKH19YrDTicVwKPehFKTbmaxgVEc5TpHdS1aYhB2s9aFJBeJH",
+        vector_13
+    );
+    bip39_vector!(
+        "6610b25967cdcca9d59875f5cb50b0ea75433311869e930b",
+        "gravity machine north sort system female filter attitude volume fold club stay feature office ecology stable narrow fog",
+        "628c3827a8823298ee685db84f55caa34b5cc195a778e52d45f59bcf75aba68e4d7590e101dc414bc1bbd5737666fbbef35d1f1903953b66624f910feef245ac",
+        "xprv9s21ZrQH143K3uT8eQowUjsxrmsA9YUuQQK1RLqFufzybxD6DH6gPY7NjJ5G3EPHjsWDrs9iivSbmvjc9DQJbJGatfa9pv4MZ3wjr8qWPAK",
+        vector_14
+    );
+    bip39_vector!(
+        "68a79eaca2324873eacc50cb9c6eca8cc68ea5d936f98787c60c7ebc74e6ce7c",
+        "hamster diagram private dutch cause delay private meat slide toddler razor book happy fancy gospel tennis maple dilemma loan word shrug inflict delay length",
+        "64c87cde7e12ecf6704ab95bb1408bef047c22db4cc7491c4271d170a1b213d20b385bc1588d9c7b38f1b39d415665b8a9030c9ec653d75e65f847d8fc1fc440",
+        "xprv9s21ZrQH143K2XTAhys3pMNcGn261Fi5Ta2Pw8PwaVPhg3D8DWkzWQwjTJfskj8ofb81i9NP2cUNKxwjueJHHMQAnxtivTA75uUFqPFeWzk",
+        vector_15
+    );
+    bip39_vector!(
+        "c0ba5a8e914111210f2bd131f3d5e08d",
+        "scheme spot photo card baby mountain device kick cradle pact join borrow",
+        "ea725895aaae8d4c1cf682c1bfd2d358d52ed9f0f0591131b559e2724bb234fca05aa9c02c57407e04ee9dc3b454aa63fbff483a8b11de949624b9f1831a9612",
+        "xprv9s21ZrQH143K3FperxDp8vFsFycKCRcJGAFmcV7umQmcnMZaLtZRt13QJDsoS5F6oYT6BB4sS6zmTmyQAEkJKxJ7yByDNtRe5asP2jFGhT6",
+        vector_16
+    );
+    bip39_vector!(
+        "6d9be1ee6ebd27a258115aad99b7317b9c8d28b6d76431c3",
+        "horn tenant knee talent sponsor spell gate clip pulse soap slush warm silver nephew swap uncle crack brave",
+        "fd579828af3da1d32544ce4db5c73d53fc8acc4ddb1e3b251a31179cdb71e853c56d2fcb11aed39898ce6c34b10b5382772db8796e52837b54468aeb312cfc3d",
+        "xprv9s21ZrQH143K3R1SfVZZLtVbXEB9ryVxmVtVMsMwmEyEvgXN6Q84LKkLRmf4ST6QrLeBm3jQsb9gx1uo23TS7vo3vAkZGZz71uuLCcywUkt",
+        vector_17
+    );
+    bip39_vector!(
+        "9f6a2878b2520799a44ef18bc7df394e7061a224d2c33cd015b157d746869863",
+        "panda eyebrow bullet gorilla call smoke muffin taste mesh discover soft ostrich alcohol speed nation flash devote level hobby quick inner drive ghost inside",
+        "72be8e052fc4919d2adf28d5306b5474b0069df35b02303de8c1729c9538dbb6fc2d731d5f832193cd9fb6aeecbc469594a70e3dd50811b5067f3b88b28c3e8d",
+        "xprv9s21ZrQH143K2WNnKmssvZYM96VAr47iHUQUTUyUXH3sAGNjhJANddnhw3i3y3pBbRAVk5M5qUGFr4rHbEWwXgX4qrvrceifCYQJbbFDems",
+        vector_18
+    );
+    bip39_vector!(
+        "23db8160a31d3e0dca3688ed941adbf3",
+        "cat swing flag economy stadium alone churn speed unique patch report train",
+        "deb5f45449e615feff5640f2e49f933ff51895de3b4381832b3139941c57b59205a42480c52175b6efcffaa58a2503887c1e8b363a707256bdd2b587b46541f5",
+        "xprv9s21ZrQH143K4G28omGMogEoYgDQuigBo8AFHAGDaJdqQ99QKMQ5J6fYTMfANTJy6xBmhvsNZ1CJzRZ64PWbnTFUn6CDV2FxoMDLXdk95DQ",
+        vector_19
+    );
+    bip39_vector!(
+        "8197a4a47f0425faeaa69deebc05ca29c0a5b5cc76ceacc0",
+        "light rule cinnamon wrap drastic word pride squirrel upgrade then income fatal apart sustain crack supply proud access",
+        "4cbdff1ca2db800fd61cae72a57475fdc6bab03e441fd63f96dabd1f183ef5b782925f00105f318309a7e9c3ea6967c7801e46c8a58082674c860a37b93eda02",
+        "xprv9s21ZrQH143K3wtsvY8L2aZyxkiWULZH4vyQE5XkHTXkmx8gHo6RUEfH3Jyr6NwkJhvano7Xb2o6UqFKWHVo5scE31SGDCAUsgVhiUuUDyh",
+        vector_20
+    );
+    bip39_vector!(
+        "066dca1a2bb7e8a1db2832148ce9933eea0f3ac9548d793112d9a95c9407efad",
+        "all hour make first leader extend hole alien behind guard gospel lava path output census museum junior mass reopen famous sing advance salt reform",
+        "26e975ec644423f4a4c4f4215ef09b4bd7ef924e85d1d17c4cf3f136c2863cf6df0a475045652c57eb5fb41513ca2a2d67722b77e954b4b3fc11f7590449191d",
+        "xprv9s21ZrQH143K3rEfqSM4QZRVmiMuSWY9wugscmaCjYja3SbUD3KPEB1a7QXJoajyR2T1SiXU7rFVRXMV9XdYVSZe7JoUXdP4SRHTxsT1nzm",
+        vector_21
+    );
+    bip39_vector!(
+        "f30f8c1da665478f49b001d94c5fc452",
+        "vessel ladder alter error federal sibling chat ability sun glass valve picture",
+        "2aaa9242daafcee6aa9d7269f17d4efe271e1b9a529178d7dc139cd18747090bf9d60295d0ce74309a78852a9caadf0af48aae1c6253839624076224374bc63f",
+        "xprv9s21ZrQH143K2QWV9Wn8Vvs6jbqfF1YbTCdURQW9dLFKDovpKaKrqS3SEWsXCu6ZNky9PSAENg6c9AQYHcg4PjopRGGKmdD313ZHszymnps",
+        vector_22
+    );
+    bip39_vector!(
+        "c10ec20dc3cd9f652c7fac2f1230f7a3c828389a14392f05",
+        "scissors invite lock maple supreme raw rapid void congress muscle digital elegant little brisk hair mango congress clump",
+        "7b4a10be9d98e6cba265566db7f136718e1398c71cb581e1b2f464cac1ceedf4f3e274dc270003c670ad8d02c4558b2f8e39edea2775c9e232c7cb798b069e88",
+        "xprv9s21ZrQH143K4aERa2bq7559eMCCEs2QmmqVjUuzfy5eAeDX4mqZffkYwpzGQRE2YEEeLVRoH4CSHxianrFaVnMN2RYaPUZJhJx8S5j6puX",
+        vector_23
+    );
+    bip39_vector!(
+        "f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f",
+        "void come effort suffer camp survey warrior heavy shoot primary clutch crush open amazing screen patrol group space point ten exist slush involve unfold",
+        "01f5bced59dec48e362f2c45b5de68b9fd6c92c6634f44d6d40aab69056506f0e35524a518034ddc1192e1dacd32c1ed3eaa3c3b131c88ed8e7e54c49a5d0998",
+        "xprv9s21ZrQH143K39rnQJknpH1WEPFJrzmAqqasiDcVrNuk926oizzJDDQkdiTvNPr2FYDYzWgiMiC63YmfPAa2oPyNB23r2g7d1yiK6WpqaQS",
+        vector_24
+    );
+    #[test]
+    fn generate_mnemonic() {
+        let mnemonic = Mnemonic::generate(32, &mut OsEntropy).unwrap();
+        let phrase = mnemonic.phrase();
+
+        assert_eq!(phrase.split(' ').count(), 24);
+        assert_eq!(Mnemonic::from_phrase(&phrase).unwrap(), mnemonic);
+    }
+
+    #[test]
+    fn generate_mnemonic_from_deterministic_entropy() {
+        let mnemonic = Mnemonic::generate(16, &mut DeterministicEntropy::new(b"bitcoin_rules")).unwrap();
+        assert_eq!(
+            mnemonic.entropy(),
+            &hex_string_to_bytes("43618508B9039CFB3E7ACC92A1D631F4").unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn generate_mnemonic_invalid_length() {
+        let res = Mnemonic::generate(15, &mut OsEntropy);
+        assert_eq!(res.err().unwrap().to_string(), "invalid_entropy_length");
+    }
+
+    #[test]
+    fn seed_without_passphrase() {
+        let mnemonic = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )
+        .unwrap();
+
+        assert_eq!(
+            mnemonic.to_seed("").to_vec(),
+            hex_string_to_bytes("5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4").unwrap()
+        );
+    }
+
+    #[test]
+    fn phrase_with_extra_whitespaces() {
+        let mnemonic = Mnemonic::from_phrase(
+            "  abandon abandon abandon abandon abandon abandon\tabandon abandon abandon abandon abandon about\n",
+        );
+
+        assert!(mnemonic.is_ok());
+    }
+
+    macro_rules! invalid_entropy {
+        ($length:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let res = Mnemonic::from_entropy(&[0u8; $length]);
+                assert_eq!(res.err().unwrap().to_string(), "invalid_entropy_length");
+            }
+        };
+    }
+
+    invalid_entropy!(12, invalid_entropy_too_short);
+    invalid_entropy!(36, invalid_entropy_too_long);
+    invalid_entropy!(17, invalid_entropy_not_multiple_of_32_bits);
+
+    macro_rules! invalid_phrase {
+        ($phrase:literal, $reason:literal, $f:ident) => {
+            #[test]
+            fn $f() {
+                let res = Mnemonic::from_phrase($phrase);
+                assert_eq!(res.err().unwrap().to_string(), $reason);
+            }
+        };
+    }
+
+    invalid_phrase!(
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon",
+        "invalid_checksum",
+        invalid_phrase_checksum
+    );
+    invalid_phrase!(
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo",
+        "invalid_checksum",
+        invalid_phrase_checksum_24_words
+    );
+    invalid_phrase!(
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "invalid_word_count",
+        invalid_phrase_word_count
+    );
+    invalid_phrase!("", "invalid_word_count", invalid_phrase_empty);
+    invalid_phrase!(
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon bitcoin",
+        "invalid_word",
+        invalid_phrase_unknown_word
+    );
+    invalid_phrase!(
+        "Abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "invalid_word",
+        invalid_phrase_upper_case_word
+    );
+}
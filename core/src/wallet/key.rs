@@ -1,29 +1,23 @@
-// To create a more deterministic tests, See also
-// https://en.wikipedia.org/wiki/Linear_congruential_generator to generate
-// a deterministic seed within a sequence of pseudo-randomized numbers
+use std::fmt::{Debug, Display, Formatter};
 
-use std::fmt::{Display, Formatter};
-
-use rug::{
-    rand::{ThreadRandGen, ThreadRandState},
-    Integer,
-};
+use rug::{integer::Order, Integer};
+use zeroize::Zeroize;
 
 use crate::{
-    bitcoin::ecdsa::P,
+    bitcoin::ecdsa::N,
     flags::{compression::Compression, network::Network},
     keys::key::Key,
-    std_lib::{rand::generate_rand_32, vector::bytes_to_hex_string},
+    std_lib::{
+        integer_extended::IntegerExtended,
+        rand::{EntropySource, OsEntropy},
+        std_result::StdResult,
+        vector::bytes_to_hex_string,
+    },
 };
 
-struct Seed();
-impl ThreadRandGen for Seed {
-    fn gen(&mut self) -> u32 {
-        generate_rand_32()
-    }
-}
+// A candidate out of range has probability ~2^-128: many failures mean a broken entropy source.
+const MAX_SECRET_ATTEMPTS: usize = 16;
 
-#[derive(Debug)]
 pub struct UserKey {
     pub secret: Integer,
     pub pubkey: Vec<u8>,
@@ -31,12 +25,18 @@ pub struct UserKey {
     pub key: Key,
 }
 
+impl Drop for UserKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+// Public data only: the secret is never printed (see `wallet_file` to export it)
 impl Display for UserKey {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(
             f,
-            "privkey: {:}\npubkey: {:} (len: {:})\naddress: {:}",
-            self.secret,
+            "pubkey: {:} (len: {:})\naddress: {:}",
             bytes_to_hex_string(&self.pubkey),
             self.pubkey.len(),
             self.address,
@@ -44,37 +44,148 @@ impl Display for UserKey {
     }
 }
 
-// Deep investigate using /dev/urandom
-// see also https://github.com/trezor/trezor-firmware/blob/main/core/embed/trezorhal/unix/rng.c#L26
-#[allow(dead_code)]
-fn get_seed_from_system() -> u32 {
-    use std::{fs::File, io::Read};
+impl Debug for UserKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserKey")
+            .field("pubkey", &bytes_to_hex_string(&self.pubkey))
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+/// New random secret in [1, N - 1] from 256 bits of `entropy`.
+/// Out of range candidates are rejected (not reduced by modulo, to avoid any bias).
+pub fn generate_secret(entropy: &mut dyn EntropySource) -> StdResult<Integer> {
+    for _ in 0..MAX_SECRET_ATTEMPTS {
+        let mut bytes = [0u8; 32];
+        entropy.fill_bytes(&mut bytes)?;
 
-    let mut rnd = File::open("/dev/urandom").unwrap();
+        let candidate = Integer::from_digits(&bytes, Order::Msf);
+        bytes.zeroize();
 
-    let mut buffer = [0u8; 4];
-    rnd.read_exact(&mut buffer).unwrap();
+        if candidate >= 1 && candidate < *N {
+            return Ok(candidate);
+        }
+    }
 
-    u32::from_le_bytes(buffer)
+    Err("invalid_entropy_source".into())
 }
 
 // Usage:
 //  let user_key = new(Network::Testnet);
 pub fn new(network: Network) -> UserKey {
-    let mut seed = Seed();
-    let mut rand = ThreadRandState::new_custom(&mut seed);
+    new_from_entropy(network, &mut OsEntropy).expect("key generation from system entropy failed")
+}
 
-    let max_value = (*P).clone();
-    let secret = max_value.random_below(&mut rand);
+/// New `UserKey` from a given `EntropySource` (e.g. `DeterministicEntropy` for reproducible tests).
+pub fn new_from_entropy(network: Network, entropy: &mut dyn EntropySource) -> StdResult<UserKey> {
+    let secret = generate_secret(entropy)?;
 
     let key = Key::new(secret.clone());
     let address = key.address(Compression::Compressed, network);
     let pubkey = key.public_key_sec();
 
-    UserKey {
+    Ok(UserKey {
         secret,
         pubkey,
         address,
         key,
+    })
+}
+
+#[cfg(test)]
+mod key_test {
+    use super::*;
+    use crate::std_lib::{integer_extended::IntegerExtended, rand::DeterministicEntropy};
+
+    /// Return the given chunks in sequence.
+    struct FixedEntropy(Vec<[u8; 32]>);
+
+    impl EntropySource for FixedEntropy {
+        fn fill_bytes(&mut self, dest: &mut [u8]) -> StdResult<()> {
+            if self.0.is_empty() {
+                Err("no_more_entropy")?;
+            }
+
+            dest.copy_from_slice(&self.0.remove(0));
+
+            Ok(())
+        }
+    }
+
+    fn n_bytes() -> [u8; 32] {
+        let mut res = [0u8; 32];
+        res.copy_from_slice(&(*N).to_digits::<u8>(Order::Msf));
+
+        res
+    }
+
+    #[test]
+    fn new_key() {
+        let user_key = new(Network::Testnet);
+
+        assert!(user_key.secret >= 1 && user_key.secret < *N);
+        assert_eq!(user_key.pubkey.len(), 33);
+        assert_eq!(*user_key.key.private_key(), user_key.secret);
+    }
+
+    #[test]
+    fn secret_is_not_printed() {
+        let user_key = new_from_entropy(Network::Testnet, &mut DeterministicEntropy::new(b"bitcoin_rules")).unwrap();
+
+        for printed in [user_key.to_string(), format!("{:?}", user_key)] {
+            assert!(printed.contains(&user_key.address));
+            assert!(!printed.contains(&user_key.secret.to_string()));
+            assert!(!printed.contains("43618508B9039CFB3E7ACC92A1D631F4507842A61D66AB28B5C271D5BB410A7D"));
+        }
+    }
+
+    #[test]
+    fn new_keys_are_different() {
+        assert_ne!(new(Network::Mainnet).secret, new(Network::Mainnet).secret);
+    }
+
+    #[test]
+    fn new_key_from_deterministic_entropy() {
+        let user_key = new_from_entropy(Network::Testnet, &mut DeterministicEntropy::new(b"bitcoin_rules")).unwrap();
+        let same_key = new_from_entropy(Network::Testnet, &mut DeterministicEntropy::new(b"bitcoin_rules")).unwrap();
+
+        assert_eq!(
+            user_key.secret,
+            Integer::from_hex_str("43618508B9039CFB3E7ACC92A1D631F4507842A61D66AB28B5C271D5BB410A7D")
+        );
+        assert_eq!(user_key.address, same_key.address);
+    }
+
+    #[test]
+    fn reject_zero_and_out_of_range_secrets() {
+        let mut n_minus_one = n_bytes();
+        n_minus_one[31] -= 1;
+
+        let mut entropy = FixedEntropy(vec![[0u8; 32], n_bytes(), [0xFFu8; 32], n_minus_one]);
+        let secret = generate_secret(&mut entropy).unwrap();
+
+        assert_eq!(secret, (*N).clone() - 1);
+    }
+
+    #[test]
+    fn secret_in_range_is_accepted() {
+        let mut one = [0u8; 32];
+        one[31] = 1;
+
+        let secret = generate_secret(&mut FixedEntropy(vec![one])).unwrap();
+        assert_eq!(secret, 1);
+    }
+
+    #[test]
+    fn entropy_source_error() {
+        let res = generate_secret(&mut FixedEntropy(vec![]));
+        assert_eq!(res.err().unwrap().to_string(), "no_more_entropy");
+    }
+
+    #[test]
+    fn broken_entropy_source() {
+        let res = generate_secret(&mut FixedEntropy(vec![[0u8; 32]; MAX_SECRET_ATTEMPTS]));
+        assert_eq!(res.err().unwrap().to_string(), "invalid_entropy_source");
     }
 }
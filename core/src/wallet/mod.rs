@@ -1 +1,6 @@
+pub mod address;
+pub mod bip32;
+pub mod bip39;
 pub mod key;
+pub mod wallet_file;
+pub mod watch_only;
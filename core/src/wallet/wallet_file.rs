@@ -0,0 +1,753 @@
+/*
+    Encrypted wallet file: keys, descriptors, labels and metadata, sealed with a passphrase.
+        magic           "BRWL"
+        version         1 byte
+        scrypt params   log_n (1 byte), r, p (4 bytes LE each)
+        salt            16 bytes
+        nonce           12 bytes
+        sealed          ChaCha20-Poly1305 of the contents (with the tag), the header as associated data
+    The key is scrypt(passphrase, salt): a wrong passphrase and a corrupted file cannot be told apart.
+
+    Contents:
+        network         1 byte
+        keys            count, then <secret (32 bytes)> <label>
+        descriptors     count, then <descriptor> <label>
+        labels          count, then <address> <label>
+        metadata        count, then <name> <value>
+    Counts are compact sizes, strings compact size prefixed.
+
+    While unlocked the key and the contents are kept in memory (erased when locked), changes are written when sealed.
+    Exports contain public data only, unless secrets are asked for.
+*/
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Formatter},
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+};
+
+use rug::{integer::Order, Integer};
+use zeroize::Zeroize;
+
+use crate::{
+    bitcoin::ecdsa::N,
+    descriptor::output_descriptor::Descriptor,
+    encryption::{
+        chacha20_poly1305::{decrypt, encrypt, KEY_LENGTH, NONCE_LENGTH},
+        scrypt::{scrypt, ScryptParams},
+    },
+    flags::{compression::Compression, network::Network},
+    keys::key::Key,
+    std_lib::{
+        rand::EntropySource,
+        std_result::StdResult,
+        varint::{decode, encode},
+        vector::{bytes_to_hex_string, padding_left},
+    },
+};
+
+const MAGIC: &[u8; 4] = b"BRWL";
+const VERSION: u8 = 0x01;
+const SALT_LENGTH: usize = 16;
+const HEADER_LENGTH: usize = 4 + 1 + 1 + 4 + 4 + SALT_LENGTH + NONCE_LENGTH;
+
+pub struct WalletKey {
+    secret: Integer,
+    pub label: String,
+}
+
+impl Drop for WalletKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+// The secret is never printed
+impl Debug for WalletKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalletKey")
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WalletKey {
+    pub fn new(secret: Integer, label: &str) -> StdResult<Self> {
+        if secret < 1 || secret >= *N {
+            Err("invalid_secret")?;
+        }
+
+        Ok(Self {
+            secret,
+            label: label.to_string(),
+        })
+    }
+
+    pub fn key(&self) -> Key {
+        Key::new(self.secret.clone())
+    }
+
+    // Compressed SEC
+    pub fn public_key(&self) -> Vec<u8> {
+        self.key().public_key_sec()
+    }
+
+    // P2PKH of the compressed key
+    pub fn address(&self, network: Network) -> String {
+        self.key().address(Compression::Compressed, network)
+    }
+
+    pub fn wif(&self, network: Network) -> String {
+        self.key().to_wif(Compression::Compressed, network)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletDescriptor {
+    pub descriptor: Descriptor,
+    pub label: String,
+}
+
+#[derive(Debug)]
+pub struct WalletContents {
+    pub network: Network,
+    pub keys: Vec<WalletKey>,
+    pub descriptors: Vec<WalletDescriptor>,
+    // by address
+    pub labels: BTreeMap<String, String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl WalletContents {
+    pub fn new(network: Network) -> Self {
+        Self {
+            network,
+            keys: vec![],
+            descriptors: vec![],
+            labels: BTreeMap::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_key(&mut self, secret: Integer, label: &str) -> StdResult<()> {
+        if self.keys.iter().any(|key| key.secret == secret) {
+            Err("key_already_in_wallet")?;
+        }
+
+        self.keys.push(WalletKey::new(secret, label)?);
+
+        Ok(())
+    }
+
+    pub fn add_descriptor(&mut self, descriptor: &str, label: &str) -> StdResult<()> {
+        let descriptor = Descriptor::parse(descriptor, self.network)?;
+
+        self.descriptors.push(WalletDescriptor {
+            descriptor,
+            label: label.to_string(),
+        });
+
+        Ok(())
+    }
+
+    pub fn set_label(&mut self, address: &str, label: &str) {
+        self.labels.insert(address.to_string(), label.to_string());
+    }
+
+    pub fn set_metadata(&mut self, name: &str, value: &str) {
+        self.metadata.insert(name.to_string(), value.to_string());
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        let mut res = vec![self.network as u8];
+
+        res.extend(encode(self.keys.len() as u64));
+        for key in self.keys.iter() {
+            let mut secret = padding_left(&key.secret.to_digits::<u8>(Order::Msf), 32, 0);
+            res.extend_from_slice(&secret);
+            secret.zeroize();
+            res.extend(encode_string(&key.label));
+        }
+
+        res.extend(encode(self.descriptors.len() as u64));
+        for descriptor in self.descriptors.iter() {
+            res.extend(encode_string(&descriptor.descriptor.to_string()));
+            res.extend(encode_string(&descriptor.label));
+        }
+
+        for map in [&self.labels, &self.metadata] {
+            res.extend(encode(map.len() as u64));
+            for (name, value) in map.iter() {
+                res.extend(encode_string(name));
+                res.extend(encode_string(value));
+            }
+        }
+
+        res
+    }
+
+    fn deserialize(data: &[u8]) -> StdResult<Self> {
+        let mut cursor = 0;
+
+        let network = match read_bytes(data, &mut cursor, 1)?[0] {
+            n if n == Network::Mainnet as u8 => Network::Mainnet,
+            n if n == Network::Testnet as u8 => Network::Testnet,
+            _ => Err("invalid_wallet_network")?,
+        };
+        let mut contents = WalletContents::new(network);
+
+        for _ in 0..read_count(data, &mut cursor)? {
+            let secret = Integer::from_digits(read_bytes(data, &mut cursor, 32)?, Order::Msf);
+            contents.add_key(secret, &read_string(data, &mut cursor)?)?;
+        }
+
+        for _ in 0..read_count(data, &mut cursor)? {
+            let descriptor = read_string(data, &mut cursor)?;
+            contents.add_descriptor(&descriptor, &read_string(data, &mut cursor)?)?;
+        }
+
+        for _ in 0..read_count(data, &mut cursor)? {
+            let address = read_string(data, &mut cursor)?;
+            contents.set_label(&address, &read_string(data, &mut cursor)?);
+        }
+
+        for _ in 0..read_count(data, &mut cursor)? {
+            let name = read_string(data, &mut cursor)?;
+            contents.set_metadata(&name, &read_string(data, &mut cursor)?);
+        }
+
+        if cursor != data.len() {
+            Err("invalid_wallet_file")?;
+        }
+
+        Ok(contents)
+    }
+}
+
+pub struct WalletFile {
+    params: ScryptParams,
+    salt: [u8; SALT_LENGTH],
+    nonce: [u8; NONCE_LENGTH],
+    sealed: Vec<u8>,
+    // while unlocked
+    key: [u8; KEY_LENGTH],
+    contents: Option<WalletContents>,
+}
+
+impl Drop for WalletFile {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl Debug for WalletFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalletFile")
+            .field("params", &self.params)
+            .field("locked", &self.is_locked())
+            .finish_non_exhaustive()
+    }
+}
+
+impl WalletFile {
+    // Sealed with `passphrase`, unlocked
+    pub fn create(
+        contents: WalletContents,
+        passphrase: &str,
+        params: ScryptParams,
+        entropy: &mut dyn EntropySource,
+    ) -> StdResult<Self> {
+        let mut salt = [0u8; SALT_LENGTH];
+        entropy.fill_bytes(&mut salt)?;
+
+        let mut wallet = Self {
+            params,
+            salt,
+            nonce: [0u8; NONCE_LENGTH],
+            sealed: vec![],
+            key: derive_key(passphrase, &salt, params)?,
+            contents: Some(contents),
+        };
+        wallet.seal(entropy)?;
+
+        Ok(wallet)
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.contents.is_none()
+    }
+
+    pub fn contents(&self) -> StdResult<&WalletContents> {
+        match &self.contents {
+            Some(contents) => Ok(contents),
+            None => Err("wallet_locked")?,
+        }
+    }
+
+    pub fn contents_mut(&mut self) -> StdResult<&mut WalletContents> {
+        match &mut self.contents {
+            Some(contents) => Ok(contents),
+            None => Err("wallet_locked")?,
+        }
+    }
+
+    pub fn unlock(&mut self, passphrase: &str) -> StdResult<()> {
+        let mut key = derive_key(passphrase, &self.salt, self.params)?;
+
+        match self.open(&key) {
+            Ok(contents) => {
+                self.key = key;
+                self.contents = Some(contents);
+                key.zeroize();
+
+                Ok(())
+            }
+            Err(e) => {
+                key.zeroize();
+                Err(e)
+            }
+        }
+    }
+
+    // Seals the changes, then forgets the key and the contents
+    pub fn lock(&mut self, entropy: &mut dyn EntropySource) -> StdResult<()> {
+        self.seal(entropy)?;
+
+        self.contents = None;
+        self.key.zeroize();
+
+        Ok(())
+    }
+
+    // Encrypts the contents again (with a new nonce)
+    pub fn seal(&mut self, entropy: &mut dyn EntropySource) -> StdResult<()> {
+        let mut plaintext = self.contents()?.serialize();
+
+        entropy.fill_bytes(&mut self.nonce)?;
+        self.sealed = encrypt(&self.key, &self.nonce, &plaintext, &self.header());
+        plaintext.zeroize();
+
+        Ok(())
+    }
+
+    // A new salt and key, same lock state
+    pub fn change_passphrase(
+        &mut self,
+        passphrase: &str,
+        new_passphrase: &str,
+        entropy: &mut dyn EntropySource,
+    ) -> StdResult<()> {
+        let was_locked = self.is_locked();
+
+        // the current passphrase is always checked
+        let mut key = derive_key(passphrase, &self.salt, self.params)?;
+        let opened = self.open(&key);
+        key.zeroize();
+        let opened = opened?;
+
+        if was_locked {
+            self.contents = Some(opened);
+        }
+
+        entropy.fill_bytes(&mut self.salt)?;
+        self.key = derive_key(new_passphrase, &self.salt, self.params)?;
+
+        if was_locked {
+            self.lock(entropy)
+        } else {
+            self.seal(entropy)
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        [self.header(), self.sealed.clone()].concat()
+    }
+
+    // Locked
+    pub fn deserialize(data: &[u8]) -> StdResult<Self> {
+        if data.len() < HEADER_LENGTH || &data[..4] != MAGIC {
+            Err("invalid_wallet_file")?;
+        }
+
+        if data[4] != VERSION {
+            Err("unsupported_wallet_version")?;
+        }
+
+        let le_u32 = |from: usize| u32::from_le_bytes([data[from], data[from + 1], data[from + 2], data[from + 3]]);
+        let params = ScryptParams::new(data[5], le_u32(6), le_u32(10))?;
+
+        let mut salt = [0u8; SALT_LENGTH];
+        salt.copy_from_slice(&data[14..14 + SALT_LENGTH]);
+        let mut nonce = [0u8; NONCE_LENGTH];
+        nonce.copy_from_slice(&data[14 + SALT_LENGTH..HEADER_LENGTH]);
+
+        Ok(Self {
+            params,
+            salt,
+            nonce,
+            sealed: data[HEADER_LENGTH..].to_vec(),
+            key: [0u8; KEY_LENGTH],
+            contents: None,
+        })
+    }
+
+    pub fn load(path: &str) -> StdResult<Self> {
+        Self::deserialize(&std::fs::read(path)?)
+    }
+
+    /*
+        Seals the changes first, if unlocked.
+        The file is replaced atomically: written to `<path>.tmp` (readable by the owner only), synced, then renamed.
+    */
+    pub fn save(&mut self, path: &str, entropy: &mut dyn EntropySource) -> StdResult<()> {
+        if !self.is_locked() {
+            self.seal(entropy)?;
+        }
+
+        let temporary = format!("{path}.tmp");
+        match fs::remove_file(&temporary) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e)?,
+            _ => {}
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(&temporary)?;
+        file.write_all(&self.serialize())?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temporary, path)?;
+
+        // the rename itself
+        #[cfg(unix)]
+        {
+            let directory = std::path::Path::new(path)
+                .parent()
+                .filter(|d| !d.as_os_str().is_empty());
+            if let Some(directory) = directory {
+                fs::File::open(directory)?.sync_all()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /*
+        One line for each item:
+            network <network>
+            metadata <name> <value>
+            key <address> <public key> [<WIF>] <label>
+            descriptor <descriptor> <label>
+            label <address> <label>
+        Descriptors with private keys are exported with their public keys (or omitted if they have no public form),
+        unless `include_secrets`.
+    */
+    pub fn export(&self, include_secrets: bool) -> StdResult<String> {
+        let contents = self.contents()?;
+        let network = contents.network;
+
+        let mut lines = vec![match network {
+            Network::Mainnet => "network mainnet".to_string(),
+            Network::Testnet => "network testnet".to_string(),
+        }];
+
+        for (name, value) in contents.metadata.iter() {
+            lines.push(format!("metadata {name} {value}"));
+        }
+
+        for key in contents.keys.iter() {
+            let public = format!("{} {}", key.address(network), bytes_to_hex_string(&key.public_key()));
+            if include_secrets {
+                lines.push(format!("key {public} {} {}", key.wif(network), key.label));
+            } else {
+                lines.push(format!("key {public} {}", key.label));
+            }
+        }
+
+        for descriptor in contents.descriptors.iter() {
+            let exported = if descriptor.descriptor.has_private_keys() && !include_secrets {
+                descriptor.descriptor.public().ok()
+            } else {
+                Some(descriptor.descriptor.clone())
+            };
+
+            if let Some(exported) = exported {
+                lines.push(format!("descriptor {exported} {}", descriptor.label));
+            }
+        }
+
+        for (address, label) in contents.labels.iter() {
+            lines.push(format!("label {address} {label}"));
+        }
+
+        Ok(lines.join("\n"))
+    }
+
+    fn header(&self) -> Vec<u8> {
+        [
+            MAGIC.as_slice(),
+            &[VERSION, self.params.log_n],
+            &self.params.r.to_le_bytes(),
+            &self.params.p.to_le_bytes(),
+            &self.salt,
+            &self.nonce,
+        ]
+        .concat()
+    }
+
+    fn open(&self, key: &[u8; KEY_LENGTH]) -> StdResult<WalletContents> {
+        let mut plaintext =
+            decrypt(key, &self.nonce, &self.sealed, &self.header()).map_err(|_| "invalid_passphrase")?;
+
+        let contents = WalletContents::deserialize(&plaintext);
+        plaintext.zeroize();
+
+        contents
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: ScryptParams) -> StdResult<[u8; KEY_LENGTH]> {
+    let mut derived = scrypt(passphrase.as_bytes(), salt, params, KEY_LENGTH)?;
+
+    let mut key = [0u8; KEY_LENGTH];
+    key.copy_from_slice(&derived);
+    derived.zeroize();
+
+    Ok(key)
+}
+
+fn encode_string(s: &str) -> Vec<u8> {
+    [encode(s.len() as u64), s.as_bytes().to_vec()].concat()
+}
+
+fn read_bytes<'a>(data: &'a [u8], cursor: &mut usize, length: usize) -> StdResult<&'a [u8]> {
+    match data.get(*cursor..*cursor + length) {
+        Some(bytes) => {
+            *cursor += length;
+            Ok(bytes)
+        }
+        None => Err("invalid_wallet_file")?,
+    }
+}
+
+fn read_count(data: &[u8], cursor: &mut usize) -> StdResult<u64> {
+    let count = decode(data, *cursor).map_err(|_| "invalid_wallet_file")?;
+    *cursor += count.length;
+
+    Ok(count.value)
+}
+
+fn read_string(data: &[u8], cursor: &mut usize) -> StdResult<String> {
+    let length = read_count(data, cursor)? as usize;
+    let bytes = read_bytes(data, cursor, length)?;
+
+    let s = String::from_utf8(bytes.to_vec()).map_err(|_| "invalid_wallet_file")?;
+
+    Ok(s)
+}
+
+#[cfg(test)]
+mod wallet_file_test {
+    use super::*;
+    use crate::std_lib::rand::DeterministicEntropy;
+
+    const XPRV: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+
+    // Fast to derive, for tests only
+    fn params() -> ScryptParams {
+        ScryptParams::new(4, 1, 1).unwrap()
+    }
+
+    fn contents() -> WalletContents {
+        let mut contents = WalletContents::new(Network::Mainnet);
+        contents.add_key(Integer::from(1), "first").unwrap();
+        contents
+            .add_descriptor(&format!("wpkh({XPRV}/0/*)"), "receive")
+            .unwrap();
+        contents.set_label("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH", "savings");
+        contents.set_metadata("name", "test");
+
+        contents
+    }
+
+    fn wallet(entropy: &mut DeterministicEntropy) -> WalletFile {
+        WalletFile::create(contents(), "passphrase", params(), entropy).unwrap()
+    }
+
+    #[test]
+    fn seal_and_unlock() {
+        let mut entropy = DeterministicEntropy::new(b"wallet");
+        let data = wallet(&mut entropy).serialize();
+
+        let mut wallet = WalletFile::deserialize(&data).unwrap();
+        assert!(wallet.is_locked());
+        assert_eq!(wallet.contents().expect_err("Err").to_string(), "wallet_locked");
+        assert_eq!(
+            wallet.unlock("wrong").expect_err("Err").to_string(),
+            "invalid_passphrase"
+        );
+
+        wallet.unlock("passphrase").unwrap();
+        let unlocked = wallet.contents().unwrap();
+
+        assert_eq!(unlocked.network, Network::Mainnet);
+        assert_eq!(
+            unlocked.keys[0].address(Network::Mainnet),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+        );
+        assert_eq!(unlocked.keys[0].label, "first");
+        assert_eq!(
+            unlocked.descriptors[0].descriptor.to_string(),
+            contents().descriptors[0].descriptor.to_string()
+        );
+        assert_eq!(unlocked.labels["1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"], "savings");
+        assert_eq!(unlocked.metadata["name"], "test");
+    }
+
+    #[test]
+    fn secrets_are_encrypted() {
+        let data = wallet(&mut DeterministicEntropy::new(b"wallet")).serialize();
+
+        let secret = padding_left(&[0x01], 32, 0);
+        assert!(!data.windows(32).any(|window| window == secret.as_slice()));
+        assert!(!data.windows(4).any(|window| window == b"xprv"));
+    }
+
+    #[test]
+    fn changes_are_sealed_when_locked() {
+        let mut entropy = DeterministicEntropy::new(b"wallet");
+        let mut wallet = wallet(&mut entropy);
+
+        wallet
+            .contents_mut()
+            .unwrap()
+            .add_key(Integer::from(2), "second")
+            .unwrap();
+        wallet.lock(&mut entropy).unwrap();
+        assert!(wallet.is_locked());
+
+        let mut reloaded = WalletFile::deserialize(&wallet.serialize()).unwrap();
+        reloaded.unlock("passphrase").unwrap();
+        assert_eq!(reloaded.contents().unwrap().keys.len(), 2);
+    }
+
+    #[test]
+    fn change_passphrase() {
+        let mut entropy = DeterministicEntropy::new(b"wallet");
+        let mut wallet = wallet(&mut entropy);
+        wallet.lock(&mut entropy).unwrap();
+
+        assert_eq!(
+            wallet
+                .change_passphrase("wrong", "new", &mut entropy)
+                .expect_err("Err")
+                .to_string(),
+            "invalid_passphrase"
+        );
+
+        wallet.change_passphrase("passphrase", "new", &mut entropy).unwrap();
+        assert!(wallet.is_locked());
+
+        let mut reloaded = WalletFile::deserialize(&wallet.serialize()).unwrap();
+        assert!(reloaded.unlock("passphrase").is_err());
+        reloaded.unlock("new").unwrap();
+        assert_eq!(reloaded.contents().unwrap().keys.len(), 1);
+    }
+
+    #[test]
+    fn corrupted_file() {
+        let data = wallet(&mut DeterministicEntropy::new(b"wallet")).serialize();
+
+        // header (authenticated) and contents
+        for position in [5, 14, HEADER_LENGTH, data.len() - 1] {
+            let mut corrupted = data.clone();
+            corrupted[position] ^= 0x01;
+
+            let mut wallet = WalletFile::deserialize(&corrupted).unwrap();
+            assert_eq!(
+                wallet.unlock("passphrase").expect_err("Err").to_string(),
+                "invalid_passphrase"
+            );
+        }
+
+        let mut version = data.clone();
+        version[4] = 0x02;
+        assert_eq!(
+            WalletFile::deserialize(&version).expect_err("Err").to_string(),
+            "unsupported_wallet_version"
+        );
+
+        assert_eq!(
+            WalletFile::deserialize(&data[..10]).expect_err("Err").to_string(),
+            "invalid_wallet_file"
+        );
+    }
+
+    #[test]
+    fn export() {
+        let wallet = wallet(&mut DeterministicEntropy::new(b"wallet"));
+        // private key 1
+        let wif = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+
+        let public = wallet.export(false).unwrap();
+        assert!(public.starts_with("network mainnet\nmetadata name test\n"));
+        assert!(public.contains(
+            "key 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH 0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798 first"
+        ));
+        assert!(public.contains("descriptor wpkh(xpub"));
+        assert!(public.ends_with("label 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH savings"));
+        assert!(!public.contains(wif));
+        assert!(!public.contains(XPRV));
+
+        let private = wallet.export(true).unwrap();
+        assert!(private.contains(wif));
+        assert!(private.contains(XPRV));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut entropy = DeterministicEntropy::new(b"wallet");
+        let mut wallet = wallet(&mut entropy);
+        let path = std::env::temp_dir().join("wallet_file_test.wallet");
+        let path = path.to_str().unwrap();
+
+        wallet.contents_mut().unwrap().set_label("address", "label");
+        wallet.save(path, &mut entropy).unwrap();
+        // replaced
+        wallet.save(path, &mut entropy).unwrap();
+
+        assert!(!std::path::Path::new(&format!("{path}.tmp")).exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let mut loaded = WalletFile::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        loaded.unlock("passphrase").unwrap();
+        assert_eq!(loaded.contents().unwrap().labels["address"], "label");
+    }
+
+    #[test]
+    fn invalid_keys() {
+        let mut contents = WalletContents::new(Network::Testnet);
+
+        assert_eq!(
+            contents.add_key(Integer::from(0), "").expect_err("Err").to_string(),
+            "invalid_secret"
+        );
+
+        contents.add_key(Integer::from(1), "").unwrap();
+        assert_eq!(
+            contents.add_key(Integer::from(1), "").expect_err("Err").to_string(),
+            "key_already_in_wallet"
+        );
+    }
+}
@@ -0,0 +1,660 @@
+/*
+    Watch-only wallet: the scripts of public key sources (no private key), the outputs received and spent by them.
+
+    Each source has two chains of scripts (BIP44): receive (`/0/i`) and change (`/1/i`),
+    from an xpub and a script type, or from two ranged descriptors.
+    Scripts are derived ahead of the last one used (or given out) by the gap limit (BIP44, 20 by default):
+    a transaction paying to a script past it is not found, as a wallet restored from the same keys would not.
+
+    Blocks must be scanned in height order (see `block::full_block::chain_blocks` for raw block files),
+    unconfirmed transactions can be added before their block, that then confirms them.
+
+    Balance:
+        confirmed       UTXOs in a block
+        unconfirmed     UTXOs of transactions not in a block yet
+    The UTXOs spent by an unconfirmed transaction are not in the balance anymore.
+*/
+
+use std::collections::HashMap;
+
+use rug::Integer;
+
+use crate::{
+    block::full_block::{chain_blocks, read_block_file, Block},
+    descriptor::output_descriptor::Descriptor,
+    flags::{network::Network, network_magic::NetworkMagic},
+    hashing::hash160::hash160,
+    scripting::standard::{p2pkh_script, p2sh_script, p2wpkh_script},
+    std_lib::std_result::StdResult,
+    transaction::{builder::Spendable, script::Script, tx::Tx},
+};
+
+use super::{address::address, bip32::ExtendedPublicKey};
+
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+const RECEIVE_CHAIN: u32 = 0;
+const CHANGE_CHAIN: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptType {
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+}
+
+#[derive(Debug, Clone)]
+pub enum ScriptSource {
+    // `<xpub>/0/i` and `<xpub>/1/i`
+    Xpub {
+        xpub: ExtendedPublicKey,
+        script_type: ScriptType,
+    },
+    // ranged descriptors, as `<xpub>/0/*` and `<xpub>/1/*`
+    Descriptor {
+        receive: Descriptor,
+        change: Descriptor,
+    },
+}
+
+impl ScriptSource {
+    pub fn script_pub_key(&self, change: bool, index: u32) -> StdResult<Script> {
+        match self {
+            ScriptSource::Xpub { xpub, script_type } => {
+                let chain = if change { CHANGE_CHAIN } else { RECEIVE_CHAIN };
+                let public_key = xpub.derive_child(chain)?.derive_child(index)?.public_key_sec();
+
+                key_script_pub_key(&public_key, *script_type)
+            }
+            ScriptSource::Descriptor {
+                receive,
+                change: change_descriptor,
+            } => {
+                let descriptor = if change { change_descriptor } else { receive };
+
+                Ok(Script::new_from_script_lang(&descriptor.script_pub_key(index)?))
+            }
+        }
+    }
+}
+
+// Script of a compressed public key
+fn key_script_pub_key(public_key: &[u8], script_type: ScriptType) -> StdResult<Script> {
+    let public_key_hash = hash160(public_key);
+    let p2wpkh = p2wpkh_script(&public_key_hash);
+
+    let script_pub_key = match script_type {
+        ScriptType::P2pkh => p2pkh_script(&public_key_hash),
+        ScriptType::P2shP2wpkh => p2sh_script(&hash160(&p2wpkh.serialize()?)),
+        ScriptType::P2wpkh => p2wpkh,
+    };
+
+    Ok(Script::new_from_script_lang(&script_pub_key))
+}
+
+// Where a script comes from: source, chain and index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScriptOrigin {
+    pub source: usize,
+    pub change: bool,
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Balance {
+    pub confirmed: u64,
+    pub unconfirmed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletTx {
+    pub txid: String,
+    // None if unconfirmed
+    pub height: Option<u32>,
+    pub received: u64,
+    pub sent: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletUtxo {
+    pub txid: String,
+    pub vout: u32,
+    pub amount: u64,
+    pub script_pub_key: Script,
+    pub height: Option<u32>,
+    pub origin: ScriptOrigin,
+}
+
+// Scripts derived, and the next index given out, of a chain
+#[derive(Debug, Clone, Copy, Default)]
+struct Chain {
+    derived: u32,
+    next: u32,
+}
+
+pub struct WatchOnlyWallet {
+    network: Network,
+    gap_limit: u32,
+    sources: Vec<ScriptSource>,
+    // receive and change chains of each source
+    chains: Vec<[Chain; 2]>,
+    scripts: HashMap<Vec<u8>, ScriptOrigin>,
+    // by outpoint, transaction id as `TxIn::previous_transaction_id`
+    utxos: HashMap<(Integer, u32), WalletUtxo>,
+    history: Vec<WalletTx>,
+}
+
+impl WatchOnlyWallet {
+    pub fn new(network: Network, gap_limit: u32) -> Self {
+        Self {
+            network,
+            gap_limit,
+            sources: vec![],
+            chains: vec![],
+            scripts: HashMap::new(),
+            utxos: HashMap::new(),
+            history: vec![],
+        }
+    }
+
+    // Index of the source added
+    pub fn add_source(&mut self, source: ScriptSource) -> StdResult<usize> {
+        if let ScriptSource::Descriptor { receive, change } = &source {
+            if !receive.is_range() || !change.is_range() {
+                Err("descriptor_not_ranged")?;
+            }
+
+            if receive.network() != self.network || change.network() != self.network {
+                Err("incongruent_network")?;
+            }
+        }
+
+        self.sources.push(source);
+        self.chains.push([Chain::default(); 2]);
+
+        let source = self.sources.len() - 1;
+        self.derive_lookahead(source, false)?;
+        self.derive_lookahead(source, true)?;
+
+        Ok(source)
+    }
+
+    pub fn sources(&self) -> &[ScriptSource] {
+        &self.sources
+    }
+
+    // Address not used nor given out yet
+    pub fn new_address(&mut self, source: usize, change: bool) -> StdResult<String> {
+        let script_pub_key = self.new_script_pub_key(source, change)?;
+        address(&script_pub_key, self.network)
+    }
+
+    pub fn new_script_pub_key(&mut self, source: usize, change: bool) -> StdResult<Script> {
+        let chain = self.chain(source, change)?;
+        let index = chain.next;
+
+        chain.next += 1;
+        self.derive_lookahead(source, change)?;
+
+        self.sources[source].script_pub_key(change, index)
+    }
+
+    pub fn is_mine(&self, script_pub_key: &Script) -> Option<ScriptOrigin> {
+        self.scripts.get(&script_pub_key.raw).copied()
+    }
+
+    // Number of wallet transactions in the block
+    pub fn scan_block(&mut self, block: &Block, height: u32) -> StdResult<usize> {
+        let mut found: usize = 0;
+
+        for tx in block.transactions.iter() {
+            if self.add_tx(tx, Some(height))? {
+                found += 1;
+            }
+        }
+
+        Ok(found)
+    }
+
+    /*
+        Blocks of a raw block file following `previous_block`, at `height` + 1 on.
+        Height of the last block scanned, `height` if none.
+    */
+    pub fn scan_block_file(
+        &mut self,
+        data: &[u8],
+        magic: NetworkMagic,
+        previous_block: &Integer,
+        height: u32,
+    ) -> StdResult<u32> {
+        let blocks = chain_blocks(read_block_file(data, magic, self.network)?, previous_block);
+
+        let mut height = height;
+        for block in blocks.iter() {
+            height += 1;
+            self.scan_block(block, height)?;
+        }
+
+        Ok(height)
+    }
+
+    // False if the transaction does not belong to the wallet
+    pub fn add_unconfirmed_tx(&mut self, tx: &Tx) -> StdResult<bool> {
+        self.add_tx(tx, None)
+    }
+
+    pub fn balance(&self) -> Balance {
+        let mut balance = Balance {
+            confirmed: 0,
+            unconfirmed: 0,
+        };
+
+        for utxo in self.utxos.values() {
+            match utxo.height {
+                Some(_) => balance.confirmed += utxo.amount,
+                None => balance.unconfirmed += utxo.amount,
+            }
+        }
+
+        balance
+    }
+
+    // By height, unconfirmed last
+    pub fn history(&self) -> Vec<&WalletTx> {
+        let mut history: Vec<&WalletTx> = self.history.iter().collect();
+        history.sort_by_key(|tx| tx.height.unwrap_or(u32::MAX));
+
+        history
+    }
+
+    // By height, unconfirmed last
+    pub fn utxos(&self) -> Vec<&WalletUtxo> {
+        let mut utxos: Vec<&WalletUtxo> = self.utxos.values().collect();
+        utxos.sort_by(|a, b| {
+            (a.height.unwrap_or(u32::MAX), &a.txid, a.vout).cmp(&(b.height.unwrap_or(u32::MAX), &b.txid, b.vout))
+        });
+
+        utxos
+    }
+
+    // Confirmed UTXOs, for `TxBuilder` (unsigned transactions)
+    pub fn spendables(&self) -> StdResult<Vec<Spendable>> {
+        self.utxos()
+            .into_iter()
+            .filter(|utxo| utxo.height.is_some())
+            .map(|utxo| Spendable::new_watch_only(&utxo.txid, utxo.vout, utxo.amount, utxo.script_pub_key.clone()))
+            .collect()
+    }
+
+    // Already known transactions are only confirmed (if `height` is given)
+    fn add_tx(&mut self, tx: &Tx, height: Option<u32>) -> StdResult<bool> {
+        let txid = tx.id();
+
+        if let Some(known) = self.history.iter_mut().find(|known| known.txid == txid) {
+            if height.is_some() {
+                known.height = height;
+
+                for utxo in self.utxos.values_mut().filter(|utxo| utxo.txid == txid) {
+                    utxo.height = height;
+                }
+            }
+
+            return Ok(true);
+        }
+
+        let mut sent: u64 = 0;
+        for index in 0..tx.input_len() {
+            let input = tx.input(index)?;
+            let outpoint = (input.previous_transaction_id.clone(), input.previous_transaction_index);
+
+            if let Some(utxo) = self.utxos.remove(&outpoint) {
+                sent += utxo.amount;
+            }
+        }
+
+        let mut received: u64 = 0;
+        let mut found = sent > 0;
+        for vout in 0..tx.output_len() {
+            let output = tx.output(vout)?;
+
+            // a script used can derive the next ones: checked one output at a time
+            let Some(origin) = self.is_mine(&output.script_pub_key) else {
+                continue;
+            };
+
+            received += output.amount;
+            found = true;
+
+            self.utxos.insert(
+                (tx.id_as_integer(), vout as u32),
+                WalletUtxo {
+                    txid: txid.clone(),
+                    vout: vout as u32,
+                    amount: output.amount,
+                    script_pub_key: output.script_pub_key.clone(),
+                    height,
+                    origin,
+                },
+            );
+
+            self.mark_used(origin)?;
+        }
+
+        if found {
+            self.history.push(WalletTx {
+                txid,
+                height,
+                received,
+                sent,
+            });
+        }
+
+        Ok(found)
+    }
+
+    fn mark_used(&mut self, origin: ScriptOrigin) -> StdResult<()> {
+        let chain = self.chain(origin.source, origin.change)?;
+        chain.next = chain.next.max(origin.index + 1);
+
+        self.derive_lookahead(origin.source, origin.change)
+    }
+
+    // Scripts up to the gap limit after the next index
+    fn derive_lookahead(&mut self, source: usize, change: bool) -> StdResult<()> {
+        let gap_limit = self.gap_limit;
+        let chain = *self.chain(source, change)?;
+
+        for index in chain.derived..chain.next + gap_limit {
+            let script_pub_key = self.sources[source].script_pub_key(change, index)?;
+            self.scripts
+                .insert(script_pub_key.raw, ScriptOrigin { source, change, index });
+        }
+
+        let chain = self.chain(source, change)?;
+        chain.derived = chain.derived.max(chain.next + gap_limit);
+
+        Ok(())
+    }
+
+    fn chain(&mut self, source: usize, change: bool) -> StdResult<&mut Chain> {
+        match self.chains.get_mut(source) {
+            Some(chains) => Ok(&mut chains[change as usize]),
+            None => Err("unknown_script_source")?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod watch_only_test {
+    use crate::{
+        block::header::Header,
+        transaction::{builder::TxBuilder, tx_in::TxIn, tx_out::TxOut},
+        wallet::bip32::ExtendedPrivateKey,
+    };
+
+    use super::*;
+
+    const NETWORK: Network = Network::Testnet;
+    const GAP_LIMIT: u32 = 5;
+
+    fn source(script_type: ScriptType) -> ScriptSource {
+        let master = ExtendedPrivateKey::new_master(&[0x01; 32], NETWORK).unwrap();
+
+        ScriptSource::Xpub {
+            xpub: master.extended_public_key(),
+            script_type,
+        }
+    }
+
+    fn wallet() -> WatchOnlyWallet {
+        let mut wallet = WatchOnlyWallet::new(NETWORK, GAP_LIMIT);
+        wallet.add_source(source(ScriptType::P2wpkh)).unwrap();
+
+        wallet
+    }
+
+    // `inputs` not empty, or the transaction would be read back as segwit
+    fn tx(inputs: &[(Integer, u32)], outputs: Vec<(u64, Script)>) -> Tx {
+        let mut tx = Tx::new(NETWORK);
+
+        for (id, index) in inputs {
+            tx.add_input(TxIn::new(id.clone(), *index, Script::new_empty(), 0xFFFFFFFF, NETWORK));
+        }
+
+        for (amount, script_pub_key) in outputs {
+            tx.add_output(TxOut::new(amount, script_pub_key));
+        }
+
+        tx
+    }
+
+    fn block(previous: Option<&Block>, transactions: Vec<Tx>) -> Block {
+        let previous_block = previous.map_or(Integer::new(), |b| b.id_as_integer());
+        let header = Header::new(1, previous_block, Integer::new(), 0, 0x207FFFFF, 0);
+
+        Block::new(header, transactions)
+    }
+
+    fn script(source: &ScriptSource, change: bool, index: u32) -> Script {
+        source.script_pub_key(change, index).unwrap()
+    }
+
+    fn foreign() -> Script {
+        Script::new_from_script_lang(&p2pkh_script(&[0xAB; 20]))
+    }
+
+    #[test]
+    fn addresses() {
+        let mut wallet = wallet();
+
+        let first = wallet.new_address(0, false).unwrap();
+        assert!(first.starts_with("tb1q"));
+        assert_eq!(
+            first,
+            address(&script(&source(ScriptType::P2wpkh), false, 0), NETWORK).unwrap()
+        );
+        assert_ne!(wallet.new_address(0, false).unwrap(), first);
+        assert_ne!(wallet.new_address(0, true).unwrap(), first);
+
+        let p2pkh = address(&script(&source(ScriptType::P2pkh), false, 0), NETWORK).unwrap();
+        assert!(p2pkh.starts_with(['m', 'n']));
+        let p2sh = address(&script(&source(ScriptType::P2shP2wpkh), false, 0), NETWORK).unwrap();
+        assert!(p2sh.starts_with('2'));
+
+        let err = wallet.new_address(1, false).expect_err("Err");
+        assert_eq!(err.to_string(), "unknown_script_source");
+    }
+
+    #[test]
+    fn descriptor_source() {
+        let xpub = match source(ScriptType::P2wpkh) {
+            ScriptSource::Xpub { xpub, .. } => xpub.to_base58(),
+            _ => unreachable!(),
+        };
+        let descriptor = |d: &str| Descriptor::parse(d, NETWORK).unwrap();
+
+        let mut wallet = WatchOnlyWallet::new(NETWORK, GAP_LIMIT);
+        let from_descriptor = ScriptSource::Descriptor {
+            receive: descriptor(&format!("wpkh({xpub}/0/*)")),
+            change: descriptor(&format!("wpkh({xpub}/1/*)")),
+        };
+        wallet.add_source(from_descriptor).unwrap();
+
+        let xpub_source = source(ScriptType::P2wpkh);
+        assert_eq!(
+            wallet.new_address(0, false).unwrap(),
+            address(&script(&xpub_source, false, 0), NETWORK).unwrap()
+        );
+        assert_eq!(
+            wallet.new_address(0, true).unwrap(),
+            address(&script(&xpub_source, true, 0), NETWORK).unwrap()
+        );
+
+        let not_ranged = ScriptSource::Descriptor {
+            receive: descriptor(&format!("wpkh({xpub}/0/0)")),
+            change: descriptor(&format!("wpkh({xpub}/1/*)")),
+        };
+        let err = wallet.add_source(not_ranged).expect_err("Err");
+        assert_eq!(err.to_string(), "descriptor_not_ranged");
+    }
+
+    #[test]
+    fn gap_limit() {
+        let mut wallet = wallet();
+        let source = source(ScriptType::P2wpkh);
+
+        // past the gap limit: not found
+        let far = tx(
+            &[(Integer::from(1), 0)],
+            vec![(1_000, script(&source, false, GAP_LIMIT))],
+        );
+        assert!(!wallet.add_unconfirmed_tx(&far).unwrap());
+
+        // the last script of the gap limit derives the next ones
+        let near = tx(
+            &[(Integer::from(2), 0)],
+            vec![(1_000, script(&source, false, GAP_LIMIT - 1))],
+        );
+        assert!(wallet.add_unconfirmed_tx(&near).unwrap());
+        assert!(wallet.add_unconfirmed_tx(&far).unwrap());
+
+        assert_eq!(
+            wallet.is_mine(&script(&source, false, 2 * GAP_LIMIT)).unwrap().index,
+            2 * GAP_LIMIT
+        );
+        assert!(wallet.is_mine(&script(&source, false, 2 * GAP_LIMIT + 1)).is_none());
+        assert!(wallet.is_mine(&script(&source, true, GAP_LIMIT)).is_none());
+    }
+
+    #[test]
+    fn scan_receive_and_spend() {
+        let mut wallet = wallet();
+        let source = source(ScriptType::P2wpkh);
+
+        let coinbase = tx(
+            &[(Integer::new(), 0xFFFFFFFF)],
+            vec![(50_000, script(&source, false, 0))],
+        );
+        let other = tx(&[(Integer::from(1), 0)], vec![(10_000, foreign())]);
+        let first = block(None, vec![coinbase.clone(), other]);
+        assert_eq!(wallet.scan_block(&first, 1).unwrap(), 1);
+
+        let spend = tx(
+            &[(coinbase.id_as_integer(), 0)],
+            vec![(30_000, foreign()), (19_000, script(&source, true, 0))],
+        );
+        assert!(wallet.add_unconfirmed_tx(&spend).unwrap());
+        assert_eq!(
+            wallet.balance(),
+            Balance {
+                confirmed: 0,
+                unconfirmed: 19_000
+            }
+        );
+
+        let second = block(Some(&first), vec![spend.clone()]);
+        assert_eq!(wallet.scan_block(&second, 2).unwrap(), 1);
+        assert_eq!(
+            wallet.balance(),
+            Balance {
+                confirmed: 19_000,
+                unconfirmed: 0
+            }
+        );
+
+        let history = wallet.history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            *history[0],
+            WalletTx {
+                txid: coinbase.id(),
+                height: Some(1),
+                received: 50_000,
+                sent: 0
+            }
+        );
+        assert_eq!(
+            *history[1],
+            WalletTx {
+                txid: spend.id(),
+                height: Some(2),
+                received: 19_000,
+                sent: 50_000
+            }
+        );
+
+        let utxos = wallet.utxos();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!((utxos[0].txid.as_str(), utxos[0].vout), (spend.id().as_str(), 1));
+        assert_eq!(
+            utxos[0].origin,
+            ScriptOrigin {
+                source: 0,
+                change: true,
+                index: 0
+            }
+        );
+    }
+
+    #[test]
+    fn scan_block_file() {
+        let mut wallet = wallet();
+        let source = source(ScriptType::P2wpkh);
+
+        let first = block(
+            None,
+            vec![tx(&[(Integer::from(1), 0)], vec![(1_000, script(&source, false, 0))])],
+        );
+        let second = block(
+            Some(&first),
+            vec![tx(&[(Integer::from(2), 0)], vec![(2_000, script(&source, false, 1))])],
+        );
+
+        // out of order, as in the files
+        let mut data: Vec<u8> = vec![];
+        for block in [&second, &first] {
+            let serialized = block.serialize();
+            data.extend(NetworkMagic::Testnet3.to_le_bytes());
+            data.extend((serialized.len() as u32).to_le_bytes());
+            data.extend(serialized);
+        }
+
+        let height = wallet
+            .scan_block_file(&data, NetworkMagic::Testnet3, &Integer::new(), 0)
+            .unwrap();
+        assert_eq!(height, 2);
+        assert_eq!(wallet.balance().confirmed, 3_000);
+
+        let heights: Vec<Option<u32>> = wallet.history().iter().map(|tx| tx.height).collect();
+        assert_eq!(heights, vec![Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn spendables_to_builder() {
+        let mut wallet = wallet();
+        let source = source(ScriptType::P2wpkh);
+
+        let funding = tx(
+            &[(Integer::from(1), 0)],
+            vec![(40_000, script(&source, false, 0)), (60_000, script(&source, false, 1))],
+        );
+        wallet.scan_block(&block(None, vec![funding.clone()]), 1).unwrap();
+        let unconfirmed = tx(&[(Integer::from(2), 0)], vec![(5_000, script(&source, false, 2))]);
+        wallet.add_unconfirmed_tx(&unconfirmed).unwrap();
+
+        let spendables = wallet.spendables().unwrap();
+        assert_eq!(spendables.len(), 2);
+        assert!(spendables.iter().all(|s| s.is_watch_only()));
+
+        let mut builder = TxBuilder::new(NETWORK, 10);
+        for spendable in spendables {
+            builder.add_utxo(spendable);
+        }
+        builder.add_recipient(70_000, foreign());
+        builder.set_change_script_pub_key(wallet.new_script_pub_key(0, true).unwrap());
+
+        let tx = builder.build_unsigned().unwrap();
+        assert_eq!(tx.input_len(), 2);
+        assert!((0..tx.input_len()).all(|i| tx.input(i).unwrap().previous_transaction_id == funding.id_as_integer()));
+    }
+}
@@ -0,0 +1,90 @@
+/*
+    PSBT against the test vectors of BIP174, `tests/fixtures/psbt_vectors.json` (see its README):
+        - `invalid`: PSBTs that must be rejected.
+        - `valid`: PSBTs that must be accepted and serialized back as they are.
+        - `combiner`: a signed PSBT combined with the PSBT of all the signatures.
+
+    How to run:
+        cargo test --test psbt_vectors -- --nocapture
+
+*/
+
+extern crate core;
+
+#[cfg(test)]
+mod psbt_vectors_test {
+    use serde_json::Value;
+
+    use core::{
+        flags::network::Network,
+        psbt::{map::PsbtMap, partially_signed_tx::Psbt},
+        std_lib::{fixture::load_fixture_file, vector::hex_string_to_bytes},
+    };
+
+    const NETWORK: Network = Network::Testnet;
+
+    #[test]
+    fn invalid() {
+        for case in read_fixture()["invalid"].as_array().unwrap() {
+            let description = case["description"].as_str().unwrap();
+
+            assert!(
+                Psbt::deserialize(&hex(&case["psbt"]), NETWORK).is_err(),
+                "{}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn valid() {
+        for case in read_fixture()["valid"].as_array().unwrap() {
+            let description = case["description"].as_str().unwrap();
+            let serialized = hex(&case["psbt"]);
+
+            let psbt = Psbt::deserialize(&serialized, NETWORK).expect(description);
+            assert_eq!(psbt.serialize(), serialized, "{}", description);
+
+            let tx = psbt.unsigned_tx().expect(description);
+            assert_eq!(tx.input_len(), psbt.inputs.len(), "{}", description);
+            assert_eq!(tx.output_len(), psbt.outputs.len(), "{}", description);
+        }
+    }
+
+    #[test]
+    fn combiner() {
+        let fixture = read_fixture();
+        let signed = Psbt::deserialize(&hex(&fixture["combiner"]["signed"]), NETWORK).unwrap();
+        let combined = Psbt::deserialize(&hex(&fixture["combiner"]["combined"]), NETWORK).unwrap();
+
+        // the pairs already in the PSBT are kept in their order
+        let mut psbt = combined.clone();
+        psbt.combine(&signed).unwrap();
+        assert_eq!(psbt.serialize(), combined.serialize());
+
+        let mut psbt = signed.clone();
+        psbt.combine(&combined).unwrap();
+        assert!(same_pairs(&psbt.global, &combined.global));
+        for (input, combined_input) in psbt.inputs.iter().zip(combined.inputs.iter()) {
+            assert!(same_pairs(input, combined_input));
+        }
+        for (output, combined_output) in psbt.outputs.iter().zip(combined.outputs.iter()) {
+            assert!(same_pairs(output, combined_output));
+        }
+    }
+
+    // The same pairs, in any order
+    fn same_pairs(map: &PsbtMap, other: &PsbtMap) -> bool {
+        map.pairs().len() == other.pairs().len() && map.pairs().iter().all(|pair| other.pairs().contains(pair))
+    }
+
+    fn read_fixture() -> Value {
+        let content = std::fs::read_to_string(load_fixture_file("psbt_vectors.json")).unwrap();
+
+        serde_json::from_str(&content).unwrap()
+    }
+
+    fn hex(value: &Value) -> Vec<u8> {
+        hex_string_to_bytes(value.as_str().unwrap()).unwrap()
+    }
+}
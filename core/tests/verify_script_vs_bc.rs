@@ -0,0 +1,512 @@
+/*
+    Conformance of the script interpreter with the reference test vectors of Bitcoin Core:
+        - `script_tests.json`: ScriptSig/ScriptPubKey pairs, spent by the transactions built by Bitcoin Core
+          (`BuildCreditingTransaction` and `BuildSpendingTransaction` in `src/test/script_tests.cpp`).
+        - `tx_valid.json`/`tx_invalid.json`: serialized transactions with the ScriptPubKey of the outputs they spend.
+
+    Files are in `tests/fixtures` and have the same format of Bitcoin Core ones (`src/test/data`),
+    so they can be replaced by the complete files.
+
+    Every case is run, then the failing ones are reported (a panic of the interpreter is a failure too).
+    The error of `script_tests.json` cases is compared with the script error of Bitcoin Core (`ScriptError`),
+    the transactions of `tx_invalid.json` only have to fail.
+    Known gaps are skipped (and reported): cases with verification flags not supported yet, tapscript cases and
+    cases failing with an error of what is not verified yet (e.g. witness programs of future versions).
+    Each file lists its gaps with the maximum number of cases: any other difference is a failure.
+
+    How to run:
+        cargo test --test verify_script_vs_bc -- --nocapture
+
+*/
+
+extern crate core;
+
+#[cfg(test)]
+mod verify_script_test {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use rug::Integer;
+    use serde_json::Value;
+
+    use core::{
+        flags::{network::Network, script_flags::ScriptFlags},
+        scripting::{
+            opcode::{OpCode, OP_0, OP_1, OP_1NEGATE, OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4, OP_TO_FN},
+            script_num::ScriptNum,
+        },
+        std_lib::{fixture::load_fixture_file, integer_extended::IntegerExtended, vector::hex_string_to_bytes},
+        transaction::{script::Script, tx::Tx, tx_in::TxIn, tx_out::TxOut},
+        validate::tx::verify_input_script,
+    };
+
+    #[test]
+    pub fn verify_script_tests() {
+        let mut report = Report::new("script_tests.json", &[]);
+
+        for case in read_fixture("script_tests.json") {
+            let items = case.as_array().unwrap();
+
+            // comments
+            if items.len() == 1 {
+                continue;
+            }
+
+            let description = case.to_string();
+
+            // [[witness..., amount], scriptSig, scriptPubKey, flags, expected_scripterror, comment]
+            let (witness, amount, items) = match items[0].as_array() {
+                Some(witness) => {
+                    let (amount, witness) = witness.split_last().unwrap();
+                    (witness.to_vec(), btc_to_satoshi(amount), &items[1..])
+                }
+                None => (vec![], 0, &items[..]),
+            };
+
+            // `#SCRIPT#`, `#CONTROLBLOCK#` and `#TAPROOTOUTPUT#` build the tapscript cases
+            let placeholder = |item: &Value| item.as_str().unwrap().starts_with('#');
+            if witness.iter().any(placeholder) || items[1].as_str().unwrap().contains("#TAPROOTOUTPUT#") {
+                report.skip(description, TAPSCRIPT);
+                continue;
+            }
+
+            let script_sig = items[0].as_str().unwrap();
+            let script_pub_key = items[1].as_str().unwrap();
+            let expected = items[3].as_str().unwrap();
+
+            let flags = match parse_flags(items[2].as_str().unwrap()) {
+                Some(flags) => flags,
+                None => {
+                    report.skip(description, UNSUPPORTED_FLAGS);
+                    continue;
+                }
+            };
+
+            let outcome = run(|| {
+                let script_sig = parse_script(script_sig)?;
+                let script_pub_key = Script::new_from_raw(parse_script(script_pub_key)?);
+                let witness = witness
+                    .iter()
+                    .map(|element| hex_string_to_bytes(element.as_str().unwrap()).map_err(|e| e.to_string()))
+                    .collect::<Result<Vec<Vec<u8>>, String>>()?;
+                let tx = spending_transaction(script_sig, witness, amount, &script_pub_key);
+
+                verify(&tx, 0, &[TxOut::new(amount, script_pub_key)], flags)
+            });
+
+            report.check(description, expected, outcome);
+        }
+
+        report.assert_all_passed();
+    }
+
+    #[test]
+    pub fn verify_tx_valid() {
+        let mut report = Report::new("tx_valid.json", &[]);
+
+        for case in read_fixture("tx_valid.json") {
+            // [[[prevout hash, prevout index, prevout scriptPubKey, amount?], ...], serializedTransaction, excluded verifyFlags]
+            let Some((prevouts, tx, excluded)) = tx_case(&case) else {
+                continue;
+            };
+
+            let flags = match all_flags_except(excluded) {
+                Some(flags) => flags,
+                None => {
+                    report.skip(case.to_string(), UNSUPPORTED_FLAGS);
+                    continue;
+                }
+            };
+
+            let outcome = run(|| verify_transaction(prevouts, tx, flags));
+            report.check(case.to_string(), "OK", outcome);
+        }
+
+        report.assert_all_passed();
+    }
+
+    #[test]
+    pub fn verify_tx_invalid() {
+        let mut report = Report::new("tx_invalid.json", &[]);
+
+        for case in read_fixture("tx_invalid.json") {
+            // [[[prevout hash, prevout index, prevout scriptPubKey, amount?], ...], serializedTransaction, verifyFlags]
+            let Some((prevouts, tx, flags)) = tx_case(&case) else {
+                continue;
+            };
+
+            // `BADTX` (transaction failing the context free checks) is not supported yet
+            let flags = match parse_flags(flags) {
+                Some(flags) => flags,
+                None => {
+                    report.skip(case.to_string(), UNSUPPORTED_FLAGS);
+                    continue;
+                }
+            };
+
+            let outcome = run(|| verify_transaction(prevouts, tx, flags));
+            report.check(case.to_string(), "INVALID", outcome);
+        }
+
+        report.assert_all_passed();
+    }
+
+    // Reasons of the known gaps
+    const UNSUPPORTED_FLAGS: &str = "unsupported flags";
+    const TAPSCRIPT: &str = "tapscript (BIP342)";
+    const NOT_VERIFIED: &str = "not verified yet";
+
+    // Errors of what is not verified yet: Bitcoin Core could accept the case (or reject it for another reason)
+    const NOT_VERIFIED_ERRORS: [&str; 5] = [
+        "witness_program_not_supported",
+        "taproot_annex_not_supported",
+        "tapscript_not_supported",
+        "taproot_leaf_version_not_supported",
+        "unsupported_sighash_type",
+    ];
+
+    struct Report {
+        file_name: &'static str,
+        gaps: &'static [(&'static str, usize)],
+        passed: usize,
+        skipped: Vec<(&'static str, String)>,
+        failed: Vec<String>,
+    }
+
+    impl Report {
+        // `gaps`: the maximum number of skipped cases by reason (none if not listed), lower them as they get supported
+        fn new(file_name: &'static str, gaps: &'static [(&'static str, usize)]) -> Self {
+            Report {
+                file_name,
+                gaps,
+                passed: 0,
+                skipped: vec![],
+                failed: vec![],
+            }
+        }
+
+        fn skip(&mut self, description: String, reason: &'static str) {
+            self.skipped.push((reason, description));
+        }
+
+        // `expected` is `OK`, `INVALID` (any error) or the script error of Bitcoin Core
+        fn check(&mut self, description: String, expected: &str, outcome: Result<(), String>) {
+            match (expected, outcome) {
+                (_, Err(e)) if NOT_VERIFIED_ERRORS.contains(&e.as_str()) => {
+                    self.skip(format!("{}: {}", description, e), NOT_VERIFIED)
+                }
+                ("OK", Ok(())) | ("INVALID", Err(_)) => self.passed += 1,
+                ("OK", Err(e)) => self.failed.push(format!("{}: expected OK, got {}", description, e)),
+                (_, Ok(())) => self
+                    .failed
+                    .push(format!("{}: expected {}, got OK", description, expected)),
+                (_, Err(e)) => {
+                    if core_script_errors(&e).contains(&expected) {
+                        self.passed += 1;
+                    } else {
+                        self.failed
+                            .push(format!("{}: expected {}, got {}", description, expected, e));
+                    }
+                }
+            }
+        }
+
+        fn assert_all_passed(&self) {
+            println!(
+                "{}: {} passed, {} failed, {} skipped",
+                self.file_name,
+                self.passed,
+                self.failed.len(),
+                self.skipped.len()
+            );
+
+            for (reason, skipped) in &self.skipped {
+                println!("  skipped ({}): {}", reason, skipped);
+            }
+
+            for failed in &self.failed {
+                println!("  failed: {}", failed);
+            }
+
+            for (reason, _) in &self.skipped {
+                let skipped = self.skipped.iter().filter(|(r, _)| r == reason).count();
+                let max_skipped = self.gaps.iter().find(|(r, _)| r == reason).map_or(0, |(_, max)| *max);
+
+                assert!(
+                    skipped <= max_skipped,
+                    "{}: {} cases skipped ({}), more than {}",
+                    self.file_name,
+                    skipped,
+                    reason,
+                    max_skipped
+                );
+            }
+
+            assert!(
+                self.failed.is_empty(),
+                "{}: {} cases failed:\n{}",
+                self.file_name,
+                self.failed.len(),
+                self.failed.join("\n")
+            );
+        }
+    }
+
+    fn read_fixture(file_name: &str) -> Vec<Value> {
+        let content = std::fs::read_to_string(load_fixture_file(file_name)).unwrap();
+        let json: Value = serde_json::from_str(&content).unwrap();
+
+        json.as_array().unwrap().to_vec()
+    }
+
+    // A panic of the interpreter is a failure of the case, not of the whole suite
+    fn run(case: impl FnOnce() -> Result<(), String>) -> Result<(), String> {
+        match panic::catch_unwind(AssertUnwindSafe(case)) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                let message = e
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| e.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+
+                Err(format!("panic: {}", message))
+            }
+        }
+    }
+
+    fn verify(tx: &Tx, input_index: usize, spent_outputs: &[TxOut], flags: ScriptFlags) -> Result<(), String> {
+        match verify_input_script(tx, input_index, spent_outputs, flags) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("false".to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /*
+        Script errors of Bitcoin Core (`ScriptErrorString` in `src/script/script_error.cpp`) matching an error
+        of the interpreter.
+        The verify opcodes share the same error, and the errors of `CScriptNum` are `UNKNOWN_ERROR` in Bitcoin Core.
+    */
+    fn core_script_errors(error: &str) -> &'static [&'static str] {
+        match error {
+            "false" | "missing_redeem_script" => &["EVAL_FALSE"],
+            "exit_by_failed_verify" => &["VERIFY", "EQUALVERIFY", "NUMEQUALVERIFY"],
+            "exit_by_failed_checksigverify" => &["CHECKSIGVERIFY"],
+            "exit_by_failed_checkmultisigverify" => &["CHECKMULTISIGVERIFY"],
+            "exit_by_return" | "return_data_too_long" => &["OP_RETURN"],
+            "exit_by_reserved" | "invalid_opcode" | "truncated_push" => &["BAD_OPCODE"],
+            "deprecated_opcode" => &["DISABLED_OPCODE"],
+            "script_size_exceeded" => &["SCRIPT_SIZE"],
+            "element_size_exceeded" => &["PUSH_SIZE"],
+            "op_count_exceeded" => &["OP_COUNT"],
+            "stack_size_exceeded" => &["STACK_SIZE"],
+            "m_greater_than_n_for_multisig" => &["SIG_COUNT"],
+            "pubkey_count_exceeded" => &["PUBKEY_COUNT"],
+            "not_enough_items_in_stack" => &["INVALID_STACK_OPERATION"],
+            "unexpected_else" | "unexpected_end_if" => &["UNBALANCED_CONDITIONAL"],
+            "negative_locktime" => &["NEGATIVE_LOCKTIME"],
+            "unsatisfied_locktime" => &["UNSATISFIED_LOCKTIME"],
+            "signature_not_strict_der" => &["SIG_DER"],
+            "signature_high_s" => &["SIG_HIGH_S"],
+            "multisig_dummy_not_null" => &["SIG_NULLDUMMY"],
+            "signature_null_fail" => &["NULLFAIL"],
+            "script_sig_not_push_only" => &["SIG_PUSHONLY"],
+            "push_not_minimal" => &["MINIMALDATA"],
+            "minimal_if" => &["MINIMALIF"],
+            "witness_program_mismatch" => &["WITNESS_PROGRAM_MISMATCH"],
+            "witness_program_witness_empty" => &["WITNESS_PROGRAM_WITNESS_EMPTY"],
+            "witness_program_wrong_length" => &["WITNESS_PROGRAM_WRONG_LENGTH"],
+            "witness_malleated" => &["WITNESS_MALLEATED"],
+            "witness_malleated_p2sh" => &["WITNESS_MALLEATED_P2SH"],
+            "witness_unexpected" => &["WITNESS_UNEXPECTED"],
+            "witness_clean_stack" => &["CLEANSTACK"],
+            "invalid_schnorr_signature" => &["SCHNORR_SIG", "SCHNORR_SIG_SIZE", "SCHNORR_SIG_HASHTYPE"],
+            "invalid_control_block_size" => &["TAPROOT_WRONG_CONTROL_SIZE"],
+            "script_num_not_minimal" | "input_length_too_long" | "not_an_element" => &["UNKNOWN_ERROR"],
+            _ => &[],
+        }
+    }
+
+    fn tx_case(case: &Value) -> Option<(&Vec<Value>, &str, &str)> {
+        let items = case.as_array().unwrap();
+
+        // comments
+        if !items[0].is_array() {
+            return None;
+        }
+
+        Some((
+            items[0].as_array().unwrap(),
+            items[1].as_str().unwrap(),
+            items[2].as_str().unwrap(),
+        ))
+    }
+
+    fn verify_transaction(prevouts: &[Value], tx: &str, flags: ScriptFlags) -> Result<(), String> {
+        let serialized = hex_string_to_bytes(tx).map_err(|e| e.to_string())?;
+        let tx = Tx::deserialize(&serialized, Network::Testnet).map_err(|e| e.to_string())?;
+
+        let mut spent_outputs = vec![];
+        for input_index in 0..tx.input_len() {
+            let input = tx.input(input_index).map_err(|e| e.to_string())?;
+
+            let prevout = prevouts
+                .iter()
+                .map(|prevout| prevout.as_array().unwrap())
+                .find(|prevout| {
+                    Integer::from_hex_str(prevout[0].as_str().unwrap()) == input.previous_transaction_id
+                        && prevout[1].as_i64().unwrap() as u32 == input.previous_transaction_index
+                })
+                .ok_or("missing_prevout")?;
+
+            let script_pub_key = Script::new_from_raw(parse_script(prevout[2].as_str().unwrap())?);
+            // the amount is only there for the outputs spent by witness inputs
+            let amount = prevout.get(3).and_then(|amount| amount.as_u64()).unwrap_or(0);
+            spent_outputs.push(TxOut::new(amount, script_pub_key));
+        }
+
+        for input_index in 0..tx.input_len() {
+            verify(&tx, input_index, &spent_outputs, flags)?;
+        }
+
+        Ok(())
+    }
+
+    /*
+        The transaction spending the output (of `amount`) of `script_pub_key` with `script_sig` and `witness`,
+        as `BuildCreditingTransaction` and `BuildSpendingTransaction` of Bitcoin Core.
+    */
+    fn spending_transaction(script_sig: Vec<u8>, witness: Vec<Vec<u8>>, amount: u64, script_pub_key: &Script) -> Tx {
+        let mut credit = Tx::new(Network::Testnet);
+        credit.add_input(TxIn::new(
+            Integer::from(0),
+            0xFFFFFFFF,
+            Script::new_from_raw(vec![OP_0 as u8, OP_0 as u8]),
+            0xFFFFFFFF,
+            Network::Testnet,
+        ));
+        credit.add_output(TxOut::new(amount, script_pub_key.clone()));
+
+        let mut spend = Tx::new(Network::Testnet);
+        spend.add_input(TxIn::new(
+            Integer::from_hex_str(&credit.id()),
+            0,
+            Script::new_from_raw(script_sig),
+            0xFFFFFFFF,
+            Network::Testnet,
+        ));
+        spend.add_output(TxOut::new(amount, Script::new_empty()));
+        spend.set_witnesses(0, witness);
+
+        spend
+    }
+
+    // Amounts of the test vectors are in BTC
+    fn btc_to_satoshi(amount: &Value) -> u64 {
+        (amount.as_f64().unwrap() * 100_000_000.0).round() as u64
+    }
+
+    static FLAG_NAMES: [(&str, ScriptFlags); 11] = [
+        ("P2SH", ScriptFlags::P2SH),
+        ("DERSIG", ScriptFlags::DERSIG),
+        ("LOW_S", ScriptFlags::LOW_S),
+        ("NULLDUMMY", ScriptFlags::NULLDUMMY),
+        ("MINIMALDATA", ScriptFlags::MINIMALDATA),
+        ("CHECKLOCKTIMEVERIFY", ScriptFlags::CHECKLOCKTIMEVERIFY),
+        ("CHECKSEQUENCEVERIFY", ScriptFlags::CHECKSEQUENCEVERIFY),
+        ("WITNESS", ScriptFlags::WITNESS),
+        ("MINIMALIF", ScriptFlags::MINIMALIF),
+        ("NULLFAIL", ScriptFlags::NULLFAIL),
+        ("TAPROOT", ScriptFlags::TAPROOT),
+    ];
+
+    // e.g. "P2SH,DERSIG", `None` if any flag is not supported
+    fn parse_flags(names: &str) -> Option<ScriptFlags> {
+        let mut flags = ScriptFlags::NONE;
+
+        for name in names.split(',').filter(|name| !name.is_empty() && *name != "NONE") {
+            let (_, flag) = FLAG_NAMES.iter().find(|(flag_name, _)| flag_name == &name)?;
+            flags |= *flag;
+        }
+
+        Some(flags)
+    }
+
+    // tx_valid.json lists the flags to exclude
+    fn all_flags_except(names: &str) -> Option<ScriptFlags> {
+        let excluded = parse_flags(names)?;
+
+        let flags = FLAG_NAMES
+            .iter()
+            .filter(|(_, flag)| !excluded.contains(*flag))
+            .fold(ScriptFlags::NONE, |flags, (_, flag)| flags | *flag);
+
+        Some(flags)
+    }
+
+    /*
+        Script in the format of Bitcoin Core tests (`ParseScript` in `src/core_read.cpp`):
+            - numbers are pushed as `CScriptNum` (OP_0, OP_1NEGATE and OP_1 ... OP_16 when possible)
+            - `0x...` are raw bytes, inserted as they are
+            - `'...'` are strings, pushed
+            - opcodes, with or without `OP_`
+    */
+    fn parse_script(asm: &str) -> Result<Vec<u8>, String> {
+        let mut script: Vec<u8> = vec![];
+
+        for word in asm.split_whitespace() {
+            let digits = word.strip_prefix('-').unwrap_or(word);
+
+            if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+                let n: i64 = word.parse().map_err(|_| format!("invalid number: {}", word))?;
+                script.extend(push_number(n));
+            } else if let Some(hex) = word.strip_prefix("0x") {
+                script.extend(hex_string_to_bytes(hex).map_err(|_| format!("invalid hex: {}", word))?);
+            } else if word.len() >= 2 && word.starts_with('\'') && word.ends_with('\'') {
+                script.extend(push_data(&word.as_bytes()[1..word.len() - 1]));
+            } else {
+                script.push(op_code_by_name(word)? as u8);
+            }
+        }
+
+        Ok(script)
+    }
+
+    fn push_number(n: i64) -> Vec<u8> {
+        match n {
+            0 => vec![OP_0 as u8],
+            -1 => vec![OP_1NEGATE as u8],
+            1..=16 => vec![(OP_1 as i64 + n - 1) as u8],
+            _ => push_data(&ScriptNum::new(n).encode()),
+        }
+    }
+
+    fn push_data(data: &[u8]) -> Vec<u8> {
+        let len = data.len();
+
+        let prefix = if len < OP_PUSHDATA1 {
+            vec![len as u8]
+        } else if len <= 0xFF {
+            vec![OP_PUSHDATA1 as u8, len as u8]
+        } else if len <= 0xFFFF {
+            [vec![OP_PUSHDATA2 as u8], (len as u16).to_le_bytes().to_vec()].concat()
+        } else {
+            [vec![OP_PUSHDATA4 as u8], (len as u32).to_le_bytes().to_vec()].concat()
+        };
+
+        [prefix, data.to_vec()].concat()
+    }
+
+    fn op_code_by_name(word: &str) -> Result<OpCode, String> {
+        let name = if word.starts_with("OP_") {
+            word.to_string()
+        } else {
+            format!("OP_{}", word)
+        };
+
+        OP_TO_FN
+            .iter()
+            .position(|op| op.name == name)
+            .ok_or_else(|| format!("unknown opcode: {}", word))
+    }
+}
@@ -1,4 +1,4 @@
-use core::{hashing::hash256::Hash256, network::headers::Headers};
+use core::{block::full_block::Block, hashing::hash256::Hash256, network::headers::Headers};
 
 // TODO: the u8 in NodeReady should be the node id: we have to find a way to assign it to message transparently
 #[derive(Debug, Clone, PartialEq)]
@@ -6,4 +6,6 @@ pub enum InternalMessage {
     NodeIsReady(u8),
     GetHeadersRequest(u8, Hash256),
     GetHeadersResponse(u8, Headers),
+    GetBlocksRequest(u8, Vec<Hash256>),
+    GetBlocksResponse(u8, Block),
 }
@@ -0,0 +1,16 @@
+use core::{
+    flags::network_magic::NetworkMagic,
+    hashing::hash256::Hash256,
+    network::{command::GET_DATA_COMMAND, get_data::GetData, network_message::NetworkMessage},
+    std_lib::std_result::StdResult,
+};
+
+// Requests the blocks (with witnesses) with these hashes
+pub fn new(hashes: &[Hash256]) -> GetData {
+    GetData::blocks(hashes)
+}
+
+pub fn as_network_message(get_data: &GetData, network: NetworkMagic) -> StdResult<NetworkMessage> {
+    let payload = get_data.serialize();
+    NetworkMessage::new(GET_DATA_COMMAND, payload, network)
+}
@@ -1,3 +1,4 @@
+pub mod get_data;
 pub mod get_headers;
 pub mod pong;
 pub mod verack;
@@ -20,7 +20,7 @@ use core::{
 use crate::{
     handshake_state::HandshakeState,
     internal_message::InternalMessage,
-    message::{get_headers, pong, verack, version},
+    message::{get_data, get_headers, pong, verack, version},
     node_listener::NodeListener,
 };
 
@@ -169,6 +169,16 @@ impl<'a> RemoteNode<'a> {
                     log::debug!(NID = self.node_id; "Headers command received ({} headers).", headers.0.len());
                     node_to_rest_sender.send(InternalMessage::GetHeadersResponse(self.node_id, headers))?;
                 }
+                Commands::GetData(gd) => {
+                    log::debug!(NID = self.node_id; "GetData should send to remote node.");
+                    let get_data_message = get_data::as_network_message(&gd, self.network)?;
+
+                    self.send_message(&get_data_message).await?;
+                }
+                Commands::Block(block) => {
+                    log::debug!(NID = self.node_id; "Block command received ({}).", block.id_str());
+                    node_to_rest_sender.send(InternalMessage::GetBlocksResponse(self.node_id, block))?;
+                }
                 _ => continue,
             }
         }
@@ -213,6 +223,17 @@ impl<'a> RemoteNode<'a> {
                             let gh = get_headers::new(start_hash);
                             return Ok(Commands::GetHeaders(gh));
                         }
+                        Ok(InternalMessage::GetBlocksRequest(node_id, hashes)) => {
+                            log::debug!(NID = self.node_id; "Received GetBlocksRequest from internal.");
+
+                            if node_id != self.node_id {
+                                // message is not for this node
+                                continue;
+                            }
+
+                            let gd = get_data::new(&hashes);
+                            return Ok(Commands::GetData(gd));
+                        }
                         Ok(val) => {
                             log::debug!(NID = self.node_id; "Received unknown value from rest_to_node_receiver: {:?}", val);
                             continue;
@@ -34,6 +34,8 @@ pub async fn start(
 
                 // let _ = sender.send(NodeMessage::GetHeadersRequest(start_block_hash));
             }
+            // blocks are requested and scanned by the wallet
+            InternalMessage::GetBlocksRequest(..) | InternalMessage::GetBlocksResponse(..) => continue,
             _ => {
                 log::info!("Received message: {:?}", message);
                 log::debug!("timechain_synchronyzer exiting...");